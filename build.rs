@@ -0,0 +1,32 @@
+use std::process::Command;
+use std::time::SystemTime;
+
+// Embed build metadata for `sentinel --version`. Both values are optional:
+// source tarballs have no git checkout, and reproducible builds can pin the
+// timestamp with SOURCE_DATE_EPOCH.
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    if let Some(hash) = git_hash {
+        println!("cargo:rustc-env=SENTINEL_GIT_HASH={}", hash);
+    }
+
+    let timestamp = std::env::var("SOURCE_DATE_EPOCH").ok().or_else(|| {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_secs().to_string())
+    });
+    if let Some(ts) = timestamp {
+        println!("cargo:rustc-env=SENTINEL_BUILD_TIMESTAMP={}", ts);
+    }
+}
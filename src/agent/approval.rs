@@ -0,0 +1,242 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::llm::provider::ContentBlock;
+use crate::net::json::JsonValue;
+
+// ── Tool-call approval ──────────────────────────────────────────────────────
+//
+// Tools named in `require_approval_for` don't run on the model's say-so
+// alone. The turn stops at such a call, the user is shown exactly what it
+// would do, and the next message in the conversation (/approve or /deny)
+// decides. Calls already run and those still queued travel with the pending
+// approval, so the history gets a result for every call either way.
+
+/// Longest input value shown in full in an approval request.
+const MAX_SHOWN_CHARS: usize = 1500;
+
+/// Which tools need approval, and how long a request stays open.
+#[derive(Debug, Clone)]
+pub struct ApprovalPolicy {
+    pub tools: Vec<String>,
+    pub timeout: Duration,
+}
+
+impl Default for ApprovalPolicy {
+    fn default() -> Self {
+        ApprovalPolicy {
+            tools: Vec::new(),
+            timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+impl ApprovalPolicy {
+    pub fn requires(&self, tool: &str) -> bool {
+        self.tools.iter().any(|t| t == tool)
+    }
+}
+
+/// A tool call requested by the model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub input: JsonValue,
+}
+
+/// The user's answer to an approval request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decision {
+    Approved,
+    Denied,
+    /// No answer before the request expired.
+    TimedOut,
+}
+
+impl Decision {
+    /// Parse a reply; `None` if it is neither /approve nor /deny.
+    pub fn parse(text: &str) -> Option<Decision> {
+        match text.trim() {
+            "/approve" => Some(Decision::Approved),
+            "/deny" => Some(Decision::Denied),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Decision::Approved => "approved",
+            Decision::Denied => "denied",
+            Decision::TimedOut => "timed_out",
+        }
+    }
+}
+
+/// The tool calls from one model response, run in order.
+#[derive(Debug, PartialEq)]
+pub struct ToolBatch {
+    /// Calls not yet run.
+    pub calls: VecDeque<ToolCall>,
+    /// Results of the calls already handled.
+    pub results: Vec<ContentBlock>,
+    /// The user's answer for the first call in `calls`, once it has one.
+    pub decision: Option<Decision>,
+}
+
+impl ToolBatch {
+    pub fn from_response(content: &[ContentBlock]) -> Self {
+        let calls = content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::ToolUse { id, name, input } => Some(ToolCall {
+                    id: id.clone(),
+                    name: name.clone(),
+                    input: input.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+        ToolBatch {
+            calls,
+            results: Vec::new(),
+            decision: None,
+        }
+    }
+
+    /// Give every remaining call an error result without running it.
+    pub fn abandon(&mut self, reason: &str) -> Vec<ContentBlock> {
+        while let Some(call) = self.calls.pop_front() {
+            self.results.push(not_run(&call, reason));
+        }
+        std::mem::take(&mut self.results)
+    }
+}
+
+/// A batch stopped at a call that needs the user's approval.
+#[derive(Debug, PartialEq)]
+pub struct PendingApproval {
+    pub batch: ToolBatch,
+    pub requested_at: Instant,
+}
+
+impl PendingApproval {
+    pub fn new(batch: ToolBatch) -> Self {
+        PendingApproval {
+            batch,
+            requested_at: Instant::now(),
+        }
+    }
+
+    /// The call awaiting approval.
+    pub fn call(&self) -> Option<&ToolCall> {
+        self.batch.calls.front()
+    }
+
+    pub fn expired(&self, timeout: Duration) -> bool {
+        self.requested_at.elapsed() >= timeout
+    }
+}
+
+/// The error result recorded for a call that was not run.
+pub fn not_run(call: &ToolCall, reason: &str) -> ContentBlock {
+    ContentBlock::ToolResult {
+        tool_use_id: call.id.clone(),
+        content: reason.to_string(),
+        is_error: true,
+    }
+}
+
+/// The message asking the user to approve `call`, showing its exact input.
+pub fn approval_request(call: &ToolCall, timeout: Duration) -> String {
+    let mut s = format!("Approval needed to run {}:\n", call.name);
+    let fields: Vec<(String, JsonValue)> = match call.input.as_object() {
+        Some(fields) => fields.to_vec(),
+        None => vec![("input".to_string(), call.input.clone())],
+    };
+    for (key, value) in &fields {
+        let shown = match value.as_str() {
+            Some(v) => v.to_string(),
+            None => value.to_json_string(),
+        };
+        if shown.contains('\n') || shown.len() > 80 {
+            let mut cut = shown.len().min(MAX_SHOWN_CHARS);
+            while !shown.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            s.push_str(&format!("{}:\n```\n{}\n```\n", key, &shown[..cut]));
+            if cut < shown.len() {
+                s.push_str(&format!("({} more characters not shown)\n", shown.len() - cut));
+            }
+        } else {
+            s.push_str(&format!("{}: {}\n", key, shown));
+        }
+    }
+    let secs = timeout.as_secs();
+    let within = if secs >= 60 && secs.is_multiple_of(60) {
+        format!("{} minute{}", secs / 60, if secs == 60 { "" } else { "s" })
+    } else {
+        format!("{} seconds", secs)
+    };
+    s.push_str(&format!("Reply /approve or /deny within {}.", within));
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::json::json_obj;
+
+    fn call(name: &str, input: JsonValue) -> ToolCall {
+        ToolCall { id: "t1".into(), name: name.into(), input }
+    }
+
+    #[test]
+    fn test_request_shows_exact_input() {
+        let c = call(
+            "write_file",
+            json_obj()
+                .field_str("path", "/tmp/notes.txt")
+                .field_str("content", "line one\nline two")
+                .build(),
+        );
+        assert_eq!(
+            approval_request(&c, Duration::from_secs(300)),
+            "Approval needed to run write_file:\n\
+             path: /tmp/notes.txt\n\
+             content:\n```\nline one\nline two\n```\n\
+             Reply /approve or /deny within 5 minutes."
+        );
+    }
+
+    #[test]
+    fn test_request_truncates_long_content() {
+        let c = call("write_file", json_obj().field_str("content", &"x".repeat(2000)).build());
+        let msg = approval_request(&c, Duration::from_secs(90));
+        assert!(msg.contains("(500 more characters not shown)"));
+        assert!(msg.ends_with("within 90 seconds."));
+    }
+
+    #[test]
+    fn test_decision_parse() {
+        assert_eq!(Decision::parse(" /approve "), Some(Decision::Approved));
+        assert_eq!(Decision::parse("/deny"), Some(Decision::Denied));
+        assert_eq!(Decision::parse("yes"), None);
+    }
+
+    #[test]
+    fn test_abandon_answers_every_call() {
+        let mut batch = ToolBatch::from_response(&[
+            ContentBlock::Text { text: "Doing both.".into() },
+            ContentBlock::ToolUse { id: "a".into(), name: "run_command".into(), input: json_obj().build() },
+            ContentBlock::ToolUse { id: "b".into(), name: "read_file".into(), input: json_obj().build() },
+        ]);
+        let results = batch.abandon("not run: approval timed out");
+        assert_eq!(results.len(), 2);
+        assert!(matches!(
+            &results[1],
+            ContentBlock::ToolResult { tool_use_id, is_error: true, .. } if tool_use_id == "b"
+        ));
+        assert!(batch.calls.is_empty());
+    }
+}
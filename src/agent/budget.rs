@@ -0,0 +1,97 @@
+use crate::llm::provider::LlmResponse;
+
+// ── Per-conversation token budget ───────────────────────────────────────────
+//
+// Every LLM response reports its token usage. The totals are kept per
+// conversation so a runaway tool loop can't spend without bound; once the
+// configured limit is reached the agent stops until the user runs /clear.
+
+pub struct TokenBudget {
+    limit: Option<u64>,
+    /// Input tokens, including those written to or read from a prompt cache.
+    pub input: u64,
+    pub output: u64,
+}
+
+impl TokenBudget {
+    /// A budget of `limit` input plus output tokens (`None` = unlimited).
+    pub fn new(limit: Option<u64>) -> Self {
+        TokenBudget {
+            limit,
+            input: 0,
+            output: 0,
+        }
+    }
+
+    /// Add the usage reported with a response.
+    pub fn record(&mut self, resp: &LlmResponse) {
+        let input = resp.usage_input + resp.usage_cache_creation + resp.usage_cache_read;
+        self.input += input.max(0) as u64;
+        self.output += resp.usage_output.max(0) as u64;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.input + self.output
+    }
+
+    pub fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+
+    /// True once the total has reached the limit.
+    pub fn exhausted(&self) -> bool {
+        self.limit.is_some_and(|l| self.total() >= l)
+    }
+
+    /// Reply to the /usage command.
+    pub fn report(&self) -> String {
+        let mut s = format!(
+            "Tokens used in this conversation: {} input, {} output",
+            self.input, self.output
+        );
+        if let Some(limit) = self.limit {
+            s.push_str(&format!(" ({} of {} budget)", self.total(), limit));
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::provider::StopReason;
+
+    fn usage(input: i64, output: i64, cache_read: i64) -> LlmResponse {
+        LlmResponse {
+            stop_reason: StopReason::EndTurn,
+            content: Vec::new(),
+            usage_input: input,
+            usage_output: output,
+            usage_cache_creation: 0,
+            usage_cache_read: cache_read,
+        }
+    }
+
+    #[test]
+    fn test_exhausted_at_limit() {
+        let mut budget = TokenBudget::new(Some(1000));
+        budget.record(&usage(400, 100, 0));
+        assert!(!budget.exhausted());
+        budget.record(&usage(300, 100, 100));
+        assert_eq!(budget.input, 800);
+        assert_eq!(budget.output, 200);
+        assert!(budget.exhausted());
+        assert!(budget.report().contains("(1000 of 1000 budget)"));
+    }
+
+    #[test]
+    fn test_unlimited_never_exhausted() {
+        let mut budget = TokenBudget::new(None);
+        budget.record(&usage(i64::from(u32::MAX), 1, 0));
+        assert!(!budget.exhausted());
+        assert_eq!(
+            budget.report(),
+            format!("Tokens used in this conversation: {} input, 1 output", u32::MAX)
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+// ── Turn cancellation ───────────────────────────────────────────────────────
+//
+// Turns run on the main thread, which doesn't read the queue until the turn
+// ends, so /cancel is caught by the poll threads instead. They check it
+// against the conversation whose turn is running and raise a flag that the
+// tool loop and the running command poll.
+
+/// Shared between the turn executor and every poll thread.
+#[derive(Default)]
+pub struct CancelSignal {
+    /// Conversation whose turn is running, if any.
+    active: Mutex<Option<String>>,
+    flag: Arc<AtomicBool>,
+}
+
+impl CancelSignal {
+    /// The flag raised by a successful `request`, for code that only needs
+    /// to poll it (the tool executor, a running command).
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.flag)
+    }
+
+    /// A turn for `conversation` is starting. Beginning again before `end`
+    /// (a resumed batch of tool calls, then the turn that follows it) keeps
+    /// a cancel already requested.
+    pub fn begin(&self, conversation: &str) {
+        let mut active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        if active.as_deref() != Some(conversation) {
+            *active = Some(conversation.to_string());
+            self.flag.store(false, Ordering::SeqCst);
+        }
+    }
+
+    /// The turn has ended. Returns whether it was asked to cancel.
+    pub fn end(&self) -> bool {
+        *self.active.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.flag.swap(false, Ordering::SeqCst)
+    }
+
+    /// Cancel the running turn if it belongs to `conversation`. Returns
+    /// false when there is nothing of that conversation's to cancel.
+    pub fn request(&self, conversation: &str) -> bool {
+        let active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        if active.as_deref() != Some(conversation) {
+            return false;
+        }
+        self.flag.store(true, Ordering::SeqCst);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_only_cancels_the_running_conversation() {
+        let signal = CancelSignal::default();
+        let flag = signal.flag();
+        assert!(!signal.request("telegram:1"));
+
+        signal.begin("telegram:1");
+        assert!(!signal.request("telegram:2"));
+        assert!(!flag.load(Ordering::SeqCst));
+        assert!(signal.request("telegram:1"));
+        assert!(flag.load(Ordering::SeqCst));
+
+        signal.begin("telegram:1");
+        assert!(flag.load(Ordering::SeqCst));
+
+        assert!(signal.end());
+        assert!(!flag.load(Ordering::SeqCst));
+        assert!(!signal.request("telegram:1"));
+    }
+}
@@ -0,0 +1,227 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::llm::provider::{ContentBlock, Message};
+
+// ── Tool result freshness ───────────────────────────────────────────────────
+//
+// A file read early in a conversation may have changed since. Results of
+// read-only tools older than the configured TTL are marked as possibly stale
+// in the history sent to the model, so it re-reads instead of trusting them.
+// The stored history is left untouched.
+
+/// Tools whose results describe external state that can change later.
+const READ_ONLY_TOOLS: &[&str] = &[
+    "read_file",
+    "list_directory",
+    "stat_file",
+    "search_files",
+    "http_fetch",
+];
+
+pub struct ToolResultAges {
+    ttl: Option<Duration>,
+    /// When each tool result was produced, by tool_use_id.
+    produced_at: HashMap<String, Instant>,
+}
+
+impl ToolResultAges {
+    pub fn new(ttl: Option<Duration>) -> Self {
+        ToolResultAges {
+            ttl,
+            produced_at: HashMap::new(),
+        }
+    }
+
+    /// Record that the result for `tool_use_id` was just produced.
+    pub fn record(&mut self, tool_use_id: &str) {
+        self.record_at(tool_use_id, Instant::now());
+    }
+
+    fn record_at(&mut self, tool_use_id: &str, at: Instant) {
+        if self.ttl.is_some() {
+            self.produced_at.insert(tool_use_id.to_string(), at);
+        }
+    }
+
+    /// Forget results no longer present in `history` (e.g. after trimming).
+    pub fn retain_in(&mut self, history: &[Message]) {
+        if self.produced_at.is_empty() {
+            return;
+        }
+        let live: Vec<&str> = history
+            .iter()
+            .flat_map(|m| &m.content)
+            .filter_map(|b| match b {
+                ContentBlock::ToolResult { tool_use_id, .. } => Some(tool_use_id.as_str()),
+                _ => None,
+            })
+            .collect();
+        self.produced_at.retain(|id, _| live.contains(&id.as_str()));
+    }
+
+    /// History to send, with stale read-only results annotated. Borrows the
+    /// original when nothing is stale.
+    pub fn annotate<'a>(&self, history: &'a [Message]) -> Cow<'a, [Message]> {
+        self.annotate_at(history, Instant::now())
+    }
+
+    fn annotate_at<'a>(&self, history: &'a [Message], now: Instant) -> Cow<'a, [Message]> {
+        let ttl = match self.ttl {
+            Some(t) => t,
+            None => return Cow::Borrowed(history),
+        };
+
+        // Tool names by call id, to tell which results are read-only
+        let mut names: HashMap<&str, &str> = HashMap::new();
+        for block in history.iter().flat_map(|m| &m.content) {
+            if let ContentBlock::ToolUse { id, name, .. } = block {
+                names.insert(id.as_str(), name.as_str());
+            }
+        }
+
+        let age_of = |id: &str| -> Option<Duration> {
+            let name = names.get(id)?;
+            if !READ_ONLY_TOOLS.contains(name) {
+                return None;
+            }
+            let age = now.saturating_duration_since(*self.produced_at.get(id)?);
+            (age > ttl).then_some(age)
+        };
+
+        let any_stale = history.iter().flat_map(|m| &m.content).any(|b| {
+            matches!(b, ContentBlock::ToolResult { tool_use_id, .. } if age_of(tool_use_id).is_some())
+        });
+        if !any_stale {
+            return Cow::Borrowed(history);
+        }
+
+        let annotated = history
+            .iter()
+            .map(|msg| Message {
+                role: msg.role.clone(),
+                content: msg
+                    .content
+                    .iter()
+                    .map(|block| match block {
+                        ContentBlock::ToolResult {
+                            tool_use_id,
+                            content,
+                            is_error,
+                        } => match age_of(tool_use_id) {
+                            Some(age) => ContentBlock::ToolResult {
+                                tool_use_id: tool_use_id.clone(),
+                                content: format!(
+                                    "(possibly stale: read {}s ago)\n{}",
+                                    age.as_secs(),
+                                    content
+                                ),
+                                is_error: *is_error,
+                            },
+                            None => block.clone(),
+                        },
+                        other => other.clone(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        Cow::Owned(annotated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::provider::Role;
+    use crate::net::json::JsonValue;
+
+    fn call_and_result(id: &str, name: &str, content: &str) -> Vec<Message> {
+        vec![
+            Message {
+                role: Role::Assistant,
+                content: vec![ContentBlock::ToolUse {
+                    id: id.into(),
+                    name: name.into(),
+                    input: JsonValue::Null,
+                }],
+            },
+            Message {
+                role: Role::User,
+                content: vec![ContentBlock::ToolResult {
+                    tool_use_id: id.into(),
+                    content: content.into(),
+                    is_error: false,
+                }],
+            },
+        ]
+    }
+
+    fn result_text(msg: &Message) -> &str {
+        match &msg.content[0] {
+            ContentBlock::ToolResult { content, .. } => content,
+            _ => panic!("expected tool result"),
+        }
+    }
+
+    #[test]
+    fn test_stale_result_annotated_fresh_kept() {
+        let now = Instant::now() + Duration::from_secs(600);
+        let mut history = call_and_result("old", "read_file", "v1");
+        history.extend(call_and_result("new", "read_file", "v2"));
+
+        let mut ages = ToolResultAges::new(Some(Duration::from_secs(60)));
+        ages.record_at("old", now - Duration::from_secs(300));
+        ages.record_at("new", now - Duration::from_secs(10));
+
+        let sent = ages.annotate_at(&history, now);
+        assert!(result_text(&sent[1]).starts_with("(possibly stale: read 300s ago)\n"));
+        assert!(result_text(&sent[1]).ends_with("v1"));
+        assert_eq!(result_text(&sent[3]), "v2");
+        // Stored history is unchanged
+        assert_eq!(result_text(&history[1]), "v1");
+    }
+
+    #[test]
+    fn test_search_and_fetch_results_go_stale() {
+        let now = Instant::now() + Duration::from_secs(600);
+        let mut history = call_and_result("s", "search_files", "a.rs:3: match");
+        history.extend(call_and_result("f", "http_fetch", "200 OK"));
+        let mut ages = ToolResultAges::new(Some(Duration::from_secs(60)));
+        ages.record_at("s", now - Duration::from_secs(120));
+        ages.record_at("f", now - Duration::from_secs(120));
+
+        let sent = ages.annotate_at(&history, now);
+        assert!(result_text(&sent[1]).starts_with("(possibly stale: read 120s ago)\n"));
+        assert!(result_text(&sent[3]).starts_with("(possibly stale: read 120s ago)\n"));
+    }
+
+    #[test]
+    fn test_side_effect_results_never_stale() {
+        let now = Instant::now() + Duration::from_secs(600);
+        let history = call_and_result("w", "write_file", "ok");
+        let mut ages = ToolResultAges::new(Some(Duration::from_secs(60)));
+        ages.record_at("w", now - Duration::from_secs(300));
+        assert!(matches!(ages.annotate_at(&history, now), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_no_ttl_disables_tracking() {
+        let history = call_and_result("a", "read_file", "data");
+        let mut ages = ToolResultAges::new(None);
+        ages.record("a");
+        assert!(ages.produced_at.is_empty());
+        assert!(matches!(ages.annotate(&history), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_retain_in_forgets_trimmed_results() {
+        let history = call_and_result("kept", "read_file", "x");
+        let mut ages = ToolResultAges::new(Some(Duration::from_secs(60)));
+        ages.record("kept");
+        ages.record("trimmed");
+        ages.retain_in(&history);
+        assert_eq!(ages.produced_at.len(), 1);
+        assert!(ages.produced_at.contains_key("kept"));
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use crate::security::audit::AuditEvent;
+
+// ── Metrics ─────────────────────────────────────────────────────────────────
+//
+// Counters for Prometheus to scrape from `metrics_bind`. Most are counted
+// from audit events as they are logged, so every hot path that already
+// audits is covered; LLM latency and turn errors are recorded directly.
+// One process-wide instance, since the auditors and poll threads that
+// record into it are spread over every thread.
+
+/// Upper bounds, in seconds, of the LLM request latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 10] = [0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0];
+/// Largest request line or header block accepted from a scraper.
+const MAX_REQUEST_BYTES: u64 = 16 * 1024;
+/// How long a scraper may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+static METRICS: Metrics = Metrics::new();
+
+/// The process-wide metrics.
+pub fn global() -> &'static Metrics {
+    &METRICS
+}
+
+struct Histogram {
+    /// Observations per bucket (not cumulative), the last past every bound.
+    counts: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: f64,
+}
+
+pub struct Metrics {
+    messages_received: AtomicU64,
+    /// By (tool, "allowed" or "denied").
+    tool_calls: Mutex<BTreeMap<(String, &'static str), u64>>,
+    tokens_input: AtomicU64,
+    tokens_output: AtomicU64,
+    llm_latency: Mutex<Histogram>,
+    errors: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Metrics {
+            messages_received: AtomicU64::new(0),
+            tool_calls: Mutex::new(BTreeMap::new()),
+            tokens_input: AtomicU64::new(0),
+            tokens_output: AtomicU64::new(0),
+            llm_latency: Mutex::new(Histogram {
+                counts: [0; LATENCY_BUCKETS.len() + 1],
+                sum: 0.0,
+            }),
+            errors: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Count what an audit event says happened.
+    pub fn record_event(&self, event: &AuditEvent) {
+        match *event {
+            AuditEvent::MessageReceived { .. } => {
+                self.messages_received.fetch_add(1, Ordering::Relaxed);
+            }
+            AuditEvent::ToolCallAllowed { tool, .. } => self.record_tool_call(tool, "allowed"),
+            AuditEvent::ToolCallDenied { tool, .. } => self.record_tool_call(tool, "denied"),
+            AuditEvent::LlmUsage { input, output, .. } => {
+                self.tokens_input.fetch_add(input.max(0) as u64, Ordering::Relaxed);
+                self.tokens_output.fetch_add(output.max(0) as u64, Ordering::Relaxed);
+            }
+            AuditEvent::UnauthorizedUser { .. } => self.record_error("unauthorized_user"),
+            AuditEvent::EgressDenied { .. } => self.record_error("egress_denied"),
+            AuditEvent::SkillFailed { .. } => self.record_error("skill"),
+            _ => {}
+        }
+    }
+
+    fn record_tool_call(&self, tool: &str, outcome: &'static str) {
+        *lock(&self.tool_calls).entry((tool.to_string(), outcome)).or_default() += 1;
+    }
+
+    /// Count an error of `kind`, e.g. "poll" or "turn".
+    pub fn record_error(&self, kind: &'static str) {
+        *lock(&self.errors).entry(kind).or_default() += 1;
+    }
+
+    pub fn observe_llm_latency(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|&bound| secs <= bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        let mut histogram = lock(&self.llm_latency);
+        histogram.counts[bucket] += 1;
+        histogram.sum += secs;
+    }
+
+    /// Everything in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        header(&mut out, "sentinel_messages_received_total", "counter", "Messages received from all connectors.");
+        out.push_str(&format!(
+            "sentinel_messages_received_total {}\n",
+            self.messages_received.load(Ordering::Relaxed)
+        ));
+
+        header(&mut out, "sentinel_tool_calls_total", "counter", "Tool calls by tool and capability check outcome.");
+        for ((tool, outcome), n) in lock(&self.tool_calls).iter() {
+            out.push_str(&format!(
+                "sentinel_tool_calls_total{{tool=\"{}\",outcome=\"{}\"}} {}\n",
+                escape_label(tool),
+                outcome,
+                n
+            ));
+        }
+
+        header(&mut out, "sentinel_llm_tokens_total", "counter", "LLM tokens by direction.");
+        out.push_str(&format!(
+            "sentinel_llm_tokens_total{{direction=\"input\"}} {}\n",
+            self.tokens_input.load(Ordering::Relaxed)
+        ));
+        out.push_str(&format!(
+            "sentinel_llm_tokens_total{{direction=\"output\"}} {}\n",
+            self.tokens_output.load(Ordering::Relaxed)
+        ));
+
+        header(&mut out, "sentinel_llm_request_duration_seconds", "histogram", "LLM request latency.");
+        let histogram = lock(&self.llm_latency);
+        let mut cumulative = 0;
+        for (bound, n) in LATENCY_BUCKETS.iter().zip(&histogram.counts) {
+            cumulative += n;
+            out.push_str(&format!(
+                "sentinel_llm_request_duration_seconds_bucket{{le=\"{}\"}} {}\n",
+                bound, cumulative
+            ));
+        }
+        cumulative += histogram.counts[LATENCY_BUCKETS.len()];
+        out.push_str(&format!(
+            "sentinel_llm_request_duration_seconds_bucket{{le=\"+Inf\"}} {}\n",
+            cumulative
+        ));
+        out.push_str(&format!("sentinel_llm_request_duration_seconds_sum {}\n", histogram.sum));
+        out.push_str(&format!("sentinel_llm_request_duration_seconds_count {}\n", cumulative));
+        drop(histogram);
+
+        header(&mut out, "sentinel_errors_total", "counter", "Errors by type.");
+        for (kind, n) in lock(&self.errors).iter() {
+            out.push_str(&format!("sentinel_errors_total{{type=\"{}\"}} {}\n", kind, n));
+        }
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+}
+
+/// Escape a label value: backslash, double quote and newline.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// ── Listener ────────────────────────────────────────────────────────────────
+
+/// Bind `addr`. Done before the sandbox, which doesn't allow listening.
+pub fn bind(addr: &str) -> io::Result<(TcpListener, SocketAddr)> {
+    let listener = TcpListener::bind(addr)?;
+    let local = listener.local_addr()?;
+    Ok((listener, local))
+}
+
+/// Answer `GET /metrics` on `listener` from a background thread.
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    thread::Builder::new()
+        .name("metrics".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => handle(stream),
+                    Err(e) => eprintln!("sentinel: metrics accept error: {}", e),
+                }
+            }
+        })?;
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream) {
+    let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(REQUEST_TIMEOUT));
+    let (status, body) = match read_request_target(&mut stream) {
+        Some((method, path)) if path == "/metrics" && method == "GET" => ("200 OK", global().render()),
+        Some((_, path)) if path == "/metrics" => ("405 Method Not Allowed", String::new()),
+        Some(_) => ("404 Not Found", String::new()),
+        None => ("400 Bad Request", String::new()),
+    };
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+}
+
+/// The method and path of a request, after reading through its headers.
+fn read_request_target(stream: impl Read) -> Option<(String, String)> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_BYTES));
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?;
+    let path = target.split('?').next().unwrap_or_default().to_string();
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        if header.trim_end_matches(['\r', '\n']).is_empty() {
+            return Some((method, path));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_counts_events() {
+        let metrics = Metrics::new();
+        metrics.record_event(&AuditEvent::MessageReceived { chat_id: 1, user_id: 2, username: "ann" });
+        metrics.record_event(&AuditEvent::ToolCallAllowed { tool: "read_file", params: "{}" });
+        metrics.record_event(&AuditEvent::ToolCallAllowed { tool: "read_file", params: "{}" });
+        metrics.record_event(&AuditEvent::ToolCallDenied { tool: "run_command", params: "{}", reason: "no" });
+        metrics.record_event(&AuditEvent::LlmUsage { input: 100, output: 20, cache_creation: 0, cache_read: 0 });
+        metrics.record_event(&AuditEvent::EgressDenied { host: "evil.example" });
+        metrics.record_error("poll");
+        metrics.record_error("poll");
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE sentinel_messages_received_total counter\nsentinel_messages_received_total 1\n"));
+        assert!(text.contains("sentinel_tool_calls_total{tool=\"read_file\",outcome=\"allowed\"} 2\n"));
+        assert!(text.contains("sentinel_tool_calls_total{tool=\"run_command\",outcome=\"denied\"} 1\n"));
+        assert!(text.contains("sentinel_llm_tokens_total{direction=\"input\"} 100\n"));
+        assert!(text.contains("sentinel_llm_tokens_total{direction=\"output\"} 20\n"));
+        assert!(text.contains("sentinel_errors_total{type=\"egress_denied\"} 1\n"));
+        assert!(text.contains("sentinel_errors_total{type=\"poll\"} 2\n"));
+    }
+
+    #[test]
+    fn test_latency_histogram_is_cumulative() {
+        let metrics = Metrics::new();
+        metrics.observe_llm_latency(Duration::from_millis(300));
+        metrics.observe_llm_latency(Duration::from_millis(1500));
+        metrics.observe_llm_latency(Duration::from_secs(500));
+
+        let text = metrics.render();
+        assert!(text.contains("sentinel_llm_request_duration_seconds_bucket{le=\"0.25\"} 0\n"));
+        assert!(text.contains("sentinel_llm_request_duration_seconds_bucket{le=\"0.5\"} 1\n"));
+        assert!(text.contains("sentinel_llm_request_duration_seconds_bucket{le=\"2\"} 2\n"));
+        assert!(text.contains("sentinel_llm_request_duration_seconds_bucket{le=\"120\"} 2\n"));
+        assert!(text.contains("sentinel_llm_request_duration_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(text.contains("sentinel_llm_request_duration_seconds_sum 501.8\n"));
+        assert!(text.contains("sentinel_llm_request_duration_seconds_count 3\n"));
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("my_skill"), "my_skill");
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+
+    #[test]
+    fn test_serve_on_bound_listener() {
+        let (listener, addr) = bind("127.0.0.1:0").unwrap();
+        serve(listener).unwrap();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("sentinel_messages_received_total"));
+    }
+
+    #[test]
+    fn test_read_request_target() {
+        let req = b"GET /metrics?x=1 HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        assert_eq!(
+            read_request_target(&req[..]),
+            Some(("GET".to_string(), "/metrics".to_string()))
+        );
+        // Cut off before the end of the headers
+        assert_eq!(read_request_target(&b"GET /metrics HTTP/1.1\r\nHost: x\r\n"[..]), None);
+    }
+}
@@ -1 +1,10 @@
+pub mod approval;
+pub mod budget;
+pub mod cancel;
+pub mod freshness;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod prompt;
+pub mod status;
 pub mod tools;
+pub mod transcript;
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::net::json::{self, json_obj};
+
+// ── Per-turn system prompt ──────────────────────────────────────────────────
+//
+// The base system prompt is fixed per conversation. A `SystemPromptBuilder` adds
+// dynamic context (date, caller, channel) to it on every turn.
+
+/// What is known about the turn being answered. Only `timestamp` is used by
+/// the default builder; the rest is for custom builders.
+#[allow(dead_code)]
+pub struct TurnContext<'a> {
+    pub platform: &'a str,
+    pub channel_id: &'a str,
+    pub user_id: &'a str,
+    pub username: Option<&'a str>,
+    /// Unix seconds when the turn started.
+    pub timestamp: u64,
+}
+
+/// Produces extra system prompt text for a turn. Closures of the form
+/// `Fn(&TurnContext) -> Option<String>` implement this.
+pub trait SystemPromptBuilder {
+    fn build(&self, ctx: &TurnContext) -> Option<String>;
+}
+
+impl<F> SystemPromptBuilder for F
+where
+    F: Fn(&TurnContext) -> Option<String>,
+{
+    fn build(&self, ctx: &TurnContext) -> Option<String> {
+        self(ctx)
+    }
+}
+
+/// Default builder: tells the model today's date.
+pub struct UtcDateContext;
+
+impl SystemPromptBuilder for UtcDateContext {
+    fn build(&self, ctx: &TurnContext) -> Option<String> {
+        let (y, m, d) = civil_from_days((ctx.timestamp / 86400) as i64);
+        Some(format!("Current date: {:04}-{:02}-{:02} (UTC)", y, m, d))
+    }
+}
+
+/// Merge the configured prompt with the builder's output for this turn.
+pub fn compose(
+    base: Option<&str>,
+    builder: &dyn SystemPromptBuilder,
+    ctx: &TurnContext,
+) -> Option<String> {
+    match (base, builder.build(ctx)) {
+        (Some(b), Some(extra)) => Some(format!("{}\n\n{}", b, extra)),
+        (Some(b), None) => Some(b.to_string()),
+        (None, extra) => extra,
+    }
+}
+
+// ── Per-conversation prompts ────────────────────────────────────────────────
+//
+// A conversation ("platform:channel_id") can have its own base prompt: one
+// set by an admin with /setprompt wins over the `[system_prompts]` table,
+// which wins over the global `system_prompt`.
+
+/// File in the state directory that /setprompt overrides are saved to.
+const PROMPTS_FILE: &str = "system_prompts.json";
+
+/// System prompts set at runtime with /setprompt. Shared by the chat
+/// workers, and saved to the state directory when one is configured so
+/// they survive a restart.
+pub struct ChannelPrompts {
+    overrides: Mutex<HashMap<String, String>>,
+    path: Option<PathBuf>,
+    /// The save file, opened at startup. The OS sandbox doesn't let the
+    /// agent write the state directory, since commands it runs would be
+    /// able to as well and rewrite the prompts.
+    file: Option<fs::File>,
+}
+
+impl ChannelPrompts {
+    /// Load the overrides saved in `state_dir`, if any, and open the file
+    /// to save them to. Call before the OS sandbox is applied.
+    pub fn load(state_dir: Option<&str>) -> Self {
+        let path = state_dir.map(|dir| Path::new(dir).join(PROMPTS_FILE));
+        let file = path.as_ref().and_then(|p| {
+            let opened = fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(p);
+            opened
+                .map_err(|e| {
+                    eprintln!(
+                        "sentinel: warning: cannot open '{}', /setprompt won't be saved: {}",
+                        p.display(),
+                        e
+                    )
+                })
+                .ok()
+        });
+        let mut content = String::new();
+        if let Some(mut f) = file.as_ref() {
+            let _ = f.read_to_string(&mut content);
+        }
+        let mut overrides = HashMap::new();
+        if !content.trim().is_empty() {
+            match json::parse(&content) {
+                Ok(doc) => {
+                    for (key, value) in doc.as_object().into_iter().flatten() {
+                        if let Some(prompt) = value.as_str() {
+                            overrides.insert(key.clone(), prompt.to_string());
+                        }
+                    }
+                }
+                Err(e) => eprintln!("sentinel: warning: ignoring saved system prompts: {}", e),
+            }
+        }
+        ChannelPrompts {
+            overrides: Mutex::new(overrides),
+            path,
+            file,
+        }
+    }
+
+    /// The base prompt for a conversation: its /setprompt override, else
+    /// its configured prompt, else the global one.
+    pub fn resolve(
+        &self,
+        conversation: &str,
+        configured: &[(String, String)],
+        global: Option<&str>,
+    ) -> Option<String> {
+        if let Some(prompt) = self.lock().get(conversation) {
+            return Some(prompt.clone());
+        }
+        configured
+            .iter()
+            .find(|(key, _)| key == conversation)
+            .map(|(_, prompt)| prompt.as_str())
+            .or(global)
+            .map(str::to_string)
+    }
+
+    /// Set a conversation's override, or remove it with `None`, and save
+    /// the result. The change applies even if saving fails.
+    pub fn set(&self, conversation: &str, prompt: Option<&str>) -> Result<(), String> {
+        let mut overrides = self.lock();
+        match prompt {
+            Some(p) => overrides.insert(conversation.to_string(), p.to_string()),
+            None => overrides.remove(conversation),
+        };
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let Some(mut file) = self.file.as_ref() else {
+            return Err(format!("cannot save to '{}': it couldn't be opened", path.display()));
+        };
+        let mut keys: Vec<&String> = overrides.keys().collect();
+        keys.sort();
+        let doc = keys
+            .into_iter()
+            .fold(json_obj(), |obj, key| obj.field_str(key, &overrides[key]))
+            .build();
+        let content = doc.to_json_string_pretty(2);
+        // Written over the old contents, then cut to length
+        file.seek(SeekFrom::Start(0))
+            .and_then(|_| file.write_all(content.as_bytes()))
+            .and_then(|()| file.set_len(content.len() as u64))
+            .map_err(|e| format!("cannot save to '{}': {}", path.display(), e))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, String>> {
+        self.overrides.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Proleptic Gregorian (year, month, day) for days since 1970-01-01.
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(timestamp: u64) -> TurnContext<'static> {
+        TurnContext {
+            platform: "telegram",
+            channel_id: "42",
+            user_id: "7",
+            username: Some("alice"),
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_default_appends_utc_date() {
+        // 2025-03-01T12:00:00Z
+        let prompt = compose(Some("Be helpful."), &UtcDateContext, &ctx(1_740_830_400));
+        assert_eq!(
+            prompt.as_deref(),
+            Some("Be helpful.\n\nCurrent date: 2025-03-01 (UTC)")
+        );
+    }
+
+    #[test]
+    fn test_custom_builder_sees_turn_context() {
+        let builder = |c: &TurnContext| {
+            Some(format!(
+                "Talking to {} in {}:{}",
+                c.username.unwrap_or(c.user_id),
+                c.platform,
+                c.channel_id
+            ))
+        };
+        let prompt = compose(None, &builder, &ctx(0));
+        assert_eq!(prompt.as_deref(), Some("Talking to alice in telegram:42"));
+    }
+
+    #[test]
+    fn test_builder_without_output_keeps_base() {
+        let builder = |_: &TurnContext| None;
+        assert_eq!(compose(Some("base"), &builder, &ctx(0)).as_deref(), Some("base"));
+        assert_eq!(compose(None, &builder, &ctx(0)), None);
+    }
+
+    fn configured() -> Vec<(String, String)> {
+        vec![("slack:C1".to_string(), "Be terse.".to_string())]
+    }
+
+    #[test]
+    fn test_channel_prompt_precedence() {
+        let prompts = ChannelPrompts::load(None);
+        let global = Some("Be helpful.");
+        // The global prompt, unless the conversation has its own
+        assert_eq!(
+            prompts.resolve("slack:C2", &configured(), global).as_deref(),
+            Some("Be helpful.")
+        );
+        assert_eq!(
+            prompts.resolve("slack:C1", &configured(), global).as_deref(),
+            Some("Be terse.")
+        );
+        assert_eq!(prompts.resolve("slack:C2", &configured(), None), None);
+
+        // /setprompt wins over both, and clearing it falls back again
+        prompts.set("slack:C1", Some("Be friendly.")).unwrap();
+        assert_eq!(
+            prompts.resolve("slack:C1", &configured(), global).as_deref(),
+            Some("Be friendly.")
+        );
+        prompts.set("slack:C1", None).unwrap();
+        assert_eq!(
+            prompts.resolve("slack:C1", &configured(), global).as_deref(),
+            Some("Be terse.")
+        );
+    }
+
+    #[test]
+    fn test_channel_prompts_persist_to_state_dir() {
+        let dir = "/tmp/sentinel_test_channel_prompts";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+
+        let prompts = ChannelPrompts::load(Some(dir));
+        prompts.set("telegram:42", Some("Answer in \"French\".")).unwrap();
+        prompts.set("discord:7", Some("Ops only.")).unwrap();
+
+        let reloaded = ChannelPrompts::load(Some(dir));
+        assert_eq!(
+            reloaded.resolve("telegram:42", &[], None).as_deref(),
+            Some("Answer in \"French\".")
+        );
+        assert_eq!(reloaded.resolve("discord:7", &[], None).as_deref(), Some("Ops only."));
+
+        // A shorter save leaves nothing of the longer one behind
+        reloaded.set("telegram:42", None).unwrap();
+        let again = ChannelPrompts::load(Some(dir));
+        assert_eq!(again.resolve("telegram:42", &[], None), None);
+        assert_eq!(again.resolve("discord:7", &[], None).as_deref(), Some("Ops only."));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_channel_prompts_save_under_sandbox_without_state_dir() {
+        use crate::security::linux::{apply_sandbox, SeccompMode};
+        // Not under /tmp, which the sandbox lets everything write
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/target/sentinel_test_channel_prompts");
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+
+        let prompts = ChannelPrompts::load(Some(dir));
+        std::thread::spawn(move || {
+            let result = apply_sandbox(&[], &[], true, SeccompMode::Enforce, true);
+            assert!(result.seccomp_applied && result.seccomp_error.is_none());
+            prompts.set("telegram:42", Some("Be brief.")).unwrap();
+            if result.landlock_applied {
+                // The directory itself stays out of reach
+                assert!(fs::write(format!("{}/{}", dir, PROMPTS_FILE), "{}").is_err());
+            }
+        })
+        .join()
+        .unwrap();
+        let reloaded = ChannelPrompts::load(Some(dir));
+        assert_eq!(reloaded.resolve("telegram:42", &[], None).as_deref(), Some("Be brief."));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+}
@@ -0,0 +1,178 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// ── Runtime status ──────────────────────────────────────────────────────────
+//
+// What /status reports: uptime, each connector's last poll, how many
+// conversations the chat workers hold, and the last error and rate limit.
+// Poll threads and chat workers record into one shared `RuntimeStatus`.
+
+/// The outcome of a connector's most recent poll.
+struct PollResult {
+    at: Instant,
+    error: Option<String>,
+}
+
+pub struct RuntimeStatus {
+    started: Instant,
+    connectors: Vec<(&'static str, Mutex<Option<PollResult>>)>,
+    /// Conversations held by each chat worker.
+    conversations: Vec<AtomicUsize>,
+    last_error: Mutex<Option<(Instant, String)>>,
+    last_rate_limit: Mutex<Option<Instant>>,
+}
+
+impl RuntimeStatus {
+    pub fn new(connectors: &[&'static str], workers: usize) -> Self {
+        RuntimeStatus {
+            started: Instant::now(),
+            connectors: connectors.iter().map(|&name| (name, Mutex::new(None))).collect(),
+            conversations: (0..workers).map(|_| AtomicUsize::new(0)).collect(),
+            last_error: Mutex::new(None),
+            last_rate_limit: Mutex::new(None),
+        }
+    }
+
+    /// Record how the poll of the connector at `connector` went.
+    pub fn record_poll(&self, connector: usize, result: Result<(), &str>) {
+        if let Some((_, last)) = self.connectors.get(connector) {
+            *lock(last) = Some(PollResult {
+                at: Instant::now(),
+                error: result.err().map(str::to_string),
+            });
+        }
+    }
+
+    pub fn set_conversations(&self, worker: usize, count: usize) {
+        if let Some(n) = self.conversations.get(worker) {
+            n.store(count, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_error(&self, message: &str) {
+        *lock(&self.last_error) = Some((Instant::now(), message.to_string()));
+    }
+
+    pub fn record_rate_limit(&self) {
+        *lock(&self.last_rate_limit) = Some(Instant::now());
+    }
+
+    /// The /status reply, in Markdown.
+    pub fn report(&self, provider: &str, model: &str) -> String {
+        self.report_at(Instant::now(), provider, model)
+    }
+
+    fn report_at(&self, now: Instant, provider: &str, model: &str) -> String {
+        let ago = |at: Instant| format_duration(now.saturating_duration_since(at));
+        let conversations: usize = self
+            .conversations
+            .iter()
+            .map(|n| n.load(Ordering::Relaxed))
+            .sum();
+
+        let mut out = String::from("**Status**\n");
+        out.push_str(&format!("Uptime: {}\n", ago(self.started)));
+        out.push_str(&format!("Model: {} ({})\n", model, provider));
+        out.push_str(&format!("Active conversations: {}\n", conversations));
+
+        out.push_str("\n**Connectors**\n");
+        for (name, last) in &self.connectors {
+            let health = match &*lock(last) {
+                None => "no poll yet".to_string(),
+                Some(PollResult { at, error: None }) => format!("ok (last poll {} ago)", ago(*at)),
+                Some(PollResult { at, error: Some(e) }) => {
+                    format!("failing (last poll {} ago): {}", ago(*at), e)
+                }
+            };
+            out.push_str(&format!("{}: {}\n", name, health));
+        }
+
+        out.push('\n');
+        match *lock(&self.last_rate_limit) {
+            Some(at) => out.push_str(&format!("Last rate limit: {} ago\n", ago(at))),
+            None => out.push_str("Last rate limit: none\n"),
+        }
+        match &*lock(&self.last_error) {
+            Some((at, e)) => out.push_str(&format!("Last error: {} ago: {}", ago(*at), e)),
+            None => out.push_str("Last error: none"),
+        }
+        out
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// "45s", "12m 5s", "3h 4m", or "2d 1h".
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    let (days, hours, mins) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, mins)
+    } else if mins > 0 {
+        format!("{}m {}s", mins, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_before_anything_happened() {
+        let status = RuntimeStatus::new(&["telegram", "slack"], 2);
+        let report = status.report_at(status.started, "anthropic", "claude-sonnet-4-5");
+        assert_eq!(
+            report,
+            "**Status**\n\
+             Uptime: 0s\n\
+             Model: claude-sonnet-4-5 (anthropic)\n\
+             Active conversations: 0\n\
+             \n\
+             **Connectors**\n\
+             telegram: no poll yet\n\
+             slack: no poll yet\n\
+             \n\
+             Last rate limit: none\n\
+             Last error: none"
+        );
+    }
+
+    #[test]
+    fn test_report_tracks_polls_conversations_and_errors() {
+        let status = RuntimeStatus::new(&["telegram", "slack"], 2);
+        status.record_poll(0, Ok(()));
+        status.record_poll(1, Err("HTTP 503"));
+        status.record_poll(7, Ok(())); // unknown connectors are ignored
+        status.set_conversations(0, 2);
+        status.set_conversations(1, 3);
+        status.record_rate_limit();
+        status.record_error("LLM API error: overloaded");
+
+        let later = Instant::now() + Duration::from_secs(90);
+        let report = status.report_at(later, "openai", "gpt-4o");
+        assert!(report.contains("Active conversations: 5\n"));
+        assert!(report.contains("telegram: ok (last poll 1m 30s ago)\n"));
+        assert!(report.contains("slack: failing (last poll 1m 30s ago): HTTP 503\n"));
+        assert!(report.contains("Last rate limit: 1m 30s ago\n"));
+        assert!(report.ends_with("Last error: 1m 30s ago: LLM API error: overloaded"));
+
+        // A later successful poll clears the failure
+        status.record_poll(1, Ok(()));
+        assert!(status.report("openai", "gpt-4o").contains("slack: ok (last poll 0s ago)\n"));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(format_duration(Duration::from_secs(725)), "12m 5s");
+        assert_eq!(format_duration(Duration::from_secs(3 * 3600 + 240)), "3h 4m");
+        assert_eq!(format_duration(Duration::from_secs(2 * 86400 + 3600)), "2d 1h");
+    }
+}
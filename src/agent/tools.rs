@@ -1,7 +1,16 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::agent::approval::ApprovalPolicy;
+use crate::agent::prompt::TurnContext;
 use crate::llm::provider::{ContentBlock, ToolDef};
 use crate::net::json::{json_obj, json_arr, JsonValue};
+#[cfg(feature = "tls")]
+use crate::net::http::{self, HttpClient};
 use crate::platform::{CapType, Platform};
 use crate::security::audit::{AuditEvent, Auditor};
+use crate::security::capability::{ArgPolicies, CapabilityChecker, CapabilityResult};
 use crate::skills::SkillRunner;
 
 // ── Tool executor ───────────────────────────────────────────────────────────
@@ -9,7 +18,15 @@ use crate::skills::SkillRunner;
 pub struct ToolExecutor<'a> {
     platform: &'a dyn Platform,
     command_timeout: u64,
+    output_limit: usize,
+    max_tool_output: usize,
     skill_runner: Option<&'a SkillRunner>,
+    approval: ApprovalPolicy,
+    cancel: Option<Arc<AtomicBool>>,
+    #[cfg(feature = "tls")]
+    fetch: Option<HttpClient>,
+    network: CapabilityChecker,
+    command_args: ArgPolicies,
 }
 
 impl<'a> ToolExecutor<'a> {
@@ -17,7 +34,15 @@ impl<'a> ToolExecutor<'a> {
         ToolExecutor {
             platform,
             command_timeout: command_timeout_secs,
+            output_limit: DEFAULT_OUTPUT_LIMIT,
+            max_tool_output: DEFAULT_MAX_TOOL_OUTPUT,
             skill_runner: None,
+            approval: ApprovalPolicy::default(),
+            cancel: None,
+            #[cfg(feature = "tls")]
+            fetch: None,
+            network: CapabilityChecker::new(Vec::new(), Vec::new(), Vec::new()),
+            command_args: ArgPolicies::default(),
         }
     }
 
@@ -26,11 +51,61 @@ impl<'a> ToolExecutor<'a> {
         self
     }
 
+    /// Most bytes of command output returned to the model; the middle of
+    /// longer output is elided.
+    pub fn with_output_limit(mut self, bytes: usize) -> Self {
+        self.output_limit = bytes;
+        self
+    }
+
+    /// Most bytes of any tool result; longer results are cut with a marker.
+    pub fn with_max_tool_output(mut self, bytes: usize) -> Self {
+        self.max_tool_output = bytes;
+        self
+    }
+
+    /// Refuse run_command calls whose arguments `policies` deny.
+    pub fn with_command_args(mut self, policies: ArgPolicies) -> Self {
+        self.command_args = policies;
+        self
+    }
+
+    /// Hold calls to the policy's tools until the user approves them.
+    pub fn with_approval(mut self, policy: ApprovalPolicy) -> Self {
+        self.approval = policy;
+        self
+    }
+
+    pub fn approval_policy(&self) -> &ApprovalPolicy {
+        &self.approval
+    }
+
+    /// Stop the turn's tool loop once `flag` is raised.
+    pub fn with_cancel(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(flag);
+        self
+    }
+
+    /// Enable http_fetch, sending requests through `http` to the hosts
+    /// `network` allows.
+    #[cfg(feature = "tls")]
+    pub fn with_http_fetch(mut self, http: HttpClient, network: CapabilityChecker) -> Self {
+        self.fetch = Some(http);
+        self.network = network;
+        self
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|f| f.load(Ordering::SeqCst))
+    }
+
     pub fn tool_definitions() -> Vec<ToolDef> {
         vec![
             ToolDef {
                 name: "read_file".into(),
-                description: "Read the contents of a file at the given path.".into(),
+                description: "Read the contents of a file at the given path. Use offset and \
+                              limit to read a large file in parts."
+                    .into(),
                 input_schema: json_obj()
                     .field_str("type", "object")
                     .field(
@@ -43,6 +118,20 @@ impl<'a> ToolExecutor<'a> {
                                     .field_str("description", "Absolute path to the file to read")
                                     .build(),
                             )
+                            .field(
+                                "offset",
+                                json_obj()
+                                    .field_str("type", "integer")
+                                    .field_str("description", "Line to start reading at, counting from 1")
+                                    .build(),
+                            )
+                            .field(
+                                "limit",
+                                json_obj()
+                                    .field_str("type", "integer")
+                                    .field_str("description", "Most lines to read")
+                                    .build(),
+                            )
                             .build(),
                     )
                     .field("required", json_arr().push_str("path").build())
@@ -78,6 +167,107 @@ impl<'a> ToolExecutor<'a> {
                     )
                     .build(),
             },
+            ToolDef {
+                name: "edit_file".into(),
+                description: "Replace text in a file. old_string must occur exactly once \
+                              unless replace_all is set; include enough surrounding text \
+                              to make it unique."
+                    .into(),
+                input_schema: json_obj()
+                    .field_str("type", "object")
+                    .field(
+                        "properties",
+                        json_obj()
+                            .field(
+                                "path",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "Absolute path to the file to edit")
+                                    .build(),
+                            )
+                            .field(
+                                "old_string",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "Exact text to replace")
+                                    .build(),
+                            )
+                            .field(
+                                "new_string",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "Text to replace it with")
+                                    .build(),
+                            )
+                            .field(
+                                "replace_all",
+                                json_obj()
+                                    .field_str("type", "boolean")
+                                    .field_str("description", "Replace every occurrence (default false)")
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .field(
+                        "required",
+                        json_arr()
+                            .push_str("path")
+                            .push_str("old_string")
+                            .push_str("new_string")
+                            .build(),
+                    )
+                    .build(),
+            },
+            ToolDef {
+                name: "append_file".into(),
+                description: "Append content to the end of a file, creating it if it doesn't exist.".into(),
+                input_schema: json_obj()
+                    .field_str("type", "object")
+                    .field(
+                        "properties",
+                        json_obj()
+                            .field(
+                                "path",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "Absolute path to the file to append to")
+                                    .build(),
+                            )
+                            .field(
+                                "content",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "Content to add to the end of the file")
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .field(
+                        "required",
+                        json_arr().push_str("path").push_str("content").build(),
+                    )
+                    .build(),
+            },
+            ToolDef {
+                name: "delete_file".into(),
+                description: "Delete a file. Directories are not deleted.".into(),
+                input_schema: json_obj()
+                    .field_str("type", "object")
+                    .field(
+                        "properties",
+                        json_obj()
+                            .field(
+                                "path",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "Absolute path to the file to delete")
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .field("required", json_arr().push_str("path").build())
+                    .build(),
+            },
             ToolDef {
                 name: "list_directory".into(),
                 description: "List the contents of a directory.".into(),
@@ -98,6 +288,139 @@ impl<'a> ToolExecutor<'a> {
                     .field("required", json_arr().push_str("path").build())
                     .build(),
             },
+            ToolDef {
+                name: "create_directory".into(),
+                description: "Create a directory, including any missing parent directories.".into(),
+                input_schema: json_obj()
+                    .field_str("type", "object")
+                    .field(
+                        "properties",
+                        json_obj()
+                            .field(
+                                "path",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "Absolute path of the directory to create")
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .field("required", json_arr().push_str("path").build())
+                    .build(),
+            },
+            ToolDef {
+                name: "stat_file".into(),
+                description: "Get the size, type, modification time, and a text/binary guess for a path, without reading it.".into(),
+                input_schema: json_obj()
+                    .field_str("type", "object")
+                    .field(
+                        "properties",
+                        json_obj()
+                            .field(
+                                "path",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "Absolute path to the file or directory")
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .field("required", json_arr().push_str("path").build())
+                    .build(),
+            },
+            ToolDef {
+                name: "search_files".into(),
+                description: "Search the text files under a directory for a literal string. \
+                              Returns matching lines as file:line: text."
+                    .into(),
+                input_schema: json_obj()
+                    .field_str("type", "object")
+                    .field(
+                        "properties",
+                        json_obj()
+                            .field(
+                                "path",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "Absolute path to the directory to search")
+                                    .build(),
+                            )
+                            .field(
+                                "pattern",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "Text to find (matched literally)")
+                                    .build(),
+                            )
+                            .field(
+                                "glob",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "Only search files whose name matches, e.g. \"*.rs\"")
+                                    .build(),
+                            )
+                            .field(
+                                "case_insensitive",
+                                json_obj()
+                                    .field_str("type", "boolean")
+                                    .field_str("description", "Ignore case when matching (default false)")
+                                    .build(),
+                            )
+                            .field(
+                                "max_results",
+                                json_obj()
+                                    .field_str("type", "integer")
+                                    .field_str("description", "Most matching lines to return (default 100)")
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .field("required", json_arr().push_str("path").push_str("pattern").build())
+                    .build(),
+            },
+            ToolDef {
+                name: "http_fetch".into(),
+                description: "Make an HTTPS request to an allowed host and return the \
+                              status, headers and body."
+                    .into(),
+                input_schema: json_obj()
+                    .field_str("type", "object")
+                    .field(
+                        "properties",
+                        json_obj()
+                            .field(
+                                "url",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "https:// URL to request")
+                                    .build(),
+                            )
+                            .field(
+                                "method",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "GET, POST, PUT, PATCH or DELETE (default GET)")
+                                    .build(),
+                            )
+                            .field(
+                                "headers",
+                                json_obj()
+                                    .field_str("type", "object")
+                                    .field_str("description", "Extra request headers, name to value")
+                                    .build(),
+                            )
+                            .field(
+                                "body",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "JSON request body for POST, PUT or PATCH")
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .field("required", json_arr().push_str("url").build())
+                    .build(),
+            },
             ToolDef {
                 name: "run_command".into(),
                 description: "Run a shell command and return its output.".into(),
@@ -124,6 +447,26 @@ impl<'a> ToolExecutor<'a> {
                                     .field_str("description", "Arguments to the command")
                                     .build(),
                             )
+                            .field(
+                                "cwd",
+                                json_obj()
+                                    .field_str("type", "string")
+                                    .field_str("description", "Absolute path of the directory to run in; must be readable")
+                                    .build(),
+                            )
+                            .field(
+                                "env",
+                                json_obj()
+                                    .field_str("type", "object")
+                                    .field_str(
+                                        "description",
+                                        "Environment variables to set, name to value: LANG, LC_ALL, \
+                                         LC_CTYPE, LC_MESSAGES, TZ, TERM, NO_COLOR, COLUMNS or LINES. \
+                                         The command otherwise sees only PATH, HOME, LANG, LC_ALL, \
+                                         TZ and TERM.",
+                                    )
+                                    .build(),
+                            )
                             .build(),
                     )
                     .field("required", json_arr().push_str("command").build())
@@ -132,51 +475,52 @@ impl<'a> ToolExecutor<'a> {
         ]
     }
 
+    /// Execute a tool call made during the turn described by `ctx`, which
+    /// skills are told. Interim progress (currently from skills) is
+    /// forwarded to `on_progress` while the tool runs.
     pub fn execute(
         &self,
         tool_use_id: &str,
         name: &str,
         input: &JsonValue,
+        ctx: &TurnContext,
         auditor: &mut Auditor,
+        on_progress: &mut dyn FnMut(&str),
     ) -> ContentBlock {
         let params_str = input.to_json_string();
 
         let result = match name {
             "read_file" => self.exec_read_file(input, auditor, &params_str),
             "write_file" => self.exec_write_file(input, auditor, &params_str),
+            "edit_file" => self.exec_edit_file(input, auditor, &params_str),
+            "append_file" => self.exec_append_file(input, auditor, &params_str),
+            "delete_file" => self.exec_delete_file(input, auditor, &params_str),
             "list_directory" => self.exec_list_directory(input, auditor, &params_str),
-            "run_command" => self.exec_run_command(input, auditor, &params_str),
+            "create_directory" => self.exec_create_directory(input, auditor, &params_str),
+            "stat_file" => self.exec_stat_file(input, auditor, &params_str),
+            "search_files" => self.exec_search_files(input, auditor, &params_str),
+            "run_command" => self.exec_run_command(input, auditor, &params_str, on_progress),
+            "http_fetch" => self.exec_http_fetch(input, auditor, &params_str),
             _ => {
                 // Check if a loaded skill handles this tool
-                if let Some(runner) = self.skill_runner {
-                    if runner.handles(name) {
-                        return match runner.execute(name, input, auditor) {
-                            Ok(output) => ContentBlock::ToolResult {
-                                tool_use_id: tool_use_id.to_string(),
-                                content: output,
-                                is_error: false,
-                            },
-                            Err(err) => ContentBlock::ToolResult {
-                                tool_use_id: tool_use_id.to_string(),
-                                content: err,
-                                is_error: true,
-                            },
-                        };
+                match self.skill_runner {
+                    Some(runner) if runner.handles(name) => {
+                        runner.execute(name, input, ctx, auditor, on_progress)
                     }
+                    _ => Err(format!("unknown tool: {}", name)),
                 }
-                Err(format!("unknown tool: {}", name))
             }
         };
 
         match result {
             Ok(output) => ContentBlock::ToolResult {
                 tool_use_id: tool_use_id.to_string(),
-                content: output,
+                content: truncate_output(output, self.max_tool_output),
                 is_error: false,
             },
             Err(err) => ContentBlock::ToolResult {
                 tool_use_id: tool_use_id.to_string(),
-                content: err,
+                content: truncate_output(err, self.max_tool_output),
                 is_error: true,
             },
         }
@@ -214,9 +558,36 @@ impl<'a> ToolExecutor<'a> {
             }
         }
 
-        self.platform
+        let content = self
+            .platform
             .read_file(path)
-            .map_err(|e| format!("failed to read '{}': {}", path, e))
+            .map_err(|e| format!("failed to read '{}': {}", path, e))?;
+        let offset = input.get("offset").and_then(|v| v.as_u64());
+        let limit = input.get("limit").and_then(|v| v.as_u64());
+        if offset.is_none() && limit.is_none() {
+            return Ok(content);
+        }
+
+        let total = content.lines().count();
+        let start = offset.unwrap_or(1).max(1) as usize;
+        if start > total.max(1) {
+            return Err(format!("offset {} is past the end of '{}' ({} lines)", start, path, total));
+        }
+        let count = limit.map_or(usize::MAX, |n| n as usize);
+        let mut out: Vec<&str> = content.lines().skip(start - 1).take(count).collect();
+        let end = start - 1 + out.len();
+        let note;
+        if end < total {
+            note = format!(
+                "[lines {}-{} of {}; use offset {} to read on]",
+                start,
+                end,
+                total,
+                end + 1
+            );
+            out.push(&note);
+        }
+        Ok(out.join("\n"))
     }
 
     fn exec_write_file(
@@ -261,7 +632,7 @@ impl<'a> ToolExecutor<'a> {
             .map_err(|e| format!("failed to write '{}': {}", path, e))
     }
 
-    fn exec_list_directory(
+    fn exec_append_file(
         &self,
         input: &JsonValue,
         auditor: &mut Auditor,
@@ -271,36 +642,220 @@ impl<'a> ToolExecutor<'a> {
             .get("path")
             .and_then(|v| v.as_str())
             .ok_or("missing 'path' parameter")?;
+        let content = input
+            .get("content")
+            .and_then(|v| v.as_str())
+            .ok_or("missing 'content' parameter")?;
 
-        match self.platform.check_capability(CapType::FileRead, path) {
-            Ok(true) => {
-                auditor.log(AuditEvent::ToolCallAllowed {
-                    tool: "list_directory",
-                    params: params_str,
-                });
-            }
-            Ok(false) => {
-                let reason = format!("read access denied for path '{}'", path);
-                auditor.log(AuditEvent::ToolCallDenied {
-                    tool: "list_directory",
-                    params: params_str,
-                    reason: &reason,
-                });
-                return Err(format!("access denied: {}", reason));
-            }
-            Err(e) => {
-                return Err(format!("capability check failed: {}", e));
-            }
-        }
-
-        let entries = self
-            .platform
-            .list_directory(path)
-            .map_err(|e| format!("failed to list '{}': {}", path, e))?;
+        self.check_write("append_file", CapType::FileWrite, path, path, auditor, params_str)?;
+        self.platform
+            .append_file(path, content)
+            .map(|_| format!("appended {} bytes to '{}'", content.len(), path))
+            .map_err(|e| format!("failed to append to '{}': {}", path, e))
+    }
 
-        let lines: Vec<String> = entries
-            .iter()
-            .map(|e| {
+    fn exec_delete_file(
+        &self,
+        input: &JsonValue,
+        auditor: &mut Auditor,
+        params_str: &str,
+    ) -> Result<String, String> {
+        let path = input
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or("missing 'path' parameter")?;
+
+        self.check_write("delete_file", CapType::FileDelete, path, path, auditor, params_str)?;
+        self.platform
+            .delete_file(path)
+            .map(|_| format!("deleted '{}'", path))
+            .map_err(|e| format!("failed to delete '{}': {}", path, e))
+    }
+
+    fn exec_create_directory(
+        &self,
+        input: &JsonValue,
+        auditor: &mut Auditor,
+        params_str: &str,
+    ) -> Result<String, String> {
+        let path = input
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or("missing 'path' parameter")?;
+
+        // Paths are checked by resolving their parent, which may not exist
+        // yet. Check the first missing directory instead: everything created
+        // below it is inside it, as long as no component climbs back out.
+        let target = std::path::Path::new(path);
+        if target.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+            return Err(format!("'{}' must not contain '..'", path));
+        }
+        let mut first_missing = target;
+        while let Some(parent) = first_missing.parent() {
+            if parent.as_os_str().is_empty() || parent.exists() {
+                break;
+            }
+            first_missing = parent;
+        }
+        let checked = first_missing.to_string_lossy();
+        self.check_write("create_directory", CapType::FileWrite, path, &checked, auditor, params_str)?;
+        self.platform
+            .create_directory(path)
+            .map(|_| format!("created directory '{}'", path))
+            .map_err(|e| format!("failed to create '{}': {}", path, e))
+    }
+
+    /// Check write access (`cap`) to `checked` for a call on `path`,
+    /// auditing the outcome.
+    fn check_write(
+        &self,
+        tool: &str,
+        cap: CapType,
+        path: &str,
+        checked: &str,
+        auditor: &mut Auditor,
+        params_str: &str,
+    ) -> Result<(), String> {
+        match self.platform.check_capability(cap, checked) {
+            Ok(true) => {
+                auditor.log(AuditEvent::ToolCallAllowed {
+                    tool,
+                    params: params_str,
+                });
+                Ok(())
+            }
+            Ok(false) => {
+                let reason = format!("write access denied for path '{}'", path);
+                auditor.log(AuditEvent::ToolCallDenied {
+                    tool,
+                    params: params_str,
+                    reason: &reason,
+                });
+                Err(format!("access denied: {}", reason))
+            }
+            Err(e) => Err(format!("capability check failed: {}", e)),
+        }
+    }
+
+    fn exec_edit_file(
+        &self,
+        input: &JsonValue,
+        auditor: &mut Auditor,
+        params_str: &str,
+    ) -> Result<String, String> {
+        let path = input
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or("missing 'path' parameter")?;
+        let old_string = input
+            .get("old_string")
+            .and_then(|v| v.as_str())
+            .ok_or("missing 'old_string' parameter")?;
+        let new_string = input
+            .get("new_string")
+            .and_then(|v| v.as_str())
+            .ok_or("missing 'new_string' parameter")?;
+        let replace_all = input
+            .get("replace_all")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        if old_string.is_empty() {
+            return Err("'old_string' must not be empty".into());
+        }
+
+        // The file is read and written back, so both grants are needed
+        for (cap, kind) in [(CapType::FileRead, "read"), (CapType::FileWrite, "write")] {
+            match self.platform.check_capability(cap, path) {
+                Ok(true) => {}
+                Ok(false) => {
+                    let reason = format!("{} access denied for path '{}'", kind, path);
+                    auditor.log(AuditEvent::ToolCallDenied {
+                        tool: "edit_file",
+                        params: params_str,
+                        reason: &reason,
+                    });
+                    return Err(format!("access denied: {}", reason));
+                }
+                Err(e) => {
+                    return Err(format!("capability check failed: {}", e));
+                }
+            }
+        }
+        auditor.log(AuditEvent::ToolCallAllowed {
+            tool: "edit_file",
+            params: params_str,
+        });
+
+        let original = self
+            .platform
+            .read_file(path)
+            .map_err(|e| format!("failed to read '{}': {}", path, e))?;
+        let count = original.matches(old_string).count();
+        if count == 0 {
+            return Err(format!("'old_string' not found in '{}'", path));
+        }
+        if count > 1 && !replace_all {
+            return Err(format!(
+                "'old_string' occurs {} times in '{}'; include more surrounding text \
+                 to make it unique, or set replace_all",
+                count, path
+            ));
+        }
+
+        let edited = original.replace(old_string, new_string);
+        self.platform
+            .write_file(path, &edited)
+            .map_err(|e| format!("failed to write '{}': {}", path, e))?;
+
+        Ok(format!(
+            "edited '{}' ({} replacement{})\n{}",
+            path,
+            count,
+            if count == 1 { "" } else { "s" },
+            edit_diff(&original, old_string, new_string)
+        ))
+    }
+
+    fn exec_list_directory(
+        &self,
+        input: &JsonValue,
+        auditor: &mut Auditor,
+        params_str: &str,
+    ) -> Result<String, String> {
+        let path = input
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or("missing 'path' parameter")?;
+
+        match self.platform.check_capability(CapType::FileRead, path) {
+            Ok(true) => {
+                auditor.log(AuditEvent::ToolCallAllowed {
+                    tool: "list_directory",
+                    params: params_str,
+                });
+            }
+            Ok(false) => {
+                let reason = format!("read access denied for path '{}'", path);
+                auditor.log(AuditEvent::ToolCallDenied {
+                    tool: "list_directory",
+                    params: params_str,
+                    reason: &reason,
+                });
+                return Err(format!("access denied: {}", reason));
+            }
+            Err(e) => {
+                return Err(format!("capability check failed: {}", e));
+            }
+        }
+
+        let entries = self
+            .platform
+            .list_directory(path)
+            .map_err(|e| format!("failed to list '{}': {}", path, e))?;
+
+        let lines: Vec<String> = entries
+            .iter()
+            .map(|e| {
                 if e.is_dir {
                     format!("{}/", e.name)
                 } else {
@@ -312,28 +867,28 @@ impl<'a> ToolExecutor<'a> {
         Ok(lines.join("\n"))
     }
 
-    fn exec_run_command(
+    fn exec_stat_file(
         &self,
         input: &JsonValue,
         auditor: &mut Auditor,
         params_str: &str,
     ) -> Result<String, String> {
-        let command = input
-            .get("command")
+        let path = input
+            .get("path")
             .and_then(|v| v.as_str())
-            .ok_or("missing 'command' parameter")?;
+            .ok_or("missing 'path' parameter")?;
 
-        match self.platform.check_capability(CapType::Command, command) {
+        match self.platform.check_capability(CapType::FileRead, path) {
             Ok(true) => {
                 auditor.log(AuditEvent::ToolCallAllowed {
-                    tool: "run_command",
+                    tool: "stat_file",
                     params: params_str,
                 });
             }
             Ok(false) => {
-                let reason = format!("command '{}' not in allowlist", command);
+                let reason = format!("read access denied for path '{}'", path);
                 auditor.log(AuditEvent::ToolCallDenied {
-                    tool: "run_command",
+                    tool: "stat_file",
                     params: params_str,
                     reason: &reason,
                 });
@@ -344,6 +899,269 @@ impl<'a> ToolExecutor<'a> {
             }
         }
 
+        let stat = self
+            .platform
+            .stat_file(path)
+            .map_err(|e| format!("failed to stat '{}': {}", path, e))?;
+
+        let mut obj = json_obj()
+            .field_i64("size", stat.size as i64)
+            .field_bool("is_dir", stat.is_dir);
+        obj = match stat.modified {
+            Some(t) => obj.field_i64("modified", t as i64),
+            None => obj.field_null("modified"),
+        };
+        obj = match stat.is_text {
+            Some(text) => obj.field_bool("is_text", text),
+            None => obj.field_null("is_text"),
+        };
+        Ok(obj.build().to_json_string())
+    }
+
+    fn exec_search_files(
+        &self,
+        input: &JsonValue,
+        auditor: &mut Auditor,
+        params_str: &str,
+    ) -> Result<String, String> {
+        let path = input
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or("missing 'path' parameter")?;
+        let pattern = input
+            .get("pattern")
+            .and_then(|v| v.as_str())
+            .ok_or("missing 'pattern' parameter")?;
+        if pattern.is_empty() {
+            return Err("'pattern' must not be empty".into());
+        }
+        let glob = input.get("glob").and_then(|v| v.as_str());
+        let case_insensitive = input
+            .get("case_insensitive")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let max_results = input
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .map_or(SEARCH_DEFAULT_RESULTS, |n| (n as usize).clamp(1, SEARCH_MAX_RESULTS));
+
+        match self.platform.check_capability(CapType::FileRead, path) {
+            Ok(true) => {
+                auditor.log(AuditEvent::ToolCallAllowed {
+                    tool: "search_files",
+                    params: params_str,
+                });
+            }
+            Ok(false) => {
+                let reason = format!("read access denied for path '{}'", path);
+                auditor.log(AuditEvent::ToolCallDenied {
+                    tool: "search_files",
+                    params: params_str,
+                    reason: &reason,
+                });
+                return Err(format!("access denied: {}", reason));
+            }
+            Err(e) => {
+                return Err(format!("capability check failed: {}", e));
+            }
+        }
+
+        let needle = if case_insensitive {
+            pattern.to_lowercase()
+        } else {
+            pattern.to_string()
+        };
+        let mut search = Search {
+            needle,
+            case_insensitive,
+            glob,
+            max_results,
+            results: Vec::new(),
+            output_len: 0,
+            files_seen: 0,
+            truncated: false,
+        };
+        self.search_dir(path.trim_end_matches('/'), 0, &mut search)?;
+
+        if search.results.is_empty() {
+            return Ok(format!("no matches for '{}' under '{}'", pattern, path));
+        }
+        let mut out = search.results.join("\n");
+        if search.truncated {
+            out.push_str(&format!("\n(stopped after {} matches)", search.results.len()));
+        }
+        Ok(out)
+    }
+
+    fn exec_http_fetch(
+        &self,
+        input: &JsonValue,
+        auditor: &mut Auditor,
+        params_str: &str,
+    ) -> Result<String, String> {
+        let url = input
+            .get("url")
+            .and_then(|v| v.as_str())
+            .ok_or("missing 'url' parameter")?;
+        let method = input
+            .get("method")
+            .and_then(|v| v.as_str())
+            .unwrap_or("GET")
+            .to_ascii_uppercase();
+        if !matches!(method.as_str(), "GET" | "POST" | "PUT" | "PATCH" | "DELETE") {
+            return Err(format!("unsupported method '{}'", method));
+        }
+        let body = input.get("body").and_then(|v| v.as_str());
+        if body.is_some() && !matches!(method.as_str(), "POST" | "PUT" | "PATCH") {
+            return Err(format!("{} requests can't have a body", method));
+        }
+        let mut headers = Vec::new();
+        if let Some(fields) = input.get("headers").and_then(|v| v.as_object()) {
+            for (name, value) in fields {
+                let value = value
+                    .as_str()
+                    .ok_or_else(|| format!("header '{}' must be a string", name))?;
+                if FETCH_RESERVED_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+                    return Err(format!("header '{}' can't be set", name));
+                }
+                if name.contains(['\r', '\n', ':']) || value.contains(['\r', '\n']) {
+                    return Err(format!("header '{}' is malformed", name));
+                }
+                headers.push((name.clone(), value.to_string()));
+            }
+        }
+
+        #[cfg(feature = "tls")]
+        {
+            let Some(ref http) = self.fetch else {
+                return Err("http_fetch is disabled (no allowed_fetch_hosts configured)".into());
+            };
+            // A listed name that resolves to an internal address is refused
+            // by the client itself, against the addresses it connects to
+            let (host, port) = http::url_host(url).map_err(|e| e.to_string())?;
+            let verdict = self.network.check_network(&host, port);
+            auditor.log(AuditEvent::NetworkCall {
+                host: &host,
+                port,
+                allowed: matches!(verdict, CapabilityResult::Allowed),
+            });
+            if let CapabilityResult::Denied(reason) = verdict {
+                auditor.log(AuditEvent::ToolCallDenied {
+                    tool: "http_fetch",
+                    params: params_str,
+                    reason: &reason,
+                });
+                return Err(format!("access denied: {}", reason));
+            }
+            auditor.log(AuditEvent::ToolCallAllowed {
+                tool: "http_fetch",
+                params: params_str,
+            });
+
+            let extra: Vec<(&str, &str)> =
+                headers.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+            let body = body.unwrap_or("");
+            let response = match method.as_str() {
+                "GET" => http.get(url, &extra),
+                "DELETE" => http.delete(url, &extra),
+                "POST" => http.post_json(url, body, &extra),
+                "PUT" => http.put_json(url, body, &extra),
+                _ => http.patch_json(url, body, &extra),
+            }
+            .map_err(|e| format!("request failed: {}", e))?;
+            Ok(format_fetch_response(response.status, &response.headers, &response.body))
+        }
+        #[cfg(not(feature = "tls"))]
+        {
+            let _ = (url, body, headers, auditor, params_str, &self.network);
+            Err("http_fetch is not available in this build (tls feature disabled)".into())
+        }
+    }
+
+    /// Walk `dir` depth-first, collecting matches until a limit is hit.
+    /// Symlinked directories aren't followed, and every file must pass the
+    /// read check on its own, so links can't lead outside the allowed paths.
+    fn search_dir(&self, dir: &str, depth: usize, search: &mut Search) -> Result<(), String> {
+        let entries = self
+            .platform
+            .list_directory(dir)
+            .map_err(|e| format!("failed to list '{}': {}", dir, e))?;
+        for entry in entries {
+            if search.truncated {
+                return Ok(());
+            }
+            let path = format!("{}/{}", dir, entry.name);
+            if entry.is_dir {
+                // Unreadable subdirectories are skipped, not fatal
+                if depth < SEARCH_MAX_DEPTH {
+                    let _ = self.search_dir(&path, depth + 1, search);
+                }
+                continue;
+            }
+            if search.glob.is_some_and(|g| !glob_match(g, &entry.name)) {
+                continue;
+            }
+            search.files_seen += 1;
+            if search.files_seen > SEARCH_MAX_FILES {
+                search.truncated = true;
+                return Ok(());
+            }
+            if !matches!(self.platform.check_capability(CapType::FileRead, &path), Ok(true)) {
+                continue;
+            }
+            match self.platform.stat_file(&path) {
+                Ok(stat) if stat.is_text == Some(true) && stat.size <= SEARCH_MAX_FILE_BYTES => {}
+                _ => continue,
+            }
+            let Ok(text) = self.platform.read_file(&path) else {
+                continue;
+            };
+            for (n, line) in text.lines().enumerate() {
+                let hit = if search.case_insensitive {
+                    line.to_lowercase().contains(&search.needle)
+                } else {
+                    line.contains(&search.needle)
+                };
+                if hit && !search.push(format!("{}:{}: {}", path, n + 1, truncate_line(line))) {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_run_command(
+        &self,
+        input: &JsonValue,
+        auditor: &mut Auditor,
+        params_str: &str,
+        on_progress: &mut dyn FnMut(&str),
+    ) -> Result<String, String> {
+        let command = input
+            .get("command")
+            .and_then(|v| v.as_str())
+            .ok_or("missing 'command' parameter")?;
+        let cwd = input.get("cwd").and_then(|v| v.as_str());
+        let mut env = Vec::new();
+        if let Some(fields) = input.get("env").and_then(|v| v.as_object()) {
+            for (name, value) in fields {
+                let value = value
+                    .as_str()
+                    .ok_or_else(|| format!("env var '{}' must be a string", name))?;
+                if name.is_empty() || name.contains(['=', '\0']) || value.contains('\0') {
+                    return Err(format!("env var '{}' is malformed", name));
+                }
+                if !SETTABLE_ENV.contains(&name.as_str()) {
+                    return Err(format!(
+                        "env var '{}' can't be set; only {}",
+                        name,
+                        SETTABLE_ENV.join(", ")
+                    ));
+                }
+                env.push((name.clone(), value.to_string()));
+            }
+        }
+
         let args: Vec<String> = input
             .get("args")
             .and_then(|v| v.as_array())
@@ -354,9 +1172,51 @@ impl<'a> ToolExecutor<'a> {
             })
             .unwrap_or_default();
 
+        let denied = match self.platform.check_capability(CapType::Command, command) {
+            Ok(true) => match (self.command_args.check(command, &args), cwd) {
+                (CapabilityResult::Denied(reason), _) => Some(reason),
+                (CapabilityResult::Allowed, Some(dir)) => {
+                    match self.platform.check_capability(CapType::FileRead, dir) {
+                        Ok(true) => None,
+                        Ok(false) => Some(format!("read access denied for cwd '{}'", dir)),
+                        Err(e) => return Err(format!("capability check failed: {}", e)),
+                    }
+                }
+                (CapabilityResult::Allowed, None) => None,
+            },
+            Ok(false) => Some(format!("command '{}' not in allowlist", command)),
+            Err(e) => return Err(format!("capability check failed: {}", e)),
+        };
+        match denied {
+            None => {
+                auditor.log(AuditEvent::ToolCallAllowed {
+                    tool: "run_command",
+                    params: params_str,
+                });
+            }
+            Some(reason) => {
+                auditor.log(AuditEvent::ToolCallDenied {
+                    tool: "run_command",
+                    params: params_str,
+                    reason: &reason,
+                });
+                return Err(format!("access denied: {}", reason));
+            }
+        }
+
+        // Progress shows the latest few lines rather than just the last
+        let mut tail: VecDeque<String> = VecDeque::with_capacity(PROGRESS_TAIL_LINES);
+        let mut on_line = |line: &str| {
+            if tail.len() == PROGRESS_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(truncate_line(line));
+            let shown: Vec<&str> = tail.iter().map(String::as_str).collect();
+            on_progress(&format!("\n{}", shown.join("\n")));
+        };
         let output = self
             .platform
-            .run_command(command, &args, self.command_timeout)
+            .run_command_streaming(command, &args, cwd, &env, self.command_timeout, &mut on_line)
             .map_err(|e| format!("{}", e))?;
 
         let mut result = String::new();
@@ -370,6 +1230,7 @@ impl<'a> ToolExecutor<'a> {
             result.push_str(&output.stderr);
         }
 
+        let result = elide_middle(&result, self.output_limit);
         if output.exit_code == 0 {
             Ok(result)
         } else {
@@ -381,6 +1242,209 @@ impl<'a> ToolExecutor<'a> {
     }
 }
 
+/// Default for `with_max_tool_output`.
+pub const DEFAULT_MAX_TOOL_OUTPUT: usize = 100 * 1024;
+
+/// Cut `output` to `limit` bytes, saying how much was dropped.
+fn truncate_output(mut output: String, limit: usize) -> String {
+    if output.len() <= limit {
+        return output;
+    }
+    let mut cut = limit;
+    while !output.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    let dropped = output.len() - cut;
+    output.truncate(cut);
+    output.push_str(&format!("\n[...truncated {} bytes...]", dropped));
+    output
+}
+
+/// Default for `with_output_limit`.
+pub const DEFAULT_OUTPUT_LIMIT: usize = 32 * 1024;
+/// Output lines shown in a running command's progress message.
+const PROGRESS_TAIL_LINES: usize = 10;
+/// Variables a run_command call may set. Anything else could change which
+/// binary runs or what it loads (PATH, LD_*, PYTHONPATH, GIT_SSH_COMMAND
+/// and many more), so only ones that shape output are let through.
+const SETTABLE_ENV: &[&str] = &[
+    "LANG", "LC_ALL", "LC_CTYPE", "LC_MESSAGES", "TZ", "TERM", "NO_COLOR", "COLUMNS", "LINES",
+];
+
+/// Keep the start and end of `text` within `limit` bytes, noting how much
+/// was cut from the middle. Errors usually show up at the end of a build or
+/// test run, so the end gets the larger share.
+fn elide_middle(text: &str, limit: usize) -> String {
+    if text.len() <= limit {
+        return text.to_string();
+    }
+    let mut head = limit / 4;
+    while !text.is_char_boundary(head) {
+        head -= 1;
+    }
+    let mut tail = text.len() - (limit - limit / 4);
+    while !text.is_char_boundary(tail) {
+        tail += 1;
+    }
+    format!(
+        "{}\n… ({} bytes elided) …\n{}",
+        &text[..head],
+        tail - head,
+        &text[tail..]
+    )
+}
+
+/// Matching lines returned by search_files when `max_results` isn't given,
+/// and the most it may ask for.
+const SEARCH_DEFAULT_RESULTS: usize = 100;
+const SEARCH_MAX_RESULTS: usize = 1000;
+/// Bytes of search output returned, whatever `max_results` allows.
+const SEARCH_MAX_OUTPUT: usize = 16 * 1024;
+/// Longest matching line shown in full.
+const SEARCH_MAX_LINE_CHARS: usize = 200;
+/// Files larger than this are skipped rather than read.
+const SEARCH_MAX_FILE_BYTES: u64 = 1024 * 1024;
+/// Bounds on the walk itself, so a huge tree can't stall the turn.
+const SEARCH_MAX_FILES: usize = 10_000;
+const SEARCH_MAX_DEPTH: usize = 20;
+
+/// State of one search_files walk.
+struct Search<'a> {
+    needle: String,
+    case_insensitive: bool,
+    glob: Option<&'a str>,
+    max_results: usize,
+    results: Vec<String>,
+    output_len: usize,
+    files_seen: usize,
+    truncated: bool,
+}
+
+impl Search<'_> {
+    /// Record a match. Returns false (and marks the search truncated) once
+    /// a result or output limit is reached.
+    fn push(&mut self, line: String) -> bool {
+        if self.results.len() >= self.max_results || self.output_len + line.len() > SEARCH_MAX_OUTPUT {
+            self.truncated = true;
+            return false;
+        }
+        self.output_len += line.len() + 1;
+        self.results.push(line);
+        true
+    }
+}
+
+fn truncate_line(line: &str) -> String {
+    match line.char_indices().nth(SEARCH_MAX_LINE_CHARS) {
+        Some((cut, _)) => format!("{}…", &line[..cut]),
+        None => line.to_string(),
+    }
+}
+
+/// Match a file name against a glob with `*` (any run of characters) and
+/// `?` (any one character).
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    // Where the last `*` was, and how much of the name it has taken
+    let mut star: Option<(usize, usize)> = None;
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((sp, sn)) = star {
+            pi = sp + 1;
+            ni = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Headers the client sets itself.
+const FETCH_RESERVED_HEADERS: &[&str] = &[
+    "host",
+    "content-length",
+    "content-encoding",
+    "transfer-encoding",
+    "connection",
+];
+/// Longest response body returned to the model.
+const FETCH_MAX_BODY: usize = 32 * 1024;
+
+/// Status line, headers and body text, with the body cut at FETCH_MAX_BODY.
+fn format_fetch_response(status: u16, headers: &[(String, String)], body: &[u8]) -> String {
+    let mut out = format!("HTTP {}\n", status);
+    for (name, value) in headers {
+        out.push_str(&format!("{}: {}\n", name, value));
+    }
+    out.push('\n');
+    if body.is_empty() {
+        return out;
+    }
+    let shown = &body[..body.len().min(FETCH_MAX_BODY)];
+    let text = match std::str::from_utf8(shown) {
+        Ok(text) => text,
+        // Cut mid-character: drop the partial sequence
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&shown[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return format!("{}(binary body, {} bytes)", out, body.len()),
+    };
+    out.push_str(text);
+    if shown.len() < body.len() {
+        out.push_str(&format!("\n(truncated, {} bytes total)", body.len()));
+    }
+    out
+}
+
+/// Most hunks shown in an edit_file summary.
+const MAX_DIFF_HUNKS: usize = 10;
+
+/// A unified-diff-style summary of replacing every `old` in `original`
+/// with `new`: one hunk per replacement, covering the lines it touches.
+fn edit_diff(original: &str, old: &str, new: &str) -> String {
+    let mut out = String::new();
+    // New-file lines minus old-file lines from the replacements so far
+    let mut line_shift: isize = 0;
+    let per_edit = new.matches('\n').count() as isize - old.matches('\n').count() as isize;
+    let matches: Vec<usize> = original.match_indices(old).map(|(i, _)| i).collect();
+    for (n, &at) in matches.iter().enumerate() {
+        if n == MAX_DIFF_HUNKS {
+            out.push_str(&format!("... {} more\n", matches.len() - n));
+            break;
+        }
+        let end = at + old.len();
+        let line_start = original[..at].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = original[end..].find('\n').map_or(original.len(), |i| end + i);
+        let before = &original[line_start..line_end];
+        let after = format!("{}{}{}", &original[line_start..at], new, &original[end..line_end]);
+
+        let old_line = original[..line_start].matches('\n').count() as isize + 1;
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_line,
+            before.split('\n').count(),
+            old_line + line_shift,
+            after.split('\n').count()
+        ));
+        for line in before.split('\n') {
+            out.push_str(&format!("-{}\n", line));
+        }
+        for line in after.split('\n') {
+            out.push_str(&format!("+{}\n", line));
+        }
+        line_shift += per_edit;
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -396,14 +1460,212 @@ mod tests {
         )
     }
 
+    fn test_ctx() -> TurnContext<'static> {
+        TurnContext {
+            platform: "telegram",
+            channel_id: "42",
+            user_id: "7",
+            username: None,
+            timestamp: 0,
+        }
+    }
+
     #[test]
     fn test_tool_definitions_count() {
         let defs = ToolExecutor::tool_definitions();
-        assert_eq!(defs.len(), 4);
+        assert_eq!(defs.len(), 11);
         assert_eq!(defs[0].name, "read_file");
         assert_eq!(defs[1].name, "write_file");
-        assert_eq!(defs[2].name, "list_directory");
-        assert_eq!(defs[3].name, "run_command");
+        assert_eq!(defs[2].name, "edit_file");
+        assert_eq!(defs[3].name, "append_file");
+        assert_eq!(defs[4].name, "delete_file");
+        assert_eq!(defs[5].name, "list_directory");
+        assert_eq!(defs[6].name, "create_directory");
+        assert_eq!(defs[7].name, "stat_file");
+        assert_eq!(defs[8].name, "search_files");
+        assert_eq!(defs[9].name, "http_fetch");
+        assert_eq!(defs[10].name, "run_command");
+    }
+
+    fn call(executor: &ToolExecutor, name: &str, input: JsonValue) -> (bool, String) {
+        let platform = test_platform(vec![], vec![], vec![]);
+        let mut auditor = Auditor::new(&platform);
+        match executor.execute("test-id", name, &input, &test_ctx(), &mut auditor, &mut |_| {}) {
+            ContentBlock::ToolResult { is_error, content, .. } => (is_error, content),
+            _ => panic!("expected ToolResult"),
+        }
+    }
+
+    #[test]
+    fn test_append_file() {
+        let path = "/tmp/sentinel_test_append.txt";
+        let _ = std::fs::remove_file(path);
+        let platform = test_platform(vec![], vec!["/tmp"], vec![]);
+        let executor = ToolExecutor::new(&platform, 5);
+        let append = |text: &str| {
+            call(&executor, "append_file", json_obj().field_str("path", path).field_str("content", text).build())
+        };
+        assert_eq!(append("one\n"), (false, format!("appended 4 bytes to '{}'", path)));
+        append("two\n");
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "one\ntwo\n");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_delete_file() {
+        let dir = "/tmp/sentinel_test_delete";
+        let _ = std::fs::create_dir_all(dir);
+        let file = format!("{}/gone.txt", dir);
+        std::fs::write(&file, "x").unwrap();
+        let platform = test_platform(vec![], vec!["/tmp"], vec![]);
+        let executor = ToolExecutor::new(&platform, 5);
+
+        let (is_error, content) = call(&executor, "delete_file", json_obj().field_str("path", dir).build());
+        assert!(is_error);
+        assert!(content.contains("is a directory"), "{}", content);
+        assert_eq!(
+            call(&executor, "delete_file", json_obj().field_str("path", &file).build()),
+            (false, format!("deleted '{}'", file))
+        );
+        assert!(!std::path::Path::new(&file).exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_delete_symlink_checks_the_link() {
+        let dir = "/tmp/sentinel_test_delete_link";
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(format!("{}/work", dir)).unwrap();
+        std::fs::create_dir_all(format!("{}/keep", dir)).unwrap();
+        let target = format!("{}/keep/data.txt", dir);
+        std::fs::write(&target, "keep").unwrap();
+        let link = format!("{}/work/link", dir);
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let inward = format!("{}/keep/inward", dir);
+        std::os::unix::fs::symlink(format!("{}/work", dir), &inward).unwrap();
+        let platform = test_platform(vec![], vec![&format!("{}/work", dir)], vec![]);
+        let executor = ToolExecutor::new(&platform, 5);
+
+        // The link is inside the write paths even though its target isn't
+        assert_eq!(
+            call(&executor, "delete_file", json_obj().field_str("path", &link).build()),
+            (false, format!("deleted '{}'", link))
+        );
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "keep");
+        // and a link outside them is denied even though its target is inside
+        let (is_error, content) = call(&executor, "delete_file", json_obj().field_str("path", &inward).build());
+        assert!(is_error);
+        assert!(content.contains("write access denied"), "{}", content);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_file_management_under_agent_filter() {
+        let root = "/tmp/sentinel_test_mgmt_filtered";
+        let _ = std::fs::remove_dir_all(root);
+        std::fs::create_dir_all(root).unwrap();
+        std::fs::write(format!("{}/gone.txt", root), "x").unwrap();
+        std::thread::spawn(move || {
+            use crate::security::linux::{apply_sandbox, SeccompMode};
+            let agent = apply_sandbox(&[], &[], true, SeccompMode::Enforce, false);
+            assert!(agent.seccomp_applied && agent.seccomp_error.is_none());
+            let platform = test_platform(vec![], vec![root], vec![]);
+            let executor = ToolExecutor::new(&platform, 5);
+            let path = format!("{}/gone.txt", root);
+            assert!(!call(&executor, "delete_file", json_obj().field_str("path", &path).build()).0);
+            let nested = format!("{}/a/b", root);
+            assert!(!call(&executor, "create_directory", json_obj().field_str("path", &nested).build()).0);
+        })
+        .join()
+        .unwrap();
+        assert!(!std::path::Path::new(&format!("{}/gone.txt", root)).exists());
+        assert!(std::path::Path::new(&format!("{}/a/b", root)).is_dir());
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_create_directory() {
+        let root = "/tmp/sentinel_test_mkdir";
+        let _ = std::fs::remove_dir_all(root);
+        let platform = test_platform(vec![], vec!["/tmp"], vec![]);
+        let executor = ToolExecutor::new(&platform, 5);
+        let nested = format!("{}/a/b", root);
+        assert_eq!(
+            call(&executor, "create_directory", json_obj().field_str("path", &nested).build()),
+            (false, format!("created directory '{}'", nested))
+        );
+        assert!(std::path::Path::new(&nested).is_dir());
+
+        let (is_error, content) = call(
+            &executor,
+            "create_directory",
+            json_obj().field_str("path", &format!("{}/c/../../../../etc/x", root)).build(),
+        );
+        assert!(is_error);
+        assert!(content.contains("'..'"), "{}", content);
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_file_management_denied_outside_write_paths() {
+        let platform = test_platform(vec!["/tmp"], vec!["/tmp/sentinel_test_none"], vec![]);
+        let executor = ToolExecutor::new(&platform, 5);
+        let path = "/tmp/sentinel_test_denied_mgmt.txt";
+        std::fs::write(path, "keep").unwrap();
+        for (tool, input) in [
+            ("append_file", json_obj().field_str("path", path).field_str("content", "x").build()),
+            ("delete_file", json_obj().field_str("path", path).build()),
+            ("create_directory", json_obj().field_str("path", "/var/sentinel_test/new/dir").build()),
+        ] {
+            let (is_error, content) = call(&executor, tool, input);
+            assert!(is_error, "{}", tool);
+            assert!(content.contains("write access denied"), "{}: {}", tool, content);
+        }
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "keep");
+        let _ = std::fs::remove_file(path);
+    }
+
+    fn fetch_error(executor: &ToolExecutor, url: &str) -> String {
+        let (is_error, content) = call(executor, "http_fetch", json_obj().field_str("url", url).build());
+        assert!(is_error, "should fail: {}", content);
+        content
+    }
+
+    #[test]
+    fn test_http_fetch_disabled_without_hosts() {
+        let platform = test_platform(vec![], vec![], vec![]);
+        let executor = ToolExecutor::new(&platform, 5);
+        let err = fetch_error(&executor, "https://api.example.com/");
+        assert!(err.contains("http_fetch"), "{}", err);
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn test_http_fetch_denies_unlisted_and_internal_hosts() {
+        let platform = test_platform(vec![], vec![], vec![]);
+        let network = CapabilityChecker::new(vec![], vec![], vec![])
+            .with_network_hosts(vec!["api.example.com".into(), "127.0.0.1".into()]);
+        let executor = ToolExecutor::new(&platform, 5)
+            .with_http_fetch(HttpClient::new().unwrap(), network);
+        let err = fetch_error(&executor, "https://other.example.com/");
+        assert!(err.contains("not in allowlist"), "{}", err);
+        let err = fetch_error(&executor, "https://127.0.0.1:8080/admin");
+        assert!(err.contains("internal address"), "{}", err);
+        let err = fetch_error(&executor, "http://api.example.com/");
+        assert!(err.contains("invalid URL"), "{}", err);
+    }
+
+    #[test]
+    fn test_format_fetch_response() {
+        let headers = vec![("Content-Type".to_string(), "text/plain".to_string())];
+        assert_eq!(
+            format_fetch_response(200, &headers, b"hello"),
+            "HTTP 200\nContent-Type: text/plain\n\nhello"
+        );
+        let big = "é".repeat(FETCH_MAX_BODY);
+        let out = format_fetch_response(200, &[], big.as_bytes());
+        assert!(out.ends_with(&format!("(truncated, {} bytes total)", big.len())));
+        assert!(format_fetch_response(200, &[], &[0xff, 0xfe, 0]).contains("binary body, 3 bytes"));
     }
 
     #[test]
@@ -417,7 +1679,7 @@ mod tests {
             .field("args", json_arr().push_str("10").build())
             .build();
 
-        let result = executor.execute("test-id", "run_command", &input, &mut auditor);
+        let result = executor.execute("test-id", "run_command", &input, &test_ctx(), &mut auditor, &mut |_| {});
         match result {
             ContentBlock::ToolResult { is_error, content, .. } => {
                 assert!(is_error, "should be an error");
@@ -438,7 +1700,7 @@ mod tests {
             .field("args", json_arr().push_str("hello").build())
             .build();
 
-        let result = executor.execute("test-id", "run_command", &input, &mut auditor);
+        let result = executor.execute("test-id", "run_command", &input, &test_ctx(), &mut auditor, &mut |_| {});
         match result {
             ContentBlock::ToolResult { is_error, content, .. } => {
                 assert!(!is_error, "should succeed");
@@ -458,7 +1720,7 @@ mod tests {
             .field_str("command", "rm")
             .build();
 
-        let result = executor.execute("test-id", "run_command", &input, &mut auditor);
+        let result = executor.execute("test-id", "run_command", &input, &test_ctx(), &mut auditor, &mut |_| {});
         match result {
             ContentBlock::ToolResult { is_error, content, .. } => {
                 assert!(is_error, "should be denied");
@@ -468,6 +1730,157 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_command_output_streams_and_is_capped() {
+        let platform = test_platform(vec![], vec![], vec!["seq"]);
+        let executor = ToolExecutor::new(&platform, 5).with_output_limit(100);
+        let mut auditor = Auditor::new(&platform);
+        let input = json_obj()
+            .field_str("command", "seq")
+            .field("args", json_arr().push_str("1000").build())
+            .build();
+        let mut updates = Vec::new();
+        let result = executor.execute("test-id", "run_command", &input, &test_ctx(), &mut auditor, &mut |p| {
+            updates.push(p.to_string())
+        });
+        assert_eq!(updates.len(), 1000);
+        assert_eq!(updates[2], "\n1\n2\n3");
+        assert!(updates[999].ends_with("\n991\n992\n993\n994\n995\n996\n997\n998\n999\n1000"));
+        match result {
+            ContentBlock::ToolResult { is_error, content, .. } => {
+                assert!(!is_error);
+                // seq 1000 prints 3893 bytes
+                assert!(content.starts_with("1\n2\n"), "{}", content);
+                assert!(content.contains("(3793 bytes elided)"), "{}", content);
+                assert!(content.ends_with("999\n1000\n"), "{}", content);
+            }
+            _ => panic!("expected ToolResult"),
+        }
+    }
+
+    #[test]
+    fn test_tool_output_is_truncated() {
+        let path = "/tmp/sentinel_test_truncate.txt";
+        std::fs::write(path, "x".repeat(250)).unwrap();
+        let platform = test_platform(vec!["/tmp"], vec![], vec![]);
+        let executor = ToolExecutor::new(&platform, 5).with_max_tool_output(100);
+        let (is_error, content) = call(&executor, "read_file", json_obj().field_str("path", path).build());
+        assert!(!is_error);
+        assert_eq!(content, format!("{}\n[...truncated 150 bytes...]", "x".repeat(100)));
+        let _ = std::fs::remove_file(path);
+
+        assert_eq!(truncate_output("ééé".into(), 3), "é\n[...truncated 4 bytes...]");
+    }
+
+    #[test]
+    fn test_read_file_line_range() {
+        let path = "/tmp/sentinel_test_line_range.txt";
+        std::fs::write(path, "one\ntwo\nthree\nfour\nfive\n").unwrap();
+        let platform = test_platform(vec!["/tmp"], vec![], vec![]);
+        let executor = ToolExecutor::new(&platform, 5);
+        let range = |offset: Option<i64>, limit: Option<i64>| {
+            let mut input = json_obj().field_str("path", path);
+            if let Some(o) = offset {
+                input = input.field_i64("offset", o);
+            }
+            if let Some(l) = limit {
+                input = input.field_i64("limit", l);
+            }
+            call(&executor, "read_file", input.build())
+        };
+
+        assert_eq!(
+            range(Some(2), Some(2)),
+            (false, "two\nthree\n[lines 2-3 of 5; use offset 4 to read on]".to_string())
+        );
+        assert_eq!(range(Some(4), None), (false, "four\nfive".to_string()));
+        assert_eq!(range(None, Some(1)).1, "one\n[lines 1-1 of 5; use offset 2 to read on]");
+        let (is_error, content) = range(Some(9), None);
+        assert!(is_error);
+        assert!(content.contains("past the end"), "{}", content);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_elide_middle() {
+        assert_eq!(elide_middle("short", 10), "short");
+        assert_eq!(elide_middle("aaaabbbbbbbbcccc", 8), "aa\n… (8 bytes elided) …\nbbcccc");
+        // Never splits a character
+        let out = elide_middle(&"é".repeat(10), 9);
+        assert!(out.starts_with("é\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_command_cwd() {
+        let dir = "/tmp/sentinel_test_cwd";
+        let _ = std::fs::create_dir_all(dir);
+        std::fs::write(format!("{}/marker.txt", dir), "here").unwrap();
+        let platform = test_platform(vec!["/tmp"], vec![], vec!["cat"]);
+        let executor = ToolExecutor::new(&platform, 5);
+        let input = json_obj()
+            .field_str("command", "cat")
+            .field("args", json_arr().push_str("marker.txt").build())
+            .field_str("cwd", dir)
+            .build();
+        assert_eq!(call(&executor, "run_command", input), (false, "here".to_string()));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_command_cwd_denied() {
+        let platform = test_platform(vec!["/tmp"], vec![], vec!["ls"]);
+        let executor = ToolExecutor::new(&platform, 5);
+        let input = json_obj().field_str("command", "ls").field_str("cwd", "/etc").build();
+        let (is_error, content) = call(&executor, "run_command", input);
+        assert!(is_error);
+        assert!(content.contains("read access denied for cwd '/etc'"), "{}", content);
+    }
+
+    #[test]
+    fn test_command_denied_argument() {
+        let platform = test_platform(vec![], vec![], vec!["sh"]);
+        let policies = ArgPolicies::new(&[], &[("sh".into(), vec!["-c".into()])]);
+        let executor = ToolExecutor::new(&platform, 5).with_command_args(policies);
+        let input = json_obj()
+            .field_str("command", "sh")
+            .field("args", json_arr().push_str("-ec").push_str("echo ran").build())
+            .build();
+        let (is_error, content) = call(&executor, "run_command", input);
+        assert!(is_error);
+        assert!(content.contains("argument '-ec' is not allowed for 'sh'"), "{}", content);
+    }
+
+    #[test]
+    fn test_command_env_is_scrubbed() {
+        let platform = test_platform(vec![], vec![], vec!["env"]);
+        let executor = ToolExecutor::new(&platform, 5);
+        let input = json_obj()
+            .field_str("command", "env")
+            .field("env", json_obj().field_str("NO_COLOR", "1").build())
+            .build();
+        let (is_error, content) = call(&executor, "run_command", input);
+        assert!(!is_error, "{}", content);
+        assert!(content.contains("NO_COLOR=1"), "{}", content);
+        // Cargo sets this for the test process; the command shouldn't see it
+        assert!(!content.contains("CARGO_MANIFEST_DIR"), "{}", content);
+
+        let input = json_obj()
+            .field_str("command", "env")
+            .field("env", json_obj().field_str("LD_PRELOAD", "/tmp/x.so").build())
+            .build();
+        let (is_error, content) = call(&executor, "run_command", input);
+        assert!(is_error);
+        assert!(content.contains("can't be set"), "{}", content);
+        // Only listed names get through, not just ones known to be risky
+        let input = json_obj()
+            .field_str("command", "env")
+            .field("env", json_obj().field_str("PYTHONPATH", "/tmp").build())
+            .build();
+        let (is_error, content) = call(&executor, "run_command", input);
+        assert!(is_error);
+        assert!(content.contains("can't be set"), "{}", content);
+    }
+
     #[test]
     fn test_unknown_tool() {
         let platform = test_platform(vec![], vec![], vec![]);
@@ -475,7 +1888,7 @@ mod tests {
         let mut auditor = Auditor::new(&platform);
 
         let input = JsonValue::Null;
-        let result = executor.execute("test-id", "nonexistent_tool", &input, &mut auditor);
+        let result = executor.execute("test-id", "nonexistent_tool", &input, &test_ctx(), &mut auditor, &mut |_| {});
         match result {
             ContentBlock::ToolResult { is_error, content, .. } => {
                 assert!(is_error);
@@ -496,7 +1909,7 @@ mod tests {
         let mut auditor = Auditor::new(&platform);
 
         let input = json_obj().field_str("path", path).build();
-        let result = executor.execute("test-id", "read_file", &input, &mut auditor);
+        let result = executor.execute("test-id", "read_file", &input, &test_ctx(), &mut auditor, &mut |_| {});
         match result {
             ContentBlock::ToolResult { is_error, content, .. } => {
                 assert!(!is_error, "should succeed: {}", content);
@@ -508,6 +1921,25 @@ mod tests {
         std::fs::remove_file(path).ok();
     }
 
+    #[test]
+    fn test_read_file_under_denied_path() {
+        let home = "/tmp/sentinel_test_denied_read";
+        let _ = std::fs::remove_dir_all(home);
+        std::fs::create_dir_all(format!("{}/.ssh", home)).unwrap();
+        std::fs::write(format!("{}/.ssh/id_rsa", home), "key").unwrap();
+        std::fs::write(format!("{}/notes.txt", home), "notes").unwrap();
+
+        let platform = test_platform(vec![home], vec![], vec![])
+            .with_denied_paths(vec![format!("{}/.ssh", home)], vec![]);
+        let executor = ToolExecutor::new(&platform, 5);
+        let read = |path: String| call(&executor, "read_file", json_obj().field_str("path", &path).build());
+        assert_eq!(read(format!("{}/notes.txt", home)), (false, "notes".to_string()));
+        let (is_error, content) = read(format!("{}/.ssh/id_rsa", home));
+        assert!(is_error && content.contains("access denied"), "{}", content);
+
+        let _ = std::fs::remove_dir_all(home);
+    }
+
     #[test]
     fn test_write_file() {
         let path = "/tmp/sentinel_test_write.txt";
@@ -520,7 +1952,7 @@ mod tests {
             .field_str("path", path)
             .field_str("content", "written by test")
             .build();
-        let result = executor.execute("test-id", "write_file", &input, &mut auditor);
+        let result = executor.execute("test-id", "write_file", &input, &test_ctx(), &mut auditor, &mut |_| {});
         match result {
             ContentBlock::ToolResult { is_error, content, .. } => {
                 assert!(!is_error, "should succeed: {}", content);
@@ -534,6 +1966,170 @@ mod tests {
         std::fs::remove_file(path).ok();
     }
 
+    fn edit(path: &str, old: &str, new: &str, replace_all: bool) -> (bool, String) {
+        let platform = test_platform(vec!["/tmp"], vec!["/tmp"], vec![]);
+        let executor = ToolExecutor::new(&platform, 5);
+        let input = json_obj()
+            .field_str("path", path)
+            .field_str("old_string", old)
+            .field_str("new_string", new)
+            .field_bool("replace_all", replace_all)
+            .build();
+        call(&executor, "edit_file", input)
+    }
+
+    #[test]
+    fn test_edit_file_not_found() {
+        let path = "/tmp/sentinel_test_edit_missing.txt";
+        std::fs::write(path, "alpha\nbeta\n").unwrap();
+        let (is_error, content) = edit(path, "gamma", "delta", false);
+        assert!(is_error);
+        assert!(content.contains("not found"), "{}", content);
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "alpha\nbeta\n");
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_edit_file_ambiguous_match() {
+        let path = "/tmp/sentinel_test_edit_ambiguous.txt";
+        std::fs::write(path, "x = 1\ny = 1\n").unwrap();
+        let (is_error, content) = edit(path, "= 1", "= 2", false);
+        assert!(is_error);
+        assert!(content.contains("occurs 2 times"), "{}", content);
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "x = 1\ny = 1\n");
+
+        let (is_error, content) = edit(path, "= 1", "= 2", true);
+        assert!(!is_error, "{}", content);
+        assert!(content.starts_with(&format!("edited '{}' (2 replacements)", path)));
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "x = 2\ny = 2\n");
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_edit_file_success() {
+        let path = "/tmp/sentinel_test_edit_success.txt";
+        std::fs::write(path, "fn main() {\n    println!(\"hi\");\n}\n").unwrap();
+        let (is_error, content) = edit(path, "\"hi\"", "\"hello\"", false);
+        assert!(!is_error, "{}", content);
+        assert_eq!(
+            content,
+            format!(
+                "edited '{}' (1 replacement)\n@@ -2,1 +2,1 @@\n-    println!(\"hi\");\n+    println!(\"hello\");\n",
+                path
+            )
+        );
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "fn main() {\n    println!(\"hello\");\n}\n"
+        );
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_edit_diff_tracks_line_shift() {
+        let diff = edit_diff("a\nb\na\n", "a", "a1\na2");
+        assert_eq!(diff, "@@ -1,1 +1,2 @@\n-a\n+a1\n+a2\n@@ -3,1 +4,2 @@\n-a\n+a1\n+a2\n");
+    }
+
+    #[test]
+    fn test_edit_file_denied_without_write_access() {
+        let platform = test_platform(vec!["/tmp"], vec![], vec![]);
+        let executor = ToolExecutor::new(&platform, 5);
+        let input = json_obj()
+            .field_str("path", "/tmp/sentinel_test_edit_denied.txt")
+            .field_str("old_string", "a")
+            .field_str("new_string", "b")
+            .build();
+        let (is_error, content) = call(&executor, "edit_file", input);
+        assert!(is_error);
+        assert!(content.contains("write access denied"), "{}", content);
+    }
+
+    /// A small tree under /tmp, removed when dropped.
+    struct SearchTree(String);
+
+    impl SearchTree {
+        fn new(name: &str) -> Self {
+            let root = format!("/tmp/sentinel_test_search_{}_{}", name, std::process::id());
+            std::fs::create_dir_all(format!("{}/sub/deeper", root)).unwrap();
+            std::fs::write(format!("{}/a.txt", root), "one needle\ntwo\nNEEDLE three\n").unwrap();
+            std::fs::write(format!("{}/sub/b.rs", root), "fn needle() {}\n").unwrap();
+            std::fs::write(format!("{}/sub/deeper/c.txt", root), "no match here\n").unwrap();
+            std::fs::write(format!("{}/sub/blob.bin", root), [0u8, 159, 146, 150, 110, 101]).unwrap();
+            SearchTree(root)
+        }
+    }
+
+    impl Drop for SearchTree {
+        fn drop(&mut self) {
+            std::fs::remove_dir_all(&self.0).ok();
+        }
+    }
+
+    fn search(read: Vec<&str>, input: JsonValue) -> (bool, String) {
+        let platform = test_platform(read, vec![], vec![]);
+        let executor = ToolExecutor::new(&platform, 5);
+        call(&executor, "search_files", input)
+    }
+
+    #[test]
+    fn test_search_files_recurses_and_reports_lines() {
+        let tree = SearchTree::new("basic");
+        let input = json_obj().field_str("path", &tree.0).field_str("pattern", "needle").build();
+        let (is_error, content) = search(vec!["/tmp"], input);
+        assert!(!is_error, "{}", content);
+        assert_eq!(
+            content,
+            format!("{0}/a.txt:1: one needle\n{0}/sub/b.rs:1: fn needle() {{}}", tree.0)
+        );
+    }
+
+    #[test]
+    fn test_search_files_glob_case_and_limit() {
+        let tree = SearchTree::new("options");
+        let input = json_obj()
+            .field_str("path", &tree.0)
+            .field_str("pattern", "needle")
+            .field_str("glob", "*.txt")
+            .field_bool("case_insensitive", true)
+            .build();
+        let (_, content) = search(vec!["/tmp"], input);
+        assert_eq!(content.lines().count(), 2);
+        assert!(content.contains("a.txt:3: NEEDLE three"));
+        assert!(!content.contains("b.rs"));
+
+        let input = json_obj()
+            .field_str("path", &tree.0)
+            .field_str("pattern", "needle")
+            .field_bool("case_insensitive", true)
+            .field_i64("max_results", 1)
+            .build();
+        let (_, content) = search(vec!["/tmp"], input);
+        assert!(content.ends_with("(stopped after 1 matches)"), "{}", content);
+
+        let input = json_obj().field_str("path", &tree.0).field_str("pattern", "absent").build();
+        assert!(search(vec!["/tmp"], input).1.starts_with("no matches"));
+    }
+
+    #[test]
+    fn test_search_files_denied_outside_read_paths() {
+        let tree = SearchTree::new("denied");
+        let input = json_obj().field_str("path", &tree.0).field_str("pattern", "needle").build();
+        let (is_error, content) = search(vec!["/nonexistent"], input);
+        assert!(is_error);
+        assert!(content.contains("access denied"), "{}", content);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(!glob_match("*.rs", "main.rsx"));
+        assert!(glob_match("test_?.txt", "test_1.txt"));
+        assert!(glob_match("*_*.txt", "a_b_c.txt"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("a*", "ba"));
+    }
+
     #[test]
     fn test_list_directory() {
         let platform = test_platform(vec!["/tmp"], vec![], vec![]);
@@ -541,7 +2137,7 @@ mod tests {
         let mut auditor = Auditor::new(&platform);
 
         let input = json_obj().field_str("path", "/tmp").build();
-        let result = executor.execute("test-id", "list_directory", &input, &mut auditor);
+        let result = executor.execute("test-id", "list_directory", &input, &test_ctx(), &mut auditor, &mut |_| {});
         match result {
             ContentBlock::ToolResult { is_error, .. } => {
                 assert!(!is_error, "should succeed listing /tmp");
@@ -549,4 +2145,74 @@ mod tests {
             _ => panic!("expected ToolResult"),
         }
     }
+
+    fn stat(path: &str) -> JsonValue {
+        let platform = test_platform(vec!["/tmp"], vec![], vec![]);
+        let executor = ToolExecutor::new(&platform, 5);
+        let (is_error, content) = call(&executor, "stat_file", json_obj().field_str("path", path).build());
+        assert!(!is_error, "should succeed: {}", content);
+        crate::net::json::parse(&content).unwrap()
+    }
+
+    #[test]
+    fn test_stat_text_file() {
+        let path = "/tmp/sentinel_test_stat.txt";
+        std::fs::write(path, "héllo\n").unwrap();
+
+        let meta = stat(path);
+        assert_eq!(meta.get("size").and_then(|v| v.as_i64()), Some(7));
+        assert_eq!(meta.get("is_dir").and_then(|v| v.as_bool()), Some(false));
+        assert_eq!(meta.get("is_text").and_then(|v| v.as_bool()), Some(true));
+        assert!(meta.get("modified").and_then(|v| v.as_i64()).unwrap() > 0);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_stat_directory() {
+        let path = "/tmp/sentinel_test_stat_dir";
+        std::fs::create_dir_all(path).unwrap();
+
+        let meta = stat(path);
+        assert_eq!(meta.get("is_dir").and_then(|v| v.as_bool()), Some(true));
+        assert!(meta.get("is_text").unwrap().is_null());
+
+        std::fs::remove_dir(path).ok();
+    }
+
+    #[test]
+    fn test_stat_fifo_is_not_opened() {
+        let path = "/tmp/sentinel_test_stat_fifo";
+        let _ = std::fs::remove_file(path);
+        assert!(std::process::Command::new("mkfifo").arg(path).status().unwrap().success());
+
+        // Opening it to sniff would block until something wrote to it
+        let meta = stat(path);
+        assert_eq!(meta.get("is_dir").and_then(|v| v.as_bool()), Some(false));
+        assert!(meta.get("is_text").unwrap().is_null());
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_stat_binary_file() {
+        let path = "/tmp/sentinel_test_stat.bin";
+        std::fs::write(path, [0x7f, b'E', b'L', b'F', 0x02, 0x01, 0x00, 0x00]).unwrap();
+
+        let meta = stat(path);
+        assert_eq!(meta.get("size").and_then(|v| v.as_i64()), Some(8));
+        assert_eq!(meta.get("is_text").and_then(|v| v.as_bool()), Some(false));
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_stat_denied_outside_read_paths() {
+        let platform = test_platform(vec!["/tmp"], vec![], vec![]);
+        let executor = ToolExecutor::new(&platform, 5);
+        let input = json_obj().field_str("path", "/etc/passwd").build();
+        let (is_error, content) = call(&executor, "stat_file", input);
+        assert!(is_error);
+        assert!(content.contains("access denied"), "{}", content);
+    }
 }
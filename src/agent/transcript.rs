@@ -0,0 +1,148 @@
+use crate::llm::provider::{ContentBlock, LlmResponse};
+use crate::net::json::JsonValue;
+
+// ── Turn transcript ─────────────────────────────────────────────────────────
+//
+// A structured record of what the agent did during one turn: the model's
+// text, every tool call with its input and result, and token usage, round
+// by round. It is built alongside the history, so a failed turn still
+// leaves a transcript of the rounds that completed.
+
+/// Everything the agent did while handling one user message.
+#[derive(Debug, Default)]
+pub struct TurnTranscript {
+    pub rounds: Vec<TranscriptRound>,
+    /// The provider answered a request with a rate limit during the turn.
+    pub rate_limited: bool,
+}
+
+/// One model response and the tool calls it made.
+#[derive(Debug)]
+#[allow(dead_code)] // text and thinking are only read by callers rendering it
+pub struct TranscriptRound {
+    /// The model's text for this round, including any reasoning it gave
+    /// before calling tools.
+    pub text: String,
+    /// Extended-thinking reasoning, when the provider returns it.
+    pub thinking: String,
+    pub tool_calls: Vec<TranscriptToolCall>,
+    pub usage_input: i64,
+    pub usage_output: i64,
+}
+
+/// A tool call and, once it has run, its result.
+#[derive(Debug)]
+#[allow(dead_code)] // likewise id, input and output
+pub struct TranscriptToolCall {
+    pub id: String,
+    pub name: String,
+    pub input: JsonValue,
+    /// `None` until the tool has run (or if the turn failed first).
+    pub output: Option<String>,
+    pub is_error: bool,
+}
+
+impl TurnTranscript {
+    /// Start a round for a model response.
+    pub fn record_response(&mut self, resp: &LlmResponse) {
+        let mut text = String::new();
+        let mut thinking = String::new();
+        let mut tool_calls = Vec::new();
+        for block in &resp.content {
+            match block {
+                ContentBlock::Text { text: t } => text.push_str(t),
+                ContentBlock::ToolUse { id, name, input } => tool_calls.push(TranscriptToolCall {
+                    id: id.clone(),
+                    name: name.clone(),
+                    input: input.clone(),
+                    output: None,
+                    is_error: false,
+                }),
+                ContentBlock::Thinking { text: t, .. } => thinking.push_str(t),
+                ContentBlock::ToolResult { .. } | ContentBlock::Image { .. } => {}
+            }
+        }
+        self.rounds.push(TranscriptRound {
+            text,
+            thinking,
+            tool_calls,
+            usage_input: resp.usage_input,
+            usage_output: resp.usage_output,
+        });
+    }
+
+    /// Attach tool results to the calls in the latest round, by ID.
+    pub fn record_results(&mut self, results: &[ContentBlock]) {
+        let round = match self.rounds.last_mut() {
+            Some(r) => r,
+            None => return,
+        };
+        for block in results {
+            if let ContentBlock::ToolResult { tool_use_id, content, is_error } = block {
+                if let Some(call) = round.tool_calls.iter_mut().find(|c| &c.id == tool_use_id) {
+                    call.output = Some(content.clone());
+                    call.is_error = *is_error;
+                }
+            }
+        }
+    }
+
+    /// One-line summary for the log, e.g. "2 rounds, 3 tool calls (1 failed),
+    /// 1200/340 tokens".
+    pub fn summary(&self) -> String {
+        let calls = self.rounds.iter().flat_map(|r| &r.tool_calls);
+        let total = calls.clone().count();
+        let failed = calls.filter(|c| c.is_error).count();
+        let input: i64 = self.rounds.iter().map(|r| r.usage_input).sum();
+        let output: i64 = self.rounds.iter().map(|r| r.usage_output).sum();
+        let mut s = format!(
+            "{} round{}, {} tool call{}",
+            self.rounds.len(),
+            if self.rounds.len() == 1 { "" } else { "s" },
+            total,
+            if total == 1 { "" } else { "s" },
+        );
+        if failed > 0 {
+            s.push_str(&format!(" ({} failed)", failed));
+        }
+        s.push_str(&format!(", {}/{} tokens", input, output));
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::provider::StopReason;
+    use crate::net::json::json_obj;
+
+    #[test]
+    fn test_results_matched_to_calls_by_id() {
+        let mut t = TurnTranscript::default();
+        t.record_response(&LlmResponse {
+            stop_reason: StopReason::ToolUse,
+            content: vec![
+                ContentBlock::Text { text: "Checking both.".into() },
+                ContentBlock::ToolUse { id: "a".into(), name: "read_file".into(), input: json_obj().build() },
+                ContentBlock::ToolUse { id: "b".into(), name: "stat_file".into(), input: json_obj().build() },
+            ],
+            usage_input: 100,
+            usage_output: 20,
+            usage_cache_creation: 0,
+            usage_cache_read: 0,
+        });
+        // Results may arrive in any order
+        t.record_results(&[
+            ContentBlock::ToolResult { tool_use_id: "b".into(), content: "denied".into(), is_error: true },
+            ContentBlock::ToolResult { tool_use_id: "a".into(), content: "data".into(), is_error: false },
+        ]);
+
+        let round = &t.rounds[0];
+        assert_eq!(round.text, "Checking both.");
+        assert_eq!(round.tool_calls[0].output.as_deref(), Some("data"));
+        assert!(!round.tool_calls[0].is_error);
+        assert_eq!(round.tool_calls[1].output.as_deref(), Some("denied"));
+        assert!(round.tool_calls[1].is_error);
+        assert_eq!(t.summary(), "1 round, 2 tool calls (1 failed), 100/20 tokens");
+    }
+}
@@ -1,283 +1,1489 @@
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::net::TcpListener;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::sync::Arc;
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
+use crate::agent::approval::{self, ApprovalPolicy, Decision, PendingApproval, ToolBatch, ToolCall};
+use crate::agent::budget::TokenBudget;
+use crate::agent::cancel::CancelSignal;
+use crate::agent::freshness::ToolResultAges;
+#[cfg(feature = "metrics")]
+use crate::agent::metrics;
+use crate::agent::prompt::{self, ChannelPrompts, SystemPromptBuilder, TurnContext, UtcDateContext};
+use crate::agent::status::RuntimeStatus;
 use crate::agent::tools::ToolExecutor;
-use crate::config::Config;
+use crate::agent::transcript::TurnTranscript;
+use crate::config::{CheckReport, CheckStatus, Config, SharedConfig};
 use crate::llm::anthropic::AnthropicClient;
-use crate::llm::openai::OpenAiClient;
-use crate::llm::provider::{ContentBlock, LlmError, LlmProvider, Message, Role, StopReason, ToolDef};
-use crate::messaging::Connector;
+use crate::llm::echo::EchoProvider;
+use crate::llm::gemini::{GeminiClient, GEMINI_API};
+use crate::llm::openai::{endpoint_url, ModelFamily, OpenAiClient};
+use crate::llm::provider::{
+    ApiCall, ApiCallHook, ContentBlock, LlmError, LlmProvider, LlmResponse, Message, Role,
+    SamplingParams, StopReason, ToolDef,
+};
+use crate::log;
+use crate::messaging::poller::{self, Event};
+use crate::messaging::{Connector, IncomingMessage, TypingIndicator};
 use crate::messaging::discord::DiscordConnector;
 use crate::messaging::slack::SlackConnector;
-use crate::messaging::telegram::TelegramClient;
-use crate::net::http::HttpClient;
-use crate::platform::linux::LinuxPlatform;
-use crate::security::audit::{AuditEvent, Auditor};
+use crate::messaging::telegram::{ParseMode, TelegramClient};
+use crate::net::base64;
+use crate::net::http::{EgressPolicy, HttpClient, HttpError, TlsConfig};
+use crate::net::retry::RetryPolicy;
+use crate::platform::linux::{Allowlists, AuditLog};
+#[cfg(not(target_os = "macos"))]
+use crate::platform::linux::LinuxPlatform as HostPlatform;
+#[cfg(target_os = "macos")]
+use crate::platform::macos::MacosPlatform as HostPlatform;
+use crate::platform::signal;
+use crate::security::audit::{AuditEvent, AuditSink, Auditor};
+use crate::security::capability::{ArgPolicies, CapabilityChecker};
+use crate::skills::limit::{ConcurrencyLimit, ConcurrencyPolicy};
 use crate::skills::SkillRunner;
+use crate::version;
 
 const MAX_TOOL_ROUNDS: usize = 10;
-const MAX_HISTORY_MESSAGES: usize = 40;
+/// Telegram long-poll duration. Each connector has its own thread, so this
+/// no longer needs to be short when several are active.
+const POLL_TIMEOUT_SECS: u32 = 30;
+/// How often the dispatch loop checks for a SIGHUP reload request.
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Audit events held for the webhook before the oldest are dropped.
+const AUDIT_QUEUE_CAPACITY: usize = 10_000;
+
+/// Appended to the system prompt when the user is answering a clarifying question.
+const CONTINUATION_HINT: &str = "The user's next message answers the clarifying question \
+you just asked. Continue the task already in progress rather than starting a new one.";
+/// Added to the system prompt when a turn runs out of tool rounds.
+const HANDOFF_INSTRUCTION: &str = "You have reached the tool-call limit for this turn. \
+Do not call any more tools. Briefly summarize what you have done so far and what \
+remains, so the work can continue when the user replies.";
+/// Appended to the progress summary sent to the user.
+const HANDOFF_FOOTER: &str = "(Paused at the tool-call limit for this turn. Reply \
+\"continue\" to pick up where I left off.)";
+/// Sent, and kept in history, when the user cancels a turn.
+const CANCELLED_REPLY: &str = "Cancelled.";
+
+/// A command handled by the message loop itself rather than the model.
+struct BuiltinCommand {
+    usage: &'static str,
+    description: &'static str,
+    /// Only listed for, and only usable by, admin users.
+    admin: bool,
+}
+
+/// Every built-in command, in the order /help lists them. A new command is
+/// handled in `ChatWorker::run` and described here.
+const BUILTIN_COMMANDS: &[BuiltinCommand] = &[
+    BuiltinCommand {
+        usage: "/help",
+        description: "Show this message",
+        admin: false,
+    },
+    BuiltinCommand {
+        usage: "/clear",
+        description: "Start a new conversation",
+        admin: false,
+    },
+    BuiltinCommand {
+        usage: "/cancel",
+        description: "Stop the turn in progress",
+        admin: false,
+    },
+    BuiltinCommand {
+        usage: "/approve, /deny",
+        description: "Answer a pending tool approval",
+        admin: false,
+    },
+    BuiltinCommand {
+        usage: "/status",
+        description: "Show uptime, the model, and connector health",
+        admin: false,
+    },
+    BuiltinCommand {
+        usage: "/usage",
+        description: "Show the tokens this conversation has used",
+        admin: false,
+    },
+    BuiltinCommand {
+        usage: "/setprompt <text>",
+        description: "Set this conversation's system prompt; alone, restore the default",
+        admin: true,
+    },
+    BuiltinCommand {
+        usage: "/version",
+        description: "Show build information",
+        admin: true,
+    },
+];
+
+/// How an agent turn ended.
+#[derive(Debug, PartialEq)]
+enum TurnOutcome {
+    /// The model finished its answer.
+    Completed,
+    /// The model ended its turn by asking the user a question.
+    AwaitingInput,
+    /// The conversation's token budget ran out; nothing more was sent.
+    BudgetExhausted,
+    /// A tool call is held until the user answers /approve or /deny.
+    AwaitingApproval(PendingApproval),
+    /// The user sent /cancel; every tool call made has a result.
+    Cancelled,
+}
 
 pub fn run() {
     let config = match Config::load() {
-        Ok(c) => c,
+        Ok(c) => Arc::new(c),
         Err(e) => {
             eprintln!("sentinel: fatal: {}", e);
             std::process::exit(1);
         }
     };
+    for warning in config.validate() {
+        eprintln!("sentinel: warning: {}", warning);
+    }
+    log::set_json(config.log_format == "json");
+    // Workers read the config through this, so a reload reaches them
+    let shared_config = SharedConfig::new(Arc::clone(&config));
+    // Created before the sandbox, which only grants paths that exist
+    if let Some(dir) = &config.state_dir {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            eprintln!("sentinel: warning: cannot create state_dir '{}': {}", dir, e);
+        }
+    }
+    let channel_prompts = Arc::new(ChannelPrompts::load(config.state_dir.as_deref()));
+    if let Err(e) = signal::install_reload_handler() {
+        eprintln!("sentinel: warning: cannot install SIGHUP handler, config reload disabled: {}", e);
+    }
 
-    let platform = LinuxPlatform::new(
-        config.allowed_read_paths.clone(),
-        config.allowed_write_paths.clone(),
-        config.allowed_commands.clone(),
-        config.audit_log_path.as_deref(),
-    );
+    // Conversations are spread over a fixed set of chat workers, each
+    // running one turn at a time. Each has a signal that lets a poll thread
+    // stop its turn.
+    let cancels: Arc<[CancelSignal]> = (0..config.max_concurrent_chats.max(1))
+        .map(|_| CancelSignal::default())
+        .collect();
 
-    // Apply OS-level sandboxing (seccomp + landlock)
-    #[cfg(target_os = "linux")]
-    if config.sandbox {
-        let result = crate::security::linux::apply_sandbox(
-            &config.allowed_read_paths,
-            &config.allowed_write_paths,
-            true,  // enable seccomp
-            true,  // enable landlock
-        );
-        if result.seccomp_applied || result.landlock_applied {
-            eprintln!("sentinel: sandbox active (seccomp={}, landlock={})",
-                result.seccomp_applied, result.landlock_applied);
+    // Every HTTP client shares one of these rather than building its own
+    // trust settings; the LLM's CA file is read once, before the sandbox
+    let tls = TlsConfig::new();
+    let llm_tls = match llm_tls_config(&config) {
+        Ok(tls) => tls,
+        Err(e) => {
+            eprintln!("sentinel: fatal: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Every thread gets its own platform, all writing through one audit
+    // log, opened here before the sandbox could deny it
+    let audit_log = config.audit_log_path.as_deref().and_then(|path| {
+        match AuditLog::open(path, config.audit_max_bytes, config.audit_keep_files) {
+            Ok(log) => Some(Arc::new(log)),
+            Err(e) => {
+                eprintln!("sentinel: warning: cannot open audit log '{}': {}", path, e);
+                None
+            }
         }
+    });
+    let new_platform = || {
+        HostPlatform::new(
+            config.allowed_read_paths.clone(),
+            config.allowed_write_paths.clone(),
+            config.allowed_commands.clone(),
+            audit_log.clone(),
+        )
+        .with_denied_paths(config.denied_read_paths.clone(), config.denied_write_paths.clone())
+        .with_network(config.network_allowlist())
+        .with_command_limits(config.command_limits())
+    };
+    let platform = Rc::new(new_platform());
+    let worker_platforms: Vec<HostPlatform> = cancels
+        .iter()
+        .map(|cancel| new_platform().with_interrupt(cancel.flag()))
+        .collect();
+
+    // Listening before the sandbox, like the audit log
+    let metrics_listener = config.metrics_bind.as_deref().and_then(bind_metrics);
+    let telegram_webhook = match (&config.telegram_webhook_bind, &config.telegram_webhook_url) {
+        (Some(bind), Some(_)) if config.telegram_token.is_some() => match TcpListener::bind(bind) {
+            Ok(listener) => Some(Arc::new(listener)),
+            Err(e) => {
+                eprintln!("sentinel: fatal: cannot listen on {} for the telegram webhook: {}", bind, e);
+                std::process::exit(1);
+            }
+        },
+        _ => None,
+    };
+
+    // Apply OS-level sandboxing (seccomp + landlock, or Seatbelt on macOS)
+    if config.sandbox {
+        apply_os_sandbox(&config);
     } else {
         eprintln!("sentinel: sandbox disabled (--no-sandbox)");
     }
 
-    let mut auditor = Auditor::new(&platform);
+    // Threads are started after the sandbox, which binds only the thread
+    // that applies it and those it creates afterwards
+    if let Some(listener) = metrics_listener {
+        serve_metrics(listener);
+    }
+    let audit_sink = config
+        .audit_webhook_url
+        .clone()
+        .filter(|url| url.starts_with("https://"))
+        .map(|url| spawn_audit_webhook(Arc::clone(&config), url, tls.clone()));
 
-    // Create LLM provider based on config
-    let llm: Box<dyn LlmProvider> = match config.provider.as_str() {
-        "openai" => {
-            let llm_http = match HttpClient::new() {
-                Ok(h) => h,
-                Err(e) => {
-                    eprintln!("sentinel: fatal: {}", e);
-                    std::process::exit(1);
+    let mut auditor = Auditor::new(&*platform)
+        .with_sink(audit_sink.as_ref())
+        .with_redaction(config.audit_redact);
+    let egress = audited_egress(&config, Rc::clone(&platform), audit_sink.clone());
+
+    // Each worker builds its own LLM client; this one only checks the
+    // settings, so a bad model family fails at startup
+    match config.provider.as_str() {
+        "openai" => eprintln!("sentinel: using OpenAI provider ({})", config.openai_base_url),
+        "azure" => eprintln!("sentinel: using Azure OpenAI provider ({})", config.openai_base_url),
+        "gemini" => eprintln!("sentinel: using Gemini provider"),
+        "echo" => eprintln!("sentinel: using echo provider (offline; replies repeat the message)"),
+        _ => eprintln!("sentinel: using Anthropic provider"),
+    }
+    if config.tls_insecure_skip_verify {
+        eprintln!("sentinel: ************************************************************");
+        eprintln!("sentinel: WARNING: TLS certificate verification is DISABLED for the");
+        eprintln!("sentinel: LLM provider. Anyone on the network path can read and alter");
+        eprintln!("sentinel: requests, including the API key. Use for local testing only.");
+        eprintln!("sentinel: ************************************************************");
+    }
+    let api_calls = audited_api_calls(Rc::clone(&platform), audit_sink.clone());
+    if let Err(e) = build_llm(&config, &llm_tls, &egress, &api_calls) {
+        eprintln!("sentinel: fatal: {}", e);
+        std::process::exit(1);
+    }
+
+    let mut tool_defs = ToolExecutor::tool_definitions();
+    let skill_runner = config.skills_dir.as_ref().map(|dir| {
+        let policy = ConcurrencyPolicy::parse(&config.skill_concurrency_policy)
+            .unwrap_or_else(|| {
+                eprintln!(
+                    "sentinel: unknown skill concurrency policy '{}', using 'queue'",
+                    config.skill_concurrency_policy
+                );
+                ConcurrencyPolicy::Queue
+            });
+        let limit = ConcurrencyLimit::new(config.skill_max_concurrent, policy);
+        let persistent_limit = ConcurrencyLimit::new(config.skill_max_persistent, policy);
+        Arc::new(SkillRunner::load(dir, config.command_timeout, limit, persistent_limit))
+    });
+    if let Some(ref runner) = skill_runner {
+        if runner.has_skills() {
+            tool_defs.extend(runner.tool_definitions());
+        }
+    }
+
+    // Find the connectors that initialize; every chat worker and poll
+    // thread then builds its own instance of each
+    let mut kinds = Vec::new();
+    for kind in enabled_connectors(&config) {
+        match build_connector(kind, &config, &tls, &egress, false, None) {
+            Ok(_) => {
+                kinds.push(kind);
+                eprintln!("sentinel: {} connector enabled", kind.name());
+            }
+            Err(e) => {
+                eprintln!("sentinel: warning: failed to initialize {}: {}", kind.name(), e);
+            }
+        }
+    }
+
+    if kinds.is_empty() {
+        eprintln!("sentinel: fatal: no messaging connectors available");
+        std::process::exit(1);
+    }
+
+    let names: Vec<&'static str> = kinds.iter().map(|k| k.name()).collect();
+    let status = Arc::new(RuntimeStatus::new(&names, worker_platforms.len()));
+
+    let mut workers = Vec::new();
+    for (index, platform) in worker_platforms.into_iter().enumerate() {
+        let (jobs, inbox) = mpsc::channel();
+        let worker = ChatWorker {
+            index,
+            config: shared_config.clone(),
+            channel_prompts: Arc::clone(&channel_prompts),
+            status: Arc::clone(&status),
+            platform,
+            kinds: kinds.clone(),
+            cancels: Arc::clone(&cancels),
+            skill_runner: skill_runner.clone(),
+            tool_defs: tool_defs.clone(),
+            audit_sink: audit_sink.clone(),
+            tls: tls.clone(),
+            llm_tls: llm_tls.clone(),
+        };
+        thread::Builder::new()
+            .name(format!("chat-{}", index))
+            .spawn(move || worker.run(inbox))
+            .expect("failed to spawn chat worker");
+        workers.push(jobs);
+    }
+
+    // Every connector polls on its own thread at its natural cadence
+    let (queue, inbox) = mpsc::channel();
+    for (i, &kind) in kinds.iter().enumerate() {
+        // /cancel can't wait in the queue behind the turn it cancels
+        let intercept = {
+            let config = shared_config.clone();
+            let cancels = Arc::clone(&cancels);
+            let platform = kind.name();
+            move |msg: &IncomingMessage| {
+                let key = conversation_key(platform, &msg.channel_id);
+                msg.text.trim() == "/cancel"
+                    && is_authorized(&config.current(), platform, &msg.user_id)
+                    && cancels[worker_for(&key, cancels.len())].request(&key)
+            }
+        };
+        let config = Arc::clone(&config);
+        let tls = tls.clone();
+        let denied = queue.clone();
+        let webhook = telegram_webhook.clone();
+        let make = move || {
+            let egress = EgressPolicy::new(
+                config.allowed_egress_hosts.clone(),
+                Rc::new(move |host: &str| {
+                    let _ = denied.send(Event::EgressDenied(host.to_string()));
+                }),
+            )
+            .with_network(config.network_allowlist());
+            build_connector(kind, &config, &tls, &egress, true, webhook.as_deref())
+        };
+        let on_poll = {
+            let status = Arc::clone(&status);
+            move |result: Result<(), &str>| {
+                #[cfg(feature = "metrics")]
+                if result.is_err() {
+                    metrics::global().record_error("poll");
                 }
-            };
-            eprintln!("sentinel: using OpenAI provider ({})", config.openai_base_url);
-            Box::new(OpenAiClient::new(
-                llm_http,
-                config.api_key.clone(),
-                config.model.clone(),
-                config.max_tokens,
-                config.openai_base_url.clone(),
-            ))
+                status.record_poll(i, result)
+            }
+        };
+        poller::spawn(i, kind.name(), make, intercept, on_poll, POLL_TIMEOUT_SECS, queue.clone());
+    }
+    drop(queue);
+
+    eprintln!(
+        "sentinel: started with {} connector(s) and {} chat worker(s), polling...",
+        kinds.len(),
+        workers.len()
+    );
+
+    // Hand each message to the worker that owns its conversation, and
+    // reload the config when SIGHUP asks for it
+    loop {
+        if signal::take_reload_request() {
+            reload_config(&shared_config);
         }
-        _ => {
-            let llm_http = match HttpClient::new() {
-                Ok(h) => h,
-                Err(e) => {
-                    eprintln!("sentinel: fatal: {}", e);
+        let event = match inbox.recv_timeout(RELOAD_CHECK_INTERVAL) {
+            Ok(event) => event,
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        };
+        match event {
+            Event::Message { connector, message } => {
+                let key = conversation_key(kinds[connector].name(), &message.channel_id);
+                let worker = worker_for(&key, workers.len());
+                if workers[worker].send((connector, message)).is_err() {
+                    eprintln!("sentinel: fatal: chat worker {} has stopped", worker);
                     std::process::exit(1);
                 }
-            };
-            eprintln!("sentinel: using Anthropic provider");
-            Box::new(AnthropicClient::new(
-                llm_http,
-                config.api_key.clone(),
-                config.model.clone(),
-                config.max_tokens,
-            ))
+            }
+            Event::EgressDenied(host) => {
+                auditor.log(AuditEvent::EgressDenied { host: &host });
+            }
         }
-    };
+    }
 
-    let mut tool_defs = ToolExecutor::tool_definitions();
-    let skill_runner = config.skills_dir.as_ref().map(|dir| {
-        SkillRunner::load(dir, config.command_timeout)
-    });
-    let tool_executor = {
-        let exec = ToolExecutor::new(&platform, config.command_timeout);
-        if let Some(ref runner) = skill_runner {
-            if runner.has_skills() {
-                tool_defs.extend(runner.tool_definitions());
-            }
-            exec.with_skills(runner)
-        } else {
-            exec
+    eprintln!("sentinel: fatal: all connector poll threads have stopped");
+    std::process::exit(1);
+}
+
+/// Re-read the config and swap in the settings that can change while
+/// running. A config that fails to load leaves the current one in place.
+fn reload_config(shared: &SharedConfig) {
+    let fresh = match Config::load() {
+        Ok(fresh) => fresh,
+        Err(e) => {
+            eprintln!("sentinel: reload failed, keeping the current config: {}", e);
+            return;
         }
     };
+    for warning in fresh.validate() {
+        eprintln!("sentinel: warning: {}", warning);
+    }
+    let (next, changes) = shared.current().reload_from(&fresh);
+    if changes.is_empty() {
+        eprintln!("sentinel: config reloaded, nothing changed");
+        return;
+    }
+    for change in &changes {
+        eprintln!("sentinel: config reload: {}", change);
+    }
+    log::set_json(next.log_format == "json");
+    shared.replace(next);
+}
 
-    // Build connectors based on config
-    let mut connectors: Vec<Box<dyn Connector>> = Vec::new();
+/// Runs the turns of the conversations routed to it, one at a time.
+/// Conversation state lives here rather than in shared maps: a
+/// conversation always lands on the same worker, so nothing else touches it.
+struct ChatWorker {
+    index: usize,
+    config: SharedConfig,
+    /// Per-conversation prompts set with /setprompt.
+    channel_prompts: Arc<ChannelPrompts>,
+    /// Shared with the poll threads, for /status.
+    status: Arc<RuntimeStatus>,
+    platform: HostPlatform,
+    kinds: Vec<ConnectorKind>,
+    /// Every worker's signal; this one's is at `index`.
+    cancels: Arc<[CancelSignal]>,
+    skill_runner: Option<Arc<SkillRunner>>,
+    tool_defs: Vec<ToolDef>,
+    audit_sink: Option<AuditSink>,
+    tls: TlsConfig,
+    /// `tls` with the `[net]` CA and verification settings applied.
+    llm_tls: TlsConfig,
+}
 
-    if let Some(ref token) = config.telegram_token {
-        let http = match HttpClient::new() {
-            Ok(h) => h,
+impl ChatWorker {
+    fn run(self, inbox: mpsc::Receiver<(usize, IncomingMessage)>) {
+        let ChatWorker {
+            index,
+            config: shared_config,
+            channel_prompts,
+            status,
+            platform,
+            kinds,
+            cancels,
+            skill_runner,
+            tool_defs,
+            audit_sink,
+            tls,
+            llm_tls,
+        } = self;
+        let cancel = &cancels[index];
+        let mut config = shared_config.current();
+        let platform = Rc::new(platform);
+        let mut auditor = Auditor::new(&*platform)
+            .with_sink(audit_sink.as_ref())
+            .with_redaction(config.audit_redact);
+        let egress = audited_egress(&config, Rc::clone(&platform), audit_sink.clone());
+        let api_calls = audited_api_calls(Rc::clone(&platform), audit_sink.clone());
+
+        // HTTP clients aren't Send, so each worker makes its own
+        let llm = match build_llm(&config, &llm_tls, &egress, &api_calls) {
+            Ok(llm) => llm,
             Err(e) => {
-                eprintln!("sentinel: fatal: failed to initialize HTTP client: {}", e);
+                eprintln!("sentinel: fatal: {}", e);
                 std::process::exit(1);
             }
         };
-        connectors.push(Box::new(TelegramClient::new(http, token)));
-        eprintln!("sentinel: telegram connector enabled");
-    }
-
-    if let Some(ref token) = config.discord_token {
-        if config.discord_channel_ids.is_empty() {
-            eprintln!("sentinel: warning: discord token set but no channel_ids configured");
-        } else {
-            let http = match HttpClient::new() {
-                Ok(h) => h,
+        let mut connectors: Vec<Box<dyn Connector>> = Vec::new();
+        for &kind in &kinds {
+            match build_connector(kind, &config, &tls, &egress, false, None) {
+                Ok(c) => connectors.push(c),
                 Err(e) => {
-                    eprintln!("sentinel: fatal: failed to initialize HTTP client: {}", e);
+                    eprintln!("sentinel: fatal: chat worker failed to initialize {}: {}", kind.name(), e);
                     std::process::exit(1);
                 }
+            }
+        }
+
+        let skill_runner = skill_runner.as_deref();
+        let mut tool_executor = build_tool_executor(&config, &platform, &tls, &egress, cancel, skill_runner);
+
+        // Dynamic context added to the system prompt on every turn
+        let prompt_builder: Box<dyn SystemPromptBuilder> = Box::new(UtcDateContext);
+
+        // Per-conversation history keyed by "platform:channel_id"
+        let mut conversations: HashMap<String, Vec<Message>> = HashMap::new();
+        // Conversations where the model is waiting on an answer to its question
+        let mut awaiting_reply: HashSet<String> = HashSet::new();
+        // When each conversation's tool results were produced
+        let mut tool_result_ages: HashMap<String, ToolResultAges> = HashMap::new();
+        // Tool calls held until the user answers /approve or /deny
+        let mut pending_approvals: HashMap<String, PendingApproval> = HashMap::new();
+        let mut tool_result_ttl = config.tool_result_ttl.map(Duration::from_secs);
+        // Tokens spent by each conversation, against max_tokens_per_conversation
+        let mut token_budgets: HashMap<String, TokenBudget> = HashMap::new();
+
+        while let Ok((i, msg)) = inbox.recv() {
+            // Pick up a reloaded config between turns
+            let latest = shared_config.current();
+            if !Arc::ptr_eq(&latest, &config) {
+                config = latest;
+                platform.set_allowlists(allowlists(&config));
+                tool_executor = build_tool_executor(&config, &platform, &tls, &egress, cancel, skill_runner);
+                tool_result_ttl = config.tool_result_ttl.map(Duration::from_secs);
+            }
+
+            // Group chatter not meant for the bot
+            if config.respond_only_when_mentioned && !msg.addressed {
+                continue;
+            }
+
+            let platform = connectors[i].platform_name();
+            let username = msg.username.as_deref().unwrap_or("unknown");
+
+            auditor.log(AuditEvent::MessageReceived {
+                chat_id: msg.channel_id.parse::<i64>().unwrap_or(0),
+                user_id: msg.user_id.parse::<i64>().unwrap_or(0),
+                username,
+            });
+
+            // Authorization check
+            if !is_authorized(&config, platform, &msg.user_id) {
+                auditor.log(AuditEvent::UnauthorizedUser {
+                    user_id: msg.user_id.parse::<i64>().unwrap_or(0),
+                    username,
+                });
+                let reply = config
+                    .unauthorized_message
+                    .as_deref()
+                    .unwrap_or("Unauthorized.");
+                let _ = connectors[i].send_message(&msg.channel_id, reply);
+                continue;
+            }
+
+            let conv_key = conversation_key(platform, &msg.channel_id);
+
+            // Handle /help command
+            if msg.text.trim() == "/help" {
+                let help = help_text(
+                    &tool_defs,
+                    skill_runner,
+                    &config.allowed_commands,
+                    is_admin(&config, platform, &msg.user_id),
+                );
+                let _ = connectors[i].send_message(&msg.channel_id, &help);
+                continue;
+            }
+
+            // Handle /status command
+            if msg.text.trim() == "/status" {
+                let report = status.report(&config.provider, &config.model);
+                let _ = connectors[i].send_message(&msg.channel_id, &report);
+                continue;
+            }
+
+            // Handle /clear command
+            if msg.text.trim() == "/clear" {
+                conversations.remove(&conv_key);
+                status.set_conversations(index, conversations.len());
+                awaiting_reply.remove(&conv_key);
+                tool_result_ages.remove(&conv_key);
+                pending_approvals.remove(&conv_key);
+                token_budgets.remove(&conv_key);
+                let _ = connectors[i]
+                    .send_message(&msg.channel_id, "Conversation cleared.");
+                continue;
+            }
+
+            // Handle /cancel between turns; during one the poll thread takes it
+            if msg.text.trim() == "/cancel" {
+                let reply = match pending_approvals.remove(&conv_key) {
+                    Some(mut pending) => {
+                        let results = pending.batch.abandon("not run: the turn was cancelled");
+                        if let Some(history) = conversations.get_mut(&conv_key) {
+                            record_cancellation(history, results);
+                        }
+                        CANCELLED_REPLY
+                    }
+                    None => "Nothing to cancel.",
+                };
+                let _ = connectors[i].send_message(&msg.channel_id, reply);
+                continue;
+            }
+
+            // Handle /version command (admins only)
+            if msg.text.trim() == "/version" {
+                let reply = if is_admin(&config, platform, &msg.user_id) {
+                    version::version_string()
+                } else {
+                    "Only admins can use /version.".to_string()
+                };
+                let _ = connectors[i].send_message(&msg.channel_id, &reply);
+                continue;
+            }
+
+            // Handle /setprompt command (admins only)
+            if let Some(text) = command_arg(&msg.text, "/setprompt") {
+                let reply = if !is_admin(&config, platform, &msg.user_id) {
+                    "Only admins can use /setprompt.".to_string()
+                } else {
+                    let prompt = (!text.is_empty()).then_some(text);
+                    match (channel_prompts.set(&conv_key, prompt), prompt) {
+                        (Err(e), _) => format!("System prompt changed, but not saved: {}", e),
+                        (Ok(()), Some(_)) => "System prompt set for this conversation.".to_string(),
+                        (Ok(()), None) => "System prompt reset to the default.".to_string(),
+                    }
+                };
+                let _ = connectors[i].send_message(&msg.channel_id, &reply);
+                continue;
+            }
+
+            // Handle /usage command
+            if msg.text.trim() == "/usage" {
+                let reply = match token_budgets.get(&conv_key) {
+                    Some(budget) => budget.report(),
+                    None => TokenBudget::new(config.max_tokens_per_conversation).report(),
+                };
+                let _ = connectors[i].send_message(&msg.channel_id, &reply);
+                continue;
+            }
+
+            let budget = token_budgets
+                .entry(conv_key.clone())
+                .or_insert_with(|| TokenBudget::new(config.max_tokens_per_conversation));
+            if budget.exhausted() {
+                let connector = connectors[i].as_ref();
+                report_budget_exhausted(&mut auditor, connector, &msg.channel_id, &conv_key, budget);
+                continue;
+            }
+
+            // Greet on the first message of a new conversation
+            if let Some(greeting) = greeting_for_new_conversation(
+                config.greeting.as_deref(),
+                &conversations,
+                &conv_key,
+                &tool_defs,
+            ) {
+                let _ = connectors[i].send_message(&msg.channel_id, &greeting);
+            }
+
+            // Get or create conversation history
+            let continuation = awaiting_reply.remove(&conv_key);
+            let history = conversations.entry(conv_key.clone()).or_default();
+            let ages = tool_result_ages
+                .entry(conv_key.clone())
+                .or_insert_with(|| ToolResultAges::new(tool_result_ttl));
+
+            let connector = &*connectors[i];
+            let mut user_content = vec![ContentBlock::Text {
+                text: msg.text.clone(),
+            }];
+            // Downloaded only now the message is known to be answered. One
+            // that fails is left out; the text still notes it.
+            for image in &msg.images {
+                match connector.fetch_image(image) {
+                    Ok(image) => user_content.push(ContentBlock::Image {
+                        media_type: image.media_type,
+                        data_base64: base64::encode(&image.data),
+                    }),
+                    Err(e) => eprintln!("sentinel: {} image download failed: {}", platform, e),
+                }
+            }
+            let turn_ctx = TurnContext {
+                platform,
+                channel_id: &msg.channel_id,
+                user_id: &msg.user_id,
+                username: msg.username.as_deref(),
+                timestamp: SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0),
             };
-            match DiscordConnector::new(http, token, &config.discord_channel_ids) {
-                Ok(dc) => {
-                    connectors.push(Box::new(dc));
-                    eprintln!("sentinel: discord connector enabled");
+            match pending_approvals.remove(&conv_key) {
+                Some(mut pending) => {
+                    let decision = if pending.expired(tool_executor.approval_policy().timeout) {
+                        Decision::TimedOut
+                    } else if let Some(d) = Decision::parse(&msg.text) {
+                        d
+                    } else {
+                        let tool = pending.call().map_or("tool", |c| c.name.as_str());
+                        let reply = format!("Waiting for /approve or /deny on the pending {} call.", tool);
+                        let _ = connector.send_message(&msg.channel_id, &reply);
+                        pending_approvals.insert(conv_key, pending);
+                        continue;
+                    };
+                    if let Some(call) = pending.call() {
+                        auditor.log(AuditEvent::ApprovalDecision {
+                            conversation: &conv_key,
+                            tool: &call.name,
+                            decision: decision.as_str(),
+                        });
+                    }
+                    if decision == Decision::TimedOut {
+                        // Close out the held calls and treat the message as new
+                        let mut content = pending.batch.abandon("not run: the approval request timed out");
+                        content.extend(user_content);
+                        history.push(Message { role: Role::User, content });
+                        let _ = connector.send_message(
+                            &msg.channel_id,
+                            "The approval request timed out, so that call was not run.",
+                        );
+                    } else {
+                        pending.batch.decision = Some(decision);
+                        cancel.begin(&conv_key);
+                        let resumed = run_tool_calls(
+                            pending.batch,
+                            &tool_executor,
+                            &mut auditor,
+                            ages,
+                            connector,
+                            &turn_ctx,
+                        );
+                        match resumed {
+                            ToolRun::Done(results) => history.push(Message {
+                                role: Role::User,
+                                content: results,
+                            }),
+                            ToolRun::NeedsApproval(next) => {
+                                cancel.end();
+                                pending_approvals.insert(conv_key, next);
+                                continue;
+                            }
+                        }
+                    }
+                }
+                // Add user message
+                None => history.push(Message {
+                    role: Role::User,
+                    content: user_content,
+                }),
+            }
+
+            let channel_prompt = channel_prompts.resolve(
+                &conv_key,
+                &config.system_prompts,
+                config.system_prompt.as_deref(),
+            );
+            let base = prompt::compose(
+                channel_prompt.as_deref(),
+                prompt_builder.as_ref(),
+                &turn_ctx,
+            );
+            let system = turn_system_prompt(base.as_deref(), continuation);
+
+            // Run agent turn with streaming
+            let mut transcript = TurnTranscript::default();
+            cancel.begin(&conv_key);
+            let result = with_ack(
+                connector,
+                config.ack_reactions,
+                &msg.channel_id,
+                &msg.message_id,
+                || {
+                    run_agent_turn(
+                        llm.as_ref(),
+                        history,
+                        system.as_deref(),
+                        &tool_defs,
+                        &tool_executor,
+                        &mut auditor,
+                        TurnState {
+                            connector,
+                            ctx: &turn_ctx,
+                            ages,
+                            budget,
+                            transcript: &mut transcript,
+                        },
+                    )
+                },
+            );
+            cancel.end();
+            eprintln!("sentinel: turn on {}: {}", conv_key, transcript.summary());
+            if transcript.rate_limited {
+                status.record_rate_limit();
+            }
+            match result {
+                Ok(TurnOutcome::AwaitingInput) => {
+                    awaiting_reply.insert(conv_key);
+                }
+                Ok(TurnOutcome::AwaitingApproval(pending)) => {
+                    pending_approvals.insert(conv_key, pending);
+                }
+                Ok(TurnOutcome::Cancelled) => {
+                    record_cancellation(history, Vec::new());
+                    let _ = connector.send_message(&msg.channel_id, CANCELLED_REPLY);
+                }
+                Ok(TurnOutcome::Completed) => {}
+                Ok(TurnOutcome::BudgetExhausted) => {
+                    report_budget_exhausted(&mut auditor, connector, &msg.channel_id, &conv_key, budget);
                 }
                 Err(e) => {
-                    eprintln!("sentinel: warning: failed to initialize discord: {}", e);
+                    log::error(
+                        &format!("agent error: {}", e),
+                        &[("conversation", &conv_key), ("error", &e)],
+                    );
+                    status.record_error(&e);
+                    #[cfg(feature = "metrics")]
+                    metrics::global().record_error("turn");
+                    let error_msg = format!("Error: {}", e);
+                    let _ = connectors[i].send_message(&msg.channel_id, &error_msg);
                 }
             }
+
+            trim_history(history, config.max_context_tokens);
+            ages.retain_in(history);
+            status.set_conversations(index, conversations.len());
         }
     }
+}
 
-    if let Some(ref token) = config.slack_bot_token {
+/// The tool executor for a worker's platform, limited as the config says.
+fn build_tool_executor<'a>(
+    config: &Config,
+    platform: &'a HostPlatform,
+    tls: &TlsConfig,
+    egress: &EgressPolicy,
+    cancel: &CancelSignal,
+    skill_runner: Option<&'a SkillRunner>,
+) -> ToolExecutor<'a> {
+    let approval_policy = ApprovalPolicy {
+        tools: config.require_approval_for.clone(),
+        timeout: Duration::from_secs(config.approval_timeout),
+    };
+    let exec = ToolExecutor::new(platform, config.command_timeout)
+        .with_output_limit(config.max_command_output)
+        .with_max_tool_output(config.max_tool_output_bytes)
+        .with_command_args(ArgPolicies::new(&config.allowed_args, &config.denied_args))
+        .with_approval(approval_policy)
+        .with_cancel(cancel.flag());
+    let exec = if config.allowed_fetch_hosts.is_empty() {
+        exec
+    } else {
+        let http = configure_http(HttpClient::with_tls(tls), config, &egress.clone().refusing_internal());
+        let network = CapabilityChecker::new(Vec::new(), Vec::new(), Vec::new())
+            .with_network_hosts(config.allowed_fetch_hosts.clone());
+        exec.with_http_fetch(http, network)
+    };
+    match skill_runner {
+        Some(runner) => exec.with_skills(runner),
+        None => exec,
+    }
+}
+
+/// The paths and commands tool calls are limited to.
+fn allowlists(config: &Config) -> Allowlists {
+    Allowlists {
+        read: config.allowed_read_paths.clone(),
+        write: config.allowed_write_paths.clone(),
+        commands: config.allowed_commands.clone(),
+        denied_read: config.denied_read_paths.clone(),
+        denied_write: config.denied_write_paths.clone(),
+    }
+}
+
+/// A messaging platform that can be enabled in the config.
+#[derive(Clone, Copy)]
+enum ConnectorKind {
+    Telegram,
+    Discord,
+    Slack,
+}
+
+impl ConnectorKind {
+    fn name(self) -> &'static str {
+        match self {
+            ConnectorKind::Telegram => "telegram",
+            ConnectorKind::Discord => "discord",
+            ConnectorKind::Slack => "slack",
+        }
+    }
+}
+
+/// The platforms with enough configuration to start.
+fn enabled_connectors(config: &Config) -> Vec<ConnectorKind> {
+    let mut kinds = Vec::new();
+    if config.telegram_token.is_some() {
+        kinds.push(ConnectorKind::Telegram);
+    }
+    if config.discord_token.is_some() {
+        if config.discord_channel_ids.is_empty() {
+            eprintln!("sentinel: warning: discord token set but no channel_ids configured");
+        } else {
+            kinds.push(ConnectorKind::Discord);
+        }
+    }
+    if config.slack_bot_token.is_some() {
         if config.slack_channel_ids.is_empty() {
             eprintln!("sentinel: warning: slack token set but no channel_ids configured");
         } else {
-            let http = match HttpClient::new() {
-                Ok(h) => h,
-                Err(e) => {
-                    eprintln!("sentinel: fatal: failed to initialize HTTP client: {}", e);
-                    std::process::exit(1);
-                }
-            };
-            match SlackConnector::new(http, token, &config.slack_channel_ids) {
-                Ok(sc) => {
-                    connectors.push(Box::new(sc));
-                    eprintln!("sentinel: slack connector enabled");
+            kinds.push(ConnectorKind::Slack);
+        }
+    }
+    kinds
+}
+
+/// Build a connector with its own HTTP client using `tls`. Discord and Slack look up the
+/// bot's user ID here, so this makes a network request for them. The
+/// `receiving` instance is the one polled; for Telegram it also serves the
+/// webhook on `webhook`, bound before the sandbox, or clears a stale
+/// webhook before long polling, and for Discord it opens the gateway when
+/// configured.
+fn build_connector(
+    kind: ConnectorKind,
+    config: &Config,
+    tls: &TlsConfig,
+    egress: &EgressPolicy,
+    receiving: bool,
+    webhook: Option<&TcpListener>,
+) -> Result<Box<dyn Connector>, String> {
+    let http = configure_http(HttpClient::with_tls(tls), config, egress);
+    match kind {
+        ConnectorKind::Telegram => {
+            let token = config.telegram_token.as_deref().unwrap_or_default();
+            let parse_mode = config.telegram_parse_mode.as_deref().map(|name| {
+                ParseMode::from_name(name).ok_or_else(|| format!("unknown parse_mode {:?}", name))
+            });
+            let mut client = TelegramClient::new(http, token).with_parse_mode(parse_mode.transpose()?);
+            if receiving {
+                // Needed to spot mentions; looked up even with the mode off
+                // so a reload can turn it on
+                if let Err(e) = client.identify() {
+                    eprintln!("sentinel: warning: telegram getMe failed, mentions won't be recognized: {}", e);
                 }
-                Err(e) => {
-                    eprintln!("sentinel: warning: failed to initialize slack: {}", e);
+                match (webhook, &config.telegram_webhook_url) {
+                    (Some(listener), Some(url)) => client
+                        .start_webhook(listener, &config.telegram_webhook_path, url)
+                        .map_err(|e| e.to_string())?,
+                    _ => {
+                        if let Err(e) = client.delete_webhook() {
+                            eprintln!("sentinel: warning: telegram deleteWebhook failed: {}", e);
+                        }
+                    }
                 }
             }
+            Ok(Box::new(client))
+        }
+        ConnectorKind::Discord => {
+            let token = config.discord_token.as_deref().unwrap_or_default();
+            DiscordConnector::new(http, token, &config.discord_channel_ids)
+                .map(|c| if receiving && config.discord_gateway { c.with_gateway() } else { c })
+                .map(|c| Box::new(c) as Box<dyn Connector>)
+                .map_err(|e| e.to_string())
+        }
+        ConnectorKind::Slack => {
+            let token = config.slack_bot_token.as_deref().unwrap_or_default();
+            SlackConnector::new(http, token, &config.slack_channel_ids)
+                .map(|c| Box::new(c) as Box<dyn Connector>)
+                .map_err(|e| e.to_string())
         }
     }
+}
 
-    if connectors.is_empty() {
-        eprintln!("sentinel: fatal: no messaging connectors available");
-        std::process::exit(1);
+/// Tell the user their conversation is out of tokens and audit it.
+fn report_budget_exhausted(
+    auditor: &mut Auditor,
+    connector: &dyn Connector,
+    channel_id: &str,
+    conv_key: &str,
+    budget: &TokenBudget,
+) {
+    auditor.log(AuditEvent::TokenBudgetExhausted {
+        conversation: conv_key,
+        used: budget.total(),
+        limit: budget.limit().unwrap_or(0),
+    });
+    let text = format!(
+        "Token budget for this conversation is exhausted ({} of {} tokens). \
+         Use /clear to start a new conversation.",
+        budget.total(),
+        budget.limit().unwrap_or(0)
+    );
+    if let Err(e) = connector.send_message(channel_id, &text) {
+        eprintln!("sentinel: failed to send message: {}", e);
     }
+}
 
-    // Per-conversation history keyed by "platform:channel_id"
-    let mut conversations: HashMap<String, Vec<Message>> = HashMap::new();
+/// Run `turn` between marking the incoming message as received and replacing
+/// the mark with the turn's outcome. The mark is updated on the error path
+/// too, so a failed turn never leaves 👀 behind. Reaction failures are only
+/// logged.
+fn with_ack<T, E>(
+    connector: &dyn Connector,
+    enabled: bool,
+    channel_id: &str,
+    message_id: &str,
+    turn: impl FnOnce() -> Result<T, E>,
+) -> Result<T, E> {
+    if !enabled {
+        return turn();
+    }
+    if let Err(e) = connector.ack_received(channel_id, message_id) {
+        eprintln!("sentinel: {} ack error: {}", connector.platform_name(), e);
+    }
+    let result = turn();
+    if let Err(e) = connector.ack_done(channel_id, message_id, result.is_ok()) {
+        eprintln!("sentinel: {} ack error: {}", connector.platform_name(), e);
+    }
+    result
+}
 
-    // Use short poll timeout when multiple connectors are active
-    let poll_timeout = if connectors.len() > 1 { 2 } else { 30 };
+/// The configured LLM provider, with its own HTTP client using `tls`,
+/// telling `api_calls` about every request.
+fn build_llm(
+    config: &Config,
+    tls: &TlsConfig,
+    egress: &EgressPolicy,
+    api_calls: &ApiCallHook,
+) -> Result<Box<dyn LlmProvider>, String> {
+    let llm: Box<dyn LlmProvider> = match config.provider.as_str() {
+        "openai" | "azure" => {
+            let llm_http = configure_http(HttpClient::with_tls(tls), config, egress);
+            let family = config.openai_model_family.as_deref().map(|name| {
+                ModelFamily::from_name(name)
+                    .ok_or_else(|| format!("unknown openai model_family {:?}", name))
+            });
+            // Only OpenAI itself is known to accept gzip request bodies
+            let compress = config.compress_requests
+                && config.openai_base_url.starts_with("https://api.openai.com/");
+            Box::new(OpenAiClient::new(
+                llm_http
+                    .with_request_compression(compress)
+                    .with_retry(llm_retry_policy(config)),
+                config.api_key.clone(),
+                config.model.clone(),
+                config.max_tokens,
+                config.openai_base_url.clone(),
+            )
+            .with_model_family(family.transpose()?)
+            .with_parallel_tool_calls(config.parallel_tool_calls)
+            .with_sampling(sampling_params(config))
+            .with_extra_headers(config.openai_extra_headers.clone())
+            .with_azure(config.provider == "azure")
+            .with_api_call_hook(Rc::clone(api_calls)))
+        }
+        "gemini" => {
+            let llm_http = configure_http(HttpClient::with_tls(tls), config, egress);
+            Box::new(GeminiClient::new(
+                llm_http.with_retry(llm_retry_policy(config)),
+                config.api_key.clone(),
+                config.model.clone(),
+                config.max_tokens,
+            )
+            .with_sampling(sampling_params(config))
+            .with_api_call_hook(Rc::clone(api_calls)))
+        }
+        "echo" => Box::new(EchoProvider),
+        _ => {
+            let llm_http = configure_http(HttpClient::with_tls(tls), config, egress);
+            Box::new(AnthropicClient::new(
+                llm_http
+                    .with_request_compression(config.compress_requests)
+                    .with_retry(llm_retry_policy(config)),
+                config.api_key.clone(),
+                config.model.clone(),
+                config.max_tokens,
+            )
+            .with_parallel_tool_calls(config.parallel_tool_calls)
+            .with_prompt_cache(config.anthropic_prompt_cache)
+            .with_model_fallbacks(config.model_fallbacks.clone())
+            .with_sampling(sampling_params(config))
+            .with_extra_headers(config.anthropic_extra_headers.clone())
+            .with_api_call_hook(Rc::clone(api_calls)))
+        }
+    };
+    Ok(llm)
+}
 
-    eprintln!(
-        "sentinel: started with {} connector(s), polling...",
-        connectors.len()
+/// `--check-config --ping`: ask the LLM provider for the configured model
+/// and each messaging platform for the bot's identity, so a bad key, model
+/// or token shows up in the report rather than at the first message.
+pub fn check_endpoints(config: &Config, report: &mut CheckReport) {
+    let egress = EgressPolicy::new(config.allowed_egress_hosts.clone(), Rc::new(|_: &str| {}))
+        .with_network(config.network_allowlist());
+    let http = match llm_tls_config(config) {
+        Ok(tls) => configure_http(HttpClient::with_tls(&tls), config, &egress),
+        Err(e) => {
+            report.push("ping", CheckStatus::Fail, format!("HTTP client: {}", e));
+            return;
+        }
+    };
+
+    let (status, detail) = ping_llm(config, &http);
+    report.push("ping llm", status, detail);
+
+    if let Some(token) = &config.telegram_token {
+        let url = format!("https://api.telegram.org/bot{}/getMe", token);
+        let (status, detail) = match http.get(&url, &[]) {
+            Ok(resp) if resp.status == 200 => (CheckStatus::Ok, "token accepted".to_string()),
+            Ok(resp) => (CheckStatus::Fail, format!("token rejected (HTTP {})", resp.status)),
+            Err(e) => (CheckStatus::Fail, e.to_string()),
+        };
+        report.push("ping telegram", status, detail);
+    }
+    // Building these looks up the bot's user ID
+    for kind in [ConnectorKind::Discord, ConnectorKind::Slack] {
+        let token = match kind {
+            ConnectorKind::Discord => &config.discord_token,
+            _ => &config.slack_bot_token,
+        };
+        if token.is_none() {
+            continue;
+        }
+        let name = format!("ping {}", kind.name());
+        match build_connector(kind, config, &TlsConfig::new(), &egress, false, None) {
+            Ok(_) => report.push(&name, CheckStatus::Ok, "token accepted"),
+            Err(e) => report.push(&name, CheckStatus::Fail, e),
+        }
+    }
+}
+
+/// Ask the LLM provider whether the configured model exists, which also
+/// checks the API key.
+fn ping_llm(config: &Config, http: &HttpClient) -> (CheckStatus, String) {
+    if config.provider == "azure" {
+        // Deployments can't be looked up by name with the data-plane API
+        return (CheckStatus::Warn, "not checked for azure".to_string());
+    }
+    if config.provider == "echo" {
+        return (CheckStatus::Ok, "echo runs offline".to_string());
+    }
+    let auth = format!("Bearer {}", config.api_key);
+    let (url, headers): (String, Vec<(&str, &str)>) = match config.provider.as_str() {
+        "openai" => (
+            endpoint_url(&config.openai_base_url, &format!("/models/{}", config.model)),
+            vec![("Authorization", auth.as_str())],
+        ),
+        "gemini" => (
+            format!("{}/models/{}?key={}", GEMINI_API, config.model, config.api_key),
+            Vec::new(),
+        ),
+        _ => (
+            format!("https://api.anthropic.com/v1/models/{}", config.model),
+            vec![("x-api-key", config.api_key.as_str()), ("anthropic-version", "2023-06-01")],
+        ),
+    };
+    match http.get(&url, &headers) {
+        Ok(resp) if (200..300).contains(&resp.status) => {
+            (CheckStatus::Ok, format!("model '{}' available", config.model))
+        }
+        Ok(resp) if resp.status == 401 || resp.status == 403 => {
+            (CheckStatus::Fail, format!("API key rejected (HTTP {})", resp.status))
+        }
+        Ok(resp) if resp.status == 404 => {
+            (CheckStatus::Warn, format!("model '{}' not found (HTTP 404)", config.model))
+        }
+        Ok(resp) => (CheckStatus::Warn, format!("HTTP {}", resp.status)),
+        Err(e) => (CheckStatus::Fail, e.to_string()),
+    }
+}
+
+/// Paths the OS sandbox lets the agent read: the allowlist, plus the
+/// config file and secret files so SIGHUP can re-read them.
+fn sandbox_read_paths(config: &Config) -> Vec<String> {
+    let mut read_paths = config.allowed_read_paths.clone();
+    read_paths.extend(config.config_file.clone());
+    read_paths.extend(config.secret_files.iter().cloned());
+    read_paths
+}
+
+/// Paths the OS sandbox lets the agent write: the allowlist, and the audit
+/// log files when rotation reopens them. Only the files, so nothing else
+/// can be created or removed next to the log. The state directory isn't
+/// granted; its prompt file is opened before the sandbox.
+fn sandbox_write_paths(config: &Config) -> Vec<String> {
+    let mut write_paths = config.allowed_write_paths.clone();
+    if let (Some(path), true) = (&config.audit_log_path, config.audit_max_bytes > 0) {
+        write_paths.push(path.clone());
+        write_paths.extend((1..=config.audit_keep_files).map(|n| format!("{}.{}", path, n)));
+    }
+    write_paths
+}
+
+#[cfg(feature = "metrics")]
+fn bind_metrics(bind: &str) -> Option<TcpListener> {
+    match metrics::bind(bind) {
+        Ok((listener, addr)) => {
+            eprintln!("sentinel: metrics listening on {}/metrics", addr);
+            Some(listener)
+        }
+        Err(e) => {
+            eprintln!("sentinel: warning: cannot serve metrics on {}: {}", bind, e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+fn bind_metrics(_bind: &str) -> Option<TcpListener> {
+    eprintln!("sentinel: warning: metrics_bind is set but this build has no metrics feature");
+    None
+}
+
+#[cfg(feature = "metrics")]
+fn serve_metrics(listener: TcpListener) {
+    if let Err(e) = metrics::serve(listener) {
+        eprintln!("sentinel: warning: cannot serve metrics: {}", e);
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+fn serve_metrics(_listener: TcpListener) {}
+
+#[cfg(target_os = "linux")]
+fn apply_os_sandbox(config: &Config) {
+    use crate::security::linux::SeccompMode;
+    let Some(seccomp_mode) = SeccompMode::parse(&config.seccomp_mode) else {
+        eprintln!(
+            "sentinel: fatal: seccomp_mode must be \"enforce\" or \"log\", not \"{}\"",
+            config.seccomp_mode
+        );
+        std::process::exit(1);
+    };
+    let result = crate::security::linux::apply_sandbox(
+        &sandbox_read_paths(config),
+        &sandbox_write_paths(config),
+        true,  // enable seccomp
+        seccomp_mode,
+        true,  // enable landlock
     );
+    if let (true, Some(e)) = (result.seccomp_applied, &result.seccomp_error) {
+        eprintln!("sentinel: fatal: seccomp filter is broken: {}", e);
+        std::process::exit(1);
+    }
+    if result.seccomp_applied || result.landlock_applied {
+        let abi = result.landlock_abi.map_or("none".into(), |v| format!("v{}", v));
+        eprintln!("sentinel: sandbox active (seccomp={}, landlock={}, landlock ABI {})",
+            result.seccomp_applied, result.landlock_applied, abi);
+    } else {
+        warn_unsandboxed("neither seccomp nor landlock is available on this kernel");
+    }
+}
 
-    loop {
-        let mut had_messages = false;
+#[cfg(target_os = "macos")]
+fn apply_os_sandbox(config: &Config) {
+    match crate::security::macos::apply_sandbox(
+        &sandbox_read_paths(config),
+        &sandbox_write_paths(config),
+    ) {
+        Ok(()) => eprintln!("sentinel: sandbox active (seatbelt)"),
+        Err(e) => warn_unsandboxed(&format!("sandbox_init failed: {}", e)),
+    }
+}
 
-        for i in 0..connectors.len() {
-            let updates = match connectors[i].poll_messages(poll_timeout) {
-                Ok(msgs) => msgs,
-                Err(e) => {
-                    eprintln!(
-                        "sentinel: {} poll error: {}",
-                        connectors[i].platform_name(),
-                        e
-                    );
-                    thread::sleep(Duration::from_secs(5));
-                    continue;
-                }
-            };
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn apply_os_sandbox(_config: &Config) {
+    warn_unsandboxed("there is no OS sandbox for this platform");
+}
+
+/// The sandbox was asked for but couldn't be applied: only the
+/// application-level allowlists stand between the model and the machine.
+fn warn_unsandboxed(reason: &str) {
+    eprintln!("sentinel: ************************************************************");
+    eprintln!("sentinel: WARNING: running WITHOUT an OS sandbox: {}.", reason);
+    eprintln!("sentinel: Tool calls are limited by the configured allowlists only;");
+    eprintln!("sentinel: commands the agent runs have this user's full access.");
+    eprintln!("sentinel: ************************************************************");
+}
+
+/// Outbound host allowlist for a thread's HTTP clients. Refusals go to
+/// that thread's audit log.
+fn audited_egress(
+    config: &Config,
+    platform: Rc<HostPlatform>,
+    sink: Option<AuditSink>,
+) -> EgressPolicy {
+    EgressPolicy::new(
+        config.allowed_egress_hosts.clone(),
+        Rc::new(move |host: &str| {
+            Auditor::new(&*platform)
+                .with_sink(sink.as_ref())
+                .log(AuditEvent::EgressDenied { host });
+        }),
+    )
+    .with_network(config.network_allowlist())
+}
 
-            if !updates.is_empty() {
-                had_messages = true;
+/// A hook auditing every request an LLM client makes.
+fn audited_api_calls(platform: Rc<HostPlatform>, sink: Option<AuditSink>) -> ApiCallHook {
+    Rc::new(move |call: &ApiCall| {
+        Auditor::new(&*platform)
+            .with_sink(sink.as_ref())
+            .log(AuditEvent::ApiCall {
+                endpoint: call.endpoint,
+                status: call.status,
+                tokens_in: call.tokens_in,
+                tokens_out: call.tokens_out,
+                latency_ms: call.latency.as_millis() as u64,
+            });
+    })
+}
+
+/// Start the thread that POSTs queued audit events to `url`. It has its own
+/// HTTP client, as clients aren't Send; failures are logged and retried on
+/// the next flush, never passed back to the agent.
+fn spawn_audit_webhook(config: Arc<Config>, url: String, tls: TlsConfig) -> AuditSink {
+    let sink = AuditSink::new(AUDIT_QUEUE_CAPACITY, config.audit_webhook_batch);
+    let queue = sink.clone();
+    let run = move || {
+        // A refusal here can't be audited without queueing yet another event
+        let egress = EgressPolicy::new(
+            config.allowed_egress_hosts.clone(),
+            Rc::new(|host: &str| {
+                eprintln!("sentinel: audit webhook host '{}' is not allowed", host);
+            }),
+        )
+        .with_network(config.network_allowlist());
+        let http = configure_http(HttpClient::with_tls(&tls), &config, &egress);
+        let interval = Duration::from_secs(config.audit_webhook_interval);
+        loop {
+            let sent = queue.flush(interval, &mut |body| match http.post_json(&url, body, &[]) {
+                Ok(resp) if (200..300).contains(&resp.status) => Ok(()),
+                Ok(resp) => Err(format!("HTTP {}", resp.status)),
+                Err(e) => Err(e.to_string()),
+            });
+            if let Err(e) = sent {
+                eprintln!("sentinel: audit webhook failed: {}", e);
+                thread::sleep(interval);
             }
+        }
+    };
+    thread::Builder::new()
+        .name("audit-webhook".into())
+        .spawn(run)
+        .expect("failed to spawn audit webhook thread");
+    sink
+}
 
-            for msg in updates {
-                let platform = connectors[i].platform_name();
-                let username = msg.username.as_deref().unwrap_or("unknown");
+/// The worker that owns a conversation. It is always the same one, so a
+/// conversation's messages are handled in the order they arrived.
+fn worker_for(conversation: &str, workers: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    conversation.hash(&mut hasher);
+    (hasher.finish() % workers as u64) as usize
+}
 
-                auditor.log(AuditEvent::MessageReceived {
-                    chat_id: msg.channel_id.parse::<i64>().unwrap_or(0),
-                    user_id: msg.user_id.parse::<i64>().unwrap_or(0),
-                    username,
-                });
+/// Apply the `[net]` egress, pool, timeout and size settings to a new client.
+fn configure_http(http: HttpClient, config: &Config, egress: &EgressPolicy) -> HttpClient {
+    http.with_egress_policy(egress.clone())
+        .with_pool(
+            config.http_pool_max_per_host,
+            Duration::from_secs(config.http_pool_idle_timeout),
+        )
+        .with_timeouts(
+            Duration::from_secs(config.http_connect_timeout),
+            Duration::from_secs(config.http_read_timeout),
+            Duration::from_secs(config.http_write_timeout),
+        )
+        .with_max_response_bytes(config.http_max_response_bytes)
+}
 
-                // Authorization check
-                if !is_authorized(&config, platform, &msg.user_id) {
-                    auditor.log(AuditEvent::UnauthorizedUser {
-                        user_id: msg.user_id.parse::<i64>().unwrap_or(0),
-                        username,
-                    });
-                    let _ = connectors[i].send_message(&msg.channel_id, "Unauthorized.");
-                    continue;
-                }
+/// The `[agent]` sampling settings, shared by every provider.
+fn sampling_params(config: &Config) -> SamplingParams {
+    SamplingParams {
+        temperature: config.temperature,
+        top_p: config.top_p,
+        stop_sequences: config.stop_sequences.clone(),
+    }
+}
 
-                let conv_key = format!("{}:{}", platform, msg.channel_id);
+/// The LLM client's trust settings, from `tls_ca_file` /
+/// `tls_insecure_skip_verify`. These exist for self-hosted gateways, so the
+/// messaging clients are unaffected.
+fn llm_tls_config(config: &Config) -> Result<TlsConfig, HttpError> {
+    let tls = match &config.tls_ca_file {
+        Some(path) => TlsConfig::with_ca_file(path)?,
+        None => TlsConfig::new(),
+    };
+    if config.tls_insecure_skip_verify {
+        return Ok(TlsConfig::insecure_skip_verify());
+    }
+    Ok(tls)
+}
 
-                // Handle /clear command
-                if msg.text.trim() == "/clear" {
-                    conversations.remove(&conv_key);
-                    let _ = connectors[i]
-                        .send_message(&msg.channel_id, "Conversation cleared.");
-                    continue;
-                }
+/// LLM completion requests have no side effects, so POSTs are safe to retry.
+fn llm_retry_policy(config: &Config) -> RetryPolicy {
+    RetryPolicy::new(
+        config.max_retries,
+        Duration::from_millis(config.retry_base_delay_ms),
+    )
+    .retry_non_idempotent()
+}
 
-                // Get or create conversation history
-                let history = conversations.entry(conv_key).or_default();
+/// The /help reply: the built-in commands, the tools and skills the model
+/// can use, and the commands run_command allows. Markdown, which each
+/// connector renders in its platform's format.
+fn help_text(
+    tool_defs: &[ToolDef],
+    skill_runner: Option<&SkillRunner>,
+    allowed_commands: &[String],
+    admin: bool,
+) -> String {
+    let mut out = String::from("**Commands**\n");
+    for command in BUILTIN_COMMANDS.iter().filter(|c| admin || !c.admin) {
+        out.push_str(&format!("`{}` — {}\n", command.usage, command.description));
+    }
+
+    let is_skill = |def: &&ToolDef| skill_runner.is_some_and(|r| r.handles(&def.name));
+    let (skills, tools): (Vec<&ToolDef>, Vec<&ToolDef>) = tool_defs.iter().partition(is_skill);
+    for (heading, defs) in [("Tools", tools), ("Skills", skills)] {
+        if defs.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n**{}**\n", heading));
+        for def in defs {
+            out.push_str(&format!("`{}` — {}\n", def.name, first_sentence(&def.description)));
+        }
+    }
+
+    out.push_str("\n**Allowed commands**\n");
+    if allowed_commands.is_empty() {
+        out.push_str("None: run_command is disabled.\n");
+    } else {
+        let names: Vec<String> = allowed_commands.iter().map(|c| format!("`{}`", c)).collect();
+        out.push_str(&names.join(", "));
+        out.push('\n');
+    }
+    out.trim_end().to_string()
+}
 
-                // Add user message
-                history.push(Message {
-                    role: Role::User,
-                    content: vec![ContentBlock::Text {
-                        text: msg.text.clone(),
-                    }],
-                });
+/// A tool description's first sentence, for one-line listings.
+fn first_sentence(description: &str) -> &str {
+    match description.find(". ") {
+        Some(end) => &description[..=end],
+        None => description.trim_end(),
+    }
+}
 
-                // Run agent turn with streaming
-                match run_agent_turn(
-                    llm.as_ref(),
-                    history,
-                    &config,
-                    &tool_defs,
-                    &tool_executor,
-                    &mut auditor,
-                    &*connectors[i],
-                    &msg.channel_id,
-                ) {
-                    Ok(()) => {}
-                    Err(e) => {
-                        eprintln!("sentinel: agent error: {}", e);
-                        let error_msg = format!("Error: {}", e);
-                        let _ = connectors[i].send_message(&msg.channel_id, &error_msg);
-                    }
-                }
+/// The text after `command` if the message is that command, e.g. `"be brief"`
+/// for `"/setprompt be brief"`; empty when it has no argument.
+fn command_arg<'t>(text: &'t str, command: &str) -> Option<&'t str> {
+    let rest = text.trim().strip_prefix(command)?;
+    if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
 
-                // Trim history if too long
-                if history.len() > MAX_HISTORY_MESSAGES {
-                    let drain_count = history.len() - MAX_HISTORY_MESSAGES;
-                    history.drain(..drain_count);
-                }
-            }
-        }
+/// Key for a conversation's state: "platform:channel_id".
+fn conversation_key(platform: &str, channel_id: &str) -> String {
+    format!("{}:{}", platform, channel_id)
+}
 
-        // For HTTP-polling connectors without long-poll, avoid tight loops
-        if !had_messages && connectors.len() > 1 {
-            thread::sleep(Duration::from_secs(1));
-        }
+/// Close out a cancelled turn: any unrun calls' results, then the
+/// cancellation as the assistant's reply, so the model sees the work stopped.
+fn record_cancellation(history: &mut Vec<Message>, results: Vec<ContentBlock>) {
+    if !results.is_empty() {
+        history.push(Message {
+            role: Role::User,
+            content: results,
+        });
     }
+    history.push(Message {
+        role: Role::Assistant,
+        content: vec![ContentBlock::Text {
+            text: CANCELLED_REPLY.to_string(),
+        }],
+    });
 }
 
 fn is_authorized(config: &Config, platform: &str, user_id: &str) -> bool {
@@ -304,31 +1510,162 @@ fn is_authorized(config: &Config, platform: &str, user_id: &str) -> bool {
     }
 }
 
+fn is_admin(config: &Config, platform: &str, user_id: &str) -> bool {
+    let key = format!("{}:{}", platform, user_id);
+    config.admin_users.contains(&key)
+}
+
+/// Build the onboarding message for a conversation key that has no history yet.
+/// Returns None if no greeting is configured or the conversation already exists.
+fn greeting_for_new_conversation(
+    greeting: Option<&str>,
+    conversations: &HashMap<String, Vec<Message>>,
+    conv_key: &str,
+    tool_defs: &[ToolDef],
+) -> Option<String> {
+    let greeting = greeting?;
+    if conversations.contains_key(conv_key) {
+        return None;
+    }
+
+    let mut text = greeting.to_string();
+    if !tool_defs.is_empty() {
+        let names: Vec<&str> = tool_defs.iter().map(|t| t.name.as_str()).collect();
+        text.push_str(&format!("\n\nAvailable tools: {}", names.join(", ")));
+    }
+    text.push_str("\nCommands: /clear");
+    Some(text)
+}
+
+/// System prompt for one turn, with the continuation hint added when the user
+/// is replying to a clarifying question.
+fn turn_system_prompt(base: Option<&str>, continuation: bool) -> Option<String> {
+    match (base, continuation) {
+        (Some(b), true) => Some(format!("{}\n\n{}", b, CONTINUATION_HINT)),
+        (None, true) => Some(CONTINUATION_HINT.to_string()),
+        (b, false) => b.map(|s| s.to_string()),
+    }
+}
+
+/// Drop the oldest exchanges until the history's estimated size is within
+/// `max_tokens`. Cuts only land on a plain user message, so no tool result
+/// is left without the tool call that produced it; the latest exchange is
+/// kept even if it alone is over the limit.
+fn trim_history(history: &mut Vec<Message>, max_tokens: usize) {
+    let sizes: Vec<usize> = history.iter().map(estimate_tokens).collect();
+    let mut remaining: usize = sizes.iter().sum();
+    if remaining <= max_tokens {
+        return;
+    }
+    let mut cut = None;
+    for (i, msg) in history.iter().enumerate() {
+        if i > 0 && is_user_text(msg) {
+            cut = Some(i);
+            if remaining <= max_tokens {
+                break;
+            }
+        }
+        remaining -= sizes[i];
+    }
+    if let Some(cut) = cut {
+        history.drain(..cut);
+    }
+}
+
+/// Tokens an image is counted as; providers scale images down, so the
+/// cost doesn't grow with the upload's size.
+const IMAGE_TOKENS: usize = 1600;
+
+/// Rough token count of a message: a quarter of its characters, plus a
+/// flat cost per image.
+fn estimate_tokens(msg: &Message) -> usize {
+    let mut images = 0;
+    let chars: usize = msg
+        .content
+        .iter()
+        .map(|block| match block {
+            ContentBlock::Text { text } => text.len(),
+            ContentBlock::Thinking { text, .. } => text.len(),
+            ContentBlock::ToolUse { name, input, .. } => name.len() + input.to_json_string().len(),
+            ContentBlock::ToolResult { content, .. } => content.len(),
+            ContentBlock::Image { .. } => {
+                images += 1;
+                0
+            }
+        })
+        .sum();
+    chars.div_ceil(4) + images * IMAGE_TOKENS
+}
+
+fn is_user_text(msg: &Message) -> bool {
+    matches!(msg.role, Role::User)
+        && msg
+            .content
+            .iter()
+            .all(|b| matches!(b, ContentBlock::Text { .. } | ContentBlock::Image { .. }))
+}
+
+/// True if the model's final text ends by asking the user something.
+fn ends_with_question(text: &str) -> bool {
+    text.trim_end().ends_with('?')
+}
+
+/// The conversation a turn runs in: where replies go, and the state it
+/// carries from one turn to the next besides the history.
+struct TurnState<'a> {
+    connector: &'a dyn Connector,
+    ctx: &'a TurnContext<'a>,
+    ages: &'a mut ToolResultAges,
+    budget: &'a mut TokenBudget,
+    transcript: &'a mut TurnTranscript,
+}
+
 fn run_agent_turn(
     llm: &dyn LlmProvider,
     history: &mut Vec<Message>,
-    config: &Config,
+    system: Option<&str>,
     tool_defs: &[ToolDef],
     tool_executor: &ToolExecutor,
     auditor: &mut Auditor,
-    connector: &dyn Connector,
-    channel_id: &str,
-) -> Result<(), String> {
-    let system = config.system_prompt.as_deref();
-
+    turn: TurnState,
+) -> Result<TurnOutcome, String> {
+    let TurnState { connector, ctx, ages, budget, transcript } = turn;
+    let channel_id = ctx.channel_id;
     for _round in 0..MAX_TOOL_ROUNDS {
+        // History ends with a user message or tool results here, so
+        // stopping leaves it well-formed
+        if tool_executor.cancelled() {
+            return Ok(TurnOutcome::Cancelled);
+        }
+        if budget.exhausted() {
+            return Ok(TurnOutcome::BudgetExhausted);
+        }
+
+        // Mark old read-only tool results before sending
+        let outgoing = ages.annotate(history);
+
+        // Show "typing…" until the reply appears as a message
+        let mut typing = TypingIndicator::default();
+        typing.refresh(connector, channel_id);
+
         // Streaming state for real-time message updates
         let mut streamed_text = String::new();
         let mut platform_msg_id: Option<String> = None;
         let mut last_edit = Instant::now();
 
+        #[cfg(feature = "metrics")]
+        let request_started = Instant::now();
         let api_resp = {
             let streamed_text_ref = &mut streamed_text;
             let platform_msg_id_ref = &mut platform_msg_id;
             let last_edit_ref = &mut last_edit;
+            let typing_ref = &mut typing;
 
             let mut on_text = |delta: &str| {
                 streamed_text_ref.push_str(delta);
+                if platform_msg_id_ref.is_none() {
+                    typing_ref.refresh(connector, channel_id);
+                }
 
                 // Send/edit message periodically (every 500ms)
                 let should_update = last_edit_ref.elapsed() >= Duration::from_millis(500);
@@ -349,25 +1686,39 @@ fn run_agent_turn(
                 *last_edit_ref = Instant::now();
             };
 
-            match llm.send_streaming(system, history, tool_defs, &mut on_text) {
+            match llm.send_streaming(system, &outgoing, tool_defs, &mut on_text) {
                 Ok(r) => r,
                 Err(LlmError::RateLimit { retry_after }) => {
+                    transcript.rate_limited = true;
                     let wait = retry_after.unwrap_or(10);
-                    eprintln!("sentinel: rate limited, waiting {}s", wait);
+                    log::warn(
+                        &format!("rate limited, waiting {}s", wait),
+                        &[("source", &"llm"), ("wait_secs", &wait)],
+                    );
                     thread::sleep(Duration::from_secs(wait));
                     // Retry once (non-streaming fallback)
-                    llm.send(system, history, tool_defs)
+                    llm.send(system, &outgoing, tool_defs)
                         .map_err(|e| format!("LLM API error: {}", e))?
                 }
                 Err(e) => return Err(format!("LLM API error: {}", e)),
             }
         };
+        #[cfg(feature = "metrics")]
+        metrics::global().observe_llm_latency(request_started.elapsed());
 
         // Add assistant response to history
         history.push(Message {
             role: Role::Assistant,
             content: api_resp.content.clone(),
         });
+        transcript.record_response(&api_resp);
+        budget.record(&api_resp);
+        auditor.log(AuditEvent::LlmUsage {
+            input: api_resp.usage_input,
+            output: api_resp.usage_output,
+            cache_creation: api_resp.usage_cache_creation,
+            cache_read: api_resp.usage_cache_read,
+        });
 
         match api_resp.stop_reason {
             StopReason::EndTurn | StopReason::MaxTokens => {
@@ -383,7 +1734,10 @@ fn run_agent_turn(
                         eprintln!("sentinel: failed to send message: {}", e);
                     }
                 }
-                return Ok(());
+                if matches!(api_resp.stop_reason, StopReason::EndTurn) && ends_with_question(&text) {
+                    return Ok(TurnOutcome::AwaitingInput);
+                }
+                return Ok(TurnOutcome::Completed);
             }
             StopReason::ToolUse => {
                 // If we streamed partial text, finalize it
@@ -394,23 +1748,22 @@ fn run_agent_turn(
                     }
                 }
 
-                // Execute each tool call
-                let mut tool_results = Vec::new();
-                for block in &api_resp.content {
-                    if let ContentBlock::ToolUse { id, name, input } = block {
-                        eprintln!("sentinel: tool call: {}({})", name, input.to_json_string());
-                        let result =
-                            tool_executor.execute(id, name, input, auditor);
-                        tool_results.push(result);
+                let batch = ToolBatch::from_response(&api_resp.content);
+                match run_tool_calls(batch, tool_executor, auditor, ages, connector, ctx) {
+                    ToolRun::Done(tool_results) => {
+                        // Add tool results as user message
+                        transcript.record_results(&tool_results);
+                        if !tool_results.is_empty() {
+                            history.push(Message {
+                                role: Role::User,
+                                content: tool_results,
+                            });
+                        }
+                    }
+                    ToolRun::NeedsApproval(pending) => {
+                        transcript.record_results(&pending.batch.results);
+                        return Ok(TurnOutcome::AwaitingApproval(pending));
                     }
-                }
-
-                // Add tool results as user message
-                if !tool_results.is_empty() {
-                    history.push(Message {
-                        role: Role::User,
-                        content: tool_results,
-                    });
                 }
             }
             StopReason::Other(ref reason) => {
@@ -419,19 +1772,937 @@ fn run_agent_turn(
         }
     }
 
-    Err("max tool rounds exceeded".into())
+    if tool_executor.cancelled() {
+        return Ok(TurnOutcome::Cancelled);
+    }
+    if budget.exhausted() {
+        return Ok(TurnOutcome::BudgetExhausted);
+    }
+    let mut on_response = |resp: &LlmResponse| {
+        transcript.record_response(resp);
+        budget.record(resp);
+    };
+    summarize_progress(llm, history, system, tool_defs, connector, channel_id, &mut on_response)
+}
+
+/// How a batch of tool calls ended.
+enum ToolRun {
+    /// Every call has a result, in order.
+    Done(Vec<ContentBlock>),
+    /// A call needs the user's approval; the request has been sent.
+    NeedsApproval(PendingApproval),
+}
+
+/// Run a batch's calls in order. A call the executor's approval policy
+/// covers runs only once `batch.decision` approves it; without a decision
+/// the user is asked and the batch stops there.
+fn run_tool_calls(
+    mut batch: ToolBatch,
+    tool_executor: &ToolExecutor,
+    auditor: &mut Auditor,
+    ages: &mut ToolResultAges,
+    connector: &dyn Connector,
+    ctx: &TurnContext,
+) -> ToolRun {
+    let channel_id = ctx.channel_id;
+    let policy = tool_executor.approval_policy();
+    let mut typing = TypingIndicator::default();
+    while let Some(call) = batch.calls.front() {
+        if tool_executor.cancelled() {
+            return ToolRun::Done(batch.abandon("not run: the turn was cancelled"));
+        }
+        if policy.requires(&call.name) {
+            match batch.decision.take() {
+                Some(Decision::Approved) => {}
+                Some(decision) => {
+                    let reason = format!("not run: the user {} this call", decision.as_str());
+                    let call = batch.calls.pop_front().expect("front exists");
+                    batch.results.push(approval::not_run(&call, &reason));
+                    continue;
+                }
+                None => {
+                    let params = call.input.to_json_string();
+                    auditor.log(AuditEvent::ApprovalRequested { tool: &call.name, params: &params });
+                    let request = approval::approval_request(call, policy.timeout);
+                    if let Err(e) = connector.send_message(channel_id, &request) {
+                        eprintln!("sentinel: failed to send approval request: {}", e);
+                    }
+                    return ToolRun::NeedsApproval(PendingApproval::new(batch));
+                }
+            }
+        }
+        let ToolCall { id, name, input } = batch.calls.pop_front().expect("front exists");
+        log::info(
+            &format!("tool call: {}({})", name, input.to_json_string()),
+            &[("tool", &name)],
+        );
+        typing.refresh(connector, channel_id);
+
+        // Show skill progress as a single message edited in place
+        let mut progress_msg_id: Option<String> = None;
+        let mut last_progress: Option<Instant> = None;
+        let mut on_progress = |line: &str| {
+            if last_progress.is_some_and(|t| t.elapsed() < Duration::from_millis(500)) {
+                return;
+            }
+            let text = format!("⏳ {}: {}", name, line);
+            if let Some(ref msg_id) = progress_msg_id {
+                let _ = connector.edit_message_text(channel_id, msg_id, &text);
+            } else {
+                match connector.send_message_get_id(channel_id, &text) {
+                    Ok(id) => progress_msg_id = Some(id),
+                    Err(e) => eprintln!("sentinel: progress send error: {}", e),
+                }
+            }
+            last_progress = Some(Instant::now());
+        };
+
+        let result = tool_executor.execute(&id, &name, &input, ctx, auditor, &mut on_progress);
+        ages.record(&id);
+        batch.results.push(result);
+    }
+    ToolRun::Done(batch.results)
+}
+
+/// Out of tool rounds: ask the model for a progress summary instead of
+/// failing the turn, send it to the user, and keep it in history so a
+/// follow-up message continues the work.
+fn summarize_progress(
+    llm: &dyn LlmProvider,
+    history: &mut Vec<Message>,
+    system: Option<&str>,
+    tool_defs: &[ToolDef],
+    connector: &dyn Connector,
+    channel_id: &str,
+    on_response: &mut dyn FnMut(&LlmResponse),
+) -> Result<TurnOutcome, String> {
+    let system = match system {
+        Some(s) => format!("{}\n\n{}", s, HANDOFF_INSTRUCTION),
+        None => HANDOFF_INSTRUCTION.to_string(),
+    };
+    // Tools stay defined (providers reject tool blocks in history without
+    // them); any tool calls in the reply are dropped.
+    let resp = llm
+        .send(Some(&system), history, tool_defs)
+        .map_err(|e| format!("LLM API error: {}", e))?;
+    on_response(&resp);
+
+    let mut summary = extract_text(&resp.content);
+    if summary.trim().is_empty() {
+        summary = "I ran out of tool calls before finishing this task.".to_string();
+    }
+    history.push(Message {
+        role: Role::Assistant,
+        content: vec![ContentBlock::Text {
+            text: summary.clone(),
+        }],
+    });
+
+    let text = format!("{}\n\n{}", summary, HANDOFF_FOOTER);
+    if let Err(e) = connector.send_message(channel_id, &text) {
+        eprintln!("sentinel: failed to send message: {}", e);
+    }
+    Ok(TurnOutcome::Completed)
 }
 
+/// Text of a response for the user. Adjacent text blocks are one passage
+/// (providers may split text, e.g. around citations) and are concatenated
+/// as-is. Text before and after a tool call is joined with a blank line, so
+/// "Let me check." and "Here's the answer." read as separate paragraphs.
+/// Thinking blocks are the model's private reasoning and are left out.
 fn extract_text(content: &[ContentBlock]) -> String {
-    let mut parts = Vec::new();
+    let mut out = String::new();
+    let mut after_tool = false;
     for block in content {
-        if let ContentBlock::Text { text } = block {
-            parts.push(text.as_str());
+        match block {
+            ContentBlock::Text { text } if !text.trim().is_empty() => {
+                if after_tool && !out.is_empty() {
+                    out.truncate(out.trim_end().len());
+                    out.push_str("\n\n");
+                    out.push_str(text.trim_start());
+                } else {
+                    out.push_str(text);
+                }
+                after_tool = false;
+            }
+            ContentBlock::ToolUse { .. } => after_tool = true,
+            _ => {}
         }
     }
-    if parts.is_empty() {
+    if out.is_empty() {
         "(no text response)".to_string()
     } else {
-        parts.join("\n")
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use crate::messaging::ConnectorError;
+    use crate::llm::provider::report_call;
+    use crate::net::retry::RetryLog;
+    use crate::net::json::{self, json_obj};
+
+    /// Provider that replays canned responses and records the system prompts it saw.
+    struct FakeProvider {
+        responses: RefCell<VecDeque<LlmResponse>>,
+        systems: RefCell<Vec<Option<String>>>,
+    }
+
+    impl FakeProvider {
+        fn new(responses: Vec<LlmResponse>) -> Self {
+            FakeProvider {
+                responses: RefCell::new(responses.into()),
+                systems: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl LlmProvider for FakeProvider {
+        fn send(
+            &self,
+            system: Option<&str>,
+            _messages: &[Message],
+            _tools: &[ToolDef],
+        ) -> Result<LlmResponse, LlmError> {
+            self.systems.borrow_mut().push(system.map(|s| s.to_string()));
+            self.responses
+                .borrow_mut()
+                .pop_front()
+                .ok_or_else(|| LlmError::Json("no more canned responses".into()))
+        }
+    }
+
+    /// Connector that records every message sent and reaction set through it.
+    #[derive(Default)]
+    struct FakeConnector {
+        sent: RefCell<Vec<String>>,
+        acks: RefCell<Vec<String>>,
+    }
+
+    impl Connector for FakeConnector {
+        fn poll_messages(&mut self, _timeout_secs: u32) -> Result<Vec<IncomingMessage>, ConnectorError> {
+            Ok(Vec::new())
+        }
+
+        fn send_message(&self, _channel_id: &str, text: &str) -> Result<(), ConnectorError> {
+            self.sent.borrow_mut().push(text.to_string());
+            Ok(())
+        }
+
+        fn send_message_get_id(&self, channel_id: &str, text: &str) -> Result<String, ConnectorError> {
+            self.send_message(channel_id, text)?;
+            Ok(self.sent.borrow().len().to_string())
+        }
+
+        fn edit_message_text(
+            &self,
+            _channel_id: &str,
+            _message_id: &str,
+            _text: &str,
+        ) -> Result<(), ConnectorError> {
+            Ok(())
+        }
+
+        fn platform_name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn ack_received(&self, _channel_id: &str, message_id: &str) -> Result<(), ConnectorError> {
+            self.acks.borrow_mut().push(format!("{}:received", message_id));
+            Ok(())
+        }
+
+        fn ack_done(
+            &self,
+            _channel_id: &str,
+            message_id: &str,
+            success: bool,
+        ) -> Result<(), ConnectorError> {
+            let outcome = if success { "ok" } else { "failed" };
+            self.acks.borrow_mut().push(format!("{}:{}", message_id, outcome));
+            Ok(())
+        }
+    }
+
+    fn text_response(text: &str) -> LlmResponse {
+        LlmResponse {
+            stop_reason: StopReason::EndTurn,
+            content: vec![ContentBlock::Text { text: text.into() }],
+            usage_input: 0,
+            usage_output: 0,
+            usage_cache_creation: 0,
+            usage_cache_read: 0,
+        }
+    }
+
+    fn user_text(text: &str) -> Message {
+        Message {
+            role: Role::User,
+            content: vec![ContentBlock::Text { text: text.into() }],
+        }
+    }
+
+    fn turn_ctx(channel_id: &str) -> TurnContext<'_> {
+        TurnContext {
+            platform: "fake",
+            channel_id,
+            user_id: "u1",
+            username: None,
+            timestamp: 0,
+        }
+    }
+
+    fn test_platform() -> HostPlatform {
+        HostPlatform::new(Vec::new(), Vec::new(), Vec::new(), None)
+    }
+
+    fn tool(name: &str) -> ToolDef {
+        ToolDef {
+            name: name.into(),
+            description: String::new(),
+            input_schema: json_obj().build(),
+        }
+    }
+
+    #[test]
+    fn test_sandbox_grants_audit_log_files_not_their_directory() {
+        let mut config = crate::config::tests::test_config();
+        config.allowed_write_paths = vec!["/srv/work".into()];
+        config.audit_log_path = Some("audit.jsonl".into());
+        config.state_dir = Some("/var/lib/sentinel".into());
+        config.audit_keep_files = 2;
+        assert_eq!(sandbox_write_paths(&config), vec!["/srv/work"]);
+
+        config.audit_max_bytes = 1024;
+        assert_eq!(
+            sandbox_write_paths(&config),
+            vec!["/srv/work", "audit.jsonl", "audit.jsonl.1", "audit.jsonl.2"]
+        );
+    }
+
+    #[test]
+    fn test_sandbox_reads_config_and_secret_files() {
+        let mut config = crate::config::tests::test_config();
+        config.allowed_read_paths = vec!["/srv/work".into()];
+        config.config_file = Some("/etc/sentinel/sentinel.toml".into());
+        config.secret_files = vec!["/run/secrets/telegram".into(), "/run/secrets/anthropic".into()];
+        assert_eq!(
+            sandbox_read_paths(&config),
+            vec![
+                "/srv/work",
+                "/etc/sentinel/sentinel.toml",
+                "/run/secrets/telegram",
+                "/run/secrets/anthropic"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_greeting_sent_once_per_conversation() {
+        let tools = vec![tool("read_file"), tool("run_command")];
+        let mut conversations: HashMap<String, Vec<Message>> = HashMap::new();
+        let key = "telegram:42";
+
+        let first = greeting_for_new_conversation(Some("Hello!"), &conversations, key, &tools);
+        let first = first.expect("new conversation should be greeted");
+        assert!(first.starts_with("Hello!"));
+        assert!(first.contains("read_file, run_command"));
+
+        // The first message creates the conversation
+        conversations.entry(key.to_string()).or_default().push(Message {
+            role: Role::User,
+            content: vec![ContentBlock::Text { text: "hi".into() }],
+        });
+
+        assert!(greeting_for_new_conversation(Some("Hello!"), &conversations, key, &tools).is_none());
+        assert!(greeting_for_new_conversation(Some("Hello!"), &conversations, "telegram:7", &tools).is_some());
+    }
+
+    #[test]
+    fn test_no_greeting_when_unconfigured() {
+        let conversations: HashMap<String, Vec<Message>> = HashMap::new();
+        assert!(greeting_for_new_conversation(None, &conversations, "slack:C1", &[]).is_none());
+    }
+
+    #[test]
+    fn test_question_then_answer_keeps_coherent_history() {
+        let platform = test_platform();
+        let executor = ToolExecutor::new(&platform, 5);
+        let mut auditor = Auditor::new(&platform);
+        let connector = FakeConnector::default();
+        let llm = FakeProvider::new(vec![
+            text_response("Which environment should I deploy to?"),
+            text_response("Deployed to staging."),
+        ]);
+
+        let mut history = vec![user_text("deploy the app")];
+        let mut ages = ToolResultAges::new(None);
+        let outcome = run_agent_turn(
+            &llm, &mut history, Some("base"), &[], &executor, &mut auditor,
+            TurnState { connector: &connector, ctx: &turn_ctx("c1"), ages: &mut ages,
+                budget: &mut TokenBudget::new(None), transcript: &mut TurnTranscript::default() },
+        )
+        .unwrap();
+        assert_eq!(outcome, TurnOutcome::AwaitingInput);
+
+        history.push(user_text("staging"));
+        let system = turn_system_prompt(Some("base"), true);
+        let outcome = run_agent_turn(
+            &llm, &mut history, system.as_deref(), &[], &executor, &mut auditor,
+            TurnState { connector: &connector, ctx: &turn_ctx("c1"), ages: &mut ages,
+                budget: &mut TokenBudget::new(None), transcript: &mut TurnTranscript::default() },
+        )
+        .unwrap();
+        assert_eq!(outcome, TurnOutcome::Completed);
+
+        // user → assistant question → user answer → assistant result
+        assert_eq!(history.len(), 4);
+        let roles: Vec<bool> = history.iter().map(|m| matches!(m.role, Role::User)).collect();
+        assert_eq!(roles, vec![true, false, true, false]);
+
+        let systems = llm.systems.borrow();
+        assert_eq!(systems[0].as_deref(), Some("base"));
+        assert!(systems[1].as_deref().unwrap().contains(CONTINUATION_HINT));
+        assert_eq!(
+            *connector.sent.borrow(),
+            vec!["Which environment should I deploy to?", "Deployed to staging."]
+        );
+    }
+
+    #[test]
+    fn test_echo_provider_runs_a_tool_end_to_end() {
+        let path = "/tmp/sentinel_test_echo_turn.txt";
+        std::fs::write(path, "hello from disk\n").unwrap();
+        let platform = HostPlatform::new(vec!["/tmp".into()], Vec::new(), Vec::new(), None);
+        let executor = ToolExecutor::new(&platform, 5);
+        let mut auditor = Auditor::new(&platform);
+        let connector = FakeConnector::default();
+        let tools = ToolExecutor::tool_definitions();
+
+        let mut history = vec![user_text(&format!("!read {}", path))];
+        let outcome = run_agent_turn(
+            &EchoProvider, &mut history, None, &tools, &executor, &mut auditor,
+            TurnState { connector: &connector, ctx: &turn_ctx("c1"), ages: &mut ToolResultAges::new(None),
+                budget: &mut TokenBudget::new(None), transcript: &mut TurnTranscript::default() },
+        )
+        .unwrap();
+        std::fs::remove_file(path).ok();
+
+        assert_eq!(outcome, TurnOutcome::Completed);
+        // user → read_file call → its result → the result echoed back
+        assert_eq!(history.len(), 4);
+        assert!(matches!(
+            &history[1].content[0],
+            ContentBlock::ToolUse { name, .. } if name == "read_file"
+        ));
+        assert!(connector.sent.borrow().last().unwrap().contains("hello from disk"));
+    }
+
+    #[test]
+    fn test_trim_history_keeps_tool_pairs() {
+        let tool_use = Message {
+            role: Role::Assistant,
+            content: vec![ContentBlock::ToolUse {
+                id: "t1".into(),
+                name: "read_file".into(),
+                input: json_obj().build(),
+            }],
+        };
+        let tool_result = Message {
+            role: Role::User,
+            content: vec![ContentBlock::ToolResult {
+                tool_use_id: "t1".into(),
+                content: "data".into(),
+                is_error: false,
+            }],
+        };
+        let mut history = vec![
+            user_text("q1"),
+            tool_use,
+            tool_result,
+            Message {
+                role: Role::Assistant,
+                content: vec![ContentBlock::Text { text: "a1".into() }],
+            },
+            user_text("q2"),
+        ];
+        // Only q2 fits; the cut must not start at the orphaned tool result
+        trim_history(&mut history, 1);
+        assert_eq!(history.len(), 1);
+        assert!(is_user_text(&history[0]));
+    }
+
+    #[test]
+    fn test_trim_history_by_estimated_tokens() {
+        let tool_pair = |id: &str, size: usize| {
+            vec![
+                Message {
+                    role: Role::Assistant,
+                    content: vec![ContentBlock::ToolUse {
+                        id: id.into(),
+                        name: "read_file".into(),
+                        input: json_obj().build(),
+                    }],
+                },
+                Message {
+                    role: Role::User,
+                    content: vec![ContentBlock::ToolResult {
+                        tool_use_id: id.into(),
+                        content: "x".repeat(size),
+                        is_error: false,
+                    }],
+                },
+            ]
+        };
+        let mut history = vec![user_text("q1")];
+        history.extend(tool_pair("big", 40_000));
+        history.push(user_text("q2"));
+        history.extend(tool_pair("small", 400));
+        history.push(user_text("q3"));
+
+        // Well under the limit: nothing dropped
+        let mut kept = history.clone();
+        trim_history(&mut kept, 20_000);
+        assert_eq!(kept.len(), history.len());
+
+        // The 10k-token file dump goes; the small pair survives intact
+        trim_history(&mut history, 1_000);
+        assert_eq!(history.len(), 4);
+        assert!(is_user_text(&history[0]));
+        assert!(matches!(&history[1].content[0], ContentBlock::ToolUse { id, .. } if id == "small"));
+        assert!(
+            matches!(&history[2].content[0], ContentBlock::ToolResult { tool_use_id, .. } if tool_use_id == "small")
+        );
+    }
+
+    #[test]
+    fn test_gated_tool_waits_for_approval() {
+        let platform = test_platform();
+        let policy = ApprovalPolicy {
+            tools: vec!["list_directory".into()],
+            ..ApprovalPolicy::default()
+        };
+        let executor = ToolExecutor::new(&platform, 5).with_approval(policy);
+        let mut auditor = Auditor::new(&platform);
+        let connector = FakeConnector::default();
+        let call = |id: &str, name: &str| ContentBlock::ToolUse {
+            id: id.into(),
+            name: name.into(),
+            input: json_obj().field_str("path", "/nonexistent").build(),
+        };
+        let llm = FakeProvider::new(vec![LlmResponse {
+            stop_reason: StopReason::ToolUse,
+            content: vec![call("a", "list_directory"), call("b", "read_file")],
+            usage_input: 0,
+            usage_output: 0,
+            usage_cache_creation: 0,
+            usage_cache_read: 0,
+        }]);
+
+        let mut history = vec![user_text("what's in there?")];
+        let mut ages = ToolResultAges::new(None);
+        let outcome = run_agent_turn(
+            &llm, &mut history, None, &[], &executor, &mut auditor,
+            TurnState { connector: &connector, ctx: &turn_ctx("c1"), ages: &mut ages,
+                budget: &mut TokenBudget::new(None), transcript: &mut TurnTranscript::default() },
+        )
+        .unwrap();
+
+        // Nothing ran; the user was shown the call and the turn is held
+        let pending = match outcome {
+            TurnOutcome::AwaitingApproval(p) => p,
+            other => panic!("expected AwaitingApproval, got {:?}", other),
+        };
+        assert_eq!(pending.call().unwrap().id, "a");
+        assert!(pending.batch.results.is_empty());
+        assert!(connector.sent.borrow().last().unwrap().starts_with("Approval needed to run list_directory:"));
+        assert!(matches!(history.last().unwrap().role, Role::Assistant));
+
+        // Denied: the gated call gets an error result, the rest still runs
+        let mut batch = pending.batch;
+        batch.decision = Some(Decision::Denied);
+        let results = match run_tool_calls(batch, &executor, &mut auditor, &mut ages, &connector, &turn_ctx("c1")) {
+            ToolRun::Done(r) => r,
+            ToolRun::NeedsApproval(_) => panic!("no further approval expected"),
+        };
+        assert_eq!(results.len(), 2);
+        assert!(matches!(
+            &results[0],
+            ContentBlock::ToolResult { tool_use_id, content, is_error: true }
+                if tool_use_id == "a" && content.contains("denied")
+        ));
+        assert!(matches!(&results[1], ContentBlock::ToolResult { tool_use_id, .. } if tool_use_id == "b"));
+    }
+
+    #[test]
+    fn test_approved_call_runs_and_next_gated_call_asks_again() {
+        let platform = test_platform();
+        let policy = ApprovalPolicy {
+            tools: vec!["list_directory".into()],
+            ..ApprovalPolicy::default()
+        };
+        let executor = ToolExecutor::new(&platform, 5).with_approval(policy);
+        let mut auditor = Auditor::new(&platform);
+        let connector = FakeConnector::default();
+        let mut ages = ToolResultAges::new(None);
+        let call = |id: &str| ContentBlock::ToolUse {
+            id: id.into(),
+            name: "list_directory".into(),
+            input: json_obj().field_str("path", "/nonexistent").build(),
+        };
+        let mut batch = ToolBatch::from_response(&[call("a"), call("b")]);
+        batch.decision = Some(Decision::Approved);
+
+        let pending = match run_tool_calls(batch, &executor, &mut auditor, &mut ages, &connector, &turn_ctx("c1")) {
+            ToolRun::NeedsApproval(p) => p,
+            ToolRun::Done(_) => panic!("second call should need approval"),
+        };
+        assert_eq!(pending.batch.results.len(), 1);
+        assert!(matches!(
+            &pending.batch.results[0],
+            ContentBlock::ToolResult { tool_use_id, content, .. }
+                if tool_use_id == "a" && !content.starts_with("not run")
+        ));
+        assert_eq!(pending.call().unwrap().id, "b");
+    }
+
+    #[test]
+    fn test_cancel_stops_tool_loop_with_valid_history() {
+        let platform = test_platform();
+        let flag = Arc::new(AtomicBool::new(false));
+        let executor = ToolExecutor::new(&platform, 5).with_cancel(Arc::clone(&flag));
+        let mut auditor = Auditor::new(&platform);
+        let connector = FakeConnector::default();
+        let mut ages = ToolResultAges::new(None);
+        let call = |id: &str| ContentBlock::ToolUse {
+            id: id.into(),
+            name: "list_directory".into(),
+            input: json_obj().field_str("path", "/nonexistent").build(),
+        };
+
+        // Raised mid-batch: the remaining calls get results without running
+        flag.store(true, Ordering::SeqCst);
+        let results = match run_tool_calls(
+            ToolBatch::from_response(&[call("a"), call("b")]),
+            &executor, &mut auditor, &mut ages, &connector, &turn_ctx("c1"),
+        ) {
+            ToolRun::Done(r) => r,
+            ToolRun::NeedsApproval(_) => panic!("nothing needs approval"),
+        };
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| matches!(
+            r,
+            ContentBlock::ToolResult { content, is_error: true, .. } if content.contains("cancelled")
+        )));
+
+        // The next round doesn't start
+        let llm = FakeProvider::new(vec![text_response("unused")]);
+        let mut history = vec![user_text("list everything")];
+        let outcome = run_agent_turn(
+            &llm, &mut history, None, &[], &executor, &mut auditor,
+            TurnState { connector: &connector, ctx: &turn_ctx("c1"), ages: &mut ages,
+                budget: &mut TokenBudget::new(None), transcript: &mut TurnTranscript::default() },
+        )
+        .unwrap();
+        assert_eq!(outcome, TurnOutcome::Cancelled);
+        assert!(llm.systems.borrow().is_empty());
+
+        record_cancellation(&mut history, Vec::new());
+        assert!(matches!(history.last().unwrap().role, Role::Assistant));
+        assert_eq!(extract_text(&history.last().unwrap().content), CANCELLED_REPLY);
+    }
+
+    #[test]
+    fn test_help_lists_commands_tools_and_allowed_commands() {
+        let defs = vec![tool("read_file"), tool("weather")];
+        let help = help_text(&defs, None, &["ls".to_string(), "date".to_string()], false);
+        assert!(help.starts_with("**Commands**\n`/help` — Show this message\n"));
+        assert!(help.contains("`/clear` — Start a new conversation"));
+        assert!(!help.contains("/setprompt"));
+        assert!(help.contains("\n**Tools**\n`read_file` — "));
+        assert!(help.contains("`weather` — "));
+        assert!(help.ends_with("**Allowed commands**\n`ls`, `date`"));
+
+        let help = help_text(&defs, None, &[], true);
+        assert!(help.contains("`/setprompt <text>` — "));
+        assert!(help.contains("`/version` — "));
+        assert!(help.ends_with("None: run_command is disabled."));
+    }
+
+    #[test]
+    fn test_help_uses_first_sentence_of_descriptions() {
+        assert_eq!(first_sentence("Read a file. Use offset to page."), "Read a file.");
+        assert_eq!(first_sentence("Run a command "), "Run a command");
+    }
+
+    #[test]
+    fn test_command_arg() {
+        assert_eq!(command_arg("/setprompt Be brief.", "/setprompt"), Some("Be brief."));
+        assert_eq!(command_arg(" /setprompt\nLine one\nLine two ", "/setprompt"), Some("Line one\nLine two"));
+        assert_eq!(command_arg("/setprompt", "/setprompt"), Some(""));
+        assert_eq!(command_arg("/setprompts x", "/setprompt"), None);
+        assert_eq!(command_arg("please /setprompt x", "/setprompt"), None);
+    }
+
+    #[test]
+    fn test_worker_for_is_stable_and_in_range() {
+        let keys: Vec<String> = (0..50).map(|n| conversation_key("telegram", &n.to_string())).collect();
+        for key in &keys {
+            let worker = worker_for(key, 4);
+            assert!(worker < 4);
+            assert_eq!(worker, worker_for(key, 4));
+            assert_eq!(worker_for(key, 1), 0);
+        }
+        // Conversations actually spread out
+        let used: HashSet<usize> = keys.iter().map(|k| worker_for(k, 4)).collect();
+        assert!(used.len() > 1);
+    }
+
+    #[test]
+    fn test_round_cap_produces_summary_handoff() {
+        let platform = test_platform();
+        let executor = ToolExecutor::new(&platform, 5);
+        let mut auditor = Auditor::new(&platform);
+        let connector = FakeConnector::default();
+
+        let mut responses: Vec<LlmResponse> = (0..MAX_TOOL_ROUNDS)
+            .map(|i| LlmResponse {
+                stop_reason: StopReason::ToolUse,
+                content: vec![ContentBlock::ToolUse {
+                    id: format!("call_{}", i),
+                    name: "list_directory".into(),
+                    input: json_obj().field_str("path", "/nonexistent").build(),
+                }],
+                usage_input: 0,
+                usage_output: 0,
+                usage_cache_creation: 0,
+                usage_cache_read: 0,
+            })
+            .collect();
+        responses.push(text_response("Listed 10 directories; 3 remain."));
+        let llm = FakeProvider::new(responses);
+
+        let mut history = vec![user_text("survey every directory")];
+        let mut ages = ToolResultAges::new(None);
+        let outcome = run_agent_turn(
+            &llm, &mut history, None, &[], &executor, &mut auditor,
+            TurnState { connector: &connector, ctx: &turn_ctx("c1"), ages: &mut ages,
+                budget: &mut TokenBudget::new(None), transcript: &mut TurnTranscript::default() },
+        )
+        .unwrap();
+        assert_eq!(outcome, TurnOutcome::Completed);
+
+        // The final request carried the handoff instruction
+        let systems = llm.systems.borrow();
+        assert_eq!(systems.len(), MAX_TOOL_ROUNDS + 1);
+        assert!(systems.last().unwrap().as_deref().unwrap().contains(HANDOFF_INSTRUCTION));
+
+        // The summary is sent and persisted as the last assistant message
+        let sent = connector.sent.borrow();
+        assert!(sent.last().unwrap().starts_with("Listed 10 directories; 3 remain."));
+        assert!(sent.last().unwrap().contains("continue"));
+        let last = history.last().unwrap();
+        assert!(matches!(last.role, Role::Assistant));
+        assert_eq!(extract_text(&last.content), "Listed 10 directories; 3 remain.");
+    }
+
+    #[test]
+    fn test_transcript_records_each_round_and_tool_result() {
+        let platform = test_platform();
+        let executor = ToolExecutor::new(&platform, 5);
+        let mut auditor = Auditor::new(&platform);
+        let connector = FakeConnector::default();
+        let llm = FakeProvider::new(vec![
+            LlmResponse {
+                stop_reason: StopReason::ToolUse,
+                content: vec![
+                    ContentBlock::Text { text: "Let me look.".into() },
+                    ContentBlock::ToolUse {
+                        id: "call_1".into(),
+                        name: "list_directory".into(),
+                        input: json_obj().field_str("path", "/nonexistent").build(),
+                    },
+                ],
+                usage_input: 50,
+                usage_output: 10,
+                usage_cache_creation: 0,
+                usage_cache_read: 0,
+            },
+            text_response("That directory isn't accessible."),
+        ]);
+
+        let mut history = vec![user_text("what's in /nonexistent?")];
+        let mut ages = ToolResultAges::new(None);
+        let mut transcript = TurnTranscript::default();
+        run_agent_turn(
+            &llm, &mut history, None, &[], &executor, &mut auditor,
+            TurnState { connector: &connector, ctx: &turn_ctx("c1"), ages: &mut ages,
+                budget: &mut TokenBudget::new(None), transcript: &mut transcript },
+        )
+        .unwrap();
+
+        assert_eq!(transcript.rounds.len(), 2);
+        let first = &transcript.rounds[0];
+        assert_eq!(first.text, "Let me look.");
+        assert_eq!(first.usage_input, 50);
+        assert_eq!(first.tool_calls.len(), 1);
+        let call = &first.tool_calls[0];
+        assert_eq!(call.name, "list_directory");
+        assert_eq!(call.input.get_str("path"), Some("/nonexistent"));
+        assert!(call.output.is_some());
+        assert!(call.is_error);
+        let second = &transcript.rounds[1];
+        assert_eq!(second.text, "That directory isn't accessible.");
+        assert!(second.tool_calls.is_empty());
+    }
+
+    #[test]
+    fn test_budget_stops_turn_between_rounds() {
+        let platform = test_platform();
+        let executor = ToolExecutor::new(&platform, 5);
+        let mut auditor = Auditor::new(&platform);
+        let connector = FakeConnector::default();
+        let llm = FakeProvider::new(vec![
+            LlmResponse {
+                stop_reason: StopReason::ToolUse,
+                content: vec![ContentBlock::ToolUse {
+                    id: "call_1".into(),
+                    name: "list_directory".into(),
+                    input: json_obj().field_str("path", "/nonexistent").build(),
+                }],
+                usage_input: 80,
+                usage_output: 30,
+                usage_cache_creation: 0,
+                usage_cache_read: 0,
+            },
+            text_response("never requested"),
+        ]);
+
+        let mut history = vec![user_text("list it")];
+        let mut ages = ToolResultAges::new(None);
+        let mut budget = TokenBudget::new(Some(100));
+        let outcome = run_agent_turn(
+            &llm, &mut history, None, &[], &executor, &mut auditor,
+            TurnState { connector: &connector, ctx: &turn_ctx("c1"), ages: &mut ages,
+                budget: &mut budget, transcript: &mut TurnTranscript::default() },
+        )
+        .unwrap();
+
+        assert_eq!(outcome, TurnOutcome::BudgetExhausted);
+        assert_eq!(llm.systems.borrow().len(), 1);
+        assert_eq!(budget.total(), 110);
+        // The tool call still got its result, so the history stays valid
+        let last = history.last().unwrap();
+        assert!(matches!(last.content[0], ContentBlock::ToolResult { .. }));
+    }
+
+    #[test]
+    fn test_extract_text_separates_text_around_tool_calls() {
+        let text = |t: &str| ContentBlock::Text { text: t.into() };
+        let call = ContentBlock::ToolUse {
+            id: "t1".into(),
+            name: "read_file".into(),
+            input: json_obj().build(),
+        };
+
+        let content = [text("Let me check. "), call.clone(), text("Here's the answer.")];
+        assert_eq!(extract_text(&content), "Let me check.\n\nHere's the answer.");
+
+        // Adjacent blocks are one passage
+        let content = [text("The answer is "), text("42."), call.clone()];
+        assert_eq!(extract_text(&content), "The answer is 42.");
+
+        // Leading tool calls and blank blocks add no separators
+        let content = [call.clone(), text("\n"), call, text("Done.")];
+        assert_eq!(extract_text(&content), "Done.");
+        assert_eq!(extract_text(&[]), "(no text response)");
+
+        // Reasoning never reaches the user
+        let thinking = ContentBlock::Thinking {
+            text: "secret plan".into(),
+            signature: "s".into(),
+        };
+        assert_eq!(extract_text(&[thinking, text("Answer.")]), "Answer.");
+    }
+
+    #[test]
+    fn test_failed_turn_still_updates_ack() {
+        let platform = test_platform();
+        let executor = ToolExecutor::new(&platform, 5);
+        let mut auditor = Auditor::new(&platform);
+        let connector = FakeConnector::default();
+        let mut ages = ToolResultAges::new(None);
+
+        // No canned responses: the provider call fails
+        let llm = FakeProvider::new(vec![]);
+        let mut history = vec![user_text("hello")];
+        let result = with_ack(&connector, true, "c1", "m1", || {
+            run_agent_turn(
+                &llm, &mut history, None, &[], &executor, &mut auditor,
+                TurnState { connector: &connector, ctx: &turn_ctx("c1"), ages: &mut ages,
+                    budget: &mut TokenBudget::new(None), transcript: &mut TurnTranscript::default() },
+            )
+        });
+        assert!(result.is_err());
+
+        let llm = FakeProvider::new(vec![text_response("Hi!")]);
+        let mut history = vec![user_text("hello")];
+        with_ack(&connector, true, "c1", "m2", || {
+            run_agent_turn(
+                &llm, &mut history, None, &[], &executor, &mut auditor,
+                TurnState { connector: &connector, ctx: &turn_ctx("c1"), ages: &mut ages,
+                    budget: &mut TokenBudget::new(None), transcript: &mut TurnTranscript::default() },
+            )
+        })
+        .unwrap();
+
+        assert_eq!(
+            *connector.acks.borrow(),
+            vec!["m1:received", "m1:failed", "m2:received", "m2:ok"]
+        );
+
+        // Disabled: no reactions at all
+        let _ = with_ack(&connector, false, "c1", "m3", || Ok::<_, ()>(()));
+        assert_eq!(connector.acks.borrow().len(), 4);
+    }
+
+    #[test]
+    fn test_api_calls_are_audited() {
+        let sink = AuditSink::new(10, 10);
+        let hook = audited_api_calls(Rc::new(test_platform()), Some(sink.clone()));
+        let endpoint = "https://api.anthropic.com/v1/messages";
+
+        let mut resp = text_response("Hi!");
+        resp.usage_input = 120;
+        resp.usage_output = 7;
+        let retried = RetryLog::default();
+        report_call(Some(&hook), endpoint, &retried, || Ok((200, resp))).unwrap();
+        let _ = report_call(Some(&hook), endpoint, &retried, || {
+            Err(LlmError::Api { status: 529, message: "Overloaded".into() })
+        });
+
+        let mut sent = String::new();
+        sink.flush(Duration::ZERO, &mut |body| {
+            sent = body.to_string();
+            Ok(())
+        })
+        .unwrap();
+        let events = json::parse(&sent).unwrap();
+        let events = events.as_array().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].get_str("event"), Some("api_call"));
+        assert_eq!(events[0].get_str("endpoint"), Some(endpoint));
+        assert_eq!(events[0].get("status").and_then(|v| v.as_i64()), Some(200));
+        assert_eq!(events[0].get("tokens_in").and_then(|v| v.as_i64()), Some(120));
+        assert_eq!(events[0].get("tokens_out").and_then(|v| v.as_i64()), Some(7));
+        assert!(events[0].get("latency_ms").is_some());
+        assert_eq!(events[1].get("status").and_then(|v| v.as_i64()), Some(529));
+        assert_eq!(events[1].get("tokens_in").and_then(|v| v.as_i64()), Some(0));
     }
 }
@@ -1,23 +1,86 @@
 use std::collections::HashMap;
 use std::env;
 use std::fs;
+use std::sync::{Arc, RwLock};
+
+use crate::agent::tools::{DEFAULT_MAX_TOOL_OUTPUT, DEFAULT_OUTPUT_LIMIT};
+use crate::net::http::{url_host, DEFAULT_MAX_RESPONSE_BYTES};
+use crate::net::json;
+use crate::platform::linux::CommandLimits;
+use crate::security::capability::{glob_base, split_port, NetworkAllowlist};
+use crate::skills::loader::check_skills;
 
 // ── Config struct ───────────────────────────────────────────────────────────
 
+#[derive(Clone)]
 pub struct Config {
     pub provider: String,
     pub api_key: String,
     pub model: String,
     pub max_tokens: u32,
+    /// The OpenAI-compatible API; for the "azure" provider, the deployment
+    /// URL from `[azure] base_url`, query string included.
     pub openai_base_url: String,
+    /// "standard" or "reasoning" to override the request shape guessed
+    /// from the model name (OpenAI only).
+    pub openai_model_family: Option<String>,
+    /// Extra headers sent with every provider request, e.g. for gateways.
+    pub anthropic_extra_headers: Vec<(String, String)>,
+    pub openai_extra_headers: Vec<(String, String)>,
+    /// Cache the system prompt and tool definitions (Anthropic only).
+    pub anthropic_prompt_cache: bool,
+    /// Models tried in order when the primary is overloaded (Anthropic only).
+    pub model_fallbacks: Vec<String>,
     pub system_prompt: Option<String>,
+    /// Prompts for single conversations, by "platform:channel_id"
+    /// (`[system_prompts]`); they replace `system_prompt` there.
+    pub system_prompts: Vec<(String, String)>,
+    /// Directory runtime state such as /setprompt overrides is saved in.
+    pub state_dir: Option<String>,
+    pub greeting: Option<String>,
+    pub unauthorized_message: Option<String>,
+    pub admin_users: Vec<String>,
+    /// Seconds after which read-only tool results are marked possibly stale.
+    pub tool_result_ttl: Option<u64>,
+    /// Allow the model to request several tool calls in one response.
+    pub parallel_tool_calls: bool,
+    /// React to messages while a turn runs (👀), then with the outcome.
+    pub ack_reactions: bool,
+    /// Ignore group messages that don't mention or reply to the bot.
+    pub respond_only_when_mentioned: bool,
+    /// Address to serve Prometheus metrics on; unset disables it.
+    pub metrics_bind: Option<String>,
+    /// "text" (default) for stderr lines or "json" for JSON lines on stdout.
+    pub log_format: String,
+    /// Input plus output tokens a conversation may use before /clear.
+    pub max_tokens_per_conversation: Option<u64>,
+    /// Estimated tokens of history kept between turns; older exchanges are
+    /// dropped first.
+    pub max_context_tokens: usize,
+    /// Conversations whose turns may run at the same time.
+    pub max_concurrent_chats: usize,
+    /// Bytes of a tool result kept before it is cut.
+    pub max_tool_output_bytes: usize,
+    /// Sampling settings; unset means the provider's default.
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub stop_sequences: Vec<String>,
     // Telegram
     pub telegram_token: Option<String>,
     pub telegram_allowed_users: Vec<i64>,
+    /// Address to accept webhook updates on; unset means long polling.
+    pub telegram_webhook_bind: Option<String>,
+    pub telegram_webhook_path: String,
+    /// Public HTTPS URL registered with Telegram for the webhook.
+    pub telegram_webhook_url: Option<String>,
+    /// "MarkdownV2" or "HTML" to render formatting; unset sends plain text.
+    pub telegram_parse_mode: Option<String>,
     // Discord
     pub discord_token: Option<String>,
     pub discord_channel_ids: Vec<String>,
     pub discord_allowed_users: Vec<String>,
+    /// Receive over the gateway WebSocket rather than REST polling.
+    pub discord_gateway: bool,
     // Slack
     pub slack_bot_token: Option<String>,
     pub slack_channel_ids: Vec<String>,
@@ -25,11 +88,79 @@ pub struct Config {
     // Security
     pub allowed_read_paths: Vec<String>,
     pub allowed_write_paths: Vec<String>,
+    /// Paths refused even under an allowed one; these win.
+    pub denied_read_paths: Vec<String>,
+    pub denied_write_paths: Vec<String>,
     pub allowed_commands: Vec<String>,
+    /// Argument patterns each listed command is limited to, and arguments
+    /// it may never take (`[security.allowed_args]`, `[security.denied_args]`).
+    pub allowed_args: Vec<(String, Vec<String>)>,
+    pub denied_args: Vec<(String, Vec<String>)>,
+    /// Hosts the http_fetch tool may request; empty disables it.
+    pub allowed_fetch_hosts: Vec<String>,
+    /// Hosts, `*.domain` wildcards, and IP ranges the agent may connect to
+    /// besides its configured endpoints. Empty allows any public host;
+    /// internal addresses are refused unless listed either way.
+    pub allowed_network_hosts: Vec<String>,
     pub command_timeout: u64,
+    /// Bytes of command output returned to the model.
+    pub max_command_output: usize,
+    /// Resource limits on commands (Linux): CPU seconds, address space in
+    /// MB, and processes for the agent's user.
+    pub max_command_cpu_secs: Option<u64>,
+    pub max_command_memory_mb: Option<u64>,
+    pub max_command_processes: Option<u64>,
+    /// Confine each command with its own landlock ruleset (Linux).
+    pub sandbox_commands: bool,
     pub audit_log_path: Option<String>,
+    /// Size at which the audit log is rotated; 0 never rotates.
+    pub audit_max_bytes: u64,
+    /// Rotated audit logs kept (`name.1` is the newest).
+    pub audit_keep_files: u32,
+    /// Mask secrets in logged tool parameters.
+    pub audit_redact: bool,
+    /// HTTPS endpoint that audit events are also POSTed to, in batches.
+    pub audit_webhook_url: Option<String>,
+    /// Events per webhook POST; a partial batch is sent after the interval.
+    pub audit_webhook_batch: usize,
+    pub audit_webhook_interval: u64,
+    /// Tools that only run after the user replies /approve.
+    pub require_approval_for: Vec<String>,
+    /// Seconds an approval request stays open before the call is dropped.
+    pub approval_timeout: u64,
     pub sandbox: bool,
+    /// "enforce" (default) or "log": whether seccomp blocks syscalls outside
+    /// its allowlist or only has the kernel log them.
+    pub seccomp_mode: String,
     pub skills_dir: Option<String>,
+    pub skill_max_concurrent: usize,
+    /// Persistent skill processes running at once, counted apart from
+    /// `skill_max_concurrent`.
+    pub skill_max_persistent: usize,
+    /// "queue" or "reject" once either limit's processes are running.
+    pub skill_concurrency_policy: String,
+    // Network
+    pub compress_requests: bool,
+    pub http_pool_max_per_host: usize,
+    pub http_pool_idle_timeout: u64,
+    pub http_connect_timeout: u64,
+    pub http_read_timeout: u64,
+    pub http_write_timeout: u64,
+    /// Largest HTTP response body accepted.
+    pub http_max_response_bytes: usize,
+    /// Hosts outbound HTTPS may reach; empty allows any.
+    pub allowed_egress_hosts: Vec<String>,
+    /// Retries of transient provider failures (connection errors, 5xx).
+    pub max_retries: u32,
+    pub retry_base_delay_ms: u64,
+    /// Extra PEM root certificates trusted for the LLM provider.
+    pub tls_ca_file: Option<String>,
+    /// Skip LLM provider certificate verification (local testing only).
+    pub tls_insecure_skip_verify: bool,
+    /// The TOML file this config was read from, re-read on SIGHUP.
+    pub config_file: Option<String>,
+    /// Files the API key and bot tokens were read from, re-read on SIGHUP.
+    pub secret_files: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -46,7 +177,10 @@ impl std::fmt::Display for ConfigError {
 impl Config {
     pub fn load() -> Result<Self, ConfigError> {
         // Try loading TOML file
-        let toml = try_load_toml();
+        let (config_file, toml) = match try_load_toml()? {
+            Some((path, doc)) => (Some(path), Some(doc)),
+            None => (None, None),
+        };
 
         let get_str = |section: &str, key: &str, env_key: &str| -> Option<String> {
             // Check env var first
@@ -90,31 +224,88 @@ impl Config {
             Vec::new()
         };
 
-        // Provider selection: "anthropic" (default) or "openai"
+        let get_str_list_map = |section: &str, env_key: &str| -> Vec<(String, Vec<String>)> {
+            // Env var: comma-separated name=item item pairs
+            if let Ok(val) = env::var(env_key) {
+                return val
+                    .split(',')
+                    .filter_map(|pair| {
+                        let (k, v) = pair.split_once('=')?;
+                        let k = k.trim();
+                        let items = v.split_whitespace().map(String::from).collect();
+                        (!k.is_empty()).then(|| (k.to_string(), items))
+                    })
+                    .collect();
+            }
+            if let Some(ref t) = toml {
+                if let Some(pairs) = t.get_str_list_map(section) {
+                    return pairs;
+                }
+            }
+            Vec::new()
+        };
+
+        let get_str_map = |section: &str, env_key: &str| -> Vec<(String, String)> {
+            // Env var: comma-separated Name=value pairs
+            if let Ok(val) = env::var(env_key) {
+                return val
+                    .split(',')
+                    .filter_map(|pair| {
+                        let (k, v) = pair.split_once('=')?;
+                        let k = k.trim();
+                        (!k.is_empty()).then(|| (k.to_string(), v.trim().to_string()))
+                    })
+                    .collect();
+            }
+            if let Some(ref t) = toml {
+                if let Some(pairs) = t.get_str_map(section) {
+                    return pairs;
+                }
+            }
+            Vec::new()
+        };
+
+        // Provider selection: "anthropic" (default), "openai", "azure", "gemini",
+        // or "echo" (offline, for development)
         let provider = get_str("agent", "provider", "SENTINEL_PROVIDER")
             .unwrap_or_else(|| "anthropic".to_string());
 
+        // Files secrets were read from, re-read on SIGHUP
+        let mut secret_files = Vec::new();
         // API key: try provider-specific env first, then fall back
         let api_key = if provider == "openai" {
-            resolve_secret(&toml, "openai", "api_key_env", "OPENAI_API_KEY")
-                .or_else(|| resolve_secret(&toml, "anthropic", "api_key_env", "ANTHROPIC_API_KEY"))
-                .ok_or_else(|| ConfigError("OPENAI_API_KEY not set".into()))?
+            match resolve_secret(&toml, "openai", "api_key_env", "OPENAI_API_KEY", &mut secret_files)? {
+                Some(key) => Some(key),
+                None => resolve_secret(&toml, "anthropic", "api_key_env", "ANTHROPIC_API_KEY", &mut secret_files)?,
+            }
+            .ok_or_else(|| ConfigError("OPENAI_API_KEY not set".into()))?
+        } else if provider == "azure" {
+            resolve_secret(&toml, "azure", "api_key_env", "AZURE_OPENAI_API_KEY", &mut secret_files)?
+                .ok_or_else(|| ConfigError("AZURE_OPENAI_API_KEY not set".into()))?
+        } else if provider == "gemini" {
+            resolve_secret(&toml, "gemini", "api_key_env", "GEMINI_API_KEY", &mut secret_files)?
+                .ok_or_else(|| ConfigError("GEMINI_API_KEY not set".into()))?
+        } else if provider == "echo" {
+            String::new()
         } else {
-            resolve_secret(&toml, "anthropic", "api_key_env", "ANTHROPIC_API_KEY")
+            resolve_secret(&toml, "anthropic", "api_key_env", "ANTHROPIC_API_KEY", &mut secret_files)?
                 .ok_or_else(|| ConfigError("ANTHROPIC_API_KEY not set".into()))?
         };
 
-        let telegram_token = resolve_secret(&toml, "telegram", "token_env", "TELEGRAM_BOT_TOKEN");
+        let telegram_token = resolve_secret(&toml, "telegram", "token_env", "TELEGRAM_BOT_TOKEN", &mut secret_files)?;
 
         // Discord config
-        let discord_token = resolve_secret(&toml, "discord", "token_env", "DISCORD_BOT_TOKEN");
+        let discord_token = resolve_secret(&toml, "discord", "token_env", "DISCORD_BOT_TOKEN", &mut secret_files)?;
         let discord_channel_ids =
             get_str_list("discord", "channel_ids", "DISCORD_CHANNEL_IDS");
         let discord_allowed_users =
             get_str_list("discord", "allowed_users", "DISCORD_ALLOWED_USERS");
+        let discord_gateway = get_str("discord", "gateway", "SENTINEL_DISCORD_GATEWAY")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
 
         // Slack config
-        let slack_bot_token = resolve_secret(&toml, "slack", "bot_token_env", "SLACK_BOT_TOKEN");
+        let slack_bot_token = resolve_secret(&toml, "slack", "bot_token_env", "SLACK_BOT_TOKEN", &mut secret_files)?;
         let slack_channel_ids =
             get_str_list("slack", "channel_ids", "SLACK_CHANNEL_IDS");
         let slack_allowed_users =
@@ -127,42 +318,269 @@ impl Config {
             ));
         }
 
-        let default_model = if provider == "openai" {
+        let default_model = if provider == "openai" || provider == "azure" {
             "gpt-4o".to_string()
+        } else if provider == "gemini" {
+            "gemini-2.0-flash".to_string()
+        } else if provider == "echo" {
+            "echo".to_string()
         } else {
             "claude-sonnet-4-5-20250929".to_string()
         };
-        let model = get_str("anthropic", "model", "SENTINEL_MODEL")
-            .or_else(|| get_str("openai", "model", "SENTINEL_MODEL"))
-            .unwrap_or(default_model);
+        // The example config sets an Anthropic model, which Gemini can't use
+        let model = if provider == "gemini" {
+            get_str("gemini", "model", "SENTINEL_MODEL")
+        } else if provider == "azure" {
+            get_str("azure", "model", "SENTINEL_MODEL")
+        } else if provider == "echo" {
+            None
+        } else {
+            get_str("anthropic", "model", "SENTINEL_MODEL")
+                .or_else(|| get_str("openai", "model", "SENTINEL_MODEL"))
+        }
+        .unwrap_or(default_model);
 
         let max_tokens = get_str("anthropic", "max_tokens", "SENTINEL_MAX_TOKENS")
             .or_else(|| get_str("openai", "max_tokens", "SENTINEL_MAX_TOKENS"))
+            .or_else(|| get_str("gemini", "max_tokens", "SENTINEL_MAX_TOKENS"))
+            .or_else(|| get_str("azure", "max_tokens", "SENTINEL_MAX_TOKENS"))
             .and_then(|s| s.parse::<u32>().ok())
             .unwrap_or(4096);
 
-        let openai_base_url = get_str("openai", "base_url", "OPENAI_BASE_URL")
-            .unwrap_or_else(|| "https://api.openai.com/v1".to_string());
+        let openai_base_url = if provider == "azure" {
+            get_str("azure", "base_url", "AZURE_OPENAI_BASE_URL")
+                .ok_or_else(|| ConfigError("AZURE_OPENAI_BASE_URL not set".into()))?
+        } else {
+            get_str("openai", "base_url", "OPENAI_BASE_URL")
+                .unwrap_or_else(|| "https://api.openai.com/v1".to_string())
+        };
+
+        let openai_model_family =
+            get_str("openai", "model_family", "SENTINEL_OPENAI_MODEL_FAMILY");
+
+        let anthropic_extra_headers =
+            get_str_map("anthropic.extra_headers", "SENTINEL_ANTHROPIC_EXTRA_HEADERS");
+        let openai_extra_headers =
+            get_str_map("openai.extra_headers", "SENTINEL_OPENAI_EXTRA_HEADERS");
+        let model_fallbacks =
+            get_str_list("anthropic", "model_fallbacks", "SENTINEL_MODEL_FALLBACKS");
+        let anthropic_prompt_cache =
+            get_str("anthropic", "prompt_cache", "SENTINEL_ANTHROPIC_PROMPT_CACHE")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false);
 
         let system_prompt = get_str("agent", "system_prompt", "SENTINEL_SYSTEM_PROMPT");
+        // Env var: a JSON object, since prompts hold commas and '='
+        let system_prompts = match env::var("SENTINEL_SYSTEM_PROMPTS") {
+            Ok(val) => parse_prompt_map(&val)
+                .map_err(|e| ConfigError(format!("SENTINEL_SYSTEM_PROMPTS: {}", e)))?,
+            Err(_) => toml
+                .as_ref()
+                .and_then(|t| t.get_str_map("system_prompts"))
+                .unwrap_or_default(),
+        };
+        let state_dir = get_str("agent", "state_dir", "SENTINEL_STATE_DIR");
+        let greeting = get_str("agent", "greeting", "SENTINEL_GREETING");
+        let unauthorized_message =
+            get_str("agent", "unauthorized_message", "SENTINEL_UNAUTHORIZED_MESSAGE");
+        // Admins as "platform:user_id" (e.g. "telegram:123456789")
+        let admin_users = get_str_list("agent", "admin_users", "SENTINEL_ADMIN_USERS");
+        let parallel_tool_calls =
+            get_str("agent", "parallel_tool_calls", "SENTINEL_PARALLEL_TOOL_CALLS")
+                .map(|v| v != "false" && v != "0")
+                .unwrap_or(true);
+        let ack_reactions = get_str("agent", "ack_reactions", "SENTINEL_ACK_REACTIONS")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+        let respond_only_when_mentioned = get_str(
+            "agent",
+            "respond_only_when_mentioned",
+            "SENTINEL_RESPOND_ONLY_WHEN_MENTIONED",
+        )
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+        let metrics_bind = get_str("agent", "metrics_bind", "SENTINEL_METRICS_BIND");
+        let log_format = get_str("agent", "log_format", "SENTINEL_LOG_FORMAT")
+            .unwrap_or_else(|| "text".to_string());
+        let tool_result_ttl = get_str("agent", "tool_result_ttl", "SENTINEL_TOOL_RESULT_TTL")
+            .and_then(|s| s.parse::<u64>().ok());
+        let max_tokens_per_conversation = get_str(
+            "agent",
+            "max_tokens_per_conversation",
+            "SENTINEL_MAX_TOKENS_PER_CONVERSATION",
+        )
+        .and_then(|s| s.parse::<u64>().ok());
+        let max_context_tokens =
+            get_str("agent", "max_context_tokens", "SENTINEL_MAX_CONTEXT_TOKENS")
+                .and_then(|s| s.parse::<usize>().ok())
+                .unwrap_or(100_000);
+        let max_concurrent_chats =
+            get_str("agent", "max_concurrent_chats", "SENTINEL_MAX_CONCURRENT_CHATS")
+                .and_then(|s| s.parse::<usize>().ok())
+                .unwrap_or(4);
+        let max_tool_output_bytes =
+            get_str("agent", "max_tool_output_bytes", "SENTINEL_MAX_TOOL_OUTPUT_BYTES")
+                .and_then(|s| s.parse::<usize>().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or(DEFAULT_MAX_TOOL_OUTPUT);
+        let temperature = get_str("agent", "temperature", "SENTINEL_TEMPERATURE")
+            .and_then(|s| s.parse::<f64>().ok());
+        let top_p = get_str("agent", "top_p", "SENTINEL_TOP_P").and_then(|s| s.parse::<f64>().ok());
+        let stop_sequences = get_str_list("agent", "stop_sequences", "SENTINEL_STOP_SEQUENCES");
 
         let telegram_allowed_users =
             get_i64_list("telegram", "allowed_users", "SENTINEL_ALLOWED_USERS");
+        let telegram_webhook_bind =
+            get_str("telegram", "webhook_bind", "SENTINEL_TELEGRAM_WEBHOOK_BIND");
+        let telegram_webhook_path =
+            get_str("telegram", "webhook_path", "SENTINEL_TELEGRAM_WEBHOOK_PATH")
+                .unwrap_or_else(|| "/telegram".to_string());
+        let telegram_webhook_url =
+            get_str("telegram", "webhook_url", "SENTINEL_TELEGRAM_WEBHOOK_URL");
+        let telegram_parse_mode =
+            get_str("telegram", "parse_mode", "SENTINEL_TELEGRAM_PARSE_MODE");
+        if telegram_webhook_bind.is_some() && telegram_webhook_url.is_none() {
+            return Err(ConfigError(
+                "telegram webhook_bind is set but webhook_url is not".into(),
+            ));
+        }
 
         let allowed_read_paths =
             get_str_list("security", "allowed_read_paths", "SENTINEL_READ_PATHS");
         let allowed_write_paths =
             get_str_list("security", "allowed_write_paths", "SENTINEL_WRITE_PATHS");
+        check_glob_roots("allowed_read_paths", &allowed_read_paths)?;
+        check_glob_roots("allowed_write_paths", &allowed_write_paths)?;
+        let denied_read_paths =
+            get_str_list("security", "denied_read_paths", "SENTINEL_DENIED_READ_PATHS");
+        let denied_write_paths =
+            get_str_list("security", "denied_write_paths", "SENTINEL_DENIED_WRITE_PATHS");
         let allowed_commands =
             get_str_list("security", "allowed_commands", "SENTINEL_COMMANDS");
+        let allowed_args = get_str_list_map("security.allowed_args", "SENTINEL_ALLOWED_ARGS");
+        let denied_args = get_str_list_map("security.denied_args", "SENTINEL_DENIED_ARGS");
+        let allowed_fetch_hosts =
+            get_str_list("security", "allowed_fetch_hosts", "SENTINEL_FETCH_HOSTS");
+        let allowed_network_hosts =
+            get_str_list("security", "allowed_network_hosts", "SENTINEL_NETWORK_HOSTS");
 
         let command_timeout = get_str("security", "command_timeout", "SENTINEL_COMMAND_TIMEOUT")
             .and_then(|s| s.parse::<u64>().ok())
             .unwrap_or(30);
+        let max_command_output =
+            get_str("security", "max_command_output", "SENTINEL_MAX_COMMAND_OUTPUT")
+                .and_then(|s| s.parse::<usize>().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or(DEFAULT_OUTPUT_LIMIT);
+        let command_limit = |key: &str, env: &str| {
+            get_str("security", key, env)
+                .and_then(|s| s.parse::<u64>().ok())
+                .filter(|&n| n > 0)
+        };
+        let max_command_cpu_secs =
+            command_limit("max_command_cpu_secs", "SENTINEL_MAX_COMMAND_CPU_SECS");
+        let max_command_memory_mb =
+            command_limit("max_command_memory_mb", "SENTINEL_MAX_COMMAND_MEMORY_MB");
+        let max_command_processes =
+            command_limit("max_command_processes", "SENTINEL_MAX_COMMAND_PROCESSES");
+        let sandbox_commands = get_str("security", "sandbox_commands", "SENTINEL_SANDBOX_COMMANDS")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
 
         let audit_log_path = get_str("security", "audit_log_path", "SENTINEL_AUDIT_LOG");
+        let audit_max_bytes =
+            get_str("security", "audit_max_bytes", "SENTINEL_AUDIT_MAX_BYTES")
+                .and_then(|s| s.parse::<u64>().ok())
+                .unwrap_or(0);
+        let audit_keep_files =
+            get_str("security", "audit_keep_files", "SENTINEL_AUDIT_KEEP_FILES")
+                .and_then(|s| s.parse::<u32>().ok())
+                .unwrap_or(5);
+        let audit_redact = get_str("security", "audit_redact", "SENTINEL_AUDIT_REDACT")
+            .map(|v| v != "false" && v != "0")
+            .unwrap_or(true);
+        let audit_webhook_url =
+            get_str("security", "audit_webhook_url", "SENTINEL_AUDIT_WEBHOOK_URL");
+        let audit_webhook_batch =
+            get_str("security", "audit_webhook_batch", "SENTINEL_AUDIT_WEBHOOK_BATCH")
+                .and_then(|s| s.parse::<usize>().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or(50);
+        let audit_webhook_interval =
+            get_str("security", "audit_webhook_interval", "SENTINEL_AUDIT_WEBHOOK_INTERVAL")
+                .and_then(|s| s.parse::<u64>().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or(5);
+
+        let require_approval_for = get_str_list(
+            "security",
+            "require_approval_for",
+            "SENTINEL_REQUIRE_APPROVAL_FOR",
+        );
+        let approval_timeout =
+            get_str("security", "approval_timeout", "SENTINEL_APPROVAL_TIMEOUT")
+                .and_then(|s| s.parse::<u64>().ok())
+                .unwrap_or(300);
 
         let skills_dir = get_str("skills", "directory", "SENTINEL_SKILLS_DIR");
+        let skill_max_concurrent =
+            get_str("skills", "max_concurrent", "SENTINEL_SKILL_MAX_CONCURRENT")
+                .and_then(|s| s.parse::<usize>().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or(4);
+        let skill_max_persistent =
+            get_str("skills", "max_persistent", "SENTINEL_SKILL_MAX_PERSISTENT")
+                .and_then(|s| s.parse::<usize>().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or(4);
+        let skill_concurrency_policy =
+            get_str("skills", "concurrency_policy", "SENTINEL_SKILL_CONCURRENCY_POLICY")
+                .unwrap_or_else(|| "queue".to_string());
+
+        let compress_requests = get_str("net", "compress_requests", "SENTINEL_COMPRESS_REQUESTS")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        let http_pool_max_per_host =
+            get_str("net", "pool_max_per_host", "SENTINEL_HTTP_POOL_MAX_PER_HOST")
+                .and_then(|s| s.parse::<usize>().ok())
+                .unwrap_or(4);
+        let http_pool_idle_timeout =
+            get_str("net", "pool_idle_timeout", "SENTINEL_HTTP_POOL_IDLE_TIMEOUT")
+                .and_then(|s| s.parse::<u64>().ok())
+                .unwrap_or(90);
+
+        let http_timeout = |key: &str, env_key: &str| {
+            get_str("net", key, env_key)
+                .and_then(|s| s.parse::<u64>().ok())
+                .filter(|&t| t > 0)
+                .unwrap_or(30)
+        };
+        let http_connect_timeout = http_timeout("connect_timeout", "SENTINEL_HTTP_CONNECT_TIMEOUT");
+        let http_read_timeout = http_timeout("read_timeout", "SENTINEL_HTTP_READ_TIMEOUT");
+        let http_write_timeout = http_timeout("write_timeout", "SENTINEL_HTTP_WRITE_TIMEOUT");
+        let http_max_response_bytes =
+            get_str("net", "max_response_bytes", "SENTINEL_HTTP_MAX_RESPONSE_BYTES")
+                .and_then(|s| s.parse::<usize>().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES);
+
+        let allowed_egress_hosts =
+            get_str_list("net", "allowed_egress_hosts", "SENTINEL_ALLOWED_EGRESS_HOSTS");
+
+        let max_retries = get_str("net", "max_retries", "SENTINEL_MAX_RETRIES")
+            .and_then(|s| s.parse::<u32>().ok())
+            .unwrap_or(2);
+        let retry_base_delay_ms =
+            get_str("net", "retry_base_delay_ms", "SENTINEL_RETRY_BASE_DELAY_MS")
+                .and_then(|s| s.parse::<u64>().ok())
+                .unwrap_or(500);
+
+        let tls_ca_file = get_str("net", "tls_ca_file", "SENTINEL_TLS_CA_FILE");
+        let tls_insecure_skip_verify =
+            get_str("net", "tls_insecure_skip_verify", "SENTINEL_TLS_INSECURE_SKIP_VERIFY")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false);
 
         // Sandbox: enabled by default, disable with --no-sandbox or SENTINEL_SANDBOX=false
         let sandbox = if std::env::args().any(|a| a == "--no-sandbox") {
@@ -172,6 +590,8 @@ impl Config {
                 .map(|v| v != "false" && v != "0")
                 .unwrap_or(true) // enabled by default
         };
+        let seccomp_mode = get_str("security", "seccomp_mode", "SENTINEL_SECCOMP_MODE")
+            .unwrap_or_else(|| "enforce".to_string());
 
         Ok(Config {
             provider,
@@ -179,41 +599,575 @@ impl Config {
             model,
             max_tokens,
             openai_base_url,
+            openai_model_family,
+            anthropic_extra_headers,
+            openai_extra_headers,
+            anthropic_prompt_cache,
+            model_fallbacks,
             system_prompt,
+            system_prompts,
+            state_dir,
+            greeting,
+            unauthorized_message,
+            admin_users,
+            tool_result_ttl,
+            parallel_tool_calls,
+            ack_reactions,
+            respond_only_when_mentioned,
+            metrics_bind,
+            log_format,
+            max_tokens_per_conversation,
+            max_context_tokens,
+            max_tool_output_bytes,
+            max_concurrent_chats,
+            temperature,
+            top_p,
+            stop_sequences,
             telegram_token,
             telegram_allowed_users,
+            telegram_webhook_bind,
+            telegram_webhook_path,
+            telegram_webhook_url,
+            telegram_parse_mode,
             discord_token,
             discord_channel_ids,
             discord_allowed_users,
+            discord_gateway,
             slack_bot_token,
             slack_channel_ids,
             slack_allowed_users,
             allowed_read_paths,
             allowed_write_paths,
+            denied_read_paths,
+            denied_write_paths,
             allowed_commands,
+            allowed_args,
+            denied_args,
+            allowed_fetch_hosts,
+            allowed_network_hosts,
             command_timeout,
+            max_command_output,
+            max_command_cpu_secs,
+            max_command_memory_mb,
+            max_command_processes,
+            sandbox_commands,
             audit_log_path,
+            audit_max_bytes,
+            audit_keep_files,
+            audit_redact,
+            audit_webhook_url,
+            audit_webhook_batch,
+            audit_webhook_interval,
+            require_approval_for,
+            approval_timeout,
             sandbox,
+            seccomp_mode,
             skills_dir,
+            skill_max_concurrent,
+            skill_max_persistent,
+            skill_concurrency_policy,
+            compress_requests,
+            http_pool_max_per_host,
+            http_pool_idle_timeout,
+            http_connect_timeout,
+            http_read_timeout,
+            http_write_timeout,
+            http_max_response_bytes,
+            allowed_egress_hosts,
+            max_retries,
+            retry_base_delay_ms,
+            tls_ca_file,
+            tls_insecure_skip_verify,
+            config_file,
+            secret_files,
+        })
+    }
+}
+
+// ── Validation ──────────────────────────────────────────────────────────────
+
+impl Config {
+    /// Problems that don't stop startup but will make things fail later,
+    /// one human-readable warning each.
+    pub fn validate(&self) -> Vec<String> {
+        let mut warnings = check_allowed_paths("allowed_read_paths", &self.allowed_read_paths);
+        warnings.extend(check_allowed_paths(
+            "allowed_write_paths",
+            &self.allowed_write_paths,
+        ));
+        if !matches!(self.log_format.as_str(), "text" | "json") {
+            warnings.push(format!("unknown log_format '{}', using \"text\"", self.log_format));
+        }
+        if let Some(url) = &self.audit_webhook_url {
+            if !url.starts_with("https://") {
+                warnings.push(format!(
+                    "audit_webhook_url '{}' is not an https:// URL; audit events will not be sent",
+                    url
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+// ── Check mode ──────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// One line of a `--check-config` report.
+pub struct CheckItem {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+/// The result of checking a config without starting the agent.
+#[derive(Default)]
+pub struct CheckReport {
+    pub items: Vec<CheckItem>,
+}
+
+impl CheckReport {
+    pub fn push(&mut self, name: &str, status: CheckStatus, detail: impl Into<String>) {
+        self.items.push(CheckItem {
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+        });
+    }
+
+    /// True unless some check failed; warnings pass.
+    pub fn passed(&self) -> bool {
+        self.items.iter().all(|i| i.status != CheckStatus::Fail)
+    }
+
+    /// One `STATUS name: detail` line per check, then a summary line.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for item in &self.items {
+            let status = match item.status {
+                CheckStatus::Ok => "ok  ",
+                CheckStatus::Warn => "WARN",
+                CheckStatus::Fail => "FAIL",
+            };
+            out.push_str(&format!("{} {}: {}\n", status, item.name, item.detail));
+        }
+        let count = |status| self.items.iter().filter(|i| i.status == status).count();
+        out.push_str(&format!(
+            "{} checks: {} ok, {} warnings, {} failed\n",
+            self.items.len(),
+            count(CheckStatus::Ok),
+            count(CheckStatus::Warn),
+            count(CheckStatus::Fail)
+        ));
+        out
+    }
+}
+
+impl Config {
+    /// Check everything that can be checked locally: the provider
+    /// settings, the messaging platforms, the allowlisted paths and the
+    /// skills. Endpoints are not contacted.
+    pub fn check(&self) -> CheckReport {
+        let mut report = CheckReport::default();
+
+        if matches!(self.provider.as_str(), "anthropic" | "openai" | "azure" | "gemini" | "echo") {
+            report.push("provider", CheckStatus::Ok, self.provider.as_str());
+        } else {
+            report.push(
+                "provider",
+                CheckStatus::Fail,
+                format!(
+                    "unknown provider '{}' (expected anthropic, openai, azure, gemini or echo)",
+                    self.provider
+                ),
+            );
+        }
+        if self.model.trim().is_empty() {
+            report.push("model", CheckStatus::Fail, "model is empty");
+        } else {
+            report.push("model", CheckStatus::Ok, self.model.as_str());
+        }
+        if self.provider == "echo" {
+            report.push("api_key", CheckStatus::Ok, "not needed for echo");
+        } else if self.api_key.trim().is_empty() {
+            report.push("api_key", CheckStatus::Fail, "API key is empty");
+        } else {
+            report.push("api_key", CheckStatus::Ok, "set");
+        }
+
+        let platforms = [
+            ("telegram", &self.telegram_token, None),
+            ("discord", &self.discord_token, Some(&self.discord_channel_ids)),
+            ("slack", &self.slack_bot_token, Some(&self.slack_channel_ids)),
+        ];
+        for (name, token, channels) in platforms {
+            match (token.as_deref(), channels) {
+                (None, _) => {}
+                (Some(t), _) if t.trim().is_empty() => {
+                    report.push(name, CheckStatus::Fail, "token is empty")
+                }
+                (Some(_), Some(ids)) if ids.is_empty() => report.push(
+                    name,
+                    CheckStatus::Warn,
+                    "token set but no channel_ids configured; the connector will not start",
+                ),
+                (Some(_), _) => report.push(name, CheckStatus::Ok, "token set"),
+            }
+        }
+
+        for (key, paths) in [
+            ("allowed_read_paths", &self.allowed_read_paths),
+            ("allowed_write_paths", &self.allowed_write_paths),
+        ] {
+            let problems = check_allowed_paths(key, paths);
+            if problems.is_empty() {
+                report.push(key, CheckStatus::Ok, format!("{} path(s) resolved", paths.len()));
+            }
+            for problem in problems {
+                report.push(key, CheckStatus::Fail, problem);
+            }
+        }
+
+        if !matches!(self.seccomp_mode.as_str(), "enforce" | "log") {
+            report.push(
+                "seccomp_mode",
+                CheckStatus::Fail,
+                format!("must be \"enforce\" or \"log\", not \"{}\"", self.seccomp_mode),
+            );
+        }
+        if !matches!(self.log_format.as_str(), "text" | "json") {
+            report.push(
+                "log_format",
+                CheckStatus::Fail,
+                format!("must be \"text\" or \"json\", not \"{}\"", self.log_format),
+            );
+        }
+        if let Some(url) = &self.audit_webhook_url {
+            if !url.starts_with("https://") {
+                report.push(
+                    "audit_webhook_url",
+                    CheckStatus::Fail,
+                    format!("'{}' is not an https:// URL", url),
+                );
+            }
+        }
+
+        if let Some(dir) = &self.skills_dir {
+            match check_skills(dir) {
+                Ok(names) if names.is_empty() => {
+                    report.push("skills", CheckStatus::Warn, format!("no skills in '{}'", dir))
+                }
+                Ok(names) => report.push("skills", CheckStatus::Ok, names.join(", ")),
+                Err(problems) => {
+                    for problem in problems {
+                        report.push("skills", CheckStatus::Fail, problem);
+                    }
+                }
+            }
+        }
+
+        report
+    }
+}
+
+impl Config {
+    /// Limits on run_command children.
+    pub fn command_limits(&self) -> CommandLimits {
+        CommandLimits {
+            cpu_secs: self.max_command_cpu_secs,
+            memory_bytes: self.max_command_memory_mb.map(|mb| mb.saturating_mul(1024 * 1024)),
+            processes: self.max_command_processes,
+            landlock: self.sandbox_commands,
+        }
+    }
+
+    /// The network allowlist: `allowed_network_hosts` plus the LLM provider,
+    /// the enabled messaging platforms, the audit webhook and the http_fetch
+    /// hosts, which always stay reachable.
+    pub fn network_allowlist(&self) -> NetworkAllowlist {
+        let mut hosts: Vec<String> = match self.provider.as_str() {
+            "openai" | "azure" => url_host(&self.openai_base_url).map(|(h, _)| h).into_iter().collect(),
+            "gemini" => vec!["generativelanguage.googleapis.com".into()],
+            "echo" => Vec::new(),
+            _ => vec!["api.anthropic.com".into()],
+        };
+        if self.telegram_token.is_some() {
+            hosts.push("api.telegram.org".into());
+        }
+        if self.discord_token.is_some() {
+            hosts.extend([
+                "discord.com".into(),
+                "gateway.discord.gg".into(),
+                // Image attachments
+                "cdn.discordapp.com".into(),
+            ]);
+        }
+        if self.slack_bot_token.is_some() {
+            hosts.push("slack.com".into());
+        }
+        if let Some(Ok((host, _))) = self.audit_webhook_url.as_deref().map(url_host) {
+            hosts.push(host);
+        }
+        // A fetch host's port only narrows http_fetch; egress is by host
+        hosts.extend(self.allowed_fetch_hosts.iter().map(|entry| {
+            let (host, _) = split_port(entry);
+            host.trim_start_matches('[').trim_end_matches(']').to_string()
+        }));
+        hosts.extend(self.allowed_network_hosts.iter().cloned());
+        NetworkAllowlist::new(hosts, !self.allowed_network_hosts.is_empty())
+    }
+}
+
+// ── Reloading ───────────────────────────────────────────────────────────────
+
+impl Config {
+    /// This config with the settings that can change while running taken
+    /// from `fresh`, plus one line per setting that changed. Everything
+    /// else, such as the provider, keys and tokens, keeps its startup
+    /// value; changes to those are listed as needing a restart.
+    pub fn reload_from(&self, fresh: &Config) -> (Config, Vec<String>) {
+        let mut next = self.clone();
+        let mut changes = Vec::new();
+        let mut update = ReloadDiff {
+            changes: &mut changes,
+        };
+
+        update.field("system_prompt", &mut next.system_prompt, &fresh.system_prompt);
+        update.field("system_prompts", &mut next.system_prompts, &fresh.system_prompts);
+        update.field("greeting", &mut next.greeting, &fresh.greeting);
+        update.field(
+            "unauthorized_message",
+            &mut next.unauthorized_message,
+            &fresh.unauthorized_message,
+        );
+        update.field("admin_users", &mut next.admin_users, &fresh.admin_users);
+        update.field(
+            "telegram allowed_users",
+            &mut next.telegram_allowed_users,
+            &fresh.telegram_allowed_users,
+        );
+        update.field(
+            "discord allowed_users",
+            &mut next.discord_allowed_users,
+            &fresh.discord_allowed_users,
+        );
+        update.field(
+            "slack allowed_users",
+            &mut next.slack_allowed_users,
+            &fresh.slack_allowed_users,
+        );
+        update.field(
+            "allowed_read_paths",
+            &mut next.allowed_read_paths,
+            &fresh.allowed_read_paths,
+        );
+        update.field(
+            "allowed_write_paths",
+            &mut next.allowed_write_paths,
+            &fresh.allowed_write_paths,
+        );
+        update.field(
+            "denied_read_paths",
+            &mut next.denied_read_paths,
+            &fresh.denied_read_paths,
+        );
+        update.field(
+            "denied_write_paths",
+            &mut next.denied_write_paths,
+            &fresh.denied_write_paths,
+        );
+        update.field("allowed_commands", &mut next.allowed_commands, &fresh.allowed_commands);
+        update.field("allowed_args", &mut next.allowed_args, &fresh.allowed_args);
+        update.field("denied_args", &mut next.denied_args, &fresh.denied_args);
+        update.field(
+            "require_approval_for",
+            &mut next.require_approval_for,
+            &fresh.require_approval_for,
+        );
+        update.field("approval_timeout", &mut next.approval_timeout, &fresh.approval_timeout);
+        update.field(
+            "max_tokens_per_conversation",
+            &mut next.max_tokens_per_conversation,
+            &fresh.max_tokens_per_conversation,
+        );
+        update.field(
+            "max_context_tokens",
+            &mut next.max_context_tokens,
+            &fresh.max_context_tokens,
+        );
+        update.field("tool_result_ttl", &mut next.tool_result_ttl, &fresh.tool_result_ttl);
+        update.field("ack_reactions", &mut next.ack_reactions, &fresh.ack_reactions);
+        update.field("log_format", &mut next.log_format, &fresh.log_format);
+        update.field(
+            "respond_only_when_mentioned",
+            &mut next.respond_only_when_mentioned,
+            &fresh.respond_only_when_mentioned,
+        );
+
+        let fixed = [
+            ("provider", self.provider != fresh.provider),
+            ("api_key", self.api_key != fresh.api_key),
+            ("model", self.model != fresh.model),
+            ("openai model_family", self.openai_model_family != fresh.openai_model_family),
+            ("telegram token", self.telegram_token != fresh.telegram_token),
+            ("discord token", self.discord_token != fresh.discord_token),
+            ("slack bot_token", self.slack_bot_token != fresh.slack_bot_token),
+            ("allowed_network_hosts", self.allowed_network_hosts != fresh.allowed_network_hosts),
+            ("sandbox", self.sandbox != fresh.sandbox),
+            ("skills_dir", self.skills_dir != fresh.skills_dir),
+            ("state_dir", self.state_dir != fresh.state_dir),
+            ("metrics_bind", self.metrics_bind != fresh.metrics_bind),
+            ("command limits", self.command_limits() != fresh.command_limits()),
+        ];
+        for (name, changed) in fixed {
+            if changed {
+                changes.push(format!("{} changed; restart to apply", name));
+            }
+        }
+        (next, changes)
+    }
+}
+
+/// Collects the names of the reloadable settings that changed.
+struct ReloadDiff<'a> {
+    changes: &'a mut Vec<String>,
+}
+
+impl ReloadDiff<'_> {
+    fn field<T: PartialEq + Clone>(&mut self, name: &str, current: &mut T, fresh: &T) {
+        if current != fresh {
+            *current = fresh.clone();
+            self.changes.push(format!("{} updated", name));
+        }
+    }
+}
+
+/// The running config, replaced as a whole when it is reloaded. Readers
+/// take a snapshot per message, so a turn never sees a mix of old and new
+/// settings.
+#[derive(Clone)]
+pub struct SharedConfig(Arc<RwLock<Arc<Config>>>);
+
+impl SharedConfig {
+    pub fn new(config: Arc<Config>) -> Self {
+        SharedConfig(Arc::new(RwLock::new(config)))
+    }
+
+    pub fn current(&self) -> Arc<Config> {
+        Arc::clone(&self.0.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn replace(&self, config: Config) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(config);
+    }
+}
+
+/// Allow-list prefixes that cannot be resolved never match anything, so a
+/// typo silently denies every access under them. Globs are checked by the
+/// directory they start from.
+fn check_allowed_paths(key: &str, paths: &[String]) -> Vec<String> {
+    paths
+        .iter()
+        .filter_map(|path| match fs::canonicalize(glob_base(path)) {
+            Ok(_) => None,
+            Err(e) => Some(format!(
+                "{} entry '{}' cannot be resolved ({}); access under it will be denied",
+                key, path, e
+            )),
         })
+        .collect()
+}
+
+/// The OS sandbox can't express a glob and grants its base directory
+/// instead, so a pattern rooted at `/` would open the whole filesystem.
+fn check_glob_roots(key: &str, paths: &[String]) -> Result<(), ConfigError> {
+    match paths.iter().find(|p| p.contains(['*', '?']) && glob_base(p) == "/") {
+        Some(path) => Err(ConfigError(format!(
+            "{} entry '{}' has a wildcard directly under /; start it from a directory",
+            key, path
+        ))),
+        None => Ok(()),
     }
 }
 
-fn resolve_secret(toml: &Option<TomlDoc>, section: &str, env_key_field: &str, fallback_env: &str) -> Option<String> {
-    // Check if TOML specifies an env var name to read from
+/// A secret, from the first of these that is set:
+///
+/// 1. `<key>_file` in `[section]`: a file holding the secret
+/// 2. `<key>_env` in `[section]`: the name of an env var holding it
+/// 3. `$<FALLBACK>_FILE`: a file holding it (Docker secrets, systemd
+///    `LoadCredential`)
+/// 4. `$<FALLBACK>` itself
+///
+/// Files are read whole and trimmed, and their paths added to `files`. A
+/// file that is named but can't be read, or is empty, is an error rather
+/// than a reason to try the next.
+fn resolve_secret(
+    toml: &Option<TomlDoc>,
+    section: &str,
+    env_key_field: &str,
+    fallback_env: &str,
+    files: &mut Vec<String>,
+) -> Result<Option<String>, ConfigError> {
     if let Some(t) = toml {
+        let file_key_field = format!("{}_file", env_key_field.trim_end_matches("_env"));
+        if let Some(path) = t.get_str(section, &file_key_field) {
+            let source = format!("[{}] {}", section, file_key_field);
+            files.push(path.clone());
+            return read_secret_file(&path, &source).map(Some);
+        }
+        // Check if TOML specifies an env var name to read from
         if let Some(env_name) = t.get_str(section, env_key_field) {
             if let Ok(val) = env::var(&env_name) {
-                return Some(val);
+                return Ok(Some(val));
             }
         }
     }
+    let file_env = format!("{}_FILE", fallback_env);
+    if let Ok(path) = env::var(&file_env) {
+        files.push(path.clone());
+        return read_secret_file(&path, &file_env).map(Some);
+    }
     // Fallback to direct env var
-    env::var(fallback_env).ok()
+    Ok(env::var(fallback_env).ok())
+}
+
+fn read_secret_file(path: &str, source: &str) -> Result<String, ConfigError> {
+    let secret = fs::read_to_string(path)
+        .map_err(|e| ConfigError(format!("{}: cannot read {}: {}", source, path, e)))?;
+    let secret = secret.trim();
+    if secret.is_empty() {
+        return Err(ConfigError(format!("{}: {} is empty", source, path)));
+    }
+    Ok(secret.to_string())
+}
+
+/// Prompts by conversation from a JSON object such as
+/// `{"slack:C1": "Be terse, and cite sources."}`, sorted by key.
+fn parse_prompt_map(s: &str) -> Result<Vec<(String, String)>, String> {
+    let doc = json::parse(s).map_err(|e| format!("not valid JSON: {}", e))?;
+    let fields = doc.as_object().ok_or("expected a JSON object")?;
+    let mut pairs = fields
+        .iter()
+        .map(|(key, value)| match value.as_str() {
+            Some(prompt) => Ok((key.clone(), prompt.to_string())),
+            None => Err(format!("the prompt for '{}' must be a string", key)),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    pairs.sort();
+    Ok(pairs)
 }
 
-// ── Minimal TOML parser ─────────────────────────────────────────────────────
+// ── TOML parser ─────────────────────────────────────────────────────────────
 
+/// A parsed TOML document. Nested tables are flattened: `[a.b]`, the dotted
+/// key `b.c = 1` under `[a]`, and the inline table `b = { c = 1 }` under
+/// `[a]` all land in the section named by the full dotted path.
 pub(crate) struct TomlDoc {
     pub(crate) sections: HashMap<String, HashMap<String, TomlValue>>,
 }
@@ -221,19 +1175,38 @@ pub(crate) struct TomlDoc {
 pub(crate) enum TomlValue {
     Str(String),
     Int(i64),
+    Float(f64),
+    Bool(bool),
     StrList(Vec<String>),
     IntList(Vec<i64>),
 }
 
 impl TomlDoc {
+    /// A string, or any other scalar in its TOML spelling, so settings
+    /// that may also come from the environment parse the same either way.
     pub(crate) fn get_str(&self, section: &str, key: &str) -> Option<String> {
         match self.sections.get(section)?.get(key)? {
             TomlValue::Str(s) => Some(s.clone()),
             TomlValue::Int(n) => Some(n.to_string()),
+            TomlValue::Float(f) => Some(f.to_string()),
+            TomlValue::Bool(b) => Some(b.to_string()),
             _ => None,
         }
     }
 
+    /// A boolean, also accepted quoted (`"true"`) as the old parser read
+    /// it. Anything else is an error rather than quietly false.
+    pub(crate) fn get_bool(&self, section: &str, key: &str) -> Result<Option<bool>, String> {
+        let Some(value) = self.sections.get(section).and_then(|t| t.get(key)) else {
+            return Ok(None);
+        };
+        match value {
+            TomlValue::Bool(b) => Ok(Some(*b)),
+            TomlValue::Str(s) if s == "true" || s == "false" => Ok(Some(s == "true")),
+            _ => Err(format!("{}.{} must be true or false", section, key)),
+        }
+    }
+
     pub(crate) fn get_str_list(&self, section: &str, key: &str) -> Option<Vec<String>> {
         match self.sections.get(section)?.get(key)? {
             TomlValue::StrList(v) => Some(v.clone()),
@@ -247,117 +1220,526 @@ impl TomlDoc {
             _ => None,
         }
     }
+
+    /// Every string or integer entry in a table such as
+    /// `[openai.extra_headers]`, sorted by key.
+    pub(crate) fn get_str_map(&self, section: &str) -> Option<Vec<(String, String)>> {
+        let table = self.sections.get(section)?;
+        let mut pairs: Vec<(String, String)> = table
+            .keys()
+            .filter_map(|k| Some((k.clone(), self.get_str(section, k)?)))
+            .collect();
+        pairs.sort();
+        Some(pairs)
+    }
+
+    /// Every string-list entry in a table such as `[security.denied_args]`,
+    /// sorted by key.
+    pub(crate) fn get_str_list_map(&self, section: &str) -> Option<Vec<(String, Vec<String>)>> {
+        let table = self.sections.get(section)?;
+        let mut pairs: Vec<(String, Vec<String>)> = table
+            .keys()
+            .filter_map(|k| Some((k.clone(), self.get_str_list(section, k)?)))
+            .collect();
+        pairs.sort();
+        Some(pairs)
+    }
 }
 
-fn try_load_toml() -> Option<TomlDoc> {
+/// The first config file found, parsed, and its path. One that doesn't
+/// parse is an error, not skipped, so a mistake in it can't quietly leave
+/// every setting at its default.
+fn try_load_toml() -> Result<Option<(String, TomlDoc)>, ConfigError> {
     let paths = ["sentinel.toml", "/etc/sentinel/sentinel.toml"];
     for path in &paths {
         if let Ok(content) = fs::read_to_string(path) {
-            match parse_toml(&content) {
-                Ok(doc) => return Some(doc),
-                Err(e) => {
-                    eprintln!("sentinel: warning: failed to parse {}: {}", path, e);
-                }
-            }
+            return parse_config_toml(&content)
+                .map(|doc| Some((path.to_string(), doc)))
+                .map_err(|e| ConfigError(format!("cannot parse {}: {}", path, e)));
         }
     }
-    None
+    Ok(None)
 }
 
+/// Parse a TOML document as written, with no `${VAR}` expansion.
 pub(crate) fn parse_toml(input: &str) -> Result<TomlDoc, String> {
+    parse_toml_expanding(input, false)
+}
+
+/// Parse sentinel's own config, whose basic strings may name environment
+/// variables (see `expand_env`). Other documents, like skill manifests,
+/// come from elsewhere and don't get to read the agent's environment.
+fn parse_config_toml(input: &str) -> Result<TomlDoc, String> {
+    parse_toml_expanding(input, true)
+}
+
+fn parse_toml_expanding(input: &str, expand: bool) -> Result<TomlDoc, String> {
+    let mut parser = TomlParser::new(input);
+    parser.expand = expand;
     let mut sections: HashMap<String, HashMap<String, TomlValue>> = HashMap::new();
     let mut current_section = String::new();
 
-    for (line_num, raw_line) in input.lines().enumerate() {
-        let line = raw_line.split('#').next().unwrap_or("").trim();
-        if line.is_empty() {
-            continue;
+    loop {
+        parser.skip_blank();
+        if parser.peek().is_none() {
+            break;
         }
+        let line = parser.line;
+        let parsed = if parser.eat('[') {
+            parser.parse_header().map(|name| {
+                sections.entry(name.clone()).or_default();
+                current_section = name;
+            })
+        } else {
+            parser.parse_key_value(&current_section, &mut sections)
+        };
+        parsed
+            .and_then(|()| parser.end_of_line())
+            .map_err(|e| format!("line {}: {}", line, e))?;
+    }
 
-        // Section header
-        if line.starts_with('[') && line.ends_with(']') {
-            current_section = line[1..line.len() - 1].trim().to_string();
-            sections.entry(current_section.clone()).or_default();
-            continue;
+    Ok(TomlDoc { sections })
+}
+
+/// Parse a single value, e.g. `42` or `["a", "b"]`.
+#[cfg(test)]
+fn parse_toml_value(s: &str) -> Result<TomlValue, String> {
+    let mut parser = TomlParser::new(s.trim());
+    let value = parser.parse_value()?;
+    parser.end_of_line()?;
+    Ok(value)
+}
+
+/// Replace each `${VAR}` in a basic string value with the variable's value
+/// from the environment, so a config can refer to secrets or per-host
+/// settings without holding them. Literal (`'...'`) strings are left as
+/// written, and a `$` not followed by `{` is kept.
+fn expand_env(s: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| format!("unterminated ${{ in \"{}\"", s))?;
+        let name = &after[..end];
+        if name.is_empty() {
+            return Err(format!("empty ${{}} in \"{}\"", s));
         }
+        let value = env::var(name)
+            .map_err(|_| format!("environment variable {} is not set", name))?;
+        out.push_str(&value);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
 
-        // Key = value
-        let eq_pos = line.find('=').ok_or_else(|| {
-            format!("line {}: expected '='", line_num + 1)
-        })?;
+/// `table.a.b` for the dotted key parts `a`, `b` under `table`.
+fn join_table(table: &str, parts: &[String]) -> String {
+    std::iter::once(table)
+        .chain(parts.iter().map(|p| p.as_str()))
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join(".")
+}
 
-        let key = line[..eq_pos].trim().to_string();
-        let val_str = line[eq_pos + 1..].trim();
+struct TomlParser {
+    chars: Vec<char>,
+    pos: usize,
+    /// 1-based line of `pos`, for error messages.
+    line: usize,
+    /// Whether basic strings get `${VAR}` expanded.
+    expand: bool,
+}
 
-        let value = parse_toml_value(val_str).map_err(|e| {
-            format!("line {}: {}", line_num + 1, e)
-        })?;
+impl TomlParser {
+    fn new(input: &str) -> Self {
+        TomlParser {
+            chars: input.chars().collect(),
+            pos: 0,
+            line: 1,
+            expand: false,
+        }
+    }
 
-        sections
-            .entry(current_section.clone())
-            .or_default()
-            .insert(key, value);
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
     }
 
-    Ok(TomlDoc { sections })
-}
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars().enumerate().all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
 
-fn parse_toml_value(s: &str) -> Result<TomlValue, String> {
-    let s = s.trim();
-
-    // String
-    if s.starts_with('"') {
-        let end = s[1..]
-            .find('"')
-            .ok_or("unterminated string")?;
-        return Ok(TomlValue::Str(s[1..end + 1].to_string()));
-    }
-
-    // Array
-    if s.starts_with('[') {
-        let inner = s
-            .strip_prefix('[')
-            .and_then(|s| s.strip_suffix(']'))
-            .ok_or("unterminated array")?
-            .trim();
-
-        if inner.is_empty() {
-            return Ok(TomlValue::StrList(Vec::new()));
-        }
-
-        // Determine type from first element
-        let first = inner.split(',').next().unwrap_or("").trim();
-        if first.starts_with('"') {
-            let items: Vec<String> = inner
-                .split(',')
-                .filter_map(|item| {
-                    let item = item.trim();
-                    if item.starts_with('"') && item.ends_with('"') && item.len() >= 2 {
-                        Some(item[1..item.len() - 1].to_string())
-                    } else {
-                        None
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!("expected '{}'", c))
+        }
+    }
+
+    /// Skip spaces and tabs.
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+    }
+
+    /// Skip whitespace, comments and line breaks.
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_ws();
+            self.skip_comment();
+            if !(self.eat('\n') || self.eat('\r')) {
+                break;
+            }
+        }
+    }
+
+    /// Nothing but a comment may follow a header or value on its line.
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_ws();
+        self.skip_comment();
+        self.eat('\r');
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => Err(format!("unexpected '{}' after value", c)),
+        }
+    }
+
+    /// A `[table.name]` header, after the opening bracket.
+    fn parse_header(&mut self) -> Result<String, String> {
+        if self.peek() == Some('[') {
+            return Err("arrays of tables ([[...]]) are not supported".into());
+        }
+        let parts = self.parse_key()?;
+        self.skip_ws();
+        self.expect(']')?;
+        Ok(join_table("", &parts))
+    }
+
+    /// A bare, quoted or dotted key, as its parts.
+    fn parse_key(&mut self) -> Result<Vec<String>, String> {
+        let mut parts = Vec::new();
+        loop {
+            self.skip_ws();
+            let part = match self.peek() {
+                Some('"') => self.parse_basic_string()?,
+                Some('\'') => self.parse_literal_string()?,
+                _ => {
+                    let start = self.pos;
+                    while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                        self.bump();
                     }
-                })
-                .collect();
-            Ok(TomlValue::StrList(items))
+                    if self.pos == start {
+                        return Err("expected a key".into());
+                    }
+                    self.chars[start..self.pos].iter().collect()
+                }
+            };
+            parts.push(part);
+            self.skip_ws();
+            if !self.eat('.') {
+                return Ok(parts);
+            }
+        }
+    }
+
+    fn parse_key_value(
+        &mut self,
+        table: &str,
+        sections: &mut HashMap<String, HashMap<String, TomlValue>>,
+    ) -> Result<(), String> {
+        let mut parts = self.parse_key()?;
+        self.skip_ws();
+        self.expect('=')?;
+        self.skip_ws();
+        let key = parts.pop().unwrap_or_default();
+        self.parse_value_into(&join_table(table, &parts), key, sections)
+    }
+
+    /// Parse a value for `key` in `table`; an inline table becomes the
+    /// section `table.key`.
+    fn parse_value_into(
+        &mut self,
+        table: &str,
+        key: String,
+        sections: &mut HashMap<String, HashMap<String, TomlValue>>,
+    ) -> Result<(), String> {
+        if !self.eat('{') {
+            let value = self.parse_value()?;
+            sections.entry(table.to_string()).or_default().insert(key, value);
+            return Ok(());
+        }
+        let table = join_table(table, &[key]);
+        sections.entry(table.clone()).or_default();
+        self.skip_ws();
+        if self.eat('}') {
+            return Ok(());
+        }
+        loop {
+            self.parse_key_value(&table, sections)?;
+            self.skip_ws();
+            if self.eat('}') {
+                return Ok(());
+            }
+            if !self.eat(',') {
+                return Err("expected ',' or '}' in inline table".into());
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<TomlValue, String> {
+        match self.peek() {
+            Some('"') if self.starts_with("\"\"\"") => self
+                .parse_multiline_string()
+                .and_then(|s| self.expanded(s))
+                .map(TomlValue::Str),
+            Some('"') => self
+                .parse_basic_string()
+                .and_then(|s| self.expanded(s))
+                .map(TomlValue::Str),
+            Some('\'') if self.starts_with("\'\'\'") => {
+                self.parse_multiline_literal().map(TomlValue::Str)
+            }
+            Some('\'') => self.parse_literal_string().map(TomlValue::Str),
+            Some('[') => self.parse_array(),
+            Some('{') => Err("inline tables are only supported as key values".into()),
+            _ => self.parse_scalar(),
+        }
+    }
+
+    fn expanded(&self, s: String) -> Result<String, String> {
+        if self.expand {
+            expand_env(&s)
         } else {
-            let items: Vec<i64> = inner
-                .split(',')
-                .filter_map(|item| item.trim().parse::<i64>().ok())
-                .collect();
-            Ok(TomlValue::IntList(items))
+            Ok(s)
         }
-    } else if let Ok(n) = s.parse::<i64>() {
-        Ok(TomlValue::Int(n))
-    } else if s == "true" || s == "false" {
-        Ok(TomlValue::Str(s.to_string()))
-    } else {
-        Err(format!("cannot parse value: {}", s))
+    }
+
+    /// A boolean or number.
+    fn parse_scalar(&mut self) -> Result<TomlValue, String> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if !matches!(c, ' ' | '\t' | '\r' | '\n' | ',' | ']' | '}' | '#')) {
+            self.bump();
+        }
+        let token: String = self.chars[start..self.pos].iter().collect();
+        let digits = token.replace('_', "");
+        let radix = |prefix: &str, radix: u32| {
+            digits.strip_prefix(prefix).and_then(|d| i64::from_str_radix(d, radix).ok())
+        };
+        if token == "true" || token == "false" {
+            Ok(TomlValue::Bool(token == "true"))
+        } else if let Ok(n) = digits.parse::<i64>() {
+            Ok(TomlValue::Int(n))
+        } else if let Some(n) = radix("0x", 16).or_else(|| radix("0o", 8)).or_else(|| radix("0b", 2)) {
+            Ok(TomlValue::Int(n))
+        } else if let (true, Ok(f)) = (digits.contains(['.', 'e', 'E']), digits.parse::<f64>()) {
+            Ok(TomlValue::Float(f))
+        } else if token.is_empty() {
+            Err("expected a value".into())
+        } else {
+            Err(format!("cannot parse value: {}", token))
+        }
+    }
+
+    /// An array of strings or of integers, which may span lines.
+    fn parse_array(&mut self) -> Result<TomlValue, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.eat(']') {
+                break;
+            }
+            items.push(self.parse_value()?);
+            self.skip_blank();
+            if self.eat(']') {
+                break;
+            }
+            if !self.eat(',') {
+                return Err("expected ',' or ']' in array".into());
+            }
+        }
+        if items.iter().all(|v| matches!(v, TomlValue::Str(_))) {
+            Ok(TomlValue::StrList(
+                items
+                    .into_iter()
+                    .filter_map(|v| match v {
+                        TomlValue::Str(s) => Some(s),
+                        _ => None,
+                    })
+                    .collect(),
+            ))
+        } else if items.iter().all(|v| matches!(v, TomlValue::Int(_))) {
+            Ok(TomlValue::IntList(
+                items
+                    .into_iter()
+                    .filter_map(|v| match v {
+                        TomlValue::Int(n) => Some(n),
+                        _ => None,
+                    })
+                    .collect(),
+            ))
+        } else {
+            Err("arrays must hold only strings or only integers".into())
+        }
+    }
+
+    /// A `"..."` string on one line.
+    fn parse_basic_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(out),
+                Some('\\') => self.parse_escape(&mut out)?,
+                None | Some('\n') => return Err("unterminated string".into()),
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    /// A `"""..."""` string. A line break right after the opening quotes is
+    /// dropped, and a backslash at the end of a line joins it to the next
+    /// non-blank text, so long descriptions can be wrapped.
+    fn parse_multiline_string(&mut self) -> Result<String, String> {
+        self.pos += 3;
+        self.eat('\r');
+        self.eat('\n');
+        let mut out = String::new();
+        loop {
+            if self.starts_with("\"\"\"") {
+                // Up to two quotes just before the closing three belong to
+                // the string
+                let mut quotes = 0;
+                while self.eat('"') {
+                    quotes += 1;
+                }
+                out.extend(std::iter::repeat_n('"', (quotes - 3).min(2)));
+                return Ok(out);
+            }
+            match self.bump() {
+                Some('\\') => {
+                    let rest = self.pos;
+                    self.skip_ws();
+                    if matches!(self.peek(), Some('\n' | '\r')) {
+                        while matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+                            self.bump();
+                        }
+                    } else {
+                        self.pos = rest;
+                        self.parse_escape(&mut out)?;
+                    }
+                }
+                Some('\r') if self.peek() == Some('\n') => {}
+                Some(c) => out.push(c),
+                None => return Err("unterminated multi-line string".into()),
+            }
+        }
+    }
+
+    /// A `'...'` string: no escapes.
+    fn parse_literal_string(&mut self) -> Result<String, String> {
+        self.expect('\'')?;
+        let mut out = String::new();
+        loop {
+            match self.bump() {
+                Some('\'') => return Ok(out),
+                None | Some('\n') => return Err("unterminated string".into()),
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    /// A `'''...'''` string: no escapes, line breaks kept.
+    fn parse_multiline_literal(&mut self) -> Result<String, String> {
+        self.pos += 3;
+        self.eat('\r');
+        self.eat('\n');
+        let mut out = String::new();
+        loop {
+            if self.starts_with("\'\'\'") {
+                self.pos += 3;
+                return Ok(out);
+            }
+            match self.bump() {
+                Some('\r') if self.peek() == Some('\n') => {}
+                Some(c) => out.push(c),
+                None => return Err("unterminated multi-line string".into()),
+            }
+        }
+    }
+
+    /// The escape after a backslash. Unknown escapes are kept as written,
+    /// so patterns such as `\d` in configs that predate escape handling
+    /// still load.
+    fn parse_escape(&mut self, out: &mut String) -> Result<(), String> {
+        let c = self.bump().ok_or("unterminated string")?;
+        let unicode = |parser: &mut Self, len: usize| -> Result<char, String> {
+            let hex: String = (0..len).filter_map(|_| parser.bump()).collect();
+            u32::from_str_radix(&hex, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| format!("invalid unicode escape \\{}{}", c, hex))
+        };
+        match c {
+            'n' => out.push('\n'),
+            't' => out.push('\t'),
+            'r' => out.push('\r'),
+            'b' => out.push('\u{8}'),
+            'f' => out.push('\u{c}'),
+            '"' => out.push('"'),
+            '\\' => out.push('\\'),
+            'u' => out.push(unicode(self, 4)?),
+            'U' => out.push(unicode(self, 8)?),
+            // Kept as written by the old parser, which had no escapes; now
+            // refused so a changed meaning can't go unnoticed
+            other => {
+                return Err(format!(
+                    "invalid escape \\{} (write \\\\ for a backslash, or use a 'literal string')",
+                    other
+                ))
+            }
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
     #[test]
@@ -396,6 +1778,27 @@ allowed_commands = ["ls", "cat"]
         );
     }
 
+    #[test]
+    fn test_parse_toml_header_table() {
+        let input = r#"
+[openai]
+base_url = "https://openrouter.ai/api/v1"
+
+[openai.extra_headers]
+HTTP-Referer = "https://example.com"
+"X-Title" = "Sentinel"
+"#;
+        let doc = parse_toml(input).unwrap();
+        assert_eq!(
+            doc.get_str_map("openai.extra_headers").unwrap(),
+            vec![
+                ("HTTP-Referer".to_string(), "https://example.com".to_string()),
+                ("X-Title".to_string(), "Sentinel".to_string()),
+            ]
+        );
+        assert!(doc.get_str_map("anthropic.extra_headers").is_none());
+    }
+
     #[test]
     fn test_parse_toml_empty_arrays() {
         let input = r#"
@@ -417,6 +1820,241 @@ verbose = true
 "#;
         let doc = parse_toml(input).unwrap();
         assert_eq!(doc.get_str("agent", "verbose").unwrap(), "true");
+        assert_eq!(doc.get_bool("agent", "verbose"), Ok(Some(true)));
+        assert_eq!(doc.get_bool("agent", "missing"), Ok(None));
+
+        // Quoted, as configs for the old parser wrote them
+        let doc = parse_toml("[skill]\non = \"true\"\noff = \"false\"\nyes = \"yes\"\nn = 1\n").unwrap();
+        assert_eq!(doc.get_bool("skill", "on"), Ok(Some(true)));
+        assert_eq!(doc.get_bool("skill", "off"), Ok(Some(false)));
+        assert_eq!(doc.get_bool("skill", "yes"), Err("skill.yes must be true or false".into()));
+        assert!(doc.get_bool("skill", "n").is_err());
+    }
+
+    #[test]
+    fn test_parse_toml_nested_tables() {
+        let input = r#"
+top = 1
+
+[security]
+allowed_commands = ["ls"]
+allowed_args.git = ["status", "log"]
+
+[security.denied_args]
+bash = ["-c"]
+
+[a.b.c]
+deep = "yes"
+
+[openai]
+extra_headers = { "X-Title" = "Sentinel", retries = 2, nested = { on = true } }
+"#;
+        let doc = parse_toml(input).unwrap();
+        assert_eq!(doc.get_str("", "top").unwrap(), "1");
+        assert_eq!(doc.get_str_list("security", "allowed_commands").unwrap(), vec!["ls"]);
+        assert_eq!(
+            doc.get_str_list_map("security.allowed_args").unwrap(),
+            vec![("git".to_string(), vec!["status".to_string(), "log".to_string()])]
+        );
+        assert_eq!(doc.get_str_list("security.denied_args", "bash").unwrap(), vec!["-c"]);
+        assert_eq!(doc.get_str("a.b.c", "deep").unwrap(), "yes");
+        assert_eq!(
+            doc.get_str_map("openai.extra_headers").unwrap(),
+            vec![
+                ("X-Title".to_string(), "Sentinel".to_string()),
+                ("retries".to_string(), "2".to_string()),
+            ]
+        );
+        assert_eq!(doc.get_bool("openai.extra_headers.nested", "on"), Ok(Some(true)));
+    }
+
+    #[test]
+    fn test_parse_toml_strings() {
+        let input = r#"
+[agent]
+system_prompt = """
+You are a helpful assistant.
+Reply in "plain" text."""
+joined = """\
+    one \
+    two"""
+quotes = """"quoted""""
+literal = 'C:\Users\#1'
+raw = '''
+a\b
+'''
+escaped = "tab\there \u00e9 \"q\" #not a comment" # a comment
+pattern = "\\d+"
+"#;
+        let doc = parse_toml(input).unwrap();
+        assert_eq!(
+            doc.get_str("agent", "system_prompt").unwrap(),
+            "You are a helpful assistant.\nReply in \"plain\" text."
+        );
+        assert_eq!(doc.get_str("agent", "joined").unwrap(), "one two");
+        assert_eq!(doc.get_str("agent", "quotes").unwrap(), "\"quoted\"");
+        assert_eq!(doc.get_str("agent", "literal").unwrap(), "C:\\Users\\#1");
+        assert_eq!(doc.get_str("agent", "raw").unwrap(), "a\\b\n");
+        assert_eq!(doc.get_str("agent", "escaped").unwrap(), "tab\there é \"q\" #not a comment");
+        assert_eq!(doc.get_str("agent", "pattern").unwrap(), "\\d+");
+    }
+
+    #[test]
+    fn test_parse_toml_numbers_and_arrays() {
+        let input = r##"
+[agent]
+temperature = 0.7
+big = 1_000_000
+mask = 0xff
+users = [
+    123,   # alice
+    456,
+]
+channels = ["#general", "a,b"]
+"##;
+        let doc = parse_toml(input).unwrap();
+        assert_eq!(doc.get_str("agent", "temperature").unwrap(), "0.7");
+        assert_eq!(doc.get_str("agent", "big").unwrap(), "1000000");
+        assert_eq!(doc.get_str("agent", "mask").unwrap(), "255");
+        assert_eq!(doc.get_i64_list("agent", "users").unwrap(), vec![123, 456]);
+        assert_eq!(doc.get_str_list("agent", "channels").unwrap(), vec!["#general", "a,b"]);
+    }
+
+    #[test]
+    fn test_parse_toml_errors_name_the_line() {
+        let err = parse_toml("[a]\nx = 1\ny = \"open\n").err().unwrap();
+        assert!(err.starts_with("line 3:"), "{}", err);
+        let err = parse_toml("x = \"\"\"\nnever closed\n").err().unwrap();
+        assert!(err.starts_with("line 1:"), "{}", err);
+        assert!(parse_toml("x = [1, \"a\"]").is_err());
+        assert!(parse_toml("x = 1 2").is_err());
+        assert!(parse_toml("[[servers]]").is_err());
+        // Unknown escapes, which the old parser kept, are refused
+        let err = parse_toml("path = \"C:\\dir\"").err().unwrap();
+        assert!(err.contains("invalid escape \\d"), "{}", err);
+        assert!(parse_toml("path = 'C:\\dir'").is_ok());
+    }
+
+    #[test]
+    fn test_parse_toml_env_interpolation() {
+        env::set_var("SENTINEL_TEST_INTERP_TOKEN", "secret-token");
+        env::set_var("SENTINEL_TEST_INTERP_HOST", "example.com");
+        env::set_var("SENTINEL_TEST_INTERP_PORT", "8443");
+        let input = r#"
+[telegram]
+token = "${SENTINEL_TEST_INTERP_TOKEN}"
+
+[agent]
+base_url = "https://${SENTINEL_TEST_INTERP_HOST}:${SENTINEL_TEST_INTERP_PORT}/v1"
+hosts = ["${SENTINEL_TEST_INTERP_HOST}", "api.${SENTINEL_TEST_INTERP_HOST}"]
+price = "$5"
+literal = '${SENTINEL_TEST_INTERP_TOKEN}'
+"#;
+        let doc = parse_config_toml(input).unwrap();
+        assert_eq!(doc.get_str("telegram", "token").unwrap(), "secret-token");
+        assert_eq!(
+            doc.get_str("agent", "base_url").unwrap(),
+            "https://example.com:8443/v1"
+        );
+        assert_eq!(
+            doc.get_str_list("agent", "hosts").unwrap(),
+            vec!["example.com", "api.example.com"]
+        );
+        assert_eq!(doc.get_str("agent", "price").unwrap(), "$5");
+        assert_eq!(
+            doc.get_str("agent", "literal").unwrap(),
+            "${SENTINEL_TEST_INTERP_TOKEN}"
+        );
+        // Only the config itself is expanded, not other TOML like manifests
+        let doc = parse_toml(input).unwrap();
+        assert_eq!(
+            doc.get_str("telegram", "token").unwrap(),
+            "${SENTINEL_TEST_INTERP_TOKEN}"
+        );
+    }
+
+    #[test]
+    fn test_resolve_secret_from_file() {
+        let path = "/tmp/sentinel_test_secret_file";
+        fs::write(path, "  sk-from-file\n").unwrap();
+        let toml = Some(parse_toml(&format!("[anthropic]\napi_key_file = \"{}\"\n", path)).unwrap());
+        let mut files = Vec::new();
+        let key = resolve_secret(&toml, "anthropic", "api_key_env", "SENTINEL_TEST_SECRET_UNSET", &mut files);
+        assert_eq!(key.unwrap().as_deref(), Some("sk-from-file"));
+
+        let toml = Some(parse_toml("[slack]\nbot_token_file = \"/tmp/sentinel_test_secret_missing\"\n").unwrap());
+        let err = resolve_secret(&toml, "slack", "bot_token_env", "SENTINEL_TEST_SECRET_UNSET", &mut files)
+            .unwrap_err();
+        assert!(err.0.starts_with("[slack] bot_token_file: cannot read /tmp/sentinel_test_secret_missing"));
+
+        fs::write(path, "\n").unwrap();
+        env::set_var("SENTINEL_TEST_SECRET_EMPTY_FILE", path);
+        let err = resolve_secret(&None, "telegram", "token_env", "SENTINEL_TEST_SECRET_EMPTY", &mut files)
+            .unwrap_err();
+        assert_eq!(err.0, format!("SENTINEL_TEST_SECRET_EMPTY_FILE: {} is empty", path));
+        // Every file named is noted, read or not, for the sandbox to allow
+        assert_eq!(files, [path, "/tmp/sentinel_test_secret_missing", path]);
+        fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_resolve_secret_precedence() {
+        let path = "/tmp/sentinel_test_secret_precedence";
+        let env_path = "/tmp/sentinel_test_secret_precedence_env";
+        fs::write(path, "from-toml-file").unwrap();
+        fs::write(env_path, "from-env-file").unwrap();
+        env::set_var("SENTINEL_TEST_SECRET_NAMED", "from-named-env");
+        env::set_var("SENTINEL_TEST_SECRET_FILE", env_path);
+        env::set_var("SENTINEL_TEST_SECRET", "from-env");
+
+        let resolve = |toml: &str| {
+            let toml = Some(parse_toml(toml).unwrap());
+            resolve_secret(&toml, "telegram", "token_env", "SENTINEL_TEST_SECRET", &mut Vec::new()).unwrap()
+        };
+        let file = format!("token_file = \"{}\"\n", path);
+        let named = "token_env = \"SENTINEL_TEST_SECRET_NAMED\"\n";
+        assert_eq!(resolve(&format!("[telegram]\n{}{}", file, named)).as_deref(), Some("from-toml-file"));
+        assert_eq!(resolve(&format!("[telegram]\n{}", named)).as_deref(), Some("from-named-env"));
+        assert_eq!(resolve("[telegram]\n").as_deref(), Some("from-env-file"));
+
+        env::remove_var("SENTINEL_TEST_SECRET_FILE");
+        assert_eq!(resolve("[telegram]\n").as_deref(), Some("from-env"));
+        // A named env var that isn't set falls through to the defaults
+        let unset = "[telegram]\ntoken_env = \"SENTINEL_TEST_SECRET_UNSET\"\n";
+        assert_eq!(resolve(unset).as_deref(), Some("from-env"));
+        fs::remove_file(path).ok();
+        fs::remove_file(env_path).ok();
+    }
+
+    #[test]
+    fn test_parse_prompt_map_keeps_commas() {
+        let pairs = parse_prompt_map(
+            r#"{"slack:C1": "Be terse, and cite sources.", "discord:7": "a=b"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                ("discord:7".to_string(), "a=b".to_string()),
+                ("slack:C1".to_string(), "Be terse, and cite sources.".to_string()),
+            ]
+        );
+        assert!(parse_prompt_map("slack:C1=Be terse").is_err());
+        assert!(parse_prompt_map(r#"{"slack:C1": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_toml_env_interpolation_unset() {
+        env::remove_var("SENTINEL_TEST_INTERP_UNSET");
+        let err = parse_config_toml("[a]\nok = 1\ntoken = \"${SENTINEL_TEST_INTERP_UNSET}\"\n")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            "line 3: environment variable SENTINEL_TEST_INTERP_UNSET is not set"
+        );
+        assert!(parse_config_toml("token = \"${UNCLOSED\"").is_err());
+        assert!(parse_config_toml("token = \"${}\"").is_err());
     }
 
     #[test]
@@ -462,4 +2100,227 @@ command_timeout = 60
         let doc = parse_toml(input).unwrap();
         assert_eq!(doc.get_str("security", "command_timeout").unwrap(), "60");
     }
+
+    /// A config as `load` would build it from a minimal Telegram setup.
+    pub(crate) fn test_config() -> Config {
+        Config {
+            provider: "anthropic".into(),
+            api_key: "sk-test".into(),
+            model: "claude-sonnet-4-5-20250929".into(),
+            max_tokens: 4096,
+            openai_base_url: "https://api.openai.com/v1".into(),
+            openai_model_family: None,
+            anthropic_extra_headers: Vec::new(),
+            openai_extra_headers: Vec::new(),
+            anthropic_prompt_cache: false,
+            model_fallbacks: Vec::new(),
+            system_prompt: None,
+            system_prompts: Vec::new(),
+            state_dir: None,
+            greeting: None,
+            unauthorized_message: None,
+            admin_users: Vec::new(),
+            tool_result_ttl: None,
+            parallel_tool_calls: true,
+            ack_reactions: false,
+            respond_only_when_mentioned: false,
+            metrics_bind: None,
+            log_format: "text".into(),
+            max_tokens_per_conversation: None,
+            max_context_tokens: 100_000,
+            max_concurrent_chats: 4,
+            max_tool_output_bytes: DEFAULT_MAX_TOOL_OUTPUT,
+            temperature: None,
+            top_p: None,
+            stop_sequences: Vec::new(),
+            telegram_token: Some("123:abc".into()),
+            telegram_allowed_users: Vec::new(),
+            telegram_webhook_bind: None,
+            telegram_webhook_path: "/telegram".into(),
+            telegram_webhook_url: None,
+            telegram_parse_mode: None,
+            discord_token: None,
+            discord_channel_ids: Vec::new(),
+            discord_allowed_users: Vec::new(),
+            discord_gateway: false,
+            slack_bot_token: None,
+            slack_channel_ids: Vec::new(),
+            slack_allowed_users: Vec::new(),
+            allowed_read_paths: Vec::new(),
+            allowed_write_paths: Vec::new(),
+            denied_read_paths: Vec::new(),
+            denied_write_paths: Vec::new(),
+            allowed_commands: Vec::new(),
+            allowed_args: Vec::new(),
+            denied_args: Vec::new(),
+            allowed_fetch_hosts: Vec::new(),
+            allowed_network_hosts: Vec::new(),
+            command_timeout: 30,
+            max_command_output: DEFAULT_OUTPUT_LIMIT,
+            max_command_cpu_secs: None,
+            max_command_memory_mb: None,
+            max_command_processes: None,
+            sandbox_commands: false,
+            audit_log_path: None,
+            audit_max_bytes: 0,
+            audit_keep_files: 5,
+            audit_redact: true,
+            audit_webhook_url: None,
+            audit_webhook_batch: 50,
+            audit_webhook_interval: 5,
+            require_approval_for: Vec::new(),
+            approval_timeout: 300,
+            sandbox: true,
+            seccomp_mode: "enforce".into(),
+            skills_dir: None,
+            skill_max_concurrent: 4,
+            skill_max_persistent: 4,
+            skill_concurrency_policy: "queue".into(),
+            compress_requests: false,
+            http_pool_max_per_host: 4,
+            http_pool_idle_timeout: 90,
+            http_connect_timeout: 10,
+            http_read_timeout: 60,
+            http_write_timeout: 30,
+            http_max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            allowed_egress_hosts: Vec::new(),
+            max_retries: 2,
+            retry_base_delay_ms: 500,
+            tls_ca_file: None,
+            tls_insecure_skip_verify: false,
+            config_file: None,
+            secret_files: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_check_passes_for_valid_config() {
+        let mut config = test_config();
+        config.allowed_read_paths = vec!["/tmp".into()];
+        let report = config.check();
+        assert!(report.passed(), "{}", report.render());
+        assert!(report.items.iter().all(|i| i.status == CheckStatus::Ok));
+        assert!(report.render().ends_with("checks: 6 ok, 0 warnings, 0 failed\n"));
+    }
+
+    #[test]
+    fn test_check_reports_each_failure() {
+        let mut config = test_config();
+        config.provider = "antropic".into();
+        config.api_key = String::new();
+        config.allowed_write_paths = vec!["/hom/user/work".into()];
+        config.discord_token = Some("token".into());
+        config.seccomp_mode = "audit".into();
+        config.log_format = "logfmt".into();
+        config.skills_dir = Some("/tmp/sentinel_test_check_no_skills".into());
+        let report = config.check();
+        assert!(!report.passed());
+
+        let status = |name: &str| {
+            report
+                .items
+                .iter()
+                .find(|i| i.name == name)
+                .map(|i| i.status)
+                .unwrap()
+        };
+        assert_eq!(status("provider"), CheckStatus::Fail);
+        assert_eq!(status("api_key"), CheckStatus::Fail);
+        assert_eq!(status("model"), CheckStatus::Ok);
+        assert_eq!(status("allowed_read_paths"), CheckStatus::Ok);
+        assert_eq!(status("allowed_write_paths"), CheckStatus::Fail);
+        assert_eq!(status("discord"), CheckStatus::Warn);
+        assert_eq!(status("seccomp_mode"), CheckStatus::Fail);
+        assert_eq!(status("log_format"), CheckStatus::Fail);
+        assert_eq!(status("skills"), CheckStatus::Fail);
+
+        let rendered = report.render();
+        assert!(rendered.contains("FAIL provider: unknown provider 'antropic'"));
+        assert!(rendered.ends_with("checks: 3 ok, 1 warnings, 6 failed\n"));
+    }
+
+    #[test]
+    fn test_reload_swaps_only_runtime_settings() {
+        let current = test_config();
+        let mut fresh = test_config();
+        fresh.system_prompt = Some("Be brief.".into());
+        fresh.telegram_allowed_users = vec![42];
+        fresh.allowed_commands = vec!["ls".into()];
+        fresh.max_tokens_per_conversation = Some(1000);
+        fresh.api_key = "sk-rotated".into();
+        fresh.model = "claude-opus-4".into();
+
+        let (next, changes) = current.reload_from(&fresh);
+        assert_eq!(next.system_prompt.as_deref(), Some("Be brief."));
+        assert_eq!(next.telegram_allowed_users, vec![42]);
+        assert_eq!(next.allowed_commands, vec!["ls"]);
+        assert_eq!(next.max_tokens_per_conversation, Some(1000));
+        // Provider settings keep their startup values
+        assert_eq!(next.api_key, "sk-test");
+        assert_eq!(next.model, current.model);
+        assert_eq!(
+            changes,
+            vec![
+                "system_prompt updated",
+                "telegram allowed_users updated",
+                "allowed_commands updated",
+                "max_tokens_per_conversation updated",
+                "api_key changed; restart to apply",
+                "model changed; restart to apply",
+            ]
+        );
+
+        let (_, changes) = next.reload_from(&next.clone());
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn test_shared_config_replace_is_seen_by_clones() {
+        let shared = SharedConfig::new(Arc::new(test_config()));
+        let reader = shared.clone();
+        let before = reader.current();
+        let mut next = test_config();
+        next.greeting = Some("Hello again".into());
+        shared.replace(next);
+        assert!(before.greeting.is_none());
+        assert_eq!(reader.current().greeting.as_deref(), Some("Hello again"));
+    }
+
+    #[test]
+    fn test_missing_allowed_path_warns() {
+        let paths = vec!["/tmp".to_string(), "/hom/user".to_string()];
+        let warnings = check_allowed_paths("allowed_read_paths", &paths);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("allowed_read_paths entry '/hom/user' cannot be resolved"));
+
+        // Globs are checked by their base directory
+        let paths = vec!["/tmp/**/*.log".to_string()];
+        assert!(check_allowed_paths("allowed_read_paths", &paths).is_empty());
+        let paths = vec!["/hom/*/notes".to_string()];
+        assert_eq!(check_allowed_paths("allowed_read_paths", &paths).len(), 1);
+    }
+
+    #[test]
+    fn test_glob_rooted_at_slash_is_refused() {
+        let paths = |list: &[&str]| list.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        for bad in ["/**", "/**/*.log", "/*.txt", "/?tc/passwd"] {
+            let err = check_glob_roots("allowed_read_paths", &paths(&["/tmp", bad])).unwrap_err();
+            assert!(err.0.contains(bad), "{}", err.0);
+        }
+        assert!(check_glob_roots("allowed_write_paths", &paths(&["/", "/var/log/**/*.log"])).is_ok());
+    }
+
+    #[test]
+    fn test_network_allowlist_drops_fetch_host_ports() {
+        use crate::security::capability::CapabilityResult;
+
+        let mut config = test_config();
+        config.provider = "echo".into();
+        config.allowed_fetch_hosts = vec!["api.example.com:8443".into(), "[::1]:8080".into()];
+        config.allowed_network_hosts = vec!["other.example".into()];
+        let allowlist = config.network_allowlist();
+        assert!(matches!(allowlist.check_host("api.example.com"), CapabilityResult::Allowed));
+        assert!(matches!(allowlist.check_host("[::1]"), CapabilityResult::Allowed));
+        assert!(matches!(allowlist.check_host("elsewhere.example"), CapabilityResult::Denied(_)));
+    }
 }
@@ -1,17 +1,33 @@
+use crate::log;
 use crate::net::http::HttpClient;
 use crate::net::json::{self, JsonValue, json_obj, json_arr};
 use crate::net::sse;
 use crate::llm::provider::{
-    ContentBlock, LlmError, LlmProvider, LlmResponse, Message, Role, StopReason, ToolDef,
+    filter_extra_headers, report_call, ApiCallHook, ContentBlock, LlmError, LlmProvider,
+    LlmResponse, Message, Role, SamplingParams, StopReason, ToolDef,
 };
+use crate::llm::ratelimit::{self, RateLimitBudget, RateLimiter};
 
 // ── Client ──────────────────────────────────────────────────────────────────
 
+const MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
+/// Headers the client sets itself, which extra headers may not replace.
+const RESERVED_HEADERS: &[&str] = &["x-api-key", "anthropic-version"];
+/// Beta flag enabling `cache_control` breakpoints.
+const PROMPT_CACHING_BETA: &str = "prompt-caching-2024-07-31";
+
 pub struct AnthropicClient {
     http: HttpClient,
     api_key: String,
     model: String,
     max_tokens: u32,
+    parallel_tool_calls: bool,
+    prompt_cache: bool,
+    sampling: SamplingParams,
+    model_fallbacks: Vec<String>,
+    extra_headers: Vec<(String, String)>,
+    rate_limit: RateLimiter,
+    api_call_hook: Option<ApiCallHook>,
 }
 
 impl AnthropicClient {
@@ -26,7 +42,67 @@ impl AnthropicClient {
             api_key,
             model,
             max_tokens,
+            parallel_tool_calls: true,
+            prompt_cache: false,
+            sampling: SamplingParams::default(),
+            model_fallbacks: Vec::new(),
+            extra_headers: Vec::new(),
+            rate_limit: RateLimiter::new(),
+            api_call_hook: None,
+        }
+    }
+
+    /// When false, ask the model for at most one tool call per response.
+    pub fn with_parallel_tool_calls(mut self, enabled: bool) -> Self {
+        self.parallel_tool_calls = enabled;
+        self
+    }
+
+    /// Models to try, in order, when the primary model is overloaded or
+    /// unavailable.
+    pub fn with_model_fallbacks(mut self, models: Vec<String>) -> Self {
+        self.model_fallbacks = models;
+        self
+    }
+
+    /// Temperature, top_p and stop sequences to send with every request.
+    pub fn with_sampling(mut self, sampling: SamplingParams) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Mark the system prompt and tool definitions as cacheable, so the
+    /// stable prefix of each request is billed at the cached rate after the
+    /// first turn.
+    pub fn with_prompt_cache(mut self, enabled: bool) -> Self {
+        self.prompt_cache = enabled;
+        self
+    }
+
+    /// Send these headers with every request, e.g. routing keys required by
+    /// an API gateway. Any that would replace the API key or version header
+    /// are dropped with a warning.
+    pub fn with_extra_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.extra_headers = filter_extra_headers("anthropic", headers, RESERVED_HEADERS);
+        self
+    }
+
+    /// Tell `hook` about every request, e.g. to audit it.
+    pub fn with_api_call_hook(mut self, hook: ApiCallHook) -> Self {
+        self.api_call_hook = Some(hook);
+        self
+    }
+
+    fn request_headers(&self) -> Vec<(&str, &str)> {
+        let mut headers = vec![
+            ("X-Api-Key", self.api_key.as_str()),
+            ("anthropic-version", "2023-06-01"),
+        ];
+        if self.prompt_cache {
+            headers.push(("anthropic-beta", PROMPT_CACHING_BETA));
         }
+        headers.extend(self.extra_headers.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        headers
     }
 
     fn build_request_body(
@@ -40,8 +116,28 @@ impl AnthropicClient {
             .field_i64("max_tokens", self.max_tokens as i64)
             .field_bool("stream", false);
 
+        if let Some(t) = self.sampling.temperature {
+            body = body.field_f64("temperature", t);
+        }
+        if let Some(p) = self.sampling.top_p {
+            body = body.field_f64("top_p", p);
+        }
+        if !self.sampling.stop_sequences.is_empty() {
+            body = body.field("stop_sequences", self.sampling.stop_json());
+        }
+
         if let Some(sys) = system {
-            body = body.field_str("system", sys);
+            if self.prompt_cache {
+                // Only a block-form system prompt can carry a breakpoint
+                let block = json_obj()
+                    .field_str("type", "text")
+                    .field_str("text", sys)
+                    .field("cache_control", ephemeral())
+                    .build();
+                body = body.field("system", json_arr().push(block).build());
+            } else {
+                body = body.field_str("system", sys);
+            }
         }
 
         // Messages
@@ -54,38 +150,90 @@ impl AnthropicClient {
         // Tools
         if !tools.is_empty() {
             let mut tool_arr = json_arr();
-            for t in tools {
-                tool_arr = tool_arr.push(tool_def_to_json(t));
+            for (i, t) in tools.iter().enumerate() {
+                let mut def = tool_def_to_json(t);
+                // A breakpoint on the last tool caches all of them
+                if self.prompt_cache && i == tools.len() - 1 {
+                    if let JsonValue::Object(ref mut pairs) = def {
+                        pairs.push(("cache_control".to_string(), ephemeral()));
+                    }
+                }
+                tool_arr = tool_arr.push(def);
             }
             body = body.field("tools", tool_arr.build());
+            if !self.parallel_tool_calls {
+                body = body.field(
+                    "tool_choice",
+                    json_obj()
+                        .field_str("type", "auto")
+                        .field_bool("disable_parallel_tool_use", true)
+                        .build(),
+                );
+            }
         }
 
         body.build()
     }
 }
 
-impl LlmProvider for AnthropicClient {
-    fn send(
+impl AnthropicClient {
+    /// Run `attempt` against the primary model, then against each fallback
+    /// in turn while the model is overloaded (529) or not found (404).
+    fn with_fallbacks<T>(
         &self,
+        mut attempt: impl FnMut(&str) -> Result<T, LlmError>,
+    ) -> Result<T, LlmError> {
+        let mut models = std::iter::once(&self.model)
+            .chain(&self.model_fallbacks)
+            .peekable();
+        while let Some(model) = models.next() {
+            match attempt(model) {
+                Err(LlmError::Api { status, message }) if is_model_unavailable(status) => {
+                    let next = match models.peek() {
+                        Some(next) => next,
+                        None => return Err(LlmError::Api { status, message }),
+                    };
+                    log::warn(
+                        &format!(
+                            "anthropic model {} unavailable ({}: {}), trying {}",
+                            model, status, message, next
+                        ),
+                        &[("model", model), ("status", &status), ("fallback", next)],
+                    );
+                }
+                Ok(resp) => {
+                    if *model != self.model {
+                        eprintln!("sentinel: response served by fallback model {}", model);
+                    }
+                    return Ok(resp);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!("the primary model is always attempted")
+    }
+
+    fn send_once(
+        &self,
+        model: &str,
         system: Option<&str>,
         messages: &[Message],
         tools: &[ToolDef],
-    ) -> Result<LlmResponse, LlmError> {
-        let body = self.build_request_body(system, messages, tools);
+    ) -> Result<(u16, LlmResponse), LlmError> {
+        let mut body = self.build_request_body(system, messages, tools);
+        set_field(&mut body, "model", JsonValue::String(model.to_string()));
         let body_str = body.to_json_string();
 
-        let headers = [
-            ("X-Api-Key", self.api_key.as_str()),
-            ("anthropic-version", "2023-06-01"),
-        ];
+        let headers = self.request_headers();
 
+        self.rate_limit.wait_if_needed();
         let resp = self
             .http
-            .post_json(
-                "https://api.anthropic.com/v1/messages",
-                &body_str,
-                &headers,
-            )?;
+            .post_json(MESSAGES_URL, &body_str, &headers)?;
+        self.rate_limit.update(RateLimitBudget::from_anthropic_headers(
+            &resp.headers,
+            ratelimit::unix_now(),
+        ));
 
         if resp.status == 429 {
             let retry_after = resp
@@ -96,14 +244,13 @@ impl LlmProvider for AnthropicClient {
             return Err(LlmError::RateLimit { retry_after });
         }
 
-        let body_str = resp.body_string().map_err(|e| LlmError::Http(e))?;
+        let body_str = resp.body_string().map_err(LlmError::Http)?;
         let json_val =
             json::parse(&body_str).map_err(|e| LlmError::Json(e.to_string()))?;
 
         if resp.status != 200 {
             let msg = json_val
-                .get("error")
-                .and_then(|e| e.get("message"))
+                .get_path("error.message")
                 .and_then(|m| m.as_str())
                 .unwrap_or("unknown error");
             return Err(LlmError::Api {
@@ -112,40 +259,32 @@ impl LlmProvider for AnthropicClient {
             });
         }
 
-        parse_api_response(&json_val)
+        Ok((resp.status, parse_api_response(&json_val)?))
     }
 
-    fn send_streaming(
+    fn send_streaming_once(
         &self,
+        model: &str,
         system: Option<&str>,
         messages: &[Message],
         tools: &[ToolDef],
         on_text: &mut dyn FnMut(&str),
-    ) -> Result<LlmResponse, LlmError> {
+    ) -> Result<(u16, LlmResponse), LlmError> {
         let mut body = self.build_request_body(system, messages, tools);
-        // Override stream to true
-        if let JsonValue::Object(ref mut pairs) = body {
-            for (k, v) in pairs.iter_mut() {
-                if k == "stream" {
-                    *v = JsonValue::Bool(true);
-                    break;
-                }
-            }
-        }
+        set_field(&mut body, "model", JsonValue::String(model.to_string()));
+        set_field(&mut body, "stream", JsonValue::Bool(true));
         let body_str = body.to_json_string();
 
-        let headers = [
-            ("X-Api-Key", self.api_key.as_str()),
-            ("anthropic-version", "2023-06-01"),
-        ];
+        let headers = self.request_headers();
 
+        self.rate_limit.wait_if_needed();
         let mut stream_resp = self
             .http
-            .post_json_streaming(
-                "https://api.anthropic.com/v1/messages",
-                &body_str,
-                &headers,
-            )?;
+            .post_json_streaming(MESSAGES_URL, &body_str, &headers)?;
+        self.rate_limit.update(RateLimitBudget::from_anthropic_headers(
+            &stream_resp.headers,
+            ratelimit::unix_now(),
+        ));
 
         if stream_resp.status == 429 {
             let retry_after = stream_resp
@@ -175,12 +314,15 @@ impl LlmProvider for AnthropicClient {
         let mut stop_reason = StopReason::Other("incomplete".into());
         let mut usage_input: i64 = 0;
         let mut usage_output: i64 = 0;
+        let mut usage_cache_creation: i64 = 0;
+        let mut usage_cache_read: i64 = 0;
 
         // Accumulator for the current content block
         let mut current_text = String::new();
         let mut current_tool_id = String::new();
         let mut current_tool_name = String::new();
         let mut current_tool_json = String::new();
+        let mut current_signature = String::new();
         let mut current_block_type = String::new();
 
         loop {
@@ -198,8 +340,15 @@ impl LlmProvider for AnthropicClient {
                     if let Ok(json) = json::parse(data) {
                         if let Some(msg) = json.get("message") {
                             usage_input = msg
-                                .get("usage")
-                                .and_then(|u| u.get("input_tokens"))
+                                .get_path("usage.input_tokens")
+                                .and_then(|v| v.as_i64())
+                                .unwrap_or(0);
+                            usage_cache_creation = msg
+                                .get_path("usage.cache_creation_input_tokens")
+                                .and_then(|v| v.as_i64())
+                                .unwrap_or(0);
+                            usage_cache_read = msg
+                                .get_path("usage.cache_read_input_tokens")
                                 .and_then(|v| v.as_i64())
                                 .unwrap_or(0);
                         }
@@ -227,7 +376,9 @@ impl LlmProvider for AnthropicClient {
                                     .to_string();
                                 current_tool_json.clear();
                             } else {
+                                // Text and thinking both accumulate here
                                 current_text.clear();
+                                current_signature.clear();
                             }
                         }
                     }
@@ -245,6 +396,15 @@ impl LlmProvider for AnthropicClient {
                                     current_text.push_str(text);
                                     on_text(text);
                                 }
+                            } else if delta_type == "thinking_delta" {
+                                // Reasoning is kept, but never streamed to the user
+                                if let Some(t) = delta.get_str("thinking") {
+                                    current_text.push_str(t);
+                                }
+                            } else if delta_type == "signature_delta" {
+                                if let Some(sig) = delta.get_str("signature") {
+                                    current_signature.push_str(sig);
+                                }
                             } else if delta_type == "input_json_delta" {
                                 if let Some(json_part) =
                                     delta.get("partial_json").and_then(|v| v.as_str())
@@ -269,6 +429,11 @@ impl LlmProvider for AnthropicClient {
                             input,
                         });
                         current_tool_json.clear();
+                    } else if current_block_type == "thinking" {
+                        content_blocks.push(ContentBlock::Thinking {
+                            text: std::mem::take(&mut current_text),
+                            signature: std::mem::take(&mut current_signature),
+                        });
                     }
                     current_block_type.clear();
                 }
@@ -285,8 +450,7 @@ impl LlmProvider for AnthropicClient {
                                 };
                         }
                         usage_output = json
-                            .get("usage")
-                            .and_then(|u| u.get("output_tokens"))
+                            .get_path("usage.output_tokens")
                             .and_then(|v| v.as_i64())
                             .unwrap_or(0);
                     }
@@ -300,17 +464,69 @@ impl LlmProvider for AnthropicClient {
             }
         }
 
-        Ok(LlmResponse {
+        let response = LlmResponse {
             stop_reason,
             content: content_blocks,
             usage_input,
             usage_output,
+            usage_cache_creation,
+            usage_cache_read,
+        };
+        Ok((stream_resp.status, response))
+    }
+}
+
+impl LlmProvider for AnthropicClient {
+    fn send(
+        &self,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDef],
+    ) -> Result<LlmResponse, LlmError> {
+        self.with_fallbacks(|model| {
+            report_call(self.api_call_hook.as_ref(), MESSAGES_URL, self.http.retried(), || {
+                self.send_once(model, system, messages, tools)
+            })
         })
     }
+
+    fn send_streaming(
+        &self,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDef],
+        on_text: &mut dyn FnMut(&str),
+    ) -> Result<LlmResponse, LlmError> {
+        self.with_fallbacks(|model| {
+            report_call(self.api_call_hook.as_ref(), MESSAGES_URL, self.http.retried(), || {
+                self.send_streaming_once(model, system, messages, tools, on_text)
+            })
+        })
+    }
+}
+
+/// Errors worth retrying on a different model: 529 is Anthropic's
+/// "overloaded", and a 404 from the messages endpoint means the model isn't
+/// available to this key.
+fn is_model_unavailable(status: u16) -> bool {
+    matches!(status, 404 | 529)
 }
 
 // ── JSON serialization helpers ──────────────────────────────────────────────
 
+/// Replace the value of `key` in an object built by `build_request_body`.
+fn set_field(body: &mut JsonValue, key: &str, value: JsonValue) {
+    if let JsonValue::Object(ref mut pairs) = body {
+        if let Some((_, v)) = pairs.iter_mut().find(|(k, _)| k == key) {
+            *v = value;
+        }
+    }
+}
+
+fn ephemeral() -> JsonValue {
+    json_obj().field_str("type", "ephemeral").build()
+}
+
 fn tool_def_to_json(def: &ToolDef) -> JsonValue {
     json_obj()
         .field_str("name", &def.name)
@@ -362,6 +578,22 @@ fn content_block_to_json(block: &ContentBlock) -> JsonValue {
             }
             b.build()
         }
+        ContentBlock::Thinking { text, signature } => json_obj()
+            .field_str("type", "thinking")
+            .field_str("thinking", text)
+            .field_str("signature", signature)
+            .build(),
+        ContentBlock::Image { media_type, data_base64 } => json_obj()
+            .field_str("type", "image")
+            .field(
+                "source",
+                json_obj()
+                    .field_str("type", "base64")
+                    .field_str("media_type", media_type)
+                    .field_str("data", data_base64)
+                    .build(),
+            )
+            .build(),
     }
 }
 
@@ -378,13 +610,20 @@ fn parse_api_response(json: &JsonValue) -> Result<LlmResponse, LlmError> {
 
     let content = parse_content_blocks(json)?;
 
-    let usage = json.get("usage");
-    let usage_input = usage
-        .and_then(|u| u.get("input_tokens"))
+    let usage_input = json
+        .get_path("usage.input_tokens")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+    let usage_output = json
+        .get_path("usage.output_tokens")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+    let usage_cache_creation = json
+        .get_path("usage.cache_creation_input_tokens")
         .and_then(|v| v.as_i64())
         .unwrap_or(0);
-    let usage_output = usage
-        .and_then(|u| u.get("output_tokens"))
+    let usage_cache_read = json
+        .get_path("usage.cache_read_input_tokens")
         .and_then(|v| v.as_i64())
         .unwrap_or(0);
 
@@ -393,6 +632,8 @@ fn parse_api_response(json: &JsonValue) -> Result<LlmResponse, LlmError> {
         content,
         usage_input,
         usage_output,
+        usage_cache_creation,
+        usage_cache_read,
     })
 }
 
@@ -428,6 +669,12 @@ fn parse_content_blocks(json: &JsonValue) -> Result<Vec<ContentBlock>, LlmError>
                 let input = item.get("input").cloned().unwrap_or(JsonValue::Null);
                 blocks.push(ContentBlock::ToolUse { id, name, input });
             }
+            "thinking" => {
+                blocks.push(ContentBlock::Thinking {
+                    text: item.get_str("thinking").unwrap_or("").to_string(),
+                    signature: item.get_str("signature").unwrap_or("").to_string(),
+                });
+            }
             _ => {
                 // Skip unknown block types
             }
@@ -440,6 +687,38 @@ fn parse_content_blocks(json: &JsonValue) -> Result<Vec<ContentBlock>, LlmError>
 mod tests {
     use super::*;
 
+    fn test_client() -> AnthropicClient {
+        AnthropicClient::new(HttpClient::new().unwrap(), "k".into(), "m".into(), 100)
+    }
+
+    fn echo_tool() -> ToolDef {
+        ToolDef {
+            name: "echo".into(),
+            description: "Echo".into(),
+            input_schema: json_obj().field_str("type", "object").build(),
+        }
+    }
+
+    #[test]
+    fn test_disable_parallel_tool_use_serialized() {
+        let msgs = [Message {
+            role: Role::User,
+            content: vec![ContentBlock::Text { text: "hi".into() }],
+        }];
+
+        let body = test_client().build_request_body(None, &msgs, &[echo_tool()]);
+        assert!(body.get("tool_choice").is_none());
+
+        let client = test_client().with_parallel_tool_calls(false);
+        let body = client.build_request_body(None, &msgs, &[echo_tool()]);
+        let choice = body.get("tool_choice").unwrap();
+        assert_eq!(choice.get("type").unwrap().as_str(), Some("auto"));
+        assert_eq!(choice.get("disable_parallel_tool_use").unwrap().as_bool(), Some(true));
+
+        // No tools, no tool_choice
+        assert!(client.build_request_body(None, &msgs, &[]).get("tool_choice").is_none());
+    }
+
     #[test]
     fn test_parse_end_turn_response() {
         let json_str = r#"{
@@ -517,6 +796,19 @@ mod tests {
         assert_eq!(content[0].get("text").unwrap().as_str().unwrap(), "Hello");
     }
 
+    #[test]
+    fn test_image_block_to_json() {
+        let block = ContentBlock::Image {
+            media_type: "image/png".into(),
+            data_base64: "iVBORw==".into(),
+        };
+        let json = content_block_to_json(&block);
+        assert_eq!(json.get_str("type"), Some("image"));
+        assert_eq!(json.get_path("source.type").and_then(|v| v.as_str()), Some("base64"));
+        assert_eq!(json.get_path("source.media_type").and_then(|v| v.as_str()), Some("image/png"));
+        assert_eq!(json.get_path("source.data").and_then(|v| v.as_str()), Some("iVBORw=="));
+    }
+
     #[test]
     fn test_tool_result_to_json() {
         let block = ContentBlock::ToolResult {
@@ -555,4 +847,156 @@ mod tests {
         assert_eq!(json.get("description").unwrap().as_str().unwrap(), "A test tool");
         assert!(json.get("input_schema").is_some());
     }
+
+    #[test]
+    fn test_extra_headers_sent_after_auth() {
+        let client = test_client().with_extra_headers(vec![
+            ("X-Gateway-Route".to_string(), "team-a".to_string()),
+            ("x-api-key".to_string(), "stolen".to_string()),
+            ("Content-Length".to_string(), "0".to_string()),
+        ]);
+        assert_eq!(
+            client.request_headers(),
+            vec![
+                ("X-Api-Key", "k"),
+                ("anthropic-version", "2023-06-01"),
+                ("X-Gateway-Route", "team-a"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prompt_cache_breakpoints() {
+        let client = test_client().with_prompt_cache(true);
+        let tools = [echo_tool(), echo_tool()];
+        let body = client.build_request_body(Some("sys"), &[], &tools);
+
+        let system = body.get_path("system.0").unwrap();
+        assert_eq!(system.get_str("text"), Some("sys"));
+        assert_eq!(system.get_path("cache_control.type").and_then(|v| v.as_str()), Some("ephemeral"));
+        assert!(body.get_path("tools.0.cache_control").is_none());
+        assert!(body.get_path("tools.1.cache_control").is_some());
+        assert!(client.request_headers().contains(&("anthropic-beta", PROMPT_CACHING_BETA)));
+
+        // Disabled: plain string system prompt, no breakpoints or beta header
+        let client = test_client();
+        let body = client.build_request_body(Some("sys"), &[], &tools);
+        assert_eq!(body.get_str("system"), Some("sys"));
+        assert!(body.get_path("tools.1.cache_control").is_none());
+        assert!(!client.request_headers().iter().any(|(k, _)| *k == "anthropic-beta"));
+    }
+
+    #[test]
+    fn test_parse_cache_usage() {
+        let json = json::parse(
+            r#"{
+                "content": [{"type": "text", "text": "hi"}],
+                "stop_reason": "end_turn",
+                "usage": {
+                    "input_tokens": 12,
+                    "output_tokens": 3,
+                    "cache_creation_input_tokens": 0,
+                    "cache_read_input_tokens": 2048
+                }
+            }"#,
+        )
+        .unwrap();
+        let resp = parse_api_response(&json).unwrap();
+        assert_eq!(resp.usage_input, 12);
+        assert_eq!(resp.usage_cache_creation, 0);
+        assert_eq!(resp.usage_cache_read, 2048);
+    }
+
+    #[test]
+    fn test_sampling_fields_only_when_set() {
+        let body = test_client().build_request_body(None, &[], &[]);
+        assert!(body.get("temperature").is_none());
+        assert!(body.get("top_p").is_none());
+        assert!(body.get("stop_sequences").is_none());
+
+        let client = test_client().with_sampling(SamplingParams {
+            temperature: Some(0.2),
+            top_p: Some(0.9),
+            stop_sequences: vec!["END".into()],
+        });
+        let body = client.build_request_body(None, &[], &[]);
+        assert_eq!(body.get("temperature").and_then(|v| v.as_f64()), Some(0.2));
+        assert_eq!(body.get("top_p").and_then(|v| v.as_f64()), Some(0.9));
+        assert_eq!(body.get_path("stop_sequences.0").and_then(|v| v.as_str()), Some("END"));
+    }
+
+    #[test]
+    fn test_overloaded_model_falls_back_to_next() {
+        let client = test_client()
+            .with_model_fallbacks(vec!["fallback-a".into(), "fallback-b".into()]);
+        let mut tried = Vec::new();
+        let served = client
+            .with_fallbacks(|model| {
+                tried.push(model.to_string());
+                if model == "m" {
+                    Err(LlmError::Api { status: 529, message: "Overloaded".into() })
+                } else {
+                    Ok(model.to_string())
+                }
+            })
+            .unwrap();
+        assert_eq!(served, "fallback-a");
+        assert_eq!(tried, vec!["m", "fallback-a"]);
+    }
+
+    #[test]
+    fn test_fallback_stops_on_other_errors_and_exhaustion() {
+        let client = test_client().with_model_fallbacks(vec!["fallback".into()]);
+
+        // A bad request would fail on any model
+        let mut calls = 0;
+        let result: Result<(), _> = client.with_fallbacks(|_| {
+            calls += 1;
+            Err(LlmError::Api { status: 400, message: "bad".into() })
+        });
+        assert!(matches!(result, Err(LlmError::Api { status: 400, .. })));
+        assert_eq!(calls, 1);
+
+        // Every model unavailable: the last error is returned
+        let result: Result<(), _> = client.with_fallbacks(|model| {
+            Err(LlmError::Api { status: 404, message: model.to_string() })
+        });
+        match result {
+            Err(LlmError::Api { status, message }) => {
+                assert_eq!(status, 404);
+                assert_eq!(message, "fallback");
+            }
+            _ => panic!("expected the last model's error"),
+        }
+    }
+
+    #[test]
+    fn test_thinking_block_parsed_and_echoed() {
+        let json = json::parse(
+            r#"{
+                "content": [
+                    {"type": "thinking", "thinking": "The user wants /tmp.", "signature": "sig123"},
+                    {"type": "tool_use", "id": "t1", "name": "list_directory", "input": {"path": "/tmp"}}
+                ],
+                "stop_reason": "tool_use"
+            }"#,
+        )
+        .unwrap();
+        let resp = parse_api_response(&json).unwrap();
+        assert_eq!(resp.content.len(), 2);
+        match &resp.content[0] {
+            ContentBlock::Thinking { text, signature } => {
+                assert_eq!(text, "The user wants /tmp.");
+                assert_eq!(signature, "sig123");
+            }
+            _ => panic!("expected thinking"),
+        }
+
+        // Sent back unchanged on the next request
+        let echoed = content_block_to_json(&resp.content[0]);
+        assert_eq!(echoed.get_str("type"), Some("thinking"));
+        assert_eq!(echoed.get_str("thinking"), Some("The user wants /tmp."));
+        assert_eq!(echoed.get_str("signature"), Some("sig123"));
+    }
 }
+
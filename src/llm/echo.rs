@@ -0,0 +1,248 @@
+use crate::net::json::{self, JsonValue, json_obj};
+use crate::llm::provider::{
+    ContentBlock, LlmError, LlmProvider, LlmResponse, Message, Role, StopReason, ToolDef,
+};
+
+// ── Offline echo provider ───────────────────────────────────────────────────
+//
+// `provider = "echo"` replaces the LLM with a script, for developing
+// connectors and tools without an API key or network:
+//
+//   hello            → replies "hello"
+//   !read /tmp/x     → calls read_file with {"path": "/tmp/x"}
+//   !run_command {"command": "ls", "args": ["-l"]}
+//                    → calls run_command with that input
+//
+// A `!` word names an offered tool, either exactly or by the part before its
+// first underscore when only one tool matches. Plain text is passed as the
+// tool's first required parameter; a JSON object is passed as the whole
+// input. Once the tool has run, the reply is its output.
+
+pub struct EchoProvider;
+
+impl LlmProvider for EchoProvider {
+    fn send(
+        &self,
+        _system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDef],
+    ) -> Result<LlmResponse, LlmError> {
+        let last = messages
+            .iter()
+            .rev()
+            .find(|m| matches!(m.role, Role::User))
+            .ok_or_else(|| LlmError::Json("no user message to echo".into()))?;
+
+        let results: Vec<String> = last
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::ToolResult { content, is_error: true, .. } => {
+                    Some(format!("error: {}", content))
+                }
+                ContentBlock::ToolResult { content, .. } => Some(content.clone()),
+                _ => None,
+            })
+            .collect();
+        if !results.is_empty() {
+            return Ok(response(StopReason::EndTurn, text(results.join("\n\n"))));
+        }
+
+        let said = user_text(last);
+        match tool_call(&said, tools) {
+            Some(Ok((name, input))) => Ok(response(
+                StopReason::ToolUse,
+                ContentBlock::ToolUse {
+                    // Unique within the conversation, and stable for tests
+                    id: format!("echo_{}", messages.len()),
+                    name,
+                    input,
+                },
+            )),
+            Some(Err(e)) => Ok(response(StopReason::EndTurn, text(e))),
+            None => Ok(response(StopReason::EndTurn, text(said))),
+        }
+    }
+}
+
+fn response(stop_reason: StopReason, block: ContentBlock) -> LlmResponse {
+    LlmResponse {
+        stop_reason,
+        content: vec![block],
+        usage_input: 0,
+        usage_output: 0,
+        usage_cache_creation: 0,
+        usage_cache_read: 0,
+    }
+}
+
+fn text(text: String) -> ContentBlock {
+    ContentBlock::Text { text }
+}
+
+fn user_text(message: &Message) -> String {
+    let parts: Vec<String> = message
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text } => Some(text.clone()),
+            ContentBlock::Image { media_type, .. } => Some(format!("[image ({})]", media_type)),
+            _ => None,
+        })
+        .collect();
+    parts.join("\n")
+}
+
+/// The tool call a `!` message asks for: `None` when it doesn't name an
+/// offered tool, or an error to reply with when its input can't be built.
+fn tool_call(said: &str, tools: &[ToolDef]) -> Option<Result<(String, JsonValue), String>> {
+    let command = said.trim().strip_prefix('!')?;
+    let (word, rest) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+    let tool = find_tool(word, tools)?;
+    let rest = rest.trim();
+
+    let input = if rest.starts_with('{') {
+        match json::parse(rest) {
+            Ok(input) if input.as_object().is_some() => Ok(input),
+            Ok(_) => Err(format!("{} input must be a JSON object", tool.name)),
+            Err(e) => Err(format!("{} input is not valid JSON: {}", tool.name, e)),
+        }
+    } else if rest.is_empty() {
+        Ok(json_obj().build())
+    } else {
+        match first_required(tool) {
+            Some(param) => Ok(json_obj().field_str(param, rest).build()),
+            None => Err(format!("{} has no required parameter to pass {:?} as", tool.name, rest)),
+        }
+    };
+    Some(input.map(|input| (tool.name.clone(), input)))
+}
+
+fn find_tool<'a>(word: &str, tools: &'a [ToolDef]) -> Option<&'a ToolDef> {
+    if let Some(tool) = tools.iter().find(|t| t.name == word) {
+        return Some(tool);
+    }
+    let mut matches = tools
+        .iter()
+        .filter(|t| t.name.split('_').next() == Some(word));
+    match (matches.next(), matches.next()) {
+        (Some(tool), None) => Some(tool),
+        _ => None,
+    }
+}
+
+fn first_required(tool: &ToolDef) -> Option<&str> {
+    tool.input_schema
+        .get("required")
+        .and_then(|r| r.as_array())
+        .and_then(|r| r.first())
+        .and_then(|p| p.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::json::json_arr;
+
+    fn tool(name: &str, required: &[&str]) -> ToolDef {
+        let required = required.iter().fold(json_arr(), |arr, p| arr.push_str(p));
+        ToolDef {
+            name: name.into(),
+            description: String::new(),
+            input_schema: json_obj().field("required", required.build()).build(),
+        }
+    }
+
+    fn tools() -> Vec<ToolDef> {
+        vec![
+            tool("read_file", &["path"]),
+            tool("run_command", &["command"]),
+            tool("list_directory", &["path"]),
+            tool("list_skills", &[]),
+        ]
+    }
+
+    fn user(text: &str) -> Message {
+        Message {
+            role: Role::User,
+            content: vec![ContentBlock::Text { text: text.into() }],
+        }
+    }
+
+    #[test]
+    fn test_echoes_last_user_message() {
+        let messages = vec![user("first"), user("hello there")];
+        let resp = EchoProvider.send(None, &messages, &tools()).unwrap();
+        assert!(matches!(resp.stop_reason, StopReason::EndTurn));
+        assert_eq!(resp.content, vec![ContentBlock::Text { text: "hello there".into() }]);
+    }
+
+    #[test]
+    fn test_bang_command_calls_tool() {
+        let resp = EchoProvider.send(None, &[user("!read /tmp/x")], &tools()).unwrap();
+        assert!(matches!(resp.stop_reason, StopReason::ToolUse));
+        match &resp.content[0] {
+            ContentBlock::ToolUse { id, name, input } => {
+                assert_eq!(id, "echo_1");
+                assert_eq!(name, "read_file");
+                assert_eq!(input.get_str("path"), Some("/tmp/x"));
+            }
+            other => panic!("expected a tool call, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bang_command_with_json_input() {
+        let said = "!run_command {\"command\": \"ls\", \"args\": [\"-l\"]}";
+        let resp = EchoProvider.send(None, &[user(said)], &tools()).unwrap();
+        match &resp.content[0] {
+            ContentBlock::ToolUse { name, input, .. } => {
+                assert_eq!(name, "run_command");
+                assert_eq!(input.get_str("command"), Some("ls"));
+                assert_eq!(input.get("args").and_then(|a| a.as_array()).map(|a| a.len()), Some(1));
+            }
+            other => panic!("expected a tool call, got {:?}", other),
+        }
+
+        let resp = EchoProvider.send(None, &[user("!run {oops")], &tools()).unwrap();
+        assert!(matches!(&resp.content[0], ContentBlock::Text { text } if text.contains("not valid JSON")));
+    }
+
+    #[test]
+    fn test_unknown_or_ambiguous_command_is_echoed() {
+        // "list" matches two tools, "fly" none
+        for said in ["!list /tmp", "!fly away"] {
+            let resp = EchoProvider.send(None, &[user(said)], &tools()).unwrap();
+            assert_eq!(resp.content, vec![ContentBlock::Text { text: said.into() }]);
+        }
+    }
+
+    #[test]
+    fn test_replies_with_tool_results() {
+        let messages = vec![
+            user("!read /tmp/x"),
+            Message {
+                role: Role::Assistant,
+                content: vec![ContentBlock::ToolUse {
+                    id: "echo_1".into(),
+                    name: "read_file".into(),
+                    input: json_obj().field_str("path", "/tmp/x").build(),
+                }],
+            },
+            Message {
+                role: Role::User,
+                content: vec![ContentBlock::ToolResult {
+                    tool_use_id: "echo_1".into(),
+                    content: "path not allowed".into(),
+                    is_error: true,
+                }],
+            },
+        ];
+        let resp = EchoProvider.send(None, &messages, &tools()).unwrap();
+        assert!(matches!(resp.stop_reason, StopReason::EndTurn));
+        assert_eq!(
+            resp.content,
+            vec![ContentBlock::Text { text: "error: path not allowed".into() }]
+        );
+    }
+}
@@ -0,0 +1,499 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::net::http::HttpClient;
+use crate::net::json::{self, JsonValue, json_obj, json_arr};
+use crate::llm::provider::{
+    report_call, ApiCallHook, ContentBlock, LlmError, LlmProvider, LlmResponse, Message, Role,
+    SamplingParams, StopReason, ToolDef,
+};
+
+pub(crate) const GEMINI_API: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Source of tool call IDs for models that don't return their own. Gemini
+/// matches results to calls by name, but the agent loop needs unique IDs.
+static NEXT_CALL_ID: AtomicU64 = AtomicU64::new(1);
+
+// ── Google Gemini client ────────────────────────────────────────────────────
+
+pub struct GeminiClient {
+    http: HttpClient,
+    api_key: String,
+    model: String,
+    max_tokens: u32,
+    sampling: SamplingParams,
+    api_call_hook: Option<ApiCallHook>,
+}
+
+impl GeminiClient {
+    pub fn new(http: HttpClient, api_key: String, model: String, max_tokens: u32) -> Self {
+        GeminiClient {
+            http,
+            api_key,
+            model,
+            max_tokens,
+            sampling: SamplingParams::default(),
+            api_call_hook: None,
+        }
+    }
+
+    /// Temperature, top_p and stop sequences to send with every request.
+    pub fn with_sampling(mut self, sampling: SamplingParams) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Tell `hook` about every request, e.g. to audit it.
+    pub fn with_api_call_hook(mut self, hook: ApiCallHook) -> Self {
+        self.api_call_hook = Some(hook);
+        self
+    }
+
+    fn build_request_body(
+        &self,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDef],
+    ) -> JsonValue {
+        let mut body = json_obj().field("contents", contents_to_json(messages));
+
+        if let Some(sys) = system {
+            body = body.field(
+                "systemInstruction",
+                json_obj()
+                    .field("parts", json_arr().push(text_part(sys)).build())
+                    .build(),
+            );
+        }
+
+        if !tools.is_empty() {
+            let mut decls = json_arr();
+            for t in tools {
+                decls = decls.push(tool_def_to_json(t));
+            }
+            body = body.field(
+                "tools",
+                json_arr()
+                    .push(json_obj().field("function_declarations", decls.build()).build())
+                    .build(),
+            );
+        }
+
+        let mut generation = json_obj().field_i64("maxOutputTokens", self.max_tokens as i64);
+        if let Some(t) = self.sampling.temperature {
+            generation = generation.field_f64("temperature", t);
+        }
+        if let Some(p) = self.sampling.top_p {
+            generation = generation.field_f64("topP", p);
+        }
+        if !self.sampling.stop_sequences.is_empty() {
+            generation = generation.field("stopSequences", self.sampling.stop_json());
+        }
+        body.field("generationConfig", generation.build()).build()
+    }
+}
+
+impl LlmProvider for GeminiClient {
+    fn send(
+        &self,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDef],
+    ) -> Result<LlmResponse, LlmError> {
+        let body = self.build_request_body(system, messages, tools);
+        let body_str = body.to_json_string();
+
+        // The key is a query parameter, so it stays out of what's reported
+        let endpoint = format!("{}/models/{}:generateContent", GEMINI_API, self.model);
+        report_call(self.api_call_hook.as_ref(), &endpoint, self.http.retried(), || {
+            self.send_once(&endpoint, &body_str)
+        })
+    }
+}
+
+impl GeminiClient {
+    fn send_once(
+        &self,
+        endpoint: &str,
+        body_str: &str,
+    ) -> Result<(u16, LlmResponse), LlmError> {
+        let url = format!("{}?key={}", endpoint, self.api_key);
+        let resp = self.http.post_json(&url, body_str, &[])?;
+
+        if resp.status == 429 {
+            let retry_after = resp
+                .headers
+                .iter()
+                .find(|(k, _)| k == "retry-after")
+                .and_then(|(_, v)| v.parse::<u64>().ok());
+            return Err(LlmError::RateLimit { retry_after });
+        }
+
+        let body_str = resp.body_string().map_err(LlmError::Http)?;
+        let json_val =
+            json::parse(&body_str).map_err(|e| LlmError::Json(e.to_string()))?;
+
+        if resp.status != 200 {
+            let msg = json_val
+                .get_path("error.message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("unknown error");
+            return Err(LlmError::Api {
+                status: resp.status,
+                message: msg.to_string(),
+            });
+        }
+
+        Ok((resp.status, parse_gemini_response(&json_val)?))
+    }
+}
+
+// ── JSON serialization (Sentinel → Gemini format) ───────────────────────────
+
+fn text_part(text: &str) -> JsonValue {
+    json_obj().field_str("text", text).build()
+}
+
+/// Gemini rejects an OBJECT schema with no properties, so tools without
+/// parameters are declared without a schema.
+fn tool_def_to_json(def: &ToolDef) -> JsonValue {
+    let mut decl = json_obj()
+        .field_str("name", &def.name)
+        .field_str("description", &def.description);
+    let has_properties = def
+        .input_schema
+        .get("properties")
+        .and_then(|p| p.as_object())
+        .is_some_and(|p| !p.is_empty());
+    if has_properties {
+        decl = decl.field("parameters", def.input_schema.clone());
+    }
+    decl.build()
+}
+
+/// Convert the history to `contents`. A `functionResponse` carries the
+/// function name rather than a call ID, so names are looked up from the
+/// `ToolUse` blocks seen so far.
+fn contents_to_json(messages: &[Message]) -> JsonValue {
+    let mut call_names: HashMap<&str, &str> = HashMap::new();
+    let mut contents = json_arr();
+
+    for msg in messages {
+        let role = match msg.role {
+            Role::User => "user",
+            Role::Assistant => "model",
+        };
+        let mut parts = json_arr();
+        let mut empty = true;
+        for block in &msg.content {
+            let part = match block {
+                ContentBlock::Text { text } => {
+                    if text.is_empty() {
+                        continue;
+                    }
+                    text_part(text)
+                }
+                // Anthropic reasoning; Gemini can't verify its signature
+                ContentBlock::Thinking { .. } => continue,
+                ContentBlock::Image { media_type, .. } => {
+                    text_part(&ContentBlock::image_note(media_type))
+                }
+                ContentBlock::ToolUse { id, name, input } => {
+                    call_names.insert(id, name);
+                    let args = match input {
+                        JsonValue::Object(_) => input.clone(),
+                        _ => json_obj().build(),
+                    };
+                    json_obj()
+                        .field(
+                            "functionCall",
+                            json_obj().field_str("name", name).field("args", args).build(),
+                        )
+                        .build()
+                }
+                ContentBlock::ToolResult {
+                    tool_use_id,
+                    content,
+                    is_error,
+                } => {
+                    let name = call_names.get(tool_use_id.as_str()).copied().unwrap_or("");
+                    let key = if *is_error { "error" } else { "content" };
+                    json_obj()
+                        .field(
+                            "functionResponse",
+                            json_obj()
+                                .field_str("name", name)
+                                .field("response", json_obj().field_str(key, content).build())
+                                .build(),
+                        )
+                        .build()
+                }
+            };
+            parts = parts.push(part);
+            empty = false;
+        }
+        // Gemini rejects contents without parts
+        if empty {
+            continue;
+        }
+        contents = contents.push(
+            json_obj()
+                .field_str("role", role)
+                .field("parts", parts.build())
+                .build(),
+        );
+    }
+
+    contents.build()
+}
+
+// ── Response parsing (Gemini → Sentinel format) ─────────────────────────────
+
+fn parse_gemini_response(json: &JsonValue) -> Result<LlmResponse, LlmError> {
+    let candidate = match json.get_path("candidates.0") {
+        Some(c) => c,
+        None => {
+            let reason = json
+                .get_path("promptFeedback.blockReason")
+                .and_then(|v| v.as_str());
+            return Err(match reason {
+                Some(r) => LlmError::Api {
+                    status: 200,
+                    message: format!("prompt blocked: {}", r),
+                },
+                None => LlmError::Json("missing 'candidates' array".into()),
+            });
+        }
+    };
+
+    let mut content = Vec::new();
+    let mut text = String::new();
+    let parts = candidate
+        .get_path("content.parts")
+        .and_then(|v| v.as_array())
+        .map(|p| p.as_slice())
+        .unwrap_or(&[]);
+    for part in parts {
+        // Thought summaries are the model's reasoning, not its answer
+        if part.get("thought").and_then(|v| v.as_bool()) == Some(true) {
+            continue;
+        }
+        if let Some(t) = part.get_str("text") {
+            text.push_str(t);
+        } else if let Some(call) = part.get("functionCall") {
+            let id = match call.get_str("id") {
+                Some(id) => id.to_string(),
+                None => format!("gemini_call_{}", NEXT_CALL_ID.fetch_add(1, Ordering::Relaxed)),
+            };
+            content.push(ContentBlock::ToolUse {
+                id,
+                name: call.get_str("name").unwrap_or("").to_string(),
+                input: call.get("args").cloned().unwrap_or_else(|| json_obj().build()),
+            });
+        }
+    }
+    if !text.is_empty() {
+        content.insert(0, ContentBlock::Text { text });
+    }
+
+    let has_calls = content
+        .iter()
+        .any(|b| matches!(b, ContentBlock::ToolUse { .. }));
+    // Gemini reports STOP even when the response is a function call
+    let stop_reason = match candidate.get_str("finishReason") {
+        _ if has_calls => StopReason::ToolUse,
+        Some("STOP") => StopReason::EndTurn,
+        Some("MAX_TOKENS") => StopReason::MaxTokens,
+        Some(other) => StopReason::Other(other.to_string()),
+        None => StopReason::Other("missing".into()),
+    };
+
+    let usage_input = json
+        .get_path("usageMetadata.promptTokenCount")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+    let usage_output = json
+        .get_path("usageMetadata.candidatesTokenCount")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+
+    Ok(LlmResponse {
+        stop_reason,
+        content,
+        usage_input,
+        usage_output,
+        usage_cache_creation: 0,
+        usage_cache_read: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gemini_text_response() {
+        let json_str = r#"{
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": "Hello"}, {"text": "!"}]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {"promptTokenCount": 10, "candidatesTokenCount": 5}
+        }"#;
+        let json = json::parse(json_str).unwrap();
+        let resp = parse_gemini_response(&json).unwrap();
+
+        assert!(matches!(resp.stop_reason, StopReason::EndTurn));
+        assert_eq!(resp.content.len(), 1);
+        match &resp.content[0] {
+            ContentBlock::Text { text } => assert_eq!(text, "Hello!"),
+            _ => panic!("expected text"),
+        }
+        assert_eq!(resp.usage_input, 10);
+        assert_eq!(resp.usage_output, 5);
+    }
+
+    #[test]
+    fn test_parse_gemini_function_call() {
+        let json_str = r#"{
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"text": "Let me look.", "thought": true},
+                    {"functionCall": {"name": "read_file", "args": {"path": "/tmp/test"}}},
+                    {"functionCall": {"name": "list_directory", "args": {"path": "/tmp"}}}
+                ]},
+                "finishReason": "STOP"
+            }]
+        }"#;
+        let json = json::parse(json_str).unwrap();
+        let resp = parse_gemini_response(&json).unwrap();
+
+        assert!(matches!(resp.stop_reason, StopReason::ToolUse));
+        assert_eq!(resp.content.len(), 2);
+        let ids: Vec<&str> = resp
+            .content
+            .iter()
+            .map(|b| match b {
+                ContentBlock::ToolUse { id, .. } => id.as_str(),
+                _ => panic!("expected tool_use"),
+            })
+            .collect();
+        assert_ne!(ids[0], ids[1]);
+        match &resp.content[0] {
+            ContentBlock::ToolUse { name, input, .. } => {
+                assert_eq!(name, "read_file");
+                assert_eq!(input.get_str("path"), Some("/tmp/test"));
+            }
+            _ => panic!("expected tool_use"),
+        }
+    }
+
+    #[test]
+    fn test_parse_gemini_max_tokens_and_blocked() {
+        let json = json::parse(
+            r#"{"candidates": [{"content": {"parts": [{"text": "trunc"}]}, "finishReason": "MAX_TOKENS"}]}"#,
+        )
+        .unwrap();
+        let resp = parse_gemini_response(&json).unwrap();
+        assert!(matches!(resp.stop_reason, StopReason::MaxTokens));
+
+        let json = json::parse(r#"{"promptFeedback": {"blockReason": "SAFETY"}}"#).unwrap();
+        match parse_gemini_response(&json) {
+            Err(LlmError::Api { message, .. }) => assert!(message.contains("SAFETY")),
+            other => panic!("expected blocked error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tool_result_uses_call_name() {
+        let messages = vec![
+            Message {
+                role: Role::Assistant,
+                content: vec![ContentBlock::ToolUse {
+                    id: "c1".into(),
+                    name: "read_file".into(),
+                    input: json::parse(r#"{"path":"/a"}"#).unwrap(),
+                }],
+            },
+            Message {
+                role: Role::User,
+                content: vec![ContentBlock::ToolResult {
+                    tool_use_id: "c1".into(),
+                    content: "denied".into(),
+                    is_error: true,
+                }],
+            },
+        ];
+        let contents = contents_to_json(&messages);
+        assert_eq!(
+            contents.get_path("0.role").and_then(|v| v.as_str()),
+            Some("model")
+        );
+        assert_eq!(
+            contents
+                .get_path("0.parts.0.functionCall.name")
+                .and_then(|v| v.as_str()),
+            Some("read_file")
+        );
+        let response = contents.get_path("1.parts.0.functionResponse").unwrap();
+        assert_eq!(response.get_str("name"), Some("read_file"));
+        assert_eq!(response.get_path("response.error").and_then(|v| v.as_str()), Some("denied"));
+    }
+
+    #[test]
+    fn test_request_body_shape() {
+        let client = GeminiClient::new(
+            HttpClient::new().unwrap(),
+            "k".into(),
+            "gemini-2.0-flash".into(),
+            256,
+        );
+        let tools = vec![
+            ToolDef {
+                name: "read_file".into(),
+                description: "Read".into(),
+                input_schema: json::parse(
+                    r#"{"type":"object","properties":{"path":{"type":"string"}},"required":["path"]}"#,
+                )
+                .unwrap(),
+            },
+            ToolDef {
+                name: "ping".into(),
+                description: "No parameters".into(),
+                input_schema: json::parse(r#"{"type":"object","properties":{}}"#).unwrap(),
+            },
+        ];
+        let body = client.build_request_body(Some("be brief"), &[], &tools);
+
+        assert_eq!(
+            body.get_path("systemInstruction.parts.0.text").and_then(|v| v.as_str()),
+            Some("be brief")
+        );
+        assert_eq!(
+            body.get_path("generationConfig.maxOutputTokens").and_then(|v| v.as_i64()),
+            Some(256)
+        );
+        let decls = body.get_path("tools.0.function_declarations").unwrap();
+        assert!(decls.get_path("0.parameters.properties.path").is_some());
+        assert!(decls.get_path("1.parameters").is_none());
+    }
+
+    #[test]
+    fn test_sampling_in_generation_config() {
+        let client = GeminiClient::new(HttpClient::new().unwrap(), "k".into(), "m".into(), 64);
+        let body = client.build_request_body(None, &[], &[]);
+        assert!(body.get_path("generationConfig.temperature").is_none());
+
+        let body = client
+            .with_sampling(SamplingParams {
+                temperature: Some(1.5),
+                top_p: Some(0.5),
+                stop_sequences: vec!["END".into()],
+            })
+            .build_request_body(None, &[], &[]);
+        let generation = body.get("generationConfig").unwrap();
+        assert_eq!(generation.get("temperature").and_then(|v| v.as_f64()), Some(1.5));
+        assert_eq!(generation.get("topP").and_then(|v| v.as_f64()), Some(0.5));
+        assert_eq!(generation.get_path("stopSequences.0").and_then(|v| v.as_str()), Some("END"));
+    }
+}
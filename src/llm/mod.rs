@@ -1,5 +1,9 @@
+pub mod echo;
 pub mod provider;
+pub mod ratelimit;
 #[cfg(feature = "tls")]
 pub mod anthropic;
 #[cfg(feature = "tls")]
 pub mod openai;
+#[cfg(feature = "tls")]
+pub mod gemini;
@@ -1,19 +1,62 @@
-use crate::net::http::HttpClient;
+use crate::net::http::{url_host, HttpClient};
 use crate::net::json::{self, JsonValue, json_obj, json_arr};
+use crate::net::sse;
 use crate::llm::provider::{
-    ContentBlock, LlmError, LlmProvider, LlmResponse, Message, Role, StopReason, ToolDef,
+    filter_extra_headers, report_call, ApiCallHook, ContentBlock, LlmError, LlmProvider,
+    LlmResponse, Message, Role, SamplingParams, StopReason, ToolDef,
 };
+use crate::llm::ratelimit::{RateLimitBudget, RateLimiter};
 
 // ── OpenAI-compatible client ────────────────────────────────────────────────
 //
 // Works with OpenAI, Ollama, vLLM, LM Studio, and other OpenAI-compatible APIs.
 
+/// Which request shape a model takes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ModelFamily {
+    Standard,
+    /// o1, o3 and later reasoning models: `max_completion_tokens` instead
+    /// of `max_tokens`, a `developer` message instead of `system`, and no
+    /// sampling settings.
+    Reasoning,
+}
+
+impl ModelFamily {
+    /// Parse a config value ("standard" or "reasoning", case-insensitive).
+    pub fn from_name(name: &str) -> Option<ModelFamily> {
+        match name.to_ascii_lowercase().as_str() {
+            "standard" => Some(ModelFamily::Standard),
+            "reasoning" => Some(ModelFamily::Reasoning),
+            _ => None,
+        }
+    }
+
+    /// Guess from the model name: "o" and a digit ("o1", "o3-mini"), after
+    /// any gateway prefix such as "openai/".
+    pub fn detect(model: &str) -> ModelFamily {
+        let name = model.rsplit('/').next().unwrap_or(model);
+        let mut chars = name.chars();
+        match (chars.next(), chars.next()) {
+            (Some('o' | 'O'), Some(c)) if c.is_ascii_digit() => ModelFamily::Reasoning,
+            _ => ModelFamily::Standard,
+        }
+    }
+}
+
 pub struct OpenAiClient {
     http: HttpClient,
     api_key: String,
     model: String,
+    family: ModelFamily,
     max_tokens: u32,
     base_url: String,
+    parallel_tool_calls: bool,
+    sampling: SamplingParams,
+    extra_headers: Vec<(String, String)>,
+    /// Send the key in Azure's `api-key` header instead of as a bearer token.
+    azure: bool,
+    rate_limit: RateLimiter,
+    api_call_hook: Option<ApiCallHook>,
 }
 
 impl OpenAiClient {
@@ -29,10 +72,71 @@ impl OpenAiClient {
         OpenAiClient {
             http,
             api_key,
+            family: ModelFamily::detect(&model),
             model,
             max_tokens,
             base_url,
+            parallel_tool_calls: true,
+            sampling: SamplingParams::default(),
+            extra_headers: Vec::new(),
+            azure: false,
+            rate_limit: RateLimiter::new(),
+            api_call_hook: None,
+        }
+    }
+
+    /// Override the family guessed from the model name, for models whose
+    /// names don't give it away.
+    pub fn with_model_family(mut self, family: Option<ModelFamily>) -> Self {
+        if let Some(family) = family {
+            self.family = family;
         }
+        self
+    }
+
+    /// When false, send `parallel_tool_calls: false` so the model makes at
+    /// most one tool call per response.
+    pub fn with_parallel_tool_calls(mut self, enabled: bool) -> Self {
+        self.parallel_tool_calls = enabled;
+        self
+    }
+
+    /// Temperature, top_p and stop sequences to send with every request.
+    pub fn with_sampling(mut self, sampling: SamplingParams) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Send these headers with every request, e.g. `HTTP-Referer` and
+    /// `X-Title` for OpenRouter. Any that would replace the Authorization
+    /// or api-key header are dropped with a warning.
+    pub fn with_extra_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.extra_headers = filter_extra_headers("openai", headers, &["authorization", "api-key"]);
+        self
+    }
+
+    /// Talk to Azure OpenAI: the key goes in an `api-key` header, and the
+    /// base URL is a deployment URL with its `api-version` query, e.g.
+    /// `https://res.openai.azure.com/openai/deployments/gpt-4o?api-version=2024-10-21`.
+    pub fn with_azure(mut self, enabled: bool) -> Self {
+        self.azure = enabled;
+        self
+    }
+
+    /// Tell `hook` about every request, e.g. to audit it.
+    pub fn with_api_call_hook(mut self, hook: ApiCallHook) -> Self {
+        self.api_call_hook = Some(hook);
+        self
+    }
+
+    fn request_headers<'a>(&'a self, auth_value: &'a str) -> Vec<(&'a str, &'a str)> {
+        let mut headers = if self.azure {
+            vec![("api-key", self.api_key.as_str())]
+        } else {
+            vec![("Authorization", auth_value)]
+        };
+        headers.extend(self.extra_headers.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        headers
     }
 
     fn build_request_body(
@@ -41,9 +145,26 @@ impl OpenAiClient {
         messages: &[Message],
         tools: &[ToolDef],
     ) -> JsonValue {
-        let mut body = json_obj()
-            .field_str("model", &self.model)
-            .field_i64("max_tokens", self.max_tokens as i64);
+        let reasoning = self.family == ModelFamily::Reasoning;
+        let mut body = json_obj().field_str("model", &self.model);
+        body = if reasoning {
+            body.field_i64("max_completion_tokens", self.max_tokens as i64)
+        } else {
+            body.field_i64("max_tokens", self.max_tokens as i64)
+        };
+
+        // Reasoning models reject temperature and top_p
+        if !reasoning {
+            if let Some(t) = self.sampling.temperature {
+                body = body.field_f64("temperature", t);
+            }
+            if let Some(p) = self.sampling.top_p {
+                body = body.field_f64("top_p", p);
+            }
+        }
+        if !self.sampling.stop_sequences.is_empty() {
+            body = body.field("stop", self.sampling.stop_json());
+        }
 
         // Messages
         let mut msgs = json_arr();
@@ -52,14 +173,16 @@ impl OpenAiClient {
         if let Some(sys) = system {
             msgs = msgs.push(
                 json_obj()
-                    .field_str("role", "system")
+                    .field_str("role", if reasoning { "developer" } else { "system" })
                     .field_str("content", sys)
                     .build(),
             );
         }
 
         for msg in messages {
-            msgs = msgs.push(message_to_openai_json(msg));
+            for json in message_to_openai_json(msg) {
+                msgs = msgs.push(json);
+            }
         }
         body = body.field("messages", msgs.build());
 
@@ -82,6 +205,9 @@ impl OpenAiClient {
                 );
             }
             body = body.field("tools", tool_arr.build());
+            if !self.parallel_tool_calls {
+                body = body.field_bool("parallel_tool_calls", false);
+            }
         }
 
         body.build()
@@ -95,14 +221,44 @@ impl LlmProvider for OpenAiClient {
         messages: &[Message],
         tools: &[ToolDef],
     ) -> Result<LlmResponse, LlmError> {
+        let url = endpoint_url(&self.base_url, "/chat/completions");
+        report_call(self.api_call_hook.as_ref(), &url, self.http.retried(), || {
+            self.send_once(&url, system, messages, tools)
+        })
+    }
+
+    fn send_streaming(
+        &self,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDef],
+        on_text: &mut dyn FnMut(&str),
+    ) -> Result<LlmResponse, LlmError> {
+        let url = endpoint_url(&self.base_url, "/chat/completions");
+        report_call(self.api_call_hook.as_ref(), &url, self.http.retried(), || {
+            self.send_streaming_once(&url, system, messages, tools, on_text)
+        })
+    }
+}
+
+impl OpenAiClient {
+    fn send_once(
+        &self,
+        url: &str,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDef],
+    ) -> Result<(u16, LlmResponse), LlmError> {
         let body = self.build_request_body(system, messages, tools);
         let body_str = body.to_json_string();
 
-        let url = format!("{}/chat/completions", self.base_url);
         let auth_value = format!("Bearer {}", self.api_key);
-        let headers = [("Authorization", auth_value.as_str())];
+        let headers = self.request_headers(&auth_value);
 
-        let resp = self.http.post_json(&url, &body_str, &headers)?;
+        self.rate_limit.wait_if_needed();
+        let resp = self.http.post_json(url, &body_str, &headers)?;
+        self.rate_limit
+            .update(RateLimitBudget::from_openai_headers(&resp.headers));
 
         if resp.status == 429 {
             let retry_after = resp
@@ -113,14 +269,13 @@ impl LlmProvider for OpenAiClient {
             return Err(LlmError::RateLimit { retry_after });
         }
 
-        let body_str = resp.body_string().map_err(|e| LlmError::Http(e))?;
+        let body_str = resp.body_string().map_err(LlmError::Http)?;
         let json_val =
             json::parse(&body_str).map_err(|e| LlmError::Json(e.to_string()))?;
 
         if resp.status != 200 {
             let msg = json_val
-                .get("error")
-                .and_then(|e| e.get("message"))
+                .get_path("error.message")
                 .and_then(|m| m.as_str())
                 .unwrap_or("unknown error");
             return Err(LlmError::Api {
@@ -129,33 +284,139 @@ impl LlmProvider for OpenAiClient {
             });
         }
 
-        parse_openai_response(&json_val)
+        Ok((resp.status, parse_openai_response(&json_val)?))
+    }
+
+    /// The request body with streaming on. Usage is only reported in a
+    /// stream when asked for, in a last chunk with no choices. Some
+    /// compatible servers reject `stream_options`, so it is only sent to
+    /// OpenAI itself.
+    fn build_streaming_request_body(
+        &self,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDef],
+    ) -> JsonValue {
+        let mut body = self.build_request_body(system, messages, tools);
+        if let JsonValue::Object(ref mut pairs) = body {
+            pairs.push(("stream".to_string(), JsonValue::Bool(true)));
+            if self.is_openai() {
+                pairs.push((
+                    "stream_options".to_string(),
+                    json_obj().field_bool("include_usage", true).build(),
+                ));
+            }
+        }
+        body
+    }
+
+    fn is_openai(&self) -> bool {
+        !self.azure
+            && url_host(&self.base_url).is_ok_and(|(host, _)| host == "api.openai.com")
+    }
+
+    fn send_streaming_once(
+        &self,
+        url: &str,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDef],
+        on_text: &mut dyn FnMut(&str),
+    ) -> Result<(u16, LlmResponse), LlmError> {
+        let body_str = self
+            .build_streaming_request_body(system, messages, tools)
+            .to_json_string();
+
+        let auth_value = format!("Bearer {}", self.api_key);
+        let headers = self.request_headers(&auth_value);
+
+        self.rate_limit.wait_if_needed();
+        let mut stream_resp = self.http.post_json_streaming(url, &body_str, &headers)?;
+        self.rate_limit
+            .update(RateLimitBudget::from_openai_headers(&stream_resp.headers));
+
+        if stream_resp.status == 429 {
+            let retry_after = stream_resp
+                .headers
+                .iter()
+                .find(|(k, _)| k == "retry-after")
+                .and_then(|(_, v)| v.parse::<u64>().ok());
+            return Err(LlmError::RateLimit { retry_after });
+        }
+
+        if stream_resp.status != 200 {
+            // Read the error body
+            let mut error_data = String::new();
+            for _ in 0..100 {
+                let line = stream_resp.read_line().map_err(LlmError::Http)?;
+                if line.is_empty() { break; }
+                error_data.push_str(&line);
+            }
+            let message = json::parse(&error_data)
+                .ok()
+                .and_then(|j| j.get_path("error.message").and_then(|m| m.as_str()).map(String::from))
+                .unwrap_or(error_data);
+            return Err(LlmError::Api {
+                status: stream_resp.status,
+                message,
+            });
+        }
+
+        let mut acc = StreamAccumulator::default();
+        loop {
+            let event = match sse::read_event(&mut stream_resp) {
+                Ok(Some(e)) => e,
+                Ok(None) => break,
+                Err(e) => return Err(LlmError::Http(e)),
+            };
+            if acc.push_chunk(&event.data, on_text)? {
+                break;
+            }
+        }
+        Ok((stream_resp.status, acc.finish()))
     }
 }
 
+/// `path` appended to the path of `base_url`, keeping any query string at
+/// the end: `https://h/v1?x=1` and `/models` make `https://h/v1/models?x=1`.
+pub(crate) fn endpoint_url(base_url: &str, path: &str) -> String {
+    let (base, query) = base_url.split_at(base_url.find('?').unwrap_or(base_url.len()));
+    format!("{}{}{}", base.trim_end_matches('/'), path, query)
+}
+
 // ── JSON serialization (Sentinel → OpenAI format) ───────────────────────────
 
-fn message_to_openai_json(msg: &Message) -> JsonValue {
+/// One message in OpenAI's format. That is usually one JSON message, but
+/// tool results each become their own `role: "tool"` message, followed by a
+/// user message for any text sent alongside them.
+fn message_to_openai_json(msg: &Message) -> Vec<JsonValue> {
     let role = match msg.role {
         Role::User => "user",
         Role::Assistant => "assistant",
     };
 
-    // Check if this message contains tool results (user role with ToolResult blocks)
     let has_tool_results = msg.content.iter().any(|b| matches!(b, ContentBlock::ToolResult { .. }));
     if has_tool_results {
-        // OpenAI expects separate "tool" role messages for each tool result
-        // but we need to return a single JSON value, so we return the first one
-        // The main loop handles multiple tool results by sending them separately
+        let mut out = Vec::new();
         for block in &msg.content {
             if let ContentBlock::ToolResult { tool_use_id, content, .. } = block {
-                return json_obj()
-                    .field_str("role", "tool")
-                    .field_str("tool_call_id", tool_use_id)
-                    .field_str("content", content)
-                    .build();
+                out.push(
+                    json_obj()
+                        .field_str("role", "tool")
+                        .field_str("tool_call_id", tool_use_id)
+                        .field_str("content", content)
+                        .build(),
+                );
             }
         }
+        let has_rest = msg
+            .content
+            .iter()
+            .any(|b| matches!(b, ContentBlock::Text { .. } | ContentBlock::Image { .. }));
+        if has_rest {
+            out.push(text_message(role, &msg.content));
+        }
+        return out;
     }
 
     // Check if assistant message has tool calls
@@ -190,21 +451,47 @@ fn message_to_openai_json(msg: &Message) -> JsonValue {
         if !text_parts.is_empty() {
             obj = obj.field_str("content", &text_parts.join("\n"));
         }
-        return obj.build();
+        return vec![obj.build()];
     }
 
-    // Simple text message
+    vec![text_message(role, &msg.content)]
+}
+
+/// A message of the text blocks in `content`. With images, `content`
+/// becomes an array of parts, each image a data URI.
+fn text_message(role: &str, content: &[ContentBlock]) -> JsonValue {
     let mut text_parts = Vec::new();
-    for block in &msg.content {
-        if let ContentBlock::Text { text } = block {
-            text_parts.push(text.as_str());
+    let mut images = Vec::new();
+    for block in content {
+        match block {
+            ContentBlock::Text { text } => text_parts.push(text.as_str()),
+            ContentBlock::Image { media_type, data_base64 } => images.push(
+                json_obj()
+                    .field_str("type", "image_url")
+                    .field(
+                        "image_url",
+                        json_obj()
+                            .field_str("url", &format!("data:{};base64,{}", media_type, data_base64))
+                            .build(),
+                    )
+                    .build(),
+            ),
+            _ => {}
         }
     }
-
-    json_obj()
-        .field_str("role", role)
-        .field_str("content", &text_parts.join("\n"))
-        .build()
+    let text = text_parts.join("\n");
+    let obj = json_obj().field_str("role", role);
+    if images.is_empty() {
+        return obj.field_str("content", &text).build();
+    }
+    let mut parts = json_arr();
+    if !text.is_empty() {
+        parts = parts.push(json_obj().field_str("type", "text").field_str("text", &text).build());
+    }
+    for image in images {
+        parts = parts.push(image);
+    }
+    obj.field("content", parts.build()).build()
 }
 
 // ── Response parsing (OpenAI → Sentinel format) ─────────────────────────────
@@ -229,12 +516,7 @@ fn parse_openai_response(json: &JsonValue) -> Result<LlmResponse, LlmError> {
         .and_then(|v| v.as_str())
         .unwrap_or("stop");
 
-    let stop_reason = match finish_reason {
-        "stop" => StopReason::EndTurn,
-        "tool_calls" => StopReason::ToolUse,
-        "length" => StopReason::MaxTokens,
-        other => StopReason::Other(other.to_string()),
-    };
+    let stop_reason = map_finish_reason(finish_reason);
 
     let mut content = Vec::new();
 
@@ -251,14 +533,13 @@ fn parse_openai_response(json: &JsonValue) -> Result<LlmResponse, LlmError> {
     if let Some(tool_calls) = message.get("tool_calls").and_then(|v| v.as_array()) {
         for tc in tool_calls {
             let id = tc.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string();
-            let function = tc.get("function");
-            let name = function
-                .and_then(|f| f.get("name"))
+            let name = tc
+                .get_path("function.name")
                 .and_then(|v| v.as_str())
                 .unwrap_or("")
                 .to_string();
-            let args_str = function
-                .and_then(|f| f.get("arguments"))
+            let args_str = tc
+                .get_path("function.arguments")
                 .and_then(|v| v.as_str())
                 .unwrap_or("{}");
             let input = json::parse(args_str).unwrap_or(JsonValue::Null);
@@ -268,13 +549,12 @@ fn parse_openai_response(json: &JsonValue) -> Result<LlmResponse, LlmError> {
     }
 
     // Usage
-    let usage = json.get("usage");
-    let usage_input = usage
-        .and_then(|u| u.get("prompt_tokens"))
+    let usage_input = json
+        .get_path("usage.prompt_tokens")
         .and_then(|v| v.as_i64())
         .unwrap_or(0);
-    let usage_output = usage
-        .and_then(|u| u.get("completion_tokens"))
+    let usage_output = json
+        .get_path("usage.completion_tokens")
         .and_then(|v| v.as_i64())
         .unwrap_or(0);
 
@@ -283,13 +563,237 @@ fn parse_openai_response(json: &JsonValue) -> Result<LlmResponse, LlmError> {
         content,
         usage_input,
         usage_output,
+        usage_cache_creation: 0,
+        usage_cache_read: 0,
     })
 }
 
+fn map_finish_reason(reason: &str) -> StopReason {
+    match reason {
+        "stop" => StopReason::EndTurn,
+        "tool_calls" => StopReason::ToolUse,
+        "length" => StopReason::MaxTokens,
+        other => StopReason::Other(other.to_string()),
+    }
+}
+
+// ── Streaming (chat.completion.chunk → Sentinel format) ─────────────────────
+
+/// A tool call being assembled from `delta.tool_calls` fragments.
+#[derive(Default)]
+struct PartialToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Builds an `LlmResponse` from the `data:` payloads of a streamed chat
+/// completion. Tool call fragments are keyed by their `index`; only the
+/// first fragment carries the id and name, and the argument JSON is split
+/// arbitrarily across the rest.
+#[derive(Default)]
+struct StreamAccumulator {
+    text: String,
+    tool_calls: Vec<PartialToolCall>,
+    finish_reason: Option<String>,
+    usage_input: i64,
+    usage_output: i64,
+}
+
+impl StreamAccumulator {
+    /// Feed one SSE `data` payload. Returns true on the `[DONE]` sentinel.
+    fn push_chunk(&mut self, data: &str, on_text: &mut dyn FnMut(&str)) -> Result<bool, LlmError> {
+        if data == "[DONE]" {
+            return Ok(true);
+        }
+        let chunk = json::parse(data).map_err(|e| LlmError::Json(e.to_string()))?;
+        if let Some(msg) = chunk.get_path("error.message").and_then(|m| m.as_str()) {
+            return Err(LlmError::Api { status: 200, message: msg.to_string() });
+        }
+
+        // Sent on the last chunk when the server reports usage while streaming
+        if let Some(n) = chunk.get_path("usage.prompt_tokens").and_then(|v| v.as_i64()) {
+            self.usage_input = n;
+        }
+        if let Some(n) = chunk.get_path("usage.completion_tokens").and_then(|v| v.as_i64()) {
+            self.usage_output = n;
+        }
+
+        let choice = match chunk.get_path("choices.0") {
+            Some(c) => c,
+            None => return Ok(false),
+        };
+        if let Some(reason) = choice.get_str("finish_reason") {
+            self.finish_reason = Some(reason.to_string());
+        }
+        let delta = match choice.get("delta") {
+            Some(d) => d,
+            None => return Ok(false),
+        };
+
+        if let Some(text) = delta.get_str("content") {
+            if !text.is_empty() {
+                self.text.push_str(text);
+                on_text(text);
+            }
+        }
+
+        if let Some(calls) = delta.get("tool_calls").and_then(|v| v.as_array()) {
+            for tc in calls {
+                let index = tc
+                    .get("index")
+                    .and_then(|v| v.as_u64())
+                    .map(|i| i as usize)
+                    .unwrap_or(self.tool_calls.len().saturating_sub(1));
+                if self.tool_calls.len() <= index {
+                    self.tool_calls.resize_with(index + 1, PartialToolCall::default);
+                }
+                let call = &mut self.tool_calls[index];
+                if let Some(id) = tc.get_str("id") {
+                    call.id.push_str(id);
+                }
+                if let Some(name) = tc.get_path("function.name").and_then(|v| v.as_str()) {
+                    call.name.push_str(name);
+                }
+                if let Some(args) = tc.get_path("function.arguments").and_then(|v| v.as_str()) {
+                    call.arguments.push_str(args);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn finish(self) -> LlmResponse {
+        let mut content = Vec::new();
+        if !self.text.is_empty() {
+            content.push(ContentBlock::Text { text: self.text });
+        }
+        for call in self.tool_calls {
+            if call.name.is_empty() {
+                continue;
+            }
+            let args = if call.arguments.trim().is_empty() { "{}" } else { &call.arguments };
+            content.push(ContentBlock::ToolUse {
+                id: call.id,
+                name: call.name,
+                input: json::parse(args).unwrap_or(JsonValue::Null),
+            });
+        }
+        let stop_reason = match self.finish_reason.as_deref() {
+            Some(reason) => map_finish_reason(reason),
+            None => StopReason::Other("incomplete".into()),
+        };
+        LlmResponse {
+            stop_reason,
+            content,
+            usage_input: self.usage_input,
+            usage_output: self.usage_output,
+            usage_cache_creation: 0,
+            usage_cache_read: 0,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_extra_headers_sent_after_auth() {
+        let client = OpenAiClient::new(
+            HttpClient::new().unwrap(),
+            "k".into(),
+            "openai/gpt-4o".into(),
+            100,
+            "https://openrouter.ai/api/v1".into(),
+        )
+        .with_extra_headers(vec![
+            ("HTTP-Referer".to_string(), "https://example.com".to_string()),
+            ("X-Title".to_string(), "Sentinel".to_string()),
+            ("authorization".to_string(), "Bearer other".to_string()),
+        ]);
+        assert_eq!(
+            client.request_headers("Bearer k"),
+            vec![
+                ("Authorization", "Bearer k"),
+                ("HTTP-Referer", "https://example.com"),
+                ("X-Title", "Sentinel"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_azure_sends_api_key_header() {
+        let client = OpenAiClient::new(
+            HttpClient::new().unwrap(),
+            "k".into(),
+            "gpt-4o".into(),
+            100,
+            "https://res.openai.azure.com/openai/deployments/gpt-4o?api-version=2024-10-21".into(),
+        )
+        .with_azure(true)
+        .with_extra_headers(vec![("Api-Key".to_string(), "other".to_string())]);
+        assert_eq!(client.request_headers("Bearer k"), vec![("api-key", "k")]);
+    }
+
+    #[test]
+    fn test_endpoint_url_keeps_query_last() {
+        assert_eq!(
+            endpoint_url("https://api.openai.com/v1", "/chat/completions"),
+            "https://api.openai.com/v1/chat/completions"
+        );
+        assert_eq!(
+            endpoint_url("https://localhost:11434/v1/", "/chat/completions"),
+            "https://localhost:11434/v1/chat/completions"
+        );
+        assert_eq!(
+            endpoint_url(
+                "https://res.openai.azure.com/openai/deployments/gpt-4o?api-version=2024-10-21",
+                "/chat/completions"
+            ),
+            "https://res.openai.azure.com/openai/deployments/gpt-4o/chat/completions?api-version=2024-10-21"
+        );
+        assert_eq!(
+            endpoint_url("https://gw.example.com/openai/?team=a&tier=b", "/models/gpt-4o"),
+            "https://gw.example.com/openai/models/gpt-4o?team=a&tier=b"
+        );
+    }
+
+    #[test]
+    fn test_parallel_tool_calls_serialized() {
+        let client = |parallel: bool| {
+            OpenAiClient::new(
+                HttpClient::new().unwrap(),
+                "k".into(),
+                "gpt-4o".into(),
+                100,
+                "https://api.openai.com/v1".into(),
+            )
+            .with_parallel_tool_calls(parallel)
+        };
+        let msgs = [Message {
+            role: Role::User,
+            content: vec![ContentBlock::Text { text: "hi".into() }],
+        }];
+        let tools = [ToolDef {
+            name: "echo".into(),
+            description: "Echo".into(),
+            input_schema: json_obj().field_str("type", "object").build(),
+        }];
+
+        assert!(client(true)
+            .build_request_body(None, &msgs, &tools)
+            .get("parallel_tool_calls")
+            .is_none());
+        let body = client(false).build_request_body(None, &msgs, &tools);
+        assert_eq!(body.get("parallel_tool_calls").unwrap().as_bool(), Some(false));
+        // OpenAI rejects the field without tools
+        assert!(client(false)
+            .build_request_body(None, &msgs, &[])
+            .get("parallel_tool_calls")
+            .is_none());
+    }
+
     #[test]
     fn test_parse_openai_text_response() {
         let json_str = r#"{
@@ -370,11 +874,35 @@ mod tests {
             role: Role::User,
             content: vec![ContentBlock::Text { text: "Hello".into() }],
         };
-        let json = message_to_openai_json(&msg);
+        let json = &message_to_openai_json(&msg)[0];
         assert_eq!(json.get("role").unwrap().as_str().unwrap(), "user");
         assert_eq!(json.get("content").unwrap().as_str().unwrap(), "Hello");
     }
 
+    #[test]
+    fn test_message_to_openai_image() {
+        let msg = Message {
+            role: Role::User,
+            content: vec![
+                ContentBlock::Text { text: "What is this?".into() },
+                ContentBlock::Image {
+                    media_type: "image/jpeg".into(),
+                    data_base64: "/9j/4A==".into(),
+                },
+            ],
+        };
+        let json = &message_to_openai_json(&msg)[0];
+        let parts = json.get("content").unwrap().as_array().unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].get_str("type"), Some("text"));
+        assert_eq!(parts[0].get_str("text"), Some("What is this?"));
+        assert_eq!(parts[1].get_str("type"), Some("image_url"));
+        assert_eq!(
+            parts[1].get_path("image_url.url").and_then(|v| v.as_str()),
+            Some("data:image/jpeg;base64,/9j/4A==")
+        );
+    }
+
     #[test]
     fn test_message_to_openai_tool_result() {
         let msg = Message {
@@ -386,8 +914,307 @@ mod tests {
             }],
         };
         let json = message_to_openai_json(&msg);
-        assert_eq!(json.get("role").unwrap().as_str().unwrap(), "tool");
-        assert_eq!(json.get("tool_call_id").unwrap().as_str().unwrap(), "call_123");
-        assert_eq!(json.get("content").unwrap().as_str().unwrap(), "file data");
+        assert_eq!(json.len(), 1);
+        assert_eq!(json[0].get("role").unwrap().as_str().unwrap(), "tool");
+        assert_eq!(json[0].get("tool_call_id").unwrap().as_str().unwrap(), "call_123");
+        assert_eq!(json[0].get("content").unwrap().as_str().unwrap(), "file data");
+    }
+
+    #[test]
+    fn test_multiple_tool_results_become_separate_messages() {
+        let client = OpenAiClient::new(
+            HttpClient::new().unwrap(),
+            "k".into(),
+            "gpt-4o".into(),
+            100,
+            "https://api.openai.com/v1".into(),
+        );
+        let msgs = [
+            Message {
+                role: Role::Assistant,
+                content: vec![
+                    ContentBlock::ToolUse {
+                        id: "call_a".into(),
+                        name: "read_file".into(),
+                        input: json_obj().field_str("path", "/tmp/a").build(),
+                    },
+                    ContentBlock::ToolUse {
+                        id: "call_b".into(),
+                        name: "read_file".into(),
+                        input: json_obj().field_str("path", "/tmp/b").build(),
+                    },
+                ],
+            },
+            Message {
+                role: Role::User,
+                content: vec![
+                    ContentBlock::ToolResult {
+                        tool_use_id: "call_a".into(),
+                        content: "contents of a".into(),
+                        is_error: false,
+                    },
+                    ContentBlock::ToolResult {
+                        tool_use_id: "call_b".into(),
+                        content: "no such file".into(),
+                        is_error: true,
+                    },
+                ],
+            },
+        ];
+        let body = client.build_request_body(None, &msgs, &[]);
+        let messages = body.get("messages").and_then(|v| v.as_array()).unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].get_path("tool_calls.1.id").and_then(|v| v.as_str()), Some("call_b"));
+        for (json, (id, content)) in messages[1..]
+            .iter()
+            .zip([("call_a", "contents of a"), ("call_b", "no such file")])
+        {
+            assert_eq!(json.get_str("role"), Some("tool"));
+            assert_eq!(json.get_str("tool_call_id"), Some(id));
+            assert_eq!(json.get_str("content"), Some(content));
+        }
+    }
+
+    /// Feed each `data:` line of an SSE transcript to a fresh accumulator.
+    fn replay(transcript: &str) -> (LlmResponse, Vec<String>) {
+        let mut acc = StreamAccumulator::default();
+        let mut deltas = Vec::new();
+        for line in transcript.lines() {
+            if let Some(data) = line.strip_prefix("data: ") {
+                if acc.push_chunk(data, &mut |t| deltas.push(t.to_string())).unwrap() {
+                    break;
+                }
+            }
+        }
+        (acc.finish(), deltas)
+    }
+
+    #[test]
+    fn test_stream_text_deltas() {
+        let transcript = r#"data: {"id":"c1","object":"chat.completion.chunk","choices":[{"index":0,"delta":{"role":"assistant","content":""},"finish_reason":null}]}
+
+data: {"id":"c1","object":"chat.completion.chunk","choices":[{"index":0,"delta":{"content":"Hello"},"finish_reason":null}]}
+
+data: {"id":"c1","object":"chat.completion.chunk","choices":[{"index":0,"delta":{"content":", world"},"finish_reason":null}]}
+
+data: {"id":"c1","object":"chat.completion.chunk","choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}
+
+data: [DONE]
+
+data: {"never":"read"}
+"#;
+        let (resp, deltas) = replay(transcript);
+        assert_eq!(deltas, vec!["Hello", ", world"]);
+        assert!(matches!(resp.stop_reason, StopReason::EndTurn));
+        assert_eq!(resp.content.len(), 1);
+        match &resp.content[0] {
+            ContentBlock::Text { text } => assert_eq!(text, "Hello, world"),
+            _ => panic!("expected text block"),
+        }
+    }
+
+    #[test]
+    fn test_stream_tool_calls_assembled_by_index() {
+        let transcript = r#"data: {"choices":[{"index":0,"delta":{"role":"assistant","content":null,"tool_calls":[{"index":0,"id":"call_a","type":"function","function":{"name":"read_file","arguments":""}}]},"finish_reason":null}]}
+
+data: {"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"pa"}}]},"finish_reason":null}]}
+
+data: {"choices":[{"index":0,"delta":{"tool_calls":[{"index":1,"id":"call_b","type":"function","function":{"name":"list_dir","arguments":"{\"path\""}}]},"finish_reason":null}]}
+
+data: {"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"th\":\"/tmp/a\"}"}}]},"finish_reason":null}]}
+
+data: {"choices":[{"index":0,"delta":{"tool_calls":[{"index":1,"function":{"arguments":":\"/tmp\"}"}}]},"finish_reason":null}]}
+
+data: {"choices":[{"index":0,"delta":{},"finish_reason":"tool_calls"}]}
+
+data: {"choices":[],"usage":{"prompt_tokens":12,"completion_tokens":30}}
+
+data: [DONE]
+"#;
+        // Usage only arrives in a stream when the request asks for it
+        let client = OpenAiClient::new(
+            HttpClient::new().unwrap(),
+            "k".into(),
+            "gpt-4o".into(),
+            100,
+            "https://api.openai.com/v1".into(),
+        );
+        let body = client.build_streaming_request_body(None, &[], &[]);
+        assert_eq!(body.get("stream").and_then(|v| v.as_bool()), Some(true));
+        assert_eq!(
+            body.get_path("stream_options.include_usage").and_then(|v| v.as_bool()),
+            Some(true)
+        );
+        // Compatible servers may reject stream_options, so they don't get it
+        let local = OpenAiClient::new(
+            HttpClient::new().unwrap(),
+            "k".into(),
+            "llama3".into(),
+            100,
+            "https://localhost:8000/v1".into(),
+        );
+        let body = local.build_streaming_request_body(None, &[], &[]);
+        assert_eq!(body.get("stream").and_then(|v| v.as_bool()), Some(true));
+        assert!(body.get("stream_options").is_none());
+
+        let (resp, deltas) = replay(transcript);
+        assert!(deltas.is_empty());
+        assert!(matches!(resp.stop_reason, StopReason::ToolUse));
+        assert_eq!(resp.usage_input, 12);
+        assert_eq!(resp.usage_output, 30);
+        assert_eq!(resp.content.len(), 2);
+        match &resp.content[0] {
+            ContentBlock::ToolUse { id, name, input } => {
+                assert_eq!(id, "call_a");
+                assert_eq!(name, "read_file");
+                assert_eq!(input.get_str("path"), Some("/tmp/a"));
+            }
+            _ => panic!("expected tool use"),
+        }
+        match &resp.content[1] {
+            ContentBlock::ToolUse { id, name, input } => {
+                assert_eq!(id, "call_b");
+                assert_eq!(name, "list_dir");
+                assert_eq!(input.get_str("path"), Some("/tmp"));
+            }
+            _ => panic!("expected tool use"),
+        }
+    }
+
+    #[test]
+    fn test_stream_length_and_missing_finish() {
+        let (resp, _) = replay(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"cut\"},\"finish_reason\":\"length\"}]}\n",
+        );
+        assert!(matches!(resp.stop_reason, StopReason::MaxTokens));
+
+        let (resp, _) = replay("data: {\"choices\":[{\"delta\":{\"content\":\"cut\"}}]}\n");
+        assert!(matches!(resp.stop_reason, StopReason::Other(ref r) if r == "incomplete"));
+    }
+
+    #[test]
+    fn test_sampling_fields_only_when_set() {
+        let client = || {
+            OpenAiClient::new(
+                HttpClient::new().unwrap(),
+                "k".into(),
+                "gpt-4o".into(),
+                100,
+                "https://api.openai.com/v1".into(),
+            )
+        };
+        let body = client().build_request_body(None, &[], &[]);
+        assert!(body.get("temperature").is_none());
+        assert!(body.get("top_p").is_none());
+        assert!(body.get("stop").is_none());
+
+        let body = client()
+            .with_sampling(SamplingParams {
+                temperature: Some(0.0),
+                top_p: None,
+                stop_sequences: vec!["END".into(), "STOP".into()],
+            })
+            .build_request_body(None, &[], &[]);
+        assert_eq!(body.get("temperature").and_then(|v| v.as_f64()), Some(0.0));
+        assert!(body.get("top_p").is_none());
+        assert_eq!(body.get_path("stop.1").and_then(|v| v.as_str()), Some("STOP"));
+    }
+
+    #[test]
+    fn test_model_family_detection() {
+        assert_eq!(ModelFamily::detect("o1"), ModelFamily::Reasoning);
+        assert_eq!(ModelFamily::detect("o3-mini"), ModelFamily::Reasoning);
+        assert_eq!(ModelFamily::detect("openai/o4-mini"), ModelFamily::Reasoning);
+        assert_eq!(ModelFamily::detect("gpt-4o"), ModelFamily::Standard);
+        assert_eq!(ModelFamily::detect("llama3"), ModelFamily::Standard);
+        assert_eq!(ModelFamily::detect("openchat"), ModelFamily::Standard);
+        assert_eq!(ModelFamily::from_name("Reasoning"), Some(ModelFamily::Reasoning));
+        assert_eq!(ModelFamily::from_name("chat"), None);
+    }
+
+    #[test]
+    fn test_reasoning_and_standard_request_bodies() {
+        let client = |model: &str, family: Option<ModelFamily>| {
+            OpenAiClient::new(
+                HttpClient::new().unwrap(),
+                "k".into(),
+                model.into(),
+                100,
+                "https://api.openai.com/v1".into(),
+            )
+            .with_model_family(family)
+            .with_sampling(SamplingParams {
+                temperature: Some(0.2),
+                top_p: Some(0.9),
+                stop_sequences: Vec::new(),
+            })
+        };
+
+        let body = client("gpt-4o", None).build_request_body(Some("Be brief."), &[], &[]);
+        assert_eq!(body.get("max_tokens").and_then(|v| v.as_i64()), Some(100));
+        assert!(body.get("max_completion_tokens").is_none());
+        assert_eq!(body.get("temperature").and_then(|v| v.as_f64()), Some(0.2));
+        assert_eq!(body.get_path("messages.0.role").and_then(|v| v.as_str()), Some("system"));
+
+        let body = client("o3-mini", None).build_request_body(Some("Be brief."), &[], &[]);
+        assert_eq!(body.get("max_completion_tokens").and_then(|v| v.as_i64()), Some(100));
+        assert!(body.get("max_tokens").is_none());
+        assert!(body.get("temperature").is_none());
+        assert!(body.get("top_p").is_none());
+        assert_eq!(body.get_path("messages.0.role").and_then(|v| v.as_str()), Some("developer"));
+        assert_eq!(body.get_path("messages.0.content").and_then(|v| v.as_str()), Some("Be brief."));
+
+        // The config hint wins over the name
+        let body = client("my-finetune", Some(ModelFamily::Reasoning)).build_request_body(None, &[], &[]);
+        assert!(body.get("max_completion_tokens").is_some());
+        let body = client("o1-proxy", Some(ModelFamily::Standard)).build_request_body(None, &[], &[]);
+        assert!(body.get("max_tokens").is_some());
+    }
+
+    #[test]
+    fn test_api_calls_report_each_attempt_with_its_status() {
+        use crate::llm::provider::ApiCall;
+        use crate::net::http::tests::serve_tls;
+        use crate::net::http::TlsConfig;
+        use crate::net::retry::RetryPolicy;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        use std::time::Duration;
+
+        let port = serve_tls(vec![
+            (503, r#"{"error":{"message":"overloaded"}}"#),
+            (
+                200,
+                r#"{"choices":[{"message":{"content":"Hi"},"finish_reason":"stop"}],
+                    "usage":{"prompt_tokens":12,"completion_tokens":3}}"#,
+            ),
+            (400, r#"{"error":{"message":"bad request"}}"#),
+        ]);
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let seen = Rc::clone(&calls);
+        let http = HttpClient::with_tls(&TlsConfig::insecure_skip_verify())
+            .with_retry(RetryPolicy::new(1, Duration::ZERO).retry_non_idempotent());
+        let client = OpenAiClient::new(
+            http,
+            "k".into(),
+            "gpt-4o".into(),
+            100,
+            format!("https://127.0.0.1:{}/v1", port),
+        )
+        .with_api_call_hook(Rc::new(move |call: &ApiCall| {
+            seen.borrow_mut().push((call.status, call.tokens_in, call.tokens_out));
+        }));
+        let msgs = [Message {
+            role: Role::User,
+            content: vec![ContentBlock::Text { text: "hi".into() }],
+        }];
+
+        // The retried 503 is reported as well as the answer after it
+        let resp = client.send(None, &msgs, &[]).unwrap();
+        assert_eq!(resp.usage_input, 12);
+        assert_eq!(*calls.borrow(), vec![(503, 0, 0), (200, 12, 3)]);
+
+        assert!(client.send(None, &msgs, &[]).is_err());
+        assert_eq!(calls.borrow()[2], (400, 0, 0));
     }
 }
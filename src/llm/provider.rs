@@ -1,4 +1,8 @@
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
 use crate::net::json::JsonValue;
+use crate::net::retry::RetryLog;
 
 // ── Shared types for all LLM providers ──────────────────────────────────────
 
@@ -8,11 +12,23 @@ pub enum Role {
     Assistant,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ContentBlock {
     Text { text: String },
     ToolUse { id: String, name: String, input: JsonValue },
     ToolResult { tool_use_id: String, content: String, is_error: bool },
+    /// Extended-thinking reasoning. Never shown to the user, but kept in the
+    /// history because Anthropic requires it echoed back with tool results.
+    Thinking { text: String, signature: String },
+    /// An image the user sent, base64-encoded.
+    Image { media_type: String, data_base64: String },
+}
+
+impl ContentBlock {
+    /// What stands in for an image with providers that can't take one.
+    pub fn image_note(media_type: &str) -> String {
+        format!("[image ({}) not shown: this provider doesn't accept images]", media_type)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -35,6 +51,68 @@ pub struct LlmResponse {
     pub content: Vec<ContentBlock>,
     pub usage_input: i64,
     pub usage_output: i64,
+    /// Input tokens written to / read from the provider's prompt cache.
+    pub usage_cache_creation: i64,
+    pub usage_cache_read: i64,
+}
+
+/// Optional sampling settings. Unset fields are left out of the request, so
+/// the provider's defaults apply.
+#[derive(Debug, Clone, Default)]
+pub struct SamplingParams {
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub stop_sequences: Vec<String>,
+}
+
+impl SamplingParams {
+    /// Stop sequences as a JSON array of strings.
+    pub fn stop_json(&self) -> JsonValue {
+        JsonValue::Array(
+            self.stop_sequences
+                .iter()
+                .map(|s| JsonValue::String(s.clone()))
+                .collect(),
+        )
+    }
+}
+
+// ── Extra request headers ───────────────────────────────────────────────────
+
+/// Headers the HTTP layer manages; configured extra headers never replace them.
+const FRAMING_HEADERS: &[&str] = &[
+    "host",
+    "content-length",
+    "content-type",
+    "content-encoding",
+    "transfer-encoding",
+    "connection",
+];
+
+/// Drop configured extra headers that would replace a framing header or one
+/// of the client's own `reserved` headers (auth, API version), warning about
+/// each. Auth is configured through the API key settings instead.
+pub fn filter_extra_headers(
+    provider: &str,
+    extra: Vec<(String, String)>,
+    reserved: &[&str],
+) -> Vec<(String, String)> {
+    extra
+        .into_iter()
+        .filter(|(name, _)| {
+            let clash = FRAMING_HEADERS
+                .iter()
+                .chain(reserved)
+                .any(|r| r.eq_ignore_ascii_case(name));
+            if clash {
+                eprintln!(
+                    "sentinel: warning: ignoring {} extra header '{}': set by the client",
+                    provider, name
+                );
+            }
+            !clash
+        })
+        .collect()
 }
 
 #[derive(Debug)]
@@ -64,14 +142,84 @@ impl std::fmt::Display for LlmError {
     }
 }
 
+impl LlmError {
+    /// The HTTP status the API answered with, if the error came from one.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            LlmError::Api { status, .. } => Some(*status),
+            LlmError::RateLimit { .. } => Some(429),
+            LlmError::Http(_) | LlmError::Json(_) => None,
+        }
+    }
+}
+
 impl From<crate::net::http::HttpError> for LlmError {
     fn from(e: crate::net::http::HttpError) -> Self {
         LlmError::Http(e)
     }
 }
 
+// ── API call reporting ──────────────────────────────────────────────────────
+
+/// One request to a provider's API, as told to an `ApiCallHook`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiCall<'a> {
+    /// The URL requested, less any API key passed in it.
+    pub endpoint: &'a str,
+    /// The status the API answered with, or 0 if no usable response
+    /// arrived.
+    pub status: u16,
+    pub tokens_in: i64,
+    pub tokens_out: i64,
+    /// Until the response, or for streaming the end of the stream, was read.
+    pub latency: Duration,
+}
+
+/// Called after every request a provider client makes, including each
+/// fallback model tried and each attempt the HTTP client retried.
+pub type ApiCallHook = Rc<dyn Fn(&ApiCall)>;
+
+/// Time `request` to `endpoint` and tell `hook` how it went. `request`
+/// returns the HTTP status alongside the response; the attempts in
+/// `retried` are reported first, without tokens.
+pub fn report_call(
+    hook: Option<&ApiCallHook>,
+    endpoint: &str,
+    retried: &RetryLog,
+    request: impl FnOnce() -> Result<(u16, LlmResponse), LlmError>,
+) -> Result<LlmResponse, LlmError> {
+    let started = Instant::now();
+    let result = request();
+    if let Some(hook) = hook {
+        let mut waited = Duration::ZERO;
+        for attempt in retried.take() {
+            hook(&ApiCall {
+                endpoint,
+                status: attempt.status,
+                tokens_in: 0,
+                tokens_out: 0,
+                latency: attempt.latency,
+            });
+            waited += attempt.latency + attempt.delay;
+        }
+        let (status, tokens_in, tokens_out) = match &result {
+            Ok((status, resp)) => (*status, resp.usage_input, resp.usage_output),
+            Err(e) => (e.status().unwrap_or(0), 0, 0),
+        };
+        hook(&ApiCall {
+            endpoint,
+            status,
+            tokens_in,
+            tokens_out,
+            latency: started.elapsed().saturating_sub(waited),
+        });
+    }
+    result.map(|(_, resp)| resp)
+}
+
 // ── Tool definition (shared across providers) ───────────────────────────────
 
+#[derive(Clone)]
 pub struct ToolDef {
     pub name: String,
     pub description: String,
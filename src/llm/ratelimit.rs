@@ -0,0 +1,320 @@
+use std::cell::RefCell;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::log;
+
+// ── Rate-limit budget ───────────────────────────────────────────────────────
+//
+// Providers report the remaining request/token budget on every response.
+// Tracking it lets the client pause before the next request instead of
+// waiting to be rejected with a 429.
+
+/// Requests remaining at or below which the next request is delayed.
+const LOW_REQUESTS_REMAINING: u64 = 1;
+/// Tokens remaining at or below which the next request is delayed.
+const LOW_TOKENS_REMAINING: u64 = 1000;
+/// Upper bound on a single pre-emptive wait.
+const MAX_PREEMPTIVE_WAIT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimitBudget {
+    pub requests_remaining: Option<u64>,
+    pub tokens_remaining: Option<u64>,
+    /// Seconds until the request budget resets.
+    pub requests_reset_secs: Option<u64>,
+    /// Seconds until the token budget resets.
+    pub tokens_reset_secs: Option<u64>,
+}
+
+impl RateLimitBudget {
+    /// Parse Anthropic's `anthropic-ratelimit-*` headers. Reset values are
+    /// RFC 3339 timestamps, converted to seconds relative to `now_unix`.
+    pub fn from_anthropic_headers(headers: &[(String, String)], now_unix: u64) -> Option<Self> {
+        let reset = |key: &str| {
+            header(headers, key)
+                .and_then(parse_rfc3339)
+                .map(|t| t.saturating_sub(now_unix))
+        };
+        let budget = RateLimitBudget {
+            requests_remaining: header_u64(headers, "anthropic-ratelimit-requests-remaining"),
+            tokens_remaining: header_u64(headers, "anthropic-ratelimit-tokens-remaining"),
+            requests_reset_secs: reset("anthropic-ratelimit-requests-reset"),
+            tokens_reset_secs: reset("anthropic-ratelimit-tokens-reset"),
+        };
+        budget.non_empty()
+    }
+
+    /// Parse OpenAI's `x-ratelimit-*` headers. Reset values are durations
+    /// such as `1s`, `6m0s`, or `20ms`.
+    pub fn from_openai_headers(headers: &[(String, String)]) -> Option<Self> {
+        let reset = |key: &str| header(headers, key).and_then(parse_duration_secs);
+        let budget = RateLimitBudget {
+            requests_remaining: header_u64(headers, "x-ratelimit-remaining-requests"),
+            tokens_remaining: header_u64(headers, "x-ratelimit-remaining-tokens"),
+            requests_reset_secs: reset("x-ratelimit-reset-requests"),
+            tokens_reset_secs: reset("x-ratelimit-reset-tokens"),
+        };
+        budget.non_empty()
+    }
+
+    /// How long to wait before the next request, if the budget is nearly spent.
+    pub fn preemptive_wait(&self) -> Option<Duration> {
+        let mut wait_secs: Option<u64> = None;
+        if self.requests_remaining.is_some_and(|r| r <= LOW_REQUESTS_REMAINING) {
+            wait_secs = Some(self.requests_reset_secs.unwrap_or(1));
+        }
+        if self.tokens_remaining.is_some_and(|t| t <= LOW_TOKENS_REMAINING) {
+            let secs = self.tokens_reset_secs.unwrap_or(1);
+            wait_secs = Some(wait_secs.map_or(secs, |w| w.max(secs)));
+        }
+        wait_secs
+            .filter(|&s| s > 0)
+            .map(|s| Duration::from_secs(s).min(MAX_PREEMPTIVE_WAIT))
+    }
+
+    fn non_empty(self) -> Option<Self> {
+        if self == RateLimitBudget::default() {
+            None
+        } else {
+            Some(self)
+        }
+    }
+}
+
+// ── Rate limiter ────────────────────────────────────────────────────────────
+
+/// Holds the most recently observed budget for one provider client.
+pub struct RateLimiter {
+    latest: RefCell<Option<(RateLimitBudget, Instant)>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        RateLimiter {
+            latest: RefCell::new(None),
+        }
+    }
+
+    /// Record the budget reported by the latest response.
+    pub fn update(&self, budget: Option<RateLimitBudget>) {
+        if let Some(b) = budget {
+            *self.latest.borrow_mut() = Some((b, Instant::now()));
+        }
+    }
+
+    /// Remaining time to wait before the next request, accounting for time
+    /// already elapsed since the budget was observed.
+    pub fn pending_wait(&self) -> Option<Duration> {
+        let latest = self.latest.borrow();
+        let (budget, observed_at) = latest.as_ref()?;
+        let wait = budget.preemptive_wait()?;
+        wait.checked_sub(observed_at.elapsed())
+            .filter(|d| !d.is_zero())
+    }
+
+    /// Sleep until the budget resets if it is nearly exhausted.
+    pub fn wait_if_needed(&self) {
+        if let Some(wait) = self.pending_wait() {
+            let secs = wait.as_secs().max(1);
+            log::warn(
+                &format!("rate-limit budget low, pausing {}s before next request", secs),
+                &[("source", &"llm"), ("wait_secs", &secs)],
+            );
+            thread::sleep(wait);
+            *self.latest.borrow_mut() = None;
+        }
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// ── Header parsing helpers ──────────────────────────────────────────────────
+
+fn header<'a>(headers: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+fn header_u64(headers: &[(String, String)], key: &str) -> Option<u64> {
+    header(headers, key).and_then(|v| v.trim().parse::<u64>().ok())
+}
+
+/// Parse an RFC 3339 UTC timestamp (`2025-01-01T00:00:30Z`) into Unix seconds.
+/// Fractional seconds are ignored; numeric offsets are applied.
+fn parse_rfc3339(s: &str) -> Option<u64> {
+    let s = s.trim();
+    if s.len() < 19 {
+        return None;
+    }
+    let num = |range: std::ops::Range<usize>| s.get(range)?.parse::<i64>().ok();
+    let year = num(0..4)?;
+    let month = num(5..7)?;
+    let day = num(8..10)?;
+    let hour = num(11..13)?;
+    let min = num(14..16)?;
+    let sec = num(17..19)?;
+
+    // Skip fractional seconds, then read the zone designator
+    let mut rest = &s[19..];
+    if let Some(frac) = rest.strip_prefix('.') {
+        let digits = frac.chars().take_while(|c| c.is_ascii_digit()).count();
+        rest = &frac[digits..];
+    }
+    let offset_secs = match rest {
+        "Z" | "z" | "" => 0,
+        _ => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let h = rest.get(1..3)?.parse::<i64>().ok()?;
+            let m = rest.get(4..6)?.parse::<i64>().ok()?;
+            sign * (h * 3600 + m * 60)
+        }
+    };
+
+    let days = days_from_civil(year, month, day);
+    let secs = days * 86400 + hour * 3600 + min * 60 + sec - offset_secs;
+    u64::try_from(secs).ok()
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Parse an OpenAI-style duration (`1h2m3.5s`, `20ms`) into whole seconds,
+/// rounding up so a sub-second reset still waits.
+fn parse_duration_secs(s: &str) -> Option<u64> {
+    let s = s.trim();
+    if s.is_empty() {
+        return None;
+    }
+    let mut total_ms: f64 = 0.0;
+    let mut num = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == '.' {
+            num.push(c);
+            continue;
+        }
+        let value: f64 = num.parse().ok()?;
+        num.clear();
+        let unit_ms = match c {
+            'h' => 3_600_000.0,
+            'm' if chars.peek() == Some(&'s') => {
+                chars.next();
+                1.0
+            }
+            'm' => 60_000.0,
+            's' => 1000.0,
+            _ => return None,
+        };
+        total_ms += value * unit_ms;
+    }
+    if !num.is_empty() {
+        // Bare number: seconds
+        total_ms += num.parse::<f64>().ok()? * 1000.0;
+    }
+    Some((total_ms / 1000.0).ceil() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_anthropic_headers() {
+        // 2025-01-01T00:00:00Z == 1735689600
+        let now = 1_735_689_600;
+        let h = headers(&[
+            ("anthropic-ratelimit-requests-remaining", "49"),
+            ("anthropic-ratelimit-requests-reset", "2025-01-01T00:00:30Z"),
+            ("anthropic-ratelimit-tokens-remaining", "38000"),
+            ("anthropic-ratelimit-tokens-reset", "2025-01-01T00:00:05.250Z"),
+        ]);
+        let budget = RateLimitBudget::from_anthropic_headers(&h, now).unwrap();
+        assert_eq!(budget.requests_remaining, Some(49));
+        assert_eq!(budget.tokens_remaining, Some(38000));
+        assert_eq!(budget.requests_reset_secs, Some(30));
+        assert_eq!(budget.tokens_reset_secs, Some(5));
+        assert!(budget.preemptive_wait().is_none());
+    }
+
+    #[test]
+    fn test_parse_openai_headers() {
+        let h = headers(&[
+            ("x-ratelimit-remaining-requests", "0"),
+            ("x-ratelimit-reset-requests", "6m0s"),
+            ("x-ratelimit-remaining-tokens", "149984"),
+            ("x-ratelimit-reset-tokens", "20ms"),
+        ]);
+        let budget = RateLimitBudget::from_openai_headers(&h).unwrap();
+        assert_eq!(budget.requests_remaining, Some(0));
+        assert_eq!(budget.requests_reset_secs, Some(360));
+        assert_eq!(budget.tokens_reset_secs, Some(1));
+    }
+
+    #[test]
+    fn test_no_headers_no_budget() {
+        let h = headers(&[("content-type", "application/json")]);
+        assert!(RateLimitBudget::from_anthropic_headers(&h, 0).is_none());
+        assert!(RateLimitBudget::from_openai_headers(&h).is_none());
+    }
+
+    #[test]
+    fn test_near_zero_budget_triggers_wait() {
+        let budget = RateLimitBudget {
+            requests_remaining: Some(100),
+            tokens_remaining: Some(200),
+            requests_reset_secs: Some(2),
+            tokens_reset_secs: Some(7),
+        };
+        assert_eq!(budget.preemptive_wait(), Some(Duration::from_secs(7)));
+
+        let limiter = RateLimiter::new();
+        assert!(limiter.pending_wait().is_none());
+        limiter.update(Some(budget));
+        let wait = limiter.pending_wait().unwrap();
+        assert!(wait > Duration::from_secs(6) && wait <= Duration::from_secs(7));
+    }
+
+    #[test]
+    fn test_wait_is_capped() {
+        let budget = RateLimitBudget {
+            requests_remaining: Some(0),
+            requests_reset_secs: Some(3600),
+            ..Default::default()
+        };
+        assert_eq!(budget.preemptive_wait(), Some(MAX_PREEMPTIVE_WAIT));
+    }
+
+    #[test]
+    fn test_parse_rfc3339_offset() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_rfc3339("1970-01-01T01:00:00+01:00"), Some(0));
+        assert_eq!(parse_rfc3339("not a date"), None);
+    }
+}
@@ -0,0 +1,108 @@
+// ── Operational logging ─────────────────────────────────────────────────────
+//
+// Lines go to stderr as "sentinel: <message>" by default. With
+// `log_format = "json"` they go to stdout instead, one object per line, with
+// the values worth filtering on as separate fields:
+//
+//   {"level":"warn","ts":1700000000,"msg":"telegram poll error: ...","fields":{"connector":"telegram",...}}
+//
+// This is for operators' log pipelines; security events go to the audit log.
+
+use std::fmt::Display;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+
+use crate::net::json::json_obj;
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    fn as_str(self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+}
+
+/// Switch between JSON lines on stdout (`true`) and text on stderr.
+pub fn set_json(enabled: bool) {
+    JSON.store(enabled, Ordering::Relaxed);
+}
+
+pub fn info(msg: &str, fields: &[(&str, &dyn Display)]) {
+    log(Level::Info, msg, fields);
+}
+
+pub fn warn(msg: &str, fields: &[(&str, &dyn Display)]) {
+    log(Level::Warn, msg, fields);
+}
+
+pub fn error(msg: &str, fields: &[(&str, &dyn Display)]) {
+    log(Level::Error, msg, fields);
+}
+
+fn log(level: Level, msg: &str, fields: &[(&str, &dyn Display)]) {
+    if !JSON.load(Ordering::Relaxed) {
+        eprintln!("sentinel: {}", msg);
+        return;
+    }
+    let ts = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let line = json_line(level, ts, msg, fields);
+    // One write per line, so lines from different threads don't interleave
+    let _ = std::io::stdout().lock().write_all(line.as_bytes());
+}
+
+fn json_line(level: Level, ts: u64, msg: &str, fields: &[(&str, &dyn Display)]) -> String {
+    let fields = fields
+        .iter()
+        .fold(json_obj(), |obj, (key, value)| obj.field_str(key, &value.to_string()))
+        .build();
+    let mut line = json_obj()
+        .field_str("level", level.as_str())
+        .field_i64("ts", ts as i64)
+        .field_str("msg", msg)
+        .field("fields", fields)
+        .build()
+        .to_json_string();
+    line.push('\n');
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::json;
+
+    #[test]
+    fn test_json_line_shape() {
+        let line = json_line(Level::Warn, 1700000000, "telegram poll error", &[("connector", &"telegram"), ("attempt", &3)]);
+        assert_eq!(
+            line,
+            "{\"level\":\"warn\",\"ts\":1700000000,\"msg\":\"telegram poll error\",\"fields\":{\"connector\":\"telegram\",\"attempt\":\"3\"}}\n"
+        );
+    }
+
+    #[test]
+    fn test_json_line_escapes_fields() {
+        let error = "bad \"quote\" \\ and\nnewline\ttab \u{1}";
+        let line = json_line(Level::Error, 0, "say \"hi\"", &[("error", &error)]);
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+        let parsed = json::parse(line.trim_end()).unwrap();
+        assert_eq!(parsed.get_str("msg"), Some("say \"hi\""));
+        assert_eq!(parsed.get_path("fields.error").and_then(|v| v.as_str()), Some(error));
+    }
+}
@@ -1,22 +1,76 @@
 mod agent;
 mod config;
 mod llm;
+mod log;
 mod messaging;
 mod net;
 mod platform;
 mod security;
 mod skills;
+mod version;
 
 #[cfg(feature = "tls")]
 mod app;
 
+/// Print build information and exit if `--version` was passed.
+fn handle_version_flag() {
+    if std::env::args().any(|a| a == "--version" || a == "-V") {
+        println!("{}", version::version_string());
+        std::process::exit(0);
+    }
+}
+
+/// Check the config and exit if `--check-config` was passed or
+/// `SENTINEL_CHECK` is set. `--ping` (or `SENTINEL_CHECK=ping`) also
+/// contacts the LLM provider and messaging platforms. Exits non-zero if any
+/// check failed.
+fn handle_check_flag() {
+    let mode = std::env::var("SENTINEL_CHECK").unwrap_or_default();
+    let requested = std::env::args().any(|a| a == "--check-config")
+        || matches!(mode.as_str(), "1" | "true" | "ping");
+    if !requested {
+        return;
+    }
+    let ping = std::env::args().any(|a| a == "--ping") || mode == "ping";
+    let report = match config::Config::load() {
+        Ok(config) => {
+            let mut report = config.check();
+            if ping {
+                ping_endpoints(&config, &mut report);
+            }
+            report
+        }
+        Err(e) => {
+            let mut report = config::CheckReport::default();
+            report.push("config", config::CheckStatus::Fail, e.0);
+            report
+        }
+    };
+    print!("{}", report.render());
+    std::process::exit(if report.passed() { 0 } else { 1 });
+}
+
+#[cfg(feature = "tls")]
+fn ping_endpoints(config: &config::Config, report: &mut config::CheckReport) {
+    app::check_endpoints(config, report);
+}
+
+#[cfg(not(feature = "tls"))]
+fn ping_endpoints(_config: &config::Config, report: &mut config::CheckReport) {
+    report.push("ping", config::CheckStatus::Warn, "built without TLS; endpoints not contacted");
+}
+
 #[cfg(feature = "tls")]
 fn main() {
+    handle_version_flag();
+    handle_check_flag();
     app::run();
 }
 
 #[cfg(not(feature = "tls"))]
 fn main() {
+    handle_version_flag();
+    handle_check_flag();
     // The LuperIQ OS userspace binary is a separate crate at:
     //   luperiq-agent-os/kernel/user/sentinel/
     // It uses luperiq-rt and kernel syscalls directly (no_std).
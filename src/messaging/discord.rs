@@ -1,21 +1,38 @@
-use std::collections::HashMap;
-
-use crate::messaging::{split_message, Connector, ConnectorError, IncomingMessage};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::log;
+use crate::messaging::discord_gateway::Gateway;
+use crate::messaging::{
+    channel_poll_result, download_image, image_client, is_supported_image, split_message,
+    strip_mention, Connector, ConnectorError, ImageAttachment, ImageRef, IncomingMessage,
+    MAX_IMAGE_BYTES,
+};
 use crate::net::http::HttpClient;
-use crate::net::json::{self, json_obj};
+use crate::net::json::{self, json_obj, JsonValue};
 
 const DISCORD_API: &str = "https://discord.com/api/v10";
 const DISCORD_MSG_LIMIT: usize = 2000;
+/// Messages per page of channel history, the most Discord returns.
+const HISTORY_PAGE: usize = 100;
+/// Pages of history read per channel per poll; the rest wait for the next.
+const MAX_HISTORY_PAGES: usize = 5;
 
 // ── Client ──────────────────────────────────────────────────────────────────
 
 pub struct DiscordConnector {
     http: HttpClient,
+    /// For attachment downloads, which are held to `MAX_IMAGE_BYTES`.
+    files: HttpClient,
     token: String,
     channel_ids: Vec<String>,
     bot_user_id: String,
+    /// Configured channels that are direct messages with the bot.
+    dm_channels: HashSet<String>,
     last_message_ids: HashMap<String, String>,
     initialized_channels: HashMap<String, bool>,
+    /// Real-time gateway session; `None` polls over REST.
+    gateway: Option<Gateway>,
 }
 
 impl DiscordConnector {
@@ -30,15 +47,22 @@ impl DiscordConnector {
         let resp = http.get(&url, &[("Authorization", &auth)])?;
         let body = resp
             .body_string()
-            .map_err(|e| ConnectorError::Http(e))?;
+            .map_err(ConnectorError::Http)?;
         let json_val =
             json::parse(&body).map_err(|e| ConnectorError::Json(e.to_string()))?;
         let bot_user_id = json_val
-            .get("id")
-            .and_then(|v| v.as_str())
+            .get_str("id")
             .ok_or_else(|| ConnectorError::Api("failed to get bot user ID from /users/@me".into()))?
             .to_string();
 
+        // Messages read over REST don't say whether they are direct, so
+        // each channel's kind is looked up once
+        let dm_channels = channel_ids
+            .iter()
+            .filter(|id| is_dm_channel(&http, &auth, id))
+            .cloned()
+            .collect();
+
         eprintln!(
             "sentinel: discord connector ready (bot_user_id={}, channels={})",
             bot_user_id,
@@ -46,15 +70,130 @@ impl DiscordConnector {
         );
 
         Ok(DiscordConnector {
+            files: image_client(&http),
             http,
             token: token.to_string(),
             channel_ids: channel_ids.to_vec(),
             bot_user_id,
+            dm_channels,
             last_message_ids: HashMap::new(),
             initialized_channels: HashMap::new(),
+            gateway: None,
         })
     }
 
+    /// Receive messages over the gateway WebSocket. If the handshake fails
+    /// the connector keeps polling over REST.
+    pub fn with_gateway(mut self) -> Self {
+        match self.open_gateway() {
+            Ok(gateway) => {
+                eprintln!("sentinel: discord gateway connected");
+                self.gateway = Some(gateway);
+            }
+            Err(e) => {
+                eprintln!(
+                    "sentinel: discord gateway handshake failed ({}), falling back to REST polling",
+                    e
+                );
+            }
+        }
+        self
+    }
+
+    fn open_gateway(&self) -> Result<Gateway, ConnectorError> {
+        let auth = self.auth_header();
+        let url = format!("{}/gateway/bot", DISCORD_API);
+        let resp = self.http.get(&url, &[("Authorization", &auth)])?;
+        if resp.status != 200 {
+            return Err(ConnectorError::Api(format!("GET /gateway/bot returned {}", resp.status)));
+        }
+        let body = resp.body_string().map_err(ConnectorError::Http)?;
+        let json_val = json::parse(&body).map_err(|e| ConnectorError::Json(e.to_string()))?;
+        let ws_url = json_val
+            .get_str("url")
+            .ok_or_else(|| ConnectorError::Api("no url in /gateway/bot response".into()))?;
+        Gateway::connect(&self.http, ws_url, &self.token)
+    }
+
+    /// Wait for gateway messages; on failure reconnect once, and drop back
+    /// to REST polling if that fails too.
+    fn poll_gateway(&mut self, timeout_secs: u32) -> Option<Vec<IncomingMessage>> {
+        let gateway = self.gateway.as_mut()?;
+        match gateway.poll(Duration::from_secs(u64::from(timeout_secs))) {
+            Ok(events) => return Some(self.gateway_messages(events)),
+            Err(e) => eprintln!("sentinel: discord gateway error: {}, reconnecting", e),
+        }
+        self.gateway = None;
+        match self.open_gateway() {
+            Ok(gateway) => {
+                self.gateway = Some(gateway);
+                Some(Vec::new())
+            }
+            Err(e) => {
+                eprintln!(
+                    "sentinel: discord gateway reconnect failed ({}), falling back to REST polling",
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Convert MESSAGE_CREATE payloads for the configured channels. The REST
+    /// cursor follows along, so a fallback to polling resumes from here.
+    fn gateway_messages(&mut self, events: Vec<JsonValue>) -> Vec<IncomingMessage> {
+        let mut messages = Vec::new();
+        for msg in &events {
+            let (channel_id, msg_id) = match (msg.get_str("channel_id"), msg.get_str("id")) {
+                (Some(c), Some(m)) => (c, m),
+                _ => continue,
+            };
+            if !self.channel_ids.iter().any(|c| c == channel_id) {
+                continue;
+            }
+            self.last_message_ids.insert(channel_id.to_string(), msg_id.to_string());
+            self.initialized_channels.insert(channel_id.to_string(), true);
+            // Gateway messages from a server carry its guild_id
+            let direct = msg.get("guild_id").is_none();
+            if let Some(m) = incoming_message(msg, channel_id, &self.bot_user_id, direct) {
+                messages.push(m);
+            }
+        }
+        messages
+    }
+
+    /// Add (`add = true`) or remove the bot's own reaction on a message.
+    fn react(
+        &self,
+        channel_id: &str,
+        message_id: &str,
+        emoji: &str,
+        add: bool,
+    ) -> Result<(), ConnectorError> {
+        let auth = self.auth_header();
+        let url = format!(
+            "{}/channels/{}/messages/{}/reactions/{}/@me",
+            DISCORD_API,
+            channel_id,
+            message_id,
+            encode_emoji(emoji)
+        );
+        let headers = [("Authorization", auth.as_str())];
+        let resp = if add {
+            self.http.put_json(&url, "", &headers)?
+        } else {
+            self.http.delete(&url, &headers)?
+        };
+        if resp.status >= 400 {
+            let err_body = resp.body_string().unwrap_or_default();
+            return Err(ConnectorError::Api(format!(
+                "Discord reaction failed ({}): {}",
+                resp.status, err_body
+            )));
+        }
+        Ok(())
+    }
+
     fn auth_header(&self) -> String {
         format!("Bot {}", self.token)
     }
@@ -65,136 +204,104 @@ impl DiscordConnector {
 impl Connector for DiscordConnector {
     fn poll_messages(
         &mut self,
-        _timeout_secs: u32,
+        timeout_secs: u32,
     ) -> Result<Vec<IncomingMessage>, ConnectorError> {
+        if let Some(messages) = self.poll_gateway(timeout_secs) {
+            return Ok(messages);
+        }
+
         let mut all_messages = Vec::new();
+        let mut errors = Vec::new();
         let auth = self.auth_header();
 
         for channel_id in &self.channel_ids.clone() {
-            // First poll for this channel: just record the latest message ID
+            // First poll for this channel: just record the latest message
+            // ID. Until that works there's no watermark, so it is retried
+            // rather than read from the start.
             if !self.initialized_channels.contains_key(channel_id) {
                 let url = format!(
                     "{}/channels/{}/messages?limit=1",
                     DISCORD_API, channel_id
                 );
-                match self.http.get(&url, &[("Authorization", &auth)]) {
-                    Ok(resp) if resp.status == 200 => {
-                        if let Ok(body) = resp.body_string() {
-                            if let Ok(json_val) = json::parse(&body) {
-                                if let Some(msgs) = json_val.as_array() {
-                                    if let Some(latest) = msgs.first() {
-                                        if let Some(id) =
-                                            latest.get("id").and_then(|v| v.as_str())
-                                        {
-                                            self.last_message_ids
-                                                .insert(channel_id.clone(), id.to_string());
-                                        }
-                                    }
-                                }
-                            }
+                let latest = self
+                    .http
+                    .get(&url, &[("Authorization", &auth)])
+                    .map_err(|e| e.to_string())
+                    .and_then(|resp| {
+                        let body = resp.body_string().unwrap_or_default();
+                        latest_message_id(resp.status, &body)
+                    });
+                match latest {
+                    Ok(latest) => {
+                        if let Some(id) = latest {
+                            self.last_message_ids.insert(channel_id.clone(), id);
                         }
+                        self.initialized_channels.insert(channel_id.clone(), true);
+                    }
+                    Err(e) => {
+                        eprintln!(
+                            "sentinel: discord init error for {}: {}; retrying next poll",
+                            channel_id, e
+                        );
+                        errors.push(format!("{}: {}", channel_id, e));
                     }
-                    _ => {}
                 }
-                self.initialized_channels.insert(channel_id.clone(), true);
                 continue;
             }
 
             // Normal poll: fetch messages after the last seen ID
-            let mut url = format!(
-                "{}/channels/{}/messages?limit=100",
-                DISCORD_API, channel_id
-            );
-            if let Some(last_id) = self.last_message_ids.get(channel_id) {
-                url.push_str(&format!("&after={}", last_id));
-            }
-
-            let resp = match self.http.get(&url, &[("Authorization", &auth)]) {
-                Ok(r) => r,
+            // A channel that was empty at startup: everything in it is new
+            let last_id = self
+                .last_message_ids
+                .get(channel_id)
+                .cloned()
+                .unwrap_or_else(|| "0".to_string());
+            let fetch = |after: &str| -> Result<Vec<JsonValue>, String> {
+                let url = format!(
+                    "{}/channels/{}/messages?limit={}&after={}",
+                    DISCORD_API, channel_id, HISTORY_PAGE, after
+                );
+                let resp = self
+                    .http
+                    .get(&url, &[("Authorization", &auth)])
+                    .map_err(|e| e.to_string())?;
+                if resp.status == 429 {
+                    log::warn(
+                        &format!("discord rate limited on channel {}", channel_id),
+                        &[("source", &"discord"), ("channel", channel_id)],
+                    );
+                    return Err("rate limited".into());
+                }
+                if resp.status != 200 {
+                    return Err(format!("HTTP {}", resp.status));
+                }
+                let body = resp.body_string().map_err(|e| e.to_string())?;
+                match json::parse(&body).map_err(|e| e.to_string())? {
+                    JsonValue::Array(messages) => Ok(messages),
+                    _ => Err("expected an array of messages".into()),
+                }
+            };
+            let messages = match messages_after(&last_id, fetch) {
+                Ok(messages) => messages,
                 Err(e) => {
                     eprintln!("sentinel: discord poll error for {}: {}", channel_id, e);
+                    errors.push(format!("{}: {}", channel_id, e));
                     continue;
                 }
             };
 
-            // Rate limited — skip this cycle
-            if resp.status == 429 {
-                eprintln!("sentinel: discord rate limited on channel {}", channel_id);
-                continue;
-            }
-
-            if resp.status != 200 {
-                continue;
-            }
-
-            let body = resp.body_string().map_err(|e| ConnectorError::Http(e))?;
-            let json_val =
-                json::parse(&body).map_err(|e| ConnectorError::Json(e.to_string()))?;
-
-            let messages = match json_val.as_array() {
-                Some(arr) => arr,
-                None => continue,
-            };
-
-            // With after=, Discord returns messages sorted by ID ascending.
-            for msg in messages {
-                let msg_id = match msg.get("id").and_then(|v| v.as_str()) {
-                    Some(id) => id,
-                    None => continue,
-                };
-
-                let author_id = msg
-                    .get("author")
-                    .and_then(|a| a.get("id"))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-
-                // Skip bot's own messages
-                if author_id == self.bot_user_id {
-                    self.last_message_ids
-                        .insert(channel_id.clone(), msg_id.to_string());
-                    continue;
-                }
-
-                // Only process DEFAULT message type (0)
-                let msg_type = msg.get("type").and_then(|v| v.as_i64()).unwrap_or(0);
-                if msg_type != 0 {
-                    self.last_message_ids
-                        .insert(channel_id.clone(), msg_id.to_string());
-                    continue;
+            for msg in &messages {
+                if let Some(id) = msg.get_str("id") {
+                    self.last_message_ids.insert(channel_id.clone(), id.to_string());
                 }
-
-                let content = msg
-                    .get("content")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-
-                // Skip empty messages (attachments-only, embeds, etc.)
-                if content.is_empty() {
-                    self.last_message_ids
-                        .insert(channel_id.clone(), msg_id.to_string());
-                    continue;
+                let direct = self.dm_channels.contains(channel_id);
+                if let Some(m) = incoming_message(msg, channel_id, &self.bot_user_id, direct) {
+                    all_messages.push(m);
                 }
-
-                let username = msg
-                    .get("author")
-                    .and_then(|a| a.get("username"))
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string());
-
-                self.last_message_ids
-                    .insert(channel_id.clone(), msg_id.to_string());
-
-                all_messages.push(IncomingMessage {
-                    channel_id: channel_id.clone(),
-                    user_id: author_id.to_string(),
-                    username,
-                    text: content.to_string(),
-                });
             }
         }
 
-        Ok(all_messages)
+        channel_poll_result(all_messages, errors, self.channel_ids.len())
     }
 
     fn send_message(&self, channel_id: &str, text: &str) -> Result<(), ConnectorError> {
@@ -228,12 +335,11 @@ impl Connector for DiscordConnector {
         let resp =
             self.http
                 .post_json(&url, &body.to_json_string(), &[("Authorization", &auth)])?;
-        let body_str = resp.body_string().map_err(|e| ConnectorError::Http(e))?;
+        let body_str = resp.body_string().map_err(ConnectorError::Http)?;
         let json_val =
             json::parse(&body_str).map_err(|e| ConnectorError::Json(e.to_string()))?;
         let msg_id = json_val
-            .get("id")
-            .and_then(|v| v.as_str())
+            .get_str("id")
             .ok_or_else(|| ConnectorError::Api("missing message id in response".into()))?;
         Ok(msg_id.to_string())
     }
@@ -268,4 +374,403 @@ impl Connector for DiscordConnector {
     fn platform_name(&self) -> &'static str {
         "discord"
     }
+
+    fn fetch_image(&self, image: &ImageRef) -> Result<ImageAttachment, ConnectorError> {
+        download_image(&self.files, &image.source, &image.media_type)
+    }
+
+    fn send_typing(&self, channel_id: &str) -> Result<(), ConnectorError> {
+        let auth = self.auth_header();
+        let url = format!("{}/channels/{}/typing", DISCORD_API, channel_id);
+        let resp = self.http.post_json(&url, "", &[("Authorization", &auth)])?;
+        if resp.status >= 400 {
+            return Err(ConnectorError::Api(format!("typing returned {}", resp.status)));
+        }
+        Ok(())
+    }
+
+    fn ack_received(&self, channel_id: &str, message_id: &str) -> Result<(), ConnectorError> {
+        self.react(channel_id, message_id, "👀", true)
+    }
+
+    fn ack_done(
+        &self,
+        channel_id: &str,
+        message_id: &str,
+        success: bool,
+    ) -> Result<(), ConnectorError> {
+        self.react(channel_id, message_id, "👀", false)?;
+        self.react(channel_id, message_id, if success { "✅" } else { "❌" }, true)
+    }
+}
+
+/// Message types the agent answers: DEFAULT and REPLY.
+const TEXT_MESSAGE_TYPES: [i64; 2] = [0, 19];
+
+/// Whether `channel_id` is a direct message channel (type 1). A failed
+/// lookup counts as no, so the channel only answers when addressed.
+fn is_dm_channel(http: &HttpClient, auth: &str, channel_id: &str) -> bool {
+    let url = format!("{}/channels/{}", DISCORD_API, channel_id);
+    let kind = http
+        .get(&url, &[("Authorization", auth)])
+        .map_err(|e| e.to_string())
+        .and_then(|resp| match resp.status {
+            200 => resp.body_string().map_err(|e| e.to_string()),
+            status => Err(format!("HTTP {}", status)),
+        })
+        .and_then(|body| json::parse(&body).map_err(|e| e.to_string()))
+        .map(|channel| channel.get("type").and_then(|v| v.as_i64()));
+    match kind {
+        Ok(kind) => kind == Some(1),
+        Err(e) => {
+            eprintln!("sentinel: discord lookup of channel {} failed: {}", channel_id, e);
+            false
+        }
+    }
+}
+
+/// A message object as an `IncomingMessage`, or `None` for ones the agent ignores: the bot's own, other message types, and
+/// those with neither text nor an image. `direct` says it is a direct
+/// message, which is always addressed to the bot.
+fn incoming_message(
+    msg: &JsonValue,
+    channel_id: &str,
+    bot_user_id: &str,
+    direct: bool,
+) -> Option<IncomingMessage> {
+    let msg_id = msg.get_str("id")?;
+    let author_id = msg
+        .get_path("author.id")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+
+    // Skip bot's own messages
+    if author_id == bot_user_id {
+        return None;
+    }
+
+    if !TEXT_MESSAGE_TYPES.contains(&msg.get("type").and_then(|v| v.as_i64()).unwrap_or(0)) {
+        return None;
+    }
+
+    // Skip empty messages (other attachments, embeds, etc.)
+    let content = match msg.get_str("content").unwrap_or("") {
+        "" if image_attachments(msg).is_empty() => return None,
+        "" => "[image]",
+        content => content,
+    };
+
+    let username = msg
+        .get_path("author.username")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    // A mention is <@id>, or <@!id> when it was to the bot's nickname
+    let (text, mentioned) = strip_mention(content, &format!("<@{}>", bot_user_id));
+    let (text, nick_mentioned) = strip_mention(&text, &format!("<@!{}>", bot_user_id));
+    let mentions_bot = msg
+        .get("mentions")
+        .and_then(|v| v.as_array())
+        .is_some_and(|users| users.iter().any(|u| u.get_str("id") == Some(bot_user_id)));
+    let replies_to_bot = msg
+        .get_path("referenced_message.author.id")
+        .and_then(|v| v.as_str())
+        == Some(bot_user_id);
+
+    Some(IncomingMessage {
+        channel_id: channel_id.to_string(),
+        message_id: msg_id.to_string(),
+        user_id: author_id.to_string(),
+        username,
+        text,
+        addressed: direct || mentioned || nick_mentioned || mentions_bot || replies_to_bot,
+        images: image_attachments(msg)
+            .into_iter()
+            .map(|(url, media_type)| ImageRef {
+                source: url.to_string(),
+                media_type: media_type.to_string(),
+            })
+            .collect(),
+    })
+}
+
+/// The newest message ID in a `limit=1` messages response, or `None` for an
+/// empty channel.
+fn latest_message_id(status: u16, body: &str) -> Result<Option<String>, String> {
+    if status != 200 {
+        return Err(format!("HTTP {}", status));
+    }
+    match json::parse(body).map_err(|e| e.to_string())? {
+        JsonValue::Array(messages) => {
+            Ok(messages.first().and_then(|m| m.get_str("id")).map(str::to_string))
+        }
+        _ => Err("expected an array of messages".into()),
+    }
+}
+
+/// The messages after ID `after`, oldest first. `fetch` reads one page of
+/// the messages after an ID; pages are read until one comes back short or
+/// MAX_HISTORY_PAGES have been, leaving the rest for the next poll. If a
+/// later page fails, the messages before it are still returned.
+fn messages_after(
+    after: &str,
+    mut fetch: impl FnMut(&str) -> Result<Vec<JsonValue>, String>,
+) -> Result<Vec<JsonValue>, String> {
+    let id = |msg: &JsonValue| msg.get_str("id").and_then(|id| id.parse::<u64>().ok());
+    let mut messages: Vec<JsonValue> = Vec::new();
+    let mut cursor = after.to_string();
+    for _ in 0..MAX_HISTORY_PAGES {
+        let mut page = match fetch(&cursor) {
+            Ok(page) => page,
+            Err(e) if messages.is_empty() => return Err(e),
+            Err(e) => {
+                eprintln!("sentinel: discord history page failed, continuing next poll: {}", e);
+                break;
+            }
+        };
+        let full = page.len() >= HISTORY_PAGE;
+        // Snowflake IDs grow with time, whichever order the page came in
+        page.retain(|msg| id(msg).is_some());
+        page.sort_by_key(|msg| id(msg));
+        let Some(last) = page.last().and_then(|msg| msg.get_str("id")) else {
+            break;
+        };
+        cursor = last.to_string();
+        messages.extend(page);
+        if !full {
+            break;
+        }
+    }
+    Ok(messages)
+}
+
+/// URL and type of each attachment the model can be shown.
+fn image_attachments(msg: &JsonValue) -> Vec<(&str, &str)> {
+    let Some(attachments) = msg.get("attachments").and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+    attachments
+        .iter()
+        .filter(|a| a.get("size").and_then(|v| v.as_u64()).unwrap_or(u64::MAX) <= MAX_IMAGE_BYTES)
+        .filter_map(|a| {
+            let media_type = a.get_str("content_type")?;
+            let media_type = media_type.split(';').next().unwrap_or(media_type).trim();
+            is_supported_image(media_type).then_some((a.get_str("url")?, media_type))
+        })
+        .collect()
+}
+
+/// Percent-encode a unicode emoji for a reaction URL path segment.
+fn encode_emoji(emoji: &str) -> String {
+    emoji.bytes().map(|b| format!("%{:02X}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::http::EgressPolicy;
+    use std::rc::Rc;
+
+    fn page(ids: impl Iterator<Item = u64>) -> Vec<JsonValue> {
+        ids.map(|id| json::parse(&format!(r#"{{"id":"{}","content":"m{}"}}"#, id, id)).unwrap())
+            .collect()
+    }
+
+    fn ids(messages: &[JsonValue]) -> Vec<u64> {
+        messages.iter().map(|m| m.get_str("id").unwrap().parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_messages_after_follows_pages_in_order() {
+        let mut requested = Vec::new();
+        let messages = messages_after("100", |after| {
+            requested.push(after.to_string());
+            Ok(match after {
+                // A full page, newest first
+                "100" => page((101..=200).rev()),
+                "200" => page(201..=230),
+                _ => panic!("unexpected page after {}", after),
+            })
+        })
+        .unwrap();
+        assert_eq!(requested, ["100", "200"]);
+        assert_eq!(ids(&messages), (101..=230).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_messages_after_is_capped_and_keeps_pages_before_a_failure() {
+        let mut calls = 0;
+        let messages = messages_after("0", |after| {
+            calls += 1;
+            let start: u64 = after.parse().unwrap();
+            Ok(page(start + 1..=start + HISTORY_PAGE as u64))
+        })
+        .unwrap();
+        assert_eq!(calls, MAX_HISTORY_PAGES);
+        // The next poll carries on from the last message returned
+        assert_eq!(ids(&messages).last(), Some(&((MAX_HISTORY_PAGES * HISTORY_PAGE) as u64)));
+
+        let messages = messages_after("0", |after| match after {
+            "0" => Ok(page(1..=100)),
+            _ => Err("HTTP 500".into()),
+        })
+        .unwrap();
+        assert_eq!(ids(&messages), (1..=100).collect::<Vec<_>>());
+        assert!(messages_after("0", |_| Err("HTTP 500".into())).is_err());
+    }
+
+    #[test]
+    fn test_latest_message_id() {
+        assert_eq!(
+            latest_message_id(200, r#"[{"id":"42","content":"hi"}]"#).unwrap().as_deref(),
+            Some("42")
+        );
+        // An empty channel has no watermark; everything in it will be new
+        assert_eq!(latest_message_id(200, "[]").unwrap(), None);
+        // Failures leave the channel to be initialized again
+        assert_eq!(latest_message_id(503, "").unwrap_err(), "HTTP 503");
+        assert!(latest_message_id(200, "<html>").is_err());
+        assert!(latest_message_id(200, r#"{"message":"x"}"#).is_err());
+    }
+
+    #[test]
+    fn test_poll_fails_when_every_channel_fails() {
+        // Every request is refused, as with a token that works for no channel
+        let policy = EgressPolicy::new(vec!["allowed.invalid".into()], Rc::new(|_: &str| {}));
+        let http = HttpClient::new().unwrap().with_egress_policy(policy);
+        let mut conn = DiscordConnector {
+            files: image_client(&http),
+            http,
+            token: "t".into(),
+            channel_ids: vec!["1".into(), "2".into()],
+            bot_user_id: "9".into(),
+            dm_channels: HashSet::new(),
+            last_message_ids: HashMap::new(),
+            initialized_channels: HashMap::new(),
+            gateway: None,
+        };
+        // Initialization fails, and is retried on the next poll
+        let err = conn.poll_messages(0).err().unwrap().to_string();
+        assert!(err.contains("all 2 channels failed"), "{}", err);
+        assert!(conn.initialized_channels.is_empty());
+
+        conn.initialized_channels.insert("1".into(), true);
+        conn.initialized_channels.insert("2".into(), true);
+        assert!(conn.poll_messages(0).is_err());
+    }
+
+    #[test]
+    fn test_gateway_messages_without_guild_are_direct() {
+        let http = HttpClient::new().unwrap();
+        let mut conn = DiscordConnector {
+            files: image_client(&http),
+            http,
+            token: "t".into(),
+            channel_ids: vec!["1".into(), "2".into()],
+            bot_user_id: "9".into(),
+            dm_channels: HashSet::new(),
+            last_message_ids: HashMap::new(),
+            initialized_channels: HashMap::new(),
+            gateway: None,
+        };
+        let events = vec![
+            json::parse(r#"{"id":"5","channel_id":"1","guild_id":"g","type":0,"content":"hi","author":{"id":"u1"}}"#)
+                .unwrap(),
+            json::parse(r#"{"id":"6","channel_id":"2","type":0,"content":"hi","author":{"id":"u1"}}"#)
+                .unwrap(),
+        ];
+        let addressed: Vec<bool> = conn.gateway_messages(events).iter().map(|m| m.addressed).collect();
+        assert_eq!(addressed, vec![false, true]);
+    }
+
+    #[test]
+    fn test_encode_emoji() {
+        assert_eq!(encode_emoji("👀"), "%F0%9F%91%80");
+        assert_eq!(encode_emoji("✅"), "%E2%9C%85");
+    }
+
+    #[test]
+    fn test_incoming_message_filters() {
+        let msg = |author: &str, kind: i64, content: &str| {
+            json::parse(&format!(
+                r#"{{"id":"5","channel_id":"c1","type":{},"content":"{}","author":{{"id":"{}","username":"ann"}}}}"#,
+                kind, content, author
+            ))
+            .unwrap()
+        };
+        let m = incoming_message(&msg("u1", 0, "hello"), "c1", "bot", false).unwrap();
+        assert_eq!(m.user_id, "u1");
+        assert_eq!(m.message_id, "5");
+        assert_eq!(m.username.as_deref(), Some("ann"));
+        assert_eq!(m.text, "hello");
+
+        assert!(incoming_message(&msg("bot", 0, "echo"), "c1", "bot", false).is_none());
+        assert!(incoming_message(&msg("u1", 7, "joined"), "c1", "bot", false).is_none());
+        assert!(incoming_message(&msg("u1", 0, ""), "c1", "bot", false).is_none());
+        assert!(!m.addressed);
+    }
+
+    #[test]
+    fn test_image_attachments() {
+        let msg = json::parse(
+            r#"{"id":"5","type":0,"content":"","author":{"id":"u1"},"attachments":[
+                {"url":"https://cdn.discordapp.com/a.png","content_type":"image/png","size":2048},
+                {"url":"https://cdn.discordapp.com/b.pdf","content_type":"application/pdf","size":2048},
+                {"url":"https://cdn.discordapp.com/c.jpg","content_type":"image/jpeg","size":9000000}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            image_attachments(&msg),
+            vec![("https://cdn.discordapp.com/a.png", "image/png")]
+        );
+        // An image alone is enough to answer; it is only referred to here
+        let m = incoming_message(&msg, "c1", "bot", false).unwrap();
+        assert_eq!(m.text, "[image]");
+        assert_eq!(
+            m.images,
+            vec![ImageRef {
+                source: "https://cdn.discordapp.com/a.png".into(),
+                media_type: "image/png".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_incoming_message_mentions_and_replies() {
+        let parse = |extra: &str, content: &str| {
+            let msg = json::parse(&format!(
+                r#"{{"id":"5","type":0,"content":"{}","author":{{"id":"u1"}}{}}}"#,
+                content, extra
+            ))
+            .unwrap();
+            incoming_message(&msg, "c1", "42", false).unwrap()
+        };
+
+        let m = parse(r#","mentions":[{"id":"42"}]"#, "<@42> status please");
+        assert!(m.addressed);
+        assert_eq!(m.text, "status please");
+
+        let m = parse(r#","mentions":[{"id":"42"}]"#, "hey <@!42>");
+        assert!(m.addressed);
+        assert_eq!(m.text, "hey");
+
+        // Someone else mentioned
+        let m = parse(r#","mentions":[{"id":"7"}]"#, "<@7> hi");
+        assert!(!m.addressed);
+        assert_eq!(m.text, "<@7> hi");
+
+        // A reply (type 19) to one of the bot's messages
+        let reply = json::parse(
+            r#"{"id":"6","type":19,"content":"why?","author":{"id":"u1"},"referenced_message":{"author":{"id":"42"}}}"#,
+        )
+        .unwrap();
+        let m = incoming_message(&reply, "c1", "42", false).unwrap();
+        assert!(m.addressed);
+        assert_eq!(m.text, "why?");
+
+        // Anything in a direct message is for the bot
+        let dm = json::parse(r#"{"id":"7","type":0,"content":"uptime?","author":{"id":"u1"}}"#).unwrap();
+        assert!(incoming_message(&dm, "c1", "42", true).unwrap().addressed);
+        assert!(!incoming_message(&dm, "c1", "42", false).unwrap().addressed);
+    }
 }
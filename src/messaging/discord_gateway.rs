@@ -0,0 +1,272 @@
+//! Discord gateway: receive messages in real time over a WebSocket instead
+//! of polling each channel's REST endpoint.
+//!
+//! Only the receiving side lives here. The session identifies, keeps its
+//! heartbeat, and hands back `MESSAGE_CREATE` payloads; sending and editing
+//! stay on REST. Any failure ends the session and the connector decides
+//! whether to reconnect or fall back to polling.
+
+use std::time::{Duration, Instant};
+
+use crate::messaging::ConnectorError;
+use crate::net::http::{HttpClient, TlsWebSocket};
+use crate::net::json::{self, json_obj, JsonNumber, JsonValue};
+use crate::net::websocket::WsMessage;
+
+/// Gateway events wanted: GUILD_MESSAGES, DIRECT_MESSAGES and the
+/// privileged MESSAGE_CONTENT, without which message text arrives empty.
+const INTENTS: i64 = (1 << 9) | (1 << 12) | (1 << 15);
+/// How long the hello/identify/ready exchange may take.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+
+const OP_DISPATCH: i64 = 0;
+const OP_HEARTBEAT: i64 = 1;
+const OP_IDENTIFY: i64 = 2;
+const OP_RECONNECT: i64 = 7;
+const OP_INVALID_SESSION: i64 = 9;
+const OP_HELLO: i64 = 10;
+const OP_HEARTBEAT_ACK: i64 = 11;
+
+pub struct Gateway {
+    ws: TlsWebSocket,
+    session: Session,
+}
+
+/// Sequence and heartbeat bookkeeping, kept apart from the socket.
+struct Session {
+    heartbeat_interval: Duration,
+    next_heartbeat: Instant,
+    /// A heartbeat went out and Discord hasn't acknowledged it yet.
+    awaiting_ack: bool,
+    /// Last sequence number received, echoed in heartbeats.
+    seq: Option<i64>,
+}
+
+/// What the connector must do about one gateway payload.
+#[derive(Debug)]
+enum Dispatch {
+    Message(JsonValue),
+    Ready,
+    HeartbeatNow,
+    Ignore,
+}
+
+impl Session {
+    fn new(heartbeat_interval: Duration) -> Self {
+        Session {
+            heartbeat_interval,
+            // Discord asks for the first beat at a random point in the
+            // interval; halfway is as good as any for a single client
+            next_heartbeat: Instant::now() + heartbeat_interval / 2,
+            awaiting_ack: false,
+            seq: None,
+        }
+    }
+
+    fn apply(&mut self, payload: &JsonValue) -> Result<Dispatch, String> {
+        if let Some(s) = payload.get("s").and_then(|v| v.as_i64()) {
+            self.seq = Some(s);
+        }
+        let op = payload.get("op").and_then(|v| v.as_i64()).unwrap_or(-1);
+        Ok(match op {
+            OP_DISPATCH => match payload.get_str("t") {
+                Some("MESSAGE_CREATE") => match payload.get("d") {
+                    Some(d) => Dispatch::Message(d.clone()),
+                    None => Dispatch::Ignore,
+                },
+                Some("READY") => Dispatch::Ready,
+                _ => Dispatch::Ignore,
+            },
+            OP_HEARTBEAT => Dispatch::HeartbeatNow,
+            OP_RECONNECT => return Err("gateway asked for a reconnect".into()),
+            OP_INVALID_SESSION => return Err("gateway session invalidated".into()),
+            OP_HEARTBEAT_ACK => {
+                self.awaiting_ack = false;
+                Dispatch::Ignore
+            }
+            _ => Dispatch::Ignore,
+        })
+    }
+
+    fn heartbeat_payload(&self) -> String {
+        let seq = self
+            .seq
+            .map_or(JsonValue::Null, |s| JsonValue::Number(JsonNumber::Int(s)));
+        json_obj()
+            .field_i64("op", OP_HEARTBEAT)
+            .field("d", seq)
+            .build()
+            .to_json_string()
+    }
+}
+
+impl Gateway {
+    /// Connect to `url` (from GET /gateway/bot), identify, and wait for READY.
+    pub fn connect(http: &HttpClient, url: &str, token: &str) -> Result<Gateway, ConnectorError> {
+        let mut ws = http.connect_websocket(&format!("{}/?v=10&encoding=json", url))?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+
+        let hello = next_payload(&mut ws, deadline)?
+            .ok_or_else(|| ConnectorError::Api("timed out waiting for gateway hello".into()))?;
+        if hello.get("op").and_then(|v| v.as_i64()) != Some(OP_HELLO) {
+            return Err(ConnectorError::Api("gateway did not start with hello".into()));
+        }
+        let interval = hello
+            .get_path("d.heartbeat_interval")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| ConnectorError::Api("gateway hello without heartbeat_interval".into()))?;
+
+        let mut gateway = Gateway {
+            ws,
+            session: Session::new(Duration::from_millis(interval)),
+        };
+        gateway.ws.send_text(&identify_payload(token))?;
+
+        loop {
+            let payload = next_payload(&mut gateway.ws, deadline)?
+                .ok_or_else(|| ConnectorError::Api("timed out waiting for gateway READY".into()))?;
+            match gateway.session.apply(&payload).map_err(ConnectorError::Api)? {
+                Dispatch::Ready => return Ok(gateway),
+                Dispatch::HeartbeatNow => gateway.send_heartbeat()?,
+                Dispatch::Message(_) | Dispatch::Ignore => {}
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for messages, heartbeating as needed. Returns
+    /// the `d` object of each MESSAGE_CREATE as soon as one arrives.
+    pub fn poll(&mut self, timeout: Duration) -> Result<Vec<JsonValue>, ConnectorError> {
+        let deadline = Instant::now() + timeout;
+        let mut messages = Vec::new();
+        loop {
+            let now = Instant::now();
+            if now >= self.session.next_heartbeat {
+                // No ack since the last beat: the connection is a zombie
+                if self.session.awaiting_ack {
+                    return Err(ConnectorError::Api("gateway heartbeat not acknowledged".into()));
+                }
+                self.send_heartbeat()?;
+            }
+            if !messages.is_empty() || now >= deadline {
+                return Ok(messages);
+            }
+            let wake = self.session.next_heartbeat.min(deadline);
+            if let Some(payload) = next_payload(&mut self.ws, wake)? {
+                match self.session.apply(&payload).map_err(ConnectorError::Api)? {
+                    Dispatch::Message(d) => messages.push(d),
+                    Dispatch::HeartbeatNow => self.send_heartbeat()?,
+                    Dispatch::Ready | Dispatch::Ignore => {}
+                }
+            }
+        }
+    }
+
+    fn send_heartbeat(&mut self) -> Result<(), ConnectorError> {
+        self.ws.send_text(&self.session.heartbeat_payload())?;
+        self.session.awaiting_ack = true;
+        self.session.next_heartbeat = Instant::now() + self.session.heartbeat_interval;
+        Ok(())
+    }
+}
+
+impl Drop for Gateway {
+    fn drop(&mut self) {
+        let _ = self.ws.close(1000);
+    }
+}
+
+fn identify_payload(token: &str) -> String {
+    let properties = json_obj()
+        .field_str("os", std::env::consts::OS)
+        .field_str("browser", "luperiq-sentinel")
+        .field_str("device", "luperiq-sentinel")
+        .build();
+    let d = json_obj()
+        .field_str("token", token)
+        .field_i64("intents", INTENTS)
+        .field("properties", properties)
+        .build();
+    json_obj()
+        .field_i64("op", OP_IDENTIFY)
+        .field("d", d)
+        .build()
+        .to_json_string()
+}
+
+/// Read the next JSON payload, or `None` if `deadline` passes first.
+fn next_payload(ws: &mut TlsWebSocket, deadline: Instant) -> Result<Option<JsonValue>, ConnectorError> {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(None);
+        }
+        ws.set_read_timeout(remaining)?;
+        match ws.read_message()? {
+            None => return Ok(None),
+            Some(WsMessage::Text(text)) => {
+                return json::parse(&text)
+                    .map(Some)
+                    .map_err(|e| ConnectorError::Json(e.to_string()))
+            }
+            Some(WsMessage::Binary(_)) => {}
+            Some(WsMessage::Close(code)) => return Err(ConnectorError::Api(close_reason(code))),
+        }
+    }
+}
+
+fn close_reason(code: Option<u16>) -> String {
+    match code {
+        Some(4004) => "gateway closed: authentication failed".into(),
+        Some(4014) => "gateway closed: disallowed intents (enable the Message Content \
+                       intent for the bot in the Discord developer portal)"
+            .into(),
+        Some(c) => format!("gateway closed with code {}", c),
+        None => "gateway closed".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(s: &str) -> JsonValue {
+        json::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_session_tracks_sequence_and_acks() {
+        let mut session = Session::new(Duration::from_secs(41));
+        assert_eq!(session.heartbeat_payload(), r#"{"op":1,"d":null}"#);
+
+        session.awaiting_ack = true;
+        let d = session
+            .apply(&payload(r#"{"op":0,"s":42,"t":"MESSAGE_CREATE","d":{"id":"9","content":"hi"}}"#))
+            .unwrap();
+        assert!(matches!(d, Dispatch::Message(ref m) if m.get_str("content") == Some("hi")));
+        assert_eq!(session.heartbeat_payload(), r#"{"op":1,"d":42}"#);
+
+        assert!(matches!(session.apply(&payload(r#"{"op":11}"#)).unwrap(), Dispatch::Ignore));
+        assert!(!session.awaiting_ack);
+        assert!(matches!(session.apply(&payload(r#"{"op":1,"d":null}"#)).unwrap(), Dispatch::HeartbeatNow));
+        assert!(matches!(
+            session.apply(&payload(r#"{"op":0,"s":43,"t":"TYPING_START","d":{}}"#)).unwrap(),
+            Dispatch::Ignore
+        ));
+        assert_eq!(session.seq, Some(43));
+    }
+
+    #[test]
+    fn test_reconnect_and_invalid_session_end_the_session() {
+        let mut session = Session::new(Duration::from_secs(41));
+        assert!(session.apply(&payload(r#"{"op":7,"d":null}"#)).is_err());
+        assert!(session.apply(&payload(r#"{"op":9,"d":false}"#)).is_err());
+    }
+
+    #[test]
+    fn test_identify_payload() {
+        let p = payload(&identify_payload("tok"));
+        assert_eq!(p.get("op").and_then(|v| v.as_i64()), Some(OP_IDENTIFY));
+        assert_eq!(p.get_path("d.token").and_then(|v| v.as_str()), Some("tok"));
+        assert_eq!(p.get_path("d.intents").and_then(|v| v.as_i64()), Some(37376));
+    }
+}
@@ -3,8 +3,18 @@ pub mod telegram;
 #[cfg(feature = "tls")]
 pub mod discord;
 #[cfg(feature = "tls")]
+pub mod discord_gateway;
+#[cfg(feature = "tls")]
 pub mod slack;
+#[cfg(feature = "tls")]
+pub mod poller;
+#[cfg(feature = "tls")]
+pub mod webhook;
+
+use std::time::{Duration, Instant};
 
+#[cfg(feature = "tls")]
+use crate::net::http::HttpClient;
 use crate::net::http::HttpError;
 
 // ── Common types ─────────────────────────────────────────────────────────────
@@ -12,9 +22,71 @@ use crate::net::http::HttpError;
 /// A message received from a messaging platform.
 pub struct IncomingMessage {
     pub channel_id: String,
+    /// Platform ID of the message itself, for reactions.
+    pub message_id: String,
     pub user_id: String,
     pub username: Option<String>,
     pub text: String,
+    /// Whether the message is meant for the bot: a direct message, an
+    /// @-mention (already removed from `text`), or a reply to one of its
+    /// messages. Group chats can be limited to these.
+    pub addressed: bool,
+    /// Images attached to the message, for the model. They are fetched with
+    /// `Connector::fetch_image` once the message is known to be answered.
+    pub images: Vec<ImageRef>,
+}
+
+/// An image attached to a message, not downloaded yet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageRef {
+    /// Where the platform keeps it: a URL, or Telegram's file_id.
+    pub source: String,
+    /// MIME type, e.g. "image/jpeg".
+    pub media_type: String,
+}
+
+/// An image downloaded from a message.
+pub struct ImageAttachment {
+    /// MIME type, e.g. "image/jpeg".
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Largest image downloaded for the model; providers refuse bigger ones.
+pub const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Whether the model can be sent an image of this type.
+pub fn is_supported_image(media_type: &str) -> bool {
+    matches!(media_type, "image/jpeg" | "image/png" | "image/gif" | "image/webp")
+}
+
+/// Download an attachment the platform has said is an image of `media_type`.
+/// `http` should refuse bodies over `MAX_IMAGE_BYTES` (see `image_client`),
+/// so a bigger one isn't read in full first.
+#[cfg(feature = "tls")]
+pub fn download_image(
+    http: &HttpClient,
+    url: &str,
+    media_type: &str,
+) -> Result<ImageAttachment, ConnectorError> {
+    let resp = http.get(url, &[])?;
+    if resp.status != 200 {
+        return Err(ConnectorError::Api(format!("image download returned {}", resp.status)));
+    }
+    if resp.body.len() as u64 > MAX_IMAGE_BYTES {
+        return Err(ConnectorError::Api(format!("image is over {} bytes", MAX_IMAGE_BYTES)));
+    }
+    Ok(ImageAttachment {
+        media_type: media_type.to_string(),
+        data: resp.body,
+    })
+}
+
+/// A client for image downloads, with `http`'s settings and a body limit of
+/// `MAX_IMAGE_BYTES`.
+#[cfg(feature = "tls")]
+pub fn image_client(http: &HttpClient) -> HttpClient {
+    http.fork().with_max_response_bytes(MAX_IMAGE_BYTES as usize)
 }
 
 /// Error from a messaging connector.
@@ -65,46 +137,233 @@ pub trait Connector {
 
     /// Platform name for logging (e.g., "telegram", "discord", "slack").
     fn platform_name(&self) -> &'static str;
+
+    /// Download an image attached to an incoming message. Only called for
+    /// messages that get answered, after the sender is authorized.
+    fn fetch_image(&self, _image: &ImageRef) -> Result<ImageAttachment, ConnectorError> {
+        Err(ConnectorError::Api(format!("{} has no image attachments", self.platform_name())))
+    }
+
+    /// Show a "typing…" indicator in a channel. Platforms expire it after a
+    /// few seconds, so callers repeat it (see `TypingIndicator`). Does
+    /// nothing on platforms without one.
+    fn send_typing(&self, _channel_id: &str) -> Result<(), ConnectorError> {
+        Ok(())
+    }
+
+    /// Mark an incoming message as being worked on (a 👀 reaction). Does
+    /// nothing on platforms without reactions.
+    fn ack_received(&self, _channel_id: &str, _message_id: &str) -> Result<(), ConnectorError> {
+        Ok(())
+    }
+
+    /// Replace the `ack_received` mark with the outcome of the turn.
+    fn ack_done(
+        &self,
+        _channel_id: &str,
+        _message_id: &str,
+        _success: bool,
+    ) -> Result<(), ConnectorError> {
+        Ok(())
+    }
 }
 
 // ── Helpers ──────────────────────────────────────────────────────────────────
 
-/// Split a message into chunks respecting a maximum length, preferring line boundaries.
+/// How often to repeat the typing indicator; Telegram's lasts five seconds.
+const TYPING_REFRESH: Duration = Duration::from_secs(4);
+
+/// Keeps a channel's typing indicator alive while the agent works. Each
+/// `refresh` resends it once the previous one is about to expire, so it can
+/// be called freely from progress callbacks.
+#[derive(Default)]
+pub struct TypingIndicator {
+    last_sent: Option<Instant>,
+}
+
+impl TypingIndicator {
+    pub fn refresh(&mut self, connector: &dyn Connector, channel_id: &str) {
+        self.refresh_at(Instant::now(), connector, channel_id);
+    }
+
+    fn refresh_at(&mut self, now: Instant, connector: &dyn Connector, channel_id: &str) {
+        if self.last_sent.is_some_and(|t| now.duration_since(t) < TYPING_REFRESH) {
+            return;
+        }
+        if let Err(e) = connector.send_typing(channel_id) {
+            eprintln!("sentinel: typing indicator error: {}", e);
+        }
+        self.last_sent = Some(now);
+    }
+}
+
+/// The outcome of a poll over several channels. A failing channel is skipped
+/// so the others still get through, but when every channel failed (a
+/// revoked or mis-scoped token) the poll fails, so the poller backs off.
+pub fn channel_poll_result(
+    messages: Vec<IncomingMessage>,
+    errors: Vec<String>,
+    channels: usize,
+) -> Result<Vec<IncomingMessage>, ConnectorError> {
+    if channels > 0 && errors.len() >= channels {
+        return Err(ConnectorError::Api(format!(
+            "all {} channels failed: {}",
+            channels,
+            errors.join("; ")
+        )));
+    }
+    Ok(messages)
+}
+
+/// `text` without every occurrence of `mention`, and whether it had one.
+pub fn strip_mention(text: &str, mention: &str) -> (String, bool) {
+    if mention.is_empty() || !text.contains(mention) {
+        return (text.to_string(), false);
+    }
+    // Take the space after a mention along, so none are left doubled
+    let text = text.replace(&format!("{} ", mention), "").replace(mention, "");
+    (text.trim().to_string(), true)
+}
+
+/// Closes a code block cut at a chunk boundary.
+const FENCE_CLOSE: &str = "\n```";
+
+/// Split a message into chunks respecting a maximum length, preferring line
+/// boundaries. Fenced code blocks are kept whole when they fit in a chunk;
+/// one that doesn't is closed at the end of each chunk and reopened, with
+/// its language tag, at the start of the next, so every chunk renders on
+/// its own.
 pub fn split_message(text: &str, max_len: usize) -> Vec<String> {
     if text.len() <= max_len {
         return vec![text.to_string()];
     }
 
+    let lines: Vec<&str> = text.split('\n').collect();
     let mut chunks = Vec::new();
-    let mut remaining = text;
+    let mut cur = String::new();
+    // Whether `cur` holds any of `text` yet (beyond a reopened fence)
+    let mut has_content = false;
+    // The opening line (e.g. "```rust") of the code block we're inside
+    let mut fence: Option<&str> = None;
 
-    while !remaining.is_empty() {
-        if remaining.len() <= max_len {
-            chunks.push(remaining.to_string());
-            break;
-        }
+    let mut i = 0;
+    let mut rest = lines.first().copied().unwrap_or_default();
+    loop {
+        let is_fence = rest.trim_start().starts_with("```");
 
-        let split_at = remaining[..max_len]
-            .rfind('\n')
-            .unwrap_or(max_len);
+        // Move a code block to a fresh chunk rather than cut it, if it fits
+        if is_fence && fence.is_none() && has_content {
+            let block = block_len(&lines[i..]);
+            if block <= max_len && cur.len() + 1 + block > max_len {
+                flush_chunk(&mut chunks, &mut cur, fence);
+                has_content = false;
+            }
+        }
 
-        let (chunk, rest) = remaining.split_at(split_at);
-        chunks.push(chunk.to_string());
+        let fence_after = match (is_fence, fence) {
+            (true, Some(_)) => None,
+            (true, None) => Some(rest.trim()),
+            (false, f) => f,
+        };
+        let reserve = if fence_after.is_some() { FENCE_CLOSE.len() } else { 0 };
+        let sep = usize::from(has_content || !cur.is_empty());
 
-        remaining = if rest.starts_with('\n') {
-            &rest[1..]
+        if cur.len() + sep + rest.len() + reserve <= max_len {
+            if sep == 1 {
+                cur.push('\n');
+            }
+            cur.push_str(rest);
+            has_content = true;
+            fence = fence_after;
+            i += 1;
+            match lines.get(i) {
+                Some(line) => rest = line,
+                None => break,
+            }
+        } else if has_content {
+            flush_chunk(&mut chunks, &mut cur, fence);
+            has_content = false;
         } else {
-            rest
-        };
+            // A single line longer than a chunk: cut it at the limit
+            let room = max_len.saturating_sub(cur.len() + sep + reserve);
+            let at = floor_char_boundary(rest, room.max(1));
+            let at = if at == 0 { rest.chars().next().map_or(1, char::len_utf8) } else { at };
+            if sep == 1 {
+                cur.push('\n');
+            }
+            cur.push_str(&rest[..at]);
+            rest = &rest[at..];
+            flush_chunk(&mut chunks, &mut cur, fence);
+        }
+    }
+    if has_content {
+        chunks.push(cur);
     }
 
     chunks
 }
 
+/// Push the chunk being built, closing an open code block, and start the
+/// next one by reopening it.
+fn flush_chunk(chunks: &mut Vec<String>, cur: &mut String, fence: Option<&str>) {
+    if fence.is_some() {
+        cur.push_str(FENCE_CLOSE);
+    }
+    chunks.push(std::mem::take(cur));
+    if let Some(open) = fence {
+        cur.push_str(open);
+    }
+}
+
+/// Length of the code block opening at `lines[0]`, through its closing
+/// fence (or the end of the text if it has none).
+fn block_len(lines: &[&str]) -> usize {
+    let end = lines
+        .iter()
+        .skip(1)
+        .position(|l| l.trim_start().starts_with("```"))
+        .map_or(lines.len(), |p| p + 2);
+    lines[..end].iter().map(|l| l.len() + 1).sum::<usize>() - 1
+}
+
+/// The largest char boundary in `s` at or below `index`.
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    if index >= s.len() {
+        return s.len();
+    }
+    (0..=index).rev().find(|&i| s.is_char_boundary(i)).unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_is_supported_image() {
+        assert!(is_supported_image("image/png"));
+        assert!(!is_supported_image("image/svg+xml"));
+        assert!(!is_supported_image("application/pdf"));
+    }
+
+    #[test]
+    fn test_channel_poll_result() {
+        // One channel failing doesn't hold up the others
+        assert!(channel_poll_result(Vec::new(), vec!["C1: HTTP 403".into()], 2).is_ok());
+        let err = channel_poll_result(Vec::new(), vec!["C1: HTTP 401".into(), "C2: HTTP 401".into()], 2)
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "API error: all 2 channels failed: C1: HTTP 401; C2: HTTP 401");
+        assert!(channel_poll_result(Vec::new(), Vec::new(), 0).is_ok());
+    }
+
+    #[test]
+    fn test_strip_mention() {
+        assert_eq!(strip_mention("<@U1> deploy it", "<@U1>"), ("deploy it".to_string(), true));
+        assert_eq!(strip_mention("so <@U1> what now", "<@U1>"), ("so what now".to_string(), true));
+        assert_eq!(strip_mention("ask <@U2>", "<@U1>"), ("ask <@U2>".to_string(), false));
+        assert_eq!(strip_mention("hi", ""), ("hi".to_string(), false));
+    }
+
     #[test]
     fn test_split_short_message() {
         let chunks = split_message("hello", 100);
@@ -129,4 +388,103 @@ mod tests {
         assert!(chunks[0].ends_with("line1"));
         assert!(chunks[1].ends_with("line2"));
     }
+
+    /// Every chunk is within the limit and opens as many fences as it closes.
+    fn assert_chunks_valid(chunks: &[String], max_len: usize) {
+        for chunk in chunks {
+            assert!(chunk.len() <= max_len, "chunk of {} bytes", chunk.len());
+            let fences = chunk.lines().filter(|l| l.trim_start().starts_with("```")).count();
+            assert_eq!(fences % 2, 0, "unterminated fence in {:?}", chunk);
+        }
+    }
+
+    #[test]
+    fn test_split_long_code_block_reopens_fence() {
+        let code: String = (0..100).map(|i| format!("let line_{:03} = {};\n", i, "x".repeat(40))).collect();
+        assert!(code.len() >= 5000);
+        let text = format!("Here is the file:\n```rust\n{}```\nDone.", code);
+        let chunks = split_message(&text, 2000);
+
+        assert!(chunks.len() >= 3);
+        assert_chunks_valid(&chunks, 2000);
+        assert!(chunks[0].starts_with("Here is the file:\n```rust\n"));
+        for chunk in &chunks[1..] {
+            assert!(chunk.starts_with("```rust\n"), "{:?}", &chunk[..20]);
+        }
+        assert!(chunks.last().unwrap().ends_with("```\nDone."));
+
+        // Nothing lost: stripping the added fences gives back the original
+        let rejoined = chunks
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let c = if i > 0 { c.strip_prefix("```rust\n").unwrap() } else { c };
+                c.strip_suffix("\n```").filter(|_| i + 1 < chunks.len()).unwrap_or(c)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(rejoined, text);
+    }
+
+    #[test]
+    fn test_split_prefers_boundary_between_blocks() {
+        let block = format!("```\n{}\n```", "y".repeat(60));
+        let text = format!("{}\n{}", "intro ".repeat(10), block);
+        let chunks = split_message(&text, 100);
+        assert_eq!(chunks, vec!["intro ".repeat(10), block]);
+    }
+
+    #[test]
+    fn test_split_long_line_inside_code_block() {
+        let text = format!("```py\n{}\n```", "z".repeat(5000));
+        let chunks = split_message(&text, 2000);
+        assert_chunks_valid(&chunks, 2000);
+        assert!(chunks.iter().all(|c| c.starts_with("```py\n") && c.ends_with("\n```")));
+        let zs: usize = chunks.iter().map(|c| c.matches('z').count()).sum();
+        assert_eq!(zs, 5000);
+    }
+
+    #[test]
+    fn test_split_respects_char_boundaries() {
+        let text = "é".repeat(150);
+        let chunks = split_message(&text, 101);
+        assert!(chunks.iter().all(|c| c.len() <= 101));
+        assert_eq!(chunks.concat(), text);
+    }
+
+    /// Counts typing indicators; every other method is inert.
+    struct TypingCounter(std::cell::Cell<u32>);
+
+    impl Connector for TypingCounter {
+        fn poll_messages(&mut self, _: u32) -> Result<Vec<IncomingMessage>, ConnectorError> {
+            Ok(Vec::new())
+        }
+        fn send_message(&self, _: &str, _: &str) -> Result<(), ConnectorError> {
+            Ok(())
+        }
+        fn send_message_get_id(&self, _: &str, _: &str) -> Result<String, ConnectorError> {
+            Ok(String::new())
+        }
+        fn edit_message_text(&self, _: &str, _: &str, _: &str) -> Result<(), ConnectorError> {
+            Ok(())
+        }
+        fn platform_name(&self) -> &'static str {
+            "fake"
+        }
+        fn send_typing(&self, _: &str) -> Result<(), ConnectorError> {
+            self.0.set(self.0.get() + 1);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_typing_indicator_refreshes_every_four_seconds() {
+        let conn = TypingCounter(std::cell::Cell::new(0));
+        let mut typing = TypingIndicator::default();
+        let start = Instant::now();
+        for ms in [0, 500, 3999, 4000, 4100, 8000] {
+            typing.refresh_at(start + Duration::from_millis(ms), &conn, "c");
+        }
+        assert_eq!(conn.0.get(), 3);
+    }
 }
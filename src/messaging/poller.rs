@@ -0,0 +1,240 @@
+//! One polling thread per connector, all feeding a single work queue.
+//!
+//! Connectors hold non-`Send` HTTP state, so each thread builds its own
+//! instance from a factory closure and only the received messages cross
+//! threads. A long poll on one platform therefore never delays another.
+
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::{Connector, IncomingMessage};
+use crate::log;
+
+/// Pause after a failed poll before trying again, doubled for each further
+/// failure in a row up to `MAX_ERROR_BACKOFF`.
+const ERROR_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_ERROR_BACKOFF: Duration = Duration::from_secs(300);
+/// Failures in a row after which a poller is reported as failing, once.
+const ESCALATE_AFTER: u32 = 10;
+/// Pause after an empty poll, so platforms without long polling don't spin.
+const IDLE_DELAY: Duration = Duration::from_secs(1);
+
+/// An item on the shared work queue.
+pub enum Event {
+    /// A message received by the connector at `connector` (an index into
+    /// the turn executor's own connector list).
+    Message {
+        connector: usize,
+        message: IncomingMessage,
+    },
+    /// A poll thread's HTTP client refused a host outside the egress
+    /// allowlist. Forwarded so the audit log keeps a single writer.
+    EgressDenied(String),
+}
+
+/// Spawn a thread that builds a connector with `make` and polls it until
+/// the queue's receiver is dropped. If `make` fails the error is logged and
+/// the thread exits.
+///
+/// Each message is first offered to `intercept`, which handles messages
+/// that can't wait for the queue (such as /cancel while a turn is running);
+/// returning true consumes the message. `on_poll` is told how every poll
+/// went, and why `make` failed if it does.
+pub fn spawn<F, I, P>(
+    connector: usize,
+    name: &'static str,
+    make: F,
+    intercept: I,
+    on_poll: P,
+    timeout_secs: u32,
+    queue: Sender<Event>,
+) -> JoinHandle<()>
+where
+    F: FnOnce() -> Result<Box<dyn Connector>, String> + Send + 'static,
+    I: Fn(&IncomingMessage) -> bool + Send + 'static,
+    P: Fn(Result<(), &str>) + Send + 'static,
+{
+    thread::Builder::new()
+        .name(format!("poll-{}", name))
+        .spawn(move || {
+            let mut conn = match make() {
+                Ok(c) => c,
+                Err(e) => {
+                    log::error(
+                        &format!("{} poller failed to start: {}", name, e),
+                        &[("connector", &name), ("error", &e)],
+                    );
+                    on_poll(Err(&e));
+                    return;
+                }
+            };
+            // Failed polls in a row
+            let mut failures = 0u32;
+            loop {
+                let updates = match conn.poll_messages(timeout_secs) {
+                    Ok(msgs) => {
+                        if failures >= ESCALATE_AFTER {
+                            log::info(
+                                &format!("{} poller recovered after {} failures", name, failures),
+                                &[("connector", &name), ("failures", &failures)],
+                            );
+                        }
+                        failures = 0;
+                        on_poll(Ok(()));
+                        msgs
+                    }
+                    Err(e) => {
+                        failures = failures.saturating_add(1);
+                        let delay = error_backoff(failures);
+                        log::warn(
+                            &format!("{} poll error (retrying in {}s): {}", name, delay.as_secs(), e),
+                            &[("connector", &name), ("error", &e), ("failures", &failures)],
+                        );
+                        if failures == ESCALATE_AFTER {
+                            log::error(
+                                &format!(
+                                    "{} has failed {} polls in a row; retrying every {}s at most",
+                                    name,
+                                    failures,
+                                    MAX_ERROR_BACKOFF.as_secs()
+                                ),
+                                &[("connector", &name), ("error", &e), ("failures", &failures)],
+                            );
+                        }
+                        on_poll(Err(&e.to_string()));
+                        thread::sleep(delay);
+                        continue;
+                    }
+                };
+                if updates.is_empty() {
+                    thread::sleep(IDLE_DELAY);
+                    continue;
+                }
+                for message in updates {
+                    if intercept(&message) {
+                        continue;
+                    }
+                    if queue.send(Event::Message { connector, message }).is_err() {
+                        return;
+                    }
+                }
+            }
+        })
+        .expect("failed to spawn poll thread")
+}
+
+/// How long to wait after the `failures`th failed poll in a row.
+fn error_backoff(failures: u32) -> Duration {
+    let doublings = failures.saturating_sub(1).min(16);
+    (ERROR_BACKOFF * (1 << doublings)).min(MAX_ERROR_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::ConnectorError;
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    /// Returns one message per poll, each after `delay`.
+    struct DelayedConnector {
+        delay: Duration,
+        text: &'static str,
+    }
+
+    impl Connector for DelayedConnector {
+        fn poll_messages(&mut self, _: u32) -> Result<Vec<IncomingMessage>, ConnectorError> {
+            thread::sleep(self.delay);
+            Ok(vec![IncomingMessage {
+                channel_id: "c".into(),
+                message_id: "m".into(),
+                user_id: "u".into(),
+                username: None,
+                text: self.text.into(),
+                addressed: true,
+                images: Vec::new(),
+            }])
+        }
+        fn send_message(&self, _: &str, _: &str) -> Result<(), ConnectorError> {
+            Ok(())
+        }
+        fn send_message_get_id(&self, _: &str, _: &str) -> Result<String, ConnectorError> {
+            Ok(String::new())
+        }
+        fn edit_message_text(&self, _: &str, _: &str, _: &str) -> Result<(), ConnectorError> {
+            Ok(())
+        }
+        fn platform_name(&self) -> &'static str {
+            "fake"
+        }
+    }
+
+    fn delayed(delay: Duration, text: &'static str) -> Box<dyn Connector> {
+        Box::new(DelayedConnector { delay, text })
+    }
+
+    #[test]
+    fn test_long_poll_does_not_delay_other_connector() {
+        let (tx, rx) = mpsc::channel();
+        let start = Instant::now();
+        spawn(0, "slow", || Ok(delayed(Duration::from_secs(5), "slow")), |_| false, |_| {}, 30, tx.clone());
+        spawn(1, "fast", || Ok(delayed(Duration::ZERO, "fast")), |_| false, |_| {}, 30, tx);
+
+        match rx.recv_timeout(Duration::from_secs(2)).unwrap() {
+            Event::Message { connector, message } => {
+                assert_eq!(connector, 1);
+                assert_eq!(message.text, "fast");
+            }
+            Event::EgressDenied(_) => panic!("unexpected event"),
+        }
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_failed_factory_ends_thread() {
+        let (tx, rx) = mpsc::channel();
+        spawn(0, "broken", || Err("no token".to_string()), |_| false, |_| {}, 30, tx)
+            .join()
+            .unwrap();
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn test_thread_exits_when_queue_dropped() {
+        let (tx, rx) = mpsc::channel();
+        let handle = spawn(0, "fast", || Ok(delayed(Duration::ZERO, "x")), |_| false, |_| {}, 30, tx);
+        assert!(rx.recv().is_ok());
+        drop(rx);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_poll_results_are_reported() {
+        let (tx, _rx) = mpsc::channel();
+        let (results, seen) = mpsc::channel();
+        let on_poll = move |r: Result<(), &str>| {
+            let _ = results.send(r.map_err(str::to_string));
+        };
+        spawn(0, "broken", || Err("no token".to_string()), |_| false, on_poll.clone(), 30, tx.clone());
+        assert_eq!(seen.recv().unwrap(), Err("no token".to_string()));
+
+        spawn(1, "fast", || Ok(delayed(Duration::ZERO, "x")), |_| false, on_poll, 30, tx);
+        assert_eq!(seen.recv_timeout(Duration::from_secs(2)).unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn test_error_backoff_doubles_up_to_cap() {
+        let secs: Vec<u64> = (1..=8).map(|n| error_backoff(n).as_secs()).collect();
+        assert_eq!(secs, [5, 10, 20, 40, 80, 160, 300, 300]);
+        assert_eq!(error_backoff(0), ERROR_BACKOFF);
+        assert_eq!(error_backoff(u32::MAX), MAX_ERROR_BACKOFF);
+    }
+
+    #[test]
+    fn test_intercepted_messages_are_not_queued() {
+        let (tx, rx) = mpsc::channel();
+        spawn(0, "cancel", || Ok(delayed(Duration::from_millis(50), "/cancel")), |m| m.text == "/cancel", |_| {}, 30, tx);
+        assert!(rx.recv_timeout(Duration::from_millis(300)).is_err());
+    }
+}
@@ -1,11 +1,17 @@
 use std::collections::HashMap;
 
-use crate::messaging::{split_message, Connector, ConnectorError, IncomingMessage};
+use crate::messaging::{
+    channel_poll_result, split_message, strip_mention, Connector, ConnectorError, IncomingMessage,
+};
 use crate::net::http::HttpClient;
-use crate::net::json::{self, json_obj};
+use crate::net::json::{self, json_obj, JsonValue};
 
 const SLACK_API: &str = "https://slack.com/api";
 const SLACK_MSG_LIMIT: usize = 40000;
+/// Messages per page of channel history.
+const HISTORY_PAGE: usize = 100;
+/// Pages of history read per channel per poll.
+const MAX_HISTORY_PAGES: usize = 5;
 
 // ── Client ──────────────────────────────────────────────────────────────────
 
@@ -16,6 +22,9 @@ pub struct SlackConnector {
     bot_user_id: String,
     last_timestamps: HashMap<String, String>,
     initialized_channels: HashMap<String, bool>,
+    /// Where the next history read stops, while a backlog too long for one
+    /// poll is walked back through.
+    history_bounds: HashMap<String, String>,
 }
 
 impl SlackConnector {
@@ -30,7 +39,7 @@ impl SlackConnector {
         let resp = http.post_json(&url, "{}", &[("Authorization", &auth)])?;
         let body = resp
             .body_string()
-            .map_err(|e| ConnectorError::Http(e))?;
+            .map_err(ConnectorError::Http)?;
         let json_val =
             json::parse(&body).map_err(|e| ConnectorError::Json(e.to_string()))?;
 
@@ -39,10 +48,7 @@ impl SlackConnector {
             .and_then(|v| v.as_bool())
             .unwrap_or(false)
         {
-            let error = json_val
-                .get("error")
-                .and_then(|v| v.as_str())
-                .unwrap_or("unknown");
+            let error = json_val.get_str("error").unwrap_or("unknown");
             return Err(ConnectorError::Api(format!(
                 "Slack auth.test failed: {}",
                 error
@@ -50,8 +56,7 @@ impl SlackConnector {
         }
 
         let bot_user_id = json_val
-            .get("user_id")
-            .and_then(|v| v.as_str())
+            .get_str("user_id")
             .ok_or_else(|| ConnectorError::Api("missing user_id in auth.test".into()))?
             .to_string();
 
@@ -68,14 +73,128 @@ impl SlackConnector {
             bot_user_id,
             last_timestamps: HashMap::new(),
             initialized_channels: HashMap::new(),
+            history_bounds: HashMap::new(),
         })
     }
 
+    /// Call `reactions.add` or `reactions.remove` for the bot's reaction.
+    fn react(
+        &self,
+        method: &str,
+        channel_id: &str,
+        ts: &str,
+        name: &str,
+    ) -> Result<(), ConnectorError> {
+        let auth = self.auth_header();
+        let url = format!("{}/{}", SLACK_API, method);
+        let body = json_obj()
+            .field_str("channel", channel_id)
+            .field_str("timestamp", ts)
+            .field_str("name", name)
+            .build();
+        let resp =
+            self.http
+                .post_json(&url, &body.to_json_string(), &[("Authorization", &auth)])?;
+        let body_str = resp.body_string().map_err(ConnectorError::Http)?;
+        let json_val =
+            json::parse(&body_str).map_err(|e| ConnectorError::Json(e.to_string()))?;
+
+        if !json_val.get("ok").and_then(|v| v.as_bool()).unwrap_or(false) {
+            let error = json_val.get_str("error").unwrap_or("unknown");
+            // Already in the requested state
+            if error != "already_reacted" && error != "no_reaction" {
+                return Err(ConnectorError::Api(format!("Slack {} error: {}", method, error)));
+            }
+        }
+        Ok(())
+    }
+
     fn auth_header(&self) -> String {
         format!("Bearer {}", self.token)
     }
 }
 
+// ── History paging ──────────────────────────────────────────────────────────
+
+/// The messages and next-page cursor of a conversations.history response.
+fn history_page(json_val: &JsonValue) -> Result<(Vec<JsonValue>, Option<String>), String> {
+    if !json_val.get("ok").and_then(|v| v.as_bool()).unwrap_or(false) {
+        let error = json_val.get_str("error").unwrap_or("unknown");
+        return Err(format!("history error: {}", error));
+    }
+    let messages = json_val
+        .get("messages")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let has_more = json_val.get("has_more").and_then(|v| v.as_bool()).unwrap_or(false);
+    let cursor = json_val
+        .get_path("response_metadata.next_cursor")
+        .and_then(|v| v.as_str())
+        .filter(|c| has_more && !c.is_empty())
+        .map(str::to_string);
+    Ok((messages, cursor))
+}
+
+/// The timestamp of the newest message in a `limit=1` history response, or
+/// `None` for an empty channel.
+fn latest_ts(json_val: &JsonValue) -> Result<Option<String>, String> {
+    let (messages, _) = history_page(json_val)?;
+    Ok(messages.first().and_then(|m| m.get_str("ts")).map(str::to_string))
+}
+
+/// What a channel's history holds after the watermark.
+#[derive(Debug)]
+enum History {
+    /// Every new message, oldest first.
+    Complete(Vec<JsonValue>),
+    /// Over MAX_HISTORY_PAGES of new messages. Slack pages from the newest
+    /// back, so only the newest were read, and they can't be handed on
+    /// before the older ones; the next read stops short of `oldest_read`.
+    Truncated { oldest_read: String },
+}
+
+/// Every message a channel's history returns. `fetch` (given the cursor of
+/// the page to read) is followed until there's no next page, or until
+/// MAX_HISTORY_PAGES. Any failed page fails the lot, so the watermark never
+/// moves past messages not yet seen.
+fn history_since(
+    mut fetch: impl FnMut(Option<&str>) -> Result<(Vec<JsonValue>, Option<String>), String>,
+) -> Result<History, String> {
+    let mut messages = Vec::new();
+    let mut cursor: Option<String> = None;
+    for _ in 0..MAX_HISTORY_PAGES {
+        let (batch, next) = fetch(cursor.as_deref())?;
+        messages.extend(batch);
+        cursor = next;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    messages.sort_by(|a, b| {
+        let ts = |m: &JsonValue| m.get_str("ts").map(ts_key).unwrap_or_default();
+        ts(a).cmp(&ts(b))
+    });
+    if cursor.is_some() {
+        if let Some(oldest) = messages.first().and_then(|m| m.get_str("ts")) {
+            return Ok(History::Truncated { oldest_read: oldest.to_string() });
+        }
+    }
+    Ok(History::Complete(messages))
+}
+
+/// A message timestamp ("1700000000.000100") as numbers that sort in time order.
+fn ts_key(ts: &str) -> (u64, u64) {
+    let (secs, micros) = ts.split_once('.').unwrap_or((ts, "0"));
+    (secs.parse().unwrap_or(0), micros.parse().unwrap_or(0))
+}
+
+/// A cursor for a query string; they are base64, so only `+`, `/` and `=`
+/// need escaping.
+fn encode_cursor(cursor: &str) -> String {
+    cursor.replace('+', "%2B").replace('/', "%2F").replace('=', "%3D")
+}
+
 // ── Connector impl ──────────────────────────────────────────────────────────
 
 impl Connector for SlackConnector {
@@ -84,100 +203,93 @@ impl Connector for SlackConnector {
         _timeout_secs: u32,
     ) -> Result<Vec<IncomingMessage>, ConnectorError> {
         let mut all_messages = Vec::new();
+        let mut errors = Vec::new();
         let auth = self.auth_header();
 
         for channel_id in &self.channel_ids.clone() {
-            // First poll: record the latest timestamp without processing messages
+            // First poll: record the latest timestamp without processing
+            // messages. Until that works the channel has no watermark, so
+            // it is retried rather than read from the start.
             if !self.initialized_channels.contains_key(channel_id) {
                 let url = format!(
                     "{}/conversations.history?channel={}&limit=1",
                     SLACK_API, channel_id
                 );
-                match self.http.get(&url, &[("Authorization", &auth)]) {
-                    Ok(resp) => {
-                        if let Ok(body) = resp.body_string() {
-                            if let Ok(json_val) = json::parse(&body) {
-                                if let Some(msgs) =
-                                    json_val.get("messages").and_then(|v| v.as_array())
-                                {
-                                    if let Some(latest) = msgs.first() {
-                                        if let Some(ts) =
-                                            latest.get("ts").and_then(|v| v.as_str())
-                                        {
-                                            self.last_timestamps
-                                                .insert(channel_id.clone(), ts.to_string());
-                                        }
-                                    }
-                                }
-                            }
+                let latest = self
+                    .http
+                    .get(&url, &[("Authorization", &auth)])
+                    .map_err(|e| e.to_string())
+                    .and_then(|resp| resp.body_string().map_err(|e| e.to_string()))
+                    .and_then(|body| json::parse(&body).map_err(|e| e.to_string()))
+                    .and_then(|json_val| latest_ts(&json_val));
+                match latest {
+                    Ok(latest) => {
+                        if let Some(ts) = latest {
+                            self.last_timestamps.insert(channel_id.clone(), ts);
                         }
+                        self.initialized_channels.insert(channel_id.clone(), true);
                     }
                     Err(e) => {
                         eprintln!(
-                            "sentinel: slack init error for {}: {}",
+                            "sentinel: slack init error for {}: {}; retrying next poll",
                             channel_id, e
                         );
+                        errors.push(format!("{}: {}", channel_id, e));
                     }
                 }
-                self.initialized_channels.insert(channel_id.clone(), true);
                 continue;
             }
 
             // Normal poll: fetch messages after the last seen timestamp
-            let mut url = format!(
-                "{}/conversations.history?channel={}&limit=100",
-                SLACK_API, channel_id
-            );
-            if let Some(last_ts) = self.last_timestamps.get(channel_id) {
-                url.push_str(&format!("&oldest={}", last_ts));
-            }
-
-            let resp = match self.http.get(&url, &[("Authorization", &auth)]) {
-                Ok(r) => r,
+            let oldest = self.last_timestamps.get(channel_id).cloned();
+            let latest = self.history_bounds.get(channel_id).cloned();
+            let fetch = |cursor: Option<&str>| -> Result<(Vec<JsonValue>, Option<String>), String> {
+                let mut url = format!(
+                    "{}/conversations.history?channel={}&limit={}",
+                    SLACK_API, channel_id, HISTORY_PAGE
+                );
+                if let Some(last_ts) = &oldest {
+                    url.push_str(&format!("&oldest={}", last_ts));
+                }
+                if let Some(bound) = &latest {
+                    url.push_str(&format!("&latest={}", bound));
+                }
+                if let Some(cursor) = cursor {
+                    url.push_str(&format!("&cursor={}", encode_cursor(cursor)));
+                }
+                let resp = self
+                    .http
+                    .get(&url, &[("Authorization", &auth)])
+                    .map_err(|e| e.to_string())?;
+                let body = resp.body_string().map_err(|e| e.to_string())?;
+                let json_val = json::parse(&body).map_err(|e| e.to_string())?;
+                history_page(&json_val)
+            };
+            let messages = match history_since(fetch) {
+                Ok(History::Complete(messages)) => {
+                    // Anything after the bound is read on the next poll
+                    self.history_bounds.remove(channel_id);
+                    messages
+                }
+                Ok(History::Truncated { oldest_read }) => {
+                    eprintln!(
+                        "sentinel: slack channel {} has over {} new messages; reading the oldest first",
+                        channel_id,
+                        MAX_HISTORY_PAGES * HISTORY_PAGE
+                    );
+                    self.history_bounds.insert(channel_id.clone(), oldest_read);
+                    continue;
+                }
                 Err(e) => {
                     eprintln!("sentinel: slack poll error for {}: {}", channel_id, e);
+                    errors.push(format!("{}: {}", channel_id, e));
                     continue;
                 }
             };
 
-            let body = match resp.body_string() {
-                Ok(b) => b,
-                Err(_) => continue,
-            };
-            let json_val = match json::parse(&body) {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-
-            if !json_val
-                .get("ok")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false)
-            {
-                let error = json_val
-                    .get("error")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("unknown");
-                eprintln!(
-                    "sentinel: slack history error for {}: {}",
-                    channel_id, error
-                );
-                continue;
-            }
-
-            let empty = Vec::new();
-            let messages = json_val
-                .get("messages")
-                .and_then(|v| v.as_array())
-                .unwrap_or(&empty);
-
-            // Slack returns messages newest-first; reverse to process in chronological order
-            let mut msgs_vec: Vec<_> = messages.iter().collect();
-            msgs_vec.reverse();
-
-            for msg in msgs_vec {
+            for msg in &messages {
                 // Only process regular messages
-                let msg_type = msg.get("type").and_then(|v| v.as_str()).unwrap_or("");
+                let msg_type = msg.get_str("type").unwrap_or("");
                 if msg_type != "message" {
                     continue;
                 }
@@ -187,12 +299,12 @@ impl Connector for SlackConnector {
                     continue;
                 }
 
-                let user_id = msg.get("user").and_then(|v| v.as_str()).unwrap_or("");
+                let user_id = msg.get_str("user").unwrap_or("");
                 if user_id.is_empty() || user_id == self.bot_user_id {
                     continue;
                 }
 
-                let ts = match msg.get("ts").and_then(|v| v.as_str()) {
+                let ts = match msg.get_str("ts") {
                     Some(ts) => ts,
                     None => continue,
                 };
@@ -204,7 +316,7 @@ impl Connector for SlackConnector {
                     }
                 }
 
-                let text = msg.get("text").and_then(|v| v.as_str()).unwrap_or("");
+                let text = msg.get_str("text").unwrap_or("");
                 if text.is_empty() {
                     self.last_timestamps
                         .insert(channel_id.clone(), ts.to_string());
@@ -214,16 +326,25 @@ impl Connector for SlackConnector {
                 self.last_timestamps
                     .insert(channel_id.clone(), ts.to_string());
 
+                let (text, addressed) = addressed_text(
+                    text,
+                    channel_id,
+                    msg.get_str("parent_user_id"),
+                    &self.bot_user_id,
+                );
                 all_messages.push(IncomingMessage {
                     channel_id: channel_id.clone(),
+                    message_id: ts.to_string(),
                     user_id: user_id.to_string(),
                     username: None, // Slack doesn't include username in history
-                    text: text.to_string(),
+                    text,
+                    addressed,
+                    images: Vec::new(),
                 });
             }
         }
 
-        Ok(all_messages)
+        channel_poll_result(all_messages, errors, self.channel_ids.len())
     }
 
     fn send_message(&self, channel_id: &str, text: &str) -> Result<(), ConnectorError> {
@@ -238,7 +359,7 @@ impl Connector for SlackConnector {
             let resp =
                 self.http
                     .post_json(&url, &body.to_json_string(), &[("Authorization", &auth)])?;
-            let body_str = resp.body_string().map_err(|e| ConnectorError::Http(e))?;
+            let body_str = resp.body_string().map_err(ConnectorError::Http)?;
             let json_val =
                 json::parse(&body_str).map_err(|e| ConnectorError::Json(e.to_string()))?;
             if !json_val
@@ -246,10 +367,7 @@ impl Connector for SlackConnector {
                 .and_then(|v| v.as_bool())
                 .unwrap_or(false)
             {
-                let error = json_val
-                    .get("error")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("unknown");
+                let error = json_val.get_str("error").unwrap_or("unknown");
                 return Err(ConnectorError::Api(format!(
                     "Slack send error: {}",
                     error
@@ -273,7 +391,7 @@ impl Connector for SlackConnector {
         let resp =
             self.http
                 .post_json(&url, &body.to_json_string(), &[("Authorization", &auth)])?;
-        let body_str = resp.body_string().map_err(|e| ConnectorError::Http(e))?;
+        let body_str = resp.body_string().map_err(ConnectorError::Http)?;
         let json_val =
             json::parse(&body_str).map_err(|e| ConnectorError::Json(e.to_string()))?;
 
@@ -282,10 +400,7 @@ impl Connector for SlackConnector {
             .and_then(|v| v.as_bool())
             .unwrap_or(false)
         {
-            let error = json_val
-                .get("error")
-                .and_then(|v| v.as_str())
-                .unwrap_or("unknown");
+            let error = json_val.get_str("error").unwrap_or("unknown");
             return Err(ConnectorError::Api(format!(
                 "Slack send error: {}",
                 error
@@ -294,8 +409,7 @@ impl Connector for SlackConnector {
 
         // Slack uses the message timestamp as its ID
         let ts = json_val
-            .get("ts")
-            .and_then(|v| v.as_str())
+            .get_str("ts")
             .ok_or_else(|| ConnectorError::Api("missing ts in response".into()))?;
         Ok(ts.to_string())
     }
@@ -316,7 +430,7 @@ impl Connector for SlackConnector {
         let resp =
             self.http
                 .post_json(&url, &body.to_json_string(), &[("Authorization", &auth)])?;
-        let body_str = resp.body_string().map_err(|e| ConnectorError::Http(e))?;
+        let body_str = resp.body_string().map_err(ConnectorError::Http)?;
         let json_val =
             json::parse(&body_str).map_err(|e| ConnectorError::Json(e.to_string()))?;
 
@@ -325,10 +439,7 @@ impl Connector for SlackConnector {
             .and_then(|v| v.as_bool())
             .unwrap_or(false)
         {
-            let error = json_val
-                .get("error")
-                .and_then(|v| v.as_str())
-                .unwrap_or("unknown");
+            let error = json_val.get_str("error").unwrap_or("unknown");
             // "message_not_modified" is not a real error
             if error != "message_not_modified" {
                 return Err(ConnectorError::Api(format!(
@@ -343,4 +454,171 @@ impl Connector for SlackConnector {
     fn platform_name(&self) -> &'static str {
         "slack"
     }
+
+    // No send_typing: the Web API only shows status inside assistant
+    // threads (assistant.threads.setStatus), which channel messages aren't.
+
+    fn ack_received(&self, channel_id: &str, message_id: &str) -> Result<(), ConnectorError> {
+        self.react("reactions.add", channel_id, message_id, "eyes")
+    }
+
+    fn ack_done(
+        &self,
+        channel_id: &str,
+        message_id: &str,
+        success: bool,
+    ) -> Result<(), ConnectorError> {
+        self.react("reactions.remove", channel_id, message_id, "eyes")?;
+        let name = if success { "white_check_mark" } else { "x" };
+        self.react("reactions.add", channel_id, message_id, name)
+    }
+}
+
+/// A message's text with the bot's `<@BOTID>` mention removed, and whether
+/// it is meant for the bot: it mentions the bot, replies in one of its
+/// threads, or was sent in a direct message ("D…" channel).
+fn addressed_text(
+    text: &str,
+    channel_id: &str,
+    parent_user_id: Option<&str>,
+    bot_user_id: &str,
+) -> (String, bool) {
+    let (text, mentioned) = strip_mention(text, &format!("<@{}>", bot_user_id));
+    let addressed = mentioned || channel_id.starts_with('D') || parent_user_id == Some(bot_user_id);
+    (text, addressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::http::EgressPolicy;
+    use std::rc::Rc;
+
+    fn history(ts: &[&str], next_cursor: &str) -> JsonValue {
+        let messages: Vec<String> = ts
+            .iter()
+            .map(|ts| format!(r#"{{"type":"message","user":"U1","text":"hi","ts":"{}"}}"#, ts))
+            .collect();
+        json::parse(&format!(
+            r#"{{"ok":true,"messages":[{}],"has_more":{},"response_metadata":{{"next_cursor":"{}"}}}}"#,
+            messages.join(","),
+            !next_cursor.is_empty(),
+            next_cursor
+        ))
+        .unwrap()
+    }
+
+    fn timestamps(messages: &[JsonValue]) -> Vec<&str> {
+        messages.iter().map(|m| m.get_str("ts").unwrap()).collect()
+    }
+
+    fn complete(history: History) -> Vec<JsonValue> {
+        match history {
+            History::Complete(messages) => messages,
+            other => panic!("expected the whole history, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_history_since_pages_back_and_sorts_oldest_first() {
+        let mut requested = Vec::new();
+        let messages = complete(
+            history_since(|cursor| {
+                requested.push(cursor.map(str::to_string));
+                match cursor {
+                    // Newest first, as Slack sends them
+                    None => history_page(&history(&["1700000009.000300", "1700000009.000200"], "bmV4dA==")),
+                    Some("bmV4dA==") => history_page(&history(&["1700000008.000900", "999999999.000100"], "")),
+                    Some(c) => panic!("unexpected cursor {}", c),
+                }
+            })
+            .unwrap(),
+        );
+        assert_eq!(requested, [None, Some("bmV4dA==".to_string())]);
+        assert_eq!(
+            timestamps(&messages),
+            ["999999999.000100", "1700000008.000900", "1700000009.000200", "1700000009.000300"]
+        );
+        assert_eq!(encode_cursor("bmV4/dA+=="), "bmV4%2FdA%2B%3D%3D");
+    }
+
+    #[test]
+    fn test_history_since_is_capped_and_fails_whole() {
+        let mut calls = 0;
+        let result = history_since(|_| {
+            calls += 1;
+            let ts = format!("1700000000.{:06}", 100 - calls);
+            history_page(&history(&[ts.as_str()], "more"))
+        })
+        .unwrap();
+        assert_eq!(calls, MAX_HISTORY_PAGES);
+        // Nothing is handed on; the next read stops short of the oldest seen
+        match result {
+            History::Truncated { oldest_read } => {
+                assert_eq!(oldest_read, format!("1700000000.{:06}", 100 - MAX_HISTORY_PAGES))
+            }
+            other => panic!("expected a truncated history, got {:?}", other),
+        }
+
+        // A failed page leaves nothing to advance the watermark past
+        let mut first = true;
+        let result = history_since(|_| {
+            if std::mem::take(&mut first) {
+                history_page(&history(&["1700000001.000000"], "more"))
+            } else {
+                history_page(&json::parse(r#"{"ok":false,"error":"ratelimited"}"#).unwrap())
+            }
+        });
+        assert_eq!(result.unwrap_err(), "history error: ratelimited");
+    }
+
+    #[test]
+    fn test_latest_ts() {
+        assert_eq!(
+            latest_ts(&history(&["1700000009.000300"], "")).unwrap().as_deref(),
+            Some("1700000009.000300")
+        );
+        assert_eq!(latest_ts(&history(&[], "")).unwrap(), None);
+        // A failed lookup is an error, not an empty channel
+        let denied = json::parse(r#"{"ok":false,"error":"not_in_channel"}"#).unwrap();
+        assert_eq!(latest_ts(&denied).unwrap_err(), "history error: not_in_channel");
+    }
+
+    #[test]
+    fn test_poll_fails_when_every_channel_fails() {
+        // Every request is refused, as with a token that works for no channel
+        let policy = EgressPolicy::new(vec!["allowed.invalid".into()], Rc::new(|_: &str| {}));
+        let mut conn = SlackConnector {
+            http: HttpClient::new().unwrap().with_egress_policy(policy),
+            token: "t".into(),
+            channel_ids: vec!["C1".into(), "C2".into()],
+            bot_user_id: "UBOT".into(),
+            last_timestamps: HashMap::new(),
+            initialized_channels: HashMap::new(),
+            history_bounds: HashMap::new(),
+        };
+        let err = conn.poll_messages(0).err().unwrap().to_string();
+        assert!(err.contains("all 2 channels failed"), "{}", err);
+        assert!(conn.initialized_channels.is_empty());
+
+        conn.initialized_channels.insert("C1".into(), true);
+        conn.initialized_channels.insert("C2".into(), true);
+        assert!(conn.poll_messages(0).is_err());
+    }
+
+    #[test]
+    fn test_addressed_text() {
+        let (text, addressed) = addressed_text("<@UBOT> what's the load?", "C1", None, "UBOT");
+        assert_eq!(text, "what's the load?");
+        assert!(addressed);
+
+        // Mentions of other users are left alone
+        let (text, addressed) = addressed_text("ping <@U2>", "C1", None, "UBOT");
+        assert_eq!(text, "ping <@U2>");
+        assert!(!addressed);
+
+        assert!(addressed_text("hello", "D024BE91L", None, "UBOT").1);
+        assert!(addressed_text("and then?", "C1", Some("UBOT"), "UBOT").1);
+        assert!(!addressed_text("and then?", "C1", Some("U2"), "UBOT").1);
+    }
 }
@@ -1,15 +1,36 @@
-use crate::messaging::{Connector, ConnectorError, IncomingMessage};
+use crate::log;
+use crate::messaging::webhook::{self, WebhookListener};
+use crate::messaging::{
+    self, is_supported_image, Connector, ConnectorError, ImageAttachment, ImageRef,
+    IncomingMessage, MAX_IMAGE_BYTES,
+};
 use crate::net::http::{HttpClient, HttpError};
-use crate::net::json::{self, json_obj, JsonValue};
+use crate::net::json::{self, json_arr, json_obj, JsonValue};
+
+use std::net::TcpListener;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
 
 // ── Types ───────────────────────────────────────────────────────────────────
 
 pub struct TelegramMessage {
     pub update_id: i64,
     pub chat_id: i64,
+    pub message_id: i64,
     pub from_id: i64,
     pub from_username: Option<String>,
     pub text: String,
+    /// A private chat, a mention of the bot, or a reply to it.
+    pub addressed: bool,
+    /// file_id and media type of an attached image the model can be shown.
+    pub image: Option<(String, String)>,
+}
+
+/// The bot's own account, from getMe.
+pub struct BotIdentity {
+    pub id: i64,
+    pub username: String,
 }
 
 #[derive(Debug)]
@@ -17,6 +38,10 @@ pub enum TelegramError {
     Http(HttpError),
     Json(String),
     Api(String),
+    /// 429 Too Many Requests; retry after this many seconds.
+    RateLimited { retry_after: u64 },
+    /// The webhook listener could not start or has stopped.
+    Webhook(String),
 }
 
 impl std::fmt::Display for TelegramError {
@@ -25,6 +50,10 @@ impl std::fmt::Display for TelegramError {
             TelegramError::Http(e) => write!(f, "Telegram HTTP error: {}", e),
             TelegramError::Json(s) => write!(f, "Telegram JSON error: {}", s),
             TelegramError::Api(s) => write!(f, "Telegram API error: {}", s),
+            TelegramError::RateLimited { retry_after } => {
+                write!(f, "Telegram rate limited, retry after {}s", retry_after)
+            }
+            TelegramError::Webhook(s) => write!(f, "Telegram webhook error: {}", s),
         }
     }
 }
@@ -38,22 +67,189 @@ impl From<HttpError> for TelegramError {
 // ── Client ──────────────────────────────────────────────────────────────────
 
 const TELEGRAM_MSG_LIMIT: usize = 4096;
+/// Retries of a send or edit after a 429 before giving up.
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+/// Longest single wait honored from `retry_after`.
+const MAX_RETRY_AFTER_SECS: u64 = 60;
+/// Header Telegram sends the webhook's `secret_token` in.
+const WEBHOOK_SECRET_HEADER: &str = "X-Telegram-Bot-Api-Secret-Token";
+
+/// Telegram's rich-text modes for `parse_mode`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParseMode {
+    MarkdownV2,
+    Html,
+}
+
+impl ParseMode {
+    /// Parse a config value ("MarkdownV2" or "HTML", case-insensitive).
+    pub fn from_name(name: &str) -> Option<ParseMode> {
+        match name.to_ascii_lowercase().as_str() {
+            "markdownv2" => Some(ParseMode::MarkdownV2),
+            "html" => Some(ParseMode::Html),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            ParseMode::MarkdownV2 => "MarkdownV2",
+            ParseMode::Html => "HTML",
+        }
+    }
+}
 
 pub struct TelegramClient {
     http: HttpClient,
+    /// For image downloads, which are held to `MAX_IMAGE_BYTES`.
+    files: HttpClient,
     base_url: String,
+    /// Where getFile's file paths are downloaded from.
+    file_url: String,
     last_offset: i64,
+    /// Rich-text mode for sent and edited messages; `None` sends plain text.
+    parse_mode: Option<ParseMode>,
+    /// Updates pushed to the webhook listener, when in webhook mode.
+    webhook: Option<Receiver<JsonValue>>,
+    /// Who the bot is, for spotting mentions; see `identify`.
+    bot: Option<BotIdentity>,
 }
 
 impl TelegramClient {
     pub fn new(http: HttpClient, token: &str) -> Self {
         TelegramClient {
+            files: messaging::image_client(&http),
             http,
             base_url: format!("https://api.telegram.org/bot{}", token),
+            file_url: format!("https://api.telegram.org/file/bot{}", token),
             last_offset: 0,
+            parse_mode: None,
+            webhook: None,
+            bot: None,
         }
     }
 
+    /// Fetch an image sent to the bot: getFile for its path, then the file.
+    fn download_image(&self, file_id: &str, media_type: &str) -> Result<ImageAttachment, ConnectorError> {
+        let url = format!("{}/getFile?file_id={}", self.base_url, file_id);
+        let resp = self.http.get(&url, &[])?;
+        let body = resp.body_string()?;
+        let json = json::parse(&body).map_err(|e| ConnectorError::Json(e.to_string()))?;
+        check_ok(&json)?;
+        let path = json
+            .get_path("result.file_path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ConnectorError::Json("getFile: missing file_path".into()))?;
+        messaging::download_image(&self.files, &format!("{}/{}", self.file_url, path), media_type)
+    }
+
+    /// Look up the bot's own account with getMe, so messages that mention
+    /// or reply to it can be told apart from the rest of a group's chatter.
+    pub fn identify(&mut self) -> Result<(), TelegramError> {
+        let url = format!("{}/getMe", self.base_url);
+        let resp = self.http.get(&url, &[])?;
+        let body_str = resp.body_string().map_err(TelegramError::Http)?;
+        let json = json::parse(&body_str).map_err(|e| TelegramError::Json(e.to_string()))?;
+        check_ok(&json)?;
+        let id = json.get_path("result.id").and_then(|v| v.as_i64());
+        let username = json.get_path("result.username").and_then(|v| v.as_str());
+        match (id, username) {
+            (Some(id), Some(username)) => {
+                self.bot = Some(BotIdentity {
+                    id,
+                    username: username.to_string(),
+                });
+                Ok(())
+            }
+            _ => Err(TelegramError::Json("getMe result missing id or username".into())),
+        }
+    }
+
+    /// Render the model's Markdown with Telegram's `mode`.
+    pub fn with_parse_mode(mut self, mode: Option<ParseMode>) -> Self {
+        self.parse_mode = mode;
+        self
+    }
+
+    /// Switch to webhook mode: register `url` (the public address a reverse
+    /// proxy forwards to `path`) with Telegram, then serve updates POSTed
+    /// to `path` on `listener`. Each registration gets a fresh secret, so
+    /// only Telegram can deliver updates. The listener is bound by the
+    /// caller, before the sandbox, and only borrowed here, so a failed
+    /// registration can be retried on it.
+    pub fn start_webhook(&mut self, listener: &TcpListener, path: &str, url: &str) -> Result<(), TelegramError> {
+        let secret = webhook::random_secret()
+            .map_err(|e| TelegramError::Webhook(format!("no randomness for secret: {}", e)))?;
+        self.set_webhook(url, &secret)?;
+        let cannot_serve = |e: std::io::Error| TelegramError::Webhook(format!("cannot serve webhook: {}", e));
+        let addr = listener.local_addr().map_err(cannot_serve)?;
+        let updates = WebhookListener {
+            path: path.to_string(),
+            secret_header: WEBHOOK_SECRET_HEADER,
+            secret,
+        }
+        .start(listener.try_clone().map_err(cannot_serve)?)
+        .map_err(cannot_serve)?;
+        eprintln!("sentinel: telegram webhook listening on {}{}", addr, path);
+        self.webhook = Some(updates);
+        Ok(())
+    }
+
+    fn set_webhook(&self, url: &str, secret: &str) -> Result<(), TelegramError> {
+        let body = json_obj()
+            .field_str("url", url)
+            .field_str("secret_token", secret)
+            .field("allowed_updates", json_arr().push_str("message").build())
+            // One delivery at a time keeps update_ids in order
+            .field_i64("max_connections", 1)
+            .build();
+        let api_url = format!("{}/setWebhook", self.base_url);
+        let resp = self.http.post_json(&api_url, &body.to_json_string(), &[])?;
+        let body_str = resp.body_string().map_err(TelegramError::Http)?;
+        let json = json::parse(&body_str).map_err(|e| TelegramError::Json(e.to_string()))?;
+        check_ok(&json)
+    }
+
+    /// Remove any registered webhook. getUpdates is refused while one is
+    /// set, so long polling calls this on startup.
+    pub fn delete_webhook(&self) -> Result<(), TelegramError> {
+        let url = format!("{}/deleteWebhook", self.base_url);
+        let resp = self.http.post_json(&url, "{}", &[])?;
+        let body_str = resp.body_string().map_err(TelegramError::Http)?;
+        let json = json::parse(&body_str).map_err(|e| TelegramError::Json(e.to_string()))?;
+        check_ok(&json)
+    }
+
+    /// Wait up to `timeout` seconds for webhook updates, then take every
+    /// one already queued.
+    fn webhook_updates(&mut self, timeout: u32) -> Result<Vec<TelegramMessage>, TelegramError> {
+        let rx = match &self.webhook {
+            Some(rx) => rx,
+            None => return Ok(Vec::new()),
+        };
+        let mut raw = match rx.recv_timeout(Duration::from_secs(u64::from(timeout))) {
+            Ok(update) => vec![update],
+            Err(RecvTimeoutError::Timeout) => return Ok(Vec::new()),
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(TelegramError::Webhook("listener stopped".into()))
+            }
+        };
+        raw.extend(rx.try_iter());
+
+        let mut messages = Vec::new();
+        for update in &raw {
+            if let Some(msg) = parse_update(update, self.bot.as_ref()) {
+                // Telegram redelivers updates it thinks failed; skip repeats
+                if msg.update_id < self.last_offset {
+                    continue;
+                }
+                self.last_offset = msg.update_id + 1;
+                messages.push(msg);
+            }
+        }
+        Ok(messages)
+    }
+
     pub fn get_updates(&mut self, timeout: u32) -> Result<Vec<TelegramMessage>, TelegramError> {
         let url = format!(
             "{}/getUpdates?offset={}&timeout={}&allowed_updates=[\"message\"]",
@@ -63,17 +259,10 @@ impl TelegramClient {
         let resp = self.http.get(&url, &[])?;
         let body = resp
             .body_string()
-            .map_err(|e| TelegramError::Http(e))?;
+            .map_err(TelegramError::Http)?;
         let json = json::parse(&body).map_err(|e| TelegramError::Json(e.to_string()))?;
 
-        let ok = json.get("ok").and_then(|v| v.as_bool()).unwrap_or(false);
-        if !ok {
-            let desc = json
-                .get("description")
-                .and_then(|v| v.as_str())
-                .unwrap_or("unknown error");
-            return Err(TelegramError::Api(desc.to_string()));
-        }
+        check_ok(&json)?;
 
         let results = json
             .get("result")
@@ -82,7 +271,7 @@ impl TelegramClient {
 
         let mut messages = Vec::new();
         for update in results {
-            if let Some(msg) = parse_update(update) {
+            if let Some(msg) = parse_update(update, self.bot.as_ref()) {
                 if msg.update_id >= self.last_offset {
                     self.last_offset = msg.update_id + 1;
                 }
@@ -106,79 +295,426 @@ impl TelegramClient {
         self.send_message_raw(chat_id, text)
     }
 
-    /// Edit an existing message's text.
+    /// Edit an existing message's text, retrying after rate limits.
     pub fn edit_message_text(
         &self,
         chat_id: i64,
         message_id: i64,
         text: &str,
     ) -> Result<(), TelegramError> {
-        let body = json_obj()
+        retry_rate_limited(MAX_RATE_LIMIT_RETRIES, &mut thread::sleep, || {
+            self.edit_message_text_once(chat_id, message_id, text)
+        })
+    }
+
+    fn edit_message_text_once(
+        &self,
+        chat_id: i64,
+        message_id: i64,
+        text: &str,
+    ) -> Result<(), TelegramError> {
+        with_plain_fallback(self.formatted(text), text, |text, mode| {
+            self.post_edit(chat_id, message_id, text, mode)
+        })
+    }
+
+    fn post_edit(
+        &self,
+        chat_id: i64,
+        message_id: i64,
+        text: &str,
+        parse_mode: Option<ParseMode>,
+    ) -> Result<(), TelegramError> {
+        let mut body = json_obj()
             .field_i64("chat_id", chat_id)
             .field_i64("message_id", message_id)
-            .field_str("text", text)
-            .build();
+            .field_str("text", text);
+        if let Some(mode) = parse_mode {
+            body = body.field_str("parse_mode", mode.as_str());
+        }
+        let body = body.build();
 
         let url = format!("{}/editMessageText", self.base_url);
         let resp = self.http.post_json(&url, &body.to_json_string(), &[])?;
 
-        let body_str = resp.body_string().map_err(|e| TelegramError::Http(e))?;
+        let body_str = resp.body_string().map_err(TelegramError::Http)?;
         let json = json::parse(&body_str).map_err(|e| TelegramError::Json(e.to_string()))?;
 
-        let ok = json.get("ok").and_then(|v| v.as_bool()).unwrap_or(false);
-        if !ok {
-            let desc = json
-                .get("description")
-                .and_then(|v| v.as_str())
-                .unwrap_or("unknown error");
+        match check_ok(&json) {
             // "message is not modified" is not a real error — just means text didn't change
-            if !desc.contains("message is not modified") {
-                return Err(TelegramError::Api(desc.to_string()));
-            }
+            Err(TelegramError::Api(desc)) if desc.contains("message is not modified") => Ok(()),
+            other => other,
         }
-        Ok(())
     }
 
-    fn send_message_raw(&self, chat_id: i64, text: &str) -> Result<i64, TelegramError> {
+    /// Set the bot's reaction on a message, or clear it with `None`.
+    /// Only Telegram's fixed set of reaction emoji is accepted.
+    pub fn set_reaction(
+        &self,
+        chat_id: i64,
+        message_id: i64,
+        emoji: Option<&str>,
+    ) -> Result<(), TelegramError> {
+        let reaction = match emoji {
+            Some(e) => json_arr().push(
+                json_obj()
+                    .field_str("type", "emoji")
+                    .field_str("emoji", e)
+                    .build(),
+            ),
+            None => json_arr(),
+        };
         let body = json_obj()
             .field_i64("chat_id", chat_id)
-            .field_str("text", text)
+            .field_i64("message_id", message_id)
+            .field("reaction", reaction.build())
             .build();
 
-        let url = format!("{}/sendMessage", self.base_url);
+        let url = format!("{}/setMessageReaction", self.base_url);
         let resp = self.http.post_json(&url, &body.to_json_string(), &[])?;
 
-        let body_str = resp.body_string().map_err(|e| TelegramError::Http(e))?;
+        let body_str = resp.body_string().map_err(TelegramError::Http)?;
         let json = json::parse(&body_str).map_err(|e| TelegramError::Json(e.to_string()))?;
+        check_ok(&json)
+    }
+
+    /// Show "typing…" in a chat for up to five seconds.
+    pub fn send_chat_action(&self, chat_id: i64, action: &str) -> Result<(), TelegramError> {
+        let body = json_obj()
+            .field_i64("chat_id", chat_id)
+            .field_str("action", action)
+            .build();
+
+        let url = format!("{}/sendChatAction", self.base_url);
+        let resp = self.http.post_json(&url, &body.to_json_string(), &[])?;
+
+        let body_str = resp.body_string().map_err(TelegramError::Http)?;
+        let json = json::parse(&body_str).map_err(|e| TelegramError::Json(e.to_string()))?;
+        check_ok(&json)
+    }
+
+    /// Send one message, retrying after rate limits.
+    fn send_message_raw(&self, chat_id: i64, text: &str) -> Result<i64, TelegramError> {
+        retry_rate_limited(MAX_RATE_LIMIT_RETRIES, &mut thread::sleep, || {
+            self.send_message_once(chat_id, text)
+        })
+    }
+
+    fn send_message_once(&self, chat_id: i64, text: &str) -> Result<i64, TelegramError> {
+        with_plain_fallback(self.formatted(text), text, |text, mode| {
+            self.post_send(chat_id, text, mode)
+        })
+    }
 
-        let ok = json.get("ok").and_then(|v| v.as_bool()).unwrap_or(false);
-        if !ok {
-            let desc = json
-                .get("description")
-                .and_then(|v| v.as_str())
-                .unwrap_or("unknown error");
-            return Err(TelegramError::Api(desc.to_string()));
+    fn post_send(
+        &self,
+        chat_id: i64,
+        text: &str,
+        parse_mode: Option<ParseMode>,
+    ) -> Result<i64, TelegramError> {
+        let mut body = json_obj()
+            .field_i64("chat_id", chat_id)
+            .field_str("text", text);
+        if let Some(mode) = parse_mode {
+            body = body.field_str("parse_mode", mode.as_str());
         }
+        let body = body.build();
+
+        let url = format!("{}/sendMessage", self.base_url);
+        let resp = self.http.post_json(&url, &body.to_json_string(), &[])?;
+
+        let body_str = resp.body_string().map_err(TelegramError::Http)?;
+        let json = json::parse(&body_str).map_err(|e| TelegramError::Json(e.to_string()))?;
+
+        check_ok(&json)?;
 
         // Extract message_id from result
         let message_id = json
-            .get("result")
-            .and_then(|r| r.get("message_id"))
+            .get_path("result.message_id")
             .and_then(|v| v.as_i64())
             .unwrap_or(0);
 
         Ok(message_id)
     }
+
+    /// `text` rendered in the configured parse mode, or `None` to send it
+    /// plain (no mode set, or the rendering didn't validate).
+    fn formatted(&self, text: &str) -> Option<(String, ParseMode)> {
+        let mode = self.parse_mode?;
+        let rendered = match mode {
+            ParseMode::MarkdownV2 => to_markdown_v2(text),
+            ParseMode::Html => to_html(text),
+        };
+        let valid = rendered.len() <= TELEGRAM_MSG_LIMIT
+            && (mode != ParseMode::MarkdownV2 || is_valid_markdown_v2(&rendered));
+        if valid {
+            Some((rendered, mode))
+        } else {
+            None
+        }
+    }
+}
+
+/// Send `formatted` if there is one, falling back to the plain `text` when
+/// Telegram can't parse the entities, so a message is never lost to markup.
+fn with_plain_fallback<T>(
+    formatted: Option<(String, ParseMode)>,
+    text: &str,
+    mut post: impl FnMut(&str, Option<ParseMode>) -> Result<T, TelegramError>,
+) -> Result<T, TelegramError> {
+    let (rendered, mode) = match formatted {
+        Some(f) => f,
+        None => return post(text, None),
+    };
+    match post(&rendered, Some(mode)) {
+        Err(TelegramError::Api(desc)) if desc.contains("can't parse entities") => {
+            eprintln!("sentinel: telegram rejected {} ({}), sending plain text", mode.as_str(), desc);
+            post(text, None)
+        }
+        other => other,
+    }
+}
+
+// ── Formatting ──────────────────────────────────────────────────────────────
+//
+// The model writes ordinary Markdown. Code blocks, inline code and **bold**
+// are carried over; everything else is escaped so it shows literally. An
+// unterminated code block (common mid-stream) runs to the end of the text.
+
+/// Characters MarkdownV2 reserves outside code entities.
+const MARKDOWN_V2_RESERVED: &str = "_*[]()~`>#+-=|{}.!\\";
+
+/// Escape text for MarkdownV2 so every reserved character is literal.
+pub fn escape_markdown_v2(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if MARKDOWN_V2_RESERVED.contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Escape the inside of a MarkdownV2 code entity, where only ` and \ are
+/// special.
+fn escape_markdown_v2_code(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '`' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// A piece of the model's Markdown.
+enum Span<'a> {
+    Plain(&'a str),
+    Bold(&'a str),
+    Code(&'a str),
+    Block { lang: &'a str, code: &'a str },
+}
+
+/// Split Markdown into plain text, bold, inline code and fenced blocks.
+/// Unmatched markers stay in the plain text.
+fn spans(text: &str) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+    let mut plain_start = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let (span, len) = if let Some(after) = rest.strip_prefix("```") {
+            let (header, body) = after.split_once('\n').unwrap_or((after, ""));
+            let lang = header.trim();
+            let (code, consumed) = match body.find("```") {
+                Some(end) => (&body[..end], end + 3),
+                None => (body, body.len()),
+            };
+            let header_len = after.len() - body.len();
+            (Some(Span::Block { lang, code: code.strip_suffix('\n').unwrap_or(code) }), 3 + header_len + consumed)
+        } else if let Some(after) = rest.strip_prefix("**") {
+            match after.find("**") {
+                Some(end) if end > 0 => (Some(Span::Bold(&after[..end])), end + 4),
+                _ => (None, 2),
+            }
+        } else if let Some(after) = rest.strip_prefix('`') {
+            match after.find(['`', '\n']) {
+                Some(end) if end > 0 && after[end..].starts_with('`') => {
+                    (Some(Span::Code(&after[..end])), end + 2)
+                }
+                _ => (None, 1),
+            }
+        } else {
+            (None, rest.chars().next().map_or(1, char::len_utf8))
+        };
+        if let Some(span) = span {
+            if plain_start < i {
+                spans.push(Span::Plain(&text[plain_start..i]));
+            }
+            spans.push(span);
+            plain_start = i + len;
+        }
+        i += len;
+    }
+    if plain_start < text.len() {
+        spans.push(Span::Plain(&text[plain_start..]));
+    }
+    spans
+}
+
+/// Render the model's Markdown as Telegram MarkdownV2.
+pub fn to_markdown_v2(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + text.len() / 8);
+    for span in spans(text) {
+        match span {
+            Span::Plain(s) => out.push_str(&escape_markdown_v2(s)),
+            Span::Bold(s) => {
+                out.push('*');
+                out.push_str(&escape_markdown_v2(s));
+                out.push('*');
+            }
+            Span::Code(s) => {
+                out.push('`');
+                out.push_str(&escape_markdown_v2_code(s));
+                out.push('`');
+            }
+            Span::Block { lang, code } => {
+                out.push_str("```");
+                out.push_str(&escape_markdown_v2_code(lang));
+                out.push('\n');
+                out.push_str(&escape_markdown_v2_code(code));
+                out.push_str("\n```");
+            }
+        }
+    }
+    out
+}
+
+/// Render the model's Markdown as Telegram HTML.
+pub fn to_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + text.len() / 8);
+    for span in spans(text) {
+        match span {
+            Span::Plain(s) => out.push_str(&escape_html(s)),
+            Span::Bold(s) => out.push_str(&format!("<b>{}</b>", escape_html(s))),
+            Span::Code(s) => out.push_str(&format!("<code>{}</code>", escape_html(s))),
+            Span::Block { lang, code } if !lang.is_empty() => out.push_str(&format!(
+                "<pre><code class=\"language-{}\">{}</code></pre>",
+                escape_html(lang).replace('"', ""),
+                escape_html(code)
+            )),
+            Span::Block { code, .. } => out.push_str(&format!("<pre>{}</pre>", escape_html(code))),
+        }
+    }
+    out
+}
+
+/// Check rendered MarkdownV2 before sending: every reserved character
+/// outside an entity is escaped and every entity is closed.
+fn is_valid_markdown_v2(text: &str) -> bool {
+    let mut chars = text.chars().peekable();
+    let mut in_bold = false;
+    let mut in_code = false;
+    let mut in_block = false;
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if chars.next().is_none() {
+                return false;
+            }
+            continue;
+        }
+        if c == '`' {
+            if in_block || (!in_code && chars.peek() == Some(&'`')) {
+                // A fence is three backticks; a lone one inside a block is
+                // always escaped, so anything else is malformed
+                if chars.next() != Some('`') || chars.next() != Some('`') {
+                    return false;
+                }
+                in_block = !in_block;
+            } else {
+                in_code = !in_code;
+            }
+            continue;
+        }
+        if in_code || in_block {
+            continue;
+        }
+        if c == '*' {
+            in_bold = !in_bold;
+        } else if MARKDOWN_V2_RESERVED.contains(c) {
+            return false;
+        }
+    }
+    !in_bold && !in_code && !in_block
 }
 
 // ── Helpers ─────────────────────────────────────────────────────────────────
 
-fn parse_update(update: &JsonValue) -> Option<TelegramMessage> {
+/// Map a Telegram response envelope to an error if `ok` is false. A 429
+/// carries the wait in `parameters.retry_after`.
+fn check_ok(json: &JsonValue) -> Result<(), TelegramError> {
+    if json.get("ok").and_then(|v| v.as_bool()).unwrap_or(false) {
+        return Ok(());
+    }
+    if json.get("error_code").and_then(|v| v.as_i64()) == Some(429) {
+        let retry_after = json
+            .get_path("parameters.retry_after")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(1);
+        return Err(TelegramError::RateLimited { retry_after });
+    }
+    let desc = json.get_str("description").unwrap_or("unknown error");
+    Err(TelegramError::Api(desc.to_string()))
+}
+
+/// Run `op`, sleeping and retrying up to `max_retries` times while Telegram
+/// answers 429.
+fn retry_rate_limited<T>(
+    max_retries: u32,
+    sleep: &mut dyn FnMut(Duration),
+    mut op: impl FnMut() -> Result<T, TelegramError>,
+) -> Result<T, TelegramError> {
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(TelegramError::RateLimited { retry_after }) if attempt < max_retries => {
+                let wait = retry_after.min(MAX_RETRY_AFTER_SECS);
+                log::warn(
+                    &format!("telegram rate limited, retrying in {}s", wait),
+                    &[("source", &"telegram"), ("wait_secs", &wait)],
+                );
+                sleep(Duration::from_secs(wait));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn parse_update(update: &JsonValue, bot: Option<&BotIdentity>) -> Option<TelegramMessage> {
     let update_id = update.get("update_id")?.as_i64()?;
     let message = update.get("message")?;
-    let text = message.get("text")?.as_str()?;
+    let text = message_text(message)?;
+    let (text, mentioned) = match bot {
+        Some(bot) => strip_bot_mentions(message, text, bot),
+        None => (text, false),
+    };
     let chat = message.get("chat")?;
+    let private = chat.get_str("type") == Some("private");
+    let replies_to_bot = bot.is_some_and(|bot| {
+        message
+            .get_path("reply_to_message.from.id")
+            .and_then(|v| v.as_i64())
+            == Some(bot.id)
+    });
     let chat_id = chat.get("id")?.as_i64()?;
+    let message_id = message.get("message_id")?.as_i64()?;
 
     let from = message.get("from");
     let from_id = from.and_then(|f| f.get("id")).and_then(|v| v.as_i64()).unwrap_or(0);
@@ -190,43 +726,169 @@ fn parse_update(update: &JsonValue) -> Option<TelegramMessage> {
     Some(TelegramMessage {
         update_id,
         chat_id,
+        message_id,
         from_id,
         from_username,
-        text: text.to_string(),
+        text,
+        addressed: private || mentioned || replies_to_bot,
+        image: image_file(message),
     })
 }
 
-fn split_message(text: &str) -> Vec<String> {
-    if text.len() <= TELEGRAM_MSG_LIMIT {
-        return vec![text.to_string()];
+/// The image in a message, if there is one known to be small enough to
+/// download: the largest size of a photo (Telegram sends them as JPEG), or
+/// a document sent as an image file.
+fn image_file(message: &JsonValue) -> Option<(String, String)> {
+    let fits = |file: &&JsonValue| {
+        file.get("file_size").and_then(|v| v.as_u64()).is_some_and(|size| size <= MAX_IMAGE_BYTES)
+    };
+    if let Some(sizes) = message.get("photo").and_then(|v| v.as_array()) {
+        // Sizes come smallest first
+        let photo = sizes.iter().rev().find(fits)?;
+        return Some((photo.get_str("file_id")?.to_string(), "image/jpeg".to_string()));
     }
+    let doc = message.get("document").filter(fits)?;
+    let media_type = doc.get_str("mime_type").filter(|t| is_supported_image(t))?;
+    Some((doc.get_str("file_id")?.to_string(), media_type.to_string()))
+}
 
-    let mut chunks = Vec::new();
-    let mut remaining = text;
-
-    while !remaining.is_empty() {
-        if remaining.len() <= TELEGRAM_MSG_LIMIT {
-            chunks.push(remaining.to_string());
-            break;
+/// `text` without the entities that address the bot, and whether there
+/// were any: "@botname" mentions, mentions of its account by name, and
+/// commands sent as "/help@botname" (left as "/help"). A bare command
+/// counts too, since Telegram only delivers those to bots meant to see them.
+/// A caption's entities count from the start of the caption, which ends
+/// `text` after the note `message_text` puts in front of it.
+fn strip_bot_mentions(message: &JsonValue, text: String, bot: &BotIdentity) -> (String, bool) {
+    let (entities, base) = match message.get_str("caption") {
+        Some(caption) if message.get("text").is_none() => {
+            (message.get("caption_entities"), text.len() - caption.len())
         }
-
-        // Try to split at a newline before the limit
-        let split_at = remaining[..TELEGRAM_MSG_LIMIT]
-            .rfind('\n')
-            .unwrap_or(TELEGRAM_MSG_LIMIT);
-
-        let (chunk, rest) = remaining.split_at(split_at);
-        chunks.push(chunk.to_string());
-
-        // Skip the newline we split at
-        remaining = if rest.starts_with('\n') {
-            &rest[1..]
+        _ => (message.get("entities"), 0),
+    };
+    let entities = match entities.and_then(|v| v.as_array()) {
+        Some(entities) => entities,
+        None => return (text, false),
+    };
+    let at_username = format!("@{}", bot.username);
+    let mut cut = Vec::new();
+    let mut addressed = false;
+    for entity in entities {
+        let range = match (
+            entity.get("offset").and_then(|v| v.as_u64()),
+            entity.get("length").and_then(|v| v.as_u64()),
+        ) {
+            (Some(offset), Some(length)) => {
+                utf16_range(&text[base..], offset as usize, length as usize)
+                    .map(|r| r.start + base..r.end + base)
+            }
+            _ => None,
+        };
+        let range = match range {
+            Some(range) => range,
+            None => continue,
+        };
+        let entity_text = &text[range.clone()];
+        // A mention takes the space after it along
+        let with_space = if text[range.end..].starts_with(' ') {
+            range.start..range.end + 1
         } else {
-            rest
+            range.clone()
         };
+        match entity.get_str("type") {
+            Some("mention") if entity_text.eq_ignore_ascii_case(&at_username) => {
+                addressed = true;
+                cut.push(with_space);
+            }
+            Some("text_mention")
+                if entity.get_path("user.id").and_then(|v| v.as_i64()) == Some(bot.id) =>
+            {
+                addressed = true;
+                cut.push(with_space);
+            }
+            Some("bot_command") => match entity_text.split_once('@') {
+                Some((_, name)) if name.eq_ignore_ascii_case(&bot.username) => {
+                    addressed = true;
+                    cut.push(range.start + entity_text.len() - at_username.len()..range.end);
+                }
+                Some(_) => {}
+                None => addressed = true,
+            },
+            _ => {}
+        }
+    }
+    if cut.is_empty() {
+        return (text, addressed);
+    }
+    let mut text = text;
+    cut.sort_by_key(|r| std::cmp::Reverse(r.start));
+    for range in cut {
+        text.replace_range(range, "");
     }
+    (text.trim().to_string(), addressed)
+}
+
+/// The byte range of `text` that an entity's UTF-16 `offset` and `length`
+/// cover, or `None` if they don't fall on character boundaries within it.
+fn utf16_range(text: &str, offset: usize, length: usize) -> Option<std::ops::Range<usize>> {
+    let mut units = 0;
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        if units == offset {
+            start = Some(i);
+        }
+        if units == offset + length {
+            return Some(start?..i);
+        }
+        units += c.len_utf16();
+    }
+    if units == offset {
+        start = Some(text.len());
+    }
+    if units == offset + length {
+        return Some(start?..text.len());
+    }
+    None
+}
+
+/// A message's text, or for an attachment a note of what it is followed by
+/// its caption (e.g. "[photo] look at this"), so the agent can at least
+/// acknowledge it. `None` for kinds the agent can't do anything with.
+fn message_text(message: &JsonValue) -> Option<String> {
+    if let Some(text) = message.get_str("text") {
+        return Some(text.to_string());
+    }
+    let note = if message.get("photo").is_some() {
+        "[photo]".to_string()
+    } else if let Some(doc) = message.get("document") {
+        let details: Vec<&str> = ["file_name", "mime_type"]
+            .iter()
+            .filter_map(|k| doc.get_str(k))
+            .collect();
+        if details.is_empty() {
+            "[document]".to_string()
+        } else {
+            format!("[document: {}]", details.join(", "))
+        }
+    } else if let Some(voice) = message.get("voice") {
+        match voice.get("duration").and_then(|v| v.as_i64()) {
+            Some(secs) => format!("[voice, {}s]", secs),
+            None => "[voice]".to_string(),
+        }
+    } else if message.get("video").is_some() {
+        "[video]".to_string()
+    } else if message.get("audio").is_some() {
+        "[audio]".to_string()
+    } else {
+        return None;
+    };
+    Some(match message.get_str("caption") {
+        Some(caption) => format!("{} {}", note, caption),
+        None => note,
+    })
+}
 
-    chunks
+fn split_message(text: &str) -> Vec<String> {
+    messaging::split_message(text, TELEGRAM_MSG_LIMIT)
 }
 
 // ── Connector trait impl ─────────────────────────────────────────────────────
@@ -237,24 +899,42 @@ impl From<TelegramError> for ConnectorError {
             TelegramError::Http(h) => ConnectorError::Http(h),
             TelegramError::Json(s) => ConnectorError::Json(s),
             TelegramError::Api(s) => ConnectorError::Api(s),
+            e @ (TelegramError::RateLimited { .. } | TelegramError::Webhook(_)) => {
+                ConnectorError::Api(e.to_string())
+            }
         }
     }
 }
 
 impl Connector for TelegramClient {
     fn poll_messages(&mut self, timeout_secs: u32) -> Result<Vec<IncomingMessage>, ConnectorError> {
-        let msgs = self.get_updates(timeout_secs)?;
+        let msgs = if self.webhook.is_some() {
+            self.webhook_updates(timeout_secs)?
+        } else {
+            self.get_updates(timeout_secs)?
+        };
         Ok(msgs
             .into_iter()
             .map(|m| IncomingMessage {
                 channel_id: m.chat_id.to_string(),
+                message_id: m.message_id.to_string(),
                 user_id: m.from_id.to_string(),
                 username: m.from_username,
                 text: m.text,
+                addressed: m.addressed,
+                images: m
+                    .image
+                    .map(|(source, media_type)| ImageRef { source, media_type })
+                    .into_iter()
+                    .collect(),
             })
             .collect())
     }
 
+    fn fetch_image(&self, image: &ImageRef) -> Result<ImageAttachment, ConnectorError> {
+        self.download_image(&image.source, &image.media_type)
+    }
+
     fn send_message(&self, channel_id: &str, text: &str) -> Result<(), ConnectorError> {
         let chat_id: i64 = channel_id
             .parse()
@@ -290,6 +970,43 @@ impl Connector for TelegramClient {
     fn platform_name(&self) -> &'static str {
         "telegram"
     }
+
+    fn send_typing(&self, channel_id: &str) -> Result<(), ConnectorError> {
+        let chat_id: i64 = channel_id
+            .parse()
+            .map_err(|_| ConnectorError::Api("invalid chat_id".into()))?;
+        self.send_chat_action(chat_id, "typing")?;
+        Ok(())
+    }
+
+    fn ack_received(&self, channel_id: &str, message_id: &str) -> Result<(), ConnectorError> {
+        let (chat_id, msg_id) = parse_ids(channel_id, message_id)?;
+        self.set_reaction(chat_id, msg_id, Some("👀"))?;
+        Ok(())
+    }
+
+    // ✅ and ❌ are not in Telegram's reaction set; 👍 and 👎 are
+    fn ack_done(
+        &self,
+        channel_id: &str,
+        message_id: &str,
+        success: bool,
+    ) -> Result<(), ConnectorError> {
+        let (chat_id, msg_id) = parse_ids(channel_id, message_id)?;
+        let emoji = if success { "👍" } else { "👎" };
+        self.set_reaction(chat_id, msg_id, Some(emoji))?;
+        Ok(())
+    }
+}
+
+fn parse_ids(channel_id: &str, message_id: &str) -> Result<(i64, i64), ConnectorError> {
+    let chat_id: i64 = channel_id
+        .parse()
+        .map_err(|_| ConnectorError::Api("invalid chat_id".into()))?;
+    let msg_id: i64 = message_id
+        .parse()
+        .map_err(|_| ConnectorError::Api("invalid message_id".into()))?;
+    Ok((chat_id, msg_id))
 }
 
 #[cfg(test)]
@@ -310,4 +1027,266 @@ mod tests {
         assert_eq!(chunks[0].len(), TELEGRAM_MSG_LIMIT);
         assert_eq!(chunks[1].len(), 5000 - TELEGRAM_MSG_LIMIT);
     }
+
+    #[test]
+    fn test_parse_429_retry_after() {
+        let body = r#"{"ok":false,"error_code":429,"description":"Too Many Requests: retry after 7","parameters":{"retry_after":7}}"#;
+        let json = json::parse(body).unwrap();
+        assert!(matches!(
+            check_ok(&json),
+            Err(TelegramError::RateLimited { retry_after: 7 })
+        ));
+
+        let json = json::parse(r#"{"ok":false,"error_code":400,"description":"Bad Request"}"#).unwrap();
+        assert!(matches!(check_ok(&json), Err(TelegramError::Api(d)) if d == "Bad Request"));
+        assert!(check_ok(&json::parse(r#"{"ok":true}"#).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_send_retries_after_delay() {
+        let mut slept = Vec::new();
+        let mut calls = 0;
+        let result = retry_rate_limited(3, &mut |d| slept.push(d), || {
+            calls += 1;
+            if calls < 3 {
+                Err(TelegramError::RateLimited { retry_after: 2 })
+            } else {
+                Ok(42)
+            }
+        });
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(slept, vec![Duration::from_secs(2); 2]);
+    }
+
+    #[test]
+    fn test_retry_is_bounded_and_capped() {
+        let mut slept = Vec::new();
+        let mut calls = 0;
+        let result: Result<(), _> = retry_rate_limited(2, &mut |d| slept.push(d), || {
+            calls += 1;
+            Err(TelegramError::RateLimited { retry_after: 3600 })
+        });
+        assert!(matches!(result, Err(TelegramError::RateLimited { .. })));
+        assert_eq!(calls, 3);
+        assert_eq!(slept, vec![Duration::from_secs(MAX_RETRY_AFTER_SECS); 2]);
+    }
+
+    #[test]
+    fn test_escape_markdown_v2_reserved_characters() {
+        assert_eq!(escape_markdown_v2("snake_case"), "snake\\_case");
+        assert_eq!(escape_markdown_v2("[link](url)"), "\\[link\\]\\(url\\)");
+        assert_eq!(escape_markdown_v2("a `tick`"), "a \\`tick\\`");
+        assert_eq!(escape_markdown_v2("1.5 > 1!"), "1\\.5 \\> 1\\!");
+        assert_eq!(escape_markdown_v2("C:\\dir"), "C:\\\\dir");
+    }
+
+    #[test]
+    fn test_markdown_v2_keeps_code_and_bold() {
+        assert_eq!(
+            to_markdown_v2("Run `ls -la` on **my_dir** [now]."),
+            "Run `ls -la` on *my\\_dir* \\[now\\]\\."
+        );
+        // Only ` and \ are escaped inside code
+        assert_eq!(
+            to_markdown_v2("```rust\nlet v = [a_b];\n```"),
+            "```rust\nlet v = [a_b];\n```"
+        );
+        assert_eq!(to_markdown_v2("`a\\b`"), "`a\\\\b`");
+    }
+
+    #[test]
+    fn test_markdown_v2_unmatched_markers_are_literal() {
+        assert_eq!(to_markdown_v2("2 ** 3 and a ` tick"), "2 \\*\\* 3 and a \\` tick");
+        // Mid-stream an open code block runs to the end and is closed
+        assert_eq!(to_markdown_v2("```\nfn a()"), "```\nfn a()\n```");
+        for text in ["**", "`", "```", "a ``` b", "**x** `y", "_[*]_"] {
+            assert!(is_valid_markdown_v2(&to_markdown_v2(text)), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_validation_rejects_unescaped_markdown_v2() {
+        assert!(is_valid_markdown_v2("*bold* `code_x` plain\\_x"));
+        assert!(!is_valid_markdown_v2("plain_x"));
+        assert!(!is_valid_markdown_v2("*unclosed"));
+        assert!(!is_valid_markdown_v2("`unclosed"));
+        assert!(!is_valid_markdown_v2("trailing\\"));
+    }
+
+    #[test]
+    fn test_html_escapes_and_formats() {
+        assert_eq!(
+            to_html("**a<b** & `x>y`\n```py\nif a<b: pass\n```"),
+            "<b>a&lt;b</b> &amp; <code>x&gt;y</code>\n\
+             <pre><code class=\"language-py\">if a&lt;b: pass</code></pre>"
+        );
+    }
+
+    #[test]
+    fn test_unparseable_entities_fall_back_to_plain() {
+        let mut sent = Vec::new();
+        let result = with_plain_fallback(
+            Some(("*bad".to_string(), ParseMode::MarkdownV2)),
+            "bad",
+            |text, mode| {
+                sent.push((text.to_string(), mode));
+                match mode {
+                    Some(_) => Err(TelegramError::Api(
+                        "Bad Request: can't parse entities: unclosed".into(),
+                    )),
+                    None => Ok(1),
+                }
+            },
+        );
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(sent, vec![("*bad".to_string(), Some(ParseMode::MarkdownV2)), ("bad".to_string(), None)]);
+    }
+
+    fn update_text(message: &str) -> Option<String> {
+        let update = json::parse(&format!(
+            r#"{{"update_id":1,"message":{{"message_id":2,"chat":{{"id":3}},{}}}}}"#,
+            message
+        ))
+        .unwrap();
+        parse_update(&update, None).map(|m| m.text)
+    }
+
+    #[test]
+    fn test_parse_update_attachments() {
+        assert_eq!(update_text(r#""text":"hi""#).as_deref(), Some("hi"));
+        assert_eq!(
+            update_text(r#""photo":[{"file_id":"a"}],"caption":"look at this""#).as_deref(),
+            Some("[photo] look at this")
+        );
+        assert_eq!(
+            update_text(r#""document":{"file_name":"report.pdf","mime_type":"application/pdf"},"caption":"Q3""#)
+                .as_deref(),
+            Some("[document: report.pdf, application/pdf] Q3")
+        );
+        assert_eq!(update_text(r#""document":{"file_id":"d"}"#).as_deref(), Some("[document]"));
+        assert_eq!(update_text(r#""voice":{"duration":5}"#).as_deref(), Some("[voice, 5s]"));
+        // Nothing the agent could respond to
+        assert_eq!(update_text(r#""sticker":{"emoji":"👍"}"#), None);
+    }
+
+    #[test]
+    fn test_image_file() {
+        let image = |message: &str| {
+            let update = json::parse(&format!(
+                r#"{{"update_id":1,"message":{{"message_id":2,"chat":{{"id":3}},{}}}}}"#,
+                message
+            ))
+            .unwrap();
+            parse_update(&update, None).unwrap().image
+        };
+        // The largest photo size that is small enough
+        assert_eq!(
+            image(r#""photo":[{"file_id":"s","file_size":900},{"file_id":"m","file_size":90000},{"file_id":"l","file_size":9000000}]"#),
+            Some(("m".into(), "image/jpeg".into()))
+        );
+        assert_eq!(
+            image(r#""document":{"file_id":"d","mime_type":"image/png","file_size":1000}"#),
+            Some(("d".into(), "image/png".into()))
+        );
+        assert_eq!(image(r#""document":{"file_id":"d","mime_type":"application/pdf"}"#), None);
+        assert_eq!(image(r#""photo":[{"file_id":"l","file_size":9000000}]"#), None);
+        // A size that isn't given isn't taken to be small
+        assert_eq!(
+            image(r#""photo":[{"file_id":"s","file_size":900},{"file_id":"u"}]"#),
+            Some(("s".into(), "image/jpeg".into()))
+        );
+        assert_eq!(image(r#""document":{"file_id":"d","mime_type":"image/png"}"#), None);
+        assert_eq!(image(r#""text":"hi""#), None);
+    }
+
+    fn addressed_update(chat_type: &str, message: &str) -> (String, bool) {
+        let update = json::parse(&format!(
+            r#"{{"update_id":1,"message":{{"message_id":2,"chat":{{"id":3,"type":"{}"}},{}}}}}"#,
+            chat_type, message
+        ))
+        .unwrap();
+        let bot = BotIdentity {
+            id: 99,
+            username: "SentinelBot".into(),
+        };
+        let m = parse_update(&update, Some(&bot)).unwrap();
+        (m.text, m.addressed)
+    }
+
+    #[test]
+    fn test_parse_update_mentions() {
+        // "@sentinelbot" matches the username case-insensitively
+        assert_eq!(
+            addressed_update(
+                "group",
+                r#""text":"@sentinelbot disk usage?","entities":[{"type":"mention","offset":0,"length":12}]"#
+            ),
+            ("disk usage?".to_string(), true)
+        );
+        // Offsets count UTF-16 units, so the emoji is two
+        assert_eq!(
+            addressed_update(
+                "group",
+                r#""text":"👋 @SentinelBot hi","entities":[{"type":"mention","offset":3,"length":12}]"#
+            ),
+            ("👋 hi".to_string(), true)
+        );
+        assert_eq!(
+            addressed_update(
+                "group",
+                r#""text":"Sentinel, hi","entities":[{"type":"text_mention","offset":0,"length":8,"user":{"id":99}}]"#
+            ),
+            (", hi".to_string(), true)
+        );
+        assert_eq!(
+            addressed_update(
+                "supergroup",
+                r#""text":"/help@SentinelBot","entities":[{"type":"bot_command","offset":0,"length":17}]"#
+            ),
+            ("/help".to_string(), true)
+        );
+        // Another bot's command or user's mention is left alone
+        assert_eq!(
+            addressed_update(
+                "group",
+                r#""text":"/help@OtherBot","entities":[{"type":"bot_command","offset":0,"length":14}]"#
+            ),
+            ("/help@OtherBot".to_string(), false)
+        );
+        assert_eq!(
+            addressed_update(
+                "group",
+                r#""text":"@alice lunch?","entities":[{"type":"mention","offset":0,"length":6}]"#
+            ),
+            ("@alice lunch?".to_string(), false)
+        );
+        // A photo's caption has entities of its own
+        assert_eq!(
+            addressed_update(
+                "group",
+                r#""photo":[],"caption":"@SentinelBot what is this?","caption_entities":[{"type":"mention","offset":0,"length":12}]"#
+            ),
+            ("[photo] what is this?".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn test_parse_update_replies_and_private_chats() {
+        assert_eq!(
+            addressed_update("group", r#""text":"and then?","reply_to_message":{"from":{"id":99}}"#),
+            ("and then?".to_string(), true)
+        );
+        assert!(!addressed_update("group", r#""text":"and then?","reply_to_message":{"from":{"id":5}}"#).1);
+        assert!(!addressed_update("group", r#""text":"hello""#).1);
+        assert!(addressed_update("private", r#""text":"hello""#).1);
+    }
+
+    #[test]
+    fn test_utf16_range() {
+        assert_eq!(utf16_range("ab", 0, 2), Some(0..2));
+        assert_eq!(utf16_range("👋 @x", 3, 2), Some(5..7));
+        // Inside a surrogate pair, or past the end
+        assert_eq!(utf16_range("👋", 1, 1), None);
+        assert_eq!(utf16_range("ab", 1, 5), None);
+    }
 }
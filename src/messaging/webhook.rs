@@ -0,0 +1,313 @@
+//! A minimal HTTP listener for platforms that push updates by webhook.
+//!
+//! It speaks just enough plain HTTP/1.1 to accept a JSON POST and answer
+//! it; TLS is left to a reverse proxy in front of it. Every request must
+//! carry a shared secret in a header, so updates can't be forged by anyone
+//! who finds the port.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::net::json::{self, JsonValue};
+
+/// Largest request body accepted; Telegram updates are a few KiB.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+/// Largest request line or header block accepted.
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+/// How long a client may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Connections served at once; more are dropped until one finishes.
+const MAX_CONNECTIONS: usize = 16;
+
+/// Where to listen and what a valid request looks like.
+pub struct WebhookListener {
+    /// Request path updates are POSTed to, e.g. "/telegram".
+    pub path: String,
+    /// Header that must carry `secret`, compared case-insensitively by name.
+    pub secret_header: &'static str,
+    pub secret: String,
+}
+
+impl WebhookListener {
+    /// Serve `listener` on a background thread, each connection on a thread
+    /// of its own so a slow client can't hold up the rest. Each accepted
+    /// JSON body is sent to the returned receiver; serving stops once it is
+    /// dropped.
+    pub fn start(self, listener: TcpListener) -> io::Result<Receiver<JsonValue>> {
+        let (tx, rx) = mpsc::channel();
+        let this = Arc::new(self);
+        let active = Arc::new(AtomicUsize::new(0));
+        let closed = Arc::new(AtomicBool::new(false));
+        thread::Builder::new()
+            .name("webhook".into())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if closed.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    let stream = match stream {
+                        Ok(s) => s,
+                        Err(e) => {
+                            eprintln!("sentinel: webhook accept error: {}", e);
+                            continue;
+                        }
+                    };
+                    if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                        active.fetch_sub(1, Ordering::SeqCst);
+                        eprintln!("sentinel: webhook busy, dropping a connection");
+                        continue;
+                    }
+                    let (this, tx, done, closed) =
+                        (Arc::clone(&this), tx.clone(), Arc::clone(&active), Arc::clone(&closed));
+                    let spawned = thread::Builder::new().name("webhook-conn".into()).spawn(move || {
+                        if !this.handle(stream, &tx) {
+                            closed.store(true, Ordering::SeqCst);
+                        }
+                        done.fetch_sub(1, Ordering::SeqCst);
+                    });
+                    if let Err(e) = spawned {
+                        eprintln!("sentinel: webhook cannot spawn a handler: {}", e);
+                        active.fetch_sub(1, Ordering::SeqCst);
+                    }
+                }
+            })?;
+        Ok(rx)
+    }
+
+    /// Serve one connection. Returns false once the receiver is gone.
+    fn handle(&self, mut stream: TcpStream, tx: &Sender<JsonValue>) -> bool {
+        let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+        let _ = stream.set_write_timeout(Some(REQUEST_TIMEOUT));
+        let (status, update) = match read_request(&mut stream) {
+            Ok(req) => self.route(req),
+            Err(e) => {
+                eprintln!("sentinel: webhook bad request: {}", e);
+                ("400 Bad Request", None)
+            }
+        };
+        let mut alive = true;
+        if let Some(update) = update {
+            alive = tx.send(update).is_ok();
+        }
+        let status = if alive { status } else { "503 Service Unavailable" };
+        let _ = write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            status
+        );
+        alive
+    }
+
+    /// Check a parsed request and pick the response status.
+    fn route(&self, req: Request) -> (&'static str, Option<JsonValue>) {
+        if req.path != self.path {
+            return ("404 Not Found", None);
+        }
+        if req.method != "POST" {
+            return ("405 Method Not Allowed", None);
+        }
+        let secret = req
+            .headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(self.secret_header))
+            .map(|(_, v)| v.as_str());
+        if !secret.is_some_and(|s| constant_time_eq(s.as_bytes(), self.secret.as_bytes())) {
+            eprintln!("sentinel: webhook request with missing or wrong secret");
+            return ("401 Unauthorized", None);
+        }
+        let body = match std::str::from_utf8(&req.body) {
+            Ok(b) => b,
+            Err(_) => return ("400 Bad Request", None),
+        };
+        match json::parse(body) {
+            Ok(v) => ("200 OK", Some(v)),
+            Err(e) => {
+                eprintln!("sentinel: webhook body is not JSON: {}", e);
+                ("400 Bad Request", None)
+            }
+        }
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+/// Read one HTTP/1.1 request with a Content-Length body.
+fn read_request(stream: impl Read) -> Result<Request, String> {
+    let mut reader = BufReader::new(stream.take((MAX_HEADER_BYTES + MAX_BODY_BYTES) as u64));
+    let mut header_bytes = 0;
+    let mut next_line = |reader: &mut BufReader<_>| -> Result<String, String> {
+        let mut line = String::new();
+        let n = reader.read_line(&mut line).map_err(|e| e.to_string())?;
+        header_bytes += n;
+        if n == 0 {
+            return Err("connection closed mid-request".into());
+        }
+        if header_bytes > MAX_HEADER_BYTES {
+            return Err("headers too large".into());
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    };
+
+    let request_line = next_line(&mut reader)?;
+    let mut parts = request_line.split(' ');
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().ok_or("malformed request line")?;
+    // The query string (if any) is not part of the route
+    let path = target.split('?').next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let line = next_line(&mut reader)?;
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':').ok_or("malformed header")?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    let length = match headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+    {
+        Some((_, v)) => v.parse::<usize>().map_err(|_| "bad Content-Length")?,
+        None => 0,
+    };
+    if length > MAX_BODY_BYTES {
+        return Err(format!("body of {} bytes exceeds limit", length));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+
+    Ok(Request { method, path, headers, body })
+}
+
+/// Compare secrets without exiting at the first differing byte.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// A random hex token for the webhook secret.
+pub fn random_secret() -> io::Result<String> {
+    let mut bytes = [0u8; 24];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    fn start() -> (SocketAddr, Receiver<JsonValue>) {
+        let listener = WebhookListener {
+            path: "/hook".into(),
+            secret_header: "X-Secret",
+            secret: "s3cret".into(),
+        };
+        let bound = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = bound.local_addr().unwrap();
+        (addr, listener.start(bound).unwrap())
+    }
+
+    /// Send a raw request and return the response's status line.
+    fn send(addr: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    fn post(path: &str, secret: Option<&str>, body: &str) -> String {
+        let secret = secret.map(|s| format!("x-secret: {}\r\n", s)).unwrap_or_default();
+        format!(
+            "POST {} HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: {}\r\n\r\n{}",
+            path,
+            secret,
+            body.len(),
+            body
+        )
+    }
+
+    #[test]
+    fn test_valid_update_is_queued() {
+        let (addr, rx) = start();
+        let status = send(addr, &post("/hook?x=1", Some("s3cret"), r#"{"update_id":7}"#));
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let update = rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(update.get("update_id").and_then(|v| v.as_i64()), Some(7));
+    }
+
+    #[test]
+    fn test_rejected_requests_are_not_queued() {
+        let (addr, rx) = start();
+        let body = r#"{"update_id":1}"#;
+        assert_eq!(send(addr, &post("/hook", None, body)), "HTTP/1.1 401 Unauthorized");
+        assert_eq!(send(addr, &post("/hook", Some("guess"), body)), "HTTP/1.1 401 Unauthorized");
+        assert_eq!(send(addr, &post("/other", Some("s3cret"), body)), "HTTP/1.1 404 Not Found");
+        assert_eq!(send(addr, &post("/hook", Some("s3cret"), "not json")), "HTTP/1.1 400 Bad Request");
+        assert_eq!(
+            send(addr, "GET /hook HTTP/1.1\r\nx-secret: s3cret\r\n\r\n"),
+            "HTTP/1.1 405 Method Not Allowed"
+        );
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_stalled_connection_does_not_block_others() {
+        let (addr, rx) = start();
+        // Connected but never sends anything
+        let _stalled = TcpStream::connect(addr).unwrap();
+        let started = std::time::Instant::now();
+        let status = send(addr, &post("/hook", Some("s3cret"), r#"{"update_id":8}"#));
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(started.elapsed() < REQUEST_TIMEOUT);
+        assert!(rx.recv_timeout(Duration::from_secs(2)).is_ok());
+    }
+
+    #[test]
+    fn test_serves_under_agent_filter() {
+        let bound = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = bound.local_addr().unwrap();
+        let rx = thread::spawn(move || {
+            use crate::security::linux::{apply_sandbox, SeccompMode};
+            let agent = apply_sandbox(&[], &[], true, SeccompMode::Enforce, false);
+            assert!(agent.seccomp_applied && agent.seccomp_error.is_none());
+            let listener = WebhookListener {
+                path: "/hook".into(),
+                secret_header: "X-Secret",
+                secret: "s3cret".into(),
+            };
+            listener.start(bound).unwrap()
+        })
+        .join()
+        .unwrap();
+        let status = send(addr, &post("/hook", Some("s3cret"), r#"{"update_id":9}"#));
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(rx.recv_timeout(Duration::from_secs(2)).is_ok());
+    }
+
+    #[test]
+    fn test_oversized_body_rejected() {
+        let request = format!("POST /hook HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY_BYTES + 1);
+        assert!(read_request(request.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_random_secret_is_hex() {
+        let a = random_secret().unwrap();
+        assert_eq!(a.len(), 48);
+        assert!(a.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_ne!(a, random_secret().unwrap());
+    }
+}
@@ -0,0 +1,551 @@
+// ── gzip (RFC 1952) over DEFLATE (RFC 1951) ─────────────────────────────────
+//
+// Compression uses LZ77 with a hash chain and the fixed Huffman code, which
+// gets most of the gain on JSON without building per-block code tables.
+
+const WINDOW_SIZE: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+    131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Wrap `data` in a gzip member.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    out.extend_from_slice(&deflate(data));
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+/// Decode a gzip member, verifying its CRC and length. Decoding stops with
+/// an error once the output would exceed `max_out` bytes.
+pub fn decompress(data: &[u8], max_out: usize) -> Result<Vec<u8>, String> {
+    if data.len() < 18 || data[0] != 0x1f || data[1] != 0x8b {
+        return Err("not a gzip stream".into());
+    }
+    if data[2] != 8 {
+        return Err("unsupported gzip compression method".into());
+    }
+    let flags = data[3];
+    let mut pos = 10;
+    if flags & 0x04 != 0 {
+        // FEXTRA
+        let xlen = *data.get(pos).ok_or("truncated gzip header")? as usize
+            | (*data.get(pos + 1).ok_or("truncated gzip header")? as usize) << 8;
+        pos += 2 + xlen;
+    }
+    for flag in [0x08, 0x10] {
+        // FNAME, FCOMMENT: zero-terminated
+        if flags & flag != 0 {
+            let end = data
+                .get(pos..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0))
+                .ok_or("truncated gzip header")?;
+            pos += end + 1;
+        }
+    }
+    if flags & 0x02 != 0 {
+        // FHCRC
+        pos += 2;
+    }
+    if pos + 8 > data.len() {
+        return Err("truncated gzip stream".into());
+    }
+
+    let (out, used) = inflate(&data[pos..], max_out)?;
+    let trailer = data
+        .get(pos + used..pos + used + 8)
+        .ok_or("missing gzip trailer")?;
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+    if crc != crc32(&out) {
+        return Err("gzip CRC mismatch".into());
+    }
+    if size != out.len() as u32 {
+        return Err("gzip length mismatch".into());
+    }
+    Ok(out)
+}
+
+/// Decode an HTTP `deflate` body: a zlib stream (RFC 1950), or a raw DEFLATE
+/// stream from servers that omit the zlib wrapper. Output is capped at
+/// `max_out` bytes, as for `decompress`.
+pub fn decompress_zlib(data: &[u8], max_out: usize) -> Result<Vec<u8>, String> {
+    let has_header = data.len() >= 6
+        && data[0] & 0x0f == 8
+        && ((data[0] as u16) << 8 | data[1] as u16).is_multiple_of(31);
+    if !has_header {
+        return inflate(data, max_out).map(|(out, _)| out);
+    }
+    if data[1] & 0x20 != 0 {
+        return Err("zlib preset dictionary not supported".into());
+    }
+    let (out, used) = inflate(&data[2..], max_out)?;
+    let trailer = data.get(2 + used..2 + used + 4).ok_or("missing zlib trailer")?;
+    if u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]) != adler32(&out) {
+        return Err("zlib checksum mismatch".into());
+    }
+    Ok(out)
+}
+
+// ── Checksums ───────────────────────────────────────────────────────────────
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &x in chunk {
+            a += x as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+// ── Compression ─────────────────────────────────────────────────────────────
+
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u32,
+    nbits: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        BitWriter {
+            out: Vec::new(),
+            acc: 0,
+            nbits: 0,
+        }
+    }
+
+    /// Write `n` bits of `value`, least significant first.
+    fn bits(&mut self, value: u32, n: u32) {
+        self.acc |= value << self.nbits;
+        self.nbits += n;
+        while self.nbits >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.nbits -= 8;
+        }
+    }
+
+    /// Write a Huffman code, which DEFLATE packs most significant bit first.
+    fn code(&mut self, code: u32, len: u32) {
+        self.bits(code.reverse_bits() >> (32 - len), len);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.nbits > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+fn write_literal(w: &mut BitWriter, sym: u32) {
+    match sym {
+        0..=143 => w.code(0x30 + sym, 8),
+        144..=255 => w.code(0x190 + sym - 144, 9),
+        256..=279 => w.code(sym - 256, 7),
+        _ => w.code(0xc0 + sym - 280, 8),
+    }
+}
+
+fn write_match(w: &mut BitWriter, len: usize, dist: usize) {
+    let li = LENGTH_BASE.iter().rposition(|&b| b as usize <= len).unwrap_or(0);
+    write_literal(w, 257 + li as u32);
+    w.bits((len - LENGTH_BASE[li] as usize) as u32, LENGTH_EXTRA[li] as u32);
+
+    let di = DIST_BASE.iter().rposition(|&b| b as usize <= dist).unwrap_or(0);
+    w.code(di as u32, 5);
+    w.bits((dist - DIST_BASE[di] as usize) as u32, DIST_EXTRA[di] as u32);
+}
+
+fn hash3(data: &[u8], i: usize) -> usize {
+    let v = (data[i] as u32) << 16 | (data[i + 1] as u32) << 8 | data[i + 2] as u32;
+    (v.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+/// Raw DEFLATE stream: one final block with the fixed Huffman code.
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut w = BitWriter::new();
+    w.bits(1, 1); // BFINAL
+    w.bits(1, 2); // BTYPE = fixed Huffman
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; data.len()];
+    let insert = |head: &mut Vec<usize>, prev: &mut Vec<usize>, i: usize| {
+        if i + MIN_MATCH <= data.len() {
+            let h = hash3(data, i);
+            prev[i] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut best_len = 0;
+        let mut best_dist = 0;
+        if i + MIN_MATCH <= data.len() {
+            let max_len = MAX_MATCH.min(data.len() - i);
+            let mut cand = head[hash3(data, i)];
+            let mut chain = 0;
+            while cand != usize::MAX && i - cand <= WINDOW_SIZE && chain < MAX_CHAIN {
+                let len = data[cand..]
+                    .iter()
+                    .zip(&data[i..i + max_len])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best_len {
+                    best_len = len;
+                    best_dist = i - cand;
+                    if len == max_len {
+                        break;
+                    }
+                }
+                cand = prev[cand];
+                chain += 1;
+            }
+        }
+
+        if best_len >= MIN_MATCH {
+            write_match(&mut w, best_len, best_dist);
+            for j in i..i + best_len {
+                insert(&mut head, &mut prev, j);
+            }
+            i += best_len;
+        } else {
+            write_literal(&mut w, data[i] as u32);
+            insert(&mut head, &mut prev, i);
+            i += 1;
+        }
+    }
+
+    write_literal(&mut w, 256);
+    w.finish()
+}
+
+// ── Decompression ───────────────────────────────────────────────────────────
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn bits(&mut self, n: u32) -> Result<u32, String> {
+        let mut v = 0;
+        for i in 0..n {
+            let byte = *self.data.get(self.pos).ok_or("truncated deflate stream")?;
+            v |= ((byte >> self.bit) as u32 & 1) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+        Ok(v)
+    }
+
+    fn align(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+    }
+}
+
+/// Canonical Huffman decoding table: code counts per length and symbols
+/// ordered by code.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &l in lengths {
+            counts[l as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for i in 1..16 {
+            offsets[i] = offsets[i - 1] + counts[i - 1];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (sym, &l) in lengths.iter().enumerate() {
+            if l != 0 {
+                symbols[offsets[l as usize] as usize] = sym as u16;
+                offsets[l as usize] += 1;
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, r: &mut BitReader) -> Result<u16, String> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for len in 1..16 {
+            code |= r.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid Huffman code".into())
+    }
+}
+
+/// Inflate a raw DEFLATE stream. Returns the output and the number of input
+/// bytes consumed. A few KB can expand to gigabytes, so output past `max_out`
+/// is refused rather than buffered.
+fn inflate(data: &[u8], max_out: usize) -> Result<(Vec<u8>, usize), String> {
+    let mut r = BitReader { data, pos: 0, bit: 0 };
+    let mut out = Vec::new();
+    loop {
+        let last = r.bits(1)? == 1;
+        match r.bits(2)? {
+            0 => {
+                r.align();
+                let hdr = data.get(r.pos..r.pos + 4).ok_or("truncated stored block")?;
+                let len = u16::from_le_bytes([hdr[0], hdr[1]]) as usize;
+                let nlen = u16::from_le_bytes([hdr[2], hdr[3]]) as usize;
+                if len != !nlen & 0xffff {
+                    return Err("stored block length mismatch".into());
+                }
+                r.pos += 4;
+                let block = data.get(r.pos..r.pos + len).ok_or("truncated stored block")?;
+                check_size(out.len() + len, max_out)?;
+                out.extend_from_slice(block);
+                r.pos += len;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let lit = Huffman::new(&lengths);
+                let dist = Huffman::new(&[5u8; 30]);
+                inflate_block(&mut r, &mut out, &lit, &dist, max_out)?;
+            }
+            2 => {
+                let (lit, dist) = read_dynamic_tables(&mut r)?;
+                inflate_block(&mut r, &mut out, &lit, &dist, max_out)?;
+            }
+            _ => return Err("invalid deflate block type".into()),
+        }
+        if last {
+            break;
+        }
+    }
+    r.align();
+    Ok((out, r.pos))
+}
+
+fn check_size(len: usize, max_out: usize) -> Result<(), String> {
+    if len > max_out {
+        return Err(format!("decompressed body is over the {}-byte limit", max_out));
+    }
+    Ok(())
+}
+
+fn read_dynamic_tables(r: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    const ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+    let nlen = r.bits(5)? as usize + 257;
+    let ndist = r.bits(5)? as usize + 1;
+    let ncode = r.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &idx in ORDER.iter().take(ncode) {
+        code_lengths[idx] = r.bits(3)? as u8;
+    }
+    let code = Huffman::new(&code_lengths);
+
+    let mut lengths = vec![0u8; nlen + ndist];
+    let mut i = 0;
+    while i < nlen + ndist {
+        let sym = code.decode(r)?;
+        let (value, repeat) = match sym {
+            0..=15 => (sym as u8, 1),
+            16 => {
+                let prev = *lengths.get(i.wrapping_sub(1)).ok_or("repeat with no previous length")?;
+                (prev, 3 + r.bits(2)? as usize)
+            }
+            17 => (0, 3 + r.bits(3)? as usize),
+            _ => (0, 11 + r.bits(7)? as usize),
+        };
+        if i + repeat > lengths.len() {
+            return Err("too many code lengths".into());
+        }
+        lengths[i..i + repeat].fill(value);
+        i += repeat;
+    }
+
+    Ok((Huffman::new(&lengths[..nlen]), Huffman::new(&lengths[nlen..])))
+}
+
+fn inflate_block(
+    r: &mut BitReader,
+    out: &mut Vec<u8>,
+    lit: &Huffman,
+    dist: &Huffman,
+    max_out: usize,
+) -> Result<(), String> {
+    loop {
+        let sym = lit.decode(r)? as usize;
+        match sym {
+            0..=255 => {
+                check_size(out.len() + 1, max_out)?;
+                out.push(sym as u8);
+            }
+            256 => return Ok(()),
+            257..=285 => {
+                let li = sym - 257;
+                let len = LENGTH_BASE[li] as usize + r.bits(LENGTH_EXTRA[li] as u32)? as usize;
+                let di = dist.decode(r)? as usize;
+                if di >= 30 {
+                    return Err("invalid distance code".into());
+                }
+                let d = DIST_BASE[di] as usize + r.bits(DIST_EXTRA[di] as u32)? as usize;
+                if d > out.len() {
+                    return Err("distance too far back".into());
+                }
+                check_size(out.len() + len, max_out)?;
+                let start = out.len() - d;
+                for k in 0..len {
+                    out.push(out[start + k]);
+                }
+            }
+            _ => return Err("invalid literal/length code".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let body = br#"{"model":"m","messages":[{"role":"user","content":"hello"},{"role":"user","content":"hello"}]}"#;
+        let gz = compress(body);
+        assert_eq!(&gz[..2], &[0x1f, 0x8b]);
+        assert!(gz.len() < body.len() + 18);
+        assert_eq!(decompress(&gz, usize::MAX).unwrap(), body.to_vec());
+    }
+
+    #[test]
+    fn test_round_trip_empty_and_long_runs() {
+        assert_eq!(decompress(&compress(b""), usize::MAX).unwrap(), b"");
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 7) as u8 + b'a').collect();
+        let gz = compress(&data);
+        assert!(gz.len() < data.len() / 20);
+        assert_eq!(decompress(&gz, usize::MAX).unwrap(), data);
+    }
+
+    #[test]
+    fn test_crc32_known_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_corrupt_trailer_rejected() {
+        let mut gz = compress(b"some data");
+        let n = gz.len();
+        gz[n - 5] ^= 0xff;
+        assert!(decompress(&gz, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_output_limit() {
+        let gz = compress(&[b'a'; 1000]);
+        assert_eq!(decompress(&gz, 1000).unwrap().len(), 1000);
+        assert!(decompress(&gz, 999).unwrap_err().contains("999-byte limit"));
+    }
+
+    #[test]
+    fn test_decompress_captured_gzip() {
+        // `gzip -9` output for a 640-byte Telegram-style JSON body; uses a
+        // dynamic Huffman block
+        let captured: &[u8] = &[
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xad, 0xcd,
+            0x41, 0x0a, 0xc3, 0x20, 0x14, 0x04, 0xd0, 0xab, 0xc8, 0xac, 0x5d, 0x24,
+            0xc6, 0x6c, 0xbc, 0x4a, 0x29, 0xc5, 0x36, 0xbf, 0x49, 0xa8, 0xd6, 0x60,
+            0xb4, 0x6d, 0x08, 0xde, 0xbd, 0x66, 0x59, 0x28, 0xb8, 0x71, 0xf5, 0x61,
+            0xfe, 0x0c, 0x6f, 0x87, 0x7b, 0x40, 0x05, 0x1f, 0x89, 0xc3, 0xd3, 0x1a,
+            0x4d, 0x80, 0x3a, 0xed, 0x88, 0xcb, 0xa0, 0x03, 0x5d, 0xe6, 0x01, 0xaa,
+            0xe1, 0xb0, 0xb4, 0xae, 0x7a, 0x24, 0xa8, 0x1d, 0xb7, 0x49, 0x87, 0xe3,
+            0x1e, 0x1f, 0x29, 0x38, 0xc2, 0xb6, 0xe4, 0x1c, 0x8b, 0x9f, 0x5f, 0x79,
+            0x80, 0x94, 0x13, 0xfa, 0xe4, 0x06, 0x26, 0x32, 0xc6, 0xb1, 0x67, 0xb4,
+            0x57, 0xf2, 0xac, 0x61, 0x77, 0xef, 0x2c, 0x0b, 0x13, 0xb1, 0x31, 0xd7,
+            0xde, 0x7a, 0x43, 0x4a, 0xfc, 0x87, 0x69, 0x2b, 0x30, 0x6d, 0x99, 0x11,
+            0x15, 0x18, 0x51, 0x66, 0xba, 0x0a, 0x4c, 0x57, 0x66, 0x64, 0x05, 0x46,
+            0x96, 0x99, 0xbe, 0x02, 0xd3, 0xff, 0x63, 0xce, 0xe9, 0x0b, 0x37, 0x41,
+            0xf0, 0x9f, 0x80, 0x02, 0x00, 0x00,
+        ];
+        let body = String::from_utf8(decompress(captured, usize::MAX).unwrap()).unwrap();
+        assert_eq!(body.len(), 640);
+        assert!(body.starts_with(r#"{"ok":true,"result":[{"update_id":0,"#));
+        assert!(body.ends_with(r#""text":"hello number 5 from the gateway"}}]}"#));
+    }
+
+    #[test]
+    fn test_decompress_captured_zlib() {
+        let captured: &[u8] = &[
+            0x78, 0x9c, 0xab, 0x56, 0xca, 0xcf, 0x56, 0xb2, 0x2a, 0x29, 0x2a, 0x4d,
+            0xd5, 0x51, 0x4a, 0x49, 0x2d, 0x4e, 0x2e, 0xca, 0x2c, 0x28, 0xc9, 0xcc,
+            0xcf, 0x53, 0xb2, 0x02, 0xf2, 0xd2, 0x72, 0x12, 0x4b, 0x52, 0x53, 0x94,
+            0x6a, 0x01, 0xeb, 0x00, 0x0c, 0xdc,
+        ];
+        assert_eq!(
+            decompress_zlib(captured, usize::MAX).unwrap(),
+            br#"{"ok":true,"description":"deflated"}"#.to_vec()
+        );
+        // Raw DEFLATE without the zlib wrapper is accepted too
+        assert_eq!(
+            decompress_zlib(&captured[2..captured.len() - 4], usize::MAX).unwrap(),
+            br#"{"ok":true,"description":"deflated"}"#.to_vec()
+        );
+    }
+}
@@ -5,6 +5,8 @@ use std::fmt;
 #[derive(Debug, Clone, PartialEq)]
 pub enum JsonNumber {
     Int(i64),
+    /// Integers above `i64::MAX`.
+    UInt(u64),
     Float(f64),
 }
 
@@ -42,6 +44,15 @@ impl JsonValue {
         }
     }
 
+    /// Walk a dotted path such as `choices.0.message.content`. Segments
+    /// index objects by key and arrays by position.
+    pub fn get_path(&self, path: &str) -> Option<&JsonValue> {
+        path.split('.').try_fold(self, |val, seg| match val {
+            JsonValue::Array(items) => items.get(seg.parse::<usize>().ok()?),
+            _ => val.get(seg),
+        })
+    }
+
     pub fn index(&self, i: usize) -> Option<&JsonValue> {
         match self {
             JsonValue::Array(items) => items.get(i),
@@ -49,6 +60,11 @@ impl JsonValue {
         }
     }
 
+    /// `get(key)` as a string, if present and a string.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(|v| v.as_str())
+    }
+
     pub fn as_str(&self) -> Option<&str> {
         match self {
             JsonValue::String(s) => Some(s),
@@ -64,10 +80,26 @@ impl JsonValue {
         }
     }
 
+    /// Non-negative integers, including those above `i64::MAX`. Unlike
+    /// `as_i64`, floats are only accepted when they hold an exact integer.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            JsonValue::Number(JsonNumber::Int(n)) => u64::try_from(*n).ok(),
+            JsonValue::Number(JsonNumber::UInt(n)) => Some(*n),
+            JsonValue::Number(JsonNumber::Float(f))
+                if f.fract() == 0.0 && (0.0..=9_007_199_254_740_992.0).contains(f) =>
+            {
+                Some(*f as u64)
+            }
+            _ => None,
+        }
+    }
+
     pub fn as_f64(&self) -> Option<f64> {
         match self {
             JsonValue::Number(JsonNumber::Float(f)) => Some(*f),
             JsonValue::Number(JsonNumber::Int(n)) => Some(*n as f64),
+            JsonValue::Number(JsonNumber::UInt(n)) => Some(*n as f64),
             _ => None,
         }
     }
@@ -106,6 +138,15 @@ impl JsonValue {
         serialize(self, &mut buf);
         buf
     }
+
+    /// Multi-line JSON for humans, nesting by `indent` spaces per level.
+    /// Not for the wire: use `to_json_string` there.
+    #[allow(dead_code)] // for the planned /debug command
+    pub fn to_json_string_pretty(&self, indent: usize) -> String {
+        let mut buf = String::new();
+        serialize_pretty(self, &mut buf, indent, 0);
+        buf
+    }
 }
 
 fn serialize(val: &JsonValue, buf: &mut String) {
@@ -116,13 +157,10 @@ fn serialize(val: &JsonValue, buf: &mut String) {
         JsonValue::Number(JsonNumber::Int(n)) => {
             buf.push_str(&n.to_string());
         }
-        JsonValue::Number(JsonNumber::Float(f)) => {
-            if f.is_infinite() || f.is_nan() {
-                buf.push_str("null");
-            } else {
-                buf.push_str(&format!("{}", f));
-            }
+        JsonValue::Number(JsonNumber::UInt(n)) => {
+            buf.push_str(&n.to_string());
         }
+        JsonValue::Number(JsonNumber::Float(f)) => format_float(*f, buf),
         JsonValue::String(s) => {
             buf.push('"');
             escape_string(s, buf);
@@ -154,6 +192,63 @@ fn serialize(val: &JsonValue, buf: &mut String) {
     }
 }
 
+fn serialize_pretty(val: &JsonValue, buf: &mut String, indent: usize, depth: usize) {
+    let pad = |buf: &mut String, depth: usize| {
+        buf.push('\n');
+        buf.extend(std::iter::repeat_n(' ', indent * depth));
+    };
+    match val {
+        JsonValue::Array(items) if !items.is_empty() => {
+            buf.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    buf.push(',');
+                }
+                pad(buf, depth + 1);
+                serialize_pretty(item, buf, indent, depth + 1);
+            }
+            pad(buf, depth);
+            buf.push(']');
+        }
+        JsonValue::Object(pairs) if !pairs.is_empty() => {
+            buf.push('{');
+            for (i, (key, val)) in pairs.iter().enumerate() {
+                if i > 0 {
+                    buf.push(',');
+                }
+                pad(buf, depth + 1);
+                buf.push('"');
+                escape_string(key, buf);
+                buf.push_str("\": ");
+                serialize_pretty(val, buf, indent, depth + 1);
+            }
+            pad(buf, depth);
+            buf.push('}');
+        }
+        // Scalars and empty containers are the same as compact output
+        other => serialize(other, buf),
+    }
+}
+
+/// Format a float so that parsing it back yields the same `JsonNumber::Float`:
+/// whole numbers keep a `.0` suffix (so they don't come back as `Int`), and
+/// very large or very small magnitudes use exponent notation.
+fn format_float(f: f64, buf: &mut String) {
+    if f.is_infinite() || f.is_nan() {
+        buf.push_str("null");
+        return;
+    }
+    let abs = f.abs();
+    if abs != 0.0 && !(1e-5..1e16).contains(&abs) {
+        // Rust's `{:e}` is the shortest round-trip form, e.g. `1e21`, `1.5e-7`
+        buf.push_str(&format!("{:e}", f));
+    } else if f.fract() == 0.0 {
+        buf.push_str(&format!("{:.1}", f));
+    } else {
+        buf.push_str(&format!("{}", f));
+    }
+}
+
 fn escape_string(s: &str, buf: &mut String) {
     for ch in s.chars() {
         match ch {
@@ -192,6 +287,11 @@ impl ObjectBuilder {
         self
     }
 
+    pub fn field_f64(mut self, key: &str, val: f64) -> Self {
+        self.pairs.push((key.to_string(), JsonValue::Number(JsonNumber::Float(val))));
+        self
+    }
+
     pub fn field_bool(mut self, key: &str, val: bool) -> Self {
         self.pairs.push((key.to_string(), JsonValue::Bool(val)));
         self
@@ -481,8 +581,11 @@ fn parse_number(bytes: &[u8], input: &str, pos: &mut usize) -> Result<JsonValue,
         })?;
         Ok(JsonValue::Number(JsonNumber::Float(f)))
     } else {
-        match num_str.parse::<i64>() {
-            Ok(n) => Ok(JsonValue::Number(JsonNumber::Int(n))),
+        if let Ok(n) = num_str.parse::<i64>() {
+            return Ok(JsonValue::Number(JsonNumber::Int(n)));
+        }
+        match num_str.parse::<u64>() {
+            Ok(n) => Ok(JsonValue::Number(JsonNumber::UInt(n))),
             Err(_) => {
                 // Overflow — try f64
                 let f: f64 = num_str.parse().map_err(|_| JsonError {
@@ -678,4 +781,88 @@ mod tests {
         let reparsed = parse(&output).unwrap();
         assert_eq!(val, reparsed);
     }
+
+    #[test]
+    fn test_as_u64() {
+        let val = parse(r#"{"id":18446744073709551615,"n":42,"neg":-1,"f":3.0,"frac":2.5}"#).unwrap();
+        assert_eq!(val.get("id").and_then(|v| v.as_u64()), Some(u64::MAX));
+        assert_eq!(val.get("id").and_then(|v| v.as_i64()), None);
+        assert_eq!(val.get("n").and_then(|v| v.as_u64()), Some(42));
+        assert_eq!(val.get("neg").and_then(|v| v.as_u64()), None);
+        assert_eq!(val.get("f").and_then(|v| v.as_u64()), Some(3));
+        assert_eq!(val.get("frac").and_then(|v| v.as_u64()), None);
+        // Large integers survive a round trip
+        assert_eq!(val.to_json_string(), r#"{"id":18446744073709551615,"n":42,"neg":-1,"f":3.0,"frac":2.5}"#);
+    }
+
+    #[test]
+    fn test_get_str() {
+        let val = parse(r#"{"name":"sentinel","id":7}"#).unwrap();
+        assert_eq!(val.get_str("name"), Some("sentinel"));
+        assert_eq!(val.get_str("id"), None);
+        assert_eq!(val.get_str("missing"), None);
+    }
+
+    #[test]
+    fn test_get_path() {
+        let val = parse(
+            r#"{"choices":[{"message":{"content":"hi","tool_calls":[]}}],"usage":{"prompt_tokens":7}}"#,
+        )
+        .unwrap();
+        assert_eq!(val.get_path("usage.prompt_tokens").and_then(|v| v.as_i64()), Some(7));
+        assert_eq!(
+            val.get_path("choices.0.message.content").and_then(|v| v.as_str()),
+            Some("hi")
+        );
+        assert_eq!(val.get_path("usage"), val.get("usage"));
+        // Missing intermediate keys, out-of-range and non-numeric indices
+        assert!(val.get_path("error.message").is_none());
+        assert!(val.get_path("choices.1.message").is_none());
+        assert!(val.get_path("choices.first.message").is_none());
+        assert!(val.get_path("usage.prompt_tokens.x").is_none());
+    }
+
+    #[test]
+    fn test_pretty_print() {
+        let val = json_obj()
+            .field_str("tool", "read_file")
+            .field("input", json_obj().field_str("path", "/tmp/a \"b\"").build())
+            .field("args", json_arr().push_str("-l").push(JsonValue::Null).build())
+            .field("empty_obj", json_obj().build())
+            .field("empty_arr", json_arr().build())
+            .build();
+        assert_eq!(
+            val.to_json_string_pretty(2),
+            "{\n  \"tool\": \"read_file\",\n  \"input\": {\n    \"path\": \"/tmp/a \\\"b\\\"\"\n  },\n  \
+             \"args\": [\n    \"-l\",\n    null\n  ],\n  \"empty_obj\": {},\n  \"empty_arr\": []\n}"
+        );
+        // Same value as the compact form
+        assert_eq!(parse(&val.to_json_string_pretty(4)).unwrap(), val);
+        assert_eq!(JsonValue::Array(vec![]).to_json_string_pretty(2), "[]");
+    }
+
+    #[test]
+    fn test_float_roundtrip_exact() {
+        for input in ["0.1", "1e21", "2.0", "-3.5", "1.5e-7", "123456.789"] {
+            let val = parse(input).unwrap();
+            assert!(matches!(val, JsonValue::Number(JsonNumber::Float(_))), "{}", input);
+            let output = val.to_json_string();
+            assert_eq!(output, input);
+            assert_eq!(parse(&output).unwrap(), val);
+        }
+    }
+
+    #[test]
+    fn test_int_float_distinction_survives() {
+        let val = parse(r#"{"i":2,"f":2.0,"big":1e21}"#).unwrap();
+        let reparsed = parse(&val.to_json_string()).unwrap();
+        assert_eq!(reparsed.get("i").unwrap(), &JsonValue::Number(JsonNumber::Int(2)));
+        assert_eq!(reparsed.get("f").unwrap(), &JsonValue::Number(JsonNumber::Float(2.0)));
+        assert_eq!(reparsed.get("big").unwrap(), &JsonValue::Number(JsonNumber::Float(1e21)));
+    }
+
+    #[test]
+    fn test_non_finite_float_serializes_null() {
+        assert_eq!(JsonValue::Number(JsonNumber::Float(f64::NAN)).to_json_string(), "null");
+    }
 }
@@ -1,4 +1,11 @@
+pub mod base64;
+#[cfg(feature = "gzip")]
+pub mod gzip;
 pub mod http;
 pub mod json;
+pub mod pool;
+pub mod retry;
 #[cfg(feature = "tls")]
 pub mod sse;
+#[cfg(feature = "tls")]
+pub mod websocket;
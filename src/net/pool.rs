@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// ── Keep-alive connection pool ──────────────────────────────────────────────
+//
+// Idle connections keyed by "host:port", so alternating between APIs does not
+// tear down and re-handshake TLS on every switch.
+
+/// Default idle connections kept per host.
+pub const DEFAULT_MAX_PER_HOST: usize = 4;
+/// Default time an idle connection may sit in the pool before it is dropped.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+struct Idle<C> {
+    conn: C,
+    since: Instant,
+}
+
+pub struct ConnPool<C> {
+    idle: HashMap<String, Vec<Idle<C>>>,
+    max_per_host: usize,
+    idle_timeout: Duration,
+}
+
+impl<C> ConnPool<C> {
+    pub fn new(max_per_host: usize, idle_timeout: Duration) -> Self {
+        ConnPool {
+            idle: HashMap::new(),
+            max_per_host,
+            idle_timeout,
+        }
+    }
+
+    /// Take the most recently used connection for `key` that has not expired
+    /// and passes `is_alive`. Connections that fail either check are dropped.
+    pub fn take(&mut self, key: &str, is_alive: impl Fn(&C) -> bool) -> Option<C> {
+        let conns = self.idle.get_mut(key)?;
+        let mut found = None;
+        while let Some(idle) = conns.pop() {
+            if idle.since.elapsed() <= self.idle_timeout && is_alive(&idle.conn) {
+                found = Some(idle.conn);
+                break;
+            }
+        }
+        if conns.is_empty() {
+            self.idle.remove(key);
+        }
+        found
+    }
+
+    /// Return a connection to the pool, dropping the oldest idle connection
+    /// for the host if it is full, and any expired connections.
+    pub fn put(&mut self, key: String, conn: C) {
+        self.evict_expired();
+        if self.max_per_host == 0 {
+            return;
+        }
+        let conns = self.idle.entry(key).or_default();
+        if conns.len() >= self.max_per_host {
+            conns.remove(0);
+        }
+        conns.push(Idle {
+            conn,
+            since: Instant::now(),
+        });
+    }
+
+    /// Drop idle connections older than the idle timeout.
+    pub fn evict_expired(&mut self) {
+        let timeout = self.idle_timeout;
+        self.idle.retain(|_, conns| {
+            conns.retain(|c| c.since.elapsed() <= timeout);
+            !conns.is_empty()
+        });
+    }
+
+    #[cfg(test)]
+    fn idle_count(&self, key: &str) -> usize {
+        self.idle.get(key).map_or(0, |c| c.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_pool_keeps_hosts_separate() {
+        let mut pool = ConnPool::new(4, Duration::from_secs(60));
+        pool.put("api.anthropic.com:443".into(), 1);
+        pool.put("api.telegram.org:443".into(), 2);
+        assert_eq!(pool.take("api.anthropic.com:443", |_| true), Some(1));
+        assert_eq!(pool.take("api.telegram.org:443", |_| true), Some(2));
+        assert_eq!(pool.take("api.telegram.org:443", |_| true), None);
+    }
+
+    #[test]
+    fn test_pool_caps_per_host() {
+        let mut pool = ConnPool::new(2, Duration::from_secs(60));
+        for i in 0..3 {
+            pool.put("h:443".into(), i);
+        }
+        assert_eq!(pool.idle_count("h:443"), 2);
+        // Oldest (0) was evicted; most recent comes out first
+        assert_eq!(pool.take("h:443", |_| true), Some(2));
+        assert_eq!(pool.take("h:443", |_| true), Some(1));
+    }
+
+    #[test]
+    fn test_pool_skips_dead_connections() {
+        let mut pool = ConnPool::new(4, Duration::from_secs(60));
+        pool.put("h:443".into(), 1);
+        pool.put("h:443".into(), 2);
+        assert_eq!(pool.take("h:443", |&c| c != 2), Some(1));
+        assert_eq!(pool.idle_count("h:443"), 0);
+    }
+
+    #[test]
+    fn test_pool_evicts_idle_connections() {
+        let mut pool = ConnPool::new(4, Duration::from_millis(20));
+        pool.put("h:443".into(), 1);
+        thread::sleep(Duration::from_millis(40));
+        assert_eq!(pool.take("h:443", |_| true), None);
+
+        pool.put("a:443".into(), 1);
+        thread::sleep(Duration::from_millis(40));
+        pool.put("b:443".into(), 2);
+        assert_eq!(pool.idle_count("a:443"), 0);
+        assert_eq!(pool.idle_count("b:443"), 1);
+    }
+}
@@ -0,0 +1,290 @@
+use std::cell::RefCell;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::net::http::{HttpError, HttpResponse};
+
+// ── Retry with backoff ──────────────────────────────────────────────────────
+//
+// Transient failures (dropped connections, timeouts, 5xx from an overloaded
+// gateway) are retried with exponential backoff and jitter. 429 is left to
+// the callers' rate-limit handling, and other 4xx responses return at once.
+
+/// Longest single backoff delay.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    /// Also retry POST/PATCH. Only safe for endpoints where repeating a
+    /// request has no side effects (e.g. LLM completions).
+    pub retry_non_idempotent: bool,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32, base_delay: Duration) -> Self {
+        RetryPolicy {
+            max_retries,
+            base_delay,
+            retry_non_idempotent: false,
+        }
+    }
+
+    /// Never retry.
+    pub fn none() -> Self {
+        RetryPolicy::new(0, Duration::ZERO)
+    }
+
+    pub fn retry_non_idempotent(mut self) -> Self {
+        self.retry_non_idempotent = true;
+        self
+    }
+
+    fn applies_to(&self, method: &str) -> bool {
+        self.retry_non_idempotent || matches!(method, "GET" | "HEAD" | "PUT" | "DELETE")
+    }
+
+    /// Delay before retry number `attempt` (0-based): base × 2^attempt, plus
+    /// up to 50% jitter so clients don't retry in lockstep.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(1u32.checked_shl(attempt).unwrap_or(u32::MAX))
+            .min(MAX_DELAY);
+        let jitter_ms = exp.as_millis() as u64 / 2;
+        let jitter = if jitter_ms > 0 {
+            Duration::from_millis(jitter_seed() % (jitter_ms + 1))
+        } else {
+            Duration::ZERO
+        };
+        (exp + jitter).min(MAX_DELAY)
+    }
+}
+
+/// A response whose status decides whether it is worth retrying.
+pub trait Status {
+    fn status(&self) -> u16;
+}
+
+impl Status for HttpResponse {
+    fn status(&self) -> u16 {
+        self.status
+    }
+}
+
+/// Whether a request outcome is worth retrying.
+pub fn is_transient<T: Status>(result: &Result<T, HttpError>) -> bool {
+    match result {
+        Ok(resp) => matches!(resp.status(), 500 | 502 | 503 | 504),
+        Err(HttpError::Connect(_) | HttpError::Tls(_) | HttpError::Timeout) => true,
+        Err(HttpError::Io(e)) => matches!(
+            e.kind(),
+            std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::BrokenPipe
+                | std::io::ErrorKind::UnexpectedEof
+        ),
+        Err(_) => false,
+    }
+}
+
+/// An attempt that failed transiently and was tried again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Retried {
+    /// The status it was answered with, or 0 if no response arrived.
+    pub status: u16,
+    /// How long the attempt took.
+    pub latency: Duration,
+    /// How long was waited before the next attempt.
+    pub delay: Duration,
+}
+
+/// The attempts retried during a request, oldest first, so that callers
+/// can account for each one and not just the attempt that was returned.
+#[derive(Debug, Default)]
+pub struct RetryLog(RefCell<Vec<Retried>>);
+
+impl RetryLog {
+    /// The attempts retried since the last `take`.
+    pub fn take(&self) -> Vec<Retried> {
+        self.0.take()
+    }
+
+    fn push(&self, retried: Retried) {
+        self.0.borrow_mut().push(retried);
+    }
+}
+
+/// Run `op`, retrying transient failures per `policy`. `log` is cleared
+/// first, then told about each attempt that is retried.
+pub fn with_retries<T: Status>(
+    policy: &RetryPolicy,
+    method: &str,
+    sleep: &mut dyn FnMut(Duration),
+    log: &RetryLog,
+    mut op: impl FnMut() -> Result<T, HttpError>,
+) -> Result<T, HttpError> {
+    let max = if policy.applies_to(method) {
+        policy.max_retries
+    } else {
+        0
+    };
+    log.take();
+    let mut attempt = 0;
+    loop {
+        let started = Instant::now();
+        let result = op();
+        if attempt >= max || !is_transient(&result) {
+            return result;
+        }
+        let latency = started.elapsed();
+        let delay = policy.backoff(attempt);
+        let status = match &result {
+            Ok(resp) => {
+                eprintln!(
+                    "sentinel: HTTP {} from server, retrying in {}ms",
+                    resp.status(),
+                    delay.as_millis()
+                );
+                resp.status()
+            }
+            Err(e) => {
+                eprintln!("sentinel: {}, retrying in {}ms", e, delay.as_millis());
+                0
+            }
+        };
+        log.push(Retried { status, latency, delay });
+        sleep(delay);
+        attempt += 1;
+    }
+}
+
+fn jitter_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or(0);
+    // Spread the low-entropy nanosecond count across the range
+    nanos.wrapping_mul(6_364_136_223_846_793_005) >> 16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(code: u16) -> Result<HttpResponse, HttpError> {
+        Ok(HttpResponse {
+            status: code,
+            headers: Vec::new(),
+            body: Vec::new(),
+        })
+    }
+
+    fn run(
+        policy: &RetryPolicy,
+        method: &str,
+        mut outcomes: Vec<Result<HttpResponse, HttpError>>,
+    ) -> (Result<HttpResponse, HttpError>, usize, Vec<Duration>) {
+        outcomes.reverse();
+        let mut calls = 0;
+        let mut slept = Vec::new();
+        let log = RetryLog::default();
+        let result = with_retries(policy, method, &mut |d| slept.push(d), &log, || {
+            calls += 1;
+            outcomes.pop().unwrap()
+        });
+        let retried = log.take();
+        assert_eq!(retried.len(), slept.len());
+        assert!(retried.iter().zip(&slept).all(|(r, d)| r.delay == *d));
+        (result, calls, slept)
+    }
+
+    #[test]
+    fn test_retries_transient_then_succeeds() {
+        let policy = RetryPolicy::new(3, Duration::from_millis(100)).retry_non_idempotent();
+        let (result, calls, slept) = run(
+            &policy,
+            "POST",
+            vec![status(503), Err(HttpError::Timeout), status(200)],
+        );
+        assert_eq!(result.unwrap().status, 200);
+        assert_eq!(calls, 3);
+        assert_eq!(slept.len(), 2);
+        assert!(slept[0] >= Duration::from_millis(100) && slept[0] <= Duration::from_millis(150));
+        assert!(slept[1] >= Duration::from_millis(200) && slept[1] <= Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_log_records_each_retried_attempt() {
+        let policy = RetryPolicy::new(3, Duration::ZERO).retry_non_idempotent();
+        let log = RetryLog::default();
+        let mut outcomes = vec![status(200), Err(HttpError::Timeout), status(503)];
+        let result = with_retries(&policy, "POST", &mut |_| {}, &log, || outcomes.pop().unwrap());
+        assert_eq!(result.unwrap().status, 200);
+        let statuses: Vec<u16> = log.take().iter().map(|r| r.status).collect();
+        assert_eq!(statuses, vec![503, 0]);
+        assert!(log.take().is_empty());
+
+        // Each request starts with an empty log
+        log.push(Retried { status: 502, latency: Duration::ZERO, delay: Duration::ZERO });
+        let _ = with_retries(&policy, "POST", &mut |_| {}, &log, || status(200));
+        assert!(log.take().is_empty());
+    }
+
+    #[test]
+    fn test_client_errors_return_immediately() {
+        let policy = RetryPolicy::new(3, Duration::from_millis(1)).retry_non_idempotent();
+        for code in [400, 401, 429] {
+            let (result, calls, _) = run(&policy, "POST", vec![status(code)]);
+            assert_eq!(result.unwrap().status, code);
+            assert_eq!(calls, 1);
+        }
+        let (result, calls, _) = run(
+            &policy,
+            "POST",
+            vec![Err(HttpError::InvalidUrl("x".into()))],
+        );
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_gives_up_after_max_retries() {
+        let policy = RetryPolicy::new(2, Duration::from_millis(1));
+        let (result, calls, _) = run(
+            &policy,
+            "GET",
+            vec![
+                Err(HttpError::Connect("refused".into())),
+                status(502),
+                status(504),
+            ],
+        );
+        assert_eq!(result.unwrap().status, 504);
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_post_not_retried_unless_enabled() {
+        let policy = RetryPolicy::new(3, Duration::from_millis(1));
+        let (result, calls, _) = run(&policy, "POST", vec![status(503)]);
+        assert_eq!(result.unwrap().status, 503);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_put_and_delete_are_idempotent() {
+        let policy = RetryPolicy::new(1, Duration::from_millis(1));
+        for method in ["PUT", "DELETE"] {
+            let (result, calls, _) = run(&policy, method, vec![status(503), status(204)]);
+            assert_eq!(result.unwrap().status, 204);
+            assert_eq!(calls, 2);
+        }
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RetryPolicy::new(40, Duration::from_secs(1));
+        assert_eq!(policy.backoff(35), MAX_DELAY);
+    }
+}
@@ -2,58 +2,84 @@ use crate::net::http::{HttpError, StreamingResponse};
 
 // ── Server-Sent Events parser ───────────────────────────────────────────────
 
+#[allow(dead_code)] // id and retry matter only to a reconnecting client
 pub struct SseEvent {
     pub event_type: String,
     pub data: String,
+    /// The event's `id:` field, if it set one.
+    pub id: Option<String>,
+    /// Reconnection delay in milliseconds from a `retry:` field.
+    pub retry: Option<u64>,
 }
 
-/// Read a single SSE event from the streaming response.
-/// Returns None on end of stream.
-pub fn read_event(response: &mut StreamingResponse) -> Result<Option<SseEvent>, HttpError> {
-    let mut event_type = String::new();
-    let mut data_parts: Vec<String> = Vec::new();
-    let mut got_content = false;
+/// Line-at-a-time event assembly, following the field rules of the SSE spec.
+#[derive(Default)]
+struct SseParser {
+    event_type: String,
+    data_parts: Vec<String>,
+    id: Option<String>,
+    retry: Option<u64>,
+}
 
-    loop {
-        let line = response.read_line()?;
+impl SseParser {
+    /// Process one line without its terminator. Returns an event when a
+    /// blank line ends one. Comments and `id:`/`retry:`-only blocks produce
+    /// no event (their fields carry over to the next one).
+    fn feed_line(&mut self, line: &str) -> Option<SseEvent> {
+        let line = line.strip_suffix('\r').unwrap_or(line);
 
-        // EOF
-        if line.is_empty() && !got_content {
-            return Ok(None);
+        if line.is_empty() {
+            return self.take_event();
+        }
+        // Comments (keep-alives such as ": ping")
+        if line.starts_with(':') {
+            return None;
         }
 
-        let line = line.trim_end_matches('\r');
-
-        // Empty line = end of event
-        if line.is_empty() {
-            if got_content {
-                return Ok(Some(SseEvent {
-                    event_type,
-                    data: data_parts.join("\n"),
-                }));
+        let (field, value) = match line.split_once(':') {
+            Some((f, v)) => (f, v.strip_prefix(' ').unwrap_or(v)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event_type = value.to_string(),
+            "data" => self.data_parts.push(value.to_string()),
+            "id" if !value.contains('\0') => self.id = Some(value.to_string()),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                self.retry = value.parse().ok();
             }
-            continue;
+            _ => {}
         }
+        None
+    }
 
-        // Comment lines (starting with :)
-        if line.starts_with(':') {
-            continue;
+    /// The event accumulated so far, if it has a type or data.
+    fn take_event(&mut self) -> Option<SseEvent> {
+        if self.event_type.is_empty() && self.data_parts.is_empty() {
+            return None;
         }
+        Some(SseEvent {
+            event_type: std::mem::take(&mut self.event_type),
+            data: std::mem::take(&mut self.data_parts).join("\n"),
+            id: self.id.take(),
+            retry: self.retry.take(),
+        })
+    }
+}
 
-        if let Some(value) = line.strip_prefix("event:") {
-            event_type = value.trim().to_string();
-            got_content = true;
-        } else if let Some(value) = line.strip_prefix("data:") {
-            data_parts.push(value.trim().to_string());
-            got_content = true;
-        } else if let Some(value) = line.strip_prefix("event: ") {
-            event_type = value.to_string();
-            got_content = true;
-        } else if let Some(value) = line.strip_prefix("data: ") {
-            data_parts.push(value.to_string());
-            got_content = true;
+/// Read a single SSE event from the streaming response.
+/// Returns None on end of stream.
+pub fn read_event(response: &mut StreamingResponse) -> Result<Option<SseEvent>, HttpError> {
+    let mut parser = SseParser::default();
+    loop {
+        match response.next_line()? {
+            Some(line) => {
+                if let Some(event) = parser.feed_line(&line) {
+                    return Ok(Some(event));
+                }
+            }
+            // A final event without its trailing blank line is still delivered
+            None => return Ok(parser.take_event()),
         }
-        // Ignore other fields (id:, retry:)
     }
 }
 
@@ -61,17 +87,70 @@ pub fn read_event(response: &mut StreamingResponse) -> Result<Option<SseEvent>,
 mod tests {
     use super::*;
 
-    // Helper: create a StreamingResponse from raw bytes for testing
-    // We can't easily test this without a real stream, so we test the parsing logic
-    // through integration with the anthropic module instead.
+    /// Run a transcript through the parser, including the EOF flush.
+    fn parse_all(transcript: &str) -> Vec<SseEvent> {
+        let mut parser = SseParser::default();
+        let mut events: Vec<SseEvent> = transcript
+            .split('\n')
+            .filter_map(|line| parser.feed_line(line))
+            .collect();
+        events.extend(parser.take_event());
+        events
+    }
 
     #[test]
-    fn test_sse_event_struct() {
-        let event = SseEvent {
-            event_type: "message_start".to_string(),
-            data: r#"{"type":"message_start"}"#.to_string(),
-        };
-        assert_eq!(event.event_type, "message_start");
-        assert!(event.data.contains("message_start"));
+    fn test_comments_do_not_end_stream_or_split_events() {
+        let events = parse_all(
+            ": keep-alive\n\n\
+             event: message_start\n\
+             : mid-event comment\n\
+             data: {\"a\":1}\n\n\
+             :\n\n\
+             data: second\n\n",
+        );
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event_type, "message_start");
+        assert_eq!(events[0].data, "{\"a\":1}");
+        assert_eq!(events[1].event_type, "");
+        assert_eq!(events[1].data, "second");
+    }
+
+    #[test]
+    fn test_id_and_retry_fields() {
+        let events = parse_all(
+            "id: 42\r\n\
+             retry: 3000\r\n\
+             data: x\r\n\r\n\
+             retry: soon\n\
+             data: y\n\n\
+             id: 7\n\n\
+             data: z\n\n",
+        );
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].id.as_deref(), Some("42"));
+        assert_eq!(events[0].retry, Some(3000));
+        assert_eq!(events[0].data, "x");
+        // An invalid retry is ignored
+        assert_eq!(events[1].retry, None);
+        assert_eq!(events[1].id, None);
+        // An id-only block is not an event; the id carries over
+        assert_eq!(events[2].id.as_deref(), Some("7"));
+        assert_eq!(events[2].data, "z");
+    }
+
+    #[test]
+    fn test_multiline_data_joined_with_newline() {
+        let events = parse_all("data: line one\ndata:line two\ndata\ndata:  indented\n\n");
+        assert_eq!(events.len(), 1);
+        // One leading space is stripped; a bare "data" adds an empty line
+        assert_eq!(events[0].data, "line one\nline two\n\n indented");
+    }
+
+    #[test]
+    fn test_final_event_without_blank_line() {
+        let events = parse_all("data: [DONE]");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "[DONE]");
+        assert!(parse_all("").is_empty());
     }
 }
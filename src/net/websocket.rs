@@ -0,0 +1,438 @@
+//! Minimal WebSocket client (RFC 6455) over any byte stream.
+//!
+//! Covers what a bot gateway needs: the upgrade handshake, masked client
+//! frames, fragmented messages, ping/pong and close. Extensions such as
+//! per-message compression are never negotiated.
+
+use std::io::{self, Read, Write};
+
+use crate::net::base64;
+use crate::net::http::HttpError;
+
+/// Largest message accepted, after reassembling fragments.
+const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+/// Appended to the client key before hashing it into Sec-WebSocket-Accept.
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// A complete message from the server. Pings and pongs are handled inside
+/// `read_message` and never surface.
+#[derive(Debug, PartialEq)]
+pub enum WsMessage {
+    Text(String),
+    Binary(Vec<u8>),
+    /// The server closed the connection, with its status code if it gave one.
+    Close(Option<u16>),
+}
+
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+pub struct WebSocket<S> {
+    stream: S,
+    /// Bytes read from the stream but not yet parsed into a frame. A read
+    /// timeout can land mid-frame, so partial frames wait here.
+    buf: Vec<u8>,
+    /// Opcode and payload so far of a fragmented message.
+    fragments: Option<(u8, Vec<u8>)>,
+}
+
+impl<S: Read + Write> WebSocket<S> {
+    /// Perform the upgrade handshake on a connected stream. `key` is the
+    /// base64 client nonce; use `new_key` outside tests.
+    pub fn handshake(mut stream: S, host: &str, path: &str, key: &str) -> Result<Self, HttpError> {
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            path, host, key
+        );
+        stream.write_all(request.as_bytes())?;
+        stream.flush()?;
+
+        // Read the response head a byte at a time so no frame data is consumed
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut byte = [0u8; 1];
+            stream.read_exact(&mut byte)?;
+            head.push(byte[0]);
+            if head.len() > 16 * 1024 {
+                return Err(HttpError::Protocol("upgrade response headers too large".into()));
+            }
+        }
+        let head = String::from_utf8_lossy(&head);
+        let mut lines = head.split("\r\n");
+        let status = lines.next().unwrap_or_default();
+        if status.split(' ').nth(1) != Some("101") {
+            return Err(HttpError::Protocol(format!("WebSocket upgrade refused: {}", status)));
+        }
+        let accept = lines
+            .filter_map(|l| l.split_once(':'))
+            .find(|(k, _)| k.trim().eq_ignore_ascii_case("sec-websocket-accept"))
+            .map(|(_, v)| v.trim().to_string());
+        if accept.as_deref() != Some(accept_key(key).as_str()) {
+            return Err(HttpError::Protocol("WebSocket upgrade has wrong Sec-WebSocket-Accept".into()));
+        }
+
+        Ok(WebSocket {
+            stream,
+            buf: Vec::new(),
+            fragments: None,
+        })
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    pub fn send_text(&mut self, text: &str) -> Result<(), HttpError> {
+        self.send_frame(OP_TEXT, text.as_bytes())
+    }
+
+    /// Send a close frame. The connection should be dropped afterwards.
+    pub fn close(&mut self, code: u16) -> Result<(), HttpError> {
+        self.send_frame(OP_CLOSE, &code.to_be_bytes())
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), HttpError> {
+        let mask = random_bytes::<4>()?;
+        self.stream.write_all(&encode_frame(opcode, payload, mask))?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    /// The next complete message, or `None` if the stream's read timeout
+    /// expired first. Pings are answered as they arrive.
+    pub fn read_message(&mut self) -> Result<Option<WsMessage>, HttpError> {
+        loop {
+            while let Some((frame, used)) = decode_frame(&self.buf)? {
+                self.buf.drain(..used);
+                if let Some(msg) = self.handle_frame(frame)? {
+                    return Ok(Some(msg));
+                }
+            }
+            let mut chunk = [0u8; 8192];
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    return Err(HttpError::Protocol("WebSocket closed without a close frame".into()))
+                }
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                    return Ok(None)
+                }
+                Err(e) => return Err(HttpError::Io(e)),
+            }
+        }
+    }
+
+    fn handle_frame(&mut self, frame: Frame) -> Result<Option<WsMessage>, HttpError> {
+        match frame.opcode {
+            OP_PING => {
+                self.send_frame(OP_PONG, &frame.payload)?;
+                Ok(None)
+            }
+            OP_PONG => Ok(None),
+            OP_CLOSE => {
+                let code = (frame.payload.len() >= 2)
+                    .then(|| u16::from_be_bytes([frame.payload[0], frame.payload[1]]));
+                // Echo the close; the server drops the connection either way
+                let _ = self.send_frame(OP_CLOSE, &frame.payload[..frame.payload.len().min(2)]);
+                Ok(Some(WsMessage::Close(code)))
+            }
+            OP_TEXT | OP_BINARY if self.fragments.is_some() => Err(HttpError::Protocol(
+                "WebSocket data frame inside a fragmented message".into(),
+            )),
+            OP_TEXT | OP_BINARY if !frame.fin => {
+                self.fragments = Some((frame.opcode, frame.payload));
+                Ok(None)
+            }
+            OP_TEXT | OP_BINARY => message(frame.opcode, frame.payload).map(Some),
+            OP_CONTINUATION => {
+                let (opcode, mut payload) = self.fragments.take().ok_or_else(|| {
+                    HttpError::Protocol("WebSocket continuation without a first frame".into())
+                })?;
+                payload.extend_from_slice(&frame.payload);
+                if payload.len() > MAX_MESSAGE_BYTES {
+                    return Err(HttpError::Protocol("WebSocket message too large".into()));
+                }
+                if frame.fin {
+                    message(opcode, payload).map(Some)
+                } else {
+                    self.fragments = Some((opcode, payload));
+                    Ok(None)
+                }
+            }
+            op => Err(HttpError::Protocol(format!("unknown WebSocket opcode {:#x}", op))),
+        }
+    }
+}
+
+fn message(opcode: u8, payload: Vec<u8>) -> Result<WsMessage, HttpError> {
+    if opcode == OP_BINARY {
+        return Ok(WsMessage::Binary(payload));
+    }
+    String::from_utf8(payload)
+        .map(WsMessage::Text)
+        .map_err(|_| HttpError::Protocol("WebSocket text message is not UTF-8".into()))
+}
+
+/// A client frame: always final, always masked.
+fn encode_frame(opcode: u8, payload: &[u8], mask: [u8; 4]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        n if n < 126 => frame.push(0x80 | n as u8),
+        n if n <= 0xFFFF => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        n => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(n as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    frame
+}
+
+/// Parse one frame from the front of `buf`, returning it and the bytes it
+/// used, or `None` if `buf` doesn't hold a whole frame yet.
+fn decode_frame(buf: &[u8]) -> Result<Option<(Frame, usize)>, HttpError> {
+    if buf.len() < 2 {
+        return Ok(None);
+    }
+    if buf[0] & 0x70 != 0 {
+        return Err(HttpError::Protocol("WebSocket frame uses reserved bits".into()));
+    }
+    let fin = buf[0] & 0x80 != 0;
+    let opcode = buf[0] & 0x0F;
+    let masked = buf[1] & 0x80 != 0;
+    let (len, mut pos) = match buf[1] & 0x7F {
+        126 if buf.len() >= 4 => (u16::from_be_bytes([buf[2], buf[3]]) as u64, 4),
+        127 if buf.len() >= 10 => {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&buf[2..10]);
+            (u64::from_be_bytes(bytes), 10)
+        }
+        126 | 127 => return Ok(None),
+        n => (n as u64, 2),
+    };
+    if len > MAX_MESSAGE_BYTES as u64 {
+        return Err(HttpError::Protocol("WebSocket frame too large".into()));
+    }
+    let len = len as usize;
+    let mask = if masked {
+        if buf.len() < pos + 4 {
+            return Ok(None);
+        }
+        let m = [buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]];
+        pos += 4;
+        Some(m)
+    } else {
+        None
+    };
+    if buf.len() < pos + len {
+        return Ok(None);
+    }
+    let mut payload = buf[pos..pos + len].to_vec();
+    if let Some(m) = mask {
+        payload.iter_mut().enumerate().for_each(|(i, b)| *b ^= m[i % 4]);
+    }
+    Ok(Some((Frame { fin, opcode, payload }, pos + len)))
+}
+
+/// A fresh base64 nonce for Sec-WebSocket-Key.
+pub fn new_key() -> Result<String, HttpError> {
+    Ok(base64::encode(&random_bytes::<16>()?))
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], HttpError> {
+    let mut bytes = [0u8; N];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// The Sec-WebSocket-Accept value a server must answer `key` with.
+fn accept_key(key: &str) -> String {
+    base64::encode(&sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes()))
+}
+
+/// SHA-1, needed only for the handshake's accept key.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in msg.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let t = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (hi, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *hi = hi.wrapping_add(v);
+        }
+    }
+
+    let mut out = [0u8; 20];
+    for (i, v) in h.iter().enumerate() {
+        out[i * 4..i * 4 + 4].copy_from_slice(&v.to_be_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Serves scripted reads (an `Err` entry simulates a read timeout) and
+    /// records writes.
+    struct Scripted {
+        reads: VecDeque<io::Result<Vec<u8>>>,
+        written: Vec<u8>,
+    }
+
+    impl Scripted {
+        fn new(reads: Vec<io::Result<Vec<u8>>>) -> Self {
+            Scripted { reads: reads.into(), written: Vec::new() }
+        }
+    }
+
+    impl Read for Scripted {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            match self.reads.pop_front() {
+                None => Ok(0),
+                Some(Err(e)) => Err(e),
+                Some(Ok(mut data)) => {
+                    let n = data.len().min(out.len());
+                    out[..n].copy_from_slice(&data[..n]);
+                    if n < data.len() {
+                        self.reads.push_front(Ok(data.split_off(n)));
+                    }
+                    Ok(n)
+                }
+            }
+        }
+    }
+
+    impl Write for Scripted {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            self.written.extend_from_slice(data);
+            Ok(data.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn timeout() -> io::Result<Vec<u8>> {
+        Err(io::Error::new(io::ErrorKind::WouldBlock, "timed out"))
+    }
+
+    /// An unmasked server frame with a short payload.
+    fn server_frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut f = vec![if fin { 0x80 | opcode } else { opcode }, payload.len() as u8];
+        f.extend_from_slice(payload);
+        f
+    }
+
+    fn connected(reads: Vec<io::Result<Vec<u8>>>) -> WebSocket<Scripted> {
+        WebSocket { stream: Scripted::new(reads), buf: Vec::new(), fragments: None }
+    }
+
+    #[test]
+    fn test_accept_key_matches_rfc_example() {
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn test_handshake_checks_accept_header() {
+        let key = "dGhlIHNhbXBsZSBub25jZQ==";
+        let ok = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                   Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n";
+        let ws = WebSocket::handshake(Scripted::new(vec![Ok(ok.to_vec())]), "gw.example", "/?v=10", key)
+            .unwrap();
+        let request = String::from_utf8(ws.stream.written).unwrap();
+        assert!(request.starts_with("GET /?v=10 HTTP/1.1\r\nHost: gw.example\r\n"));
+        assert!(request.contains("Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n"));
+
+        let wrong = b"HTTP/1.1 101 Switching Protocols\r\nSec-WebSocket-Accept: bogus\r\n\r\n";
+        assert!(WebSocket::handshake(Scripted::new(vec![Ok(wrong.to_vec())]), "h", "/", key).is_err());
+        let refused = b"HTTP/1.1 403 Forbidden\r\n\r\n";
+        assert!(WebSocket::handshake(Scripted::new(vec![Ok(refused.to_vec())]), "h", "/", key).is_err());
+    }
+
+    #[test]
+    fn test_client_frames_are_masked() {
+        for len in [5, 300, 70_000] {
+            let payload = vec![b'x'; len];
+            let frame = encode_frame(OP_TEXT, &payload, [1, 2, 3, 4]);
+            assert_eq!(frame[1] & 0x80, 0x80);
+            let (decoded, used) = decode_frame(&frame).unwrap().unwrap();
+            assert_eq!(used, frame.len());
+            assert_eq!(decoded.payload, payload);
+            assert!(decoded.fin);
+        }
+    }
+
+    #[test]
+    fn test_fragments_reassembled_and_ping_answered() {
+        let mut ws = connected(vec![
+            Ok(server_frame(false, OP_TEXT, b"hel")),
+            Ok(server_frame(true, OP_PING, b"p")),
+            Ok(server_frame(true, OP_CONTINUATION, b"lo")),
+        ]);
+        assert_eq!(ws.read_message().unwrap(), Some(WsMessage::Text("hello".into())));
+        // The pong echoes the ping's payload
+        let (pong, _) = decode_frame(&ws.stream.written).unwrap().unwrap();
+        assert_eq!(pong.opcode, OP_PONG);
+        assert_eq!(pong.payload, b"p");
+    }
+
+    #[test]
+    fn test_timeout_mid_frame_keeps_partial_data() {
+        let frame = server_frame(true, OP_TEXT, b"{\"op\":11}");
+        let (head, tail) = frame.split_at(4);
+        let mut ws = connected(vec![Ok(head.to_vec()), timeout(), Ok(tail.to_vec())]);
+        assert_eq!(ws.read_message().unwrap(), None);
+        assert_eq!(ws.read_message().unwrap(), Some(WsMessage::Text("{\"op\":11}".into())));
+    }
+
+    #[test]
+    fn test_close_frame_reports_code() {
+        let mut ws = connected(vec![Ok(server_frame(true, OP_CLOSE, &4014u16.to_be_bytes()))]);
+        assert_eq!(ws.read_message().unwrap(), Some(WsMessage::Close(Some(4014))));
+        assert!(connected(vec![]).read_message().is_err());
+    }
+}
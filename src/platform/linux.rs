@@ -1,21 +1,75 @@
+use std::cell::RefCell;
 use std::fs;
-use std::io::{Read, Write};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::Path;
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
+use crate::security::capability::{path_allowed, path_denied, CapabilityResult, NetworkAllowlist};
+
 use super::{
-    CapType, CommandOutput, DirEntry, Platform, PlatformError, TcpStream as PlatformTcpStream,
+    looks_like_text, CapType, CommandOutput, DirEntry, FileStat, Platform, PlatformError,
+    TcpStream as PlatformTcpStream, SNIFF_LEN,
 };
 
 // ── Linux platform ─────────────────────────────────────────────────────────
 
+/// Variables commands inherit from the agent; everything else is dropped.
+const BASE_ENV: &[&str] = &["PATH", "HOME", "LANG", "LC_ALL", "TZ", "TERM"];
+const DEFAULT_PATH: &str = "/usr/bin:/usr/local/bin:/bin";
+
+/// Most of each of a command's stdout and stderr kept for its result; the
+/// rest is read and dropped.
+const MAX_CAPTURED_OUTPUT: usize = 1024 * 1024;
+/// Longest wait for output still buffered once a command has exited.
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Output read from a running command's pipes.
+enum Chunk {
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+}
+
+/// The paths and commands tool calls are checked against.
+#[derive(Default)]
+pub struct Allowlists {
+    pub read: Vec<String>,
+    pub write: Vec<String>,
+    pub commands: Vec<String>,
+    pub denied_read: Vec<String>,
+    pub denied_write: Vec<String>,
+}
+
+/// Restrictions on `run_command` children beyond the timeout. Only applied
+/// on Linux.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandLimits {
+    /// Seconds of CPU time before the kernel kills the command.
+    pub cpu_secs: Option<u64>,
+    /// Bytes of address space the command may map.
+    pub memory_bytes: Option<u64>,
+    /// Processes the agent's user may have, counting ones the command
+    /// didn't start.
+    pub processes: Option<u64>,
+    /// Confine the command with a landlock ruleset of its own, allowing only
+    /// the allowed read and write paths and commands.
+    pub landlock: bool,
+}
+
 pub struct LinuxPlatform {
-    allowed_read_paths: Vec<String>,
-    allowed_write_paths: Vec<String>,
-    allowed_commands: Vec<String>,
-    audit_file: std::cell::RefCell<Option<fs::File>>,
+    // In a RefCell so a config reload can replace them while tool
+    // executors hold a reference to the platform
+    allowlists: RefCell<Allowlists>,
+    /// Hosts `CapType::Network` allows; `None` allows any.
+    network: Option<NetworkAllowlist>,
+    audit_log: Option<Arc<AuditLog>>,
+    /// Raised to stop a running command early, like a timeout.
+    interrupt: Option<Arc<AtomicBool>>,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    command_limits: CommandLimits,
 }
 
 impl LinuxPlatform {
@@ -23,26 +77,103 @@ impl LinuxPlatform {
         allowed_read_paths: Vec<String>,
         allowed_write_paths: Vec<String>,
         allowed_commands: Vec<String>,
-        audit_log_path: Option<&str>,
+        audit_log: Option<Arc<AuditLog>>,
     ) -> Self {
-        let audit_file = audit_log_path.and_then(|path| {
-            match fs::OpenOptions::new().create(true).append(true).open(path) {
-                Ok(f) => Some(f),
-                Err(e) => {
-                    eprintln!(
-                        "sentinel: warning: cannot open audit log '{}': {}",
-                        path, e
-                    );
-                    None
-                }
-            }
-        });
         LinuxPlatform {
-            allowed_read_paths,
-            allowed_write_paths,
-            allowed_commands,
-            audit_file: std::cell::RefCell::new(audit_file),
+            allowlists: RefCell::new(Allowlists {
+                read: allowed_read_paths,
+                write: allowed_write_paths,
+                commands: allowed_commands,
+                denied_read: Vec::new(),
+                denied_write: Vec::new(),
+            }),
+            network: None,
+            audit_log,
+            interrupt: None,
+            command_limits: CommandLimits::default(),
+        }
+    }
+
+    /// Paths refused even under an allowed one.
+    pub fn with_denied_paths(self, read: Vec<String>, write: Vec<String>) -> Self {
+        {
+            let mut lists = self.allowlists.borrow_mut();
+            lists.denied_read = read;
+            lists.denied_write = write;
+        }
+        self
+    }
+
+    /// Hosts network capability checks allow.
+    pub fn with_network(mut self, allowlist: NetworkAllowlist) -> Self {
+        self.network = Some(allowlist);
+        self
+    }
+
+    pub fn with_interrupt(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.interrupt = Some(flag);
+        self
+    }
+
+    pub fn with_command_limits(mut self, limits: CommandLimits) -> Self {
+        self.command_limits = limits;
+        self
+    }
+
+    /// Have `cmd` apply the command limits in the child, before exec.
+    /// `search_path` is the `PATH` the child runs with, which allowed
+    /// commands are looked up on.
+    #[cfg(target_os = "linux")]
+    fn confine(&self, cmd: &mut Command, search_path: &str) -> Result<(), PlatformError> {
+        use crate::security::linux::{set_rlimit, ChildSandbox, RLIMIT_AS, RLIMIT_CPU, RLIMIT_NPROC};
+        use crate::skills::sandbox::{interpreters, resolve_command};
+        use std::os::unix::process::CommandExt;
+
+        let limits = &self.command_limits;
+        let rlimits: Vec<(u32, u64)> = [
+            (RLIMIT_CPU, limits.cpu_secs),
+            (RLIMIT_AS, limits.memory_bytes),
+            (RLIMIT_NPROC, limits.processes),
+        ]
+        .into_iter()
+        .filter_map(|(resource, value)| Some((resource, value?)))
+        .collect();
+        let sandbox = if limits.landlock {
+            let lists = self.allowlists.borrow();
+            let mut exec_paths = Vec::new();
+            for path in lists.commands.iter().filter_map(|name| resolve_command(name, search_path)) {
+                exec_paths.extend(interpreters(&path, search_path));
+                exec_paths.push(path);
+            }
+            let sandbox = ChildSandbox::prepare(&lists.read, &lists.write, &exec_paths, true)
+                .map_err(|e| PlatformError::Io(format!("failed to sandbox command: {}", e)))?;
+            Some(sandbox)
+        } else {
+            None
+        };
+        if rlimits.is_empty() && sandbox.is_none() {
+            return Ok(());
         }
+        // Safety: the closure only makes syscalls; nothing is allocated or
+        // locked in the forked child.
+        unsafe {
+            cmd.pre_exec(move || {
+                for &(resource, value) in &rlimits {
+                    set_rlimit(resource, value)?;
+                }
+                match &sandbox {
+                    Some(sandbox) => sandbox.apply(),
+                    None => Ok(()),
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Replace the allowlists, e.g. after a config reload. Calls already
+    /// checked are unaffected.
+    pub fn set_allowlists(&self, allowlists: Allowlists) {
+        *self.allowlists.borrow_mut() = allowlists;
     }
 }
 
@@ -57,6 +188,30 @@ impl Platform for LinuxPlatform {
             .map_err(|e| PlatformError::Io(format!("failed to write '{}': {}", path, e)))
     }
 
+    fn append_file(&self, path: &str, content: &str) -> Result<(), PlatformError> {
+        fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .and_then(|mut f| f.write_all(content.as_bytes()))
+            .map_err(|e| PlatformError::Io(format!("failed to append to '{}': {}", path, e)))
+    }
+
+    fn delete_file(&self, path: &str) -> Result<(), PlatformError> {
+        let meta = fs::symlink_metadata(path)
+            .map_err(|e| PlatformError::NotFound(format!("'{}': {}", path, e)))?;
+        if meta.is_dir() {
+            return Err(PlatformError::Io(format!("'{}' is a directory", path)));
+        }
+        fs::remove_file(path)
+            .map_err(|e| PlatformError::Io(format!("failed to delete '{}': {}", path, e)))
+    }
+
+    fn create_directory(&self, path: &str) -> Result<(), PlatformError> {
+        fs::create_dir_all(path)
+            .map_err(|e| PlatformError::Io(format!("failed to create '{}': {}", path, e)))
+    }
+
     fn list_directory(&self, path: &str) -> Result<Vec<DirEntry>, PlatformError> {
         let entries = fs::read_dir(path)
             .map_err(|e| PlatformError::Io(format!("failed to list '{}': {}", path, e)))?;
@@ -76,50 +231,182 @@ impl Platform for LinuxPlatform {
         Ok(result)
     }
 
+    fn stat_file(&self, path: &str) -> Result<FileStat, PlatformError> {
+        let meta = fs::metadata(path)
+            .map_err(|e| PlatformError::Io(format!("failed to stat '{}': {}", path, e)))?;
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
+
+        // Only a regular file is sniffed: opening a FIFO would wait for a
+        // writer
+        let is_text = if !meta.is_file() {
+            None
+        } else {
+            let mut head = Vec::with_capacity(SNIFF_LEN);
+            fs::File::open(path)
+                .and_then(|f| f.take(SNIFF_LEN as u64).read_to_end(&mut head))
+                .map_err(|e| PlatformError::Io(format!("failed to read '{}': {}", path, e)))?;
+            Some(looks_like_text(&head))
+        };
+
+        Ok(FileStat {
+            size: meta.len(),
+            is_dir: meta.is_dir(),
+            modified,
+            is_text,
+        })
+    }
+
     fn run_command(
         &self,
         command: &str,
         args: &[String],
+        cwd: Option<&str>,
+        env: &[(String, String)],
         timeout_secs: u64,
+    ) -> Result<CommandOutput, PlatformError> {
+        self.run_command_streaming(command, args, cwd, env, timeout_secs, &mut |_| {})
+    }
+
+    fn run_command_streaming(
+        &self,
+        command: &str,
+        args: &[String],
+        cwd: Option<&str>,
+        env: &[(String, String)],
+        timeout_secs: u64,
+        on_line: &mut dyn FnMut(&str),
     ) -> Result<CommandOutput, PlatformError> {
         let timeout = Duration::from_secs(timeout_secs);
-        let mut child = Command::new(command)
+        let mut cmd = Command::new(command);
+        cmd.env_clear();
+        for &name in BASE_ENV {
+            if let Some(value) = std::env::var_os(name) {
+                cmd.env(name, value);
+            } else if name == "PATH" {
+                cmd.env(name, DEFAULT_PATH);
+            }
+        }
+        cmd.envs(env.iter().map(|(k, v)| (k, v)));
+        if let Some(dir) = cwd {
+            cmd.current_dir(dir);
+        }
+        #[cfg(target_os = "linux")]
+        {
+            let search_path = env
+                .iter()
+                .rev()
+                .find(|(k, _)| k == "PATH")
+                .map(|(_, v)| v.clone())
+                .or_else(|| std::env::var_os("PATH").map(|p| p.to_string_lossy().into_owned()))
+                .unwrap_or_else(|| DEFAULT_PATH.to_string());
+            self.confine(&mut cmd, &search_path)?;
+        }
+        let mut child = cmd
             .args(args)
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
             .spawn()
             .map_err(|e| PlatformError::Io(format!("failed to run '{}': {}", command, e)))?;
 
+        // Pipes are read on their own threads so the wait below can keep
+        // checking the timeout while the command is quiet
+        let (tx, rx) = mpsc::channel();
+        if let Some(out) = child.stdout.take() {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let mut reader = BufReader::new(out);
+                let mut line = Vec::new();
+                // A line without an end is passed on in pieces
+                while matches!(
+                    reader.by_ref().take(MAX_CAPTURED_OUTPUT as u64).read_until(b'\n', &mut line),
+                    Ok(n) if n > 0
+                ) {
+                    if tx.send(Chunk::Stdout(std::mem::take(&mut line))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        if let Some(mut err) = child.stderr.take() {
+            thread::spawn(move || {
+                let mut buf = [0u8; 4096];
+                while let Ok(n @ 1..) = err.read(&mut buf) {
+                    if tx.send(Chunk::Stderr(buf[..n].to_vec())).is_err() {
+                        break;
+                    }
+                }
+            });
+        } else {
+            drop(tx);
+        }
+
+        let mut stdout_buf = Vec::new();
+        let mut stderr_buf = Vec::new();
+        let mut take = |chunk: Chunk| match chunk {
+            Chunk::Stdout(line) => {
+                on_line(String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']));
+                capture(&mut stdout_buf, &line);
+            }
+            Chunk::Stderr(bytes) => capture(&mut stderr_buf, &bytes),
+        };
+
         let start = Instant::now();
         loop {
+            match rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(chunk) => take(chunk),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                // Both pipes closed; wait for the exit status
+                Err(mpsc::RecvTimeoutError::Disconnected) => thread::sleep(Duration::from_millis(10)),
+            }
+            // Checked on every pass, so steady output can't outrun the limit
+            let interrupted = self
+                .interrupt
+                .as_ref()
+                .is_some_and(|f| f.load(Ordering::SeqCst));
+            if interrupted || start.elapsed() >= timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                if interrupted {
+                    return Err(PlatformError::Io(format!(
+                        "command '{}' was cancelled",
+                        command
+                    )));
+                }
+                return Err(PlatformError::Timeout(format!(
+                    "command '{}' timed out after {}s",
+                    command, timeout_secs
+                )));
+            }
             match child.try_wait() {
                 Ok(Some(status)) => {
-                    let mut stdout_buf = Vec::new();
-                    let mut stderr_buf = Vec::new();
-                    if let Some(ref mut out) = child.stdout {
-                        let _ = out.read_to_end(&mut stdout_buf);
+                    // Collect what's still buffered. A background process
+                    // left holding the pipes open doesn't hold up the
+                    // result, however much it writes.
+                    let drain_until = Instant::now() + DRAIN_TIMEOUT;
+                    while let Some(left) = drain_until.checked_duration_since(Instant::now()) {
+                        match rx.recv_timeout(left) {
+                            Ok(chunk) => take(chunk),
+                            Err(_) => break,
+                        }
                     }
-                    if let Some(ref mut err) = child.stderr {
-                        let _ = err.read_to_end(&mut stderr_buf);
+                    let mut stdout = String::from_utf8_lossy(&stdout_buf).to_string();
+                    let mut stderr = String::from_utf8_lossy(&stderr_buf).to_string();
+                    for (out, buf) in [(&mut stdout, &stdout_buf), (&mut stderr, &stderr_buf)] {
+                        if buf.len() >= MAX_CAPTURED_OUTPUT {
+                            out.push_str(&format!("\n[output past {} bytes dropped]", MAX_CAPTURED_OUTPUT));
+                        }
                     }
-
                     return Ok(CommandOutput {
-                        stdout: String::from_utf8_lossy(&stdout_buf).to_string(),
-                        stderr: String::from_utf8_lossy(&stderr_buf).to_string(),
+                        stdout,
+                        stderr,
                         exit_code: status.code().unwrap_or(-1),
                     });
                 }
-                Ok(None) => {
-                    if start.elapsed() >= timeout {
-                        let _ = child.kill();
-                        let _ = child.wait();
-                        return Err(PlatformError::Timeout(format!(
-                            "command '{}' timed out after {}s",
-                            command, timeout_secs
-                        )));
-                    }
-                    thread::sleep(Duration::from_millis(100));
-                }
+                Ok(None) => {}
                 Err(e) => {
                     return Err(PlatformError::Io(format!(
                         "error waiting for '{}': {}",
@@ -169,30 +456,42 @@ impl Platform for LinuxPlatform {
 
     fn check_capability(&self, cap_type: CapType, resource: &str) -> Result<bool, PlatformError> {
         match cap_type {
-            CapType::FileRead => Ok(check_path_allowed(
-                resource,
-                &self.allowed_read_paths,
-                self,
-            )),
-            CapType::FileWrite => Ok(check_path_allowed(
-                resource,
-                &self.allowed_write_paths,
-                self,
-            )),
+            CapType::FileRead => {
+                let lists = self.allowlists.borrow();
+                Ok(check_path_allowed(resource, &lists.read, &lists.denied_read, self))
+            }
+            CapType::FileWrite => {
+                let lists = self.allowlists.borrow();
+                Ok(check_path_allowed(resource, &lists.write, &lists.denied_write, self))
+            }
+            CapType::FileDelete => {
+                let lists = self.allowlists.borrow();
+                if lists.write.is_empty() {
+                    return Ok(false);
+                }
+                Ok(entry_path(resource).is_some_and(|entry| {
+                    path_denied(&entry, &lists.denied_write).is_none()
+                        && path_allowed(&entry, &lists.write)
+                }))
+            }
             CapType::Command => {
-                if self.allowed_commands.is_empty() {
+                let lists = self.allowlists.borrow();
+                if lists.commands.is_empty() {
                     return Ok(false);
                 }
                 let base = Path::new(resource)
                     .file_name()
                     .and_then(|n| n.to_str())
                     .unwrap_or(resource);
-                Ok(self
-                    .allowed_commands
+                Ok(lists
+                    .commands
                     .iter()
                     .any(|c| c == base || c == resource))
             }
-            CapType::Network => Ok(true), // Linux: network always allowed (TLS handles auth)
+            CapType::Network => Ok(self
+                .network
+                .as_ref()
+                .is_none_or(|n| matches!(n.check_host(resource), CapabilityResult::Allowed))),
         }
     }
 
@@ -209,8 +508,8 @@ impl Platform for LinuxPlatform {
 
     fn audit_event(&self, event_json: &str) -> Result<(), PlatformError> {
         eprintln!("audit: {}", event_json);
-        if let Some(ref mut f) = *self.audit_file.borrow_mut() {
-            let _ = writeln!(f, "{}", event_json);
+        if let Some(log) = &self.audit_log {
+            log.write_line(event_json);
         }
         Ok(())
     }
@@ -231,24 +530,133 @@ impl Platform for LinuxPlatform {
     }
 }
 
-fn check_path_allowed(path: &str, allowed: &[String], platform: &LinuxPlatform) -> bool {
+fn check_path_allowed(
+    path: &str,
+    allowed: &[String],
+    denied: &[String],
+    platform: &LinuxPlatform,
+) -> bool {
     if allowed.is_empty() {
         return false;
     }
-    let canonical = match platform.canonicalize(path) {
-        Ok(c) => c,
-        Err(_) => return false,
+    match platform.canonicalize(path) {
+        Ok(canonical) => path_denied(&canonical, denied).is_none() && path_allowed(&canonical, allowed),
+        Err(_) => false,
+    }
+}
+
+/// `path` with its parent canonicalized but its last component kept as is,
+/// so a symlink there names the link rather than its target.
+fn entry_path(path: &str) -> Option<String> {
+    let p = Path::new(path);
+    let name = p.file_name()?;
+    let parent = match p.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
     };
-    for prefix in allowed {
-        let canon_prefix = match fs::canonicalize(prefix) {
-            Ok(p) => p.to_string_lossy().to_string(),
-            Err(_) => prefix.clone(),
-        };
-        if canonical.starts_with(&canon_prefix) {
-            return true;
+    let parent = fs::canonicalize(parent).ok()?;
+    Some(parent.join(name).to_string_lossy().to_string())
+}
+
+/// Append `bytes` to `buf` up to `MAX_CAPTURED_OUTPUT` in all.
+fn capture(buf: &mut Vec<u8>, bytes: &[u8]) {
+    let room = MAX_CAPTURED_OUTPUT.saturating_sub(buf.len());
+    buf.extend_from_slice(&bytes[..bytes.len().min(room)]);
+}
+
+// ── Audit log ──────────────────────────────────────────────────────────────
+
+/// The audit log file, shared by every platform so that rotation counts all
+/// of their writes.
+pub struct AuditLog {
+    path: String,
+    /// Rotate before the file would grow past this; 0 never rotates.
+    max_bytes: u64,
+    keep_files: u32,
+    file: Mutex<AuditFile>,
+}
+
+struct AuditFile {
+    file: fs::File,
+    /// Size of the current file, tracked instead of stat-ing every line.
+    written: u64,
+}
+
+impl AuditLog {
+    pub fn open(path: &str, max_bytes: u64, keep_files: u32) -> std::io::Result<Self> {
+        let file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        // The OS sandbox grants the log files alone, not their directory,
+        // and only files that exist when it is applied
+        if max_bytes > 0 {
+            for n in 1..=keep_files {
+                fs::OpenOptions::new().create(true).append(true).open(format!("{}.{}", path, n))?;
+            }
+        }
+        Ok(AuditLog {
+            path: path.to_string(),
+            max_bytes,
+            keep_files,
+            file: Mutex::new(AuditFile { file, written }),
+        })
+    }
+
+    /// Append one line, rotating first if it would take the file past
+    /// `max_bytes`.
+    pub fn write_line(&self, line: &str) {
+        let line = format!("{}\n", line);
+        let mut current = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if self.max_bytes > 0
+            && current.written > 0
+            && current.written + line.len() as u64 > self.max_bytes
+        {
+            match self.rotate() {
+                Ok(file) => *current = AuditFile { file, written: 0 },
+                Err(e) => {
+                    eprintln!("sentinel: warning: cannot rotate audit log '{}': {}", self.path, e);
+                    // Try again after another max_bytes
+                    current.written = 0;
+                }
+            }
+        }
+        // One write per line, so a line is never split across files
+        if current.file.write_all(line.as_bytes()).is_ok() {
+            current.written += line.len() as u64;
+        }
+    }
+
+    /// Shift `path.N` to `path.N+1`, dropping the oldest, copy the current
+    /// file to `path.1`, and empty it. Copying instead of renaming needs
+    /// nothing the seccomp filter refuses, and no access to the directory.
+    fn rotate(&self) -> std::io::Result<fs::File> {
+        let generation = |n: u32| format!("{}.{}", self.path, n);
+        for n in (1..self.keep_files).rev() {
+            match fs::File::open(generation(n)) {
+                Ok(older) => copy_into(older, &generation(n + 1))?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if self.keep_files > 0 {
+            copy_into(fs::File::open(&self.path)?, &generation(1))?;
+        }
+        fs::OpenOptions::new().write(true).truncate(true).open(&self.path)?;
+        fs::OpenOptions::new().append(true).open(&self.path)
+    }
+}
+
+/// Replace the contents of `to` with what is left to read of `from`.
+fn copy_into(mut from: fs::File, to: &str) -> std::io::Result<()> {
+    let mut to = fs::OpenOptions::new().create(true).write(true).truncate(true).open(to)?;
+    // A plain read/write loop: `io::copy` between files may use
+    // copy_file_range or sendfile, which the seccomp filter refuses
+    let mut buf = [0u8; 8192];
+    loop {
+        match from.read(&mut buf)? {
+            0 => return Ok(()),
+            n => to.write_all(&buf[..n])?,
         }
     }
-    false
 }
 
 // ── Linux TCP stream wrapper ───────────────────────────────────────────────
@@ -284,3 +692,183 @@ impl PlatformTcpStream for LinuxTcpStream {
             .map_err(|e| PlatformError::Io(e.to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_log_rotates_by_size() {
+        let dir = "/tmp/sentinel_test_audit_rotate";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        let path = format!("{}/audit.jsonl", dir);
+        let read = |name: &str| fs::read_to_string(format!("{}/{}", dir, name)).ok();
+
+        // Two 10-byte lines fit in 25 bytes; the third starts a new file
+        let log = AuditLog::open(&path, 25, 2).unwrap();
+        log.write_line("{\"n\":\"01\"}");
+        log.write_line("{\"n\":\"02\"}");
+        // Generations exist from the start, so the sandbox can grant them
+        assert_eq!(read("audit.jsonl.1").unwrap(), "");
+        log.write_line("{\"n\":\"03\"}");
+        assert_eq!(read("audit.jsonl").unwrap(), "{\"n\":\"03\"}\n");
+        assert_eq!(read("audit.jsonl.1").unwrap(), "{\"n\":\"01\"}\n{\"n\":\"02\"}\n");
+
+        // Older generations shift up; past audit_keep_files they are dropped
+        for n in 4..=7 {
+            log.write_line(&format!("{{\"n\":\"0{}\"}}", n));
+        }
+        assert_eq!(read("audit.jsonl").unwrap(), "{\"n\":\"07\"}\n");
+        assert_eq!(read("audit.jsonl.1").unwrap(), "{\"n\":\"05\"}\n{\"n\":\"06\"}\n");
+        assert_eq!(read("audit.jsonl.2").unwrap(), "{\"n\":\"03\"}\n{\"n\":\"04\"}\n");
+        assert_eq!(read("audit.jsonl.3"), None);
+
+        // The size of an existing file counts toward the limit
+        drop(log);
+        let log = AuditLog::open(&path, 25, 2).unwrap();
+        log.write_line("{\"n\":\"08\"}");
+        log.write_line("{\"n\":\"09\"}");
+        assert_eq!(read("audit.jsonl").unwrap(), "{\"n\":\"09\"}\n");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_audit_log_rotates_under_agent_filter() {
+        let dir = "/tmp/sentinel_test_audit_filtered";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        let path = format!("{}/audit.jsonl", dir);
+        let log = AuditLog::open(&path, 15, 1).unwrap();
+        thread::spawn(move || {
+            use crate::security::linux::{apply_sandbox, SeccompMode};
+            let agent = apply_sandbox(&[], &[], true, SeccompMode::Enforce, false);
+            assert!(agent.seccomp_applied && agent.seccomp_error.is_none());
+            log.write_line("{\"n\":\"01\"}");
+            log.write_line("{\"n\":\"02\"}");
+        })
+        .join()
+        .unwrap();
+        let read = |name: &str| fs::read_to_string(format!("{}/{}", dir, name)).unwrap();
+        assert_eq!(read("audit.jsonl"), "{\"n\":\"02\"}\n");
+        assert_eq!(read("audit.jsonl.1"), "{\"n\":\"01\"}\n");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_audit_log_without_limit_never_rotates() {
+        let dir = "/tmp/sentinel_test_audit_norotate";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        let path = format!("{}/audit.jsonl", dir);
+        let log = AuditLog::open(&path, 0, 2).unwrap();
+        for _ in 0..100 {
+            log.write_line("{}");
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap().len(), 300);
+        assert!(!Path::new(&format!("{}.1", path)).exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_landlocked_command_under_agent_filter() {
+        use std::os::unix::fs::PermissionsExt;
+
+        if crate::security::linux::landlock_abi().is_none() {
+            eprintln!("landlock not supported; skipping");
+            return;
+        }
+        let dir = "/tmp/sentinel_test_confined_cmd";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(format!("{}/sbin", dir)).unwrap();
+        fs::create_dir_all(format!("{}/data", dir)).unwrap();
+        fs::write(format!("{}/data/ok.txt", dir), "ok\n").unwrap();
+        fs::write(format!("{}/secret.txt", dir), "secret\n").unwrap();
+        // Only on the child's PATH, not the default one
+        let tool = format!("{}/sbin/show", dir);
+        fs::write(&tool, "#!/bin/sh\nread -r line < \"$1\" && echo \"$line\"\n").unwrap();
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let outcome = thread::spawn(move || {
+            use crate::security::linux::{apply_sandbox, SeccompMode};
+            let agent = apply_sandbox(&[], &[], true, SeccompMode::Enforce, false);
+            assert!(agent.seccomp_applied && agent.seccomp_error.is_none());
+            let platform = LinuxPlatform::new(
+                vec![format!("{}/data", dir)],
+                Vec::new(),
+                vec!["show".into()],
+                None,
+            )
+            .with_command_limits(CommandLimits { landlock: true, ..CommandLimits::default() });
+            let env = [("PATH".to_string(), format!("{}/sbin", dir))];
+            let run = |file: &str| {
+                let args = [format!("{}/{}", dir, file)];
+                platform.run_command("show", &args, None, &env, 10).unwrap()
+            };
+            (run("data/ok.txt"), run("secret.txt"))
+        })
+        .join()
+        .unwrap();
+        assert_eq!(outcome.0.stdout, "ok\n");
+        assert_eq!(outcome.1.stdout, "");
+        assert_ne!(outcome.1.exit_code, 0);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_command_over_cpu_limit_is_killed() {
+        let platform = LinuxPlatform::new(Vec::new(), Vec::new(), Vec::new(), None)
+            .with_command_limits(CommandLimits {
+                cpu_secs: Some(1),
+                ..CommandLimits::default()
+            });
+        let args = ["-c".to_string(), "while :; do :; done".to_string()];
+        let start = Instant::now();
+        // Killed by SIGXCPU well before the timeout
+        let output = platform.run_command("sh", &args, None, &[], 30).unwrap();
+        assert_eq!(output.exit_code, -1);
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        // Commands within the limit run as before
+        let output = platform.run_command("echo", &["ok".to_string()], None, &[], 30).unwrap();
+        assert_eq!(output.exit_code, 0);
+        assert_eq!(output.stdout, "ok\n");
+    }
+
+    fn sh(platform: &LinuxPlatform, script: &str, timeout_secs: u64) -> Result<CommandOutput, PlatformError> {
+        let args = ["-c".to_string(), script.to_string()];
+        platform.run_command("sh", &args, None, &[], timeout_secs)
+    }
+
+    #[test]
+    fn test_command_with_steady_output_times_out() {
+        let platform = LinuxPlatform::new(Vec::new(), Vec::new(), Vec::new(), None);
+        let start = Instant::now();
+        let result = sh(&platform, "while :; do echo x; sleep 0.01; done", 1);
+        assert!(matches!(result, Err(PlatformError::Timeout(_))));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_command_output_is_capped() {
+        let platform = LinuxPlatform::new(Vec::new(), Vec::new(), Vec::new(), None);
+        let output = sh(&platform, "head -c 3000000 /dev/zero | tr '\\0' a; head -c 3000000 /dev/zero >&2", 30).unwrap();
+        let note = format!("\n[output past {} bytes dropped]", MAX_CAPTURED_OUTPUT);
+        assert_eq!(output.stdout.len(), MAX_CAPTURED_OUTPUT + note.len());
+        assert!(output.stdout.ends_with(&note));
+        assert!(output.stderr.ends_with(&note));
+    }
+
+    #[test]
+    fn test_background_output_does_not_hold_up_result() {
+        let platform = LinuxPlatform::new(Vec::new(), Vec::new(), Vec::new(), None);
+        let start = Instant::now();
+        let output = sh(&platform, "(while :; do echo y; sleep 0.01; done) & echo done", 30).unwrap();
+        assert!(output.stdout.lines().any(|l| l == "done"), "{}", output.stdout);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -14,7 +14,8 @@ use alloc::vec::Vec;
 use luperiq_rt::syscall;
 
 use super::{
-    CapType, CommandOutput, DirEntry, Platform, PlatformError, TcpStream as PlatformTcpStream,
+    CapType, CommandOutput, DirEntry, FileStat, Platform, PlatformError,
+    TcpStream as PlatformTcpStream,
 };
 
 // ── Syscall numbers ────────────────────────────────────────────────────────
@@ -86,6 +87,21 @@ impl Platform for LuperiqPlatform {
         Ok(())
     }
 
+    fn append_file(&self, path: &str, content: &str) -> Result<(), PlatformError> {
+        // No append mode in the VFS yet: rewrite the file with the addition
+        let mut existing = self.read_file(path).unwrap_or_default();
+        existing.push_str(content);
+        self.write_file(path, &existing)
+    }
+
+    fn delete_file(&self, path: &str) -> Result<(), PlatformError> {
+        Err(PlatformError::NotSupported(format!("delete '{}'", path)))
+    }
+
+    fn create_directory(&self, path: &str) -> Result<(), PlatformError> {
+        Err(PlatformError::NotSupported(format!("mkdir '{}'", path)))
+    }
+
     fn list_directory(&self, path: &str) -> Result<Vec<DirEntry>, PlatformError> {
         // The kernel VFS supports reading directory entries via stat + open+read on dir
         // For now, use a simple approach: open the directory and read entries
@@ -121,12 +137,25 @@ impl Platform for LuperiqPlatform {
         Ok(entries)
     }
 
+    fn stat_file(&self, path: &str) -> Result<FileStat, PlatformError> {
+        // SYS_STAT's result layout is not wired up in luperiq-rt yet.
+        Err(PlatformError::NotSupported(format!("stat '{}'", path)))
+    }
+
     fn run_command(
         &self,
         command: &str,
         _args: &[String],
+        cwd: Option<&str>,
+        env: &[(String, String)],
         _timeout_secs: u64,
     ) -> Result<CommandOutput, PlatformError> {
+        if cwd.is_some() || !env.is_empty() {
+            return Err(PlatformError::NotSupported(format!(
+                "cwd/env for '{}'",
+                command
+            )));
+        }
         // On LuperIQ OS, spawn a child process from the binary path
         let handle = syscall::spawn(command)
             .map_err(|e| PlatformError::Io(format!("spawn '{}': error {}", command, e)))?;
@@ -191,7 +220,7 @@ impl Platform for LuperiqPlatform {
     ) -> Result<(), PlatformError> {
         let spec = match cap_type {
             CapType::FileRead => format!("FILE_READ:{}", resource),
-            CapType::FileWrite => format!("FILE_WRITE:{}", resource),
+            CapType::FileWrite | CapType::FileDelete => format!("FILE_WRITE:{}", resource),
             CapType::Command => format!("SPAWN:{}", resource),
             CapType::Network => format!("NETWORK:{}", resource),
         };
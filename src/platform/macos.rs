@@ -0,0 +1,150 @@
+// macOS platform implementation.
+//
+// File, process and socket operations are the same std calls as on Linux,
+// so they are delegated to LinuxPlatform, which uses nothing Linux-specific.
+// What differs is OS-level sandboxing (security::macos, a Seatbelt profile
+// instead of seccomp + landlock), applied once at startup by app.rs.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use super::linux::{Allowlists, AuditLog, CommandLimits, LinuxPlatform};
+use super::{
+    CapType, CommandOutput, DirEntry, FileStat, Platform, PlatformError,
+    TcpStream as PlatformTcpStream,
+};
+use crate::security::capability::NetworkAllowlist;
+
+pub struct MacosPlatform {
+    inner: LinuxPlatform,
+}
+
+impl MacosPlatform {
+    pub fn new(
+        allowed_read_paths: Vec<String>,
+        allowed_write_paths: Vec<String>,
+        allowed_commands: Vec<String>,
+        audit_log: Option<Arc<AuditLog>>,
+    ) -> Self {
+        MacosPlatform {
+            inner: LinuxPlatform::new(
+                allowed_read_paths,
+                allowed_write_paths,
+                allowed_commands,
+                audit_log,
+            ),
+        }
+    }
+
+    /// Paths refused even under an allowed one.
+    pub fn with_denied_paths(mut self, read: Vec<String>, write: Vec<String>) -> Self {
+        self.inner = self.inner.with_denied_paths(read, write);
+        self
+    }
+
+    /// Hosts network capability checks allow.
+    pub fn with_network(mut self, allowlist: NetworkAllowlist) -> Self {
+        self.inner = self.inner.with_network(allowlist);
+        self
+    }
+
+    pub fn with_interrupt(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.inner = self.inner.with_interrupt(flag);
+        self
+    }
+
+    /// Accepted for the same setup code as Linux; the limits aren't applied.
+    pub fn with_command_limits(mut self, limits: CommandLimits) -> Self {
+        self.inner = self.inner.with_command_limits(limits);
+        self
+    }
+
+    pub fn set_allowlists(&self, allowlists: Allowlists) {
+        self.inner.set_allowlists(allowlists);
+    }
+}
+
+impl Platform for MacosPlatform {
+    fn read_file(&self, path: &str) -> Result<String, PlatformError> {
+        self.inner.read_file(path)
+    }
+
+    fn write_file(&self, path: &str, content: &str) -> Result<(), PlatformError> {
+        self.inner.write_file(path, content)
+    }
+
+    fn append_file(&self, path: &str, content: &str) -> Result<(), PlatformError> {
+        self.inner.append_file(path, content)
+    }
+
+    fn delete_file(&self, path: &str) -> Result<(), PlatformError> {
+        self.inner.delete_file(path)
+    }
+
+    fn create_directory(&self, path: &str) -> Result<(), PlatformError> {
+        self.inner.create_directory(path)
+    }
+
+    fn list_directory(&self, path: &str) -> Result<Vec<DirEntry>, PlatformError> {
+        self.inner.list_directory(path)
+    }
+
+    fn stat_file(&self, path: &str) -> Result<FileStat, PlatformError> {
+        self.inner.stat_file(path)
+    }
+
+    fn run_command(
+        &self,
+        command: &str,
+        args: &[String],
+        cwd: Option<&str>,
+        env: &[(String, String)],
+        timeout_secs: u64,
+    ) -> Result<CommandOutput, PlatformError> {
+        self.inner.run_command(command, args, cwd, env, timeout_secs)
+    }
+
+    fn run_command_streaming(
+        &self,
+        command: &str,
+        args: &[String],
+        cwd: Option<&str>,
+        env: &[(String, String)],
+        timeout_secs: u64,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<CommandOutput, PlatformError> {
+        self.inner
+            .run_command_streaming(command, args, cwd, env, timeout_secs, on_line)
+    }
+
+    fn canonicalize(&self, path: &str) -> Result<String, PlatformError> {
+        self.inner.canonicalize(path)
+    }
+
+    fn check_capability(&self, cap_type: CapType, resource: &str) -> Result<bool, PlatformError> {
+        self.inner.check_capability(cap_type, resource)
+    }
+
+    fn request_capability(
+        &self,
+        _cap_type: CapType,
+        _resource: &str,
+    ) -> Result<(), PlatformError> {
+        // As on Linux, capabilities are static (from config)
+        Err(PlatformError::NotSupported(
+            "capability requests not supported on macOS".into(),
+        ))
+    }
+
+    fn audit_event(&self, event_json: &str) -> Result<(), PlatformError> {
+        self.inner.audit_event(event_json)
+    }
+
+    fn tcp_connect(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Result<Box<dyn PlatformTcpStream>, PlatformError> {
+        self.inner.tcp_connect(host, port)
+    }
+}
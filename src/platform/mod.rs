@@ -1,29 +1,61 @@
 pub mod linux;
+pub mod signal;
 #[cfg(target_os = "none")]
 pub mod luperiq;
+#[cfg(target_os = "macos")]
+pub mod macos;
 
 // ── Platform trait ─────────────────────────────────────────────────────────
 
 /// Abstraction over OS-specific operations.
 ///
 /// On Linux: uses std::fs, std::process, std::net + rustls.
+/// On macOS: the same std implementation, sandboxed with Seatbelt.
 /// On LuperIQ OS: uses kernel syscalls via luperiq-rt.
 pub trait Platform {
     // ── File operations ────────────────────────────────────────────────
 
     fn read_file(&self, path: &str) -> Result<String, PlatformError>;
     fn write_file(&self, path: &str, content: &str) -> Result<(), PlatformError>;
+    /// Add `content` to the end of a file, creating it if needed.
+    fn append_file(&self, path: &str, content: &str) -> Result<(), PlatformError>;
+    /// Remove a file (or symlink). Directories are refused.
+    fn delete_file(&self, path: &str) -> Result<(), PlatformError>;
+    /// Create a directory and any missing parents.
+    fn create_directory(&self, path: &str) -> Result<(), PlatformError>;
     fn list_directory(&self, path: &str) -> Result<Vec<DirEntry>, PlatformError>;
+    fn stat_file(&self, path: &str) -> Result<FileStat, PlatformError>;
 
     // ── Process operations ─────────────────────────────────────────────
 
+    /// Run `command` in `cwd` (the agent's own when `None`). It sees only
+    /// a scrubbed base environment plus `env`.
     fn run_command(
         &self,
         command: &str,
         args: &[String],
+        cwd: Option<&str>,
+        env: &[(String, String)],
         timeout_secs: u64,
     ) -> Result<CommandOutput, PlatformError>;
 
+    /// Like `run_command`, passing each stdout line to `on_line` as the
+    /// command prints it. Platforms that can't read output incrementally
+    /// report the lines once the command exits.
+    fn run_command_streaming(
+        &self,
+        command: &str,
+        args: &[String],
+        cwd: Option<&str>,
+        env: &[(String, String)],
+        timeout_secs: u64,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<CommandOutput, PlatformError> {
+        let output = self.run_command(command, args, cwd, env, timeout_secs)?;
+        output.stdout.lines().for_each(&mut *on_line);
+        Ok(output)
+    }
+
     // ── Path operations ────────────────────────────────────────────────
 
     fn canonicalize(&self, path: &str) -> Result<String, PlatformError>;
@@ -53,6 +85,31 @@ pub struct DirEntry {
     pub is_dir: bool,
 }
 
+pub struct FileStat {
+    pub size: u64,
+    pub is_dir: bool,
+    /// Last modification time, Unix seconds, where the platform reports it.
+    pub modified: Option<u64>,
+    /// Whether the start of the file looks like text. `None` for anything
+    /// but a regular file.
+    pub is_text: Option<bool>,
+}
+
+/// Bytes sniffed from the start of a file to guess text vs binary.
+pub const SNIFF_LEN: usize = 8192;
+
+/// Guess whether `head` (the first bytes of a file) is text: no NUL bytes
+/// and valid UTF-8, allowing a multi-byte character cut off at the end.
+pub fn looks_like_text(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
 pub struct CommandOutput {
     pub stdout: String,
     pub stderr: String,
@@ -63,6 +120,9 @@ pub struct CommandOutput {
 pub enum CapType {
     FileRead,
     FileWrite,
+    /// Removing a directory entry. A symlink is checked where it is, not
+    /// where it points, since the link is what goes.
+    FileDelete,
     Command,
     Network,
 }
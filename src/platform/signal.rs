@@ -0,0 +1,54 @@
+// SIGHUP handling for config reloads.
+//
+// The handler only sets a flag: almost nothing is safe to do inside a
+// signal handler, and the config is read by threads holding non-Send HTTP
+// clients. The main loop polls the flag and does the reload itself.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const SIGHUP: i32 = 1;
+/// `SIG_ERR`, what signal(3) returns on failure.
+const SIG_ERR: usize = usize::MAX;
+
+static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" {
+    // libc; BSD semantics on glibc and macOS, so the handler stays
+    // installed and interrupted syscalls restart.
+    fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+}
+
+extern "C" fn on_sighup(_signum: i32) {
+    RELOAD_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Make SIGHUP request a reload instead of terminating the process.
+pub fn install_reload_handler() -> Result<(), String> {
+    if unsafe { signal(SIGHUP, on_sighup) } == SIG_ERR {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+    Ok(())
+}
+
+/// Whether SIGHUP arrived since the last call.
+pub fn take_reload_request() -> bool {
+    RELOAD_REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" {
+        fn raise(signum: i32) -> i32;
+    }
+
+    #[test]
+    fn test_sighup_sets_reload_flag() {
+        install_reload_handler().unwrap();
+        take_reload_request();
+        assert_eq!(unsafe { raise(SIGHUP) }, 0);
+        assert!(take_reload_request());
+        assert!(!take_reload_request());
+    }
+}
@@ -1,12 +1,19 @@
-use std::time::SystemTime;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
 use crate::net::json::json_obj;
 use crate::platform::Platform;
+use crate::security::redact::redact;
 
 // ── Types ───────────────────────────────────────────────────────────────────
 
 pub struct Auditor<'a> {
     platform: &'a dyn Platform,
+    sink: Option<&'a AuditSink>,
+    /// Mask secrets in tool parameters and errors before logging them.
+    redact: bool,
 }
 
 #[derive(Debug)]
@@ -15,17 +22,48 @@ pub enum AuditEvent<'a> {
     ToolCallDenied { tool: &'a str, params: &'a str, reason: &'a str },
     MessageReceived { chat_id: i64, user_id: i64, username: &'a str },
     UnauthorizedUser { user_id: i64, username: &'a str },
-    ApiCall { endpoint: &'a str, status: u16 },
+    /// A request to the LLM provider's API.
+    ApiCall { endpoint: &'a str, status: u16, tokens_in: i64, tokens_out: i64, latency_ms: u64 },
+    EgressDenied { host: &'a str },
+    NetworkCall { host: &'a str, port: u16, allowed: bool },
+    LlmUsage { input: i64, output: i64, cache_creation: i64, cache_read: i64 },
+    TokenBudgetExhausted { conversation: &'a str, used: u64, limit: u64 },
+    ApprovalRequested { tool: &'a str, params: &'a str },
+    ApprovalDecision { conversation: &'a str, tool: &'a str, decision: &'a str },
+    SkillFailed { skill: &'a str, error: &'a str },
 }
 
 // ── Implementation ──────────────────────────────────────────────────────────
 
 impl<'a> Auditor<'a> {
     pub fn new(platform: &'a dyn Platform) -> Self {
-        Auditor { platform }
+        Auditor { platform, sink: None, redact: true }
+    }
+
+    /// Also queue every event for a remote collector. `None` keeps events
+    /// local.
+    pub fn with_sink(mut self, sink: Option<&'a AuditSink>) -> Self {
+        self.sink = sink;
+        self
+    }
+
+    pub fn with_redaction(mut self, enabled: bool) -> Self {
+        self.redact = enabled;
+        self
+    }
+
+    fn scrub<'s>(&self, text: &'s str) -> std::borrow::Cow<'s, str> {
+        if self.redact {
+            redact(text)
+        } else {
+            text.into()
+        }
     }
 
     pub fn log(&mut self, event: AuditEvent) {
+        #[cfg(feature = "metrics")]
+        crate::agent::metrics::global().record_event(&event);
+
         let timestamp = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .map(|d| d.as_secs())
@@ -36,14 +74,14 @@ impl<'a> Auditor<'a> {
                 .field_str("event", "tool_call_allowed")
                 .field_i64("ts", timestamp as i64)
                 .field_str("tool", tool)
-                .field_str("params", params)
+                .field_str("params", &self.scrub(params))
                 .build(),
             AuditEvent::ToolCallDenied { tool, params, reason } => json_obj()
                 .field_str("event", "tool_call_denied")
                 .field_i64("ts", timestamp as i64)
                 .field_str("tool", tool)
-                .field_str("params", params)
-                .field_str("reason", reason)
+                .field_str("params", &self.scrub(params))
+                .field_str("reason", &self.scrub(reason))
                 .build(),
             AuditEvent::MessageReceived { chat_id, user_id, username } => json_obj()
                 .field_str("event", "message_received")
@@ -58,11 +96,60 @@ impl<'a> Auditor<'a> {
                 .field_i64("user_id", user_id)
                 .field_str("username", username)
                 .build(),
-            AuditEvent::ApiCall { endpoint, status } => json_obj()
+            AuditEvent::ApiCall { endpoint, status, tokens_in, tokens_out, latency_ms } => json_obj()
                 .field_str("event", "api_call")
                 .field_i64("ts", timestamp as i64)
                 .field_str("endpoint", endpoint)
                 .field_i64("status", status as i64)
+                .field_i64("tokens_in", tokens_in)
+                .field_i64("tokens_out", tokens_out)
+                .field_i64("latency_ms", latency_ms as i64)
+                .build(),
+            AuditEvent::EgressDenied { host } => json_obj()
+                .field_str("event", "egress_denied")
+                .field_i64("ts", timestamp as i64)
+                .field_str("host", host)
+                .build(),
+            AuditEvent::NetworkCall { host, port, allowed } => json_obj()
+                .field_str("event", "network_call")
+                .field_i64("ts", timestamp as i64)
+                .field_str("host", host)
+                .field_i64("port", port as i64)
+                .field_bool("allowed", allowed)
+                .build(),
+            AuditEvent::LlmUsage { input, output, cache_creation, cache_read } => json_obj()
+                .field_str("event", "llm_usage")
+                .field_i64("ts", timestamp as i64)
+                .field_i64("input_tokens", input)
+                .field_i64("output_tokens", output)
+                .field_i64("cache_creation_input_tokens", cache_creation)
+                .field_i64("cache_read_input_tokens", cache_read)
+                .build(),
+            AuditEvent::TokenBudgetExhausted { conversation, used, limit } => json_obj()
+                .field_str("event", "token_budget_exhausted")
+                .field_i64("ts", timestamp as i64)
+                .field_str("conversation", conversation)
+                .field_i64("used", used as i64)
+                .field_i64("limit", limit as i64)
+                .build(),
+            AuditEvent::ApprovalRequested { tool, params } => json_obj()
+                .field_str("event", "approval_requested")
+                .field_i64("ts", timestamp as i64)
+                .field_str("tool", tool)
+                .field_str("params", &self.scrub(params))
+                .build(),
+            AuditEvent::ApprovalDecision { conversation, tool, decision } => json_obj()
+                .field_str("event", "approval_decision")
+                .field_i64("ts", timestamp as i64)
+                .field_str("conversation", conversation)
+                .field_str("tool", tool)
+                .field_str("decision", decision)
+                .build(),
+            AuditEvent::SkillFailed { skill, error } => json_obj()
+                .field_str("event", "skill_failed")
+                .field_i64("ts", timestamp as i64)
+                .field_str("skill", skill)
+                .field_str("error", &self.scrub(error))
                 .build(),
         };
 
@@ -71,5 +158,167 @@ impl<'a> Auditor<'a> {
         // Delegate to platform — on Linux: eprintln + file append,
         // on LuperIQ: kernel AuditWrite syscall
         let _ = self.platform.audit_event(&line);
+        if let Some(sink) = self.sink {
+            sink.push(line);
+        }
+    }
+}
+
+// ── Remote sink ─────────────────────────────────────────────────────────────
+
+/// Events waiting to be sent to a remote collector. Clones share one queue
+/// of at most `capacity` events; when the collector falls behind, the
+/// oldest are dropped rather than blocking the agent.
+#[derive(Clone)]
+pub struct AuditSink {
+    queue: Arc<SinkQueue>,
+}
+
+struct SinkQueue {
+    events: Mutex<VecDeque<String>>,
+    /// Signalled when a full batch is waiting.
+    ready: Condvar,
+    capacity: usize,
+    batch: usize,
+    dropped: AtomicU64,
+}
+
+impl AuditSink {
+    pub fn new(capacity: usize, batch: usize) -> Self {
+        AuditSink {
+            queue: Arc::new(SinkQueue {
+                events: Mutex::new(VecDeque::new()),
+                ready: Condvar::new(),
+                capacity: capacity.max(1),
+                batch: batch.max(1),
+                dropped: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub fn push(&self, line: String) {
+        let q = &self.queue;
+        let mut events = q.events.lock().unwrap_or_else(|e| e.into_inner());
+        if events.len() >= q.capacity {
+            events.pop_front();
+            q.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        events.push_back(line);
+        if events.len() >= q.batch {
+            q.ready.notify_one();
+        }
+    }
+
+    /// Wait until a full batch is queued or `interval` has passed, then
+    /// send up to one batch as a JSON array. A batch that fails to send
+    /// goes back on the queue for the next flush.
+    pub fn flush(
+        &self,
+        interval: Duration,
+        send: &mut dyn FnMut(&str) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let batch = self.next_batch(interval);
+        let dropped = self.queue.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            eprintln!("sentinel: audit webhook falling behind; dropped {} event(s)", dropped);
+        }
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let body = format!("[{}]", batch.join(","));
+        send(&body).inspect_err(|_| self.requeue(batch))
+    }
+
+    fn next_batch(&self, interval: Duration) -> Vec<String> {
+        let q = &self.queue;
+        let deadline = Instant::now() + interval;
+        let mut events = q.events.lock().unwrap_or_else(|e| e.into_inner());
+        while events.len() < q.batch {
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else { break };
+            events = q.ready.wait_timeout(events, left).unwrap_or_else(|e| e.into_inner()).0;
+        }
+        let n = events.len().min(q.batch);
+        events.drain(..n).collect()
+    }
+
+    /// Put an unsent batch back in front of newer events, still dropping
+    /// the oldest past capacity.
+    fn requeue(&self, batch: Vec<String>) {
+        let q = &self.queue;
+        let mut events = q.events.lock().unwrap_or_else(|e| e.into_inner());
+        for line in batch.into_iter().rev() {
+            if events.len() >= q.capacity {
+                q.dropped.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            events.push_front(line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(sink: &AuditSink) -> Vec<String> {
+        sink.queue.events.lock().unwrap().iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_sink_drops_oldest_when_full() {
+        let sink = AuditSink::new(3, 10);
+        for n in 1..=5 {
+            sink.push(n.to_string());
+        }
+        assert_eq!(queued(&sink), vec!["3", "4", "5"]);
+        assert_eq!(sink.queue.dropped.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_sink_flushes_full_batch_without_waiting() {
+        let sink = AuditSink::new(100, 2);
+        for n in 1..=3 {
+            sink.push(format!("{{\"n\":{}}}", n));
+        }
+        let mut sent = Vec::new();
+        let start = Instant::now();
+        sink.flush(Duration::from_secs(30), &mut |body| {
+            sent.push(body.to_string());
+            Ok(())
+        })
+        .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(sent, vec![r#"[{"n":1},{"n":2}]"#]);
+        assert_eq!(queued(&sink), vec![r#"{"n":3}"#]);
+    }
+
+    #[test]
+    fn test_sink_flushes_partial_batch_after_interval() {
+        let sink = AuditSink::new(100, 50);
+        sink.push("{}".into());
+        let mut sent = Vec::new();
+        sink.flush(Duration::from_millis(50), &mut |body| {
+            sent.push(body.to_string());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(sent, vec!["[{}]"]);
+
+        // Nothing queued: nothing sent
+        sink.flush(Duration::from_millis(10), &mut |_| panic!("sent an empty batch"))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_sink_keeps_failed_batch() {
+        let sink = AuditSink::new(3, 2);
+        sink.push("1".into());
+        sink.push("2".into());
+        let failed = sink.flush(Duration::ZERO, &mut |_| Err("503".into()));
+        assert_eq!(failed, Err("503".to_string()));
+        sink.push("3".into());
+        sink.push("4".into());
+        // The retried batch keeps its place; the oldest beyond capacity goes
+        assert_eq!(queued(&sink), vec!["2", "3", "4"]);
     }
 }
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
 use std::path::Path;
 
 // ── Types ───────────────────────────────────────────────────────────────────
@@ -12,6 +14,29 @@ pub struct CapabilityChecker {
     allowed_read_paths: Vec<String>,
     allowed_write_paths: Vec<String>,
     allowed_commands: Vec<String>,
+    allowed_hosts: Vec<String>,
+    arg_policies: ArgPolicies,
+    denied_read_paths: Vec<String>,
+    denied_write_paths: Vec<String>,
+}
+
+/// What arguments one command may be given.
+#[derive(Debug, Clone, Default)]
+pub struct ArgPolicy {
+    /// If not empty, every argument must match one of these patterns
+    /// (`*` matches any run of characters).
+    pub allowed: Vec<String>,
+    /// Arguments refused outright. A short flag such as `-c` is also found
+    /// in a cluster (`-ec`), and a long one such as `--exec` with a value
+    /// (`--exec=...`); `*` patterns are matched as in `allowed`.
+    pub denied: Vec<String>,
+}
+
+/// Argument policies by command name. Commands without one take any
+/// arguments.
+#[derive(Debug, Clone, Default)]
+pub struct ArgPolicies {
+    by_command: HashMap<String, ArgPolicy>,
 }
 
 // ── Implementation ──────────────────────────────────────────────────────────
@@ -26,18 +51,43 @@ impl CapabilityChecker {
             allowed_read_paths,
             allowed_write_paths,
             allowed_commands,
+            allowed_hosts: Vec::new(),
+            arg_policies: ArgPolicies::default(),
+            denied_read_paths: Vec::new(),
+            denied_write_paths: Vec::new(),
         }
     }
 
+    /// Paths refused even when under an allowed one, e.g. `~/.ssh` inside
+    /// an allowed home directory.
+    pub fn with_denied_paths(mut self, read: Vec<String>, write: Vec<String>) -> Self {
+        self.denied_read_paths = read;
+        self.denied_write_paths = write;
+        self
+    }
+
+    /// Argument rules `check_command` applies to allowed commands.
+    pub fn with_arg_policies(mut self, policies: ArgPolicies) -> Self {
+        self.arg_policies = policies;
+        self
+    }
+
+    /// Hosts `check_network` allows; none by default. Entries are as in
+    /// `NetworkAllowlist`, optionally with a `:port` that they're limited to.
+    pub fn with_network_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.allowed_hosts = hosts;
+        self
+    }
+
     pub fn check_file_read(&self, path: &str) -> CapabilityResult {
-        check_path(path, &self.allowed_read_paths, "read")
+        check_path(path, &self.allowed_read_paths, &self.denied_read_paths, "read")
     }
 
     pub fn check_file_write(&self, path: &str) -> CapabilityResult {
-        check_path(path, &self.allowed_write_paths, "write")
+        check_path(path, &self.allowed_write_paths, &self.denied_write_paths, "write")
     }
 
-    pub fn check_command(&self, command: &str) -> CapabilityResult {
+    pub fn check_command(&self, command: &str, args: &[String]) -> CapabilityResult {
         if self.allowed_commands.is_empty() {
             return CapabilityResult::Denied("no commands are allowed".into());
         }
@@ -49,7 +99,7 @@ impl CapabilityChecker {
             .unwrap_or(command);
 
         if self.allowed_commands.iter().any(|c| c == base || c == command) {
-            CapabilityResult::Allowed
+            self.arg_policies.check(command, args)
         } else {
             CapabilityResult::Denied(format!(
                 "command '{}' not in allowlist",
@@ -59,7 +109,253 @@ impl CapabilityChecker {
     }
 }
 
-fn check_path(path: &str, allowed: &[String], operation: &str) -> CapabilityResult {
+impl CapabilityChecker {
+    /// Whether a request may go to `host` on `port`. It must be listed, and
+    /// loopback, private and link-local addresses are refused even if listed,
+    /// so a fetch can't be turned against services on the machine or its
+    /// network.
+    pub fn check_network(&self, host: &str, port: u16) -> CapabilityResult {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let lower = host.to_ascii_lowercase();
+        if lower == "localhost" || lower.ends_with(".localhost") {
+            return CapabilityResult::Denied(format!("'{}' is an internal address", host));
+        }
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            if is_internal(ip) {
+                return CapabilityResult::Denied(format!("'{}' is an internal address", host));
+            }
+        }
+        if self.allowed_hosts.is_empty() {
+            return CapabilityResult::Denied("no network hosts are allowed".into());
+        }
+        let listed = self.allowed_hosts.iter().any(|entry| {
+            let (entry, entry_port) = split_port(entry);
+            host_matches(entry, host) && entry_port.is_none_or(|p| p == port)
+        });
+        if listed {
+            CapabilityResult::Allowed
+        } else {
+            CapabilityResult::Denied(format!("host '{}' port {} not in allowlist", host, port))
+        }
+    }
+}
+
+/// Where the agent's own connections may go. Entries are host names
+/// (case-insensitive), `*.example.com` for any subdomain, IP addresses, or
+/// CIDR ranges such as `10.0.0.0/8`. Internal addresses are refused unless
+/// listed; other hosts are refused too when the list is restrictive.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkAllowlist {
+    entries: Vec<String>,
+    restrict: bool,
+}
+
+impl NetworkAllowlist {
+    /// `restrict`: whether hosts that aren't listed are refused, rather
+    /// than only unlisted internal ones.
+    pub fn new(entries: Vec<String>, restrict: bool) -> Self {
+        NetworkAllowlist { entries, restrict }
+    }
+
+    fn lists_host(&self, host: &str) -> bool {
+        self.entries.iter().any(|e| host_matches(e, host))
+    }
+
+    /// Check a host before it is resolved.
+    pub fn check_host(&self, host: &str) -> CapabilityResult {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if self.lists_host(host) {
+            return CapabilityResult::Allowed;
+        }
+        let lower = host.to_ascii_lowercase();
+        let internal = lower == "localhost"
+            || lower.ends_with(".localhost")
+            || host.parse::<IpAddr>().is_ok_and(is_internal);
+        if internal {
+            CapabilityResult::Denied(format!("'{}' is an internal address", host))
+        } else if self.restrict {
+            CapabilityResult::Denied(format!("host '{}' not in allowed_network_hosts", host))
+        } else {
+            CapabilityResult::Allowed
+        }
+    }
+
+    /// Check the addresses `host` resolved to: a name that isn't listed
+    /// may not lead to an internal address, unless that address is listed.
+    pub fn check_addrs(&self, host: &str, addrs: &[IpAddr]) -> CapabilityResult {
+        if self.lists_host(host.trim_start_matches('[').trim_end_matches(']')) {
+            return CapabilityResult::Allowed;
+        }
+        let unlisted = addrs.iter().find(|ip| {
+            is_internal(**ip) && !self.entries.iter().any(|e| addr_matches(e, **ip))
+        });
+        match unlisted {
+            Some(ip) => CapabilityResult::Denied(format!(
+                "'{}' resolves to internal address {}",
+                host, ip
+            )),
+            None => CapabilityResult::Allowed,
+        }
+    }
+}
+
+/// An allowlist entry and the port at its end, if it has one:
+/// "example.com:8443" and "[::1]:8443", but not the IPv6 address "::1".
+pub fn split_port(entry: &str) -> (&str, Option<u16>) {
+    let split = match entry.rsplit_once(':') {
+        Some((host, port)) if entry.starts_with('[') => host.ends_with(']').then_some((host, port)),
+        Some((host, port)) if !host.contains(':') => Some((host, port)),
+        _ => None,
+    };
+    match split.map(|(host, port)| (host, port.parse::<u16>())) {
+        Some((host, Ok(port))) => (host, Some(port)),
+        _ => (entry, None),
+    }
+}
+
+/// Whether allowlist `entry` names `host`, a host name or IP literal.
+pub fn host_matches(entry: &str, host: &str) -> bool {
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return addr_matches(entry, ip);
+    }
+    match entry.strip_prefix("*.") {
+        Some(domain) => host
+            .to_ascii_lowercase()
+            .ends_with(&format!(".{}", domain.to_ascii_lowercase())),
+        None => entry.eq_ignore_ascii_case(host),
+    }
+}
+
+/// Whether allowlist `entry` is `ip` or a CIDR range containing it.
+pub fn addr_matches(entry: &str, ip: IpAddr) -> bool {
+    let entry = entry.trim_start_matches('[').trim_end_matches(']');
+    let ip = ip.to_canonical();
+    let Some((base, bits)) = entry.split_once('/') else {
+        return entry.parse::<IpAddr>().is_ok_and(|e| e.to_canonical() == ip);
+    };
+    let (Ok(base), Ok(bits)) = (base.parse::<IpAddr>(), bits.parse::<u32>()) else {
+        return false;
+    };
+    match (base.to_canonical(), ip) {
+        (IpAddr::V4(base), IpAddr::V4(ip)) if bits <= 32 => {
+            let mask = u32::MAX.checked_shl(32 - bits).unwrap_or(0);
+            u32::from(base) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(base), IpAddr::V6(ip)) if bits <= 128 => {
+            let mask = u128::MAX.checked_shl(128 - bits).unwrap_or(0);
+            u128::from(base) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Loopback, private, link-local, shared (CGNAT) and unspecified addresses.
+pub fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_internal_v4(v4),
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_internal_v4(v4);
+            }
+            let first = v6.segments()[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || (first & 0xfe00) == 0xfc00 // unique local
+                || (first & 0xffc0) == 0xfe80 // link-local
+        }
+    }
+}
+
+fn is_internal_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || (a == 100 && (64..128).contains(&b)) // shared address space
+        || a == 0
+}
+
+impl ArgPolicies {
+    /// Policies from `[security.allowed_args]` and `[security.denied_args]`
+    /// style tables of command name to patterns.
+    pub fn new(allowed: &[(String, Vec<String>)], denied: &[(String, Vec<String>)]) -> Self {
+        let mut by_command: HashMap<String, ArgPolicy> = HashMap::new();
+        for (command, patterns) in allowed {
+            by_command.entry(command.clone()).or_default().allowed.extend(patterns.iter().cloned());
+        }
+        for (command, patterns) in denied {
+            by_command.entry(command.clone()).or_default().denied.extend(patterns.iter().cloned());
+        }
+        ArgPolicies { by_command }
+    }
+
+    /// Whether `command` may run with `args`. The policy is looked up by
+    /// the path as given, then by its base name.
+    pub fn check(&self, command: &str, args: &[String]) -> CapabilityResult {
+        let base = Path::new(command)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(command);
+        let Some(policy) = self.by_command.get(command).or_else(|| self.by_command.get(base)) else {
+            return CapabilityResult::Allowed;
+        };
+        for arg in args {
+            if let Some(flag) = policy.denied.iter().find(|d| denies(d, arg)) {
+                return CapabilityResult::Denied(format!(
+                    "argument '{}' is not allowed for '{}' (denied: {})",
+                    arg, base, flag
+                ));
+            }
+            if !policy.allowed.is_empty() && !policy.allowed.iter().any(|p| glob_match(p, arg)) {
+                return CapabilityResult::Denied(format!(
+                    "argument '{}' is not in the allowlist for '{}'",
+                    arg, base
+                ));
+            }
+        }
+        CapabilityResult::Allowed
+    }
+}
+
+/// Whether the denied pattern `pattern` catches `arg`.
+fn denies(pattern: &str, arg: &str) -> bool {
+    if glob_match(pattern, arg) {
+        return true;
+    }
+    // `--exec` also denies `--exec=...`
+    if pattern.starts_with("--") && arg.strip_prefix(pattern).is_some_and(|v| v.starts_with('=')) {
+        return true;
+    }
+    // `-c` also denies `-ec` and `-cfoo`
+    let short = pattern.strip_prefix('-').filter(|f| f.len() == 1 && !f.starts_with('-'));
+    match (short, arg.strip_prefix('-')) {
+        (Some(flag), Some(cluster)) if !cluster.starts_with('-') => cluster.contains(flag),
+        _ => false,
+    }
+}
+
+/// Match `text` against `pattern`, where `*` matches any run of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let Some((first, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut text) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let last = parts.pop().unwrap_or_default();
+    for part in parts {
+        match text.find(part) {
+            Some(i) => text = &text[i + part.len()..],
+            None => return false,
+        }
+    }
+    text.len() >= last.len() && text.ends_with(last)
+}
+
+fn check_path(path: &str, allowed: &[String], denied: &[String], operation: &str) -> CapabilityResult {
     if allowed.is_empty() {
         return CapabilityResult::Denied(format!("no {} paths are allowed", operation));
     }
@@ -95,18 +391,15 @@ fn check_path(path: &str, allowed: &[String], operation: &str) -> CapabilityResu
         }
     };
 
-    let canonical_str = canonical.to_string_lossy();
-
-    for allowed_prefix in allowed {
-        // Canonicalize the allowed prefix too
-        let canon_prefix = match std::fs::canonicalize(allowed_prefix) {
-            Ok(p) => p.to_string_lossy().to_string(),
-            Err(_) => allowed_prefix.clone(),
-        };
-
-        if canonical_str.starts_with(&canon_prefix) {
-            return CapabilityResult::Allowed;
-        }
+    let canonical = canonical.to_string_lossy();
+    if let Some(entry) = path_denied(&canonical, denied) {
+        return CapabilityResult::Denied(format!(
+            "{} access denied for path '{}' (under denied path '{}')",
+            operation, path, entry
+        ));
+    }
+    if path_allowed(&canonical, allowed) {
+        return CapabilityResult::Allowed;
     }
 
     CapabilityResult::Denied(format!(
@@ -115,6 +408,96 @@ fn check_path(path: &str, allowed: &[String], operation: &str) -> CapabilityResu
     ))
 }
 
+// ── Path patterns ───────────────────────────────────────────────────────────
+//
+// An allow or deny entry is either a plain prefix (`/tmp` covers everything
+// under it) or a glob matched against the whole canonical path:
+//   `?`   one character other than `/`
+//   `*`   any run of characters other than `/`
+//   `**`  as a whole component, any number of directories (including none)
+// Targets are canonicalized first, so neither `..` nor a symlink can step
+// out of a pattern or around a denied path, and a pattern's literal leading
+// directories are canonicalized the same way.
+
+/// Whether `canonical` is covered by the allowlist `allowed`.
+pub fn path_allowed(canonical: &str, allowed: &[String]) -> bool {
+    allowed.iter().any(|entry| entry_matches(entry, canonical))
+}
+
+/// The entry of `denied` covering `canonical`, if any. Denials win over
+/// any allowlist.
+pub fn path_denied<'d>(canonical: &str, denied: &'d [String]) -> Option<&'d str> {
+    denied
+        .iter()
+        .find(|entry| entry_matches(entry, canonical))
+        .map(String::as_str)
+}
+
+/// Whether an allowlist entry is a glob rather than a plain prefix.
+pub fn is_glob(entry: &str) -> bool {
+    entry.contains(['*', '?'])
+}
+
+/// The directory a glob entry is rooted at: its components before the
+/// first one with a wildcard. A plain prefix is its own base.
+pub fn glob_base(entry: &str) -> &str {
+    let Some(wild) = entry.find(['*', '?']) else {
+        return entry;
+    };
+    match entry[..wild].rfind('/') {
+        Some(0) => "/",
+        Some(slash) => &entry[..slash],
+        None => ".",
+    }
+}
+
+fn entry_matches(entry: &str, canonical: &str) -> bool {
+    let base = glob_base(entry);
+    let canon_base = match std::fs::canonicalize(base) {
+        Ok(p) => p.to_string_lossy().to_string(),
+        Err(_) => base.to_string(),
+    };
+    if !is_glob(entry) {
+        return canonical.starts_with(&canon_base);
+    }
+    let rest = entry[base.len()..].trim_start_matches('/');
+    let pattern = format!("{}/{}", canon_base.trim_end_matches('/'), rest);
+    glob_matches_path(&pattern, canonical)
+}
+
+/// Match a whole path against a glob, component by component.
+fn glob_matches_path(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    match_components(&pattern, &path)
+}
+
+fn match_components(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_components(rest, &path[skip..])),
+        Some((segment, rest)) => path.split_first().is_some_and(|(component, path_rest)| {
+            match_segment(segment.as_bytes(), component.as_bytes()) && match_components(rest, path_rest)
+        }),
+    }
+}
+
+/// `*` and `?` within one path component.
+fn match_segment(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| match_segment(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && match_segment(rest, char_tail(text)),
+        Some((&c, rest)) => text.first() == Some(&c) && match_segment(rest, &text[1..]),
+    }
+}
+
+/// `text` after its first UTF-8 character, so `?` matches a whole one.
+fn char_tail(text: &[u8]) -> &[u8] {
+    let len = text[1..].iter().take_while(|&&b| b & 0xC0 == 0x80).count() + 1;
+    &text[len..]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -122,18 +505,258 @@ mod tests {
     #[test]
     fn test_command_allowlist() {
         let checker = CapabilityChecker::new(vec![], vec![], vec!["ls".into(), "cat".into()]);
-        assert!(matches!(checker.check_command("ls"), CapabilityResult::Allowed));
-        assert!(matches!(checker.check_command("rm"), CapabilityResult::Denied(_)));
-        assert!(matches!(checker.check_command("/bin/ls"), CapabilityResult::Allowed));
+        assert!(matches!(checker.check_command("ls", &[]), CapabilityResult::Allowed));
+        assert!(matches!(checker.check_command("rm", &[]), CapabilityResult::Denied(_)));
+        assert!(matches!(checker.check_command("/bin/ls", &[]), CapabilityResult::Allowed));
     }
 
     #[test]
     fn test_empty_allowlist_denies() {
         let checker = CapabilityChecker::new(vec![], vec![], vec![]);
-        assert!(matches!(checker.check_command("ls"), CapabilityResult::Denied(_)));
+        assert!(matches!(checker.check_command("ls", &[]), CapabilityResult::Denied(_)));
         assert!(matches!(checker.check_file_read("/tmp/x"), CapabilityResult::Denied(_)));
     }
 
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_allowed_command_with_denied_argument() {
+        let policies = ArgPolicies::new(
+            &[],
+            &[("bash".into(), args(&["-c", "--rcfile"])), ("find".into(), args(&["-exec", "-delete"]))],
+        );
+        let checker = CapabilityChecker::new(vec![], vec![], args(&["bash", "find", "ls"]))
+            .with_arg_policies(policies);
+
+        assert!(matches!(checker.check_command("bash", &args(&["script.sh"])), CapabilityResult::Allowed));
+        match checker.check_command("bash", &args(&["-c", "rm -rf /"])) {
+            CapabilityResult::Denied(reason) => {
+                assert_eq!(reason, "argument '-c' is not allowed for 'bash' (denied: -c)")
+            }
+            CapabilityResult::Allowed => panic!("bash -c allowed"),
+        }
+        for denied in [&["-ec", "id"][..], &["--rcfile=/tmp/x"], &["-cid"]] {
+            assert!(
+                matches!(checker.check_command("/bin/bash", &args(denied)), CapabilityResult::Denied(_)),
+                "{:?}",
+                denied
+            );
+        }
+        assert!(matches!(checker.check_command("bash", &args(&["--norc", "s.sh"])), CapabilityResult::Allowed));
+        assert!(matches!(
+            checker.check_command("find", &args(&["/tmp", "-name", "*.log", "-delete"])),
+            CapabilityResult::Denied(_)
+        ));
+        assert!(matches!(checker.check_command("find", &args(&["/tmp", "-name", "x"])), CapabilityResult::Allowed));
+        // Commands without a policy take anything
+        assert!(matches!(checker.check_command("ls", &args(&["-c"])), CapabilityResult::Allowed));
+    }
+
+    #[test]
+    fn test_argument_allowlist() {
+        let policies = ArgPolicies::new(&[("git".into(), args(&["status", "log", "diff", "--stat", "-n*"]))], &[]);
+        assert!(matches!(policies.check("git", &args(&["log", "-n5", "--stat"])), CapabilityResult::Allowed));
+        match policies.check("git", &args(&["push", "--force"])) {
+            CapabilityResult::Denied(reason) => {
+                assert_eq!(reason, "argument 'push' is not in the allowlist for 'git'")
+            }
+            CapabilityResult::Allowed => panic!("git push allowed"),
+        }
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("abc", "abc"));
+        assert!(!glob_match("abc", "abcd"));
+        assert!(glob_match("*.log", "a.log"));
+        assert!(glob_match("-n*", "-n"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("a*b*c", "axxbyy"));
+        assert!(!glob_match("ab*ba", "aba"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_network_allowlist() {
+        let checker = CapabilityChecker::new(vec![], vec![], vec![])
+            .with_network_hosts(vec!["api.example.com".into(), "10.0.0.5".into()]);
+        assert!(matches!(checker.check_network("api.example.com", 443), CapabilityResult::Allowed));
+        assert!(matches!(checker.check_network("API.Example.com", 443), CapabilityResult::Allowed));
+        assert!(matches!(checker.check_network("example.com", 443), CapabilityResult::Denied(_)));
+        assert!(matches!(checker.check_network("evil.api.example.com", 443), CapabilityResult::Denied(_)));
+        // Listing an internal address doesn't allow it
+        assert!(matches!(checker.check_network("10.0.0.5", 443), CapabilityResult::Denied(_)));
+
+        let none = CapabilityChecker::new(vec![], vec![], vec![]);
+        assert!(matches!(none.check_network("api.example.com", 443), CapabilityResult::Denied(_)));
+    }
+
+    #[test]
+    fn test_network_wildcards_and_ports() {
+        let checker = CapabilityChecker::new(vec![], vec![], vec![]).with_network_hosts(vec![
+            "*.githubusercontent.com".into(),
+            "api.example.com:8443".into(),
+            "[2606:4700::1111]:443".into(),
+        ]);
+        assert!(matches!(checker.check_network("raw.githubusercontent.com", 443), CapabilityResult::Allowed));
+        assert!(matches!(checker.check_network("a.b.githubusercontent.com", 80), CapabilityResult::Allowed));
+        assert!(matches!(checker.check_network("githubusercontent.com", 443), CapabilityResult::Denied(_)));
+        assert!(matches!(checker.check_network("evilgithubusercontent.com", 443), CapabilityResult::Denied(_)));
+        // An entry with a port allows only that port
+        assert!(matches!(checker.check_network("api.example.com", 8443), CapabilityResult::Allowed));
+        assert!(matches!(checker.check_network("api.example.com", 443), CapabilityResult::Denied(_)));
+        assert!(matches!(checker.check_network("[2606:4700::1111]", 443), CapabilityResult::Allowed));
+        assert!(matches!(checker.check_network("2606:4700::1111", 80), CapabilityResult::Denied(_)));
+
+        assert_eq!(split_port("example.com:8443"), ("example.com", Some(8443)));
+        assert_eq!(split_port("example.com"), ("example.com", None));
+        assert_eq!(split_port("::1"), ("::1", None));
+        assert_eq!(split_port("[::1]:80"), ("[::1]", Some(80)));
+        assert_eq!(split_port("host:http"), ("host:http", None));
+    }
+
+    #[test]
+    fn test_host_and_addr_matching() {
+        assert!(host_matches("api.example.com", "API.example.com"));
+        assert!(!host_matches("api.example.com", "evil.api.example.com"));
+        assert!(host_matches("*.example.com", "a.b.example.com"));
+        assert!(!host_matches("*.example.com", "example.com"));
+        assert!(!host_matches("*.example.com", "badexample.com"));
+        assert!(host_matches("10.0.0.0/8", "10.200.3.4"));
+        assert!(!host_matches("10.0.0.0/8", "11.0.0.1"));
+        assert!(host_matches("::1", "::1"));
+        assert!(host_matches("[::1]", "::1"));
+        assert!(!host_matches("*.example.com", "10.0.0.1"));
+
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert!(addr_matches("192.168.1.0/24", ip("192.168.1.77")));
+        assert!(!addr_matches("192.168.1.0/24", ip("192.168.2.1")));
+        assert!(addr_matches("192.168.1.0/24", ip("::ffff:192.168.1.5")));
+        assert!(addr_matches("fd00::/8", ip("fd12::1")));
+        assert!(addr_matches("0.0.0.0/0", ip("8.8.8.8")));
+        assert!(!addr_matches("10.0.0.0/33", ip("10.0.0.1")));
+        assert!(!addr_matches("db.internal", ip("10.0.0.1")));
+    }
+
+    #[test]
+    fn test_network_allowlist_blocks_unlisted_internal() {
+        let allow = |r| NetworkAllowlist::new(vec!["gateway.lan".into(), "127.0.0.1".into(), "10.1.0.0/16".into()], r);
+        let open = allow(false);
+        assert!(matches!(open.check_host("api.example.com"), CapabilityResult::Allowed));
+        assert!(matches!(open.check_host("127.0.0.1"), CapabilityResult::Allowed));
+        assert!(matches!(open.check_host("10.1.2.3"), CapabilityResult::Allowed));
+        for host in ["localhost", "[::1]", "169.254.169.254", "10.2.0.1"] {
+            assert!(matches!(open.check_host(host), CapabilityResult::Denied(_)), "{}", host);
+        }
+
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        // A listed name may resolve anywhere; others only to listed ranges
+        assert!(matches!(open.check_addrs("gateway.lan", &[ip("192.168.0.2")]), CapabilityResult::Allowed));
+        assert!(matches!(open.check_addrs("x.example", &[ip("10.1.9.9")]), CapabilityResult::Allowed));
+        assert!(matches!(open.check_addrs("x.example", &[ip("93.184.216.34"), ip("192.168.0.2")]), CapabilityResult::Denied(_)));
+        assert!(matches!(open.check_addrs("x.example", &[ip("93.184.216.34")]), CapabilityResult::Allowed));
+
+        let strict = allow(true);
+        assert!(matches!(strict.check_host("gateway.lan"), CapabilityResult::Allowed));
+        assert!(matches!(strict.check_host("api.example.com"), CapabilityResult::Denied(_)));
+    }
+
+    #[test]
+    fn test_network_blocks_internal_addresses() {
+        let checker = CapabilityChecker::new(vec![], vec![], vec![])
+            .with_network_hosts(vec!["localhost".into(), "127.0.0.1".into(), "::1".into()]);
+        for host in ["localhost", "db.localhost", "127.0.0.1", "[::1]", "169.254.169.254", "192.168.1.1", "100.64.0.1", "0.0.0.0"] {
+            assert!(matches!(checker.check_network(host, 443), CapabilityResult::Denied(_)), "{}", host);
+        }
+        assert!(is_internal("fd00::1".parse().unwrap()));
+        assert!(is_internal("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!is_internal("93.184.216.34".parse().unwrap()));
+        assert!(!is_internal("2606:2800:220:1::".parse().unwrap()));
+
+    }
+
+    #[test]
+    fn test_glob_double_star_crosses_directories() {
+        assert!(glob_matches_path("/var/log/**/*.log", "/var/log/syslog.log"));
+        assert!(glob_matches_path("/var/log/**/*.log", "/var/log/nginx/access.log"));
+        assert!(glob_matches_path("/var/log/**/*.log", "/var/log/a/b/c/x.log"));
+        assert!(!glob_matches_path("/var/log/**/*.log", "/var/log/nginx/access.txt"));
+        assert!(glob_matches_path("/srv/**", "/srv/app/data"));
+        assert!(!glob_matches_path("/srv/**", "/srvx/app"));
+    }
+
+    #[test]
+    fn test_glob_single_star_stays_in_directory() {
+        assert!(glob_matches_path("/var/log/*.log", "/var/log/syslog.log"));
+        assert!(!glob_matches_path("/var/log/*.log", "/var/log/nginx/access.log"));
+        assert!(glob_matches_path("/home/*/notes", "/home/ann/notes"));
+        assert!(!glob_matches_path("/home/*/notes", "/home/ann/x/notes"));
+        assert!(glob_matches_path("/tmp/file?.txt", "/tmp/file1.txt"));
+        assert!(glob_matches_path("/tmp/file?.txt", "/tmp/fileé.txt"));
+        assert!(!glob_matches_path("/tmp/file?.txt", "/tmp/file.txt"));
+        assert!(!glob_matches_path("/tmp/?", "/tmp/"));
+    }
+
+    #[test]
+    fn test_glob_base() {
+        assert_eq!(glob_base("/var/log/**/*.log"), "/var/log");
+        assert_eq!(glob_base("/var/lo*/x"), "/var");
+        assert_eq!(glob_base("/*.txt"), "/");
+        assert_eq!(glob_base("/tmp"), "/tmp");
+    }
+
+    #[test]
+    fn test_glob_allowlist() {
+        let dir = "/tmp/sentinel_test_glob_paths";
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(format!("{}/logs/app", dir)).unwrap();
+        std::fs::create_dir_all(format!("{}/private", dir)).unwrap();
+        std::fs::write(format!("{}/logs/app/today.log", dir), "").unwrap();
+        std::fs::write(format!("{}/logs/app/notes.txt", dir), "").unwrap();
+        std::fs::write(format!("{}/private/key.log", dir), "").unwrap();
+
+        let checker = CapabilityChecker::new(vec![format!("{}/**/*.log", dir)], vec![], vec![])
+            .with_denied_paths(vec![format!("{}/private", dir)], vec![]);
+        let read = |p: &str| checker.check_file_read(&format!("{}/{}", dir, p));
+        assert!(matches!(read("logs/app/today.log"), CapabilityResult::Allowed));
+        assert!(matches!(read("logs/app/notes.txt"), CapabilityResult::Denied(_)));
+        // Excluded even though the glob matches
+        assert!(matches!(read("private/key.log"), CapabilityResult::Denied(_)));
+        // `..` is resolved before matching
+        assert!(matches!(read("logs/app/../app/today.log"), CapabilityResult::Allowed));
+        assert!(matches!(read("logs/../private/key.log"), CapabilityResult::Denied(_)));
+
+        // Plain prefixes still work alongside
+        let checker = CapabilityChecker::new(vec![dir.to_string()], vec![], vec![]);
+        assert!(matches!(checker.check_file_read(&format!("{}/logs/app/notes.txt", dir)), CapabilityResult::Allowed));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_denied_paths_override_allowed() {
+        let home = "/tmp/sentinel_test_denied_home";
+        let _ = std::fs::remove_dir_all(home);
+        std::fs::create_dir_all(format!("{}/.ssh", home)).unwrap();
+        std::fs::write(format!("{}/.ssh/id_rsa", home), "key").unwrap();
+        std::fs::write(format!("{}/notes.txt", home), "notes").unwrap();
+        // A symlink out of the allowed area into the denied one
+        let _ = std::os::unix::fs::symlink(format!("{}/.ssh", home), format!("{}/keys", home));
+
+        let checker = CapabilityChecker::new(vec![home.into()], vec![home.into()], vec![])
+            .with_denied_paths(vec![format!("{}/.ssh", home)], vec![format!("{}/.ssh", home)]);
+        assert!(matches!(checker.check_file_read(&format!("{}/notes.txt", home)), CapabilityResult::Allowed));
+        match checker.check_file_read(&format!("{}/.ssh/id_rsa", home)) {
+            CapabilityResult::Denied(reason) => assert!(reason.contains("under denied path"), "{}", reason),
+            CapabilityResult::Allowed => panic!(".ssh/id_rsa readable"),
+        }
+        assert!(matches!(checker.check_file_read(&format!("{}/keys/id_rsa", home)), CapabilityResult::Denied(_)));
+        assert!(matches!(checker.check_file_write(&format!("{}/.ssh/authorized_keys", home)), CapabilityResult::Denied(_)));
+        assert!(matches!(checker.check_file_write(&format!("{}/new.txt", home)), CapabilityResult::Allowed));
+        let _ = std::fs::remove_dir_all(home);
+    }
+
     #[test]
     fn test_path_check() {
         let checker = CapabilityChecker::new(vec!["/tmp".into()], vec![], vec![]);
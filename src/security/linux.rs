@@ -9,17 +9,31 @@
 
 use std::ffi::CString;
 use std::io;
-use std::path::Path;
+
+use crate::security::capability::glob_base;
 
 // ============================================================================
-// Syscall numbers (x86_64)
+// Per-architecture syscall ABI
 // ============================================================================
 
-const SYS_PRCTL: i64 = 157;
-const SYS_SECCOMP: i64 = 317;
-const SYS_LANDLOCK_CREATE_RULESET: i64 = 444;
-const SYS_LANDLOCK_ADD_RULE: i64 = 445;
-const SYS_LANDLOCK_RESTRICT_SELF: i64 = 446;
+// Syscall numbers, the audit architecture the seccomp filter checks, and the
+// raw syscall instruction all differ between architectures. Selecting them at
+// compile time means an x86_64 filter can never be applied on ARM.
+#[cfg(target_arch = "x86_64")]
+mod x86_64;
+#[cfg(target_arch = "x86_64")]
+use x86_64 as arch;
+#[cfg(target_arch = "aarch64")]
+mod aarch64;
+#[cfg(target_arch = "aarch64")]
+use aarch64 as arch;
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+compile_error!("the Linux sandbox supports x86_64 and aarch64 only");
+
+use arch::{
+    prctl, prlimit, syscall3, ALLOWED_SYSCALLS, AUDIT_ARCH, SYS_GETPID, SYS_LANDLOCK_ADD_RULE,
+    SYS_LANDLOCK_CREATE_RULESET, SYS_LANDLOCK_RESTRICT_SELF, SYS_SECCOMP, SYS_SOCKET,
+};
 
 // ============================================================================
 // prctl constants
@@ -34,6 +48,7 @@ const PR_SET_NO_NEW_PRIVS: i32 = 38;
 const SECCOMP_SET_MODE_FILTER: u32 = 1;
 const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
 const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const SECCOMP_RET_LOG: u32 = 0x7ffc_0000;
 const EPERM: u32 = 1;
 
 // BPF instruction opcodes
@@ -65,7 +80,6 @@ struct SockFprog {
 // seccomp_data offsets
 const SECCOMP_DATA_NR: u32 = 0; // offset of syscall number in seccomp_data
 const SECCOMP_DATA_ARCH: u32 = 4; // offset of architecture in seccomp_data
-const AUDIT_ARCH_X86_64: u32 = 0xC000003E;
 
 // ============================================================================
 // Landlock constants and structures
@@ -84,18 +98,31 @@ const LANDLOCK_ACCESS_FS_MAKE_SOCK: u64 = 1 << 9;
 const LANDLOCK_ACCESS_FS_MAKE_FIFO: u64 = 1 << 10;
 const LANDLOCK_ACCESS_FS_MAKE_BLOCK: u64 = 1 << 11;
 const LANDLOCK_ACCESS_FS_MAKE_SYM: u64 = 1 << 12;
+/// ABI v2: link or rename a file into a different directory.
+const LANDLOCK_ACCESS_FS_REFER: u64 = 1 << 13;
+/// ABI v3: truncate a file.
+const LANDLOCK_ACCESS_FS_TRUNCATE: u64 = 1 << 14;
 
 const LANDLOCK_RULE_PATH_BENEATH: u32 = 1;
 
 /// All read access flags.
 const LANDLOCK_READ_ALL: u64 = LANDLOCK_ACCESS_FS_READ_FILE | LANDLOCK_ACCESS_FS_READ_DIR;
 
-/// All write access flags.
+/// All write access flags. Rights the kernel doesn't handle are masked off
+/// per rule (see `handled_access_fs`).
 const LANDLOCK_WRITE_ALL: u64 = LANDLOCK_ACCESS_FS_WRITE_FILE
     | LANDLOCK_ACCESS_FS_REMOVE_DIR
     | LANDLOCK_ACCESS_FS_REMOVE_FILE
     | LANDLOCK_ACCESS_FS_MAKE_DIR
-    | LANDLOCK_ACCESS_FS_MAKE_REG;
+    | LANDLOCK_ACCESS_FS_MAKE_REG
+    | LANDLOCK_ACCESS_FS_REFER
+    | LANDLOCK_ACCESS_FS_TRUNCATE;
+
+/// The rights a rule on a file (rather than a directory) may grant.
+const LANDLOCK_FILE_ACCESS: u64 = LANDLOCK_ACCESS_FS_EXECUTE
+    | LANDLOCK_ACCESS_FS_WRITE_FILE
+    | LANDLOCK_ACCESS_FS_READ_FILE
+    | LANDLOCK_ACCESS_FS_TRUNCATE;
 
 /// All access flags for landlock v1.
 const LANDLOCK_ALL_V1: u64 = LANDLOCK_ACCESS_FS_EXECUTE
@@ -112,6 +139,21 @@ const LANDLOCK_ALL_V1: u64 = LANDLOCK_ACCESS_FS_EXECUTE
     | LANDLOCK_ACCESS_FS_MAKE_BLOCK
     | LANDLOCK_ACCESS_FS_MAKE_SYM;
 
+/// The filesystem rights to handle for a kernel supporting landlock `abi`.
+/// A ruleset naming rights the kernel doesn't know is refused with EINVAL.
+/// Later ABIs add ioctl on devices (v5), which stays unhandled and so
+/// allowed, as it always was before.
+fn handled_access_fs(abi: i64) -> u64 {
+    let mut access = LANDLOCK_ALL_V1;
+    if abi >= 2 {
+        access |= LANDLOCK_ACCESS_FS_REFER;
+    }
+    if abi >= 3 {
+        access |= LANDLOCK_ACCESS_FS_TRUNCATE;
+    }
+    access
+}
+
 #[repr(C)]
 struct LandlockRulesetAttr {
     handled_access_fs: u64,
@@ -124,36 +166,37 @@ struct LandlockPathBeneathAttr {
 }
 
 // ============================================================================
-// Raw syscall wrapper
+// Public API
 // ============================================================================
 
-unsafe fn syscall3(num: i64, a1: i64, a2: i64, a3: i64) -> i64 {
-    let ret: i64;
-    std::arch::asm!(
-        "syscall",
-        inlateout("rax") num => ret,
-        in("rdi") a1,
-        in("rsi") a2,
-        in("rdx") a3,
-        lateout("rcx") _,
-        lateout("r11") _,
-        options(nostack),
-    );
-    ret
+/// What the seccomp filter does with a syscall outside the allowlist.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeccompMode {
+    /// Fail it with EPERM.
+    Enforce,
+    /// Let it through and have the kernel log it (dmesg / auditd), to find
+    /// syscalls the allowlist is missing before enforcing.
+    Log,
 }
 
-unsafe fn syscall2(num: i64, a1: i64, a2: i64) -> i64 {
-    syscall3(num, a1, a2, 0)
+impl SeccompMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "enforce" => Some(SeccompMode::Enforce),
+            "log" => Some(SeccompMode::Log),
+            _ => None,
+        }
+    }
 }
 
-// ============================================================================
-// Public API
-// ============================================================================
-
 /// Result of sandbox setup.
 pub struct SandboxResult {
     pub seccomp_applied: bool,
     pub landlock_applied: bool,
+    /// The landlock ABI version the kernel reports, `None` without landlock.
+    pub landlock_abi: Option<i64>,
+    /// Set together with `seccomp_applied` when the filter is in place but
+    /// failed its self-test: the process can't be trusted to keep working.
     pub seccomp_error: Option<String>,
     pub landlock_error: Option<String>,
 }
@@ -163,26 +206,36 @@ pub struct SandboxResult {
 /// `read_paths`: paths the agent can read from
 /// `write_paths`: paths the agent can write to
 /// `enable_seccomp`: whether to apply seccomp BPF filter
+/// `seccomp_mode`: whether the filter blocks or only logs other syscalls
 /// `enable_landlock`: whether to apply landlock filesystem restrictions
 pub fn apply_sandbox(
     read_paths: &[String],
     write_paths: &[String],
     enable_seccomp: bool,
+    seccomp_mode: SeccompMode,
     enable_landlock: bool,
 ) -> SandboxResult {
     let mut result = SandboxResult {
         seccomp_applied: false,
         landlock_applied: false,
+        landlock_abi: landlock_abi(),
         seccomp_error: None,
         landlock_error: None,
     };
 
     // Landlock must be applied BEFORE seccomp (seccomp may block landlock syscalls)
     if enable_landlock {
-        match apply_landlock(read_paths, write_paths) {
-            Ok(()) => {
+        let applied = match result.landlock_abi {
+            Some(abi) => apply_landlock(abi, read_paths, write_paths).map(|()| abi),
+            None => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the kernel does not support landlock (Linux 5.13+, enabled in the LSM list)",
+            )),
+        };
+        match applied {
+            Ok(abi) => {
                 result.landlock_applied = true;
-                eprintln!("sentinel: landlock sandbox active");
+                eprintln!("sentinel: landlock sandbox active (ABI v{})", abi);
             }
             Err(e) => {
                 result.landlock_error = Some(format!("{}", e));
@@ -192,10 +245,23 @@ pub fn apply_sandbox(
     }
 
     if enable_seccomp {
-        match apply_seccomp() {
+        match apply_seccomp(seccomp_mode) {
             Ok(()) => {
                 result.seccomp_applied = true;
-                eprintln!("sentinel: seccomp sandbox active");
+                // A filter that refuses what it should allow fails silently
+                // everywhere, so check one known-allowed syscall right away
+                if let Err(e) = seccomp_self_test() {
+                    eprintln!("sentinel: seccomp self-test failed: {}", e);
+                    result.seccomp_error = Some(e);
+                    return result;
+                }
+                match seccomp_mode {
+                    SeccompMode::Enforce => eprintln!("sentinel: seccomp sandbox active"),
+                    SeccompMode::Log => eprintln!(
+                        "sentinel: seccomp sandbox in log mode: syscalls outside the \
+                         allowlist are allowed and logged by the kernel, not blocked"
+                    ),
+                }
             }
             Err(e) => {
                 result.seccomp_error = Some(format!("{}", e));
@@ -207,107 +273,158 @@ pub fn apply_sandbox(
     result
 }
 
+// ============================================================================
+// Resource limits
+// ============================================================================
+
+// The same on x86_64 and aarch64
+pub const RLIMIT_CPU: u32 = 0;
+pub const RLIMIT_NPROC: u32 = 6;
+pub const RLIMIT_AS: u32 = 9;
+
+/// Set the soft and hard limit on `resource` for the calling process. Only
+/// makes a syscall, so it can run in `CommandExt::pre_exec`.
+pub fn set_rlimit(resource: u32, value: u64) -> Result<(), io::Error> {
+    let limit = [value, value];
+    let ret = unsafe { prlimit(resource, limit.as_ptr()) };
+    if ret != 0 {
+        return Err(io::Error::from_raw_os_error(-ret as i32));
+    }
+    Ok(())
+}
+
+/// Restrictions for a child process, prepared in the parent and applied in
+/// the child between fork and exec (see `apply`).
+pub struct ChildSandbox {
+    /// Landlock ruleset with the child's rules; `None` where landlock isn't
+    /// supported.
+    ruleset_fd: Option<i32>,
+    /// seccomp filter refusing IP sockets, unless the child may use the network.
+    net_filter: Option<Vec<SockFilter>>,
+}
+
+impl ChildSandbox {
+    /// Build the rules for a child that may read `read_paths`, read and
+    /// write `write_paths`, and execute `exec_paths` (files or directories),
+    /// plus the system libraries every program needs.
+    pub fn prepare(
+        read_paths: &[String],
+        write_paths: &[String],
+        exec_paths: &[String],
+        network: bool,
+    ) -> Result<Self, io::Error> {
+        let net_filter = if network { None } else { Some(build_no_network_filter()) };
+        let Some(abi) = landlock_abi() else {
+            // An old kernel: keep the network restriction at least
+            eprintln!("sentinel: landlock not available; child file access is unrestricted");
+            return Ok(ChildSandbox { ruleset_fd: None, net_filter });
+        };
+        let handled = handled_access_fs(abi);
+        let ruleset_fd = create_ruleset(handled)?;
+        let sandbox = ChildSandbox { ruleset_fd: Some(ruleset_fd), net_filter };
+
+        for path in read_paths {
+            add_landlock_path_rule(ruleset_fd, handled, path, LANDLOCK_READ_ALL)?;
+        }
+        for path in write_paths {
+            add_landlock_path_rule(
+                ruleset_fd,
+                handled,
+                path,
+                LANDLOCK_READ_ALL | LANDLOCK_WRITE_ALL,
+            )?;
+        }
+        for path in exec_paths {
+            add_landlock_path_rule(
+                ruleset_fd,
+                handled,
+                path,
+                LANDLOCK_READ_ALL | LANDLOCK_ACCESS_FS_EXECUTE,
+            )?;
+        }
+        // The dynamic loader and shared libraries
+        for path in ["/lib", "/lib64", "/usr/lib", "/usr/lib64", "/usr/libexec"] {
+            let _ = add_landlock_path_rule(
+                ruleset_fd,
+                handled,
+                path,
+                LANDLOCK_READ_ALL | LANDLOCK_ACCESS_FS_EXECUTE,
+            );
+        }
+        let mut system_read = vec!["/etc/ld.so.cache", "/etc/localtime", "/dev/urandom", "/proc/self"];
+        if network {
+            system_read.extend(["/etc/resolv.conf", "/etc/hosts", "/etc/nsswitch.conf", "/etc/ssl", "/etc/ca-certificates", "/usr/share/ca-certificates"]);
+        }
+        for path in system_read {
+            let _ = add_landlock_path_rule(ruleset_fd, handled, path, LANDLOCK_READ_ALL);
+        }
+        let _ = add_landlock_path_rule(
+            ruleset_fd,
+            handled,
+            "/dev/null",
+            // `> /dev/null` opens with O_TRUNC
+            LANDLOCK_ACCESS_FS_READ_FILE
+                | LANDLOCK_ACCESS_FS_WRITE_FILE
+                | LANDLOCK_ACCESS_FS_TRUNCATE,
+        );
+        Ok(sandbox)
+    }
+
+    /// Restrict the calling process. Meant for `CommandExt::pre_exec`: it
+    /// only makes syscalls, with everything it needs built beforehand.
+    pub fn apply(&self) -> Result<(), io::Error> {
+        let ret = unsafe { prctl(PR_SET_NO_NEW_PRIVS, 1) };
+        if ret != 0 {
+            return Err(io::Error::from_raw_os_error(-ret as i32));
+        }
+        if let Some(fd) = self.ruleset_fd {
+            let ret = unsafe { syscall3(SYS_LANDLOCK_RESTRICT_SELF, fd as i64, 0, 0) };
+            if ret != 0 {
+                return Err(io::Error::from_raw_os_error(-ret as i32));
+            }
+        }
+        if let Some(ref filter) = self.net_filter {
+            let prog = SockFprog {
+                len: filter.len() as u16,
+                filter: filter.as_ptr(),
+            };
+            let ret = unsafe {
+                syscall3(
+                    SYS_SECCOMP,
+                    SECCOMP_SET_MODE_FILTER as i64,
+                    0,
+                    &prog as *const SockFprog as i64,
+                )
+            };
+            if ret != 0 {
+                return Err(io::Error::from_raw_os_error(-ret as i32));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ChildSandbox {
+    fn drop(&mut self) {
+        if let Some(fd) = self.ruleset_fd {
+            unsafe { close_fd(fd); }
+        }
+    }
+}
+
 // ============================================================================
 // seccomp BPF implementation
 // ============================================================================
 
-/// Syscalls allowed by the seccomp filter.
-/// This is the minimum set needed for Sentinel's operation.
-const ALLOWED_SYSCALLS: &[u32] = &[
-    // File I/O
-    0,   // read
-    1,   // write
-    2,   // open
-    3,   // close
-    4,   // stat
-    5,   // fstat
-    6,   // lstat
-    7,   // poll
-    8,   // lseek
-    9,   // mmap
-    10,  // mprotect
-    11,  // munmap
-    12,  // brk
-    13,  // rt_sigaction
-    14,  // rt_sigprocmask
-    16,  // ioctl (needed for terminal)
-    17,  // pread64
-    18,  // pwrite64
-    19,  // readv
-    20,  // writev
-    21,  // access
-    24,  // sched_yield
-    28,  // madvise
-    32,  // dup
-    33,  // dup2
-    35,  // nanosleep
-    39,  // getpid
-    41,  // socket
-    42,  // connect
-    44,  // sendto
-    45,  // recvfrom
-    46,  // sendmsg
-    47,  // recvmsg
-    49,  // bind (for localhost connections)
-    54,  // setsockopt
-    55,  // getsockopt
-    56,  // clone (for thread creation)
-    59,  // execve (for run_command tool)
-    60,  // exit
-    61,  // wait4
-    62,  // kill (for process timeout)
-    72,  // fcntl
-    78,  // getdents
-    79,  // getcwd
-    80,  // chdir
-    89,  // readlink
-    96,  // gettimeofday
-    97,  // getrlimit
-    102, // getuid
-    104, // getgid
-    107, // geteuid
-    108, // getegid
-    110, // getppid
-    131, // sigaltstack
-    137, // statfs
-    157, // prctl
-    158, // arch_prctl
-    202, // futex
-    217, // getdents64
-    218, // set_tid_address
-    228, // clock_gettime
-    230, // clock_nanosleep
-    231, // exit_group
-    233, // epoll_wait
-    234, // tgkill
-    257, // openat
-    262, // newfstatat
-    270, // pselect6
-    271, // ppoll
-    273, // set_robust_list
-    281, // epoll_pwait
-    288, // accept4
-    291, // epoll_create1
-    292, // dup3
-    293, // pipe2
-    302, // prlimit64
-    309, // getcpu
-    318, // getrandom
-    332, // statx
-    334, // rseq
-    435, // clone3
-    439, // faccessat2
-];
-
-fn apply_seccomp() -> Result<(), io::Error> {
+fn apply_seccomp(mode: SeccompMode) -> Result<(), io::Error> {
     // Step 1: Set NO_NEW_PRIVS (required before seccomp filter)
-    let ret = unsafe { syscall2(SYS_PRCTL, PR_SET_NO_NEW_PRIVS as i64, 1) };
+    let ret = unsafe { prctl(PR_SET_NO_NEW_PRIVS, 1) };
     if ret != 0 {
         return Err(io::Error::from_raw_os_error(-ret as i32));
     }
 
     // Step 2: Build BPF filter
-    let filter = build_seccomp_filter();
+    let filter = build_seccomp_filter(mode);
 
     // Step 3: Apply seccomp filter
     let prog = SockFprog {
@@ -331,10 +448,24 @@ fn apply_seccomp() -> Result<(), io::Error> {
     Ok(())
 }
 
-fn build_seccomp_filter() -> Vec<SockFilter> {
+/// Check that an allowed syscall still goes through the installed filter.
+/// getpid is on every architecture's allowlist and can't otherwise fail.
+fn seccomp_self_test() -> Result<(), String> {
+    let pid = unsafe { syscall3(SYS_GETPID, 0, 0, 0) };
+    if pid != std::process::id() as i64 {
+        return Err(format!(
+            "getpid returned {} instead of {}; the filter is refusing allowed syscalls",
+            pid,
+            std::process::id()
+        ));
+    }
+    Ok(())
+}
+
+fn build_seccomp_filter(mode: SeccompMode) -> Vec<SockFilter> {
     let mut filter = Vec::with_capacity(ALLOWED_SYSCALLS.len() + 5);
 
-    // Verify architecture is x86_64
+    // Verify the architecture is the one the syscall numbers are for
     filter.push(SockFilter {
         code: BPF_LD | BPF_W | BPF_ABS,
         jt: 0,
@@ -345,7 +476,7 @@ fn build_seccomp_filter() -> Vec<SockFilter> {
         code: BPF_JMP | BPF_JEQ | BPF_K,
         jt: 1,
         jf: 0,
-        k: AUDIT_ARCH_X86_64,
+        k: AUDIT_ARCH,
     });
     // Kill on wrong architecture
     filter.push(SockFilter {
@@ -375,12 +506,15 @@ fn build_seccomp_filter() -> Vec<SockFilter> {
         });
     }
 
-    // Default: DENY with EPERM
+    // Default: DENY with EPERM, or allow and log
     filter.push(SockFilter {
         code: BPF_RET | BPF_K,
         jt: 0,
         jf: 0,
-        k: SECCOMP_RET_ERRNO | EPERM,
+        k: match mode {
+            SeccompMode::Enforce => SECCOMP_RET_ERRNO | EPERM,
+            SeccompMode::Log => SECCOMP_RET_LOG,
+        },
     });
 
     // ALLOW
@@ -394,17 +528,47 @@ fn build_seccomp_filter() -> Vec<SockFilter> {
     filter
 }
 
+const AF_INET: u32 = 2;
+const AF_INET6: u32 = 10;
+/// Offset of the first syscall argument in seccomp_data.
+const SECCOMP_DATA_ARG0: u32 = 16;
+
+/// A filter failing `socket(AF_INET|AF_INET6, ...)` with EPERM. Unix
+/// sockets still work.
+fn build_no_network_filter() -> Vec<SockFilter> {
+    let deny = SECCOMP_RET_ERRNO | EPERM;
+    let stmt = |code: u16, k: u32| SockFilter { code, jt: 0, jf: 0, k };
+    let jeq = |k: u32, jt: u8, jf: u8| SockFilter { code: BPF_JMP | BPF_JEQ | BPF_K, jt, jf, k };
+    vec![
+        stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_ARCH),
+        jeq(AUDIT_ARCH, 1, 0),
+        stmt(BPF_RET | BPF_K, deny),
+        stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_NR),
+        jeq(SYS_SOCKET, 0, 4),
+        stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_ARG0),
+        jeq(AF_INET, 1, 0),
+        jeq(AF_INET6, 0, 1),
+        stmt(BPF_RET | BPF_K, deny),
+        stmt(BPF_RET | BPF_K, SECCOMP_RET_ALLOW),
+    ]
+}
+
 // ============================================================================
 // Landlock implementation
 // ============================================================================
 
-fn apply_landlock(read_paths: &[String], write_paths: &[String]) -> Result<(), io::Error> {
-    // Step 1: Create ruleset
+/// The landlock ABI version the kernel supports, if any.
+pub fn landlock_abi() -> Option<i64> {
+    // LANDLOCK_CREATE_RULESET_VERSION
+    let ret = unsafe { syscall3(SYS_LANDLOCK_CREATE_RULESET, 0, 0, 1) };
+    (ret > 0).then_some(ret)
+}
+
+fn create_ruleset(handled: u64) -> Result<i32, io::Error> {
     let attr = LandlockRulesetAttr {
-        handled_access_fs: LANDLOCK_ALL_V1,
+        handled_access_fs: handled,
     };
-
-    let ruleset_fd = unsafe {
+    let fd = unsafe {
         syscall3(
             SYS_LANDLOCK_CREATE_RULESET,
             &attr as *const LandlockRulesetAttr as i64,
@@ -412,24 +576,38 @@ fn apply_landlock(read_paths: &[String], write_paths: &[String]) -> Result<(), i
             0,
         )
     };
-
-    if ruleset_fd < 0 {
-        return Err(io::Error::from_raw_os_error(-ruleset_fd as i32));
+    if fd < 0 {
+        return Err(io::Error::from_raw_os_error(-fd as i32));
     }
+    Ok(fd as i32)
+}
+
+fn apply_landlock(abi: i64, read_paths: &[String], write_paths: &[String]) -> Result<(), io::Error> {
+    // Step 1: Create ruleset with the rights this kernel knows about
+    let handled = handled_access_fs(abi);
+    let ruleset_fd = create_ruleset(handled)?;
 
-    let ruleset_fd = ruleset_fd as i32;
+    // Landlock grants whole directories: a glob entry opens its base
+    // directory, which the allowlist still narrows (as do denied paths,
+    // which landlock can't express)
+    let roots = |paths: &'_ [String]| -> Vec<String> {
+        paths.iter().map(|p| glob_base(p).to_string()).collect()
+    };
+    let read_paths = roots(read_paths);
+    let write_paths = roots(write_paths);
 
     // Step 2: Add read-only rules
-    for path in read_paths {
-        if let Err(e) = add_landlock_path_rule(ruleset_fd, path, LANDLOCK_READ_ALL) {
+    for path in &read_paths {
+        if let Err(e) = add_landlock_path_rule(ruleset_fd, handled, path, LANDLOCK_READ_ALL) {
             eprintln!("sentinel: landlock: failed to add read rule for {}: {}", path, e);
         }
     }
 
     // Step 3: Add read-write rules
-    for path in write_paths {
+    for path in &write_paths {
         if let Err(e) = add_landlock_path_rule(
             ruleset_fd,
+            handled,
             path,
             LANDLOCK_READ_ALL | LANDLOCK_WRITE_ALL,
         ) {
@@ -450,7 +628,7 @@ fn apply_landlock(read_paths: &[String], write_paths: &[String]) -> Result<(), i
         "/proc/self",
     ];
     for path in &system_read_paths {
-        let _ = add_landlock_path_rule(ruleset_fd, path, LANDLOCK_READ_ALL);
+        let _ = add_landlock_path_rule(ruleset_fd, handled, path, LANDLOCK_READ_ALL);
     }
 
     // Allow executing standard paths for run_command tool
@@ -458,6 +636,7 @@ fn apply_landlock(read_paths: &[String], write_paths: &[String]) -> Result<(), i
     for path in &exec_paths {
         let _ = add_landlock_path_rule(
             ruleset_fd,
+            handled,
             path,
             LANDLOCK_READ_ALL | LANDLOCK_ACCESS_FS_EXECUTE,
         );
@@ -466,12 +645,13 @@ fn apply_landlock(read_paths: &[String], write_paths: &[String]) -> Result<(), i
     // Allow /tmp for temporary files
     let _ = add_landlock_path_rule(
         ruleset_fd,
+        handled,
         "/tmp",
         LANDLOCK_READ_ALL | LANDLOCK_WRITE_ALL,
     );
 
     // Step 4: Set NO_NEW_PRIVS (required for landlock)
-    let ret = unsafe { syscall2(SYS_PRCTL, PR_SET_NO_NEW_PRIVS as i64, 1) };
+    let ret = unsafe { prctl(PR_SET_NO_NEW_PRIVS, 1) };
     if ret != 0 {
         unsafe { close_fd(ruleset_fd); }
         return Err(io::Error::from_raw_os_error(-ret as i32));
@@ -491,15 +671,23 @@ fn apply_landlock(read_paths: &[String], write_paths: &[String]) -> Result<(), i
     Ok(())
 }
 
+/// Grant `access` beneath `path`, limited to the ruleset's `handled` rights.
 fn add_landlock_path_rule(
     ruleset_fd: i32,
+    handled: u64,
     path: &str,
     access: u64,
 ) -> Result<(), io::Error> {
     // Verify path exists before trying to add rule
-    if !Path::new(path).exists() {
+    let Ok(meta) = std::fs::metadata(path) else {
         return Ok(()); // Skip non-existent paths silently
-    }
+    };
+    // Rules on a file may only grant the rights that apply to files
+    let access = if meta.is_dir() {
+        access & handled
+    } else {
+        access & handled & LANDLOCK_FILE_ACCESS
+    };
 
     let c_path = CString::new(path.as_bytes()).map_err(|_| {
         io::Error::new(io::ErrorKind::InvalidInput, "path contains null byte")
@@ -550,3 +738,62 @@ unsafe fn libc_open(path: *const i8, flags: i32) -> i32 {
 unsafe fn close_fd(fd: i32) {
     close(fd);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seccomp_default_action_follows_mode() {
+        // The second-to-last instruction handles syscalls not on the list
+        let default_action = |mode| {
+            let filter = build_seccomp_filter(mode);
+            filter[filter.len() - 2].k
+        };
+        assert_eq!(default_action(SeccompMode::Enforce), SECCOMP_RET_ERRNO | EPERM);
+        assert_eq!(default_action(SeccompMode::Log), SECCOMP_RET_LOG);
+        // A foreign architecture is refused either way
+        assert_eq!(build_seccomp_filter(SeccompMode::Log)[2].k, SECCOMP_RET_ERRNO | EPERM);
+    }
+
+    #[test]
+    fn test_seccomp_filter_checks_native_arch() {
+        let filter = build_seccomp_filter(SeccompMode::Enforce);
+        assert_eq!(filter[1].k, AUDIT_ARCH);
+        assert!(ALLOWED_SYSCALLS.contains(&(SYS_GETPID as u32)));
+    }
+
+    #[test]
+    fn test_seccomp_self_test_passes_under_filter() {
+        let abi = landlock_abi();
+        // Without TSYNC the filter only binds the thread that installs it
+        let outcome = std::thread::spawn(move || {
+            apply_seccomp(SeccompMode::Enforce).map_err(|e| e.to_string())?;
+            seccomp_self_test()?;
+            // Something off the list is refused (without the filter, an
+            // unknown number fails with ENOSYS instead)
+            let ret = unsafe { syscall3(1000, 0, 0, 0) };
+            // Child sandboxes are still built under the agent's filter
+            Ok::<_, String>((ret, landlock_abi()))
+        })
+        .join()
+        .unwrap();
+        assert_eq!(outcome, Ok((-(EPERM as i64), abi)));
+    }
+
+    #[test]
+    fn test_landlock_handled_access_follows_abi() {
+        assert_eq!(handled_access_fs(1), LANDLOCK_ALL_V1);
+        assert_eq!(handled_access_fs(2), LANDLOCK_ALL_V1 | LANDLOCK_ACCESS_FS_REFER);
+        let v3 = LANDLOCK_ALL_V1 | LANDLOCK_ACCESS_FS_REFER | LANDLOCK_ACCESS_FS_TRUNCATE;
+        assert_eq!(handled_access_fs(3), v3);
+        assert_eq!(handled_access_fs(6), v3);
+    }
+
+    #[test]
+    fn test_seccomp_mode_parse() {
+        assert_eq!(SeccompMode::parse("enforce"), Some(SeccompMode::Enforce));
+        assert_eq!(SeccompMode::parse("log"), Some(SeccompMode::Log));
+        assert_eq!(SeccompMode::parse("audit"), None);
+    }
+}
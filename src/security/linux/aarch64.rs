@@ -0,0 +1,162 @@
+//! aarch64 syscall numbers, seccomp allowlist, and raw syscall wrappers.
+//!
+//! aarch64 uses the generic syscall table, which has no legacy calls like
+//! open, stat, poll or dup2: libc goes through openat, newfstatat, ppoll and
+//! dup3 instead, so the allowlist mirrors the x86_64 one through those.
+
+// ============================================================================
+// Syscall numbers
+// ============================================================================
+
+const SYS_PRCTL: i64 = 167;
+const SYS_PRLIMIT64: i64 = 261;
+pub(super) const SYS_SECCOMP: i64 = 277;
+pub(super) const SYS_GETPID: i64 = 172;
+pub(super) const SYS_LANDLOCK_CREATE_RULESET: i64 = 444;
+pub(super) const SYS_LANDLOCK_ADD_RULE: i64 = 445;
+pub(super) const SYS_LANDLOCK_RESTRICT_SELF: i64 = 446;
+pub(super) const SYS_SOCKET: u32 = 198;
+
+/// AUDIT_ARCH_AARCH64, as reported in seccomp_data.arch.
+pub(super) const AUDIT_ARCH: u32 = 0xC00000B7;
+
+// ============================================================================
+// seccomp allowlist
+// ============================================================================
+
+/// Syscalls allowed by the seccomp filter.
+/// This is the minimum set needed for Sentinel's operation.
+pub(super) const ALLOWED_SYSCALLS: &[u32] = &[
+    17,  // getcwd
+    20,  // epoll_create1
+    22,  // epoll_pwait
+    23,  // dup
+    24,  // dup3
+    25,  // fcntl
+    29,  // ioctl (needed for terminal)
+    34,  // mkdirat (create_directory tool)
+    35,  // unlinkat (delete_file tool)
+    43,  // statfs
+    46,  // ftruncate (saving /setprompt overrides)
+    48,  // faccessat
+    49,  // chdir
+    56,  // openat
+    57,  // close
+    59,  // pipe2
+    61,  // getdents64
+    62,  // lseek
+    63,  // read
+    64,  // write
+    65,  // readv
+    66,  // writev
+    67,  // pread64
+    68,  // pwrite64
+    72,  // pselect6
+    73,  // ppoll
+    78,  // readlinkat
+    79,  // newfstatat
+    80,  // fstat
+    93,  // exit
+    94,  // exit_group
+    96,  // set_tid_address
+    98,  // futex
+    99,  // set_robust_list
+    101, // nanosleep
+    113, // clock_gettime
+    115, // clock_nanosleep
+    124, // sched_yield
+    129, // kill (for process timeout)
+    131, // tgkill
+    132, // sigaltstack
+    134, // rt_sigaction
+    135, // rt_sigprocmask
+    139, // rt_sigreturn (return from the SIGHUP handler)
+    163, // getrlimit
+    167, // prctl
+    168, // getcpu
+    169, // gettimeofday
+    172, // getpid
+    173, // getppid
+    174, // getuid
+    175, // geteuid
+    176, // getgid
+    177, // getegid
+    198, // socket
+    199, // socketpair (std's spawn path when a child is sandboxed)
+    200, // bind (for localhost connections)
+    203, // connect
+    206, // sendto
+    207, // recvfrom
+    208, // setsockopt
+    209, // getsockopt
+    211, // sendmsg
+    212, // recvmsg
+    214, // brk
+    215, // munmap
+    220, // clone (for thread creation)
+    221, // execve (for run_command tool)
+    222, // mmap
+    226, // mprotect
+    233, // madvise
+    242, // accept4
+    260, // wait4
+    261, // prlimit64
+    277, // seccomp (a child's network filter; filters only add up)
+    278, // getrandom
+    291, // statx
+    293, // rseq
+    435, // clone3
+    439, // faccessat2
+    444, // landlock_create_ruleset (child sandboxes; rulesets only narrow)
+    445, // landlock_add_rule
+    446, // landlock_restrict_self
+];
+
+// ============================================================================
+// Raw syscall wrapper
+// ============================================================================
+
+pub(super) unsafe fn syscall3(num: i64, a1: i64, a2: i64, a3: i64) -> i64 {
+    let ret: i64;
+    std::arch::asm!(
+        "svc 0",
+        in("x8") num,
+        inlateout("x0") a1 => ret,
+        in("x1") a2,
+        in("x2") a3,
+        options(nostack),
+    );
+    ret
+}
+
+/// prlimit64(2) on the calling process, without reading back the old limit.
+pub(super) unsafe fn prlimit(resource: u32, limit: *const u64) -> i64 {
+    let ret: i64;
+    std::arch::asm!(
+        "svc 0",
+        in("x8") SYS_PRLIMIT64,
+        inlateout("x0") 0i64 => ret,
+        in("x1") resource as i64,
+        in("x2") limit as i64,
+        in("x3") 0i64,
+        options(nostack),
+    );
+    ret
+}
+
+/// prctl(2) checks that its unused arguments are zero, so they must be set
+/// rather than left as whatever the registers held.
+pub(super) unsafe fn prctl(option: i32, arg2: i64) -> i64 {
+    let ret: i64;
+    std::arch::asm!(
+        "svc 0",
+        in("x8") SYS_PRCTL,
+        inlateout("x0") option as i64 => ret,
+        in("x1") arg2,
+        in("x2") 0i64,
+        in("x3") 0i64,
+        in("x4") 0i64,
+        options(nostack),
+    );
+    ret
+}
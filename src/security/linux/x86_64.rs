@@ -0,0 +1,175 @@
+//! x86_64 syscall numbers, seccomp allowlist, and raw syscall wrappers.
+
+// ============================================================================
+// Syscall numbers
+// ============================================================================
+
+const SYS_PRCTL: i64 = 157;
+const SYS_PRLIMIT64: i64 = 302;
+pub(super) const SYS_SECCOMP: i64 = 317;
+pub(super) const SYS_GETPID: i64 = 39;
+pub(super) const SYS_LANDLOCK_CREATE_RULESET: i64 = 444;
+pub(super) const SYS_LANDLOCK_ADD_RULE: i64 = 445;
+pub(super) const SYS_LANDLOCK_RESTRICT_SELF: i64 = 446;
+pub(super) const SYS_SOCKET: u32 = 41;
+
+/// AUDIT_ARCH_X86_64, as reported in seccomp_data.arch.
+pub(super) const AUDIT_ARCH: u32 = 0xC000003E;
+
+// ============================================================================
+// seccomp allowlist
+// ============================================================================
+
+/// Syscalls allowed by the seccomp filter.
+/// This is the minimum set needed for Sentinel's operation.
+pub(super) const ALLOWED_SYSCALLS: &[u32] = &[
+    // File I/O
+    0,   // read
+    1,   // write
+    2,   // open
+    3,   // close
+    4,   // stat
+    5,   // fstat
+    6,   // lstat
+    7,   // poll
+    8,   // lseek
+    9,   // mmap
+    10,  // mprotect
+    11,  // munmap
+    12,  // brk
+    13,  // rt_sigaction
+    14,  // rt_sigprocmask
+    15,  // rt_sigreturn (return from the SIGHUP handler)
+    16,  // ioctl (needed for terminal)
+    17,  // pread64
+    18,  // pwrite64
+    19,  // readv
+    20,  // writev
+    21,  // access
+    24,  // sched_yield
+    28,  // madvise
+    32,  // dup
+    33,  // dup2
+    35,  // nanosleep
+    39,  // getpid
+    41,  // socket
+    42,  // connect
+    44,  // sendto
+    45,  // recvfrom
+    46,  // sendmsg
+    47,  // recvmsg
+    49,  // bind (for localhost connections)
+    53,  // socketpair (std's spawn path when a child is sandboxed)
+    54,  // setsockopt
+    55,  // getsockopt
+    56,  // clone (for thread creation)
+    59,  // execve (for run_command tool)
+    60,  // exit
+    61,  // wait4
+    62,  // kill (for process timeout)
+    72,  // fcntl
+    77,  // ftruncate (saving /setprompt overrides)
+    78,  // getdents
+    79,  // getcwd
+    80,  // chdir
+    83,  // mkdir (create_directory tool)
+    87,  // unlink (delete_file tool)
+    89,  // readlink
+    96,  // gettimeofday
+    97,  // getrlimit
+    102, // getuid
+    104, // getgid
+    107, // geteuid
+    108, // getegid
+    110, // getppid
+    131, // sigaltstack
+    137, // statfs
+    157, // prctl
+    158, // arch_prctl
+    202, // futex
+    217, // getdents64
+    218, // set_tid_address
+    228, // clock_gettime
+    230, // clock_nanosleep
+    231, // exit_group
+    233, // epoll_wait
+    234, // tgkill
+    257, // openat
+    258, // mkdirat
+    262, // newfstatat
+    263, // unlinkat
+    270, // pselect6
+    271, // ppoll
+    273, // set_robust_list
+    281, // epoll_pwait
+    288, // accept4
+    291, // epoll_create1
+    292, // dup3
+    293, // pipe2
+    302, // prlimit64
+    309, // getcpu
+    317, // seccomp (a child's network filter; filters only add up)
+    318, // getrandom
+    332, // statx
+    334, // rseq
+    435, // clone3
+    439, // faccessat2
+    444, // landlock_create_ruleset (child sandboxes; rulesets only narrow)
+    445, // landlock_add_rule
+    446, // landlock_restrict_self
+];
+
+// ============================================================================
+// Raw syscall wrapper
+// ============================================================================
+
+pub(super) unsafe fn syscall3(num: i64, a1: i64, a2: i64, a3: i64) -> i64 {
+    let ret: i64;
+    std::arch::asm!(
+        "syscall",
+        inlateout("rax") num => ret,
+        in("rdi") a1,
+        in("rsi") a2,
+        in("rdx") a3,
+        lateout("rcx") _,
+        lateout("r11") _,
+        options(nostack),
+    );
+    ret
+}
+
+/// prlimit64(2) on the calling process, without reading back the old limit.
+pub(super) unsafe fn prlimit(resource: u32, limit: *const u64) -> i64 {
+    let ret: i64;
+    std::arch::asm!(
+        "syscall",
+        inlateout("rax") SYS_PRLIMIT64 => ret,
+        in("rdi") 0i64,
+        in("rsi") resource as i64,
+        in("rdx") limit as i64,
+        in("r10") 0i64,
+        lateout("rcx") _,
+        lateout("r11") _,
+        options(nostack),
+    );
+    ret
+}
+
+/// prctl(2) checks that its unused arguments are zero, so they must be set
+/// rather than left as whatever the registers held.
+pub(super) unsafe fn prctl(option: i32, arg2: i64) -> i64 {
+    let ret: i64;
+    std::arch::asm!(
+        "syscall",
+        inlateout("rax") SYS_PRCTL => ret,
+        in("rdi") option as i64,
+        in("rsi") arg2,
+        in("rdx") 0i64,
+        in("r10") 0i64,
+        in("r8") 0i64,
+        lateout("rcx") _,
+        lateout("r11") _,
+        options(nostack),
+    );
+    ret
+}
@@ -0,0 +1,120 @@
+//! macOS sandboxing: a Seatbelt (SBPL) profile applied with sandbox_init.
+//!
+//! Best-effort counterpart to the Linux landlock rules: file contents can
+//! only be read under the allowed paths and the system locations every
+//! process needs, and written only under the allowed write paths. Metadata,
+//! the network and process creation stay open, and the profile is inherited
+//! by every command the agent runs.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+use crate::security::capability::glob_base;
+
+extern "C" {
+    // libsandbox, part of libSystem. Deprecated in the SDK headers but still
+    // what sandbox-exec and the system services use.
+    fn sandbox_init(profile: *const c_char, flags: u64, errorbuf: *mut *mut c_char) -> i32;
+    fn sandbox_free_error(errorbuf: *mut c_char);
+}
+
+/// Read-only locations the agent, dyld, TLS and DNS need.
+const SYSTEM_READ_PATHS: &[&str] = &[
+    "/System",
+    "/Library",
+    "/usr",
+    "/bin",
+    "/sbin",
+    "/dev",
+    "/private/etc",
+    "/private/var/db/timezone",
+    "/opt/homebrew",
+];
+
+/// Writable scratch locations: temporary files and the null device.
+const SYSTEM_WRITE_PATHS: &[&str] = &["/dev/null", "/dev/tty", "/private/tmp", "/private/var/folders"];
+
+/// Apply the sandbox profile to this process and everything it spawns.
+/// There's no way back: on success the restrictions hold for the
+/// process's lifetime.
+pub fn apply_sandbox(read_paths: &[String], write_paths: &[String]) -> Result<(), String> {
+    let profile = build_profile(read_paths, write_paths);
+    let profile = CString::new(profile).map_err(|_| "path contains null byte".to_string())?;
+    let mut error: *mut c_char = std::ptr::null_mut();
+    // flags = 0: `profile` is the SBPL source, not a named profile
+    let ret = unsafe { sandbox_init(profile.as_ptr(), 0, &mut error) };
+    if ret == 0 {
+        return Ok(());
+    }
+    let message = if error.is_null() {
+        format!("sandbox_init failed ({})", ret)
+    } else {
+        let message = unsafe { CStr::from_ptr(error) }.to_string_lossy().into_owned();
+        unsafe { sandbox_free_error(error) };
+        message
+    };
+    Err(message)
+}
+
+/// The SBPL profile: allow everything, then deny reading file contents and
+/// writing anywhere except the listed paths. Later rules take precedence.
+fn build_profile(read_paths: &[String], write_paths: &[String]) -> String {
+    let mut profile = String::from("(version 1)\n(allow default)\n(deny file-read-data file-write*)\n");
+
+    let mut readable: Vec<String> = SYSTEM_READ_PATHS.iter().map(|p| p.to_string()).collect();
+    readable.extend(read_paths.iter().chain(write_paths).map(|p| resolve(p)));
+    profile.push_str(&allow_rule("file-read-data", &readable));
+
+    let mut writable: Vec<String> = SYSTEM_WRITE_PATHS.iter().map(|p| p.to_string()).collect();
+    writable.extend(write_paths.iter().map(|p| resolve(p)));
+    profile.push_str(&allow_rule("file-write*", &writable));
+    profile
+}
+
+/// The directory an allowlist entry covers, with symlinks resolved: the
+/// sandbox sees real paths, and `/tmp` or `/var` are links into `/private`.
+/// Globs open their base directory, which the allowlist still narrows.
+fn resolve(path: &str) -> String {
+    let base = glob_base(path);
+    std::fs::canonicalize(base)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| base.to_string())
+}
+
+fn allow_rule(operation: &str, paths: &[String]) -> String {
+    let filters: Vec<String> = paths
+        .iter()
+        .map(|p| format!("    (subpath \"{}\")", escape(p)))
+        .collect();
+    format!("(allow {}\n{})\n", operation, filters.join("\n"))
+}
+
+/// Quote a path as an SBPL string literal.
+fn escape(path: &str) -> String {
+    path.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_limits_reads_and_writes_to_allowed_paths() {
+        let profile = build_profile(
+            &["/nonexistent/read".into()],
+            &["/nonexistent/write/**/*.txt".into()],
+        );
+        assert!(profile.starts_with("(version 1)\n(allow default)\n(deny file-read-data file-write*)\n"));
+        let (reads, writes) = profile.split_once("(allow file-write*").unwrap();
+        assert!(reads.contains("(subpath \"/nonexistent/read\")"));
+        assert!(reads.contains("(subpath \"/nonexistent/write\")"));
+        assert!(reads.contains("(subpath \"/System\")"));
+        assert!(writes.contains("(subpath \"/nonexistent/write\")"));
+        assert!(!writes.contains("/nonexistent/read"));
+    }
+
+    #[test]
+    fn test_profile_escapes_paths() {
+        assert_eq!(escape("/a \"b\"\\c"), "/a \\\"b\\\"\\\\c");
+    }
+}
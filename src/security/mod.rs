@@ -2,3 +2,6 @@ pub mod audit;
 pub mod capability;
 #[cfg(target_os = "linux")]
 pub mod linux;
+#[cfg(target_os = "macos")]
+pub mod macos;
+pub mod redact;
@@ -1,93 +1,251 @@
 use std::io::{BufRead, BufReader, Write};
+use std::sync::mpsc;
 use std::thread;
 use std::time::{Duration, Instant};
 
+use crate::agent::prompt::TurnContext;
 use crate::net::json::{self, json_obj, JsonValue};
 
+use super::limit::SkillPermit;
 use super::sandbox::SandboxedProcess;
 
 // ── Skill IPC protocol ──────────────────────────────────────────────────────
 //
 // Request (written to skill's stdin):
-//   {"params":{"key":"value"}}\n
+//   {"params":{"key":"value"},"context":{...}}\n
 //
-// Response (read from skill's stdout):
+// `context` says who the call is for, so a skill can personalize its answer
+// or post into the conversation itself. `username` is null where the
+// platform gave none. Skills that don't need it can ignore it:
+//   {"platform":"telegram","channel_id":"42","user_id":"7","username":"ada"}
+//
+// Interim progress (zero or more lines on the skill's stdout):
+//   {"progress":"step 1 of 3 done"}\n
+//
+// Response (final line read from skill's stdout):
 //   {"result":"output text"}\n
 //   or
 //   {"error":"error message"}\n
+//
+// A one-shot skill then sees EOF on stdin and exits; a persistent one reads
+// the next request.
 
 /// Invoke a skill binary with the given parameters and return the result.
-/// The skill process is spawned, given the request, and expected to respond
-/// with a single JSON line. Killed after timeout_secs if no response.
+/// `on_progress` is called for each interim `{"progress":...}` line; the
+/// first `result` or `error` line ends the invocation. Killed after
+/// timeout_secs if no final response arrives. If the exchange fails, the
+/// error ends with the tail of the skill's stderr.
 pub fn invoke_skill(
     process: &mut SandboxedProcess,
     params: &JsonValue,
+    ctx: &TurnContext,
     timeout_secs: u64,
+    on_progress: &mut dyn FnMut(&str),
 ) -> Result<String, String> {
-    // Build request JSON
-    let request = json_obj().field("params", params.clone()).build();
-    let request_str = format!("{}\n", request.to_json_string());
-
-    // Write request to stdin then close it
-    {
-        let stdin = process
-            .stdin()
-            .ok_or("failed to get skill stdin")?;
-        stdin
-            .write_all(request_str.as_bytes())
-            .map_err(|e| format!("failed to write to skill stdin: {}", e))?;
-        stdin
-            .flush()
-            .map_err(|e| format!("failed to flush skill stdin: {}", e))?;
-    }
-    // Drop stdin to signal EOF to the child
-    // (take it from the child so it gets closed)
-    drop(process.stdin().take());
-
-    // Read response with timeout
+    let response = write_request(process, params, ctx).and_then(|_| {
+        // Drop stdin to signal EOF to the child
+        // (take it from the child so it gets closed)
+        drop(process.stdin().take());
+        let lines = read_lines(process)?;
+        read_response(process, &lines, timeout_secs, on_progress)
+    });
+    match response {
+        Ok((response, line)) => parse_final_response(&response, &line),
+        Err(e) => Err(with_stderr(process, e)),
+    }
+}
+
+// ── Persistent skills ───────────────────────────────────────────────────────
+//
+// A skill declaring `persistent = true` keeps running between calls. Each
+// call is one request line in and one final response line out, exactly as
+// above, but stdin stays open: the skill must answer every request line
+// (and flush) rather than waiting for EOF, which now means "shut down".
+
+/// A long-lived skill process serving one request at a time.
+pub struct SkillSession {
+    process: SandboxedProcess,
+    lines: mpsc::Receiver<String>,
+    last_used: Instant,
+    /// A call failed in a way that leaves the stream unusable (timeout,
+    /// exit, garbage output); the process must be replaced.
+    broken: bool,
+    /// The process's concurrency slot, released when the session ends.
+    _permit: SkillPermit,
+}
+
+impl SkillSession {
+    pub fn start(mut process: SandboxedProcess, permit: SkillPermit) -> Result<Self, String> {
+        let lines = read_lines(&mut process)?;
+        Ok(SkillSession {
+            process,
+            lines,
+            last_used: Instant::now(),
+            broken: false,
+            _permit: permit,
+        })
+    }
+
+    /// Send one request and wait for its response.
+    pub fn call(
+        &mut self,
+        params: &JsonValue,
+        ctx: &TurnContext,
+        timeout_secs: u64,
+        on_progress: &mut dyn FnMut(&str),
+    ) -> Result<String, String> {
+        self.last_used = Instant::now();
+        let response = write_request(&mut self.process, params, ctx).and_then(|_| {
+            read_response(&mut self.process, &self.lines, timeout_secs, on_progress)
+        });
+        self.last_used = Instant::now();
+        match response {
+            Ok((response, line)) => parse_final_response(&response, &line),
+            Err(e) => {
+                self.broken = true;
+                Err(with_stderr(&mut self.process, e))
+            }
+        }
+    }
+
+    /// Whether the process can take another request.
+    pub fn is_usable(&mut self) -> bool {
+        !self.broken && self.process.is_running()
+    }
+
+    pub fn idle_for(&self) -> Duration {
+        self.last_used.elapsed()
+    }
+}
+
+/// Write one request line to the skill's stdin.
+fn write_request(
+    process: &mut SandboxedProcess,
+    params: &JsonValue,
+    ctx: &TurnContext,
+) -> Result<(), String> {
+    let request_str = format!("{}\n", request_json(params, ctx).to_json_string());
+    let stdin = process
+        .stdin()
+        .ok_or("failed to get skill stdin")?;
+    stdin
+        .write_all(request_str.as_bytes())
+        .map_err(|e| format!("failed to write to skill stdin: {}", e))?;
+    stdin
+        .flush()
+        .map_err(|e| format!("failed to flush skill stdin: {}", e))
+}
+
+fn request_json(params: &JsonValue, ctx: &TurnContext) -> JsonValue {
+    let username = match ctx.username {
+        Some(name) => JsonValue::String(name.to_string()),
+        None => JsonValue::Null,
+    };
+    let context = json_obj()
+        .field_str("platform", ctx.platform)
+        .field_str("channel_id", ctx.channel_id)
+        .field_str("user_id", ctx.user_id)
+        .field("username", username)
+        .build();
+    json_obj()
+        .field("params", params.clone())
+        .field("context", context)
+        .build()
+}
+
+/// Read stdout lines on a separate thread so the timeout still applies.
+fn read_lines(process: &mut SandboxedProcess) -> Result<mpsc::Receiver<String>, String> {
+    let stdout = process
+        .take_stdout()
+        .ok_or("failed to get skill stdout")?;
+    let (tx, rx) = mpsc::channel::<String>();
+    thread::spawn(move || {
+        let reader = BufReader::new(stdout);
+        for line in reader.lines() {
+            match line {
+                Ok(l) => {
+                    if tx.send(l).is_err() {
+                        break;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    });
+    Ok(rx)
+}
+
+/// Wait for the final response line, forwarding progress lines. Errors
+/// here (timeout, no output, invalid JSON) mean the exchange itself failed,
+/// not that the skill reported an error.
+fn read_response(
+    process: &mut SandboxedProcess,
+    lines: &mpsc::Receiver<String>,
+    timeout_secs: u64,
+    on_progress: &mut dyn FnMut(&str),
+) -> Result<(JsonValue, String), String> {
     let timeout = Duration::from_secs(timeout_secs);
     let start = Instant::now();
 
-    // Wait for the child to exit (with timeout)
     loop {
-        match process.try_wait() {
-            Ok(Some(_exit_code)) => {
-                // Child exited — read stdout
-                break;
-            }
-            Ok(None) => {
-                if start.elapsed() >= timeout {
-                    process.kill();
-                    return Err(format!("skill timed out after {}s", timeout_secs));
-                }
-                thread::sleep(Duration::from_millis(100));
+        let remaining = match timeout.checked_sub(start.elapsed()) {
+            Some(r) => r,
+            None => {
+                process.kill();
+                return Err(format!("skill timed out after {}s", timeout_secs));
             }
-            Err(e) => {
+        };
+
+        let line = match lines.recv_timeout(remaining) {
+            Ok(l) => l,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
                 process.kill();
-                return Err(format!("error waiting for skill: {}", e));
+                return Err(format!("skill timed out after {}s", timeout_secs));
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(match process.wait_exit(Duration::from_secs(1)) {
+                    Some(status) if !status.success() => {
+                        format!("skill exited without a response ({})", status)
+                    }
+                    _ => "skill produced no output".into(),
+                });
             }
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
         }
-    }
 
-    // Read all of stdout
-    let stdout = process
-        .stdout()
-        .ok_or("failed to get skill stdout")?;
-    let mut reader = BufReader::new(stdout);
-    let mut response_line = String::new();
-    reader
-        .read_line(&mut response_line)
-        .map_err(|e| format!("failed to read skill response: {}", e))?;
+        // Parse response JSON
+        let json_val = json::parse(line)
+            .map_err(|e| format!("skill response is not valid JSON: {}", e))?;
 
-    let response_line = response_line.trim();
-    if response_line.is_empty() {
-        return Err("skill produced no output".into());
+        // Interim progress — forward and keep reading
+        if let Some(progress) = json_val.get("progress") {
+            match progress.as_str() {
+                Some(p) => on_progress(p),
+                None => on_progress(&progress.to_json_string()),
+            }
+            continue;
+        }
+
+        return Ok((json_val, line.to_string()));
     }
+}
 
-    // Parse response JSON
-    let json_val = json::parse(response_line)
-        .map_err(|e| format!("skill response is not valid JSON: {}", e))?;
+/// Append what the skill last wrote to stderr, which usually says why it
+/// failed.
+fn with_stderr(process: &mut SandboxedProcess, error: String) -> String {
+    let tail = process.stderr_tail();
+    if tail.is_empty() {
+        error
+    } else {
+        format!("{}\n--- stderr ---\n{}", error, tail)
+    }
+}
 
+fn parse_final_response(json_val: &JsonValue, line: &str) -> Result<String, String> {
     // Check for error
     if let Some(err) = json_val.get("error") {
         if let Some(err_str) = err.as_str() {
@@ -105,14 +263,38 @@ pub fn invoke_skill(
     }
 
     // No result or error field — return the whole response
-    Ok(response_line.to_string())
+    Ok(line.to_string())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::skills::sandbox::SkillCaps;
     use std::fs;
 
+    fn test_ctx() -> TurnContext<'static> {
+        TurnContext {
+            platform: "telegram",
+            channel_id: "42",
+            user_id: "7",
+            username: Some("ada"),
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_request_carries_context() {
+        let params = json_obj().field_str("text", "hello").build();
+        assert_eq!(
+            request_json(&params, &test_ctx()).to_json_string(),
+            r#"{"params":{"text":"hello"},"context":{"platform":"telegram","channel_id":"42","user_id":"7","username":"ada"}}"#
+        );
+
+        let anonymous = TurnContext { username: None, ..test_ctx() };
+        let request = request_json(&params, &anonymous);
+        assert_eq!(request.get_path("context.username"), Some(&JsonValue::Null));
+    }
+
     #[test]
     fn test_invoke_skill_echo() {
         // Create a simple skill script that echoes back the input
@@ -131,9 +313,9 @@ mod tests {
             fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755)).unwrap();
         }
 
-        let mut process = SandboxedProcess::spawn(&script_path, script_dir).unwrap();
+        let mut process = SandboxedProcess::spawn(&script_path, script_dir, &SkillCaps::default()).unwrap();
         let params = json_obj().field_str("text", "hello").build();
-        let result = invoke_skill(&mut process, &params, 5);
+        let result = invoke_skill(&mut process, &params, &test_ctx(), 5, &mut |_| {});
         assert!(result.is_ok(), "should succeed: {:?}", result);
         assert_eq!(result.unwrap(), "got it");
 
@@ -157,15 +339,42 @@ mod tests {
             fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755)).unwrap();
         }
 
-        let mut process = SandboxedProcess::spawn(&script_path, script_dir).unwrap();
+        let mut process = SandboxedProcess::spawn(&script_path, script_dir, &SkillCaps::default()).unwrap();
         let params = json_obj().build();
-        let result = invoke_skill(&mut process, &params, 5);
+        let result = invoke_skill(&mut process, &params, &test_ctx(), 5, &mut |_| {});
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("something failed"));
 
         let _ = fs::remove_dir_all(script_dir);
     }
 
+    #[test]
+    fn test_invoke_skill_failure_includes_stderr() {
+        let script_dir = "/tmp/sentinel_test_ipc_stderr";
+        let script_path = format!("{}/crash.sh", script_dir);
+        let _ = fs::create_dir_all(script_dir);
+        fs::write(
+            &script_path,
+            "#!/bin/sh\nread line\necho 'Traceback (most recent call last):' >&2\necho 'KeyError: missing_field' >&2\nexit 3\n",
+        )
+        .unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let mut process = SandboxedProcess::spawn(&script_path, script_dir, &SkillCaps::default()).unwrap();
+        let params = json_obj().build();
+        let err = invoke_skill(&mut process, &params, &test_ctx(), 5, &mut |_| {}).unwrap_err();
+        assert!(err.starts_with("skill exited without a response"), "{}", err);
+        assert!(err.contains("--- stderr ---\nTraceback"), "{}", err);
+        assert!(err.ends_with("KeyError: missing_field"), "{}", err);
+
+        let _ = fs::remove_dir_all(script_dir);
+    }
+
     #[test]
     fn test_invoke_skill_timeout() {
         let script_dir = "/tmp/sentinel_test_ipc_timeout";
@@ -183,12 +392,42 @@ mod tests {
             fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755)).unwrap();
         }
 
-        let mut process = SandboxedProcess::spawn(&script_path, script_dir).unwrap();
+        let caps = SkillCaps { commands: vec!["sleep".into()], ..Default::default() };
+        let mut process = SandboxedProcess::spawn(&script_path, script_dir, &caps).unwrap();
         let params = json_obj().build();
-        let result = invoke_skill(&mut process, &params, 1);
+        let result = invoke_skill(&mut process, &params, &test_ctx(), 1, &mut |_| {});
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("timed out"));
 
         let _ = fs::remove_dir_all(script_dir);
     }
+
+    #[test]
+    fn test_invoke_skill_progress() {
+        let script_dir = "/tmp/sentinel_test_ipc_progress";
+        let script_path = format!("{}/progress.sh", script_dir);
+        let _ = fs::create_dir_all(script_dir);
+        fs::write(
+            &script_path,
+            "#!/bin/sh\nread line\necho '{\"progress\":\"step 1\"}'\necho '{\"progress\":\"step 2\"}'\necho '{\"result\":\"done\"}'\n",
+        )
+        .unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let mut process = SandboxedProcess::spawn(&script_path, script_dir, &SkillCaps::default()).unwrap();
+        let params = json_obj().build();
+        let mut progress = Vec::new();
+        let result = invoke_skill(&mut process, &params, &test_ctx(), 5, &mut |p| {
+            progress.push(p.to_string())
+        });
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(progress, vec!["step 1", "step 2"]);
+
+        let _ = fs::remove_dir_all(script_dir);
+    }
 }
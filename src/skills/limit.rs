@@ -0,0 +1,147 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+// ── Skill concurrency limit ─────────────────────────────────────────────────
+//
+// Counting semaphore bounding how many skill processes may be alive at once.
+// A permit is held for as long as its process runs, so long-lived processes
+// count toward the limit too.
+
+/// What to do when every slot is taken.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConcurrencyPolicy {
+    /// Wait (up to a deadline) for a slot to free up.
+    Queue,
+    /// Fail the invocation immediately.
+    Reject,
+}
+
+impl ConcurrencyPolicy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "queue" => Some(ConcurrencyPolicy::Queue),
+            "reject" => Some(ConcurrencyPolicy::Reject),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ConcurrencyLimit {
+    max: usize,
+    policy: ConcurrencyPolicy,
+    state: Arc<(Mutex<usize>, Condvar)>,
+}
+
+/// Releases its slot when dropped.
+pub struct SkillPermit {
+    state: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl ConcurrencyLimit {
+    pub fn new(max: usize, policy: ConcurrencyPolicy) -> Self {
+        ConcurrencyLimit {
+            max: max.max(1),
+            policy,
+            state: Arc::new((Mutex::new(0), Condvar::new())),
+        }
+    }
+
+    /// Acquire a slot according to the policy. Queued callers give up after
+    /// `wait`.
+    pub fn acquire(&self, wait: Duration) -> Result<SkillPermit, String> {
+        let (lock, cvar) = &*self.state;
+        let mut running = lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        if *running >= self.max && self.policy == ConcurrencyPolicy::Reject {
+            return Err(format!(
+                "skill concurrency limit reached ({} running)",
+                self.max
+            ));
+        }
+
+        let deadline = Instant::now() + wait;
+        while *running >= self.max {
+            let remaining = match deadline.checked_duration_since(Instant::now()) {
+                Some(r) if !r.is_zero() => r,
+                _ => {
+                    return Err(format!(
+                        "timed out waiting for a skill slot ({} running)",
+                        self.max
+                    ))
+                }
+            };
+            running = cvar
+                .wait_timeout(running, remaining)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+
+        *running += 1;
+        Ok(SkillPermit {
+            state: self.state.clone(),
+        })
+    }
+}
+
+impl Drop for SkillPermit {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.state;
+        let mut running = lock.lock().unwrap_or_else(|e| e.into_inner());
+        *running = running.saturating_sub(1);
+        cvar.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn in_use(limit: &ConcurrencyLimit) -> usize {
+        *limit.state.0.lock().unwrap()
+    }
+
+    #[test]
+    fn test_reject_past_limit() {
+        let limit = ConcurrencyLimit::new(2, ConcurrencyPolicy::Reject);
+        let _a = limit.acquire(Duration::from_secs(1)).unwrap();
+        let _b = limit.acquire(Duration::from_secs(1)).unwrap();
+        let err = limit.acquire(Duration::from_secs(1)).err().unwrap();
+        assert!(err.contains("limit reached"));
+        assert_eq!(in_use(&limit), 2);
+    }
+
+    #[test]
+    fn test_queue_waits_for_release() {
+        let limit = ConcurrencyLimit::new(1, ConcurrencyPolicy::Queue);
+        let held = limit.acquire(Duration::from_secs(1)).unwrap();
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            drop(held);
+        });
+
+        let start = Instant::now();
+        let permit = limit.acquire(Duration::from_secs(5));
+        assert!(permit.is_ok());
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        releaser.join().unwrap();
+    }
+
+    #[test]
+    fn test_queue_times_out() {
+        let limit = ConcurrencyLimit::new(1, ConcurrencyPolicy::Queue);
+        let _held = limit.acquire(Duration::from_secs(1)).unwrap();
+        let err = limit.acquire(Duration::from_millis(50)).err().unwrap();
+        assert!(err.contains("timed out"));
+    }
+
+    #[test]
+    fn test_permit_released_on_drop() {
+        let limit = ConcurrencyLimit::new(1, ConcurrencyPolicy::Reject);
+        drop(limit.acquire(Duration::from_secs(1)).unwrap());
+        assert_eq!(in_use(&limit), 0);
+        assert!(limit.acquire(Duration::from_secs(1)).is_ok());
+    }
+}
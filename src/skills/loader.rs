@@ -17,22 +17,63 @@ pub struct SkillDef {
 /// `skill.toml` is treated as a skill. Invalid or incomplete skills are
 /// logged and skipped.
 pub fn load_skills(skills_dir: &str) -> Vec<SkillDef> {
+    let results = match scan_skills(skills_dir) {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("sentinel: {}", e);
+            return Vec::new();
+        }
+    };
+
     let mut skills = Vec::new();
+    for result in results {
+        match result {
+            Ok(skill) => {
+                eprintln!(
+                    "sentinel: loaded skill '{}' (tool: {})",
+                    skill.manifest.name, skill.manifest.tool_name
+                );
+                skills.push(skill);
+            }
+            Err(e) => eprintln!("sentinel: {}", e),
+        }
+    }
+
+    skills.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+    skills
+}
+
+/// Check every skill in a directory without loading it: the names of the
+/// valid skills, sorted, or every problem found.
+pub fn check_skills(skills_dir: &str) -> Result<Vec<String>, Vec<String>> {
+    let results = scan_skills(skills_dir).map_err(|e| vec![e])?;
+    let mut names = Vec::new();
+    let mut problems = Vec::new();
+    for result in results {
+        match result {
+            Ok(skill) => names.push(skill.manifest.name),
+            Err(e) => problems.push(e),
+        }
+    }
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Every subdirectory with a `skill.toml`, parsed.
+fn scan_skills(skills_dir: &str) -> Result<Vec<Result<SkillDef, String>>, String> {
     let dir_path = Path::new(skills_dir);
 
     if !dir_path.is_dir() {
-        eprintln!("sentinel: skills directory '{}' not found", skills_dir);
-        return skills;
+        return Err(format!("skills directory '{}' not found", skills_dir));
     }
 
-    let entries = match fs::read_dir(dir_path) {
-        Ok(e) => e,
-        Err(e) => {
-            eprintln!("sentinel: cannot read skills directory '{}': {}", skills_dir, e);
-            return skills;
-        }
-    };
+    let entries = fs::read_dir(dir_path)
+        .map_err(|e| format!("cannot read skills directory '{}': {}", skills_dir, e))?;
 
+    let mut results = Vec::new();
     for entry in entries {
         let entry = match entry {
             Ok(e) => e,
@@ -48,58 +89,35 @@ pub fn load_skills(skills_dir: &str) -> Vec<SkillDef> {
         if !manifest_path.exists() {
             continue;
         }
+        results.push(load_skill(&path, &manifest_path));
+    }
+    Ok(results)
+}
 
-        let content = match fs::read_to_string(&manifest_path) {
-            Ok(c) => c,
-            Err(e) => {
-                eprintln!(
-                    "sentinel: cannot read {}: {}",
-                    manifest_path.display(),
-                    e
-                );
-                continue;
-            }
-        };
-
-        let manifest = match parse_manifest(&content) {
-            Ok(m) => m,
-            Err(e) => {
-                eprintln!(
-                    "sentinel: invalid manifest {}: {}",
-                    manifest_path.display(),
-                    e
-                );
-                continue;
-            }
-        };
+fn load_skill(path: &Path, manifest_path: &Path) -> Result<SkillDef, String> {
+    let content = fs::read_to_string(manifest_path)
+        .map_err(|e| format!("cannot read {}: {}", manifest_path.display(), e))?;
 
-        let skill_dir = path.to_string_lossy().to_string();
-        let binary_path = path.join(&manifest.binary).to_string_lossy().to_string();
+    let manifest = parse_manifest(&content)
+        .map_err(|e| format!("invalid manifest {}: {}", manifest_path.display(), e))?;
 
-        // Verify binary exists and is executable
-        let binary_file = Path::new(&binary_path);
-        if !binary_file.exists() {
-            eprintln!(
-                "sentinel: skill '{}' binary not found: {}",
-                manifest.name, binary_path
-            );
-            continue;
-        }
+    let skill_dir = path.to_string_lossy().to_string();
+    let binary_path = path.join(&manifest.binary).to_string_lossy().to_string();
 
-        eprintln!(
-            "sentinel: loaded skill '{}' (tool: {})",
-            manifest.name, manifest.tool_name
-        );
-
-        skills.push(SkillDef {
-            manifest,
-            binary_path,
-            skill_dir,
-        });
+    // Verify binary exists and is executable
+    let binary_file = Path::new(&binary_path);
+    if !binary_file.exists() {
+        return Err(format!(
+            "skill '{}' binary not found: {}",
+            manifest.name, binary_path
+        ));
     }
 
-    skills.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
-    skills
+    Ok(SkillDef {
+        manifest,
+        binary_path,
+        skill_dir,
+    })
 }
 
 #[cfg(test)]
@@ -143,4 +161,39 @@ param_required = ["text"]
         // Cleanup
         let _ = fs::remove_dir_all(base);
     }
+
+    #[test]
+    fn test_check_skills_reports_each_problem() {
+        let base = "/tmp/sentinel_test_check_skills";
+        let _ = fs::remove_dir_all(base);
+        let good = format!("{}/good", base);
+        let broken = format!("{}/broken", base);
+        let missing = format!("{}/missing-binary", base);
+        for dir in [&good, &broken, &missing] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        let manifest = |name: &str| {
+            format!(
+                "[skill]\nname = \"{}\"\nbinary = \"run.sh\"\n\n[tool]\nname = \"{}_tool\"\ndescription = \"d\"\n",
+                name, name
+            )
+        };
+        fs::write(format!("{}/skill.toml", good), manifest("good")).unwrap();
+        fs::write(format!("{}/run.sh", good), "#!/bin/sh\n").unwrap();
+        fs::write(format!("{}/skill.toml", broken), "[skill\n").unwrap();
+        fs::write(format!("{}/skill.toml", missing), manifest("missing")).unwrap();
+
+        let mut problems = check_skills(base).unwrap_err();
+        problems.sort();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("invalid manifest"));
+        assert!(problems[1].starts_with("skill 'missing' binary not found"));
+
+        fs::remove_dir_all(&broken).unwrap();
+        fs::remove_dir_all(&missing).unwrap();
+        assert_eq!(check_skills(base).unwrap(), vec!["good"]);
+        assert!(check_skills("/tmp/sentinel_test_nonexistent_skills_dir").is_err());
+
+        let _ = fs::remove_dir_all(base);
+    }
 }
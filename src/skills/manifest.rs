@@ -7,6 +7,10 @@ pub struct SkillManifest {
     pub version: String,
     pub description: String,
     pub binary: String,
+    /// Keep the process running between calls.
+    pub persistent: bool,
+    /// Seconds a persistent process may sit unused before it is stopped.
+    pub idle_timeout: u64,
     // Capabilities
     pub cap_network: bool,
     pub cap_file_read: Vec<String>,
@@ -41,12 +45,14 @@ pub fn parse_manifest(content: &str) -> Result<SkillManifest, String> {
     let binary = doc
         .get_str("skill", "binary")
         .ok_or("skill.binary is required")?;
+    let persistent = doc.get_bool("skill", "persistent")?.unwrap_or(false);
+    let idle_timeout = doc
+        .get_str("skill", "idle_timeout")
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(300);
 
     // [capabilities] section
-    let cap_network = doc
-        .get_str("capabilities", "network")
-        .map(|v| v == "true")
-        .unwrap_or(false);
+    let cap_network = doc.get_bool("capabilities", "network")?.unwrap_or(false);
     let cap_file_read = doc
         .get_str_list("capabilities", "file_read")
         .unwrap_or_default();
@@ -108,6 +114,8 @@ pub fn parse_manifest(content: &str) -> Result<SkillManifest, String> {
         version,
         description,
         binary,
+        persistent,
+        idle_timeout,
         cap_network,
         cap_file_read,
         cap_file_write,
@@ -132,6 +140,8 @@ name = "web-search"
 version = "0.1.0"
 description = "Search the web"
 binary = "web-search"
+persistent = true
+idle_timeout = 60
 
 [capabilities]
 network = true
@@ -151,6 +161,8 @@ param_required = ["query"]
         assert_eq!(manifest.name, "web-search");
         assert_eq!(manifest.version, "0.1.0");
         assert_eq!(manifest.binary, "web-search");
+        assert!(manifest.persistent);
+        assert_eq!(manifest.idle_timeout, 60);
         assert!(manifest.cap_network);
         assert_eq!(manifest.cap_file_read, vec!["/tmp"]);
         assert_eq!(manifest.tool_name, "web_search");
@@ -173,9 +185,77 @@ name = "hello"
         assert_eq!(manifest.name, "hello");
         assert_eq!(manifest.binary, "hello-skill");
         assert!(!manifest.cap_network);
+        assert!(!manifest.persistent);
+        assert_eq!(manifest.idle_timeout, 300);
         assert!(manifest.parameters.is_empty());
     }
 
+    #[test]
+    fn test_parse_manifest_multiline_description() {
+        let content = r#"
+[skill]
+name = "notes"
+binary = "notes"
+
+[tool]
+name = "search_notes"
+description = """
+Search the user's notes for a phrase. \
+Returns matching lines with their file names.
+
+Use this before answering questions about past meetings."""
+param_names = [
+    "query",   # what to look for
+    "limit",
+]
+param_descriptions = ['Text to find, e.g. "Q3 # budget"', "Max results"]
+"#;
+        let manifest = parse_manifest(content).unwrap();
+        assert_eq!(
+            manifest.tool_description,
+            "Search the user's notes for a phrase. Returns matching lines with their \
+             file names.\n\nUse this before answering questions about past meetings."
+        );
+        assert_eq!(manifest.parameters.len(), 2);
+        assert_eq!(manifest.parameters[0].description, "Text to find, e.g. \"Q3 # budget\"");
+    }
+
+    #[test]
+    fn test_parse_manifest_quoted_booleans() {
+        let manifest = |persistent: &str| {
+            parse_manifest(&format!(
+                "[skill]\nname = \"x\"\nbinary = \"x\"\npersistent = {}\n\n\
+                 [capabilities]\nnetwork = \"true\"\n\n[tool]\nname = \"x\"\n",
+                persistent
+            ))
+        };
+        let quoted = manifest("\"true\"").unwrap();
+        assert!(quoted.persistent);
+        assert!(quoted.cap_network);
+        let err = manifest("\"yes\"").err().unwrap();
+        assert_eq!(err, "skill.persistent must be true or false");
+    }
+
+    #[test]
+    fn test_parse_manifest_keeps_env_references() {
+        std::env::set_var("SENTINEL_TEST_MANIFEST_SECRET", "leaked");
+        std::env::remove_var("SENTINEL_TEST_MANIFEST_UNSET");
+        let content = r#"
+[skill]
+name = "echo"
+binary = "echo"
+
+[tool]
+name = "echo"
+description = "Prints ${SENTINEL_TEST_MANIFEST_SECRET} and ${SENTINEL_TEST_MANIFEST_UNSET}"
+"#;
+        let manifest = parse_manifest(content).unwrap();
+        assert_eq!(
+            manifest.tool_description,
+            "Prints ${SENTINEL_TEST_MANIFEST_SECRET} and ${SENTINEL_TEST_MANIFEST_UNSET}"
+        );
+    }
+
     #[test]
     fn test_parse_manifest_missing_name() {
         let content = r#"
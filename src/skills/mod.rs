@@ -1,30 +1,79 @@
 pub mod ipc;
+pub mod limit;
 pub mod loader;
 pub mod manifest;
 pub mod sandbox;
 
+use crate::agent::prompt::TurnContext;
 use crate::llm::provider::ToolDef;
-use crate::net::json::{json_arr, json_obj, JsonValue};
+use crate::net::json::{json_arr, json_obj, JsonNumber, JsonValue};
 use crate::security::audit::{AuditEvent, Auditor};
 
+use ipc::SkillSession;
+use limit::{ConcurrencyLimit, SkillPermit};
 use loader::SkillDef;
-use sandbox::SandboxedProcess;
+use manifest::SkillManifest;
+use sandbox::{SandboxedProcess, SkillCaps};
+
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+/// How often idle persistent skills are looked for.
+const REAP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The running process of each persistent skill, by index into `skills`.
+type Sessions = Vec<Mutex<Option<SkillSession>>>;
 
 // ── Skill runner ─────────────────────────────────────────────────────────────
 
 pub struct SkillRunner {
     skills: Vec<SkillDef>,
     skill_timeout: u64,
+    /// Slots for one-shot skill processes, each held for one call.
+    limit: ConcurrencyLimit,
+    /// Slots for persistent skill processes, each held until the process
+    /// stops. Kept apart so idle sessions can't starve one-shot calls.
+    persistent_limit: ConcurrencyLimit,
+    sessions: Arc<Sessions>,
 }
 
 impl SkillRunner {
     /// Load skills from a directory and create a runner.
-    pub fn load(skills_dir: &str, skill_timeout: u64) -> Self {
+    pub fn load(
+        skills_dir: &str,
+        skill_timeout: u64,
+        limit: ConcurrencyLimit,
+        persistent_limit: ConcurrencyLimit,
+    ) -> Self {
         let skills = loader::load_skills(skills_dir);
         eprintln!("sentinel: loaded {} skill(s)", skills.len());
+        let sessions: Arc<Sessions> = Arc::new(skills.iter().map(|_| Mutex::new(None)).collect());
+
+        // Stop persistent skills nobody has used for a while. The thread
+        // ends with the runner.
+        if skills.iter().any(|s| s.manifest.persistent) {
+            let idle: Vec<Duration> = skills
+                .iter()
+                .map(|s| Duration::from_secs(s.manifest.idle_timeout))
+                .collect();
+            let weak: Weak<Sessions> = Arc::downgrade(&sessions);
+            let names: Vec<String> = skills.iter().map(|s| s.manifest.name.clone()).collect();
+            thread::spawn(move || loop {
+                thread::sleep(REAP_INTERVAL);
+                let Some(sessions) = weak.upgrade() else { break };
+                for name in stop_idle(&sessions, &idle) {
+                    eprintln!("sentinel: stopped idle skill '{}'", names[name]);
+                }
+            });
+        }
+
         SkillRunner {
             skills,
             skill_timeout,
+            limit,
+            persistent_limit,
+            sessions,
         }
     }
 
@@ -37,35 +86,7 @@ impl SkillRunner {
     pub fn tool_definitions(&self) -> Vec<ToolDef> {
         self.skills
             .iter()
-            .map(|skill| {
-                let m = &skill.manifest;
-
-                // Build properties object from parameters
-                let mut props = json_obj();
-                let mut required = json_arr();
-
-                for param in &m.parameters {
-                    let prop = json_obj()
-                        .field_str("type", &param.param_type)
-                        .field_str("description", &param.description)
-                        .build();
-                    props = props.field(&param.name, prop);
-
-                    if param.required {
-                        required = required.push_str(&param.name);
-                    }
-                }
-
-                ToolDef {
-                    name: m.tool_name.clone(),
-                    description: m.tool_description.clone(),
-                    input_schema: json_obj()
-                        .field_str("type", "object")
-                        .field("properties", props.build())
-                        .field("required", required.build())
-                        .build(),
-                }
-            })
+            .map(|skill| tool_def(&skill.manifest))
             .collect()
     }
 
@@ -76,22 +97,59 @@ impl SkillRunner {
             .any(|s| s.manifest.tool_name == tool_name)
     }
 
-    /// Execute a skill tool invocation.
+    /// Execute a skill tool invocation for the turn described by `ctx`.
+    /// `on_progress` receives each interim progress line the skill emits
+    /// before its final result.
     pub fn execute(
         &self,
         tool_name: &str,
         input: &JsonValue,
+        ctx: &TurnContext,
         auditor: &mut Auditor,
+        on_progress: &mut dyn FnMut(&str),
     ) -> Result<String, String> {
-        let skill = self
+        let index = self
             .skills
             .iter()
-            .find(|s| s.manifest.tool_name == tool_name)
+            .position(|s| s.manifest.tool_name == tool_name)
             .ok_or_else(|| format!("unknown skill tool: {}", tool_name))?;
+        let skill = &self.skills[index];
 
         let params_str = input.to_json_string();
+        let audit_tool = format!("skill:{}", tool_name);
+
+        // Catch what the model got wrong before the skill has to
+        if let Err(e) = validate_params(&skill.manifest, input) {
+            auditor.log(AuditEvent::ToolCallDenied {
+                tool: &audit_tool,
+                params: &params_str,
+                reason: &e,
+            });
+            return Err(e);
+        }
+
+        // Hold a slot for the lifetime of the process. A persistent skill
+        // needs one only to start its process, which then keeps it.
+        let limit = match skill.manifest.persistent {
+            true if self.session_running(index) => None,
+            true => Some(&self.persistent_limit),
+            false => Some(&self.limit),
+        };
+        let permit = match limit.map(|l| l.acquire(Duration::from_secs(self.skill_timeout))) {
+            Some(Err(e)) => {
+                auditor.log(AuditEvent::ToolCallDenied {
+                    tool: &audit_tool,
+                    params: &params_str,
+                    reason: &e,
+                });
+                return Err(e);
+            }
+            Some(Ok(p)) => Some(p),
+            None => None,
+        };
+
         auditor.log(AuditEvent::ToolCallAllowed {
-            tool: &format!("skill:{}", tool_name),
+            tool: &audit_tool,
             params: &params_str,
         });
 
@@ -100,11 +158,17 @@ impl SkillRunner {
             skill.manifest.name, skill.binary_path
         );
 
-        // Spawn sandboxed process
-        let mut process = SandboxedProcess::spawn(&skill.binary_path, &skill.skill_dir)?;
+        let result = if skill.manifest.persistent {
+            self.call_persistent(index, permit, input, ctx, on_progress)
+        } else {
+            // Spawn sandboxed process
+            let caps = SkillCaps::of(&skill.manifest);
+            let mut process =
+                SandboxedProcess::spawn(&skill.binary_path, &skill.skill_dir, &caps)?;
 
-        // Invoke via IPC
-        let result = ipc::invoke_skill(&mut process, input, self.skill_timeout);
+            // Invoke via IPC
+            ipc::invoke_skill(&mut process, input, ctx, self.skill_timeout, on_progress)
+        };
 
         match &result {
             Ok(output) => {
@@ -116,9 +180,414 @@ impl SkillRunner {
             }
             Err(e) => {
                 eprintln!("sentinel: skill '{}' failed: {}", skill.manifest.name, e);
+                auditor.log(AuditEvent::SkillFailed {
+                    skill: &skill.manifest.name,
+                    error: e,
+                });
             }
         }
 
         result
     }
+
+    /// Whether a persistent skill's process is up, or busy with a call. One
+    /// that has exited is cleared, freeing its slot for the restart.
+    fn session_running(&self, index: usize) -> bool {
+        let Ok(mut session) = self.sessions[index].try_lock() else {
+            return true;
+        };
+        if session.as_mut().is_some_and(|s| !s.is_usable()) {
+            eprintln!("sentinel: skill '{}' exited; restarting", self.skills[index].manifest.name);
+            *session = None;
+        }
+        session.is_some()
+    }
+
+    /// Send a call to a persistent skill's process, starting it first if it
+    /// isn't running (never started, stopped for idling, or crashed) with
+    /// `permit`, or a slot taken now if it stopped since that was decided.
+    /// Calls to the same skill wait for each other. A running process holds
+    /// its slot until it is stopped.
+    fn call_persistent(
+        &self,
+        index: usize,
+        permit: Option<SkillPermit>,
+        input: &JsonValue,
+        ctx: &TurnContext,
+        on_progress: &mut dyn FnMut(&str),
+    ) -> Result<String, String> {
+        let skill = &self.skills[index];
+        let mut session = self.sessions[index].lock().unwrap_or_else(|e| e.into_inner());
+        if session.as_mut().is_some_and(|s| !s.is_usable()) {
+            eprintln!("sentinel: skill '{}' exited; restarting", skill.manifest.name);
+            *session = None;
+        }
+        let running = match session.as_mut() {
+            Some(running) => running,
+            None => {
+                let permit = match permit {
+                    Some(p) => p,
+                    None => self.persistent_limit.acquire(Duration::from_secs(self.skill_timeout))?,
+                };
+                let caps = SkillCaps::of(&skill.manifest);
+                let process =
+                    SandboxedProcess::spawn(&skill.binary_path, &skill.skill_dir, &caps)?;
+                session.insert(SkillSession::start(process, permit)?)
+            }
+        };
+        let result = running.call(input, ctx, self.skill_timeout, on_progress);
+        if !running.is_usable() {
+            *session = None;
+        }
+        result
+    }
+}
+
+/// Stop the persistent processes idle for longer than their skill allows.
+/// Returns the indexes of the skills stopped.
+fn stop_idle(sessions: &Sessions, idle: &[Duration]) -> Vec<usize> {
+    let mut stopped = Vec::new();
+    for (i, slot) in sessions.iter().enumerate() {
+        // A session in use is busy, not idle
+        let Ok(mut session) = slot.try_lock() else { continue };
+        if session.as_ref().is_some_and(|s| s.idle_for() >= idle[i]) {
+            *session = None;
+            stopped.push(i);
+        }
+    }
+    stopped
+}
+
+/// Check `input` against the parameters the manifest declares: every
+/// required one present, and each one given of its declared type. Unknown
+/// types and undeclared parameters are left to the skill.
+fn validate_params(m: &SkillManifest, input: &JsonValue) -> Result<(), String> {
+    let empty = Vec::new();
+    let fields = match input {
+        JsonValue::Object(fields) => fields,
+        JsonValue::Null => &empty,
+        other => {
+            return Err(format!(
+                "{}: input must be an object, got {}",
+                m.tool_name,
+                json_type(other)
+            ))
+        }
+    };
+    for param in &m.parameters {
+        let value = fields
+            .iter()
+            .find(|(k, _)| *k == param.name)
+            .map(|(_, v)| v)
+            .filter(|v| !v.is_null());
+        let Some(value) = value else {
+            if param.required {
+                return Err(format!(
+                    "{}: missing required parameter '{}'",
+                    m.tool_name, param.name
+                ));
+            }
+            continue;
+        };
+        let ok = match param.param_type.as_str() {
+            "string" => matches!(value, JsonValue::String(_)),
+            "number" => matches!(value, JsonValue::Number(_)),
+            "integer" => match value {
+                JsonValue::Number(JsonNumber::Float(f)) => f.fract() == 0.0,
+                JsonValue::Number(_) => true,
+                _ => false,
+            },
+            "boolean" => matches!(value, JsonValue::Bool(_)),
+            "array" => matches!(value, JsonValue::Array(_)),
+            "object" => matches!(value, JsonValue::Object(_)),
+            _ => true,
+        };
+        if !ok {
+            return Err(format!(
+                "{}: parameter '{}' must be of type {}, got {}",
+                m.tool_name,
+                param.name,
+                param.param_type,
+                json_type(value)
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// JSON Schema name of a value's type, for error messages.
+fn json_type(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+/// Object schema for a skill's parameters, shaped like the built-in tools'.
+/// `required` is omitted when empty, as some providers reject `[]`.
+fn tool_def(m: &SkillManifest) -> ToolDef {
+    let mut props = json_obj();
+    let mut required = Vec::new();
+
+    for param in &m.parameters {
+        let prop = json_obj()
+            .field_str("type", &param.param_type)
+            .field_str("description", &param.description)
+            .build();
+        props = props.field(&param.name, prop);
+
+        if param.required {
+            required.push(param.name.as_str());
+        }
+    }
+
+    let mut schema = json_obj()
+        .field_str("type", "object")
+        .field("properties", props.build());
+    if !required.is_empty() {
+        let list = required.iter().fold(json_arr(), |arr, name| arr.push_str(name));
+        schema = schema.field("required", list.build());
+    }
+
+    ToolDef {
+        name: m.tool_name.clone(),
+        description: m.tool_description.clone(),
+        input_schema: schema.build(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::linux::LinuxPlatform;
+    use limit::ConcurrencyPolicy;
+    use manifest::parse_manifest;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// A persistent skill in its own skills directory, answering each
+    /// request with how many it has handled.
+    fn persistent_skill(base: &str, script: &str) -> SkillRunner {
+        let dir = format!("{}/counter", base);
+        let _ = fs::remove_dir_all(base);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            format!("{}/skill.toml", dir),
+            "[skill]\nname = \"counter\"\nbinary = \"counter.sh\"\npersistent = true\n\
+             idle_timeout = 0\n\n[tool]\nname = \"counter\"\n",
+        )
+        .unwrap();
+        let path = format!("{}/counter.sh", dir);
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        SkillRunner::load(
+            base,
+            5,
+            ConcurrencyLimit::new(1, ConcurrencyPolicy::Queue),
+            ConcurrencyLimit::new(1, ConcurrencyPolicy::Reject),
+        )
+    }
+
+    fn call(runner: &SkillRunner) -> Result<String, String> {
+        let platform = LinuxPlatform::new(vec![], vec![], vec![], None);
+        let mut auditor = Auditor::new(&platform);
+        let ctx = TurnContext {
+            platform: "telegram",
+            channel_id: "42",
+            user_id: "7",
+            username: None,
+            timestamp: 0,
+        };
+        runner.execute("counter", &json_obj().build(), &ctx, &mut auditor, &mut |_| {})
+    }
+
+    #[test]
+    fn test_persistent_skill_serves_several_calls() {
+        let base = "/tmp/sentinel_test_persistent";
+        let runner = persistent_skill(
+            base,
+            "#!/bin/sh\nn=0\nwhile read -r line; do\n  n=$((n+1))\n  echo \"{\\\"result\\\":\\\"call $n\\\"}\"\ndone\n",
+        );
+        assert_eq!(call(&runner), Ok("call 1".to_string()));
+        assert_eq!(call(&runner), Ok("call 2".to_string()));
+
+        // Idle past its (zero) timeout: stopped, and a fresh process next time
+        assert_eq!(stop_idle(&runner.sessions, &[Duration::ZERO]), vec![0]);
+        assert_eq!(call(&runner), Ok("call 1".to_string()));
+        let _ = fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn test_persistent_skill_holds_slot_while_running() {
+        let base = "/tmp/sentinel_test_persistent_slot";
+        let runner = persistent_skill(base, "#!/bin/sh\nwhile read -r line; do echo '{\"result\":\"ok\"}'; done\n");
+        assert_eq!(call(&runner), Ok("ok".to_string()));
+        // The only persistent slot stays taken between calls, which still
+        // go through, while one-shot skills keep theirs
+        assert!(runner.persistent_limit.acquire(Duration::ZERO).is_err());
+        assert!(runner.limit.acquire(Duration::ZERO).is_ok());
+        assert_eq!(call(&runner), Ok("ok".to_string()));
+
+        // Stopping the process frees it
+        stop_idle(&runner.sessions, &[Duration::ZERO]);
+        assert!(runner.persistent_limit.acquire(Duration::ZERO).is_ok());
+        let _ = fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn test_persistent_skill_without_slot_is_denied() {
+        use crate::platform::linux::AuditLog;
+        let base = "/tmp/sentinel_test_persistent_denied";
+        let runner = persistent_skill(base, "#!/bin/sh\nwhile read -r line; do echo '{\"result\":\"ok\"}'; done\n");
+        let _held = runner.persistent_limit.acquire(Duration::ZERO).unwrap();
+
+        let path = format!("{}/audit.jsonl", base);
+        let log = Arc::new(AuditLog::open(&path, 0, 0).unwrap());
+        let platform = LinuxPlatform::new(vec![], vec![], vec![], Some(log));
+        let mut auditor = Auditor::new(&platform);
+        let ctx = TurnContext {
+            platform: "telegram",
+            channel_id: "42",
+            user_id: "7",
+            username: None,
+            timestamp: 0,
+        };
+        let err = runner
+            .execute("counter", &json_obj().build(), &ctx, &mut auditor, &mut |_| {})
+            .unwrap_err();
+        assert!(err.contains("limit reached"), "{}", err);
+        let audit = fs::read_to_string(&path).unwrap();
+        assert!(audit.contains("\"tool_call_denied\""), "{}", audit);
+        assert!(!audit.contains("\"tool_call_allowed\""), "{}", audit);
+        let _ = fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn test_persistent_skill_restarts_after_exit() {
+        let base = "/tmp/sentinel_test_persistent_crash";
+        // Answers one request, then exits
+        let runner = persistent_skill(base, "#!/bin/sh\nread -r line\necho '{\"result\":\"once\"}'\n");
+        assert_eq!(call(&runner), Ok("once".to_string()));
+        // Let the exit land before the next call checks for it
+        for _ in 0..50 {
+            let mut session = runner.sessions[0].lock().unwrap();
+            if !session.as_mut().is_some_and(|s| s.is_usable()) {
+                break;
+            }
+            drop(session);
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(call(&runner), Ok("once".to_string()));
+        let _ = fs::remove_dir_all(base);
+    }
+
+    fn search_manifest() -> SkillManifest {
+        parse_manifest(
+            r#"
+[skill]
+name = "web-search"
+binary = "web-search"
+
+[tool]
+name = "web_search"
+param_names = ["query", "limit", "safe"]
+param_types = ["string", "integer", "boolean"]
+param_required = ["query"]
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate_params_missing_required() {
+        let m = search_manifest();
+        let input = json_obj().field_i64("limit", 5).build();
+        assert_eq!(
+            validate_params(&m, &input),
+            Err("web_search: missing required parameter 'query'".to_string())
+        );
+        let input = json_obj().field("query", JsonValue::Null).build();
+        assert!(validate_params(&m, &input).is_err());
+        assert!(validate_params(&m, &JsonValue::Null).is_err());
+    }
+
+    #[test]
+    fn test_validate_params_wrong_type() {
+        let m = search_manifest();
+        let input = json_obj().field_str("query", "rust").field_str("limit", "5").build();
+        assert_eq!(
+            validate_params(&m, &input),
+            Err("web_search: parameter 'limit' must be of type integer, got string".to_string())
+        );
+        let input = json_obj().field_str("query", "rust").field_f64("limit", 2.5).build();
+        assert!(validate_params(&m, &input).is_err());
+        let input = json_obj().field_i64("query", 1).build();
+        assert_eq!(
+            validate_params(&m, &input),
+            Err("web_search: parameter 'query' must be of type string, got number".to_string())
+        );
+        let input = json_obj().field_str("query", "rust").field_str("safe", "yes").build();
+        assert!(validate_params(&m, &input).is_err());
+        assert!(validate_params(&m, &json_arr().build()).is_err());
+    }
+
+    #[test]
+    fn test_validate_params_accepts_valid_input() {
+        let m = search_manifest();
+        let input = json_obj()
+            .field_str("query", "rust")
+            .field_i64("limit", 5)
+            .field_bool("safe", true)
+            .field_str("extra", "left to the skill")
+            .build();
+        assert_eq!(validate_params(&m, &input), Ok(()));
+        let input = json_obj().field_str("query", "rust").build();
+        assert_eq!(validate_params(&m, &input), Ok(()));
+    }
+
+    #[test]
+    fn test_zero_param_skill_schema() {
+        let m = parse_manifest(
+            r#"
+[skill]
+name = "uptime"
+binary = "uptime"
+
+[tool]
+name = "uptime"
+"#,
+        )
+        .unwrap();
+        let schema = tool_def(&m).input_schema;
+        assert_eq!(schema.to_json_string(), r#"{"type":"object","properties":{}}"#);
+    }
+
+    #[test]
+    fn test_required_params_listed() {
+        let m = parse_manifest(
+            r#"
+[skill]
+name = "web-search"
+binary = "web-search"
+
+[tool]
+name = "web_search"
+param_names = ["query", "limit"]
+param_types = ["string", "integer"]
+param_required = ["query"]
+"#,
+        )
+        .unwrap();
+        let schema = tool_def(&m).input_schema;
+        assert_eq!(schema.get_path("type").and_then(|v| v.as_str()), Some("object"));
+        assert_eq!(
+            schema.get_path("properties.limit.type").and_then(|v| v.as_str()),
+            Some("integer")
+        );
+        assert_eq!(schema.get("required").unwrap().to_json_string(), r#"["query"]"#);
+    }
 }
@@ -1,9 +1,43 @@
-use std::process::{Child, Command, Stdio};
+use std::io::{Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::manifest::SkillManifest;
 
 // ── Sandboxed process ───────────────────────────────────────────────────────
 
+/// Where commands named without a path are looked up.
+const SKILL_PATH: &str = "/usr/bin:/usr/local/bin:/bin";
+
+/// Bytes of a skill's most recent stderr kept for error reports.
+const STDERR_TAIL_BYTES: usize = 2048;
+
+/// What a skill may do, from the `[capabilities]` of its manifest.
+#[derive(Debug, Clone, Default)]
+pub struct SkillCaps {
+    pub network: bool,
+    pub file_read: Vec<String>,
+    pub file_write: Vec<String>,
+    pub commands: Vec<String>,
+}
+
+impl SkillCaps {
+    pub fn of(manifest: &SkillManifest) -> Self {
+        SkillCaps {
+            network: manifest.cap_network,
+            file_read: manifest.cap_file_read.clone(),
+            file_write: manifest.cap_file_write.clone(),
+            commands: manifest.cap_commands.clone(),
+        }
+    }
+}
+
 pub struct SandboxedProcess {
     child: Child,
+    stderr: Arc<Mutex<Vec<u8>>>,
+    stderr_reader: Option<JoinHandle<()>>,
 }
 
 impl SandboxedProcess {
@@ -11,24 +45,65 @@ impl SandboxedProcess {
     ///
     /// The child process:
     /// - Inherits the parent's seccomp + landlock filters (automatically)
+    /// - On Linux, is further limited to `caps`: it reads its own directory
+    ///   and `file_read`, writes `file_write`, runs only its binary (and
+    ///   script interpreter) and `commands`, and opens no IP sockets unless
+    ///   `network` is set
     /// - Has stdin/stdout piped for IPC
-    /// - Has stderr inherited for logging
+    /// - Has stderr piped: forwarded to ours for logging, with the last
+    ///   STDERR_TAIL_BYTES kept for error reports
     /// - Runs in the skill's directory
     /// - Has a minimal environment
-    pub fn spawn(binary_path: &str, working_dir: &str) -> Result<Self, String> {
-        let child = Command::new(binary_path)
+    pub fn spawn(binary_path: &str, working_dir: &str, caps: &SkillCaps) -> Result<Self, String> {
+        let mut command = Command::new(binary_path);
+
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::process::CommandExt;
+
+            let mut exec_paths = vec![working_dir.to_string()];
+            exec_paths.extend(interpreters(binary_path, SKILL_PATH));
+            for name in &caps.commands {
+                match resolve_command(name, SKILL_PATH) {
+                    Some(path) => exec_paths.push(path),
+                    None => eprintln!("sentinel: skill command '{}' not found", name),
+                }
+            }
+            let sandbox = crate::security::linux::ChildSandbox::prepare(
+                &caps.file_read,
+                &caps.file_write,
+                &exec_paths,
+                caps.network,
+            )
+            .map_err(|e| format!("failed to sandbox skill '{}': {}", binary_path, e))?;
+            // Safety: `apply` only makes syscalls; nothing is allocated or
+            // locked in the forked child.
+            unsafe {
+                command.pre_exec(move || sandbox.apply());
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = caps;
+
+        let mut child = command
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
+            .stderr(Stdio::piped())
             .current_dir(working_dir)
             .env_clear()
-            .env("PATH", "/usr/bin:/usr/local/bin:/bin")
+            .env("PATH", SKILL_PATH)
             .env("HOME", working_dir)
             .env("LANG", "C.UTF-8")
             .spawn()
             .map_err(|e| format!("failed to spawn skill '{}': {}", binary_path, e))?;
 
-        Ok(SandboxedProcess { child })
+        let stderr = Arc::new(Mutex::new(Vec::new()));
+        let stderr_reader = child.stderr.take().map(|pipe| {
+            let tail = Arc::clone(&stderr);
+            thread::spawn(move || forward_stderr(pipe, &tail))
+        });
+
+        Ok(SandboxedProcess { child, stderr, stderr_reader })
     }
 
     /// Get a mutable reference to the child's stdin.
@@ -36,17 +111,41 @@ impl SandboxedProcess {
         self.child.stdin.as_mut()
     }
 
-    /// Get a mutable reference to the child's stdout.
-    pub fn stdout(&mut self) -> Option<&mut std::process::ChildStdout> {
-        self.child.stdout.as_mut()
+    /// Take ownership of the child's stdout (e.g. to read it on another thread).
+    pub fn take_stdout(&mut self) -> Option<std::process::ChildStdout> {
+        self.child.stdout.take()
     }
 
-    /// Try to wait for the child without blocking.
-    pub fn try_wait(&mut self) -> Result<Option<i32>, String> {
-        self.child
-            .try_wait()
-            .map(|status| status.map(|s| s.code().unwrap_or(-1)))
-            .map_err(|e| format!("wait error: {}", e))
+    /// Whether the child hasn't exited yet.
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Wait up to `timeout` for the child to exit.
+    pub fn wait_exit(&mut self, timeout: Duration) -> Option<ExitStatus> {
+        let start = Instant::now();
+        loop {
+            match self.child.try_wait() {
+                Ok(Some(status)) => return Some(status),
+                Ok(None) if start.elapsed() < timeout => thread::sleep(Duration::from_millis(10)),
+                _ => return None,
+            }
+        }
+    }
+
+    /// The last bytes the child wrote to stderr. Once the child has exited,
+    /// waits briefly for the rest of its output to be read.
+    pub fn stderr_tail(&mut self) -> String {
+        if !self.is_running() {
+            let start = Instant::now();
+            while self.stderr_reader.as_ref().is_some_and(|r| !r.is_finished())
+                && start.elapsed() < Duration::from_millis(200)
+            {
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        let tail = self.stderr.lock().unwrap_or_else(|e| e.into_inner());
+        String::from_utf8_lossy(&tail).trim().to_string()
     }
 
     /// Kill the child process.
@@ -56,6 +155,63 @@ impl SandboxedProcess {
     }
 }
 
+/// Copy a skill's stderr to ours, keeping the last STDERR_TAIL_BYTES.
+fn forward_stderr(mut pipe: std::process::ChildStderr, tail: &Mutex<Vec<u8>>) {
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = match pipe.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        let _ = std::io::stderr().write_all(&buf[..n]);
+        let mut tail = tail.lock().unwrap_or_else(|e| e.into_inner());
+        tail.extend_from_slice(&buf[..n]);
+        if tail.len() > STDERR_TAIL_BYTES {
+            let excess = tail.len() - STDERR_TAIL_BYTES;
+            tail.drain(..excess);
+        }
+    }
+}
+
+/// Find a command named without a path on `search_path`, a `PATH`-style
+/// list of directories.
+pub(crate) fn resolve_command(name: &str, search_path: &str) -> Option<String> {
+    if name.contains('/') {
+        return Some(name.to_string());
+    }
+    search_path
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| format!("{}/{}", dir, name))
+        .find(|path| std::path::Path::new(path).is_file())
+}
+
+/// The interpreter a script's `#!` line names, and the program after
+/// `/usr/bin/env` as found on `search_path`, which must be executable too.
+pub(crate) fn interpreters(binary_path: &str, search_path: &str) -> Vec<String> {
+    let mut head = [0u8; 256];
+    let n = std::fs::File::open(binary_path)
+        .and_then(|mut f| f.read(&mut head))
+        .unwrap_or(0);
+    let Some(line) = head[..n].strip_prefix(b"#!") else {
+        return Vec::new();
+    };
+    let line = line.split(|&b| b == b'\n').next().unwrap_or_default();
+    let line = String::from_utf8_lossy(line);
+    let mut words = line.split_whitespace();
+    let Some(interpreter) = words.next() else {
+        return Vec::new();
+    };
+    let mut paths = vec![interpreter.to_string()];
+    if interpreter.ends_with("/env") {
+        let program = words.find(|w| !w.starts_with('-'));
+        if let Some(program) = program.and_then(|p| resolve_command(p, search_path)) {
+            paths.push(program);
+        }
+    }
+    paths
+}
+
 impl Drop for SandboxedProcess {
     fn drop(&mut self) {
         // Ensure child is cleaned up
@@ -63,3 +219,136 @@ impl Drop for SandboxedProcess {
         let _ = self.child.wait();
     }
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Write a one-line shell skill into `dir`, returning its path.
+    fn write_skill(dir: &str, body: &str) -> String {
+        let script = format!("{}/skill.sh", dir);
+        fs::write(&script, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        script
+    }
+
+    /// Run a one-line shell skill and return what it printed.
+    fn run_skill(dir: &str, body: &str, caps: &SkillCaps) -> String {
+        run_script(&write_skill(dir, body), dir, caps)
+    }
+
+    fn run_script(script: &str, dir: &str, caps: &SkillCaps) -> String {
+        let mut process = SandboxedProcess::spawn(script, dir, caps).unwrap();
+        let _ = process.stdin().unwrap().write_all(b"{}\n");
+        let mut out = String::new();
+        process.take_stdout().unwrap().read_to_string(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_skill_reads_only_declared_paths() {
+        if crate::security::linux::landlock_abi().is_none() {
+            eprintln!("landlock not supported; skipping");
+            return;
+        }
+        let dir = "/tmp/sentinel_test_skill_caps";
+        let outside = "/tmp/sentinel_test_skill_caps_outside";
+        let _ = fs::create_dir_all(dir);
+        let _ = fs::create_dir_all(outside);
+        fs::write(format!("{}/own.txt", dir), "own\n").unwrap();
+        fs::write(format!("{}/secret.txt", outside), "secret\n").unwrap();
+
+        // `read` and `echo` are shell builtins, so no commands are needed
+        let body = format!(
+            "read -r a < {d}/own.txt && echo \"$a\"; read -r b < {o}/secret.txt && echo \"$b\"",
+            d = dir,
+            o = outside
+        );
+        assert_eq!(run_skill(dir, &body, &SkillCaps::default()), "own\n");
+
+        let caps = SkillCaps { file_read: vec![outside.to_string()], ..Default::default() };
+        assert_eq!(run_skill(dir, &body, &caps), "own\nsecret\n");
+
+        let _ = fs::remove_dir_all(dir);
+        let _ = fs::remove_dir_all(outside);
+    }
+
+    #[test]
+    fn test_skill_runs_only_declared_commands() {
+        if crate::security::linux::landlock_abi().is_none() {
+            eprintln!("landlock not supported; skipping");
+            return;
+        }
+        let dir = "/tmp/sentinel_test_skill_cmds";
+        let _ = fs::create_dir_all(dir);
+        let body = "cat skill.sh >/dev/null 2>&1 && echo ran || echo blocked";
+        assert_eq!(run_skill(dir, body, &SkillCaps::default()), "blocked\n");
+        let caps = SkillCaps { commands: vec!["cat".into()], ..Default::default() };
+        assert_eq!(run_skill(dir, body, &caps), "ran\n");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_skill_sandboxed_under_agent_filter() {
+        if crate::security::linux::landlock_abi().is_none() {
+            eprintln!("landlock not supported; skipping");
+            return;
+        }
+        let dir = "/tmp/sentinel_test_skill_filtered";
+        let outside = "/tmp/sentinel_test_skill_filtered_outside";
+        let _ = fs::create_dir_all(dir);
+        let _ = fs::create_dir_all(outside);
+        fs::write(format!("{}/own.txt", dir), "own\n").unwrap();
+        fs::write(format!("{}/secret.txt", outside), "secret\n").unwrap();
+        let body = format!(
+            "read -r a < {d}/own.txt && echo \"$a\"; read -r b < {o}/secret.txt && echo \"$b\"",
+            d = dir,
+            o = outside
+        );
+        // Spawned from a thread under the agent's own seccomp filter, as in
+        // a deployment; without network the skill gets a filter of its own
+        let script = write_skill(dir, &body);
+        let out = thread::spawn(move || {
+            use crate::security::linux::{apply_sandbox, SeccompMode};
+            let agent = apply_sandbox(&[], &[], true, SeccompMode::Enforce, false);
+            assert!(agent.seccomp_applied && agent.seccomp_error.is_none());
+            run_script(&script, dir, &SkillCaps::default())
+        })
+        .join()
+        .unwrap();
+        assert_eq!(out, "own\n");
+
+        let _ = fs::remove_dir_all(dir);
+        let _ = fs::remove_dir_all(outside);
+    }
+
+    #[test]
+    fn test_interpreters() {
+        let dir = "/tmp/sentinel_test_skill_interp";
+        let _ = fs::create_dir_all(dir);
+        let script = format!("{}/s", dir);
+        fs::write(&script, "#!/bin/sh -e\n").unwrap();
+        assert_eq!(interpreters(&script, SKILL_PATH), vec!["/bin/sh"]);
+        fs::write(&script, "#!/usr/bin/env -S sh -e\n").unwrap();
+        let found = interpreters(&script, SKILL_PATH);
+        assert_eq!(found[0], "/usr/bin/env");
+        assert!(found[1].ends_with("/sh"), "{:?}", found);
+        fs::write(&script, "\x7fELF").unwrap();
+        assert!(interpreters(&script, SKILL_PATH).is_empty());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_resolve_command_follows_search_path() {
+        let dir = "/tmp/sentinel_test_resolve_path";
+        let _ = fs::create_dir_all(format!("{}/sbin", dir));
+        fs::write(format!("{}/sbin/tool", dir), "").unwrap();
+        let search = format!("/nonexistent::{}/sbin", dir);
+        assert_eq!(resolve_command("tool", &search), Some(format!("{}/sbin/tool", dir)));
+        assert_eq!(resolve_command("tool", SKILL_PATH), None);
+        assert_eq!(resolve_command("/opt/x/tool", SKILL_PATH), Some("/opt/x/tool".to_string()));
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,46 @@
+// ── Build information ───────────────────────────────────────────────────────
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Human-readable build description for `--version` and `/version`.
+pub fn version_string() -> String {
+    let mut features = Vec::new();
+    if cfg!(feature = "tls") {
+        features.push("tls");
+    }
+
+    let mut s = format!(
+        "luperiq-sentinel {} ({}-{})",
+        VERSION,
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    s.push_str(&format!(
+        "\nfeatures: {}",
+        if features.is_empty() {
+            "none".to_string()
+        } else {
+            features.join(", ")
+        }
+    ));
+    if let Some(hash) = option_env!("SENTINEL_GIT_HASH") {
+        s.push_str(&format!("\ngit: {}", hash));
+    }
+    if let Some(ts) = option_env!("SENTINEL_BUILD_TIMESTAMP") {
+        s.push_str(&format!("\nbuilt: {} (unix)", ts));
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_string_includes_package_version() {
+        let s = version_string();
+        assert!(s.starts_with("luperiq-sentinel "));
+        assert!(s.contains(env!("CARGO_PKG_VERSION")));
+        assert!(s.contains(std::env::consts::ARCH));
+    }
+}